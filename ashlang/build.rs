@@ -0,0 +1,12 @@
+// Generate the C header for the `capi` feature
+fn main() {
+    #[cfg(feature = "capi")]
+    {
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        println!("cargo:rerun-if-changed=src/capi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        cbindgen::generate(&crate_dir)
+            .expect("failed to generate C header")
+            .write_to_file(format!("{crate_dir}/include/ashlang.h"));
+    }
+}
@@ -0,0 +1,97 @@
+#ifndef ASHLANG_H
+#define ASHLANG_H
+
+/* Generated by cbindgen from src/capi.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Bytes returned to the caller, e.g. a serialized proof. `data`
+ * is NULL if the call failed.
+ */
+typedef struct AshlangBuffer {
+  uint8_t *data;
+  size_t len;
+} AshlangBuffer;
+
+/**
+ * Compile `entry_fn` to ar1cs in a field, reading functions from
+ * `include_paths`. Fields are `oxfoi`, `curve25519`, `alt_bn128`,
+ * and `dilithium`.
+ *
+ * # Safety
+ *
+ * `entry_fn` and `field` must be valid strings and
+ * `include_paths` must point to `include_paths_len` strings.
+ */
+char *ashlang_compile_to_r1cs(const char *entry_fn,
+                              const char *field,
+                              const char *const *include_paths,
+                              size_t include_paths_len,
+                              char **error);
+
+/**
+ * Build and verify the witness of compiled ar1cs. `inputs` are
+ * decimal strings. Returns the value of each signal in order,
+ * separated by newlines.
+ *
+ * # Safety
+ *
+ * `r1cs` and `field` must be valid strings and `inputs` must
+ * point to `inputs_len` strings.
+ */
+char *ashlang_build_witness(const char *r1cs,
+                            const char *field,
+                            const char *const *inputs,
+                            size_t inputs_len,
+                            char **error);
+
+/**
+ * Prove ar1cs in the curve25519 field with spartan. Returns the
+ * serialized proof.
+ *
+ * # Safety
+ *
+ * `r1cs` must be a valid string and `secret_inputs` must point
+ * to `secret_inputs_len` strings.
+ */
+struct AshlangBuffer ashlang_prove(const char *r1cs,
+                                   const char *const *secret_inputs,
+                                   size_t secret_inputs_len,
+                                   char **error);
+
+/**
+ * Verify a serialized spartan proof of ar1cs. Returns 1 if the
+ * proof is valid, 0 if it is not, and -1 on error.
+ *
+ * # Safety
+ *
+ * `r1cs` must be a valid string and `proof` must point to
+ * `proof_len` bytes.
+ */
+int32_t ashlang_verify(const char *r1cs, const uint8_t *proof, size_t proof_len, char **error);
+
+/**
+ * Free a string returned by this library.
+ *
+ * # Safety
+ *
+ * `s` must be NULL or a string returned by this library that
+ * has not been freed.
+ */
+void ashlang_string_free(char *s);
+
+/**
+ * Free a buffer returned by this library.
+ *
+ * # Safety
+ *
+ * `buffer` must be returned by this library and not yet freed.
+ */
+void ashlang_buffer_free(struct AshlangBuffer buffer);
+
+#endif /* ASHLANG_H */
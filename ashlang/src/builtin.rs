@@ -0,0 +1,207 @@
+use camino::Utf8PathBuf;
+
+use crate::compiler::ReturnType;
+
+/// The module path of the functions embedded in the compiler,
+/// e.g. `std::pow`.
+pub const MODULE: &str = "std";
+
+/// A hash function lowered natively by each target, see
+/// `r1cs::poseidon` and the tasm `hash` instruction.
+pub const POSEIDON: &str = "std::poseidon";
+
+/// A Fiat-Shamir challenge derived from some scalars and every
+/// previous challenge. Each challenge is the `std::poseidon` hash
+/// of the previous challenge, or 0 for the first, followed by the
+/// arguments.
+pub const CHALLENGE: &str = "std::challenge";
+
+/// The largest number of scalars a challenge is derived from,
+/// the previous challenge is also hashed. The tasm `hash`
+/// instruction absorbs 10 elements.
+pub const CHALLENGE_INPUTS: usize = 9;
+
+/// Assert that a leaf is included in a merkle tree of
+/// `std::poseidon` hashes.
+pub const MERKLE_VERIFY: &str = "std::merkle_verify";
+
+/// The sha256 digest of a vector of bytes, r1cs only.
+pub const SHA256: &str = "std::sha256";
+
+/// The keccak256 digest of a vector of bytes as used by
+/// Ethereum, r1cs only.
+pub const KECCAK256: &str = "std::keccak256";
+
+/// Assert that a message was signed by a public key using
+/// EdDSA with `std::poseidon`, r1cs only, see `r1cs::eddsa`.
+pub const EDDSA_VERIFY: &str = "std::eddsa_verify";
+
+/// Assert that an inner product argument is valid, r1cs only,
+/// see `r1cs::ipa`.
+pub const IPA_VERIFY: &str = "std::ipa_verify";
+
+/// The number of base field coefficients in an extension field
+/// element, see `xfe`.
+pub const EXTENSION_DEGREE: usize = 3;
+
+/// The product of two extension field elements modulo
+/// `x^3 - x + 1`, the Goldilocks cubic extension used by Triton.
+/// Elements are vectors of coefficients starting with `x^0`.
+pub const XFE_MUL: &str = "std::xfe_mul";
+
+/// The inverse of a non-zero extension field element.
+pub const XFE_INV: &str = "std::xfe_inv";
+
+/// The sum of the element-wise products of two values with the
+/// same dimensions.
+pub const DOT: &str = "std::dot";
+
+/// The matrix product of two vectors or matrices, written
+/// `a @ b`. A vector is a row on the left and a column on the
+/// right.
+pub const MATMUL: &str = "std::matmul";
+
+/// The sum of the elements of a vector or matrix.
+pub const SUM: &str = "std::sum";
+
+/// The product of the elements of a vector or matrix.
+pub const PROD: &str = "std::prod";
+
+/// The largest element of a vector or matrix. Elements are
+/// compared as values in the range [0, 2^32).
+pub const MAX: &str = "std::max";
+
+/// The precompiles reducing the elements of a value to a scalar.
+pub const REDUCTIONS: &[&str] = &[SUM, PROD, MAX];
+
+/// The elements `start..end` of the outermost dimension of a
+/// value, written `v[start..end]`. The bounds must be static.
+pub const SLICE: &str = "std::slice";
+
+/// `x` raised to the power `e`, written `x ** e`. The r1cs
+/// target lowers a static exponent by square and multiply.
+pub const POW: &str = "std::pow";
+
+/// The negation of each element of a value, written `-v`.
+pub const NEG: &str = "std::neg";
+
+// functions lowered by the target instead of being compiled
+// from source, and whether they return a value
+const PRECOMPILES: &[(&str, ReturnType)] = &[
+    (POSEIDON, ReturnType::Value(None)),
+    (CHALLENGE, ReturnType::Value(None)),
+    (MERKLE_VERIFY, ReturnType::Void),
+    (SHA256, ReturnType::Value(None)),
+    (KECCAK256, ReturnType::Value(None)),
+    (EDDSA_VERIFY, ReturnType::Void),
+    (IPA_VERIFY, ReturnType::Void),
+    (XFE_MUL, ReturnType::Value(None)),
+    (XFE_INV, ReturnType::Value(None)),
+    (DOT, ReturnType::Value(None)),
+    (MATMUL, ReturnType::Value(None)),
+    (SUM, ReturnType::Value(None)),
+    (PROD, ReturnType::Value(None)),
+    (MAX, ReturnType::Value(None)),
+    (SLICE, ReturnType::Value(None)),
+    (NEG, ReturnType::Value(None)),
+];
+
+// builtin functions are mapped to paths in this directory
+// so they can be identified in messages. These paths do not
+// exist on disk
+const PATH_PREFIX: &str = "<std>";
+
+// name, extension, source
+const SOURCES: &[(&str, &str, &str)] = &[
+    (
+        "assert_bool",
+        "ar1cs",
+        include_str!("builtin/assert_bool.ar1cs"),
+    ),
+    (
+        "assert_bool",
+        "tasm",
+        include_str!("builtin/assert_bool.tasm"),
+    ),
+    (
+        "assert_false",
+        "ar1cs",
+        include_str!("builtin/assert_false.ar1cs"),
+    ),
+    (
+        "assert_false",
+        "tasm",
+        include_str!("builtin/assert_false.tasm"),
+    ),
+    (
+        "assert_true",
+        "ar1cs",
+        include_str!("builtin/assert_true.ar1cs"),
+    ),
+    (
+        "assert_true",
+        "tasm",
+        include_str!("builtin/assert_true.tasm"),
+    ),
+    ("bits8", "ar1cs", include_str!("builtin/bits8.ar1cs")),
+    ("bits8", "tasm", include_str!("builtin/bits8.tasm")),
+    ("bits32", "ar1cs", include_str!("builtin/bits32.ar1cs")),
+    ("bits32", "tasm", include_str!("builtin/bits32.tasm")),
+    ("is_zero", "ash", include_str!("builtin/is_zero.ash")),
+    ("pow", "ash", include_str!("builtin/pow.ash")),
+    ("pow", "tasm", include_str!("builtin/pow.tasm")),
+    ("select", "ash", include_str!("builtin/select.ash")),
+];
+
+/// The builtin functions available with a set of extension
+/// priorities. If a function is implemented with multiple
+/// extensions the highest priority implementation is used.
+///
+/// Returns the module path and source path of each function.
+pub fn functions(extensions: &[String]) -> Vec<(String, Utf8PathBuf)> {
+    let mut out: Vec<(String, Utf8PathBuf, usize)> = vec![];
+    for (name, ext, _) in SOURCES {
+        let priority = match extensions.iter().position(|v| v == ext) {
+            Some(v) => v,
+            None => continue,
+        };
+        let qualified = format!("{MODULE}::{name}");
+        let path = Utf8PathBuf::from(format!("{PATH_PREFIX}/{name}.{ext}"));
+        if let Some(existing) = out.iter_mut().find(|(v, _, _)| *v == qualified) {
+            if priority > existing.2 {
+                *existing = (qualified, path, priority);
+            }
+            continue;
+        }
+        out.push((qualified, path, priority));
+    }
+    out.into_iter()
+        .map(|(name, path, _)| (name, path))
+        .collect()
+}
+
+/// Whether a function is lowered by the target instead of
+/// being compiled from source.
+pub fn is_precompile(name: &str) -> bool {
+    PRECOMPILES.iter().any(|(v, _)| *v == name)
+}
+
+/// The return type of each precompile.
+pub fn precompile_return_types() -> Vec<(String, ReturnType)> {
+    PRECOMPILES
+        .iter()
+        .map(|(name, return_type)| (name.to_string(), return_type.clone()))
+        .collect()
+}
+
+/// The source of a builtin function by path, or None if the
+/// path is not a builtin function.
+pub fn source(path: &Utf8PathBuf) -> Option<&'static str> {
+    SOURCES.iter().find_map(|(name, ext, src)| {
+        if path.as_str() == format!("{PATH_PREFIX}/{name}.{ext}") {
+            Some(*src)
+        } else {
+            None
+        }
+    })
+}
@@ -0,0 +1,70 @@
+use std::fs;
+
+use anyhow::Result;
+use camino::Utf8PathBuf;
+
+/// An on-disk, content addressed cache of compiled functions.
+///
+/// Entries are keyed by a hash of the function source, the source
+/// of every function it calls, and the types of the arguments it
+/// is called with. Entries are never invalidated, a change to any
+/// of these inputs produces a new key.
+///
+/// The provers use the same cache for keys and setup parameters,
+/// keyed by the program fingerprint.
+#[derive(Clone, Debug)]
+pub struct FnCache {
+    dir: Utf8PathBuf,
+}
+
+impl FnCache {
+    pub fn new(dir: Utf8PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Hash some strings into a hex encoded cache key.
+    pub fn key(parts: &[&str]) -> String {
+        let mut hasher = blake3::Hasher::new();
+        for part in parts {
+            // length prefix so that parts cannot run together
+            hasher.update(&(part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+
+    /// Hash the contents of a source file.
+    pub fn hash(bytes: &[u8]) -> String {
+        blake3::hash(bytes).to_hex().to_string()
+    }
+
+    /// Read an entry. Missing or unreadable entries are
+    /// treated as a cache miss.
+    pub fn get(&self, key: &str) -> Option<String> {
+        fs::read_to_string(self.path(key)).ok()
+    }
+
+    /// Write an entry. The entry is written to a temporary file
+    /// and renamed so concurrent builds never read a partial entry.
+    pub fn put(&self, key: &str, contents: &str) -> Result<()> {
+        self.put_bytes(key, contents.as_bytes())
+    }
+
+    /// Read a binary entry.
+    pub fn get_bytes(&self, key: &str) -> Option<Vec<u8>> {
+        fs::read(self.path(key)).ok()
+    }
+
+    /// Write a binary entry.
+    pub fn put_bytes(&self, key: &str, contents: &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let tmp = self.dir.join(format!("{key}.{}.tmp", std::process::id()));
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, self.path(key))?;
+        Ok(())
+    }
+
+    fn path(&self, key: &str) -> Utf8PathBuf {
+        self.dir.join(key)
+    }
+}
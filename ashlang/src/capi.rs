@@ -0,0 +1,311 @@
+//! A C API for compiling programs, building witnesses, and
+//! proving with spartan. The header is generated in
+//! `include/ashlang.h` when building with the `capi` feature.
+//!
+//! Strings passed in are NUL terminated UTF-8. Strings and
+//! buffers returned by these functions are owned by the caller
+//! and must be released with `ashlang_string_free` and
+//! `ashlang_buffer_free`. On failure functions return NULL (or
+//! -1) and, if `error` is not NULL, write a message to it that
+//! must be freed with `ashlang_string_free`. A panic inside
+//! the library is reported the same way instead of unwinding into
+//! the caller.
+use std::any::Any;
+use std::ffi::c_char;
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::ptr;
+
+use anyhow::Result;
+use bincode::Options;
+use camino::Utf8PathBuf;
+use ring_math::PolynomialRingElement;
+
+use crate::cli::Config;
+use crate::compiler::Compiler;
+use crate::error::AshlangError;
+use crate::log;
+use crate::provers::AshlangProver;
+use crate::provers::SpartanProof;
+use crate::provers::SpartanProver;
+use crate::r1cs::witness;
+use crate::rings::Bn128PolynomialRing;
+use crate::rings::Curve25519PolynomialRing;
+use crate::rings::DilithiumPolynomialRingElement;
+use crate::rings::OxfoiPolynomialRing;
+
+/// Bytes returned to the caller, e.g. a serialized proof. `data`
+/// is NULL if the call failed.
+#[repr(C)]
+pub struct AshlangBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// Compile `entry_fn` to ar1cs in a field, reading functions from
+/// `include_paths`. Fields are `oxfoi`, `curve25519`, `alt_bn128`,
+/// and `dilithium`.
+///
+/// # Safety
+///
+/// `entry_fn` and `field` must be valid strings and
+/// `include_paths` must point to `include_paths_len` strings.
+#[no_mangle]
+pub unsafe extern "C" fn ashlang_compile_to_r1cs(
+    entry_fn: *const c_char,
+    field: *const c_char,
+    include_paths: *const *const c_char,
+    include_paths_len: usize,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let out = catch(|| -> Result<String> {
+        let entry_fn = read_str(entry_fn)?;
+        let field = read_str(field)?;
+        let include_paths = read_strs(include_paths, include_paths_len)?;
+        let mut config = Config::new(&entry_fn, "r1cs", &field);
+        config.include_paths = include_paths.into_iter().map(Utf8PathBuf::from).collect();
+        match field.as_str() {
+            "foi" | "oxfoi" => compile::<OxfoiPolynomialRing>(&config),
+            "curve25519" => compile::<Curve25519PolynomialRing>(&config),
+            "alt_bn128" => compile::<Bn128PolynomialRing>(&config),
+            "dilithium" => compile::<DilithiumPolynomialRingElement>(&config),
+            _ => log::error!(ConfigError: &format!("Unsupported field for target r1cs: {field}")),
+        }
+    });
+    into_c_string(out, error)
+}
+
+/// Build and verify the witness of compiled ar1cs. `inputs` are
+/// decimal strings. Returns the value of each signal in order,
+/// separated by newlines.
+///
+/// # Safety
+///
+/// `r1cs` and `field` must be valid strings and `inputs` must
+/// point to `inputs_len` strings.
+#[no_mangle]
+pub unsafe extern "C" fn ashlang_build_witness(
+    r1cs: *const c_char,
+    field: *const c_char,
+    inputs: *const *const c_char,
+    inputs_len: usize,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let out = catch(|| -> Result<String> {
+        let r1cs = read_str(r1cs)?;
+        let field = read_str(field)?;
+        let inputs = read_strs(inputs, inputs_len)?;
+        match field.as_str() {
+            "foi" | "oxfoi" => build_witness::<OxfoiPolynomialRing>(&r1cs, inputs),
+            "curve25519" => build_witness::<Curve25519PolynomialRing>(&r1cs, inputs),
+            "alt_bn128" => build_witness::<Bn128PolynomialRing>(&r1cs, inputs),
+            "dilithium" => build_witness::<DilithiumPolynomialRingElement>(&r1cs, inputs),
+            _ => log::error!(ConfigError: &format!("Unsupported field for target r1cs: {field}")),
+        }
+    });
+    into_c_string(out, error)
+}
+
+/// Prove ar1cs in the curve25519 field with spartan. Returns the
+/// serialized proof.
+///
+/// # Safety
+///
+/// `r1cs` must be a valid string and `secret_inputs` must point
+/// to `secret_inputs_len` strings.
+#[no_mangle]
+pub unsafe extern "C" fn ashlang_prove(
+    r1cs: *const c_char,
+    secret_inputs: *const *const c_char,
+    secret_inputs_len: usize,
+    error: *mut *mut c_char,
+) -> AshlangBuffer {
+    let out = catch(|| -> Result<Vec<u8>> {
+        let r1cs = read_str(r1cs)?;
+        let secret_inputs = read_strs(secret_inputs, secret_inputs_len)?;
+        let proof = SpartanProver::prove_ir(&r1cs, vec![], secret_inputs)?;
+        Ok(bincode::serialize(&proof)?)
+    });
+    match out {
+        Ok(bytes) => {
+            let mut bytes = bytes.into_boxed_slice();
+            let buffer = AshlangBuffer {
+                data: bytes.as_mut_ptr(),
+                len: bytes.len(),
+            };
+            std::mem::forget(bytes);
+            buffer
+        }
+        Err(e) => {
+            write_error(e, error);
+            AshlangBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            }
+        }
+    }
+}
+
+/// Verify a serialized spartan proof of ar1cs. Returns 1 if the
+/// proof is valid, 0 if it is not, and -1 on error.
+///
+/// # Safety
+///
+/// `r1cs` must be a valid string and `proof` must point to
+/// `proof_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ashlang_verify(
+    r1cs: *const c_char,
+    proof: *const u8,
+    proof_len: usize,
+    error: *mut *mut c_char,
+) -> i32 {
+    let out = catch(|| -> Result<bool> {
+        let r1cs = read_str(r1cs)?;
+        if proof.is_null() {
+            return log::error!(InputError: "proof is NULL");
+        }
+        let bytes = std::slice::from_raw_parts(proof, proof_len);
+        // a proof is never longer than its bytes, so length prefixes
+        // can't allocate more than the caller passed
+        let proof: SpartanProof = bincode::options()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(bytes.len() as u64)
+            .deserialize(bytes)?;
+        SpartanProver::verify(&r1cs, &proof)
+    });
+    match out {
+        Ok(valid) => valid as i32,
+        Err(e) => {
+            write_error(e, error);
+            -1
+        }
+    }
+}
+
+/// Free a string returned by this library.
+///
+/// # Safety
+///
+/// `s` must be NULL or a string returned by this library that
+/// has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn ashlang_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Free a buffer returned by this library.
+///
+/// # Safety
+///
+/// `buffer` must be returned by this library and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn ashlang_buffer_free(buffer: AshlangBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
+
+fn compile<T: PolynomialRingElement>(config: &Config) -> Result<String> {
+    let mut compiler: Compiler<T> = Compiler::new(config)?;
+    compiler.compile(&config.entry_fn)
+}
+
+fn build_witness<T: PolynomialRingElement>(r1cs: &str, inputs: Vec<String>) -> Result<String> {
+    let inputs = inputs
+        .iter()
+        .map(|v| T::from_str(v))
+        .collect::<Result<Vec<_>>>()?;
+    let witness = witness::build::<T>(r1cs, inputs)?;
+    let values = witness
+        .variables
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    witness::verify::<T>(r1cs, witness)?;
+    Ok(values)
+}
+
+// run the body of a call, a panic is returned as an error
+fn catch<T>(f: impl FnOnce() -> Result<T>) -> Result<T> {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(out) => out,
+        Err(payload) => log::error!(
+            CompileError: &format!("internal error: {}", panic_message(&*payload)),
+            "this is a bug in ashlang"
+        ),
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+unsafe fn read_str(s: *const c_char) -> Result<String> {
+    if s.is_null() {
+        return log::error!(InputError: "received a NULL string");
+    }
+    match CStr::from_ptr(s).to_str() {
+        Ok(s) => Ok(s.to_string()),
+        Err(_) => log::error!(InputError: "received a string that is not valid UTF-8"),
+    }
+}
+
+unsafe fn read_strs(strs: *const *const c_char, len: usize) -> Result<Vec<String>> {
+    if len == 0 {
+        return Ok(vec![]);
+    }
+    if strs.is_null() {
+        return log::error!(InputError: "received a NULL array");
+    }
+    std::slice::from_raw_parts(strs, len)
+        .iter()
+        .map(|s| read_str(*s))
+        .collect()
+}
+
+// errors are written without color, e.g. `type_error: message`
+unsafe fn write_error(e: anyhow::Error, error: *mut *mut c_char) {
+    if error.is_null() {
+        return;
+    }
+    let message = match e.downcast_ref::<AshlangError>() {
+        Some(e) => match e.hint() {
+            Some(hint) => format!("{}: {}\n{hint}", e.code(), e.message()),
+            None => format!("{}: {}", e.code(), e.message()),
+        },
+        None => e.to_string(),
+    };
+    // interior NUL bytes can't be represented in a C string
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    *error = message.into_raw();
+}
+
+unsafe fn into_c_string(out: Result<String>, error: *mut *mut c_char) -> *mut c_char {
+    let out = out.and_then(|s| match CString::new(s) {
+        Ok(s) => Ok(s),
+        Err(_) => log::error!(CompileError: "output contains a NUL byte"),
+    });
+    match out {
+        Ok(s) => s.into_raw(),
+        Err(e) => {
+            write_error(e, error);
+            ptr::null_mut()
+        }
+    }
+}
@@ -2,8 +2,10 @@ use anyhow::Result;
 use camino::Utf8PathBuf;
 use clap::arg;
 use clap::Arg;
+use clap::ArgMatches;
 use clap::Command;
 
+use crate::emit::Stage;
 use crate::log;
 
 /// Compiler configuration. Contains all fields necessary to compile an ashlang program.
@@ -17,43 +19,684 @@ pub struct Config {
     pub extension_priorities: Vec<String>,
     pub entry_fn: String,
     pub field: String,
+    // remove unused signals and constraints from r1cs output
+    pub eliminate_dead_witness: bool,
+    // write iden3 .r1cs and .wtns files using this path prefix
+    pub circom_out: Option<Utf8PathBuf>,
+    // write binary .ar1csb and .witb files using this path prefix
+    pub binary_out: Option<Utf8PathBuf>,
+    // directory for cached function compilations
+    pub cache_dir: Option<Utf8PathBuf>,
+    // print the static memory usage of the compiled program
+    pub report_memory: bool,
+    // print the cost of each source line
+    pub profile: bool,
+    // write a trace of witness calculation to this file
+    pub witness_trace: Option<Utf8PathBuf>,
+    // stream the witness to this file without holding it in memory
+    pub stream_witness: Option<Utf8PathBuf>,
+    // build the r1cs witness using this many threads
+    pub jobs: Option<usize>,
+    // write the proof to this file in a portable envelope
+    pub proof_out: Option<Utf8PathBuf>,
+    // directory for cached proving keys and setup parameters
+    pub key_dir: Option<Utf8PathBuf>,
+    // write a solidity verifier for the groth16 proof to this file
+    pub solidity_out: Option<Utf8PathBuf>,
+    // prove this many steps of the entry function by folding
+    pub fold: Option<usize>,
+    // loops in the tasm target with more iterations than this
+    // are compiled as a loop instead of being unrolled
+    pub unroll_limit: Option<usize>,
+    // assert that dynamic indices are within the dimensions of
+    // the indexed variable
+    pub checked_indexing: bool,
+    // run the tasm program without proving and write the
+    // instruction trace to this file
+    pub trace_only: Option<Utf8PathBuf>,
+    // write a table of the source name of each signal in the r1cs
+    // output
+    pub symbols: bool,
+    // write the artifact of each stage of compilation
+    pub emit: Vec<Stage>,
+    // directory emitted artifacts are written to, defaults to
+    // `build`
+    pub emit_dir: Option<Utf8PathBuf>,
 }
 
+impl Config {
+    /// A config compiling an entry function for a target and field
+    /// with no include paths, inputs, or output files. Other fields
+    /// may be set before compiling.
+    #[allow(dead_code)]
+    pub fn new(entry_fn: &str, target: &str, field: &str) -> Self {
+        let extension_priorities = match target {
+            "tasm" => vec!["ash", "tasm"],
+            _ => vec!["ash", "ar1cs", "r1cs"],
+        };
+        Config {
+            include_paths: vec![],
+            verbosity: 0,
+            inputs: vec![],
+            secret_inputs: vec![],
+            target: target.to_string(),
+            extension_priorities: extension_priorities.into_iter().map(String::from).collect(),
+            entry_fn: entry_fn.to_string(),
+            field: field.to_string(),
+            eliminate_dead_witness: true,
+            circom_out: None,
+            binary_out: None,
+            cache_dir: None,
+            report_memory: false,
+            profile: false,
+            witness_trace: None,
+            stream_witness: None,
+            jobs: None,
+            proof_out: None,
+            key_dir: None,
+            solidity_out: None,
+            fold: None,
+            unroll_limit: None,
+            checked_indexing: false,
+            trace_only: None,
+            symbols: false,
+            emit: vec![],
+            emit_dir: None,
+        }
+    }
+}
+
+/// Parse the command line arguments.
 #[allow(dead_code)]
-pub fn parse() -> Result<Config> {
-    let matches = cli().get_matches();
-    let entry_fn = matches
-        .get_one::<String>("ENTRY_FN")
-        .expect("Failed to get ENTRY_FN");
-    let target = matches.get_one::<String>("target");
-    let field = matches.get_one::<String>("field");
-    let include_paths = matches
+pub fn matches() -> ArgMatches {
+    cli().get_matches()
+}
+
+/// The include paths of a command, e.g. `acc lsp -i ./stdlib`.
+#[allow(dead_code)]
+pub fn include_paths(matches: &ArgMatches) -> Vec<Utf8PathBuf> {
+    matches
         .get_many::<String>("include")
         .unwrap_or_default()
         .map(|v| v.as_str())
         .filter(|v| !v.is_empty())
         .map(Utf8PathBuf::from)
+        .collect::<Vec<_>>()
+}
+
+/// The paths to format and whether files should only be
+/// checked, e.g. `acc fmt --check ./stdlib`.
+#[allow(dead_code)]
+pub fn fmt_args(matches: &ArgMatches) -> (Vec<Utf8PathBuf>, bool) {
+    let paths = matches
+        .get_many::<String>("PATHS")
+        .unwrap_or_default()
+        .map(Utf8PathBuf::from)
         .collect::<Vec<_>>();
+    let check = *matches.get_one::<bool>("check").unwrap_or(&false);
+    (paths, check)
+}
+
+/// The project directory of `acc init` or `acc build`.
+#[allow(dead_code)]
+pub fn project_dir(matches: &ArgMatches) -> Utf8PathBuf {
+    matches
+        .get_one::<String>("DIR")
+        .map(Utf8PathBuf::from)
+        .unwrap_or_else(|| Utf8PathBuf::from("."))
+}
+
+/// Apply the inputs, verbosity, proof path, and key directory of
+/// `acc build` to the config read from a manifest.
+#[allow(dead_code)]
+pub fn parse_build(matches: &ArgMatches, config: &mut Config) {
+    config.inputs = parse_inputs(matches.get_one::<String>("public_inputs"));
+    config.secret_inputs = parse_inputs(matches.get_one::<String>("secret_inputs"));
+    if *matches.get_one::<bool>("print_asm").unwrap_or(&false) {
+        config.verbosity = 1;
+    }
+    config.proof_out = matches
+        .get_one::<String>("proof_out")
+        .map(Utf8PathBuf::from);
+    config.key_dir = matches.get_one::<String>("keys").map(Utf8PathBuf::from);
+}
+
+/// The configuration and filter of `acc test`. Tests are found in
+/// the include paths, or the include paths of `project` if none
+/// are specified, e.g. a config read from a project manifest.
+#[allow(dead_code)]
+pub fn parse_test(
+    matches: &ArgMatches,
+    project: Option<Config>,
+) -> Result<(Config, Option<String>)> {
+    let filter = matches.get_one::<String>("FILTER").cloned();
+    let include_paths = include_paths(matches);
+    let mut config = match project {
+        Some(config) if include_paths.is_empty() => config,
+        _ => {
+            if include_paths.is_empty() {
+                return log::error!(
+                    ConfigError: "No include paths specified",
+                    "specify a path containing tests using -i, or run in a directory containing ash.toml"
+                );
+            }
+            Config {
+                include_paths,
+                verbosity: 0,
+                inputs: vec![],
+                secret_inputs: vec![],
+                target: "r1cs".to_string(),
+                extension_priorities: vec!["ash".to_string()],
+                entry_fn: String::new(),
+                field: "curve25519".to_string(),
+                eliminate_dead_witness: true,
+                circom_out: None,
+                binary_out: None,
+                cache_dir: None,
+                report_memory: false,
+                profile: false,
+                witness_trace: None,
+                stream_witness: None,
+                jobs: None,
+                proof_out: None,
+                key_dir: None,
+                solidity_out: None,
+                fold: None,
+                unroll_limit: None,
+                checked_indexing: false,
+                trace_only: None,
+                symbols: false,
+                emit: vec![],
+                emit_dir: None,
+            }
+        }
+    };
+    if let Some(target) = matches.get_one::<String>("target") {
+        config.target = target.clone();
+        if target == "tasm" {
+            config.field = "foi".to_string();
+        }
+    }
+    if let Some(field) = matches.get_one::<String>("field") {
+        config.field = field.clone();
+    }
+    match config.target.as_str() {
+        "tasm" => config.extension_priorities.push("tasm".to_string()),
+        _ => {
+            config.extension_priorities.push("ar1cs".to_string());
+            config.extension_priorities.push("r1cs".to_string());
+        }
+    }
+    Ok((config, filter))
+}
+
+/// The configuration, entrypoints, and output directory of
+/// `acc lib`. Without entrypoints or include paths the
+/// entrypoints and config of `project` are used, e.g. read from a
+/// project manifest.
+#[allow(dead_code)]
+pub fn parse_lib(
+    matches: &ArgMatches,
+    project: Option<(Config, Vec<String>)>,
+) -> Result<(Config, Vec<String>, Utf8PathBuf)> {
+    let entry_fns = matches
+        .get_many::<String>("ENTRY_FNS")
+        .unwrap_or_default()
+        .cloned()
+        .collect::<Vec<_>>();
+    let include_paths = include_paths(matches);
+    let out = matches
+        .get_one::<String>("out")
+        .map(Utf8PathBuf::from)
+        .unwrap_or_else(|| Utf8PathBuf::from("build"));
+    let (mut config, entry_fns) = match project {
+        Some(project) if entry_fns.is_empty() && include_paths.is_empty() => project,
+        _ => {
+            if include_paths.is_empty() {
+                return log::error!(
+                    ConfigError: "No include paths specified",
+                    "specify a path containing the entrypoints using -i, or run in a directory containing ash.toml"
+                );
+            }
+            if entry_fns.is_empty() {
+                return log::error!(
+                    ConfigError: "No entrypoints specified",
+                    "specify the entry functions of the library e.g. acc lib deposit withdraw"
+                );
+            }
+            let config = Config {
+                include_paths,
+                verbosity: 0,
+                inputs: vec![],
+                secret_inputs: vec![],
+                target: "r1cs".to_string(),
+                extension_priorities: vec!["ash".to_string()],
+                entry_fn: entry_fns[0].clone(),
+                field: "curve25519".to_string(),
+                eliminate_dead_witness: true,
+                circom_out: None,
+                binary_out: None,
+                cache_dir: None,
+                report_memory: false,
+                profile: false,
+                witness_trace: None,
+                stream_witness: None,
+                jobs: None,
+                proof_out: None,
+                key_dir: None,
+                solidity_out: None,
+                fold: None,
+                unroll_limit: None,
+                checked_indexing: false,
+                trace_only: None,
+                symbols: false,
+                emit: vec![],
+                emit_dir: None,
+            };
+            (config, entry_fns)
+        }
+    };
+    if let Some(target) = matches.get_one::<String>("target") {
+        config.target = target.clone();
+        if target == "tasm" {
+            config.field = "oxfoi".to_string();
+        }
+    }
+    if let Some(field) = matches.get_one::<String>("field") {
+        config.field = field.clone();
+    }
+    match config.target.as_str() {
+        "tasm" => config.extension_priorities.push("tasm".to_string()),
+        _ => {
+            config.extension_priorities.push("ar1cs".to_string());
+            config.extension_priorities.push("r1cs".to_string());
+        }
+    }
+    Ok((config, entry_fns, out))
+}
+
+/// The configuration, number of runs, and seed of `acc fuzz`.
+/// Programs are fuzzed in the oxfoi field, the only field both
+/// targets support.
+#[allow(dead_code)]
+pub fn parse_fuzz(matches: &ArgMatches) -> Result<(Config, usize, u64)> {
+    let entry_fn = matches
+        .get_one::<String>("ENTRY_FN")
+        .expect("Failed to get ENTRY_FN");
+    let runs = match matches.get_one::<String>("runs") {
+        Some(v) => match v.parse::<usize>() {
+            Ok(runs) => runs,
+            Err(_) => {
+                return log::error!(
+                    ConfigError: &format!("invalid number of runs: {v}"),
+                    "specify a number of runs e.g. --runs 1000"
+                )
+            }
+        },
+        None => 100,
+    };
+    let seed = match matches.get_one::<String>("seed") {
+        Some(v) => match v.parse::<u64>() {
+            Ok(seed) => seed,
+            Err(_) => {
+                return log::error!(
+                    ConfigError: &format!("invalid seed: {v}"),
+                    "specify a seed printed by a previous run e.g. --seed 42"
+                )
+            }
+        },
+        None => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_nanos() as u64,
+    };
+    let config = Config {
+        include_paths: include_paths(matches),
+        verbosity: 0,
+        inputs: vec![],
+        secret_inputs: vec![],
+        target: "tasm".to_string(),
+        extension_priorities: vec!["ash".to_string()],
+        entry_fn: entry_fn.to_string(),
+        field: "oxfoi".to_string(),
+        eliminate_dead_witness: true,
+        circom_out: None,
+        binary_out: None,
+        cache_dir: None,
+        report_memory: false,
+        profile: false,
+        witness_trace: None,
+        stream_witness: None,
+        jobs: None,
+        proof_out: None,
+        key_dir: None,
+        solidity_out: None,
+        fold: None,
+        unroll_limit: None,
+        checked_indexing: false,
+        trace_only: None,
+        symbols: false,
+        emit: vec![],
+        emit_dir: None,
+    };
+    Ok((config, runs, seed))
+}
+
+/// The configuration and fields of `acc fields`.
+#[allow(dead_code)]
+pub fn parse_fields(matches: &ArgMatches) -> (Config, Vec<String>) {
+    let entry_fn = matches
+        .get_one::<String>("ENTRY_FN")
+        .expect("Failed to get ENTRY_FN");
+    let fields = match matches.get_one::<String>("fields") {
+        Some(v) => v
+            .split(',')
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string())
+            .collect(),
+        None => crate::fieldcheck::DEFAULT_FIELDS
+            .iter()
+            .map(|v| v.to_string())
+            .collect(),
+    };
+    let config = Config {
+        include_paths: include_paths(matches),
+        verbosity: 0,
+        inputs: vec![],
+        secret_inputs: vec![],
+        target: "r1cs".to_string(),
+        extension_priorities: vec!["ash".to_string()],
+        entry_fn: entry_fn.to_string(),
+        field: String::new(),
+        eliminate_dead_witness: true,
+        circom_out: None,
+        binary_out: None,
+        cache_dir: None,
+        report_memory: false,
+        profile: false,
+        witness_trace: None,
+        stream_witness: None,
+        jobs: None,
+        proof_out: None,
+        key_dir: None,
+        solidity_out: None,
+        fold: None,
+        unroll_limit: None,
+        checked_indexing: false,
+        trace_only: None,
+        symbols: false,
+        emit: vec![],
+        emit_dir: None,
+    };
+    (config, fields)
+}
+
+/// The configuration of `acc run`. Functions are loaded as in
+/// the tasm target, the interpreter runs the stdlib io functions
+/// natively.
+#[allow(dead_code)]
+pub fn parse_run(matches: &ArgMatches) -> Config {
+    let entry_fn = matches
+        .get_one::<String>("ENTRY_FN")
+        .expect("Failed to get ENTRY_FN");
+    let field = matches
+        .get_one::<String>("field")
+        .cloned()
+        .unwrap_or("oxfoi".to_string());
+    Config {
+        include_paths: include_paths(matches),
+        verbosity: 0,
+        inputs: parse_inputs(matches.get_one::<String>("public_inputs")),
+        secret_inputs: parse_inputs(matches.get_one::<String>("secret_inputs")),
+        target: "tasm".to_string(),
+        extension_priorities: vec!["ash".to_string(), "tasm".to_string()],
+        entry_fn: entry_fn.to_string(),
+        field,
+        eliminate_dead_witness: true,
+        circom_out: None,
+        binary_out: None,
+        cache_dir: None,
+        report_memory: false,
+        profile: false,
+        witness_trace: None,
+        stream_witness: None,
+        jobs: None,
+        proof_out: None,
+        key_dir: None,
+        solidity_out: None,
+        fold: None,
+        unroll_limit: None,
+        checked_indexing: false,
+        trace_only: None,
+        symbols: false,
+        emit: vec![],
+        emit_dir: None,
+    }
+}
+
+/// The configuration, proof path, and expected fingerprint of
+/// `acc verify`. A configuration is returned if an entry function
+/// is specified. The target and field are determined by the prover
+/// that generated the proof.
+#[allow(dead_code)]
+pub fn parse_verify(
+    matches: &ArgMatches,
+) -> (
+    Option<Config>,
+    Utf8PathBuf,
+    Option<String>,
+    Option<Utf8PathBuf>,
+) {
+    let proof = matches
+        .get_one::<String>("proof")
+        .map(Utf8PathBuf::from)
+        .expect("Failed to get proof");
+    let fingerprint = matches.get_one::<String>("fingerprint").cloned();
+    let key_dir = matches.get_one::<String>("keys").map(Utf8PathBuf::from);
+    let config = matches
+        .get_one::<String>("ENTRY_FN")
+        .map(|entry_fn| Config {
+            include_paths: include_paths(matches),
+            verbosity: 0,
+            inputs: vec![],
+            secret_inputs: vec![],
+            target: String::new(),
+            extension_priorities: vec!["ash".to_string()],
+            entry_fn: entry_fn.to_string(),
+            field: String::new(),
+            eliminate_dead_witness: true,
+            circom_out: None,
+            binary_out: None,
+            cache_dir: None,
+            report_memory: false,
+            profile: false,
+            witness_trace: None,
+            stream_witness: None,
+            jobs: None,
+            proof_out: None,
+            key_dir: None,
+            solidity_out: None,
+            fold: None,
+            unroll_limit: None,
+            checked_indexing: false,
+            trace_only: None,
+            symbols: false,
+            emit: vec![],
+            emit_dir: None,
+        });
+    (config, proof, fingerprint, key_dir)
+}
+
+#[allow(dead_code)]
+pub fn parse(matches: &ArgMatches) -> Result<Config> {
+    let entry_fn = matches
+        .get_one::<String>("ENTRY_FN")
+        .expect("Failed to get ENTRY_FN");
+    let target = matches.get_one::<String>("target");
+    let field = matches.get_one::<String>("field");
+    let include_paths = include_paths(matches);
     let inputs = matches.get_one::<String>("public_inputs");
     let secret_inputs = matches.get_one::<String>("secret_inputs");
+    let circom_out = matches
+        .get_one::<String>("circom_out")
+        .map(Utf8PathBuf::from);
+    let binary_out = matches
+        .get_one::<String>("binary_out")
+        .map(Utf8PathBuf::from);
+    let cache_dir = matches.get_one::<String>("cache").map(Utf8PathBuf::from);
+    let keep_dead = *matches.get_one::<bool>("keep_dead").unwrap_or(&false);
+    let report_memory = *matches.get_one::<bool>("report_memory").unwrap_or(&false);
+    let profile = *matches.get_one::<bool>("profile").unwrap_or(&false);
+    let checked_indexing = *matches
+        .get_one::<bool>("checked_indexing")
+        .unwrap_or(&false);
+    let symbols = *matches.get_one::<bool>("symbols").unwrap_or(&false);
+    let emit_dir = matches.get_one::<String>("emit_dir").map(Utf8PathBuf::from);
+    let witness_trace = matches
+        .get_one::<String>("witness_trace")
+        .map(Utf8PathBuf::from);
+    let trace_only = matches
+        .get_one::<String>("trace_only")
+        .map(Utf8PathBuf::from);
+    let stream_witness = matches
+        .get_one::<String>("stream_witness")
+        .map(Utf8PathBuf::from);
+    let proof_out = matches
+        .get_one::<String>("proof_out")
+        .map(Utf8PathBuf::from);
+    let key_dir = matches.get_one::<String>("keys").map(Utf8PathBuf::from);
+    let solidity_out = matches
+        .get_one::<String>("solidity_out")
+        .map(Utf8PathBuf::from);
+    let jobs = match matches.get_one::<String>("jobs") {
+        Some(v) => match v.parse::<usize>() {
+            Ok(jobs) if jobs > 0 => Some(jobs),
+            _ => {
+                return log::error!(
+                    ConfigError: &format!("invalid number of jobs: {v}"),
+                    "specify a positive number of threads e.g. --jobs 4"
+                )
+            }
+        },
+        None => None,
+    };
+    let fold = match matches.get_one::<String>("fold") {
+        Some(v) => match v.parse::<usize>() {
+            Ok(steps) if steps > 0 => Some(steps),
+            _ => {
+                return log::error!(
+                    ConfigError: &format!("invalid number of steps: {v}"),
+                    "specify a positive number of steps e.g. --fold 100"
+                )
+            }
+        },
+        None => None,
+    };
+    let unroll_limit = match matches.get_one::<String>("unroll_limit") {
+        Some(v) => match v.parse::<usize>() {
+            Ok(limit) => Some(limit),
+            _ => {
+                return log::error!(
+                    ConfigError: &format!("invalid unroll limit: {v}"),
+                    "specify a number of iterations e.g. --unroll-limit 64"
+                )
+            }
+        },
+        None => None,
+    };
     let mut verbosity = 0_u8;
     if *matches.get_one::<bool>("print_asm").unwrap_or(&false) {
         verbosity = 1;
     }
     if target.is_none() {
         return log::error!(
-            "No target specified",
+            ConfigError: "No target specified",
             "specify a target using -t [r1cs | tasm]"
         );
     }
     if field.is_none() {
         return log::error!(
-            "No field specified",
+            ConfigError: "No field specified",
             "specify a field using -f [foi | alt_bn128 | curve25519]"
         );
     }
     let target = target.unwrap().clone();
     let field = field.unwrap().clone();
+    if report_memory && target != "tasm" {
+        return log::error!(
+            ConfigError: "--report-memory is only supported in the tasm target",
+            "the r1cs target does not use memory"
+        );
+    }
+    if witness_trace.is_some() && target != "r1cs" {
+        return log::error!(
+            ConfigError: "--trace-witness is only supported in the r1cs target",
+            "the tasm target does not calculate a witness"
+        );
+    }
+    if trace_only.is_some() && target != "tasm" {
+        return log::error!(
+            ConfigError: "--trace-only is only supported in the tasm target",
+            "use --trace-witness to trace an r1cs witness"
+        );
+    }
+    if trace_only.is_some() && proof_out.is_some() {
+        return log::error!(
+            ConfigError: "--trace-only does not generate a proof",
+            "remove --proof-out or --trace-only"
+        );
+    }
+    if unroll_limit.is_some() && target != "tasm" {
+        return log::error!(
+            ConfigError: "--unroll-limit is only supported in the tasm target",
+            "every loop is unrolled in the r1cs target"
+        );
+    }
+    if jobs.is_some() && target != "r1cs" {
+        return log::error!(
+            ConfigError: "--jobs is only supported in the r1cs target",
+            "the tasm target does not calculate a witness"
+        );
+    }
+    if stream_witness.is_some() && target != "r1cs" {
+        return log::error!(
+            ConfigError: "--stream-witness is only supported in the r1cs target",
+            "the tasm target does not calculate a witness"
+        );
+    }
+    if proof_out.is_some() && target == "r1cs" && field != "curve25519" && field != "alt_bn128" {
+        return log::error!(
+            ConfigError: &format!("--proof-out is not supported in the {field} field"),
+            "proofs are generated in the curve25519 and alt_bn128 fields"
+        );
+    }
+    if key_dir.is_some() && (target != "r1cs" || field != "alt_bn128") {
+        return log::error!(
+            ConfigError: "--keys is only supported in the alt_bn128 field",
+            "keys are only cached by the groth16 and halo2 provers"
+        );
+    }
+    if solidity_out.is_some() && (target != "r1cs" || field != "alt_bn128") {
+        return log::error!(
+            ConfigError: "--solidity-out is only supported in the alt_bn128 field",
+            "solidity verifiers are generated for groth16 proofs"
+        );
+    }
+    if fold.is_some() && (target != "r1cs" || field != "alt_bn128") {
+        return log::error!(
+            ConfigError: "--fold is only supported in the alt_bn128 field",
+            "folding uses the nova prover over bn256"
+        );
+    }
+    let emit = match matches.get_one::<String>("emit") {
+        Some(v) => Stage::parse_list(v, &target)?,
+        None => vec![],
+    };
+    if emit_dir.is_some() && emit.is_empty() {
+        return log::error!(
+            ConfigError: "--emit-dir requires --emit",
+            "select the stages to write, e.g. `--emit ast,ar1cs`"
+        );
+    }
     Ok(Config {
         include_paths,
         target,
@@ -63,6 +706,25 @@ pub fn parse() -> Result<Config> {
         secret_inputs: parse_inputs(secret_inputs),
         extension_priorities: vec!["ash".to_string()],
         entry_fn: entry_fn.to_string(),
+        eliminate_dead_witness: !keep_dead,
+        circom_out,
+        binary_out,
+        cache_dir,
+        report_memory,
+        profile,
+        witness_trace,
+        stream_witness,
+        jobs,
+        proof_out,
+        key_dir,
+        solidity_out,
+        fold,
+        unroll_limit,
+        checked_indexing,
+        trace_only,
+        symbols,
+        emit,
+        emit_dir,
     })
 }
 
@@ -81,7 +743,155 @@ fn cli() -> Command {
     Command::new("acc")
         .about("ashlang compiler")
         .subcommand_required(false)
+        .subcommand_negates_reqs(true)
+        .args_conflicts_with_subcommands(true)
         .arg_required_else_help(true)
+        .subcommand(
+            Command::new("lsp")
+                .about("run a language server for ash files over stdio")
+                .arg(include_arg()),
+        )
+        .subcommand(
+            Command::new("init")
+                .about("create a project with an ash.toml manifest and an entry function")
+                .arg(arg!([DIR] "The project directory, defaults to the current directory")),
+        )
+        .subcommand(
+            Command::new("build")
+                .about("compile and prove the project described by an ash.toml manifest")
+                .arg(arg!([DIR] "The project directory, defaults to the current directory"))
+                .arg(print_asm_arg())
+                .arg(proof_out_arg())
+                .arg(keys_arg())
+                .args(input_args()),
+        )
+        .subcommand(
+            Command::new("test")
+                .about("run the functions whose name starts with test_ and report each result")
+                .arg(arg!([FILTER] "Only run tests whose name contains this string"))
+                .arg(include_arg())
+                .arg(
+                    Arg::new("target")
+                        .short('t')
+                        .long("target")
+                        .required(false)
+                        .help("the compile target of the tests, defaults to r1cs"),
+                )
+                .arg(
+                    Arg::new("field")
+                        .short('f')
+                        .long("field")
+                        .required(false)
+                        .help("the scalar field of the tests, defaults to curve25519 in r1cs and foi in tasm"),
+                ),
+        )
+        .subcommand(
+            Command::new("lib")
+                .about("compile several entry functions into a library of circuits with a fingerprint manifest")
+                .arg(arg!([ENTRY_FNS] ... "The entrypoint function names, defaults to the entries of ash.toml"))
+                .arg(include_arg())
+                .arg(
+                    Arg::new("target")
+                        .short('t')
+                        .long("target")
+                        .required(false)
+                        .help("the compile target of the library, defaults to r1cs"),
+                )
+                .arg(
+                    Arg::new("field")
+                        .short('f')
+                        .long("field")
+                        .required(false)
+                        .help("the scalar field of the library, defaults to curve25519 in r1cs and oxfoi in tasm"),
+                )
+                .arg(
+                    Arg::new("out")
+                        .short('o')
+                        .long("out")
+                        .required(false)
+                        .help("the directory the circuits and library.json are written to, defaults to build"),
+                ),
+        )
+        .subcommand(
+            Command::new("fuzz")
+                .about("run a function with random inputs in tasm and r1cs and check the targets agree")
+                .arg(arg!(<ENTRY_FN> "The entrypoint function name"))
+                .arg(include_arg())
+                .arg(
+                    Arg::new("runs")
+                        .long("runs")
+                        .required(false)
+                        .help("the number of runs, defaults to 100"),
+                )
+                .arg(
+                    Arg::new("seed")
+                        .long("seed")
+                        .required(false)
+                        .help("the seed of the first run, defaults to a random seed"),
+                ),
+        )
+        .subcommand(
+            Command::new("run")
+                .about("interpret a function natively and print its outputs without compiling or proving")
+                .arg(arg!(<ENTRY_FN> "The entrypoint function name"))
+                .arg(include_arg())
+                .arg(
+                    Arg::new("field")
+                        .short('f')
+                        .long("field")
+                        .required(false)
+                        .help("the scalar field of the program, defaults to oxfoi"),
+                )
+                .args(input_args()),
+        )
+        .subcommand(
+            Command::new("fields")
+                .about("compile a function in several fields and report static values that differ")
+                .arg(arg!(<ENTRY_FN> "The entrypoint function name"))
+                .arg(include_arg())
+                .arg(
+                    Arg::new("fields")
+                        .long("fields")
+                        .required(false)
+                        .help("the fields to compare, defaults to foi,curve25519,alt_bn128"),
+                ),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("verify a proof written using --proof-out, optionally against a program")
+                .arg(arg!([ENTRY_FN] "The entrypoint function name, groth16 proofs can be verified without it using --keys"))
+                .arg(include_arg())
+                .arg(
+                    Arg::new("proof")
+                        .long("proof")
+                        .required(true)
+                        .help("the proof file to verify"),
+                )
+                .arg(
+                    Arg::new("fingerprint")
+                        .long("fingerprint")
+                        .required(false)
+                        .help("the expected program fingerprint of the proof"),
+                )
+                .arg(
+                    Arg::new("keys")
+                        .long("keys")
+                        .required(false)
+                        .help("the key cache written during setup, groth16 proofs are verified using the cached verifying key and halo2 proofs using the cached parameters"),
+                ),
+        )
+        .subcommand(
+            Command::new("fmt")
+                .about("format ash files, directories are formatted recursively")
+                .arg(arg!(<PATHS> ... "The files or directories to format"))
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .required(false)
+                        .num_args(0)
+                        .help("exit with an error if a file is not formatted instead of rewriting it"),
+                ),
+        )
         .arg(arg!(<ENTRY_FN> "The entrypoint function name"))
         .arg(
             Arg::new("target")
@@ -96,36 +906,166 @@ fn cli() -> Command {
                 .short('f')
                 .long("scalar field to execute in")
                 .required(false)
-                .help("the name of the scalar field that should be used for proving: foi (goldilocks), alt_bn128, curve25519"),
+                .help("the name of the scalar field that should be used for proving: foi (goldilocks), alt_bn128, curve25519, or custom:<prime> e.g. custom:101 in r1cs"),
         )
+        .arg(include_arg())
+        .arg(print_asm_arg())
         .arg(
-            Arg::new("include")
-                .short('i')
-                .long("include")
+            Arg::new("keep_dead")
+                .long("keep-dead")
                 .required(false)
-                .help("specify a path to be recursively included")
-                .action(clap::ArgAction::Append),
+                .num_args(0)
+                .help("keep unused signals and constraints in r1cs output, useful for debugging"),
+        )
+        .arg(
+            Arg::new("circom_out")
+                .long("circom")
+                .required(false)
+                .help("write the r1cs and witness in the circom binary formats, e.g. --circom out/main writes out/main.r1cs and out/main.wtns"),
         )
         .arg(
-            Arg::new("print_asm")
-                .short('v')
-                .long("print")
+            Arg::new("binary_out")
+                .long("binary")
+                .required(false)
+                .help("write the r1cs and witness in a compact binary format and compare load times, e.g. --binary out/main writes out/main.ar1csb and out/main.witb"),
+        )
+        .arg(
+            Arg::new("cache")
+                .long("cache")
+                .required(false)
+                .help("cache compiled functions in this directory and reuse them in later builds"),
+        )
+        .arg(
+            Arg::new("report_memory")
+                .long("report-memory")
+                .required(false)
+                .num_args(0)
+                .help("print the memory used by each function in the tasm target"),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .required(false)
+                .num_args(0)
+                .help("print the constraints, or instructions and cycles, produced by each source line"),
+        )
+        .arg(
+            Arg::new("witness_trace")
+                .long("trace-witness")
+                .required(false)
+                .help("write each step of r1cs witness calculation and verification to this file"),
+        )
+        .arg(
+            Arg::new("stream_witness")
+                .long("stream-witness")
+                .required(false)
+                .help("calculate the r1cs witness one constraint at a time and stream it to this file"),
+        )
+        .arg(
+            Arg::new("jobs")
+                .short('j')
+                .long("jobs")
+                .required(false)
+                .help("calculate the r1cs witness using this many threads"),
+        )
+        .arg(proof_out_arg())
+        .arg(keys_arg())
+        .arg(
+            Arg::new("unroll_limit")
+                .long("unroll-limit")
+                .required(false)
+                .help("compile tasm loops with more iterations than this as a loop instead of unrolling them, defaults to 64"),
+        )
+        .arg(
+            Arg::new("checked_indexing")
+                .long("checked-indexing")
+                .required(false)
+                .num_args(0)
+                .help("fail if a dynamic vector index is out of bounds, using assertions in tasm and constraints in r1cs"),
+        )
+        .arg(
+            Arg::new("symbols")
+                .long("symbols")
                 .required(false)
                 .num_args(0)
-                .help("print the compiled asm before proving"),
+                .help("write the source name of each signal to the ar1cs header and label signals in witness errors"),
+        )
+        .arg(
+            Arg::new("emit")
+                .long("emit")
+                .required(false)
+                .help("write the artifact of each stage of compilation, a comma separated list of ast, ir, ar1cs, r1cs-json, and tasm"),
+        )
+        .arg(
+            Arg::new("emit_dir")
+                .long("emit-dir")
+                .required(false)
+                .help("the directory emitted artifacts are written to, defaults to build"),
+        )
+        .arg(
+            Arg::new("trace_only")
+                .long("trace-only")
+                .required(false)
+                .help("run the tasm program without proving, print the cycle count, ram usage, and final stack, and write the instruction trace to this file"),
         )
         .arg(
-            Arg::new("public_inputs")
-                .short('p')
-                .long("public")
+            Arg::new("fold")
+                .long("fold")
                 .required(false)
-                .help("public inputs to the program"),
+                .help("prove this many steps of the entry function by folding, the public outputs of each step are the public inputs of the next"),
         )
         .arg(
-            Arg::new("secret_inputs")
-                .short('s')
-                .long("secret")
+            Arg::new("solidity_out")
+                .long("solidity-out")
                 .required(false)
-                .help("secret inputs to the program"),
+                .help("write a solidity contract verifying the groth16 proof to this file"),
         )
+        .args(input_args())
+}
+
+fn include_arg() -> Arg {
+    Arg::new("include")
+        .short('i')
+        .long("include")
+        .required(false)
+        .help("specify a path to be recursively included")
+        .action(clap::ArgAction::Append)
+}
+
+fn proof_out_arg() -> Arg {
+    Arg::new("proof_out")
+        .long("proof-out")
+        .required(false)
+        .help("write the proof, program fingerprint, and public values to this file")
+}
+
+fn keys_arg() -> Arg {
+    Arg::new("keys")
+        .long("keys")
+        .required(false)
+        .help("cache proving keys and setup parameters in this directory and reuse them for the same program")
+}
+
+fn print_asm_arg() -> Arg {
+    Arg::new("print_asm")
+        .short('v')
+        .long("print")
+        .required(false)
+        .num_args(0)
+        .help("print the compiled asm before proving")
+}
+
+fn input_args() -> [Arg; 2] {
+    [
+        Arg::new("public_inputs")
+            .short('p')
+            .long("public")
+            .required(false)
+            .help("public inputs to the program, read using `pub input` in tasm"),
+        Arg::new("secret_inputs")
+            .short('s')
+            .long("secret")
+            .required(false)
+            .help("secret inputs to the program, read using `secret input`. In r1cs public inputs are also read from these"),
+    ]
 }
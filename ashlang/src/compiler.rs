@@ -1,25 +1,308 @@
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fs;
 
 use anyhow::Result;
+use camino::Utf8Component;
 use camino::Utf8PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use rayon::prelude::*;
 use ring_math::PolynomialRingElement;
 
+use crate::builtin;
+use crate::cache::FnCache;
 use crate::cli::Config;
+use crate::constants;
+use crate::cse;
+use crate::emit::Emitter;
+use crate::emit::Stage;
+use crate::fieldcheck::StaticValue;
+use crate::interpreter::Interpreter;
+use crate::library::Circuit;
+use crate::library::Library;
 use crate::log;
+#[cfg(feature = "manifest")]
+use crate::manifest::Manifest;
 use crate::parser::AshParser;
 use crate::parser::AstNode;
+use crate::parser::Expr;
+use crate::parser::VarType;
+use crate::profile::Profile;
+use crate::r1cs::ar1cs::Ar1cs;
+use crate::r1cs::ar1cs::Ar1csPublic;
+use crate::r1cs::ar1cs::Ar1csSymbol;
 use crate::r1cs::constraint::R1csConstraint;
 use crate::r1cs::parser::R1csParser;
 use crate::tasm::asm_parser::AsmParser;
+use crate::tasm::memory::FnMemory;
+use crate::tasm::memory::MemoryReport;
+use crate::tasm::peephole;
 use crate::tasm::vm::FnCall;
+use crate::tasm::vm::UNROLL_LIMIT;
+use crate::typecheck;
+
+/// Whether a function returns a value. This is determined
+/// from the function source so that an ash function has
+/// the same return semantics on every target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReturnType {
+    /// the function has no return statement and may only
+    /// be called as a statement
+    Void,
+    /// the function returns a scalar, vector, or matrix. The
+    /// shape is known if it is declared in the function header,
+    /// e.g. `(v[N]) -> field[N]`. Each dimension is a literal or
+    /// a dimension of an argument, empty for a scalar
+    Value(Option<Vec<String>>),
+}
+
+impl ReturnType {
+    pub fn from_ast(ast: &[AstNode]) -> Self {
+        let returns = ast.iter().any(|node| match node {
+            AstNode::Rtrn(_) => true,
+            AstNode::If(_, if_block, else_block) => {
+                Self::from_ast(if_block) != Self::Void || Self::from_ast(else_block) != Self::Void
+            }
+            AstNode::Loop(_, body) | AstNode::While(_, body) => Self::from_ast(body) != Self::Void,
+            _ => false,
+        });
+        if returns {
+            Self::Value(None)
+        } else {
+            Self::Void
+        }
+    }
+
+    /// Add the shape declared in the function header, if any.
+    pub fn with_shape(self, fn_name: &str, shape: Option<Vec<String>>) -> Result<Self> {
+        match (self, shape) {
+            (Self::Void, Some(_)) => log::error!(
+                TypeError: &format!("function \"{fn_name}\" declares a return shape but does not return a value"),
+                "add a return statement or remove the shape from the function header"
+            ),
+            (Self::Value(_), Some(shape)) => Ok(Self::Value(Some(shape))),
+            (v, None) => Ok(v),
+        }
+    }
+
+    /// Check the dimensions of a value returned by a function
+    /// against the declared shape, if any. `dimension` is the
+    /// value of a named dimension in the function.
+    pub fn check(
+        &self,
+        fn_name: &str,
+        dimensions: &[usize],
+        dimension: impl Fn(&str) -> Option<usize>,
+    ) -> Result<()> {
+        let shape = match self {
+            Self::Value(Some(shape)) => shape,
+            _ => return Ok(()),
+        };
+        let expected = shape
+            .iter()
+            .map(|v| match v.parse::<usize>() {
+                Ok(v) => Ok(v),
+                Err(_) => match dimension(v) {
+                    Some(v) => Ok(v),
+                    None => log::error!(ScopeError: &format!("unknown return dimension \"{v}\"")),
+                },
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let expected = VarType::Field(expected);
+        if !expected.matches(dimensions) {
+            return log::error!(
+                TypeError: &format!(
+                    "function \"{fn_name}\" must return {} but returned {}",
+                    expected.name(),
+                    VarType::Field(dimensions.to_vec()).name()
+                ),
+                "the return shape is declared in the function header"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Whether a public value is read from the inputs or
+/// written to the outputs of a program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PublicKind {
+    Input,
+    Output,
+}
+
+/// A value declared using `pub input` or `pub output`.
+///
+/// `position` is the order of the declaration among the
+/// declarations of the same kind. In the r1cs target `signal`
+/// is the index of the signal holding the value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PublicValue {
+    pub name: String,
+    pub kind: PublicKind,
+    pub position: usize,
+    pub signal: Option<usize>,
+}
+
+impl PublicValue {
+    /// Find the public declarations in an entrypoint ast.
+    pub fn from_ast(ast: &[AstNode]) -> Vec<Self> {
+        let mut out: Vec<Self> = vec![];
+        for node in ast {
+            let (name, kind) = match node {
+                AstNode::PubInput(name) => (name, PublicKind::Input),
+                AstNode::PubOutput(name) => (name, PublicKind::Output),
+                _ => continue,
+            };
+            let position = out.iter().filter(|v| v.kind == kind).count();
+            out.push(Self {
+                name: name.clone(),
+                kind,
+                position,
+                signal: None,
+            });
+        }
+        out
+    }
+
+    /// The declaration of this value, e.g. `pub input x`. This is
+    /// attached to the symbolic constraint marking the value in the
+    /// r1cs target.
+    pub fn declaration(&self) -> String {
+        match self.kind {
+            PublicKind::Input => format!("pub input {}", self.name),
+            PublicKind::Output => format!("pub output {}", self.name),
+        }
+    }
+}
+
+// read the source of a function from the path it was included at
+fn read_fn_source(
+    fn_name: &str,
+    file_path: Option<&Utf8PathBuf>,
+    sources: &HashMap<Utf8PathBuf, String>,
+) -> Result<(String, String)> {
+    if let Some(file_path) = file_path {
+        if let Some(src) = builtin::source(file_path) {
+            return Ok((src.to_string(), file_path.extension().unwrap().to_string()));
+        }
+        if let Some(src) = sources.get(file_path) {
+            return Ok((src.clone(), file_path.extension().unwrap().to_string()));
+        }
+        let ext = match file_path.extension() {
+            Some(ext) => ext,
+            None => {
+                return log::error!(ConfigError: &format!("source file has no extension: {file_path}"))
+            }
+        };
+        match std::fs::read_to_string(file_path) {
+            Ok(unparsed_file) => Ok((unparsed_file, ext.to_string())),
+            Err(e) => log::error!(
+                ConfigError: &format!("Failed to read source file: {file_path}"),
+                &e.to_string()
+            ),
+        }
+    } else {
+        not_found(fn_name)
+    }
+}
+
+// labels of the compiled tasm functions called from some
+// assembly, directly or through other compiled functions
+fn called_labels<'a>(
+    asm: impl Iterator<Item = &'a String>,
+    compiled_fn: &[(String, &FnCall, &Vec<String>)],
+) -> HashSet<String> {
+    let call_label = |v: &String| v.trim().strip_prefix("call ").map(|l| l.trim().to_string());
+    let mut called = HashSet::new();
+    let mut pending = asm.filter_map(call_label).collect::<Vec<_>>();
+    while let Some(label) = pending.pop() {
+        if !called.insert(label.clone()) {
+            continue;
+        }
+        if let Some((_, _, fn_asm)) = compiled_fn.iter().find(|(l, _, _)| *l == label) {
+            pending.extend(fn_asm.iter().filter_map(call_label));
+        }
+    }
+    called
+}
+
+// remove source locations from an ast, recursing into blocks
+fn canonical_ast(ast: &[AstNode]) -> Vec<AstNode> {
+    ast.iter()
+        .filter_map(|node| match node {
+            AstNode::Location(_) => None,
+            AstNode::If(cond, if_block, else_block) => Some(AstNode::If(
+                cond.clone(),
+                canonical_ast(if_block),
+                canonical_ast(else_block),
+            )),
+            AstNode::Loop(count, block) => Some(AstNode::Loop(count.clone(), canonical_ast(block))),
+            AstNode::While(cond, block) => Some(AstNode::While(cond.clone(), canonical_ast(block))),
+            AstNode::Unconstrained(returns, block) => Some(AstNode::Unconstrained(
+                returns.clone(),
+                canonical_ast(block),
+            )),
+            node => Some(node.clone()),
+        })
+        .collect()
+}
+
+fn not_found<T>(fn_name: &str) -> Result<T> {
+    log::error!(
+        ScopeError: &format!("function is not present in sources: {fn_name}"),
+        &format!("unable to find a file {fn_name}.ash in your include paths after searching recursively\n\nmake sure you have specified an include path containing this file")
+    )
+}
+
+// a function source file that has been read and, where
+// possible, parsed. Sources are loaded in parallel so
+// parsing that depends on the field is done after loading
+enum LoadedSource {
+    Ash(String, AshParser),
+    Tasm(String, AsmParser),
+    Ar1cs(String),
+    Circom(Vec<u8>),
+}
+
+impl LoadedSource {
+    fn load(
+        fn_name: &str,
+        path: Option<&Utf8PathBuf>,
+        sources: &HashMap<Utf8PathBuf, String>,
+    ) -> Result<Self> {
+        if let Some(path) = path {
+            if path.extension() == Some("r1cs") {
+                // circom r1cs files are binary
+                return Ok(Self::Circom(fs::read(path)?));
+            }
+        }
+        let (text, ext) = read_fn_source(fn_name, path, sources)?;
+        match ext.as_str() {
+            "ash" => {
+                let file = path.map(|v| v.to_string()).unwrap_or_default();
+                let parser = AshParser::parse_file(&text, fn_name, &file)?;
+                Ok(Self::Ash(text, parser))
+            }
+            "tasm" => {
+                let parser = AsmParser::parse(&text, fn_name)?;
+                Ok(Self::Tasm(text, parser))
+            }
+            "ar1cs" => Ok(Self::Ar1cs(text)),
+            _ => log::error!(&format!("unexpected file extension: {ext}")),
+        }
+    }
+}
 
 // things that both Compiler and VM
 // need to modify
 pub struct CompilerState<T: PolynomialRingElement> {
-    // each function gets it's own memory space
-    // track where in the memory we're at
-    pub memory_offset: usize,
+    // number of memory cells reserved by the compiler, these
+    // are allocated downward from the top of the address space
+    pub reserved_memory: usize,
     pub called_fn: HashMap<FnCall, u64>,
     pub fn_return_types: HashMap<FnCall, FnCall>,
     pub is_fn_ash: HashMap<String, bool>,
@@ -30,7 +313,51 @@ pub struct CompilerState<T: PolynomialRingElement> {
     pub fn_to_r1cs_parser: HashMap<String, R1csParser<T>>,
     pub path_to_fn: HashMap<Utf8PathBuf, String>,
     pub fn_to_path: HashMap<String, Utf8PathBuf>,
+    // function sources held in memory instead of on disk,
+    // keyed by path
+    pub sources: HashMap<Utf8PathBuf, String>,
+    // file names that exist in multiple modules
+    pub ambiguous_fn: HashMap<String, Vec<Utf8PathBuf>>,
+    // return types of ash functions
+    pub fn_return_type: HashMap<String, ReturnType>,
     pub messages: Vec<String>,
+    // cache of compiled functions, if enabled
+    pub cache: Option<FnCache>,
+    // hash of each function source file
+    pub fn_source_hash: HashMap<String, String>,
+    // functions called directly by each ash function
+    pub fn_deps: HashMap<String, Vec<String>>,
+    // maximum recursion depth declared by each recursive function
+    pub fn_max_depth: HashMap<String, usize>,
+    // whether calls of each function are inlined in the tasm
+    // target, declared using `#[inline]` or `#[noinline]`
+    pub fn_inline: HashMap<String, bool>,
+    // tasm functions currently being compiled, outermost first
+    pub fn_call_stack: Vec<FnCall>,
+    // tasm functions that call themselves at runtime
+    pub recursive_fn: HashSet<FnCall>,
+    // nested r1cs calls to each function being inlined
+    pub fn_depth: HashMap<String, usize>,
+    // memory used by each compiled tasm function
+    pub fn_memory: HashMap<FnCall, FnMemory>,
+    // cost of each source line, if profiling
+    pub profile: Option<Profile>,
+    // static values assigned in the r1cs target, if tracing
+    pub static_trace: Option<Vec<StaticValue>>,
+    // signal of the latest std::challenge in the r1cs target
+    pub transcript: Option<usize>,
+    // memory address holding the latest std::challenge in the
+    // tasm target, reserved at the top of the address space
+    pub transcript_address: Option<usize>,
+    // static loops with more iterations than this are not
+    // unrolled in the tasm target
+    pub unroll_limit: usize,
+    // dynamic indices are checked against the dimensions of the
+    // indexed variable
+    pub checked_indexing: bool,
+    // source name keyed to the signals it has held in the r1cs
+    // target, if writing a symbol table
+    pub symbols: Option<BTreeMap<String, Vec<usize>>>,
 }
 
 impl<T: PolynomialRingElement> Default for CompilerState<T> {
@@ -42,7 +369,7 @@ impl<T: PolynomialRingElement> Default for CompilerState<T> {
 impl<T: PolynomialRingElement> CompilerState<T> {
     pub fn new() -> Self {
         CompilerState {
-            memory_offset: 0,
+            reserved_memory: 0,
             called_fn: HashMap::new(),
             fn_return_types: HashMap::new(),
             compiled_fn: HashMap::new(),
@@ -53,8 +380,58 @@ impl<T: PolynomialRingElement> CompilerState<T> {
             fn_to_r1cs_parser: HashMap::new(),
             path_to_fn: HashMap::new(),
             fn_to_path: HashMap::new(),
+            sources: HashMap::new(),
+            ambiguous_fn: HashMap::new(),
+            fn_return_type: HashMap::new(),
             messages: vec![],
+            cache: None,
+            fn_source_hash: HashMap::new(),
+            fn_deps: HashMap::new(),
+            fn_max_depth: HashMap::new(),
+            fn_inline: HashMap::new(),
+            fn_call_stack: vec![],
+            recursive_fn: HashSet::new(),
+            fn_depth: HashMap::new(),
+            fn_memory: HashMap::new(),
+            profile: None,
+            static_trace: None,
+            transcript: None,
+            transcript_address: None,
+            unroll_limit: UNROLL_LIMIT,
+            checked_indexing: false,
+            symbols: None,
+        }
+    }
+
+    /// Hash the source of a function and every function it
+    /// may call. Used to key cached compilations.
+    pub fn fn_hash(&self, name: &str) -> String {
+        let mut names = vec![name.to_string()];
+        let mut i = 0;
+        while i < names.len() {
+            for dep in self.fn_deps.get(&names[i]).unwrap_or(&vec![]) {
+                if !names.contains(dep) {
+                    names.push(dep.clone());
+                }
+            }
+            i += 1;
         }
+        names[1..].sort();
+        let mut parts = vec![];
+        // checked indexing changes the constraints of a function
+        if self.checked_indexing {
+            parts.push("checked indexing");
+        }
+        for name in &names {
+            parts.push(name.as_str());
+            parts.push(
+                self.fn_source_hash
+                    .get(name)
+                    .map(|v| v.as_str())
+                    .unwrap_or_default(),
+            );
+        }
+        FnCache::key(&parts)
     }
 }
 
@@ -66,6 +443,18 @@ pub struct Compiler<T: PolynomialRingElement> {
     state: CompilerState<T>,
     extensions: Vec<String>,
     target: String,
+    eliminate_dead_witness: bool,
+    // values declared public in the most recent compilation
+    public_values: Vec<PublicValue>,
+    // memory used by the most recent tasm compilation
+    memory_report: Option<MemoryReport>,
+    // hash identifying the most recently compiled program
+    fingerprint: Option<String>,
+    // functions whose common subexpressions have been eliminated,
+    // a function is compiled once for every entrypoint of a library
+    eliminated: HashSet<String>,
+    // writes intermediate artifacts, if any stage is emitted
+    emitter: Option<Emitter>,
 }
 
 impl<T: PolynomialRingElement> Compiler<T> {
@@ -75,14 +464,63 @@ impl<T: PolynomialRingElement> Compiler<T> {
             state: CompilerState::new(),
             extensions: config.extension_priorities.clone(),
             target: config.target.clone(),
+            eliminate_dead_witness: config.eliminate_dead_witness,
+            public_values: vec![],
+            memory_report: None,
+            fingerprint: None,
+            eliminated: HashSet::new(),
+            emitter: None,
         };
+        // the std module is always available
+        for (name, path) in builtin::functions(&compiler.extensions) {
+            compiler.state.fn_to_path.insert(name.clone(), path.clone());
+            compiler.state.path_to_fn.insert(path, name);
+        }
+        for (name, return_type) in builtin::precompile_return_types() {
+            compiler.state.fn_return_type.insert(name, return_type);
+        }
         if let Err(e) = compiler.include_many(&config.include_paths) {
-            return log::error!(&format!("Failed to include path: {:?}", e));
+            return log::error!(ConfigError: &format!("Failed to include path: {:?}", e));
         }
         compiler.print_asm = config.verbosity > 0;
+        compiler.state.cache = config.cache_dir.clone().map(FnCache::new);
+        if let Some(limit) = config.unroll_limit {
+            compiler.state.unroll_limit = limit;
+        }
+        compiler.state.checked_indexing = config.checked_indexing;
+        if config.symbols {
+            compiler.state.symbols = Some(BTreeMap::new());
+        }
+        compiler.emitter = Emitter::new(config);
+        if config.profile {
+            let unit = match config.target.as_str() {
+                "tasm" => "instructions",
+                _ => "constraints",
+            };
+            compiler.state.profile = Some(Profile::new(unit));
+        }
+        Ok(compiler)
+    }
+
+    /// Create a compiler that reads functions from in-memory sources
+    /// instead of the filesystem. Include paths in the config are
+    /// ignored, see `include_sources` for how paths are resolved.
+    pub fn from_sources(config: &Config, sources: HashMap<String, String>) -> Result<Self> {
+        let mut config = config.clone();
+        config.include_paths = vec![];
+        let mut compiler = Self::new(&config)?;
+        compiler.include_sources(sources)?;
         Ok(compiler)
     }
 
+    /// Create a compiler for a project, reading the include paths
+    /// of the package and its dependencies from the manifest.
+    #[cfg(feature = "manifest")]
+    #[allow(dead_code)]
+    pub fn from_manifest(manifest: &Manifest) -> Result<Self> {
+        Self::new(&manifest.config()?)
+    }
+
     pub fn include_many(&mut self, paths: &Vec<Utf8PathBuf>) -> Result<()> {
         for path in paths {
             self.include(path)?;
@@ -98,6 +536,21 @@ impl<T: PolynomialRingElement> Compiler<T> {
     // if the include is a directory, the directory is recursively
     // walked and passed to this function
     pub fn include(&mut self, path: &Utf8PathBuf) -> Result<()> {
+        #[cfg(target_arch = "wasm32")]
+        return log::error!(
+            ConfigError: &format!("cannot include {path}, the filesystem is not available in wasm"),
+            "use Compiler::from_sources to compile in-memory sources"
+        );
+        #[cfg(not(target_arch = "wasm32"))]
+        self.include_in_module(path, &[])
+    }
+
+    // include a path as part of a module. Files in a directory are
+    // available by file name and by module path, e.g. the file
+    // `stdlib/crypto/hash.ash` may be called as `hash` or
+    // `stdlib::crypto::hash`
+    #[cfg(not(target_arch = "wasm32"))]
+    fn include_in_module(&mut self, path: &Utf8PathBuf, module: &[String]) -> Result<()> {
         // first check if it's a directory
         let metadata = fs::metadata(path)
             .map_err(|_| anyhow::anyhow!("Failed to stat metadata for include path: {:?}", path))?;
@@ -115,78 +568,254 @@ impl<T: PolynomialRingElement> Compiler<T> {
                 anyhow::bail!("Failed to parse file stem for include path: {:?}", path)
             }
             let name_str = name_str.unwrap().to_string();
-            if self.state.fn_to_path.contains_key(&name_str) {
-                // check if another file exists at the same path with a different
-                // extension
-                //
-                // if so prefer the higher index file
-
-                let existing_path = self
-                    .state
-                    .fn_to_path
-                    .get(&name_str)
-                    .unwrap()
-                    .canonicalize_utf8()?;
-                if existing_path.parent().is_none() {
-                    anyhow::bail!(
-                        "Failed to canonicalize path: {:?}",
-                        self.state.fn_to_path.get(&name_str).unwrap()
+            if !module.is_empty() {
+                let qualified = format!("{}::{name_str}", module.join("::"));
+                self.register_fn(qualified, path, ext, true)?;
+            }
+            self.register_fn(name_str, path, ext, false)?;
+        } else if metadata.is_dir() {
+            // `-` is not allowed in module paths, e.g. `test-vectors`
+            // becomes `test_vectors`
+            let dir_name = path
+                .canonicalize_utf8()?
+                .file_name()
+                .map(|v| v.replace('-', "_"));
+            let module = [module.to_vec(), dir_name.into_iter().collect()].concat();
+            let files = match fs::read_dir(path) {
+                Ok(files) => files,
+                Err(e) => {
+                    return log::error!(
+                        ConfigError: &format!("Failed to read directory: {path}"),
+                        &e.to_string()
                     )
                 }
-                if existing_path.parent() != path.canonicalize_utf8()?.parent() {
-                    return log::error!(&format!(
-                        "Duplicate file/function names detected: {name_str}
+            };
+            for entry in files {
+                let next_path = match entry.map(|v| Utf8PathBuf::from_path_buf(v.path())) {
+                    Ok(Ok(next_path)) => next_path,
+                    Ok(Err(next_path)) => {
+                        return log::error!(ConfigError: &format!(
+                            "include path is not valid utf-8: {}",
+                            next_path.display()
+                        ))
+                    }
+                    Err(e) => {
+                        return log::error!(
+                            ConfigError: &format!("Failed to read dir entry: {path}"),
+                            &e.to_string()
+                        )
+                    }
+                };
+                self.include_in_module(&next_path, &module)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Include functions from in-memory sources. Each key is a path
+    /// and each value is the source of the file, e.g.
+    /// `stdlib/crypto/hash.ash`. The directories in a path are
+    /// modules, the same as an include path on disk, so the file may
+    /// be called as `hash` or `stdlib::crypto::hash`.
+    pub fn include_sources(&mut self, sources: HashMap<String, String>) -> Result<()> {
+        let mut paths = vec![];
+        for (path, source) in sources {
+            let path = Utf8PathBuf::from(path);
+            self.state.sources.insert(path.clone(), source);
+            paths.push(path);
+        }
+        // register in a deterministic order
+        paths.sort();
+        for path in paths {
+            let (ext, name_str) = match (path.extension(), path.file_stem()) {
+                (Some(ext), Some(name_str)) => (ext.to_string(), name_str.to_string()),
+                _ => anyhow::bail!(
+                    "Failed to get file name and extension for source: {:?}",
+                    path
+                ),
+            };
+            if !self.extensions.contains(&ext) {
+                continue;
+            }
+            if ext == "r1cs" {
+                return log::error!(
+                    ConfigError: &format!("circom components cannot be included from memory: {path}"),
+                    "include the directory containing the .r1cs file as an include path"
+                );
+            }
+            // `-` is not allowed in module paths
+            let module = path
+                .parent()
+                .map(|v| {
+                    v.components()
+                        .filter_map(|c| match c {
+                            Utf8Component::Normal(v) => Some(v.replace('-', "_")),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            if !module.is_empty() {
+                let qualified = format!("{}::{name_str}", module.join("::"));
+                self.register_fn(qualified, &path, &ext, true)?;
+            }
+            self.register_fn(name_str, &path, &ext, false)?;
+        }
+        Ok(())
+    }
+
+    // the canonical path of a source file, in-memory sources
+    // do not exist on disk
+    fn resolve_path(&self, path: &Utf8PathBuf) -> Result<Utf8PathBuf> {
+        if self.state.sources.contains_key(path) {
+            return Ok(path.clone());
+        }
+        Ok(path.canonicalize_utf8()?)
+    }
+
+    // map a function name to a file. If a function is implemented in
+    // multiple files in the same directory the extension with the
+    // highest priority is preferred
+    //
+    // a file name used in multiple modules can only be called using
+    // the module path, a module path used multiple times is an error
+    fn register_fn(
+        &mut self,
+        name_str: String,
+        path: &Utf8PathBuf,
+        ext: &str,
+        qualified: bool,
+    ) -> Result<()> {
+        if name_str.starts_with(&format!("{}::", builtin::MODULE)) {
+            return log::error!(
+                ScopeError: &format!("module path is reserved: {name_str}"),
+                &format!("the {} module is built into the compiler, rename the directory at {path}", builtin::MODULE)
+            );
+        }
+        if let Some(paths) = self.state.ambiguous_fn.get_mut(&name_str) {
+            paths.push(path.clone());
+            return Ok(());
+        }
+        if self.state.fn_to_path.contains_key(&name_str) {
+            // check if another file exists at the same path with a different
+            // extension
+            //
+            // if so prefer the higher index file
+
+            let existing_path = self.resolve_path(self.state.fn_to_path.get(&name_str).unwrap())?;
+            if existing_path.parent().is_none() {
+                anyhow::bail!(
+                    "Failed to canonicalize path: {:?}",
+                    self.state.fn_to_path.get(&name_str).unwrap()
+                )
+            }
+            if existing_path.parent() != self.resolve_path(path)?.parent() {
+                if !qualified {
+                    let existing = self.state.fn_to_path.remove(&name_str).unwrap();
+                    self.state
+                        .ambiguous_fn
+                        .insert(name_str, vec![existing, path.clone()]);
+                    return Ok(());
+                }
+                return log::error!(ScopeError: &format!(
+                    "Duplicate file/function names detected: {name_str}
 Path 1: {:?}
 Path 2: {:?}",
-                        &path,
-                        self.state.fn_to_path.get(&name_str).unwrap()
-                    ));
-                }
-                let existing_extension = existing_path.extension().unwrap();
-                let existing_index = self
-                    .extensions
-                    .iter()
-                    .position(|x| *x == *existing_extension)
-                    .unwrap();
-                let current_index = self.extensions.iter().position(|x| *x == *ext).unwrap();
-                if current_index > existing_index {
-                    // we'll prefer the higher indexed impl
-                    self.state.fn_to_path.insert(name_str.clone(), path.clone());
-                    self.state.path_to_fn.insert(path.clone(), name_str);
-                }
-                return Ok(());
+                    &path,
+                    self.state.fn_to_path.get(&name_str).unwrap()
+                ));
             }
-            self.state.fn_to_path.insert(name_str.clone(), path.clone());
-            self.state.path_to_fn.insert(path.clone(), name_str);
-        } else if metadata.is_dir() {
-            let files = fs::read_dir(path)
-                .unwrap_or_else(|_| panic!("Failed to read directory: {:?}", &path));
-            for entry in files {
-                let next_path = entry
-                    .unwrap_or_else(|_| panic!("Failed to read dir entry: {:?}", &path))
-                    .path();
-                self.include(&Utf8PathBuf::from_path_buf(next_path).unwrap())?;
+            let existing_extension = existing_path.extension().unwrap();
+            let existing_index = self
+                .extensions
+                .iter()
+                .position(|x| *x == *existing_extension)
+                .unwrap();
+            let current_index = self.extensions.iter().position(|x| *x == *ext).unwrap();
+            if current_index > existing_index {
+                // we'll prefer the higher indexed impl
+                self.state.fn_to_path.insert(name_str.clone(), path.clone());
+                self.state.path_to_fn.insert(path.clone(), name_str);
             }
+            return Ok(());
         }
+        self.state.fn_to_path.insert(name_str.clone(), path.clone());
+        self.state.path_to_fn.insert(path.clone(), name_str);
         Ok(())
     }
 
     // loads, parses, and returns an ashlang function by name
     // returns the function as an ast
     pub fn parse_fn(&self, fn_name: &str) -> Result<(String, String)> {
-        if let Some(file_path) = self.state.fn_to_path.get(fn_name) {
-            if let Some(ext) = file_path.extension() {
-                let unparsed_file = std::fs::read_to_string(file_path)
-                    .unwrap_or_else(|_| panic!("Failed to read source file: {:?}", file_path));
-                Ok((unparsed_file, ext.to_string()))
-            } else {
-                panic!("unexpected: cannot get file extension");
+        read_fn_source(
+            fn_name,
+            self.state.fn_to_path.get(fn_name),
+            &self.state.sources,
+        )
+    }
+
+    /// The path of the file implementing a function, e.g.
+    /// `stdlib::crypto::hash`. Errors if no file implements the
+    /// function or the name is defined in multiple modules.
+    pub fn fn_path(&self, fn_name: &str) -> Result<&Utf8PathBuf> {
+        self.check_ambiguous(fn_name)?;
+        match self.state.fn_to_path.get(fn_name) {
+            Some(path) => Ok(path),
+            None => not_found(fn_name),
+        }
+    }
+
+    /// The names of ash functions starting with `prefix`, e.g.
+    /// `test_`. Each file is listed once, by its shortest name that
+    /// is not ambiguous.
+    pub fn fns_with_prefix(&self, prefix: &str) -> Vec<String> {
+        let mut by_path: HashMap<&Utf8PathBuf, &String> = HashMap::new();
+        for (name, path) in &self.state.fn_to_path {
+            let file_name = name.rsplit("::").next().unwrap_or(name);
+            if !file_name.starts_with(prefix) || path.extension() != Some("ash") {
+                continue;
             }
-        } else {
-            log::error!(
-                &format!("function is not present in sources: {fn_name}"),
-                &format!("unable to find a file {fn_name}.ash in your include paths after searching recursively\n\nmake sure you have specified an include path containing this file")
-            )
+            match by_path.get(path) {
+                Some(existing) if existing.len() <= name.len() => {}
+                _ => {
+                    by_path.insert(path, name);
+                }
+            }
+        }
+        let mut names = by_path.into_values().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    fn check_ambiguous(&self, fn_name: &str) -> Result<()> {
+        if let Some(paths) = self.state.ambiguous_fn.get(fn_name) {
+            return log::error!(
+                ScopeError: &format!("function name is ambiguous: {fn_name}"),
+                &format!(
+                    "{fn_name} is defined in multiple modules, call it using a module path e.g. `module::{fn_name}()`\n\n{}",
+                    paths
+                        .iter()
+                        .map(|v| v.to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                )
+            );
+        }
+        Ok(())
+    }
+
+    /// The source and AST of `constants.ash`, if it is in the
+    /// include paths or sources, see `constants`.
+    pub fn constants_source(&self) -> Result<Option<(String, AshParser)>> {
+        let name = constants::FN_NAME;
+        match self.state.fn_to_path.get(name) {
+            Some(path) if path.extension() == Some("ash") => {
+                self.check_ambiguous(name)?;
+                let (text, _) = self.parse_fn(name)?;
+                let parser = AshParser::parse_file(&text, name, path.as_str())?;
+                Ok(Some((text, parser)))
+            }
+            _ => Ok(None),
         }
     }
 
@@ -201,28 +830,392 @@ Path 2: {:?}",
     // repeat until all files have been parsed
     pub fn compile(&mut self, entry_fn_name: &str) -> Result<String> {
         let parsed = self.parse_fn(entry_fn_name)?;
-        let parser = AshParser::parse(&parsed.0, entry_fn_name)?;
+        let path = self.state.fn_to_path[entry_fn_name].to_string();
+        let parser = AshParser::parse_file(&parsed.0, entry_fn_name, &path)?;
         self.compile_parser(parser)
     }
 
-    fn compile_parser(&mut self, parser: AshParser) -> Result<String> {
+    /// Compile several entrypoints in one pass. Functions called
+    /// by more than one entrypoint are loaded and checked once, and
+    /// in the tasm target each specialization is compiled once.
+    pub fn compile_library(&mut self, entry_fns: &[String]) -> Result<Library> {
+        if entry_fns.is_empty() {
+            return log::error!(ConfigError: "a library must have at least one entrypoint");
+        }
+        let mut circuits: Vec<Circuit> = vec![];
+        for entry_fn in entry_fns {
+            if circuits.iter().any(|v| &v.entry_fn == entry_fn) {
+                return log::error!(ConfigError: &format!(
+                    "entrypoint \"{entry_fn}\" is included in the library more than once"
+                ));
+            }
+            let output = self.compile(entry_fn)?;
+            circuits.push(Circuit {
+                entry_fn: entry_fn.clone(),
+                file: format!("{entry_fn}.{}", Library::extension(&self.target)),
+                fingerprint: self.fingerprint.clone().unwrap_or_default(),
+                output,
+            });
+        }
+        Ok(Library::new(&self.target, T::name_str(), circuits))
+    }
+
+    /// Load a program without compiling it, returning an
+    /// interpreter running the entrypoint natively, see
+    /// `interpreter`.
+    pub fn interpreter(mut self, entry_fn_name: &str) -> Result<Interpreter<T>> {
+        let parsed = self.parse_fn(entry_fn_name)?;
+        let path = self.state.fn_to_path[entry_fn_name].to_string();
+        let mut parser = AshParser::parse_file(&parsed.0, entry_fn_name, &path)?;
+        self.load(&mut parser)?;
+        Interpreter::new(self.state, parser)
+    }
+
+    /// Values declared using `pub input` and `pub output` in the
+    /// most recently compiled entrypoint.
+    pub fn public_values(&self) -> &[PublicValue] {
+        &self.public_values
+    }
+
+    /// The cost of each source line in the compiled programs,
+    /// if the compiler was configured to profile.
+    pub fn profile(&self) -> Option<&Profile> {
+        self.state.profile.as_ref()
+    }
+
+    /// Record the static values assigned in later r1cs
+    /// compilations, see `static_trace`.
+    pub fn trace_statics(&mut self) {
+        self.state.static_trace = Some(vec![]);
+    }
+
+    /// The static values assigned while compiling, in the order
+    /// they are evaluated, if tracing is enabled.
+    pub fn static_trace(&self) -> &[StaticValue] {
+        self.state.static_trace.as_deref().unwrap_or_default()
+    }
+
+    /// A hash identifying the most recently compiled program. It
+    /// covers the ast of every ash function without source locations,
+    /// so formatting doesn't change it, the source of every other
+    /// function, the compiler version, the target, the field, and
+    /// the options that change the output. Recompiling a program
+    /// produces the same fingerprint, so a verifier can check which
+    /// program a proof belongs to.
+    pub fn fingerprint(&self) -> Option<&str> {
+        self.fingerprint.as_deref()
+    }
+
+    // the symbol table of a compiled r1cs program. Signals that
+    // were removed as dead are not listed
+    fn symbols(&self, constraints: &[R1csConstraint<T::F>]) -> Vec<Ar1csSymbol> {
+        let Some(symbols) = &self.state.symbols else {
+            return vec![];
+        };
+        let assigned = constraints
+            .iter()
+            .filter(|c| c.symbolic)
+            .filter_map(|c| c.out_i)
+            .collect::<HashSet<_>>();
+        symbols
+            .iter()
+            .filter_map(|(name, signals)| {
+                let mut signals = signals
+                    .iter()
+                    .copied()
+                    .filter(|v| assigned.contains(v))
+                    .collect::<Vec<_>>();
+                signals.sort();
+                signals.dedup();
+                (!signals.is_empty()).then(|| Ar1csSymbol {
+                    name: name.clone(),
+                    signals,
+                })
+            })
+            .collect()
+    }
+
+    // the functions an entrypoint may call, directly or through
+    // other functions, sorted by name
+    fn reachable_fns(&self, parser: &AshParser) -> Vec<String> {
+        let mut names = parser.fn_names.keys().cloned().collect::<Vec<_>>();
+        let mut i = 0;
+        while i < names.len() {
+            for dep in self.state.fn_deps.get(&names[i]).unwrap_or(&vec![]) {
+                if !names.contains(dep) {
+                    names.push(dep.clone());
+                }
+            }
+            i += 1;
+        }
+        names.sort();
+        names
+    }
+
+    // the ast of an entrypoint and each ash function it may call,
+    // without source locations
+    fn ast_artifact(&self, parser: &AshParser) -> String {
+        let mut out = format!(
+            "// {}\n{:#?}\n",
+            parser.entry_fn_name,
+            canonical_ast(&parser.ast)
+        );
+        for name in self.reachable_fns(parser) {
+            if self.state.is_fn_ash.get(&name) == Some(&true) {
+                let ast = canonical_ast(&self.state.fn_to_ast[&name]);
+                out.push_str(&format!("\n// {name}\n{ast:#?}\n"));
+            }
+        }
+        out
+    }
+
+    // write the artifact of a stage of the program being compiled
+    fn emit(
+        &self,
+        stage: Stage,
+        entry_fn: &str,
+        contents: impl FnOnce() -> Result<String>,
+    ) -> Result<()> {
+        match &self.emitter {
+            Some(emitter) => emitter.write(stage, entry_fn, contents),
+            None => Ok(()),
+        }
+    }
+
+    // hash the program reachable from an entrypoint
+    fn program_fingerprint(&self, parser: &AshParser) -> String {
+        let names = self.reachable_fns(parser);
+        let mut parts = vec![
+            "ashlang".to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+            self.target.clone(),
+            T::name_str().to_string(),
+            self.eliminate_dead_witness.to_string(),
+            self.state.checked_indexing.to_string(),
+            format!("{:?}", canonical_ast(&parser.ast)),
+        ];
+        for name in names {
+            let body = match self.state.is_fn_ash.get(&name) {
+                Some(true) => format!("{:?}", canonical_ast(&self.state.fn_to_ast[&name])),
+                _ => self
+                    .state
+                    .fn_source_hash
+                    .get(&name)
+                    .cloned()
+                    .unwrap_or_default(),
+            };
+            parts.push(name);
+            parts.push(body);
+        }
+        FnCache::key(&parts.iter().map(|v| v.as_str()).collect::<Vec<_>>())
+    }
+
+    /// The memory used by the most recently compiled program,
+    /// computed statically. Only available in the tasm target.
+    pub fn memory_report(&self) -> Option<&MemoryReport> {
+        self.memory_report.as_ref()
+    }
+
+    // collect the memory used by each compiled tasm function
+    fn build_memory_report(&self, entrypoint: FnMemory) -> MemoryReport {
+        let mut functions = self
+            .state
+            .compiled_fn
+            .keys()
+            .filter_map(|call| {
+                let mut memory = self.state.fn_memory.get(call)?.clone();
+                memory.calls = self.state.called_fn.get(call).copied().unwrap_or_default();
+                Some(memory)
+            })
+            .collect::<Vec<_>>();
+        functions.sort_by(|a, b| b.peak.cmp(&a.peak).then_with(|| a.name.cmp(&b.name)));
+        MemoryReport {
+            entrypoint,
+            functions,
+            reserved: self.state.reserved_memory,
+        }
+    }
+
+    // public values may only be declared at the top level
+    // of the entrypoint so they are read/written exactly once
+    fn check_public_declarations(ast: &[AstNode], allowed: bool) -> Result<()> {
+        let mut location = None;
+        for node in ast {
+            match node {
+                AstNode::Location(l) => location = Some(l.clone()),
+                AstNode::PubInput(name) | AstNode::PubOutput(name) if !allowed => {
+                    let e: Result<()> = log::error!(
+                        ScopeError: &format!("public value \"{name}\" declared outside of the entrypoint"),
+                        "pub input and pub output may only be used at the top level of the entrypoint function"
+                    );
+                    return e.map_err(|e| log::locate(e, &location));
+                }
+                AstNode::If(_, if_block, else_block) => {
+                    Self::check_public_declarations(if_block, false)?;
+                    Self::check_public_declarations(else_block, false)?;
+                }
+                AstNode::Loop(_, body)
+                | AstNode::While(_, body)
+                | AstNode::Unconstrained(_, body) => {
+                    Self::check_public_declarations(body, false)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn check_return_usage(&self, ast: &[AstNode]) -> Result<()> {
+        let mut location = None;
+        for node in ast {
+            if let AstNode::Location(l) = node {
+                location = Some(l.clone());
+                continue;
+            }
+            self.check_node_return_usage(node)
+                .map_err(|e| log::locate(e, &location))?;
+        }
+        Ok(())
+    }
+
+    fn check_node_return_usage(&self, node: &AstNode) -> Result<()> {
+        match node {
+            AstNode::ExprUnassigned(Expr::FnCall(_, args)) => {
+                // the return value is discarded
+                for arg in args {
+                    self.check_expr_return_usage(arg)?;
+                }
+            }
+            AstNode::Stmt(_, _, expr)
+            | AstNode::ExprUnassigned(expr)
+            | AstNode::Rtrn(expr)
+            | AstNode::Assert(expr, _)
+            | AstNode::StaticDef(_, expr)
+            | AstNode::TypedDef(_, _, expr) => self.check_expr_return_usage(expr)?,
+            AstNode::If(expr, if_block, else_block) => {
+                self.check_expr_return_usage(expr)?;
+                self.check_return_usage(if_block)?;
+                self.check_return_usage(else_block)?;
+            }
+            AstNode::Loop(expr, body) | AstNode::While(expr, body) => {
+                self.check_expr_return_usage(expr)?;
+                self.check_return_usage(body)?;
+            }
+            AstNode::Unconstrained(_, body) => self.check_return_usage(body)?,
+            AstNode::AssignVec(_, indices, expr) => {
+                for i in indices {
+                    self.check_expr_return_usage(i)?;
+                }
+                self.check_expr_return_usage(expr)?;
+            }
+            AstNode::Lookup(_, entries) => {
+                for expr in entries {
+                    self.check_expr_return_usage(expr)?;
+                }
+            }
+            AstNode::FnVar(_)
+            | AstNode::FnDims(_)
+            | AstNode::EmptyVecDef(_, _)
+            | AstNode::Ar1cs(_, _, _)
+            | AstNode::Asm(_, _, _)
+            | AstNode::PubInput(_)
+            | AstNode::PubOutput(_)
+            | AstNode::SecretInput(_)
+            | AstNode::Location(_)
+            | AstNode::Inline(_) => {}
+        }
+        Ok(())
+    }
+
+    fn check_expr_return_usage(&self, expr: &Expr) -> Result<()> {
+        match expr {
+            Expr::FnCall(name, args) => {
+                if let Some(ReturnType::Void) = self.state.fn_return_type.get(name) {
+                    return log::error!(
+                        TypeError: &format!("function \"{name}\" does not return a value"),
+                        "the function has no return statement, call it as a statement instead of using it in an expression"
+                    );
+                }
+                for arg in args {
+                    self.check_expr_return_usage(arg)?;
+                }
+            }
+            Expr::Val(_, indices) | Expr::Ref(_, indices) => {
+                for i in indices {
+                    self.check_expr_return_usage(i)?;
+                }
+            }
+            Expr::NumOp { lhs, rhs, .. }
+            | Expr::BoolOp { lhs, rhs, .. }
+            | Expr::BitOp { lhs, rhs, .. } => {
+                self.check_expr_return_usage(lhs)?;
+                self.check_expr_return_usage(rhs)?;
+            }
+            Expr::VecVec(_) | Expr::VecLit(_) | Expr::Lit(_) => {}
+        }
+        Ok(())
+    }
+
+    // load every function called by the entrypoint and the
+    // program constants, then check the program
+    fn load(&mut self, parser: &mut AshParser) -> Result<()> {
         // tracks total number of includes for a fn in all sources
         let mut included_fn: HashMap<String, u64> = parser.fn_names.clone();
+        // the functions called by program constants are loaded
+        // with the functions called by the entrypoint
+        let constants_source = self.constants_source()?;
+        if let Some((_, constants)) = &constants_source {
+            for (fn_name, count) in &constants.fn_names {
+                *included_fn.entry(fn_name.clone()).or_default() += count;
+            }
+        }
         // step 1: build ast for all functions
         // each function has a single ast, but multiple implementations
         // based on argument types it is called with
+        //
+        // function lowering depends on the order of calls, so only
+        // reading and parsing files is done in parallel
         loop {
-            if included_fn.len() == self.state.fn_to_ast.len() {
+            // functions that are called but not loaded, sorted so
+            // they are merged in a deterministic order
+            let mut pending = included_fn
+                .keys()
+                .filter(|v| !self.state.fn_to_ast.contains_key(*v) && !builtin::is_precompile(v))
+                .cloned()
+                .collect::<Vec<_>>();
+            if pending.is_empty() {
                 break;
             }
-            for (fn_name, _) in included_fn.clone() {
-                if self.state.fn_to_ast.contains_key(&fn_name) {
-                    continue;
-                }
-                let (text, ext) = self.parse_fn(&fn_name)?;
-                match ext.as_str() {
-                    "ash" => {
-                        let parser = AshParser::parse(&text, &fn_name)?;
+            pending.sort();
+            for fn_name in &pending {
+                self.check_ambiguous(fn_name)?;
+            }
+            let fn_to_path = &self.state.fn_to_path;
+            let sources = &self.state.sources;
+            let load =
+                |fn_name: &String| LoadedSource::load(fn_name, fn_to_path.get(fn_name), sources);
+            #[cfg(not(target_arch = "wasm32"))]
+            let loaded = pending.par_iter().map(load).collect::<Vec<_>>();
+            // threads are not available in wasm
+            #[cfg(target_arch = "wasm32")]
+            let loaded = pending.iter().map(load).collect::<Vec<_>>();
+            for (fn_name, source) in pending.into_iter().zip(loaded) {
+                match source? {
+                    LoadedSource::Circom(bytes) => {
+                        self.state
+                            .fn_source_hash
+                            .insert(fn_name.clone(), FnCache::hash(&bytes));
+                        let parser: R1csParser<T> = R1csParser::from_circom(&bytes)?;
+                        self.state.fn_to_ast.insert(fn_name.clone(), vec![]);
+                        self.state.fn_to_r1cs_parser.insert(fn_name, parser);
+                    }
+                    LoadedSource::Ash(text, parser) => {
+                        self.state
+                            .fn_source_hash
+                            .insert(fn_name.clone(), FnCache::hash(text.as_bytes()));
+                        self.state
+                            .fn_deps
+                            .insert(fn_name.clone(), parser.fn_names.keys().cloned().collect());
                         for (fn_name, count) in parser.fn_names {
                             if let Some(x) = included_fn.get_mut(&fn_name) {
                                 *x += count;
@@ -230,11 +1223,24 @@ Path 2: {:?}",
                                 included_fn.insert(fn_name, count);
                             }
                         }
+                        if let Some(depth) = parser.max_depth {
+                            self.state.fn_max_depth.insert(fn_name.clone(), depth);
+                        }
+                        if let Some(inline) = parser.inline {
+                            self.state.fn_inline.insert(fn_name.clone(), inline);
+                        }
                         self.state.is_fn_ash.insert(fn_name.clone(), true);
+                        let return_type = ReturnType::from_ast(&parser.ast)
+                            .with_shape(&fn_name, parser.return_shape)?;
+                        self.state
+                            .fn_return_type
+                            .insert(fn_name.clone(), return_type);
                         self.state.fn_to_ast.insert(fn_name, parser.ast);
                     }
-                    "tasm" => {
-                        let parser = AsmParser::parse(&text, &fn_name)?;
+                    LoadedSource::Tasm(text, parser) => {
+                        self.state
+                            .fn_source_hash
+                            .insert(fn_name.clone(), FnCache::hash(text.as_bytes()));
                         self.state.fn_to_ast.insert(fn_name.clone(), vec![]);
                         let mut call_no_return = parser.call_type.clone();
                         call_no_return.return_type = None;
@@ -245,27 +1251,99 @@ Path 2: {:?}",
                             .compiled_fn
                             .insert(call_no_return, parser.asm.clone());
                     }
-                    "ar1cs" => {
+                    LoadedSource::Ar1cs(text) => {
+                        self.state
+                            .fn_source_hash
+                            .insert(fn_name.clone(), FnCache::hash(text.as_bytes()));
                         let parser: R1csParser<T> = R1csParser::new(&text)?;
                         self.state.fn_to_ast.insert(fn_name.clone(), vec![]);
-                        self.state.fn_to_r1cs_parser.insert(fn_name.clone(), parser);
-                    }
-                    _ => {
-                        return log::error!(&format!("unexpected file extension: {ext}"));
+                        self.state.fn_to_r1cs_parser.insert(fn_name, parser);
                     }
                 }
             }
         }
+        // constants are evaluated once and declared in each
+        // function reading them
+        if let Some((text, constants)) = constants_source {
+            let values =
+                constants::evaluate(&self.state, &constants::declarations(&constants.ast)?)?;
+            constants::declare(&mut parser.ast, &values);
+            for (fn_name, ast) in self.state.fn_to_ast.iter_mut() {
+                if self.state.is_fn_ash.get(fn_name) != Some(&true)
+                    || !constants::declare(ast, &values)
+                {
+                    continue;
+                }
+                // cached compilations depend on the constants
+                self.state
+                    .fn_deps
+                    .entry(fn_name.clone())
+                    .or_default()
+                    .push(constants::FN_NAME.to_string());
+            }
+            self.state.fn_source_hash.insert(
+                constants::FN_NAME.to_string(),
+                FnCache::hash(text.as_bytes()),
+            );
+        }
+        // make sure functions without a return statement
+        // are not used as values
+        self.check_return_usage(&parser.ast)?;
+        for ast in self.state.fn_to_ast.values() {
+            self.check_return_usage(ast)?;
+        }
+        Self::check_public_declarations(&parser.ast, true)?;
+        for ast in self.state.fn_to_ast.values() {
+            Self::check_public_declarations(ast, false)?;
+        }
+        // report type errors in every function before compiling,
+        // sorted so errors are reported in a deterministic order
+        let mut fn_names = self.state.fn_to_ast.keys().collect::<Vec<_>>();
+        fn_names.sort();
+        let asts = [
+            vec![parser.ast.as_slice()],
+            fn_names
+                .iter()
+                .map(|v| self.state.fn_to_ast[*v].as_slice())
+                .collect(),
+        ]
+        .concat();
+        typecheck::check(&asts)?;
+        self.public_values = PublicValue::from_ast(&parser.ast);
+        self.fingerprint = Some(self.program_fingerprint(parser));
+        Ok(())
+    }
+
+    fn compile_parser(&mut self, mut parser: AshParser) -> Result<String> {
+        self.load(&mut parser)?;
+        let entry_fn = parser.entry_fn_name.clone();
+        self.emit(Stage::Ast, &entry_fn, || Ok(self.ast_artifact(&parser)))?;
+        // evaluate repeated expressions once in each function
+        parser.ast = cse::eliminate(std::mem::take(&mut parser.ast));
+        for (fn_name, ast) in self.state.fn_to_ast.iter_mut() {
+            if self.eliminated.insert(fn_name.clone()) {
+                *ast = cse::eliminate(std::mem::take(ast));
+            }
+        }
+        self.emit(Stage::Ir, &entry_fn, || Ok(self.ast_artifact(&parser)))?;
+        // the transcript of challenges belongs to a single program
+        self.state.transcript = None;
+        if let Some(symbols) = &mut self.state.symbols {
+            symbols.clear();
+        }
         match self.target.as_str() {
             "r1cs" => {
                 use crate::r1cs::vm::VM;
                 let mut vm: VM<T> = VM::new(&mut self.state);
                 // build constraints from the AST
                 vm.eval_ast(parser.ast)?;
+                let mut constraints = crate::r1cs::optimizer::optimize(vm.constraints)?;
+                if self.eliminate_dead_witness {
+                    constraints = crate::r1cs::optimizer::eliminate_dead(constraints);
+                }
                 let mut final_constraints: Vec<R1csConstraint<T::F>> = Vec::new();
                 final_constraints.append(
-                    &mut vm
-                        .constraints
+                    &mut constraints
                         .iter()
                         .filter(|v| v.symbolic)
                         .cloned()
@@ -273,28 +1351,41 @@ Path 2: {:?}",
                         .to_vec(),
                 );
                 final_constraints.append(
-                    &mut vm
-                        .constraints
+                    &mut constraints
                         .iter()
                         .filter(|v| !v.symbolic)
                         .cloned()
                         .collect::<Vec<R1csConstraint<T::F>>>()
                         .to_vec(),
                 );
-                let ar1cs_src = [
-                    vec![
-                        format!("# {}", parser.entry_fn_name),
-                        format!("# Compiled at {}", crate::time::now()),
-                        format!("# Compiled for {}", T::name_str()),
-                        format!("#"),
-                    ],
-                    final_constraints
+                // find the signals holding public values
+                for public in self.public_values.iter_mut() {
+                    let comment = Some(public.declaration());
+                    public.signal = final_constraints
                         .iter()
-                        .map(|v| v.to_string())
-                        .collect::<Vec<String>>(),
-                ]
-                .concat()
-                .join("\n");
+                        .find(|c| c.symbolic && c.comment == comment)
+                        .and_then(|c| c.out_i);
+                }
+                let symbols = self.symbols(&final_constraints);
+                let ar1cs = Ar1cs {
+                    symbols,
+                    public: self
+                        .public_values
+                        .iter()
+                        .map(|v| Ar1csPublic {
+                            declaration: v.declaration(),
+                            signal: v.signal.unwrap_or_default(),
+                        })
+                        .collect(),
+                    constraints: final_constraints,
+                    ..Ar1cs::new(&parser.entry_fn_name, T::name_str())
+                };
+                let ar1cs_src = ar1cs.to_string();
+                self.emit(Stage::Ar1cs, &entry_fn, || Ok(ar1cs_src.clone()))?;
+                #[cfg(feature = "serde")]
+                self.emit(Stage::R1csJson, &entry_fn, || {
+                    crate::emit::r1cs_json(&ar1cs)
+                })?;
                 if self.print_asm {
                     // prints the raw constraints
                     println!("{ar1cs_src}");
@@ -304,17 +1395,37 @@ Path 2: {:?}",
             "tasm" => {
                 use crate::tasm::vm::VM;
                 // step 1: compile the entrypoint to assembly
-                let mut vm: VM<T> = VM::new(&mut self.state);
+                let mut vm: VM<T> = VM::new_entrypoint(&mut self.state);
                 vm.eval_ast(parser.ast, vec![], None)?;
                 let mut asm = vm.asm.clone();
                 asm.push("halt".to_string());
+                let entrypoint = FnMemory {
+                    name: "entrypoint".to_string(),
+                    calls: 1,
+                    frame: vm.memory_index,
+                    peak: vm.memory_used(),
+                };
+                self.memory_report = Some(self.build_memory_report(entrypoint));
 
-                // step 2: add functions to file
-                for (fn_call, fn_asm) in &self.state.compiled_fn {
+                // step 2: add functions to file, sorted by label so
+                // the output is the same in every compilation
+                let mut compiled_fn = self
+                    .state
+                    .compiled_fn
+                    .iter()
+                    .map(|(fn_call, fn_asm)| (fn_call.typed_name(), fn_call, fn_asm))
+                    .collect::<Vec<_>>();
+                compiled_fn.sort_by(|a, b| a.0.cmp(&b.0));
+                // functions inlined at every call site are not needed
+                let called = called_labels(
+                    asm.iter().chain(self.state.block_fn_asm.iter().flatten()),
+                    &compiled_fn,
+                );
+                compiled_fn.retain(|(label, _, _)| called.contains(label));
+                for (label, fn_call, fn_asm) in compiled_fn {
                     asm.push("\n".to_string());
                     asm.push(format!(
-                        "{}: // {}",
-                        fn_call.typed_name(),
+                        "{label}: // {}",
                         self.state.fn_to_path.get(&fn_call.name).unwrap()
                     ));
                     asm.append(&mut fn_asm.clone());
@@ -328,22 +1439,18 @@ Path 2: {:?}",
                 }
                 asm.push("\n".to_string());
 
-                // trivial optimizations
-                let mut final_asm = vec![];
-                final_asm.push(asm[0].clone());
-                for x in 1..asm.len() {
-                    let last = &asm[x - 1];
-                    let curr = &asm[x];
-                    if last == "push 0" && curr == "add" {
-                        final_asm.pop();
-                        continue;
-                    }
-                    if last == "push 1" && curr == "mul" {
-                        final_asm.pop();
-                        continue;
-                    }
-                    final_asm.push(asm[x].clone());
-                }
+                // remove redundant instructions
+                let final_asm = peephole::optimize(&asm);
+
+                // public value metadata
+                let final_asm = [
+                    self.public_values
+                        .iter()
+                        .map(|v| format!("// {}: {}", v.declaration(), v.position))
+                        .collect::<Vec<String>>(),
+                    final_asm,
+                ]
+                .concat();
 
                 if self.print_asm {
                     // prints the assembly
@@ -351,10 +1458,15 @@ Path 2: {:?}",
                         println!("{}", l);
                     }
                 }
-                Ok(final_asm.clone().join("\n"))
+                let asm = final_asm.join("\n");
+                if let Some(profile) = &mut self.state.profile {
+                    profile.count_instructions(&asm);
+                }
+                self.emit(Stage::Tasm, &entry_fn, || Ok(asm.clone()))?;
+                Ok(asm)
             }
             _ => {
-                log::error!(&format!("unexpected target: {}", self.target))
+                log::error!(ConfigError: &format!("unexpected target: {}", self.target))
             }
         }
     }
@@ -0,0 +1,217 @@
+//! Program constants. Statics declared in a file named
+//! `constants.ash` in the include paths are evaluated once for
+//! the program and are visible in every function, e.g. round
+//! constants and parameters shared by many functions.
+//!
+//! Each constant is evaluated before any function is compiled,
+//! then declared as a static literal at the top of each function
+//! reading it. A function declaring a variable with the same name
+//! uses its own variable.
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use anyhow::Result;
+use ring_math::Matrix;
+use ring_math::PolynomialRingElement;
+use scalarff::FieldElement;
+
+use crate::compiler::CompilerState;
+use crate::consteval::ConstEval;
+use crate::log;
+use crate::parser::AstNode;
+use crate::parser::Expr;
+use crate::parser::SourceLocation;
+
+/// The name of the function declaring the program constants.
+pub const FN_NAME: &str = "constants";
+
+/// A static declared in the constants file.
+#[derive(Debug, Clone)]
+pub struct Constant {
+    pub name: String,
+    pub expr: Expr,
+    location: Option<SourceLocation>,
+}
+
+/// The constants declared in the AST of the constants file.
+pub fn declarations(ast: &[AstNode]) -> Result<Vec<Constant>> {
+    let mut out: Vec<Constant> = vec![];
+    let mut location = None;
+    for node in ast {
+        match node {
+            AstNode::Location(v) => location = Some(v.clone()),
+            AstNode::StaticDef(name, expr) => {
+                if out.iter().any(|v| &v.name == name) {
+                    return log::error!(ScopeError: &format!("constant \"{name}\" is already defined"));
+                }
+                out.push(Constant {
+                    name: name.clone(),
+                    expr: expr.clone(),
+                    location: location.clone(),
+                });
+            }
+            _ => {
+                let e = log::error!(
+                    ParseError: &format!("{FN_NAME}.ash may only declare statics"),
+                    "declare each constant using `static`, e.g. `static rounds = 8`"
+                );
+                return e.map_err(|e| log::locate(e, &location));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Evaluate the constants in order, each constant may read the
+/// constants before it. Returns the value of each constant as a
+/// literal expression.
+pub fn evaluate<T: PolynomialRingElement>(
+    compiler_state: &CompilerState<T>,
+    constants: &[Constant],
+) -> Result<Vec<(String, Expr)>> {
+    let mut vars = HashMap::new();
+    let mut out = vec![];
+    for v in constants {
+        let value = ConstEval::new(compiler_state, vars.clone(), HashMap::new())
+            .eval(&v.expr)
+            .map_err(|e| log::locate(e, &v.location))?;
+        out.push((v.name.clone(), literal(&value)?));
+        vars.insert(v.name.clone(), Some(value));
+    }
+    Ok(out)
+}
+
+/// Declare the constants read by a function at the top of its
+/// AST, and the constants they read. Returns true if any
+/// constant was declared.
+pub fn declare(ast: &mut Vec<AstNode>, constants: &[(String, Expr)]) -> bool {
+    let mut reads = HashSet::new();
+    let mut declared = HashSet::new();
+    names(ast, &mut reads, &mut declared);
+    // a constant only reads the constants before it
+    for (name, expr) in constants.iter().rev() {
+        if reads.contains(name) {
+            expr_names(expr, &mut reads);
+        }
+    }
+    let statics = constants
+        .iter()
+        .filter(|(name, _)| reads.contains(name) && !declared.contains(name))
+        .map(|(name, expr)| AstNode::StaticDef(name.clone(), expr.clone()))
+        .collect::<Vec<_>>();
+    if statics.is_empty() {
+        return false;
+    }
+    // after the arguments so the header is the first statement
+    let start = ast
+        .iter()
+        .rposition(|v| matches!(v, AstNode::FnVar(_) | AstNode::FnDims(_)))
+        .map(|v| v + 1)
+        .unwrap_or_default();
+    ast.splice(start..start, statics);
+    true
+}
+
+// a literal expression with the value of a constant
+fn literal<T: PolynomialRingElement>(v: &Matrix<T>) -> Result<Expr> {
+    let values = v
+        .values
+        .iter()
+        .map(|v| Ok(v.to_scalar()?.to_biguint().to_string()))
+        .collect::<Result<Vec<_>>>()?;
+    if v.dimensions == [1] {
+        return Ok(Expr::Lit(values[0].clone()));
+    }
+    fn build(dimensions: &[usize], values: &[String]) -> Expr {
+        if dimensions.len() == 1 {
+            return Expr::VecLit(values.to_vec());
+        }
+        let len = values.len() / dimensions[0];
+        Expr::VecVec(
+            values
+                .chunks(len)
+                .map(|v| build(&dimensions[1..], v))
+                .collect(),
+        )
+    }
+    Ok(build(&v.dimensions, &values))
+}
+
+// the variables read and declared in an AST, including in
+// nested blocks
+fn names(ast: &[AstNode], reads: &mut HashSet<String>, declared: &mut HashSet<String>) {
+    for node in ast {
+        match node {
+            AstNode::FnVar(names) => declared.extend(names.iter().cloned()),
+            AstNode::FnDims(params) => {
+                declared.extend(params.iter().flat_map(|(_, v)| v.iter().cloned()))
+            }
+            AstNode::Stmt(name, is_let, expr) => {
+                if *is_let {
+                    declared.insert(name.clone());
+                } else {
+                    reads.insert(name.clone());
+                }
+                expr_names(expr, reads);
+            }
+            AstNode::StaticDef(name, expr) | AstNode::TypedDef(name, _, expr) => {
+                declared.insert(name.clone());
+                expr_names(expr, reads);
+            }
+            AstNode::EmptyVecDef(name, _)
+            | AstNode::PubInput(name)
+            | AstNode::SecretInput(name) => {
+                declared.insert(name.clone());
+            }
+            AstNode::PubOutput(name) => {
+                reads.insert(name.clone());
+            }
+            AstNode::ExprUnassigned(expr) | AstNode::Rtrn(expr) | AstNode::Assert(expr, _) => {
+                expr_names(expr, reads)
+            }
+            AstNode::If(expr, if_block, else_block) => {
+                expr_names(expr, reads);
+                names(if_block, reads, declared);
+                names(else_block, reads, declared);
+            }
+            AstNode::Loop(expr, body) | AstNode::While(expr, body) => {
+                expr_names(expr, reads);
+                names(body, reads, declared);
+            }
+            AstNode::AssignVec(name, indices, expr) => {
+                reads.insert(name.clone());
+                indices.iter().for_each(|v| expr_names(v, reads));
+                expr_names(expr, reads);
+            }
+            AstNode::Lookup(_, values) => values.iter().for_each(|v| expr_names(v, reads)),
+            AstNode::Ar1cs(args, returns, _) | AstNode::Asm(args, returns, _) => {
+                reads.extend(args.iter().cloned());
+                declared.extend(returns.iter().cloned());
+            }
+            AstNode::Unconstrained(returns, body) => {
+                declared.extend(returns.iter().cloned());
+                names(body, reads, declared);
+            }
+            AstNode::Location(_) | AstNode::Inline(_) => {}
+        }
+    }
+}
+
+fn expr_names(expr: &Expr, out: &mut HashSet<String>) {
+    match expr {
+        Expr::Val(name, indices) | Expr::Ref(name, indices) => {
+            out.insert(name.clone());
+            indices.iter().for_each(|v| expr_names(v, out));
+        }
+        Expr::FnCall(_, args) | Expr::VecVec(args) => {
+            args.iter().for_each(|v| expr_names(v, out));
+        }
+        Expr::NumOp { lhs, rhs, .. }
+        | Expr::BoolOp { lhs, rhs, .. }
+        | Expr::BitOp { lhs, rhs, .. } => {
+            expr_names(lhs, out);
+            expr_names(rhs, out);
+        }
+        Expr::VecLit(_) | Expr::Lit(_) => {}
+    }
+}
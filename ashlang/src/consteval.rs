@@ -0,0 +1,791 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use ring_math::Matrix;
+use ring_math::PolynomialRingElement;
+use scalarff::BigUint;
+use scalarff::FieldElement;
+
+use crate::builtin;
+use crate::compiler::CompilerState;
+use crate::layout;
+use crate::log;
+use crate::parser::AstNode;
+use crate::parser::BitOp;
+use crate::parser::BoolOp;
+use crate::parser::Expr;
+use crate::parser::IntType;
+use crate::parser::NumOp;
+use crate::parser::SourceLocation;
+use crate::parser::VarType;
+use crate::r1cs::lookup::LookupTable;
+
+// limits that stop a runaway evaluation from hanging the compiler
+const MAX_DEPTH: usize = 256;
+const MAX_ITERATIONS: usize = 1 << 20;
+
+/// The inputs read and the outputs written by a program run by
+/// the interpreter, see `interpreter`.
+pub struct Io<T> {
+    pub public_inputs: VecDeque<T>,
+    pub secret_inputs: VecDeque<T>,
+    pub outputs: Vec<T>,
+}
+
+/// Evaluates static expressions at compile time. Ash functions
+/// called from a static expression are interpreted, so loops,
+/// conditionals, and function calls may be used to build
+/// lookup tables and constants in-language.
+///
+/// Evaluation is target independent. Each variable is a
+/// matrix of values, scalars have dimensions `[1]`.
+pub struct ConstEval<'a, T: PolynomialRingElement> {
+    compiler_state: &'a CompilerState<T>,
+    // None if the variable exists but is not static
+    vars: HashMap<String, Option<Matrix<T>>>,
+    // variables declared with an integer type
+    int_types: HashMap<String, IntType>,
+    return_val: Option<Matrix<T>>,
+    // number of nested function calls
+    depth: usize,
+    location: Option<SourceLocation>,
+    // program inputs and outputs, None in a static evaluation
+    io: Option<Io<T>>,
+}
+
+impl<'a, T: PolynomialRingElement> ConstEval<'a, T> {
+    pub fn new(
+        compiler_state: &'a CompilerState<T>,
+        vars: HashMap<String, Option<Matrix<T>>>,
+        int_types: HashMap<String, IntType>,
+    ) -> Self {
+        Self {
+            compiler_state,
+            vars,
+            int_types,
+            return_val: None,
+            depth: 0,
+            location: None,
+            io: None,
+        }
+    }
+
+    /// Run the statements of an entrypoint, reading inputs from
+    /// and writing outputs to `io`.
+    pub fn run(compiler_state: &'a CompilerState<T>, ast: &[AstNode], io: Io<T>) -> Result<Io<T>> {
+        let mut eval = Self::new(compiler_state, HashMap::new(), HashMap::new());
+        eval.io = Some(io);
+        eval.eval_ast(ast)?;
+        Ok(eval.io.unwrap())
+    }
+
+    /// Evaluate an expression to a static value.
+    pub fn eval(&mut self, expr: &Expr) -> Result<Matrix<T>> {
+        match expr {
+            Expr::Lit(v) => Ok(Matrix::from(T::from_str(v)?)),
+            Expr::VecVec(_) | Expr::VecLit(_) => Self::eval_vec(expr),
+            Expr::Val(name, indices) => {
+                let v = match self.vars.get(name) {
+                    Some(Some(v)) => v.clone(),
+                    Some(None) => {
+                        return log::error!(
+                            TypeError: &format!("variable \"{name}\" is not static"),
+                            "statics may only be computed from literals, other statics, and ash functions"
+                        )
+                    }
+                    None => return log::error!(ScopeError: &format!("unknown variable \"{name}\"")),
+                };
+                if indices.is_empty() {
+                    return Ok(v);
+                }
+                let indices = self.eval_indices(name, &v, indices)?;
+                Ok(v.retrieve_indices(&indices).0)
+            }
+            Expr::Ref(_, _) => {
+                log::error!(TypeError: "references cannot be evaluated at compile time")
+            }
+            Expr::NumOp { lhs, op, rhs } => {
+                let lv = self.eval(lhs)?;
+                let rv = self.eval(rhs)?;
+                if lv.dimensions != rv.dimensions {
+                    return log::error!(
+                        TypeError: &format!(
+                            "static operation on values of different dimensions: {:?} and {:?}",
+                            lv.dimensions, rv.dimensions
+                        )
+                    );
+                }
+                let out = match op {
+                    NumOp::Add => lv + rv,
+                    NumOp::Sub => lv - rv,
+                    NumOp::Mul => lv * rv,
+                    NumOp::Inv => {
+                        if rv.values.iter().any(|v| *v == T::zero()) {
+                            return log::error!("division by zero in static expression");
+                        }
+                        lv / rv
+                    }
+                };
+                if let Some(int_type) = self.int_type(expr)? {
+                    Self::assert_int(&out, int_type)?;
+                }
+                Ok(out)
+            }
+            Expr::BoolOp { lhs, bool_op, rhs } => {
+                let l = self.eval_scalar(lhs)?;
+                let out = match bool_op {
+                    // the right side is not evaluated if the left
+                    // side determines the result
+                    BoolOp::And => {
+                        l != BigUint::from(0_u32) && self.eval_scalar(rhs)? != BigUint::from(0_u32)
+                    }
+                    BoolOp::Or => {
+                        l != BigUint::from(0_u32) || self.eval_scalar(rhs)? != BigUint::from(0_u32)
+                    }
+                    BoolOp::Equal => l == self.eval_scalar(rhs)?,
+                    BoolOp::NotEqual => l != self.eval_scalar(rhs)?,
+                    BoolOp::GreaterThan => l > self.eval_scalar(rhs)?,
+                    BoolOp::LessThan => l < self.eval_scalar(rhs)?,
+                    BoolOp::GreaterThanOrEqual => l >= self.eval_scalar(rhs)?,
+                    BoolOp::LessThanOrEqual => l <= self.eval_scalar(rhs)?,
+                };
+                Ok(Matrix::from(if out { T::one() } else { T::zero() }))
+            }
+            Expr::BitOp { lhs, bit_op, rhs } => {
+                let int_type = match self.int_type(expr)? {
+                    Some(int_type) => int_type,
+                    None => {
+                        return log::error!(
+                            TypeError: "bitwise operators require integer operands",
+                            "declare an operand with an integer type e.g. `let x: u32 = 1`"
+                        )
+                    }
+                };
+                self.eval_bitop(lhs, bit_op, rhs, int_type)
+            }
+            Expr::FnCall(name, args) if name == builtin::NEG && args.len() == 1 => {
+                let v = self.eval(&args[0])?;
+                Ok(Matrix {
+                    dimensions: v.dimensions,
+                    values: v.values.into_iter().map(|v| -v).collect(),
+                })
+            }
+            Expr::FnCall(name, args) if name == builtin::SLICE && args.len() == 3 => {
+                let v = self.eval(&args[0])?;
+                let name = match &args[0] {
+                    Expr::Val(name, _) => name.as_str(),
+                    _ => "value",
+                };
+                let start = self.eval_scalar(&args[1])?.to_string().parse::<usize>()?;
+                let end = self.eval_scalar(&args[2])?.to_string().parse::<usize>()?;
+                let (offset, dimensions) = layout::slice(name, &v.dimensions, start, end)?;
+                let len = layout::len(&dimensions);
+                Ok(Matrix {
+                    dimensions,
+                    values: v.values[offset..offset + len].to_vec(),
+                })
+            }
+            Expr::FnCall(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|v| self.eval(v))
+                    .collect::<Result<Vec<_>>>()?;
+                match self.call(name, args)? {
+                    Some(v) => Ok(v),
+                    None => log::error!(
+                        TypeError: &format!("function \"{name}\" does not return a value")
+                    ),
+                }
+            }
+        }
+    }
+
+    // evaluate a scalar as an integer
+    fn eval_scalar(&mut self, expr: &Expr) -> Result<BigUint> {
+        let v = self.eval(expr)?;
+        if v.len() != 1 {
+            return log::error!(
+                TypeError: "expected a scalar in static expression, received a vector/matrix"
+            );
+        }
+        Ok(v.values[0].to_scalar()?.to_biguint())
+    }
+
+    fn eval_indices(&mut self, name: &str, v: &Matrix<T>, indices: &[Expr]) -> Result<Vec<usize>> {
+        if indices.len() > v.dimensions.len() {
+            return log::error!(
+                TypeError: &format!("too many indices for variable \"{name}\"")
+            );
+        }
+        let mut out = vec![];
+        for (i, index) in indices.iter().enumerate() {
+            let index = self.eval_scalar(index)?;
+            if index >= BigUint::from(v.dimensions[i]) {
+                return log::error!(
+                    MemoryError: &format!("index {index} is out of bounds for variable \"{name}\""),
+                    &format!("dimension {i} has length {}", v.dimensions[i])
+                );
+            }
+            out.push(index.to_string().parse::<usize>()?);
+        }
+        Ok(out)
+    }
+
+    fn eval_vec(expr: &Expr) -> Result<Matrix<T>> {
+        match expr {
+            Expr::VecLit(v) => Ok(Matrix {
+                dimensions: vec![v.len()],
+                values: v.iter().map(|v| T::from_str(v)).collect::<Result<_>>()?,
+            }),
+            Expr::VecVec(v) => {
+                let rows = v.iter().map(Self::eval_vec).collect::<Result<Vec<_>>>()?;
+                if rows.iter().any(|r| r.dimensions != rows[0].dimensions) {
+                    return log::error!(TypeError: "vector rows must have the same dimensions");
+                }
+                let mut dimensions = vec![rows.len()];
+                dimensions.append(&mut rows[0].dimensions.clone());
+                Ok(Matrix {
+                    dimensions,
+                    values: rows.into_iter().flat_map(|r| r.values).collect(),
+                })
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn eval_bitop(
+        &mut self,
+        lhs: &Expr,
+        bit_op: &BitOp,
+        rhs: &Expr,
+        int_type: IntType,
+    ) -> Result<Matrix<T>> {
+        let name = int_type.name();
+        let bits = int_type.bits();
+        let lv = self.eval(lhs)?;
+        Self::assert_int(&lv, int_type)?;
+        let l = lv.values[0].to_scalar()?.to_biguint();
+        let r = self.eval_scalar(rhs)?;
+        let mask = (BigUint::from(1_u32) << bits) - 1_u32;
+        let out = match bit_op {
+            BitOp::Shl | BitOp::Shr => {
+                if r >= BigUint::from(bits as u32) {
+                    return log::error!(
+                        &format!("{name} shift amount {r} is out of range"),
+                        &format!("the shift amount must be less than {bits}")
+                    );
+                }
+                let n = r.to_string().parse::<usize>()?;
+                if matches!(bit_op, BitOp::Shl) {
+                    (l << n) & mask
+                } else {
+                    l >> n
+                }
+            }
+            _ => {
+                if r > mask {
+                    return log::error!(
+                        &format!("{name} overflow: {r} is out of range"),
+                        &format!("the result of a {name} operation must be less than 2^{bits}")
+                    );
+                }
+                match bit_op {
+                    BitOp::And => l & r,
+                    BitOp::Or => l | r,
+                    _ => l ^ r,
+                }
+            }
+        };
+        Ok(Matrix::from(T::from_str(&out.to_string())?))
+    }
+
+    // the integer type of an expression in the current scope
+    fn int_type(&self, expr: &Expr) -> Result<Option<IntType>> {
+        expr.int_type(&|name| self.int_types.get(name).copied())
+    }
+
+    fn assert_int(v: &Matrix<T>, int_type: IntType) -> Result<()> {
+        let name = int_type.name();
+        let bits = int_type.bits();
+        // the product of two integers must not overflow the field
+        let max = (BigUint::from(1_u32) << bits) - 1_u32;
+        if max.clone() * max >= T::F::prime() {
+            return log::error!(
+                ConfigError: &format!("{name} is not supported in the {} field", T::name_str()),
+                &format!("{name} requires a field of more than {} bits", 2 * bits)
+            );
+        }
+        if v.len() != 1 {
+            return log::error!(TypeError: &format!("{name} variables must be scalars"));
+        }
+        let value = v.values[0].to_scalar()?.to_biguint();
+        if value.bits() > bits as u64 {
+            return log::error!(
+                &format!("{name} overflow: {value} is out of range"),
+                &format!("the result of a {name} operation must be less than 2^{bits}")
+            );
+        }
+        Ok(())
+    }
+
+    // call a function with static arguments, returns None if the
+    // function does not return a value
+    fn call(&mut self, name: &str, args: Vec<Matrix<T>>) -> Result<Option<Matrix<T>>> {
+        match name {
+            "assert_eq" => {
+                if args.len() != 2 {
+                    return log::error!(TypeError: &format!(
+                        "assert_eq expects 2 arguments, received {}",
+                        args.len()
+                    ));
+                }
+                if args[0] != args[1] {
+                    return log::error!(&format!(
+                        "{}assertion failed: {:?} != {:?}",
+                        self.static_prefix(),
+                        args[0].values,
+                        args[1].values
+                    ));
+                }
+                return Ok(None);
+            }
+            "crash" if self.io.is_some() => return log::error!("crash called"),
+            // the stdlib io functions read and write the program io
+            "read_public_input" | "read_secret_input" if self.io.is_some() => {
+                return self.read_input(name == "read_public_input").map(Some)
+            }
+            "write_output" if self.io.is_some() => {
+                if args.len() != 1 {
+                    return log::error!(TypeError: &format!(
+                        "write_output expects 1 argument, received {}",
+                        args.len()
+                    ));
+                }
+                self.write_output("the argument of write_output", &args[0])?;
+                return Ok(None);
+            }
+            "crash" => return log::error!("crash called in static evaluation"),
+            _ => {}
+        }
+        let ast = match (
+            self.compiler_state.is_fn_ash.get(name),
+            self.compiler_state.fn_to_ast.get(name),
+        ) {
+            (Some(true), Some(ast)) => ast,
+            _ if name == builtin::POW => return Self::pow(&args).map(Some),
+            _ if builtin::REDUCTIONS.contains(&name) => return Self::reduce(name, &args).map(Some),
+            _ if name == builtin::DOT || name == builtin::MATMUL => {
+                return Self::product(name, &args).map(Some)
+            }
+            _ => {
+                return log::error!(
+                    TypeError: &format!("function \"{name}\" cannot be evaluated at compile time"),
+                    "only ash functions, assert_eq, and crash may be called from a static expression"
+                )
+            }
+        };
+        if self.depth >= MAX_DEPTH {
+            return log::error!(
+                &format!("static evaluation exceeded {MAX_DEPTH} nested function calls"),
+                &format!("in function \"{name}\"")
+            );
+        }
+        let mut vars = HashMap::new();
+        let arg_names = ast.iter().find_map(|v| match v {
+            AstNode::FnVar(names) => Some(names.clone()),
+            _ => None,
+        });
+        let arg_names = arg_names.unwrap_or_default();
+        if arg_names.len() != args.len() {
+            return log::error!(TypeError: &format!(
+                "function \"{name}\" expects {} arguments, received {}",
+                arg_names.len(),
+                args.len()
+            ));
+        }
+        for (arg_name, v) in arg_names.into_iter().zip(args) {
+            vars.insert(arg_name, Some(v));
+        }
+        let mut callee = ConstEval {
+            compiler_state: self.compiler_state,
+            vars,
+            int_types: HashMap::new(),
+            return_val: None,
+            depth: self.depth + 1,
+            location: None,
+            io: self.io.take(),
+        };
+        callee.eval_ast(ast)?;
+        self.io = callee.io;
+        Ok(callee.return_val)
+    }
+
+    // the next public or secret input of a program run
+    fn read_input(&mut self, public: bool) -> Result<Matrix<T>> {
+        let kind = if public { "public" } else { "secret" };
+        let Some(io) = &mut self.io else {
+            if public {
+                return log::error!(
+                    TypeError: "public values cannot be declared in a static evaluation"
+                );
+            }
+            return log::error!(TypeError: "secret inputs cannot be read in a static evaluation");
+        };
+        let inputs = if public {
+            &mut io.public_inputs
+        } else {
+            &mut io.secret_inputs
+        };
+        match inputs.pop_front() {
+            Some(v) => Ok(Matrix::from(v)),
+            None => log::error!(
+                InputError: &format!("missing {kind} input"),
+                &format!("pass more {kind} inputs using --{kind}")
+            ),
+        }
+    }
+
+    // append a scalar to the outputs of a program run
+    fn write_output(&mut self, what: &str, v: &Matrix<T>) -> Result<()> {
+        let Some(io) = &mut self.io else {
+            return log::error!(
+                TypeError: "public values cannot be declared in a static evaluation"
+            );
+        };
+        if v.len() != 1 {
+            return log::error!(TypeError: &format!("{what} must be a scalar"));
+        }
+        io.outputs.push(v.values[0].clone());
+        Ok(())
+    }
+
+    // "static " in a static evaluation, prefixes failure messages
+    fn static_prefix(&self) -> &'static str {
+        if self.io.is_some() {
+            ""
+        } else {
+            "static "
+        }
+    }
+
+    // the sum, product, or largest element of a value
+    fn reduce(name: &str, args: &[Matrix<T>]) -> Result<Matrix<T>> {
+        if args.len() != 1 {
+            return log::error!(TypeError: &format!(
+                "{name} expects 1 argument, received {}",
+                args.len()
+            ));
+        }
+        let values = &args[0].values;
+        let out = match name {
+            builtin::SUM => values.iter().fold(T::zero(), |acc, v| acc + v.clone()),
+            builtin::PROD => values.iter().fold(T::one(), |acc, v| acc * v.clone()),
+            _ => {
+                let mut max = BigUint::from(0_u32);
+                for v in values {
+                    let v = v.to_scalar()?.to_biguint();
+                    if v.bits() > 32 {
+                        return log::error!(
+                            &format!("{name} element {v} is out of range"),
+                            "elements must be less than 2^32"
+                        );
+                    }
+                    max = max.max(v);
+                }
+                T::from_str(&max.to_string())?
+            }
+        };
+        Ok(Matrix::from(out))
+    }
+
+    // the dot product or matrix product of two values
+    fn product(name: &str, args: &[Matrix<T>]) -> Result<Matrix<T>> {
+        if args.len() != 2 {
+            return log::error!(TypeError: &format!(
+                "{name} expects 2 arguments, received {}",
+                args.len()
+            ));
+        }
+        let (l, r) = (&args[0], &args[1]);
+        let sum = |terms: &[(usize, usize)]| {
+            terms.iter().fold(T::zero(), |acc, (i, j)| {
+                acc + l.values[*i].clone() * r.values[*j].clone()
+            })
+        };
+        if name == builtin::DOT {
+            let terms = layout::dot_terms(&l.dimensions, &r.dimensions)?;
+            return Ok(Matrix::from(sum(&terms)));
+        }
+        let dimensions = layout::matmul_dimensions(&l.dimensions, &r.dimensions)?;
+        let values = layout::matmul_terms(&l.dimensions, &r.dimensions)?
+            .iter()
+            .map(|v| sum(v))
+            .collect::<Vec<_>>();
+        // the product of two vectors is a scalar
+        if dimensions.is_empty() {
+            return Ok(Matrix::from(values[0].clone()));
+        }
+        Ok(Matrix { dimensions, values })
+    }
+
+    // x raised to a static power, element-wise
+    fn pow(args: &[Matrix<T>]) -> Result<Matrix<T>> {
+        if args.len() != 2 {
+            return log::error!(TypeError: &format!(
+                "std::pow expects 2 arguments, received {}",
+                args.len()
+            ));
+        }
+        if args[1].len() != 1 {
+            return log::error!(TypeError: "std::pow exponent must be a scalar");
+        }
+        let e = args[1].values[0].to_scalar()?.to_biguint();
+        let mut out = args[0].clone();
+        for v in out.values.iter_mut() {
+            let x = v.clone();
+            let mut r = T::one();
+            for i in (0..e.bits()).rev() {
+                r = r.clone() * r;
+                if e.bit(i) {
+                    r = r * x.clone();
+                }
+            }
+            *v = r;
+        }
+        Ok(out)
+    }
+
+    fn define(&mut self, name: &str, v: Matrix<T>) -> Result<()> {
+        if self.vars.contains_key(name) {
+            return log::error!(ScopeError: &format!("variable already defined: {name}"));
+        }
+        self.int_types.remove(name);
+        self.vars.insert(name.to_string(), Some(v));
+        Ok(())
+    }
+
+    // evaluate statements, attaching the location of the
+    // statement that failed to any error
+    fn eval_ast(&mut self, ast: &[AstNode]) -> Result<()> {
+        let outer_location = self.location.clone();
+        let out = self
+            .eval_statements(ast)
+            .map_err(|e| log::locate(e, &self.location));
+        self.location = outer_location;
+        out
+    }
+
+    // variables defined in a block are removed at the end of it
+    fn eval_block(&mut self, ast: &[AstNode]) -> Result<()> {
+        let outer_vars = self.vars.keys().cloned().collect::<HashSet<_>>();
+        self.eval_ast(ast)?;
+        self.vars.retain(|k, _| outer_vars.contains(k));
+        self.int_types.retain(|k, _| outer_vars.contains(k));
+        Ok(())
+    }
+
+    fn eval_statements(&mut self, ast: &[AstNode]) -> Result<()> {
+        for node in ast {
+            if self.return_val.is_some() {
+                break;
+            }
+            match node {
+                AstNode::Location(location) => self.location = Some(location.clone()),
+                AstNode::Inline(_) => {}
+                // arguments are bound by the caller
+                AstNode::FnVar(_) => {}
+                AstNode::FnDims(params) => {
+                    let dimensions = params
+                        .iter()
+                        .map(|(name, _)| match self.vars.get(name) {
+                            Some(Some(v)) => v.dimensions.clone(),
+                            _ => vec![],
+                        })
+                        .collect::<Vec<_>>();
+                    for (name, len) in layout::dim_params(params, &dimensions)? {
+                        self.define(&name, Matrix::from(T::from_str(&len.to_string())?))?;
+                    }
+                }
+                AstNode::Stmt(name, is_let, expr) => {
+                    let v = self.eval(expr)?;
+                    if *is_let {
+                        self.define(name, v)?;
+                    } else {
+                        if !self.vars.contains_key(name) {
+                            return log::error!(ScopeError: &format!("unknown variable \"{name}\""));
+                        }
+                        if let Some(int_type) = self.int_types.get(name) {
+                            Self::assert_int(&v, *int_type)?;
+                        }
+                        self.vars.insert(name.clone(), Some(v));
+                    }
+                }
+                AstNode::StaticDef(name, expr) => {
+                    let v = self.eval(expr)?;
+                    self.define(name, v)?;
+                }
+                AstNode::TypedDef(name, var_type, expr) => {
+                    let v = self.eval(expr)?;
+                    if !var_type.matches(&v.dimensions) {
+                        return log::error!(TypeError: &format!(
+                            "variable \"{name}\" is declared as {} but assigned a value of dimensions {:?}",
+                            var_type.name(),
+                            v.dimensions
+                        ));
+                    }
+                    if let VarType::Int(int_type) = var_type {
+                        Self::assert_int(&v, *int_type)?;
+                    }
+                    self.define(name, v)?;
+                    if let VarType::Int(int_type) = var_type {
+                        self.int_types.insert(name.clone(), *int_type);
+                    }
+                }
+                AstNode::EmptyVecDef(name, dimensions) => {
+                    let len = dimensions.iter().product();
+                    self.define(
+                        name,
+                        Matrix {
+                            dimensions: dimensions.clone(),
+                            values: vec![T::zero(); len],
+                        },
+                    )?;
+                }
+                AstNode::AssignVec(name, indices, expr) => {
+                    let mut target = match self.vars.get(name) {
+                        Some(Some(v)) => v.clone(),
+                        Some(None) => {
+                            return log::error!(TypeError: &format!("variable \"{name}\" is not static"))
+                        }
+                        None => {
+                            return log::error!(ScopeError: &format!("unknown variable \"{name}\""))
+                        }
+                    };
+                    let indices = self.eval_indices(name, &target, indices)?;
+                    let (slot, offset) = target.retrieve_indices(&indices);
+                    let v = self.eval(expr)?;
+                    if v.dimensions != slot.dimensions {
+                        return log::error!(TypeError: &format!(
+                            "cannot assign a value of dimensions {:?} to {name} at {:?}, expected dimensions {:?}",
+                            v.dimensions, indices, slot.dimensions
+                        ));
+                    }
+                    target.values.splice(offset..offset + v.len(), v.values);
+                    self.vars.insert(name.clone(), Some(target));
+                }
+                AstNode::ExprUnassigned(expr) => match expr {
+                    Expr::FnCall(name, args) => {
+                        let args = args
+                            .iter()
+                            .map(|v| self.eval(v))
+                            .collect::<Result<Vec<_>>>()?;
+                        self.call(name, args)?;
+                    }
+                    _ => {
+                        self.eval(expr)?;
+                    }
+                },
+                AstNode::Rtrn(expr) => {
+                    self.return_val = Some(self.eval(expr)?);
+                }
+                AstNode::Assert(cond, message) => {
+                    if self.eval_scalar(cond)? != BigUint::from(1_u32) {
+                        return log::error!(&format!(
+                            "{}assertion failed: {message}",
+                            self.static_prefix()
+                        ));
+                    }
+                }
+                AstNode::Lookup(table, entries) => {
+                    let table = LookupTable::<T::F>::new(table)?;
+                    let mut row = vec![];
+                    for expr in entries {
+                        for v in self.eval(expr)?.values {
+                            row.push(v.to_scalar()?);
+                        }
+                    }
+                    table.check_width(row.len())?;
+                    if !table.contains(&row) {
+                        return log::error!(&format!(
+                            "{}lookup failed: [{}] is not a row of table {}",
+                            self.static_prefix(),
+                            row.iter()
+                                .map(|v| v.to_string())
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                            table.name
+                        ));
+                    }
+                }
+                AstNode::If(cond, block, else_block) => {
+                    if self.eval_scalar(cond)? != BigUint::from(0_u32) {
+                        self.eval_block(block)?;
+                    } else {
+                        self.eval_block(else_block)?;
+                    }
+                }
+                AstNode::Loop(expr, body) => {
+                    let count = self.eval_scalar(expr)?;
+                    let mut i = BigUint::from(0_u32);
+                    while i < count && self.return_val.is_none() {
+                        self.eval_block(body)?;
+                        i += 1_u32;
+                    }
+                }
+                AstNode::While(cond, body) => {
+                    let mut i = 0;
+                    while self.return_val.is_none()
+                        && self.eval_scalar(cond)? != BigUint::from(0_u32)
+                    {
+                        if i == MAX_ITERATIONS {
+                            return log::error!(
+                                &format!("static while loop exceeded {MAX_ITERATIONS} iterations"),
+                                "the loop condition may never become false"
+                            );
+                        }
+                        self.eval_block(body)?;
+                        i += 1;
+                    }
+                }
+                AstNode::PubInput(name) | AstNode::SecretInput(name) => {
+                    let v = self.read_input(matches!(node, AstNode::PubInput(_)))?;
+                    self.define(name, v)?;
+                }
+                AstNode::PubOutput(name) => match self.vars.get(name).cloned() {
+                    Some(Some(v)) => self.write_output(&format!("public output \"{name}\""), &v)?,
+                    _ if self.io.is_none() => {
+                        return log::error!(
+                            TypeError: "public values cannot be declared in a static evaluation"
+                        )
+                    }
+                    _ => {
+                        return log::error!(ScopeError: &format!("variable does not exist: {name}"))
+                    }
+                },
+                AstNode::Ar1cs(_, _, _) | AstNode::Asm(_, _, _) => {
+                    return log::error!(
+                        TypeError: "inline ar1cs and asm blocks cannot be evaluated statically",
+                        "call the function with a signal argument"
+                    );
+                }
+                AstNode::Unconstrained(returns, body) => {
+                    // static hints are exact, the block is evaluated
+                    // and the values it declares are kept
+                    let outer_vars = self.vars.keys().cloned().collect::<HashSet<_>>();
+                    self.eval_ast(body)?;
+                    if let Some(name) = returns.iter().find(|v| !self.vars.contains_key(*v)) {
+                        return log::error!(ScopeError: &format!(
+                            "unconstrained block does not declare \"{name}\""
+                        ));
+                    }
+                    self.vars
+                        .retain(|k, _| outer_vars.contains(k) || returns.contains(k));
+                    self.int_types.retain(|k, _| outer_vars.contains(k));
+                }
+            }
+        }
+        Ok(())
+    }
+}
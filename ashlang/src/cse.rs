@@ -0,0 +1,337 @@
+//! Common subexpression elimination. An arithmetic expression
+//! evaluated more than once in a block, without its variables
+//! changing in between, is evaluated once into a new variable
+//! and each occurrence reads the variable.
+//!
+//! Only expressions of variables declared in the function and
+//! literals are eliminated. Function arguments may be static and
+//! integer variables are range checked as they are evaluated, so
+//! neither is moved. Expressions in static positions like vector
+//! indices and loop counts, and expressions that may not be
+//! evaluated like the right side of `&&`, are never moved.
+use std::collections::HashSet;
+
+use crate::parser::AstNode;
+use crate::parser::BoolOp;
+use crate::parser::Expr;
+use crate::parser::VarType;
+
+// variables declared by the pass, numbered in each function
+const TEMP_PREFIX: &str = "__cse_";
+
+/// Eliminate common subexpressions in the AST of a function.
+pub fn eliminate(ast: Vec<AstNode>) -> Vec<AstNode> {
+    let mut declared = HashSet::new();
+    let mut ints = HashSet::new();
+    collect_declared(&ast, &mut declared, &mut ints);
+    let mut pass = Cse {
+        vars: declared.difference(&ints).cloned().collect(),
+        next_temp: 0,
+    };
+    pass.block(ast)
+}
+
+struct Cse {
+    // the variables an eliminated expression may read
+    vars: HashSet<String>,
+    next_temp: usize,
+}
+
+impl Cse {
+    fn block(&mut self, mut ast: Vec<AstNode>) -> Vec<AstNode> {
+        // nested blocks start without any computed values
+        for node in ast.iter_mut() {
+            match node {
+                AstNode::If(_, if_block, else_block) => {
+                    *if_block = self.block(std::mem::take(if_block));
+                    *else_block = self.block(std::mem::take(else_block));
+                }
+                AstNode::Loop(_, body) | AstNode::While(_, body) => {
+                    *body = self.block(std::mem::take(body));
+                }
+                _ => {}
+            }
+        }
+        let mut i = 0;
+        while i < ast.len() {
+            let Some((expr, end)) = self.repeated(&ast, i) else {
+                i += 1;
+                continue;
+            };
+            let name = format!("{TEMP_PREFIX}{}", self.next_temp);
+            self.next_temp += 1;
+            let key = format!("{expr:?}");
+            for node in ast[i..=end].iter_mut() {
+                for e in exprs_mut(node) {
+                    replace(e, &key, &name);
+                }
+            }
+            // declared after the location of the statement so
+            // errors are reported at the statement
+            ast.insert(i, AstNode::Stmt(name.clone(), true, expr));
+            self.vars.insert(name);
+            i += 1;
+        }
+        ast
+    }
+
+    // the largest expression in a statement that is evaluated
+    // again before its variables change, and the index of the
+    // last statement that may read the computed value
+    fn repeated(&self, ast: &[AstNode], i: usize) -> Option<(Expr, usize)> {
+        let mut candidates = vec![];
+        for e in exprs(&ast[i]) {
+            self.candidates(e, &mut candidates);
+        }
+        let mut keys = HashSet::new();
+        let mut candidates = candidates
+            .into_iter()
+            .map(|e| (format!("{e:?}"), e))
+            .filter(|(key, _)| keys.insert(key.clone()))
+            .collect::<Vec<_>>();
+        candidates.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then(a.cmp(b)));
+        for (key, expr) in candidates {
+            let mut reads = HashSet::new();
+            read_vars(expr, &mut reads);
+            let mut count = 0;
+            let mut end = i;
+            for (j, node) in ast.iter().enumerate().skip(i) {
+                end = j;
+                count += exprs(node)
+                    .iter()
+                    .map(|e| occurrences(e, &key))
+                    .sum::<usize>();
+                let mut written = HashSet::new();
+                written_vars(node, &mut written);
+                if matches!(node, AstNode::Rtrn(_)) || !written.is_disjoint(&reads) {
+                    break;
+                }
+            }
+            if count > 1 {
+                return Some((expr.clone(), end));
+            }
+        }
+        None
+    }
+
+    // arithmetic expressions in an expression that may be
+    // eliminated
+    fn candidates<'a>(&self, e: &'a Expr, out: &mut Vec<&'a Expr>) {
+        match e {
+            Expr::NumOp { lhs, rhs, .. } => {
+                if self.movable(e) {
+                    out.push(e);
+                }
+                self.candidates(lhs, out);
+                self.candidates(rhs, out);
+            }
+            Expr::BoolOp {
+                lhs,
+                bool_op: BoolOp::And | BoolOp::Or,
+                ..
+            } => self.candidates(lhs, out),
+            Expr::BoolOp { lhs, rhs, .. } | Expr::BitOp { lhs, rhs, .. } => {
+                self.candidates(lhs, out);
+                self.candidates(rhs, out);
+            }
+            Expr::FnCall(_, args) => {
+                for arg in args {
+                    self.candidates(arg, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // an expression of eliminated variables and literals that
+    // reads at least one variable
+    fn movable(&self, e: &Expr) -> bool {
+        fn operand(vars: &HashSet<String>, e: &Expr) -> Option<bool> {
+            match e {
+                Expr::Lit(_) => Some(false),
+                Expr::Val(name, indices) => (vars.contains(name)
+                    && indices.iter().all(|v| matches!(v, Expr::Lit(_))))
+                .then_some(true),
+                Expr::NumOp { lhs, rhs, .. } => Some(operand(vars, lhs)? | operand(vars, rhs)?),
+                _ => None,
+            }
+        }
+        operand(&self.vars, e) == Some(true)
+    }
+}
+
+// the expressions a statement evaluates before any nested
+// block, in positions where an expression may be eliminated
+fn exprs(node: &AstNode) -> Vec<&Expr> {
+    match node {
+        AstNode::Stmt(_, _, e)
+        | AstNode::TypedDef(_, _, e)
+        | AstNode::ExprUnassigned(e)
+        | AstNode::Rtrn(e)
+        | AstNode::Assert(e, _)
+        | AstNode::If(e, _, _)
+        | AstNode::AssignVec(_, _, e) => vec![e],
+        AstNode::Lookup(_, values) => values.iter().collect(),
+        _ => vec![],
+    }
+}
+
+fn exprs_mut(node: &mut AstNode) -> Vec<&mut Expr> {
+    match node {
+        AstNode::Stmt(_, _, e)
+        | AstNode::TypedDef(_, _, e)
+        | AstNode::ExprUnassigned(e)
+        | AstNode::Rtrn(e)
+        | AstNode::Assert(e, _)
+        | AstNode::If(e, _, _)
+        | AstNode::AssignVec(_, _, e) => vec![e],
+        AstNode::Lookup(_, values) => values.iter_mut().collect(),
+        _ => vec![],
+    }
+}
+
+// the number of times an expression is evaluated, visiting
+// the same positions as `Cse::candidates`
+fn occurrences(e: &Expr, key: &str) -> usize {
+    if format!("{e:?}") == key {
+        return 1;
+    }
+    match e {
+        Expr::BoolOp {
+            lhs,
+            bool_op: BoolOp::And | BoolOp::Or,
+            ..
+        } => occurrences(lhs, key),
+        Expr::NumOp { lhs, rhs, .. }
+        | Expr::BoolOp { lhs, rhs, .. }
+        | Expr::BitOp { lhs, rhs, .. } => occurrences(lhs, key) + occurrences(rhs, key),
+        Expr::FnCall(_, args) => args.iter().map(|v| occurrences(v, key)).sum(),
+        _ => 0,
+    }
+}
+
+fn replace(e: &mut Expr, key: &str, name: &str) {
+    if format!("{e:?}") == key {
+        *e = Expr::Val(name.to_string(), vec![]);
+        return;
+    }
+    match e {
+        Expr::BoolOp {
+            lhs,
+            bool_op: BoolOp::And | BoolOp::Or,
+            ..
+        } => replace(lhs, key, name),
+        Expr::NumOp { lhs, rhs, .. }
+        | Expr::BoolOp { lhs, rhs, .. }
+        | Expr::BitOp { lhs, rhs, .. } => {
+            replace(lhs, key, name);
+            replace(rhs, key, name);
+        }
+        Expr::FnCall(_, args) => {
+            for arg in args {
+                replace(arg, key, name);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn read_vars(e: &Expr, out: &mut HashSet<String>) {
+    match e {
+        Expr::Val(name, _) => {
+            out.insert(name.clone());
+        }
+        Expr::NumOp { lhs, rhs, .. } => {
+            read_vars(lhs, out);
+            read_vars(rhs, out);
+        }
+        _ => {}
+    }
+}
+
+// variables a statement declares or assigns, including in
+// nested blocks and through references passed to functions
+fn written_vars(node: &AstNode, out: &mut HashSet<String>) {
+    match node {
+        AstNode::Stmt(name, _, _)
+        | AstNode::TypedDef(name, _, _)
+        | AstNode::EmptyVecDef(name, _)
+        | AstNode::AssignVec(name, _, _)
+        | AstNode::PubInput(name)
+        | AstNode::SecretInput(name)
+        | AstNode::StaticDef(name, _) => {
+            out.insert(name.clone());
+        }
+        AstNode::Ar1cs(_, returns, _) | AstNode::Asm(_, returns, _) => {
+            out.extend(returns.iter().cloned());
+        }
+        AstNode::Unconstrained(returns, body) => {
+            out.extend(returns.iter().cloned());
+            body.iter().for_each(|v| written_vars(v, out));
+        }
+        AstNode::If(_, if_block, else_block) => {
+            if_block.iter().for_each(|v| written_vars(v, out));
+            else_block.iter().for_each(|v| written_vars(v, out));
+        }
+        AstNode::Loop(_, body) | AstNode::While(_, body) => {
+            body.iter().for_each(|v| written_vars(v, out));
+        }
+        _ => {}
+    }
+    for e in exprs(node) {
+        referenced_vars(e, out);
+    }
+}
+
+fn referenced_vars(e: &Expr, out: &mut HashSet<String>) {
+    match e {
+        Expr::Ref(name, _) => {
+            out.insert(name.clone());
+        }
+        Expr::FnCall(_, args) | Expr::VecVec(args) => {
+            args.iter().for_each(|v| referenced_vars(v, out));
+        }
+        Expr::NumOp { lhs, rhs, .. }
+        | Expr::BoolOp { lhs, rhs, .. }
+        | Expr::BitOp { lhs, rhs, .. } => {
+            referenced_vars(lhs, out);
+            referenced_vars(rhs, out);
+        }
+        _ => {}
+    }
+}
+
+// variables declared with a runtime value, and variables
+// declared as integers
+fn collect_declared(ast: &[AstNode], declared: &mut HashSet<String>, ints: &mut HashSet<String>) {
+    for node in ast {
+        match node {
+            AstNode::Stmt(name, true, _)
+            | AstNode::EmptyVecDef(name, _)
+            | AstNode::PubInput(name)
+            | AstNode::SecretInput(name) => {
+                declared.insert(name.clone());
+            }
+            AstNode::TypedDef(name, var_type, _) => {
+                declared.insert(name.clone());
+                if matches!(var_type, VarType::Int(_)) {
+                    ints.insert(name.clone());
+                }
+            }
+            AstNode::Ar1cs(_, returns, _) | AstNode::Asm(_, returns, _) => {
+                declared.extend(returns.iter().cloned());
+            }
+            AstNode::Unconstrained(returns, _) => {
+                declared.extend(returns.iter().cloned());
+            }
+            AstNode::If(_, if_block, else_block) => {
+                collect_declared(if_block, declared, ints);
+                collect_declared(else_block, declared, ints);
+            }
+            AstNode::Loop(_, body) | AstNode::While(_, body) => {
+                collect_declared(body, declared, ints);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,312 @@
+//! Intermediate artifacts written with `--emit`. Each stage of
+//! compilation can be written to a file so it can be inspected
+//! without changing the compiler:
+//!
+//! - `ast`: the parsed entrypoint and each ash function it may
+//!   call
+//! - `ir`: the same functions after program constants are
+//!   declared and common subexpressions are eliminated, the input
+//!   of each vm
+//! - `ar1cs`: the compiled r1cs program
+//! - `r1cs-json`: the compiled r1cs program as JSON, with field
+//!   elements as decimal strings. When the witness is built it is
+//!   also written as JSON, e.g. `main.witness.json`
+//! - `tasm`: the compiled assembly
+//!
+//! Files are named after the entrypoint, e.g. `main.ast`.
+use std::fmt::Display;
+use std::fs;
+use std::str::FromStr;
+
+use anyhow::Result;
+use camino::Utf8PathBuf;
+#[cfg(feature = "serde")]
+use scalarff::FieldElement;
+
+use crate::cli::Config;
+use crate::log;
+#[cfg(feature = "serde")]
+use crate::r1cs::ar1cs::Ar1cs;
+#[cfg(feature = "serde")]
+use crate::r1cs::witness::Witness;
+
+/// The directory artifacts are written to by default.
+pub const EMIT_DIR: &str = "build";
+
+/// The `format` of an `r1cs-json` artifact.
+pub const R1CS_JSON_FORMAT: &str = "ashlang-r1cs";
+/// The `format` of a witness written with `r1cs-json`.
+pub const WITNESS_JSON_FORMAT: &str = "ashlang-witness";
+/// The version of the JSON schemas. It is incremented when a
+/// field is removed or changes meaning.
+pub const JSON_VERSION: u32 = 1;
+
+// witnesses are written next to the r1cs json
+const WITNESS_EXTENSION: &str = "witness.json";
+
+/// A stage of compilation that may be written to a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Ast,
+    Ir,
+    Ar1cs,
+    R1csJson,
+    Tasm,
+}
+
+impl FromStr for Stage {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        Ok(match input {
+            "ast" => Stage::Ast,
+            "ir" => Stage::Ir,
+            "ar1cs" => Stage::Ar1cs,
+            "r1cs-json" => Stage::R1csJson,
+            "tasm" => Stage::Tasm,
+            _ => {
+                return log::error!(
+                    ConfigError: &format!("unknown emit stage \"{input}\""),
+                    "stages are ast, ir, ar1cs, r1cs-json, and tasm"
+                )
+            }
+        })
+    }
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Stage::Ast => "ast",
+                Stage::Ir => "ir",
+                Stage::Ar1cs => "ar1cs",
+                Stage::R1csJson => "r1cs-json",
+                Stage::Tasm => "tasm",
+            }
+        )
+    }
+}
+
+impl Stage {
+    /// The file extension of the artifact.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Stage::Ast => "ast",
+            Stage::Ir => "ir",
+            Stage::Ar1cs => "ar1cs",
+            Stage::R1csJson => "r1cs.json",
+            Stage::Tasm => "tasm",
+        }
+    }
+
+    /// The target producing the artifact, None if it is
+    /// produced in every target.
+    pub fn target(&self) -> Option<&'static str> {
+        match self {
+            Stage::Ast | Stage::Ir => None,
+            Stage::Ar1cs | Stage::R1csJson => Some("r1cs"),
+            Stage::Tasm => Some("tasm"),
+        }
+    }
+
+    /// Parse a comma separated list of stages for a target.
+    pub fn parse_list(input: &str, target: &str) -> Result<Vec<Self>> {
+        let mut out: Vec<Self> = vec![];
+        for v in input.split(',').filter(|v| !v.is_empty()) {
+            let stage = v.parse::<Stage>()?;
+            if stage.target().is_some_and(|v| v != target) {
+                return log::error!(
+                    ConfigError: &format!("cannot emit {stage} in the {target} target"),
+                    &format!("{stage} is only produced by the {} target", stage.target().unwrap())
+                );
+            }
+            if stage == Stage::R1csJson && !cfg!(feature = "serde") {
+                return log::error!(
+                    ConfigError: "emitting r1cs-json requires the serde feature"
+                );
+            }
+            if !out.contains(&stage) {
+                out.push(stage);
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Writes the artifacts of the selected stages to a directory.
+#[derive(Clone, Debug)]
+pub struct Emitter {
+    pub dir: Utf8PathBuf,
+    pub stages: Vec<Stage>,
+}
+
+impl Emitter {
+    /// The emitter selected by a config, None if no stage is
+    /// emitted.
+    pub fn new(config: &Config) -> Option<Self> {
+        if config.emit.is_empty() {
+            return None;
+        }
+        Some(Emitter {
+            dir: config
+                .emit_dir
+                .clone()
+                .unwrap_or_else(|| Utf8PathBuf::from(EMIT_DIR)),
+            stages: config.emit.clone(),
+        })
+    }
+
+    /// Whether a stage is written.
+    pub fn emits(&self, stage: Stage) -> bool {
+        self.stages.contains(&stage)
+    }
+
+    /// Write the artifact of a stage for an entrypoint, if the
+    /// stage is selected. The contents are only built when they
+    /// are written.
+    pub fn write(
+        &self,
+        stage: Stage,
+        entry_fn: &str,
+        contents: impl FnOnce() -> Result<String>,
+    ) -> Result<()> {
+        if !self.emits(stage) {
+            return Ok(());
+        }
+        self.write_file(&format!("{entry_fn}.{}", stage.extension()), contents)
+    }
+
+    /// Write the witness of an entrypoint as JSON, if `r1cs-json`
+    /// is selected.
+    pub fn write_witness(
+        &self,
+        entry_fn: &str,
+        contents: impl FnOnce() -> Result<String>,
+    ) -> Result<()> {
+        if !self.emits(Stage::R1csJson) {
+            return Ok(());
+        }
+        self.write_file(&format!("{entry_fn}.{WITNESS_EXTENSION}"), contents)
+    }
+
+    fn write_file(&self, file: &str, contents: impl FnOnce() -> Result<String>) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(file);
+        if let Err(e) = fs::write(&path, contents()?) {
+            return log::error!(
+                ConfigError: &format!("failed to write {path}"),
+                &e.to_string()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A compiled r1cs program as JSON, see the `r1cs` readme for
+/// the schema. Each constraint lists the `a`, `b`, and `c` terms
+/// as `[coefficient, signal]` pairs, and symbolic constraints
+/// also list the signal they assign and their operation.
+#[cfg(feature = "serde")]
+pub fn r1cs_json<T: FieldElement>(ar1cs: &Ar1cs<T>) -> Result<String> {
+    use serde_json::json;
+
+    let terms = |lc: &[(T, usize)]| {
+        lc.iter()
+            .map(|(coef, i)| json!([coef.to_string(), i]))
+            .collect::<Vec<_>>()
+    };
+    let mut signals = 1;
+    let constraints = ar1cs
+        .constraints
+        .iter()
+        .map(|c| {
+            for (_, i) in c.a.iter().chain(&c.b).chain(&c.c) {
+                signals = signals.max(i + 1);
+            }
+            if let Some(i) = c.out_i {
+                signals = signals.max(i + 1);
+            }
+            json!({
+                "symbolic": c.symbolic,
+                "op": c.symbolic_op.as_ref().map(|v| v.to_string()),
+                "out": c.out_i,
+                "a": terms(&c.a),
+                "b": terms(&c.b),
+                "c": terms(&c.c),
+                "comment": c.comment,
+            })
+        })
+        .collect::<Vec<_>>();
+    let out = json!({
+        "format": R1CS_JSON_FORMAT,
+        "version": JSON_VERSION,
+        "ar1cs_version": ar1cs.version,
+        "entry_fn": ar1cs.entry_fn,
+        "field": ar1cs.field,
+        "prime": T::prime().to_string(),
+        "signals": signals,
+        "public": public_json(ar1cs, None),
+        "labels": ar1cs
+            .symbols
+            .iter()
+            .map(|v| json!({ "name": v.name, "signals": v.signals }))
+            .collect::<Vec<_>>(),
+        "constraints": constraints,
+    });
+    Ok(serde_json::to_string_pretty(&out)?)
+}
+
+/// A witness of a compiled r1cs program as JSON, see the `r1cs`
+/// readme for the schema. `values` holds the value of each signal
+/// at its index, as decimal strings.
+#[cfg(feature = "serde")]
+pub fn witness_json<T: FieldElement>(ar1cs: &Ar1cs<T>, witness: &Witness<T>) -> Result<String> {
+    use serde_json::json;
+
+    let out = json!({
+        "format": WITNESS_JSON_FORMAT,
+        "version": JSON_VERSION,
+        "entry_fn": ar1cs.entry_fn,
+        "field": ar1cs.field,
+        "public": public_json(ar1cs, Some(witness.variables.as_slice())),
+        "outputs": witness.outputs,
+        "values": witness
+            .variables
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>(),
+    });
+    Ok(serde_json::to_string_pretty(&out)?)
+}
+
+// the public values of a program, with the value of each signal
+// if a witness is known
+#[cfg(feature = "serde")]
+fn public_json<T: FieldElement>(ar1cs: &Ar1cs<T>, values: Option<&[T]>) -> Vec<serde_json::Value> {
+    ar1cs
+        .public
+        .iter()
+        .map(|v| {
+            let (kind, name) = match v
+                .declaration
+                .strip_prefix("pub ")
+                .and_then(|v| v.split_once(' '))
+            {
+                Some((kind, name)) => (kind, name),
+                None => ("", v.declaration.as_str()),
+            };
+            let mut out = serde_json::json!({
+                "kind": kind,
+                "name": name,
+                "signal": v.signal,
+            });
+            if let Some(value) = values.and_then(|values| values.get(v.signal)) {
+                out["value"] = value.to_string().into();
+            }
+            out
+        })
+        .collect()
+}
@@ -0,0 +1,158 @@
+use colored::Colorize;
+
+use crate::parser::SourceLocation;
+
+/// The message, explanation, and source location of an error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorInfo {
+    pub message: String,
+    // an explanation of why the error occurred or how to fix it
+    pub hint: Option<String>,
+    // the statement being compiled when the error occurred
+    pub location: Option<SourceLocation>,
+}
+
+impl ErrorInfo {
+    pub fn new(message: &str, hint: Option<&str>) -> Self {
+        Self {
+            message: message.to_string(),
+            hint: hint.map(|v| v.to_string()),
+            location: None,
+        }
+    }
+}
+
+/// Errors returned by the ashlang compiler and provers.
+///
+/// Functions in this crate return `anyhow::Result`. Use
+/// `e.downcast_ref::<AshlangError>()` to match on the kind of
+/// failure, and `code` for a stable machine-readable identifier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AshlangError {
+    /// Malformed ashlang, ar1cs, asm, or circom source.
+    ParseError(ErrorInfo),
+    /// A value of the wrong dimension or location was used in an operation.
+    TypeError(ErrorInfo),
+    /// A variable or function is undefined, duplicated, or used outside of its scope.
+    ScopeError(ErrorInfo),
+    /// An invalid memory or stack access in the tasm target.
+    MemoryError(ErrorInfo),
+    /// Program inputs or a witness are missing or invalid.
+    InputError(ErrorInfo),
+    /// A proving system failed or does not support the program.
+    ProverError(ErrorInfo),
+    /// The compiler configuration is invalid, e.g. an unknown target or field.
+    ConfigError(ErrorInfo),
+    /// Any other compile failure.
+    CompileError(ErrorInfo),
+}
+
+#[allow(dead_code)]
+impl AshlangError {
+    /// A stable identifier for the kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AshlangError::ParseError(_) => "parse_error",
+            AshlangError::TypeError(_) => "type_error",
+            AshlangError::ScopeError(_) => "scope_error",
+            AshlangError::MemoryError(_) => "memory_error",
+            AshlangError::InputError(_) => "input_error",
+            AshlangError::ProverError(_) => "prover_error",
+            AshlangError::ConfigError(_) => "config_error",
+            AshlangError::CompileError(_) => "compile_error",
+        }
+    }
+
+    pub fn info(&self) -> &ErrorInfo {
+        match self {
+            AshlangError::ParseError(info)
+            | AshlangError::TypeError(info)
+            | AshlangError::ScopeError(info)
+            | AshlangError::MemoryError(info)
+            | AshlangError::InputError(info)
+            | AshlangError::ProverError(info)
+            | AshlangError::ConfigError(info)
+            | AshlangError::CompileError(info) => info,
+        }
+    }
+
+    fn info_mut(&mut self) -> &mut ErrorInfo {
+        match self {
+            AshlangError::ParseError(info)
+            | AshlangError::TypeError(info)
+            | AshlangError::ScopeError(info)
+            | AshlangError::MemoryError(info)
+            | AshlangError::InputError(info)
+            | AshlangError::ProverError(info)
+            | AshlangError::ConfigError(info)
+            | AshlangError::CompileError(info) => info,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.info().message
+    }
+
+    pub fn hint(&self) -> Option<&str> {
+        self.info().hint.as_deref()
+    }
+
+    pub fn location(&self) -> Option<&SourceLocation> {
+        self.info().location.as_ref()
+    }
+
+    pub fn with_location(mut self, location: SourceLocation) -> Self {
+        self.info_mut().location = Some(location);
+        self
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            AshlangError::ParseError(_) => "Parse error",
+            AshlangError::ProverError(_) => "Prover error",
+            AshlangError::ConfigError(_) => "Config error",
+            _ => "Compile error",
+        }
+    }
+}
+
+impl std::fmt::Display for AshlangError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let info = self.info();
+        writeln!(f, "{}", self.title().red().bold())?;
+        write!(f, "{}", info.message)?;
+        if let Some(hint) = &info.hint {
+            write!(f, "\n{}\n{hint}", "Explanation".green().bold())?;
+        }
+        if let Some(location) = &info.location {
+            let line = location.line.to_string();
+            let gutter = " ".repeat(line.len());
+            write!(
+                f,
+                "\n{gutter}{} {}:{}:{}",
+                "-->".blue().bold(),
+                location.file,
+                location.line,
+                location.col
+            )?;
+            write!(f, "\n{gutter} {}", "|".blue().bold())?;
+            write!(
+                f,
+                "\n{} {} {}",
+                line.blue().bold(),
+                "|".blue().bold(),
+                location.snippet
+            )?;
+            write!(
+                f,
+                "\n{gutter} {} {}{}",
+                "|".blue().bold(),
+                " ".repeat(location.col - 1),
+                "^".red().bold()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for AshlangError {}
@@ -0,0 +1,215 @@
+//! Compare the static values of a program between fields. A
+//! program is compiled to the r1cs target in each field and the
+//! value of every static expression assigned to a variable is
+//! recorded. Values are compared as signed integers, e.g. `-1` is
+//! the same in every field, so a difference means the value
+//! overflowed or was divided in one of the fields.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use anyhow::Result;
+use ring_math::PolynomialRingElement;
+use scalarff::BigUint;
+use scalarff::FieldElement;
+
+use crate::cli::Config;
+use crate::compiler::Compiler;
+use crate::log;
+use crate::parser::SourceLocation;
+use crate::rings::init_custom_field;
+use crate::rings::Bn128PolynomialRing;
+use crate::rings::Curve25519PolynomialRing;
+use crate::rings::CustomPolynomialRing;
+use crate::rings::DilithiumPolynomialRingElement;
+use crate::rings::OxfoiPolynomialRing;
+use crate::rings::CUSTOM_FIELD_PREFIX;
+
+/// The fields compared if none are specified.
+pub const DEFAULT_FIELDS: [&str; 3] = ["foi", "curve25519", "alt_bn128"];
+
+/// A static value assigned while compiling, as integers less
+/// than the field prime.
+#[derive(Clone, Debug)]
+pub struct StaticValue {
+    pub name: String,
+    pub location: Option<SourceLocation>,
+    pub values: Vec<BigUint>,
+}
+
+/// The result of comparing a program between fields.
+#[derive(Debug)]
+pub struct FieldReport {
+    // the number of static values compared in every field
+    pub compared: usize,
+    // fields the program does not compile in, and the error
+    pub errors: Vec<(String, String)>,
+    pub differences: Vec<FieldDifference>,
+}
+
+/// A static value that differs between fields. `values` is the
+/// signed value in each field, or `None` if the expression was
+/// not evaluated in that field.
+#[derive(Debug)]
+pub struct FieldDifference {
+    pub name: String,
+    pub location: Option<SourceLocation>,
+    pub values: Vec<(String, Option<Vec<String>>)>,
+}
+
+impl Display for FieldDifference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.location {
+            Some(location) => writeln!(
+                f,
+                "{} differs between fields at {}:{}:{}",
+                self.name, location.file, location.line, location.col
+            )?,
+            None => writeln!(f, "{} differs between fields", self.name)?,
+        }
+        if let Some(location) = &self.location {
+            writeln!(f, "  {}", location.snippet.trim())?;
+        }
+        for (i, (field, values)) in self.values.iter().enumerate() {
+            let v = match values {
+                Some(values) if values.len() == 1 => values[0].clone(),
+                Some(values) => format!("[{}]", values.join(", ")),
+                None => "not evaluated".to_string(),
+            };
+            write!(f, "  {field}: {v}")?;
+            if i + 1 < self.values.len() {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compile the entry function of a config in each field and
+/// compare the static values. The config target is ignored.
+pub fn check(config: &Config, fields: &[String]) -> Result<FieldReport> {
+    if fields.len() < 2 {
+        return log::error!(
+            ConfigError: "at least two fields must be compared",
+            "specify the fields using --fields, e.g. --fields foi,alt_bn128"
+        );
+    }
+    let mut config = config.clone();
+    config.target = "r1cs".to_string();
+    config.extension_priorities = vec!["ash".into(), "ar1cs".into(), "r1cs".into()];
+    // cached functions are not evaluated
+    config.cache_dir = None;
+
+    let mut report = FieldReport {
+        compared: 0,
+        errors: vec![],
+        differences: vec![],
+    };
+    // the signed values of each expression in each field, keyed by
+    // location so branches taken in one field don't misalign the rest
+    let mut traces = vec![];
+    for field in fields {
+        let trace = match field.as_str() {
+            "foi" | "oxfoi" => trace::<OxfoiPolynomialRing>(&config),
+            "curve25519" => trace::<Curve25519PolynomialRing>(&config),
+            "alt_bn128" => trace::<Bn128PolynomialRing>(&config),
+            "dilithium" => trace::<DilithiumPolynomialRingElement>(&config),
+            _ if field.starts_with(CUSTOM_FIELD_PREFIX) => {
+                init_custom_field(field).and_then(|_| trace::<CustomPolynomialRing>(&config))
+            }
+            _ => {
+                return log::error!(
+                    ConfigError: &format!("Unsupported field for target r1cs: {field}"),
+                    "compare any of foi, curve25519, alt_bn128, dilithium, or one custom:<prime>"
+                )
+            }
+        };
+        match trace {
+            Ok(trace) => traces.push((field.clone(), trace)),
+            Err(e) => report.errors.push((field.clone(), e.to_string())),
+        }
+    }
+
+    let mut keys = vec![];
+    for (_, trace) in &traces {
+        for key in trace.keys() {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+    }
+    keys.sort();
+    for key in keys {
+        let count = traces
+            .iter()
+            .map(|(_, trace)| trace.get(&key).map(|v| v.len()).unwrap_or_default())
+            .max()
+            .unwrap_or_default();
+        // a value evaluated more than once, e.g. in a loop, is
+        // compared at each evaluation
+        for i in 0..count {
+            let values = traces
+                .iter()
+                .map(|(field, trace)| {
+                    let v = trace.get(&key).and_then(|v| v.get(i));
+                    (field.clone(), v.map(|(_, values)| values.clone()))
+                })
+                .collect::<Vec<_>>();
+            if values.iter().all(|(_, v)| *v == values[0].1) {
+                report.compared += 1;
+                continue;
+            }
+            let evaluated = traces
+                .iter()
+                .find_map(|(_, trace)| trace.get(&key).and_then(|v| v.get(i)))
+                .map(|(value, _)| value.clone());
+            if let Some(value) = evaluated {
+                report.differences.push(FieldDifference {
+                    name: value.name,
+                    location: value.location,
+                    values,
+                });
+            }
+            // report each expression once
+            break;
+        }
+    }
+    Ok(report)
+}
+
+// (file, line, col, name) of a static value
+type TraceKey = (String, usize, usize, String);
+
+// compile in a field, returning each static value and its signed
+// representation in the order they are evaluated
+fn trace<T: PolynomialRingElement>(
+    config: &Config,
+) -> Result<BTreeMap<TraceKey, Vec<(StaticValue, Vec<String>)>>> {
+    let mut compiler: Compiler<T> = Compiler::new(config)?;
+    compiler.trace_statics();
+    compiler.compile(&config.entry_fn)?;
+    let prime = T::F::prime();
+    let mut out: BTreeMap<TraceKey, Vec<(StaticValue, Vec<String>)>> = BTreeMap::new();
+    for value in compiler.static_trace() {
+        let key = match &value.location {
+            Some(location) => (
+                location.file.clone(),
+                location.line,
+                location.col,
+                value.name.clone(),
+            ),
+            None => (String::new(), 0, 0, value.name.clone()),
+        };
+        let signed = value.values.iter().map(|v| signed(v, &prime)).collect();
+        out.entry(key).or_default().push((value.clone(), signed));
+    }
+    Ok(out)
+}
+
+// values greater than half the prime are negative
+fn signed(v: &BigUint, prime: &BigUint) -> String {
+    if v.clone() * 2_u32 > *prime {
+        format!("-{}", prime - v)
+    } else {
+        v.to_string()
+    }
+}
@@ -0,0 +1,560 @@
+//! A canonical formatter for ash source. The source is parsed
+//! and each statement is printed from the parse tree:
+//!
+//! - blocks are indented by two spaces
+//! - operators, commas, and `->` are surrounded by single spaces
+//! - at most one empty line separates statements
+//! - vectors, structs, and struct literals that span multiple
+//!   lines are printed with one element per line
+//!
+//! Comments are not part of the parse tree, they are read from
+//! the source between statements. Inline ar1cs and asm blocks
+//! are re-indented but otherwise kept as written.
+use std::fs;
+
+use anyhow::Result;
+use camino::Utf8PathBuf;
+use pest::iterators::Pair;
+use pest::Parser;
+
+use crate::log;
+use crate::parser::AshPestParser;
+use crate::parser::Rule;
+
+const INDENT: &str = "  ";
+
+/// Format the source of an ash file.
+#[allow(dead_code)]
+pub fn format(source: &str) -> Result<String> {
+    format_file(source, "source")
+}
+
+/// Format each ash file in a set of paths, directories are
+/// walked recursively. Files are rewritten unless `check` is
+/// set. Returns the files that were, or would be, changed.
+pub fn format_paths(paths: &[Utf8PathBuf], check: bool) -> Result<Vec<Utf8PathBuf>> {
+    let mut files = vec![];
+    for path in paths {
+        ash_files(path, &mut files)?;
+    }
+    files.sort();
+    let mut changed = vec![];
+    for file in files {
+        let source = fs::read_to_string(&file)?;
+        let formatted = format_file(&source, file.as_str())?;
+        if formatted != source {
+            if !check {
+                fs::write(&file, &formatted)?;
+            }
+            changed.push(file);
+        }
+    }
+    Ok(changed)
+}
+
+fn ash_files(path: &Utf8PathBuf, out: &mut Vec<Utf8PathBuf>) -> Result<()> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => {
+            return log::error!(ConfigError: &format!("Failed to stat metadata for path: {path}"))
+        }
+    };
+    if metadata.is_dir() {
+        for entry in fs::read_dir(path)? {
+            let next = Utf8PathBuf::from_path_buf(entry?.path()).unwrap();
+            if next.is_dir() || next.extension() == Some("ash") {
+                ash_files(&next, out)?;
+            }
+        }
+    } else {
+        out.push(path.clone());
+    }
+    Ok(())
+}
+
+fn format_file(source: &str, name: &str) -> Result<String> {
+    // the parser requires a trailing new line
+    let source = format!("{source}\n");
+    let pairs =
+        AshPestParser::parse(Rule::program, &source).map_err(|e| log::parse_error(e, name))?;
+    let mut f = Formatter {
+        source: &source,
+        out: String::new(),
+        indent: 0,
+        blank: false,
+        at_start: true,
+    };
+    let mut end = 0;
+    for pair in pairs {
+        if pair.as_rule() == Rule::EOI {
+            break;
+        }
+        f.gap(end, pair.as_span().start(), end > 0);
+        end = pair.as_span().end();
+        f.item(pair);
+    }
+    f.gap(end, source.len(), end > 0);
+    // the output must be valid ash
+    if let Err(e) = AshPestParser::parse(Rule::program, &format!("{}\n", f.out)) {
+        return log::error!(
+            &format!("formatting {name} produced invalid source"),
+            &log::parse_error(e, name).to_string()
+        );
+    }
+    Ok(f.out)
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    out: String,
+    indent: usize,
+    // an empty line precedes the next line
+    blank: bool,
+    // nothing has been written in the current block
+    at_start: bool,
+}
+
+impl Formatter<'_> {
+    fn pad(indent: usize) -> String {
+        INDENT.repeat(indent)
+    }
+
+    // write a line at the current indentation, lines after the
+    // first are already indented
+    fn line(&mut self, s: &str) {
+        if self.blank && !self.at_start {
+            self.out.push('\n');
+        }
+        self.blank = false;
+        self.at_start = false;
+        self.out.push_str(&Self::pad(self.indent));
+        self.out.push_str(s);
+        self.out.push('\n');
+    }
+
+    // append a comment to the last line written
+    fn trailing(&mut self, comment: &str) {
+        self.out.pop();
+        self.out.push(' ');
+        self.out.push_str(comment);
+        self.out.push('\n');
+    }
+
+    // read the comments and empty lines between two items. The
+    // first segment is the rest of the line of the previous item
+    fn gap(&mut self, start: usize, end: usize, after_item: bool) {
+        let text = &self.source[start..end];
+        let mut lines = text.split('\n').collect::<Vec<_>>();
+        if after_item {
+            let first = lines.remove(0);
+            if let Some(comment) = comment(first) {
+                self.trailing(comment);
+            }
+        } else {
+            // there is no previous line, each segment is a line
+            lines.insert(0, "");
+        }
+        // the last segment is the indentation of the next item
+        let full_lines = lines.len().saturating_sub(1);
+        for line in &lines[..full_lines] {
+            match comment(line) {
+                Some(comment) => self.line(comment),
+                None => self.blank = true,
+            }
+        }
+    }
+
+    fn block(&mut self, pair: Pair<Rule>) {
+        let span = pair.as_span();
+        self.indent += 1;
+        self.at_start = true;
+        self.blank = false;
+        // skip the opening brace
+        let mut end = span.start() + 1;
+        for stmt in pair.into_inner() {
+            self.gap(end, stmt.as_span().start(), true);
+            end = stmt.as_span().end();
+            self.item(stmt);
+        }
+        self.gap(end, span.end() - 1, true);
+        self.indent -= 1;
+        self.blank = false;
+        self.at_start = false;
+    }
+
+    fn item(&mut self, pair: Pair<Rule>) {
+        let pair = match pair.as_rule() {
+            Rule::stmt => pair.into_inner().next().unwrap(),
+            _ => pair,
+        };
+        let rule = pair.as_rule();
+        if rule == Rule::attributed_stmt {
+            let mut inner = pair.into_inner();
+            let attribute = inner.next().unwrap();
+            let stmt = inner.next().unwrap();
+            self.line(attribute.as_str());
+            self.gap(attribute.as_span().end(), stmt.as_span().start(), true);
+            // the attribute is written directly before the statement
+            self.blank = false;
+            self.item(stmt);
+            return;
+        }
+        if !matches!(
+            rule,
+            Rule::if_stmt | Rule::while_stmt | Rule::loop_stmt | Rule::unconstrained_stmt
+        ) {
+            // comments inside a multi-line statement are moved
+            // before it
+            for comment in self.inner_comments(&pair) {
+                self.line(&comment);
+            }
+        }
+        match rule {
+            Rule::if_stmt => self.if_stmt(pair, "if "),
+            Rule::while_stmt | Rule::loop_stmt => {
+                let keyword = if rule == Rule::while_stmt {
+                    "while"
+                } else {
+                    "loop"
+                };
+                let mut inner = pair.into_inner();
+                let condition = inner.next().unwrap();
+                let s = format!("{keyword} {} {{", self.node(condition));
+                self.line(&s);
+                self.block(inner.next().unwrap());
+                self.line("}");
+            }
+            Rule::unconstrained_stmt => {
+                let mut inner = pair.into_inner();
+                let returns = inner.next().unwrap();
+                let s = format!("unconstrained {} {{", self.node(returns));
+                self.line(&s);
+                self.block(inner.next().unwrap());
+                self.line("}");
+            }
+            _ => {
+                let s = self.stmt(pair);
+                self.line(&s);
+            }
+        }
+    }
+
+    fn if_stmt(&mut self, pair: Pair<Rule>, prefix: &str) {
+        let mut inner = pair.into_inner();
+        let condition = inner.next().unwrap();
+        let s = format!("{prefix}{} {{", self.node(condition));
+        self.line(&s);
+        self.block(inner.next().unwrap());
+        match inner.next() {
+            Some(next) if next.as_rule() == Rule::if_stmt => self.if_stmt(next, "} else if "),
+            Some(next) => {
+                self.line("} else {");
+                self.block(next);
+                self.line("}");
+            }
+            None => self.line("}"),
+        }
+    }
+
+    // comments in the source of a statement, excluding strings
+    // and inline blocks
+    fn inner_comments(&self, pair: &Pair<Rule>) -> Vec<String> {
+        let span = pair.as_span();
+        let skip = pair
+            .clone()
+            .into_inner()
+            .flatten()
+            .filter(|v| {
+                matches!(
+                    v.as_rule(),
+                    Rule::string | Rule::byte_str | Rule::ar1cs_source | Rule::asm_source
+                )
+            })
+            .map(|v| v.as_span().start()..v.as_span().end())
+            .collect::<Vec<_>>();
+        let mut out = vec![];
+        let mut offset = span.start();
+        for line in span.as_str().split('\n') {
+            let start = line
+                .char_indices()
+                .find(|(i, c)| *c == '#' && !skip.iter().any(|r| r.contains(&(offset + i))))
+                .map(|(i, _)| i);
+            if let Some(start) = start {
+                out.push(line[start..].trim_end().to_string());
+            }
+            offset += line.len() + 1;
+        }
+        out
+    }
+
+    // a statement without a block, lines after the first are
+    // indented
+    fn stmt(&self, pair: Pair<Rule>) -> String {
+        let rule = pair.as_rule();
+        let text = pair.as_str();
+        let inner = pair.into_inner().collect::<Vec<_>>();
+        match rule {
+            Rule::fn_header => {
+                let mut args = vec![];
+                let mut return_shape = String::new();
+                let mut recursive = String::new();
+                for v in inner {
+                    match v.as_rule() {
+                        Rule::return_shape => {
+                            return_shape = format!(
+                                " -> field{}",
+                                v.into_inner()
+                                    .map(|v| format!("[{}]", v.into_inner().as_str()))
+                                    .collect::<String>()
+                            )
+                        }
+                        Rule::recursive => {
+                            recursive = format!(" recursive {}", v.into_inner().as_str())
+                        }
+                        _ => {
+                            let mut parts = v.into_inner();
+                            let mut arg = parts
+                                .next()
+                                .map(|v| v.as_str())
+                                .unwrap_or_default()
+                                .to_string();
+                            for v in parts {
+                                match v.as_rule() {
+                                    Rule::arg_dim => {
+                                        arg += &format!("[{}]", v.into_inner().as_str())
+                                    }
+                                    _ => arg += &format!(": {}", v.as_str()),
+                                }
+                            }
+                            args.push(arg);
+                        }
+                    }
+                }
+                format!("({}){return_shape}{recursive}", args.join(", "))
+            }
+            Rule::attribute => text.to_string(),
+            Rule::use_stmt => format!("use {}", inner[0].as_str()),
+            Rule::struct_def => {
+                let fields = inner[1..].iter().map(|v| v.as_str().to_string()).collect();
+                format!(
+                    "struct {} {}",
+                    inner[0].as_str(),
+                    self.braced(fields, text.contains('\n'))
+                )
+            }
+            Rule::table_def => format!(
+                "table {} = {}",
+                inner[0].as_str(),
+                self.node(inner[1].clone())
+            ),
+            Rule::return_stmt => format!("return {}", self.node(inner[0].clone())),
+            Rule::pub_input => format!("pub input {}", inner[0].as_str()),
+            Rule::pub_output => format!("pub output {}", inner[0].as_str()),
+            Rule::secret_input => format!("secret input {}", inner[0].as_str()),
+            Rule::assert_stmt => format!("assert({})", self.list(inner)),
+            Rule::lookup_stmt => format!("lookup({})", self.list(inner)),
+            Rule::ar1cs_stmt | Rule::asm_stmt => {
+                let keyword = if rule == Rule::ar1cs_stmt {
+                    "ar1cs"
+                } else {
+                    "asm"
+                };
+                format!(
+                    "{keyword} {} -> {} {}",
+                    self.node(inner[0].clone()),
+                    self.node(inner[1].clone()),
+                    self.verbatim(inner[2].as_str())
+                )
+            }
+            Rule::typed_def => format!(
+                "let {}: {} = {}",
+                inner[1].as_str(),
+                self.node(inner[2].clone()),
+                self.node(inner[3].clone())
+            ),
+            Rule::tuple_def => {
+                let is_let = inner[0].as_rule() == Rule::let_r;
+                let names = inner
+                    .iter()
+                    .filter(|v| v.as_rule() == Rule::varname)
+                    .map(|v| v.as_str())
+                    .collect::<Vec<_>>();
+                format!(
+                    "{}({}) = {}",
+                    if is_let { "let " } else { "" },
+                    names.join(", "),
+                    self.node(inner.last().unwrap().clone())
+                )
+            }
+            Rule::field_assign | Rule::var_index_assign => format!(
+                "{} = {}",
+                self.node(inner[0].clone()),
+                self.node(inner[1].clone())
+            ),
+            Rule::var_def => format!(
+                "{} = {}",
+                self.nodes(inner[0].clone().into_inner(), ""),
+                self.node(inner[1].clone())
+            ),
+            Rule::static_def => format!(
+                "static {} = {}",
+                inner[0].as_str(),
+                self.node(inner[1].clone())
+            ),
+            Rule::var_vec_def => format!("let {}", self.node(inner[1].clone())),
+            Rule::function_call => self.call(inner),
+            _ => text.trim().to_string(),
+        }
+    }
+
+    // an expression or part of a statement
+    fn node(&self, pair: Pair<Rule>) -> String {
+        let rule = pair.as_rule();
+        let text = pair.as_str();
+        match rule {
+            Rule::expr => {
+                // unary minus is written next to its operand
+                let mut out = String::new();
+                let mut prefix = true;
+                for v in pair.into_inner() {
+                    if !prefix {
+                        out.push(' ');
+                    }
+                    prefix = v.as_rule() == Rule::neg;
+                    out.push_str(&self.node(v));
+                }
+                out
+            }
+            Rule::bool_expr | Rule::bool_cmp => self.nodes(pair.into_inner(), " "),
+            Rule::let_r => "let ".to_string(),
+            Rule::atom | Rule::struct_type => self.node(pair.into_inner().next().unwrap()),
+            Rule::var_indexed => {
+                let mut inner = pair.into_inner();
+                let name = inner.next().unwrap().as_str().to_string();
+                inner.fold(name, |acc, v| format!("{acc}[{}]", self.node(v)))
+            }
+            Rule::index_range => self.nodes(pair.into_inner(), ".."),
+            Rule::function_call => self.call(pair.into_inner().collect()),
+            Rule::ref_arg => format!("&{}", self.node(pair.into_inner().next().unwrap())),
+            Rule::tuple | Rule::block_ports => {
+                format!("({})", self.list(pair.into_inner().collect()))
+            }
+            Rule::field_type => {
+                let inner = pair.into_inner();
+                inner.fold("field".to_string(), |acc, v| {
+                    format!("{acc}[{}]", v.as_str())
+                })
+            }
+            Rule::table_range => self.nodes(pair.into_inner(), ".."),
+            Rule::vec => {
+                let multi_line = text.contains('\n');
+                let values = pair.into_inner().map(|v| self.node(v)).collect::<Vec<_>>();
+                if !multi_line {
+                    return format!("[{}]", values.join(", "));
+                }
+                // nested vectors are indented by the caller
+                let pad = Self::pad(self.indent + 1);
+                format!(
+                    "[\n{pad}{}\n{}]",
+                    values
+                        .iter()
+                        .map(|v| v.replace('\n', &format!("\n{INDENT}")))
+                        .collect::<Vec<_>>()
+                        .join(&format!(",\n{pad}")),
+                    Self::pad(self.indent)
+                )
+            }
+            Rule::struct_lit => {
+                let mut inner = pair.into_inner();
+                let name = inner.next().unwrap().as_str().to_string();
+                let fields = inner
+                    .map(|v| self.nodes(v.into_inner(), ": "))
+                    .collect::<Vec<_>>();
+                format!("{name} {}", self.braced(fields, text.contains('\n')))
+            }
+            _ => text.trim().to_string(),
+        }
+    }
+
+    fn nodes(&self, pairs: pest::iterators::Pairs<Rule>, separator: &str) -> String {
+        pairs
+            .map(|v| self.node(v))
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    fn list(&self, pairs: Vec<Pair<Rule>>) -> String {
+        pairs
+            .into_iter()
+            .map(|v| self.node(v))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn call(&self, inner: Vec<Pair<Rule>>) -> String {
+        format!(
+            "{}({})",
+            inner[0].as_str(),
+            self.list(inner[1].clone().into_inner().collect())
+        )
+    }
+
+    // the fields of a struct or struct literal in braces
+    fn braced(&self, fields: Vec<String>, multi_line: bool) -> String {
+        if !multi_line {
+            return format!("{{ {} }}", fields.join(", "));
+        }
+        let pad = Self::pad(self.indent + 1);
+        let mut out = "{\n".to_string();
+        for field in fields {
+            out.push_str(&format!("{pad}{field},\n"));
+        }
+        out.push_str(&Self::pad(self.indent));
+        out.push('}');
+        out
+    }
+
+    // the source of an inline block, re-indented one level
+    // deeper than the statement
+    fn verbatim(&self, source: &str) -> String {
+        if !source.contains('\n') {
+            let source = source.trim();
+            if source.is_empty() {
+                return format!("{{\n{}}}", Self::pad(self.indent));
+            }
+            return format!("{{ {source} }}");
+        }
+        let mut lines = source.split('\n').collect::<Vec<_>>();
+        // text following the opening brace has no indentation
+        let first = lines.remove(0).trim();
+        let common = lines
+            .iter()
+            .filter(|v| !v.trim().is_empty())
+            .map(|v| v.len() - v.trim_start().len())
+            .min()
+            .unwrap_or_default();
+        let pad = Self::pad(self.indent + 1);
+        let mut out = "{\n".to_string();
+        if !first.is_empty() {
+            out.push_str(&format!("{pad}{first}\n"));
+        }
+        let last = lines.len() - 1;
+        for (i, line) in lines.iter().enumerate() {
+            if line.trim().is_empty() {
+                if i != last {
+                    out.push('\n');
+                }
+                continue;
+            }
+            out.push_str(&format!("{pad}{}\n", line[common..].trim_end()));
+        }
+        out.push_str(&Self::pad(self.indent));
+        out.push('}');
+        out
+    }
+}
+
+// the comment in a segment of source between statements
+fn comment(text: &str) -> Option<&str> {
+    text.find('#').map(|i| text[i..].trim_end())
+}
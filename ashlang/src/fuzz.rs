@@ -0,0 +1,223 @@
+//! Compare the semantics of the `tasm` and `r1cs` targets. A
+//! program is compiled to both targets in the oxfoi field and
+//! executed with random inputs, in Triton VM and by building and
+//! checking an `r1cs` witness. Each run must be accepted by both
+//! targets with the same outputs, or rejected by both.
+//!
+//! If the program can be interpreted each run is also run by the
+//! interpreter, the reference semantics of the language, and
+//! both targets must agree with it.
+use std::fmt::Display;
+
+use anyhow::Result;
+use ring_math::PolynomialRingElement;
+
+use crate::cli::Config;
+use crate::compiler::Compiler;
+use crate::provers::TritonVMProver;
+use crate::r1cs::constraint::SymbolicOp;
+use crate::r1cs::parser::R1csParser;
+use crate::r1cs::witness;
+use crate::rings::OxfoiPolynomialRing;
+
+type F = <OxfoiPolynomialRing as PolynomialRingElement>::F;
+
+// the largest element of the oxfoi field, -1
+const MINUS_ONE: &str = "18446744069414584320";
+
+/// The result of fuzzing a program.
+#[derive(Debug)]
+pub struct FuzzReport {
+    pub runs: usize,
+    // runs accepted by both targets with the same outputs
+    pub accepted: usize,
+    // runs rejected by both targets
+    pub rejected: usize,
+    // whether each run was compared with the interpreter
+    pub interpreted: bool,
+    // the first run where the targets disagree
+    pub divergence: Option<Divergence>,
+}
+
+/// A run where the targets disagree. `seed` reproduces the
+/// inputs, e.g. `acc fuzz main --seed <seed> --runs 1`.
+#[derive(Debug)]
+pub struct Divergence {
+    pub seed: u64,
+    pub public_inputs: Vec<String>,
+    pub secret_inputs: Vec<String>,
+    pub tasm: Result<Vec<String>, String>,
+    pub r1cs: Result<Vec<String>, String>,
+    // None if the program cannot be interpreted
+    pub interpreter: Option<Result<Vec<String>, String>>,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let outcome = |v: &Result<Vec<String>, String>| match v {
+            Ok(outputs) => format!("outputs [{}]", outputs.join(", ")),
+            Err(e) => format!("rejected: {e}"),
+        };
+        writeln!(f, "targets diverged with seed {}", self.seed)?;
+        writeln!(f, "public inputs: [{}]", self.public_inputs.join(", "))?;
+        writeln!(f, "secret inputs: [{}]", self.secret_inputs.join(", "))?;
+        writeln!(f, "tasm {}", outcome(&self.tasm))?;
+        write!(f, "r1cs {}", outcome(&self.r1cs))?;
+        if let Some(v) = &self.interpreter {
+            write!(f, "\ninterpreter {}", outcome(v))?;
+        }
+        Ok(())
+    }
+}
+
+/// Run the entry function of a config with random inputs. Run `i`
+/// uses the inputs generated by `seed + i`. Fuzzing stops at the
+/// first divergence.
+pub fn fuzz(config: &Config, runs: usize, seed: u64) -> Result<FuzzReport> {
+    let mut r1cs_config = config.clone();
+    r1cs_config.target = "r1cs".to_string();
+    r1cs_config.extension_priorities = vec!["ash".into(), "ar1cs".into(), "r1cs".into()];
+    let mut tasm_config = config.clone();
+    tasm_config.target = "tasm".to_string();
+    tasm_config.extension_priorities = vec!["ash".into(), "tasm".into()];
+
+    let r1cs = Compiler::<OxfoiPolynomialRing>::new(&r1cs_config)?.compile(&config.entry_fn)?;
+    let asm = Compiler::<OxfoiPolynomialRing>::new(&tasm_config)?.compile(&config.entry_fn)?;
+    // programs calling tasm or ar1cs functions are only compared
+    // between the targets
+    let interpreter = Compiler::<OxfoiPolynomialRing>::new(&tasm_config)?
+        .interpreter(&config.entry_fn)
+        .ok();
+    let parsed: R1csParser<OxfoiPolynomialRing> = R1csParser::new(&r1cs)?;
+    // whether each input is public, in the order they are read
+    let public = parsed
+        .constraints
+        .iter()
+        .filter(|c| c.symbolic)
+        .filter_map(|c| match c.symbolic_op {
+            Some(SymbolicOp::Input) => Some(false),
+            Some(SymbolicOp::PublicInput) => Some(true),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let output_signals = parsed
+        .constraints
+        .iter()
+        .filter(|c| c.symbolic && c.symbolic_op == Some(SymbolicOp::Output))
+        .filter_map(|c| c.out_i)
+        .collect::<Vec<_>>();
+
+    let mut report = FuzzReport {
+        runs: 0,
+        accepted: 0,
+        rejected: 0,
+        interpreted: interpreter.is_some(),
+        divergence: None,
+    };
+    for i in 0..runs {
+        let run_seed = seed.wrapping_add(i as u64);
+        let inputs = random_inputs(public.len(), run_seed);
+        let (public_inputs, secret_inputs) = split_inputs(&inputs, &public);
+        let tasm = TritonVMProver::run(&asm, public_inputs.clone(), secret_inputs.clone())
+            .and_then(|outputs| {
+                outputs
+                    .iter()
+                    .map(
+                        |v| Ok(OxfoiPolynomialRing::from_str(&v.value().to_string())?.to_scalar()?),
+                    )
+                    .collect::<Result<Vec<F>>>()
+            });
+        let r1cs = run_r1cs(&r1cs, &inputs, &output_signals);
+        let interpreted = interpreter.as_ref().map(|v| {
+            let parse = |inputs: &[String]| {
+                inputs
+                    .iter()
+                    .map(|v| OxfoiPolynomialRing::from_str(v))
+                    .collect::<Result<Vec<_>>>()
+            };
+            v.run(parse(&public_inputs)?, parse(&secret_inputs)?)?
+                .iter()
+                .map(|v| v.to_scalar())
+                .collect::<Result<Vec<F>>>()
+        });
+        // the interpreter must agree with both targets
+        let reference_agrees = match (&interpreted, &tasm) {
+            (None, _) | (Some(Err(_)), Err(_)) => true,
+            (Some(Ok(a)), Ok(b)) => a == b,
+            _ => false,
+        };
+        report.runs += 1;
+        match (&tasm, &r1cs) {
+            (Ok(a), Ok(b)) if a == b && reference_agrees => report.accepted += 1,
+            (Err(_), Err(_)) if reference_agrees => report.rejected += 1,
+            _ => {
+                let strings = |v: Result<Vec<F>>| {
+                    v.map(|v| v.iter().map(|v| v.to_string()).collect())
+                        .map_err(|e| e.to_string())
+                };
+                report.divergence = Some(Divergence {
+                    seed: run_seed,
+                    public_inputs,
+                    secret_inputs,
+                    tasm: strings(tasm),
+                    r1cs: strings(r1cs),
+                    interpreter: interpreted.map(strings),
+                });
+                break;
+            }
+        }
+    }
+    Ok(report)
+}
+
+// build and check the witness, returning the value of each
+// output signal
+fn run_r1cs(r1cs: &str, inputs: &[String], output_signals: &[usize]) -> Result<Vec<F>> {
+    let inputs = inputs
+        .iter()
+        .map(|v| OxfoiPolynomialRing::from_str(v))
+        .collect::<Result<Vec<_>>>()?;
+    let witness = witness::build::<OxfoiPolynomialRing>(r1cs, inputs)?;
+    let outputs = output_signals
+        .iter()
+        .map(|i| witness.variables[*i].clone())
+        .collect::<Vec<_>>();
+    witness::verify::<OxfoiPolynomialRing>(r1cs, witness)?;
+    Ok(outputs)
+}
+
+fn split_inputs(inputs: &[String], public: &[bool]) -> (Vec<String>, Vec<String>) {
+    let mut public_inputs = vec![];
+    let mut secret_inputs = vec![];
+    for (v, is_public) in inputs.iter().zip(public) {
+        if *is_public {
+            public_inputs.push(v.clone());
+        } else {
+            secret_inputs.push(v.clone());
+        }
+    }
+    (public_inputs, secret_inputs)
+}
+
+// small values and values near the modulus are more likely to
+// reach edge cases than uniformly random elements
+fn random_inputs(len: usize, seed: u64) -> Vec<String> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| match splitmix64(&mut state) % 8 {
+            0 => "0".to_string(),
+            1 => "1".to_string(),
+            2 => MINUS_ONE.to_string(),
+            3 => (splitmix64(&mut state) % 256).to_string(),
+            _ => (splitmix64(&mut state) as u32).to_string(),
+        })
+        .collect()
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,79 @@
+//! A test runner for ash functions. A test is an ash file whose
+//! name starts with `test_`, e.g. `stdlib/test_poseidon.ash`. Each
+//! test is compiled as an entry function without inputs. In `r1cs`
+//! its witness is built and checked against the constraints, in
+//! `tasm` it is executed in Triton VM.
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+use ring_math::PolynomialRingElement;
+
+use crate::cli::Config;
+use crate::compiler::Compiler;
+use crate::log;
+#[cfg(feature = "tritonvm-prover")]
+use crate::provers::TritonVMProver;
+use crate::r1cs::witness;
+
+/// Functions starting with this prefix are tests.
+pub const TEST_PREFIX: &str = "test_";
+
+/// The outcome of a single test.
+#[derive(Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub duration: Duration,
+    // the reason the test failed
+    pub error: Option<anyhow::Error>,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// The tests in the include paths of a config, optionally only
+/// those whose name contains `filter`.
+pub fn discover<T: PolynomialRingElement>(
+    config: &Config,
+    filter: Option<&str>,
+) -> Result<Vec<String>> {
+    let compiler: Compiler<T> = Compiler::new(config)?;
+    Ok(compiler
+        .fns_with_prefix(TEST_PREFIX)
+        .into_iter()
+        .filter(|name| filter.map(|f| name.contains(f)).unwrap_or(true))
+        .collect())
+}
+
+/// Compile and check a test. Each test is compiled by a new
+/// compiler so tests don't share state.
+pub fn run<T: PolynomialRingElement>(config: &Config, name: &str) -> TestResult {
+    let start = Instant::now();
+    let error = check::<T>(config, name).err();
+    TestResult {
+        name: name.to_string(),
+        duration: start.elapsed(),
+        error,
+    }
+}
+
+fn check<T: PolynomialRingElement>(config: &Config, name: &str) -> Result<()> {
+    let mut compiler: Compiler<T> = Compiler::new(config)?;
+    let compiled = compiler.compile(name)?;
+    match config.target.as_str() {
+        "r1cs" => {
+            let witness = witness::build::<T>(&compiled, vec![])?;
+            witness::verify::<T>(&compiled, witness)?;
+            Ok(())
+        }
+        #[cfg(feature = "tritonvm-prover")]
+        "tasm" => {
+            TritonVMProver::run(&compiled, vec![], vec![])?;
+            Ok(())
+        }
+        _ => log::error!(ConfigError: &format!("Unsupported target for tests: {}", config.target)),
+    }
+}
@@ -0,0 +1,106 @@
+//! Run a program natively without compiling it. The statements
+//! of the entrypoint are evaluated by `ConstEval`, reading
+//! inputs in order and writing each public output, so the
+//! result of a run depends only on the source and the field.
+//!
+//! The interpreter is the reference semantics for testing the
+//! targets, see `fuzz`. Only ash functions, the stdlib io
+//! functions, and the builtins the interpreter computes natively
+//! may be called. Programs calling other `tasm` or `ar1cs`
+//! functions must be compiled to run.
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use ring_math::PolynomialRingElement;
+
+use crate::builtin;
+use crate::compiler::CompilerState;
+use crate::constants;
+use crate::consteval::ConstEval;
+use crate::consteval::Io;
+use crate::log;
+use crate::parser::AshParser;
+use crate::parser::AstNode;
+
+// functions computed natively by the interpreter that are not
+// compiled from ash source
+const NATIVE: &[&str] = &[
+    "assert_eq",
+    "crash",
+    "read_public_input",
+    "read_secret_input",
+    "write_output",
+    builtin::POW,
+    builtin::DOT,
+    builtin::MATMUL,
+    builtin::SUM,
+    builtin::PROD,
+    builtin::MAX,
+];
+
+/// A loaded program that may be run with many inputs.
+pub struct Interpreter<T: PolynomialRingElement> {
+    state: CompilerState<T>,
+    ast: Vec<AstNode>,
+}
+
+impl<T: PolynomialRingElement> Interpreter<T> {
+    /// Create an interpreter for an entrypoint and the functions
+    /// loaded by the compiler. Errors if the program calls a
+    /// function that cannot be interpreted.
+    pub fn new(state: CompilerState<T>, parser: AshParser) -> Result<Self> {
+        let mut calls = parser
+            .fn_names
+            .keys()
+            .chain(state.fn_deps.values().flatten())
+            .filter(|v| *v != constants::FN_NAME)
+            .collect::<Vec<_>>();
+        // sorted so errors are reported in a deterministic order
+        calls.sort();
+        for name in calls {
+            if state.is_fn_ash.get(name) != Some(&true) && !NATIVE.contains(&name.as_str()) {
+                return log::error!(
+                    TypeError: &format!("function \"{name}\" cannot be interpreted"),
+                    "only ash functions, the io functions, assert_eq, crash, and the std arithmetic functions may be called"
+                );
+            }
+        }
+        let mut asts = vec![&parser.ast];
+        asts.extend(state.fn_to_ast.values());
+        if asts.iter().any(|v| inline_block(v)) {
+            return log::error!(
+                TypeError: "inline ar1cs and asm blocks cannot be interpreted",
+                "compile the program to run it"
+            );
+        }
+        Ok(Self {
+            state,
+            ast: parser.ast,
+        })
+    }
+
+    /// Run the program, returning the public outputs in the order
+    /// they are written. A failed assertion is returned as an
+    /// error.
+    pub fn run(&self, public_inputs: Vec<T>, secret_inputs: Vec<T>) -> Result<Vec<T>> {
+        let io = Io {
+            public_inputs: VecDeque::from(public_inputs),
+            secret_inputs: VecDeque::from(secret_inputs),
+            outputs: vec![],
+        };
+        Ok(ConstEval::run(&self.state, &self.ast, io)?.outputs)
+    }
+}
+
+// whether an AST contains an inline ar1cs or asm block,
+// including in nested blocks
+fn inline_block(ast: &[AstNode]) -> bool {
+    ast.iter().any(|v| match v {
+        AstNode::Ar1cs(_, _, _) | AstNode::Asm(_, _, _) => true,
+        AstNode::If(_, if_block, else_block) => inline_block(if_block) || inline_block(else_block),
+        AstNode::Loop(_, body) | AstNode::While(_, body) | AstNode::Unconstrained(_, body) => {
+            inline_block(body)
+        }
+        _ => false,
+    })
+}
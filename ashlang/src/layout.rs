@@ -0,0 +1,191 @@
+//! Target independent lowering shared by the vm of each target.
+//! Values are stored in row major order, a matrix with dimensions
+//! `[2, 3]` is 6 elements with each row of 3 elements stored
+//! after the previous row. Both targets index values, lower
+//! vector literals, and lower products of values using these
+//! functions, so a value has the same layout in memory in `tasm`
+//! and in the witness in `r1cs`.
+use anyhow::Result;
+
+use crate::log;
+use crate::parser::Expr;
+
+/// The number of elements in a value of the given dimensions.
+pub fn len(dimensions: &[usize]) -> usize {
+    dimensions.iter().product()
+}
+
+/// The number of elements between consecutive indices at a
+/// depth, e.g. the length of a row when indexing a matrix at
+/// depth 0.
+pub fn stride(dimensions: &[usize], depth: usize) -> usize {
+    len(&dimensions[(depth + 1).min(dimensions.len())..])
+}
+
+/// The offset of the element, or of the first element of the
+/// slice, at static indices into a value of a variable.
+pub fn offset(name: &str, dimensions: &[usize], indices: &[usize]) -> Result<usize> {
+    if indices.len() > dimensions.len() || indices.iter().zip(dimensions).any(|(i, len)| i >= len) {
+        return log::error!(
+            MemoryError: &format!("index {indices:?} is out of bounds for variable \"{name}\""),
+            &format!("\"{name}\" has dimensions {dimensions:?}")
+        );
+    }
+    Ok(indices
+        .iter()
+        .enumerate()
+        .map(|(depth, i)| i * stride(dimensions, depth))
+        .sum())
+}
+
+/// The offset of the first element and the dimensions of the
+/// elements `start..end` of the outermost dimension of a value
+/// of a variable. The elements of a slice are contiguous.
+pub fn slice(
+    name: &str,
+    dimensions: &[usize],
+    start: usize,
+    end: usize,
+) -> Result<(usize, Vec<usize>)> {
+    let Some(len) = dimensions.first() else {
+        return log::error!(TypeError: &format!("scalar \"{name}\" cannot be sliced"));
+    };
+    if start >= end || end > *len {
+        return log::error!(
+            MemoryError: &format!("range {start}..{end} is out of bounds for variable \"{name}\""),
+            &format!("\"{name}\" has dimensions {dimensions:?}, a range must contain at least one element")
+        );
+    }
+    Ok((
+        start * stride(dimensions, 0),
+        [&[end - start], &dimensions[1..]].concat(),
+    ))
+}
+
+/// The pairs of element offsets multiplied and summed by the dot
+/// product of two values. Both values must have the same
+/// dimensions and the result is a scalar.
+pub fn dot_terms(lhs: &[usize], rhs: &[usize]) -> Result<Vec<(usize, usize)>> {
+    if lhs != rhs {
+        return log::error!(
+            TypeError: &format!("dot product of values with dimensions {lhs:?} and {rhs:?}"),
+            "both arguments of std::dot must have the same dimensions"
+        );
+    }
+    Ok((0..len(lhs)).map(|i| (i, i)).collect())
+}
+
+/// The dimensions of the matrix product `lhs @ rhs`. Each
+/// operand is a vector or a matrix. A vector is a row on the
+/// left and a column on the right, so the product of two vectors
+/// is a scalar.
+pub fn matmul_dimensions(lhs: &[usize], rhs: &[usize]) -> Result<Vec<usize>> {
+    matmul_shape(lhs, rhs)?;
+    // the summed dimension is removed from each operand
+    Ok([&lhs[..lhs.len() - 1], &rhs[1..]].concat())
+}
+
+/// For each element of the matrix product `lhs @ rhs`, the pairs
+/// of element offsets that are multiplied and summed.
+pub fn matmul_terms(lhs: &[usize], rhs: &[usize]) -> Result<Vec<Vec<(usize, usize)>>> {
+    let (rows, inner, columns) = matmul_shape(lhs, rhs)?;
+    Ok((0..rows)
+        .flat_map(|i| {
+            (0..columns).map(move |j| {
+                (0..inner)
+                    .map(|k| (i * inner + k, k * columns + j))
+                    .collect()
+            })
+        })
+        .collect())
+}
+
+// the rows, the length of the summed dimension, and the
+// columns of a matrix product
+fn matmul_shape(lhs: &[usize], rhs: &[usize]) -> Result<(usize, usize, usize)> {
+    for v in [lhs, rhs] {
+        if v.is_empty() || v.len() > 2 {
+            return log::error!(TypeError: &format!(
+                "matrix multiplication operands must be vectors or matrices, received dimensions {v:?}"
+            ));
+        }
+    }
+    let (rows, inner) = match lhs {
+        [k] => (1, *k),
+        _ => (lhs[0], lhs[1]),
+    };
+    if inner != rhs[0] {
+        return log::error!(
+            TypeError: &format!("matrix multiplication of dimensions {lhs:?} and {rhs:?}"),
+            "the columns of the left operand must equal the rows of the right operand"
+        );
+    }
+    Ok((rows, inner, rhs.get(1).copied().unwrap_or(1)))
+}
+
+/// The value of each dimension parameter of a function, e.g. `N`
+/// in `(v[N], w[N])`, given the dimensions of the arguments of a
+/// call. Each argument must have as many dimensions as it
+/// declares, and a dimension declared more than once or as a
+/// literal must have the same length in every argument.
+pub fn dim_params(
+    params: &[(String, Vec<String>)],
+    dimensions: &[Vec<usize>],
+) -> Result<Vec<(String, usize)>> {
+    let mut out: Vec<(String, usize)> = vec![];
+    for ((arg, declared), dimensions) in params.iter().zip(dimensions) {
+        if declared.len() != dimensions.len() {
+            return log::error!(
+                TypeError: &format!(
+                    "argument \"{arg}\" has dimensions {dimensions:?}, expected {} dimensions",
+                    declared.len()
+                ),
+                &format!("the argument is declared as {arg}[{}]", declared.join("]["))
+            );
+        }
+        for (param, len) in declared.iter().zip(dimensions) {
+            let expected = match param.parse::<usize>() {
+                Ok(v) => Some(v),
+                Err(_) => out.iter().find(|(name, _)| name == param).map(|(_, v)| *v),
+            };
+            match expected {
+                Some(v) if v != *len => {
+                    return log::error!(
+                        TypeError: &format!(
+                            "argument \"{arg}\" has dimensions {dimensions:?}, expected length {v} for dimension {param}"
+                        ),
+                        &format!("the argument is declared as {arg}[{}]", declared.join("]["))
+                    );
+                }
+                Some(_) => {}
+                None => out.push((param.clone(), *len)),
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// The dimensions and elements of a vector literal, in storage
+/// order.
+pub fn vec_literal(expr: &Expr) -> (Vec<usize>, Vec<&str>) {
+    let mut dimensions = vec![];
+    let mut root = expr;
+    while let Expr::VecVec(rows) = root {
+        dimensions.push(rows.len());
+        root = &rows[0];
+    }
+    if let Expr::VecLit(values) = root {
+        dimensions.push(values.len());
+    }
+    let mut values = vec![];
+    literals(expr, &mut values);
+    (dimensions, values)
+}
+
+fn literals<'a>(expr: &'a Expr, out: &mut Vec<&'a str>) {
+    match expr {
+        Expr::VecVec(rows) => rows.iter().for_each(|v| literals(v, out)),
+        Expr::VecLit(values) => out.extend(values.iter().map(|v| v.as_str())),
+        _ => unreachable!(),
+    }
+}
@@ -20,13 +20,13 @@
 //!
 //! ashlang is a scripting language for expressing mathematical relations between scalars and vectors in a finite field.
 //!
-//! The language is untyped, with each variable being one of the following:
+//! Variables are untyped by default, with each variable being one of the following:
 //!
 //! - scalar
 //! - vector
 //! - matrix (of any dimension)
 //!
-//! ashlang is designed to be written in conjunction with a lower level language. Each file is a single function, it may be invoked using its filename. Directories are recursively imported and functions become globally available.
+//! ashlang is designed to be written in conjunction with a lower level language. Each file is a single function, it may be invoked using its filename. Directories are recursively imported and functions become globally available. Functions in a directory may also be called using a module path, e.g. `stdlib/crypto/hash.ash` is `hash()` or `stdlib::crypto::hash()`. `use stdlib::crypto` allows calling `crypto::hash()`. If two modules define the same function it must be called using a module path. The `std` module is built into the compiler and is always available, e.g. `std::pow(x, 3)`.
 //!
 //! ### Features
 //!
@@ -36,11 +36,45 @@
 //! - files are not imported, function calls match the filename and tell the compiler what files are needed
 //! - r1cs witnesses can be computed without specialized code
 
+mod builtin;
+/// On-disk cache of compiled functions.
+pub mod cache;
+/// A C API for embedding ashlang in other languages.
+#[cfg(feature = "capi")]
+pub mod capi;
 mod cli;
 pub mod compiler;
+mod constants;
+mod consteval;
+mod cse;
+/// Write the intermediate artifacts of each stage of compilation.
+pub mod emit;
+/// Structured errors returned by the compiler and provers.
+pub mod error;
+/// Compare the static values of a program between fields.
+pub mod fieldcheck;
+/// A canonical formatter for ash source.
+pub mod fmt;
+/// Compare the tasm and r1cs targets using random inputs.
+#[cfg(feature = "tritonvm-prover")]
+pub mod fuzz;
+/// Discover and run test functions.
+pub mod harness;
+/// Run programs natively without compiling or proving them.
+pub mod interpreter;
+mod layout;
+/// Compile several entrypoints into a library of circuits.
+pub mod library;
 pub mod log;
+/// Project manifests and dependency resolution.
+#[cfg(feature = "manifest")]
+pub mod manifest;
 /// Ashlang source code parser.
 pub mod parser;
+/// Attribute compiled output to the source lines that produced it.
+pub mod profile;
+/// Compile, inspect, and prove programs.
+pub mod program;
 mod provers;
 /// Core logic for the r1cs target.
 pub mod r1cs;
@@ -48,14 +82,40 @@ pub mod r1cs;
 pub mod rings;
 /// Core logic for the tasm target.
 pub mod tasm;
-mod time;
+mod typecheck;
+/// Bindings for compiling and building witnesses in a browser.
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use cli::Config;
+pub use error::AshlangError;
+pub use program::Program;
+pub use program::ProgramStats;
 
 // Expose provers at the top level export here
 // e.g. use ashlang::SpartanProver;
 pub use provers::AshlangProver;
+#[cfg(feature = "serde")]
+pub use provers::EnvelopeProof;
+#[cfg(feature = "tritonvm-prover")]
+pub use provers::ExecutionReport;
+#[cfg(feature = "groth16-prover")]
+pub use provers::Groth16Proof;
+#[cfg(feature = "groth16-prover")]
+pub use provers::Groth16Prover;
+#[cfg(feature = "halo2-prover")]
+pub use provers::Halo2Proof;
+#[cfg(feature = "halo2-prover")]
+pub use provers::Halo2Prover;
+#[cfg(feature = "halo2-prover")]
+pub use provers::Halo2VerifyingKey;
+#[cfg(feature = "serde")]
+pub use provers::ProofEnvelope;
+#[cfg(feature = "spartan-prover")]
+pub use provers::SpartanProof;
 #[cfg(feature = "spartan-prover")]
 pub use provers::SpartanProver;
+#[cfg(feature = "spartan-prover")]
+pub use provers::SpartanReport;
 #[cfg(feature = "tritonvm-prover")]
 pub use provers::TritonVMProver;
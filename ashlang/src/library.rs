@@ -0,0 +1,80 @@
+//! Libraries of circuits. A project often proves several related
+//! programs, e.g. `deposit` and `withdraw`. Compiling them with a
+//! single compiler loads and checks each shared function once and,
+//! in the tasm target, compiles each specialization of a function
+//! once.
+//!
+//! A library is written as one file per entrypoint and a
+//! `library.json` manifest listing the fingerprint of each
+//! entrypoint, so a verifier can check which program of the
+//! library a proof belongs to.
+#[cfg(feature = "serde")]
+use std::fs;
+
+#[cfg(feature = "serde")]
+use anyhow::Result;
+#[cfg(feature = "serde")]
+use camino::Utf8Path;
+
+use crate::cache::FnCache;
+
+/// The name of the manifest written to a library directory.
+pub const LIBRARY_FILE: &str = "library.json";
+
+/// A compiled entrypoint of a library.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Circuit {
+    pub entry_fn: String,
+    // the file the output is written to, relative to the library
+    pub file: String,
+    pub fingerprint: String,
+    // the compiled ar1cs or tasm
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub output: String,
+}
+
+/// The entrypoints compiled by `Compiler::compile_library`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Library {
+    pub version: String,
+    pub target: String,
+    pub field: String,
+    // a hash of the fingerprint of every circuit
+    pub fingerprint: String,
+    pub circuits: Vec<Circuit>,
+}
+
+impl Library {
+    pub fn new(target: &str, field: &str, circuits: Vec<Circuit>) -> Self {
+        let mut parts = vec![target, field];
+        parts.extend(circuits.iter().map(|v| v.fingerprint.as_str()));
+        Library {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            target: target.to_string(),
+            field: field.to_string(),
+            fingerprint: FnCache::key(&parts),
+            circuits,
+        }
+    }
+
+    /// The file extension of the output of a target.
+    pub fn extension(target: &str) -> &'static str {
+        match target {
+            "tasm" => "tasm",
+            _ => "ar1cs",
+        }
+    }
+
+    /// Write each circuit and the library manifest to a directory.
+    #[cfg(feature = "serde")]
+    pub fn write(&self, dir: &Utf8Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        for circuit in &self.circuits {
+            fs::write(dir.join(&circuit.file), &circuit.output)?;
+        }
+        fs::write(dir.join(LIBRARY_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -1,35 +1,51 @@
-use colored::Colorize;
+use crate::error::AshlangError;
+use crate::error::ErrorInfo;
+use crate::parser::SourceLocation;
 
+/// Return an `AshlangError` of the given kind, defaulting to
+/// `CompileError`, e.g. `log::error!(ScopeError: "msg", "hint")`.
 macro_rules! error {
+    ($kind:ident: $msg:expr) => {
+        Err(anyhow::Error::new(crate::error::AshlangError::$kind(
+            crate::error::ErrorInfo::new($msg, None),
+        )))
+    };
+    ($kind:ident: $msg:expr, $details:expr) => {
+        Err(anyhow::Error::new(crate::error::AshlangError::$kind(
+            crate::error::ErrorInfo::new($msg, Some($details)),
+        )))
+    };
     ($msg:expr) => {
-        Err(anyhow::anyhow!(crate::log::compile_error($msg, None)))
+        crate::log::error!(CompileError: $msg)
     };
     ($msg:expr, $details:expr) => {
-        Err(anyhow::anyhow!(crate::log::compile_error(
-            $msg,
-            Some($details)
-        )))
+        crate::log::error!(CompileError: $msg, $details)
     };
 }
 pub(crate) use error;
 
-// compiler errors always halt the program
-pub fn compile_error(msg: &str, details: Option<&str>) -> String {
-    let mut out_strs = vec![];
-    out_strs.push(format!("{}", "Compile error".red().bold()));
-    out_strs.push(msg.to_string());
-    if let Some(details) = details {
-        out_strs.push(format!("{}", "Explanation".green().bold()));
-        out_strs.push(details.to_string());
+/// Attach a source location to an error. Errors that already
+/// have a location are returned unchanged so the innermost
+/// statement is reported.
+pub fn locate(e: anyhow::Error, location: &Option<SourceLocation>) -> anyhow::Error {
+    let location = match location {
+        Some(location) => location,
+        None => return e,
+    };
+    match e.downcast::<AshlangError>() {
+        Ok(e) if e.location().is_some() => anyhow::Error::new(e),
+        Ok(e) => anyhow::Error::new(e.with_location(location.clone())),
+        Err(e) => {
+            let mut info = ErrorInfo::new(&e.to_string(), None);
+            info.location = Some(location.clone());
+            anyhow::Error::new(AshlangError::CompileError(info))
+        }
     }
-    out_strs.join("\n")
 }
 
-pub fn parse_error<T: pest::RuleType>(err: pest::error::Error<T>, filename: &str) -> String {
-    let mut out_strs = vec![];
-    out_strs.push(format!("{}", "Parse error".red().bold()));
-    out_strs.push(format!("In function {filename}"));
-    out_strs.push("".to_string());
-    out_strs.push(format!("{err}"));
-    out_strs.join("\n")
+pub fn parse_error<T: pest::RuleType>(err: pest::error::Error<T>, filename: &str) -> anyhow::Error {
+    anyhow::Error::new(AshlangError::ParseError(ErrorInfo::new(
+        &format!("In function {filename}\n\n{err}"),
+        None,
+    )))
 }
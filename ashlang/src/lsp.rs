@@ -0,0 +1,547 @@
+//! A language server for ash files. Diagnostics are reported
+//! from the parser, the type checker, and function resolution.
+//! Go to definition and hover resolve functions the same way as
+//! the compiler, using the include paths and workspace folders.
+use std::collections::HashMap;
+use std::fs;
+
+use anyhow::Result;
+use camino::Utf8PathBuf;
+use lsp_server::Connection;
+use lsp_server::ErrorCode;
+use lsp_server::Message;
+use lsp_server::Notification;
+use lsp_server::Request;
+use lsp_server::Response;
+use lsp_types::notification::DidChangeTextDocument;
+use lsp_types::notification::DidCloseTextDocument;
+use lsp_types::notification::DidOpenTextDocument;
+use lsp_types::notification::Notification as _;
+use lsp_types::notification::PublishDiagnostics;
+use lsp_types::request::GotoDefinition;
+use lsp_types::request::HoverRequest;
+use lsp_types::request::Request as _;
+use lsp_types::Diagnostic;
+use lsp_types::DiagnosticSeverity;
+use lsp_types::GotoDefinitionParams;
+use lsp_types::GotoDefinitionResponse;
+use lsp_types::Hover;
+use lsp_types::HoverContents;
+use lsp_types::HoverParams;
+use lsp_types::HoverProviderCapability;
+use lsp_types::InitializeParams;
+use lsp_types::Location;
+use lsp_types::MarkupContent;
+use lsp_types::MarkupKind;
+use lsp_types::OneOf;
+use lsp_types::Position;
+use lsp_types::PublishDiagnosticsParams;
+use lsp_types::Range;
+use lsp_types::ServerCapabilities;
+use lsp_types::TextDocumentSyncCapability;
+use lsp_types::TextDocumentSyncKind;
+use lsp_types::Url;
+use pest::Parser;
+
+use crate::builtin;
+use crate::cli::Config;
+use crate::compiler::Compiler;
+use crate::constants;
+use crate::error::AshlangError;
+use crate::log;
+use crate::parser::AshParser;
+use crate::parser::AshPestParser;
+use crate::parser::AstNode;
+use crate::parser::Expr;
+use crate::parser::Rule;
+use crate::parser::SourceLocation;
+use crate::rings::OxfoiPolynomialRing;
+use crate::typecheck;
+use crate::typecheck::VarInfo;
+
+/// Run a language server over stdio until the client shuts it
+/// down. Functions are resolved in the include paths and the
+/// workspace folders of the client.
+pub fn run(include_paths: Vec<Utf8PathBuf>) -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        ..Default::default()
+    };
+    let params = connection.initialize(serde_json::to_value(capabilities)?)?;
+    let params: InitializeParams = serde_json::from_value(params)?;
+    let mut server = Server {
+        include_paths,
+        documents: HashMap::new(),
+    };
+    for folder in params.workspace_folders.unwrap_or_default() {
+        if let Some(path) = file_path(&folder.uri) {
+            server.include_paths.push(path);
+        }
+    }
+    server.serve(&connection)?;
+    io_threads.join()?;
+    Ok(())
+}
+
+struct Server {
+    include_paths: Vec<Utf8PathBuf>,
+    // the text of each open document
+    documents: HashMap<Url, String>,
+}
+
+impl Server {
+    fn serve(&mut self, connection: &Connection) -> Result<()> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.respond(request);
+                    connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(notification) => {
+                    if let Some(uri) = self.notify(notification)? {
+                        let params = PublishDiagnosticsParams {
+                            diagnostics: self.diagnostics(&uri),
+                            uri,
+                            version: None,
+                        };
+                        connection
+                            .sender
+                            .send(Message::Notification(Notification::new(
+                                PublishDiagnostics::METHOD.to_string(),
+                                params,
+                            )))?;
+                    }
+                }
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    // update the open documents, returning the document that
+    // should be checked
+    fn notify(&mut self, notification: Notification) -> Result<Option<Url>> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.clone(), params.text_document.text);
+                Ok(Some(uri))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                // documents are synced in full
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(uri.clone(), change.text);
+                }
+                Ok(Some(uri))
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn respond(&self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            GotoDefinition::METHOD => serde_json::from_value(request.params)
+                .map_err(anyhow::Error::from)
+                .and_then(|params| self.definition(params))
+                .and_then(|v| Ok(serde_json::to_value(v)?)),
+            HoverRequest::METHOD => serde_json::from_value(request.params)
+                .map_err(anyhow::Error::from)
+                .and_then(|params| self.hover(params))
+                .and_then(|v| Ok(serde_json::to_value(v)?)),
+            _ => {
+                return Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("unsupported request: {}", request.method),
+                )
+            }
+        };
+        match result {
+            Ok(result) => Response::new_ok(id, result),
+            Err(e) => Response::new_err(id, ErrorCode::InternalError as i32, e.to_string()),
+        }
+    }
+
+    // a compiler that resolves functions in the include paths,
+    // each extension is included so any function can be found
+    fn compiler(&self) -> Result<Compiler<OxfoiPolynomialRing>> {
+        let config = Config {
+            include_paths: self.include_paths.clone(),
+            verbosity: 0,
+            inputs: vec![],
+            secret_inputs: vec![],
+            target: "r1cs".to_string(),
+            extension_priorities: ["ash", "tasm", "ar1cs", "r1cs"]
+                .iter()
+                .map(|v| v.to_string())
+                .collect(),
+            entry_fn: String::new(),
+            field: "oxfoi".to_string(),
+            eliminate_dead_witness: true,
+            circom_out: None,
+            binary_out: None,
+            cache_dir: None,
+            report_memory: false,
+            profile: false,
+            witness_trace: None,
+            stream_witness: None,
+            jobs: None,
+            proof_out: None,
+            key_dir: None,
+            solidity_out: None,
+            fold: None,
+            unroll_limit: None,
+            checked_indexing: false,
+            trace_only: None,
+            symbols: false,
+            emit: vec![],
+            emit_dir: None,
+        };
+        Compiler::new(&config)
+    }
+
+    fn parse(&self, uri: &Url) -> Result<AshParser> {
+        let text = self.documents.get(uri).cloned().unwrap_or_default();
+        let path = file_path(uri).unwrap_or_default();
+        let name = path.file_stem().unwrap_or_default();
+        AshParser::parse_file(&text, name, path.as_str())
+    }
+
+    fn diagnostics(&self, uri: &Url) -> Vec<Diagnostic> {
+        let text = match self.documents.get(uri) {
+            Some(text) => text,
+            None => return vec![],
+        };
+        let mut parser = match self.parse(uri) {
+            Ok(parser) => parser,
+            Err(e) => return vec![parse_diagnostic(text, e)],
+        };
+        // program constants are in scope in every function
+        let declarations = self
+            .compiler()
+            .and_then(|v| v.constants_source())
+            .ok()
+            .flatten()
+            .and_then(|(_, v)| constants::declarations(&v.ast).ok())
+            .unwrap_or_default();
+        let values = declarations
+            .into_iter()
+            .map(|v| (v.name, v.expr))
+            .collect::<Vec<_>>();
+        constants::declare(&mut parser.ast, &values);
+        let (errors, _) = typecheck::check_fn(&parser.ast);
+        let mut out = errors.into_iter().map(diagnostic).collect::<Vec<_>>();
+        // functions that can't be resolved are reported at the
+        // statement calling them
+        let compiler = match self.compiler() {
+            Ok(compiler) => compiler,
+            Err(e) => {
+                out.push(diagnostic(e));
+                return out;
+            }
+        };
+        let mut calls = vec![];
+        fn_calls(&parser.ast, &mut None, &mut calls);
+        for (name, location) in calls {
+            if builtin::is_precompile(&name) {
+                continue;
+            }
+            if let Err(e) = compiler.fn_path(&name) {
+                out.push(diagnostic(log::locate(e, &location)));
+            }
+        }
+        out
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Result<Option<GotoDefinitionResponse>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let word = match self.word_at(&uri, position) {
+            Some(word) => word,
+            None => return Ok(None),
+        };
+        let parser = self.parse(&uri)?;
+        let (_, vars) = typecheck::check_fn(&parser.ast);
+        if let Some(v) = vars.iter().find(|v| v.name == word) {
+            return Ok(v.location.as_ref().map(|l| {
+                GotoDefinitionResponse::Scalar(Location {
+                    uri: uri.clone(),
+                    range: range(l),
+                })
+            }));
+        }
+        let path = match self.compiler()?.fn_path(&parser.resolve_fn_name(&word)) {
+            Ok(path) => path.clone(),
+            Err(_) => return Ok(None),
+        };
+        let uri = match path
+            .canonicalize_utf8()
+            .ok()
+            .and_then(|v| Url::from_file_path(v).ok())
+        {
+            Some(uri) => uri,
+            // builtin functions do not exist on disk
+            None => return Ok(None),
+        };
+        Ok(Some(GotoDefinitionResponse::Scalar(Location {
+            uri,
+            range: Range::default(),
+        })))
+    }
+
+    fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let word = match self.word_at(&uri, position) {
+            Some(word) => word,
+            None => return Ok(None),
+        };
+        let parser = self.parse(&uri)?;
+        let (_, vars) = typecheck::check_fn(&parser.ast);
+        let value = if let Some(v) = vars.iter().find(|v| v.name == word) {
+            var_hover(v)
+        } else {
+            let name = parser.resolve_fn_name(&word);
+            match self.compiler()?.fn_path(&name) {
+                Ok(path) => fn_hover(&name, path),
+                Err(_) => return Ok(None),
+            }
+        };
+        Ok(Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: None,
+        }))
+    }
+
+    // the variable or function name at a position, e.g.
+    // `stdlib::crypto::hash`
+    fn word_at(&self, uri: &Url, position: Position) -> Option<String> {
+        let line = self
+            .documents
+            .get(uri)?
+            .lines()
+            .nth(position.line as usize)?
+            .chars()
+            .collect::<Vec<_>>();
+        let is_word = |c: &char| c.is_alphanumeric() || *c == '_' || *c == ':';
+        let col = (position.character as usize).min(line.len());
+        let start = line[..col]
+            .iter()
+            .rposition(|c| !is_word(c))
+            .map(|v| v + 1)
+            .unwrap_or(0);
+        let end = line[col..]
+            .iter()
+            .position(|c| !is_word(c))
+            .map(|v| v + col)
+            .unwrap_or(line.len());
+        let word = line[start..end]
+            .iter()
+            .collect::<String>()
+            .trim_matches(':')
+            .to_string();
+        if word.is_empty() {
+            None
+        } else {
+            Some(word)
+        }
+    }
+}
+
+fn file_path(uri: &Url) -> Option<Utf8PathBuf> {
+    Utf8PathBuf::from_path_buf(uri.to_file_path().ok()?).ok()
+}
+
+// the functions called in each statement, with the location of
+// the statement
+fn fn_calls(
+    ast: &[AstNode],
+    location: &mut Option<SourceLocation>,
+    out: &mut Vec<(String, Option<SourceLocation>)>,
+) {
+    for node in ast {
+        let mut calls = vec![];
+        match node {
+            AstNode::Location(l) => *location = Some(l.clone()),
+            AstNode::If(expr, if_block, else_block) => {
+                expr_calls(expr, &mut calls);
+                add_calls(calls, location, out);
+                fn_calls(if_block, location, out);
+                fn_calls(else_block, location, out);
+                continue;
+            }
+            AstNode::Loop(expr, body) | AstNode::While(expr, body) => {
+                expr_calls(expr, &mut calls);
+                add_calls(calls, location, out);
+                fn_calls(body, location, out);
+                continue;
+            }
+            AstNode::Unconstrained(_, body) => {
+                fn_calls(body, location, out);
+                continue;
+            }
+            AstNode::Stmt(_, _, expr)
+            | AstNode::ExprUnassigned(expr)
+            | AstNode::Rtrn(expr)
+            | AstNode::Assert(expr, _)
+            | AstNode::StaticDef(_, expr)
+            | AstNode::TypedDef(_, _, expr) => expr_calls(expr, &mut calls),
+            AstNode::AssignVec(_, indices, expr) => {
+                for i in indices {
+                    expr_calls(i, &mut calls);
+                }
+                expr_calls(expr, &mut calls);
+            }
+            AstNode::Lookup(_, entries) => {
+                for expr in entries {
+                    expr_calls(expr, &mut calls);
+                }
+            }
+            AstNode::FnVar(_)
+            | AstNode::FnDims(_)
+            | AstNode::EmptyVecDef(_, _)
+            | AstNode::Ar1cs(_, _, _)
+            | AstNode::Asm(_, _, _)
+            | AstNode::PubInput(_)
+            | AstNode::PubOutput(_)
+            | AstNode::SecretInput(_)
+            | AstNode::Inline(_) => {}
+        }
+        add_calls(calls, location, out);
+    }
+}
+
+fn add_calls(
+    calls: Vec<String>,
+    location: &Option<SourceLocation>,
+    out: &mut Vec<(String, Option<SourceLocation>)>,
+) {
+    out.extend(calls.into_iter().map(|v| (v, location.clone())));
+}
+
+fn expr_calls(expr: &Expr, out: &mut Vec<String>) {
+    match expr {
+        Expr::FnCall(name, args) => {
+            out.push(name.clone());
+            for arg in args {
+                expr_calls(arg, out);
+            }
+        }
+        Expr::Val(_, indices) | Expr::Ref(_, indices) => {
+            for i in indices {
+                expr_calls(i, out);
+            }
+        }
+        Expr::NumOp { lhs, rhs, .. }
+        | Expr::BoolOp { lhs, rhs, .. }
+        | Expr::BitOp { lhs, rhs, .. } => {
+            expr_calls(lhs, out);
+            expr_calls(rhs, out);
+        }
+        Expr::VecVec(_) | Expr::VecLit(_) | Expr::Lit(_) => {}
+    }
+}
+
+fn var_hover(v: &VarInfo) -> String {
+    let kind = match v.is_static {
+        Some(true) => "static",
+        _ => "let",
+    };
+    format!("```ash\n{kind} {}: {}\n```", v.name, v.type_name)
+}
+
+// the arguments of ash functions are shown, other functions
+// are described by their path
+fn fn_hover(name: &str, path: &Utf8PathBuf) -> String {
+    let source = builtin::source(path)
+        .map(|v| v.to_string())
+        .or_else(|| fs::read_to_string(path).ok());
+    let args = source
+        .filter(|_| path.extension() == Some("ash"))
+        .and_then(|v| AshParser::parse(&v, name).ok())
+        .and_then(|parser| {
+            parser.ast.into_iter().find_map(|node| match node {
+                AstNode::FnVar(args) => Some(args),
+                _ => None,
+            })
+        });
+    match args {
+        Some(args) => format!("```ash\n{name}({})\n```\n{path}", args.join(", ")),
+        None => format!("`{name}`\n\n{path}"),
+    }
+}
+
+// LSP positions are zero indexed, source locations start at 1
+fn range(location: &SourceLocation) -> Range {
+    let position = Position::new(
+        location.line.saturating_sub(1) as u32,
+        location.col.saturating_sub(1) as u32,
+    );
+    Range::new(position, position)
+}
+
+fn diagnostic(e: anyhow::Error) -> Diagnostic {
+    let (message, location) = match e.downcast_ref::<AshlangError>() {
+        Some(e) => {
+            let message = match e.hint() {
+                Some(hint) => format!("{}\n{hint}", e.message()),
+                None => e.message().to_string(),
+            };
+            (message, e.location().cloned())
+        }
+        None => (e.to_string(), None),
+    };
+    let range = match &location {
+        Some(location) => {
+            let start = range(location).start;
+            let end = Position::new(start.line, location.snippet.chars().count() as u32);
+            Range::new(start, end)
+        }
+        None => Range::default(),
+    };
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some("ashlang".to_string()),
+        message,
+        ..Default::default()
+    }
+}
+
+// parse errors do not have a location, the grammar is run
+// again to find the position of the error
+fn parse_diagnostic(text: &str, e: anyhow::Error) -> Diagnostic {
+    let mut out = diagnostic(e);
+    if let Err(e) = AshPestParser::parse(Rule::program, &format!("{text}\n")) {
+        let (line, col) = match e.line_col {
+            pest::error::LineColLocation::Pos(v) => v,
+            pest::error::LineColLocation::Span(v, _) => v,
+        };
+        let position = Position::new(line.saturating_sub(1) as u32, col.saturating_sub(1) as u32);
+        out.range = Range::new(position, position);
+        out.message = e.variant.message().to_string();
+    }
+    out
+}
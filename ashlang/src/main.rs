@@ -1,42 +1,344 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Cursor;
+use std::time::Instant;
+
 use anyhow::Result;
 use ashlang::rings::Curve25519PolynomialRing;
+use cache::FnCache;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
 use cli::Config;
 use compiler::Compiler;
+use emit::Emitter;
+use manifest::Manifest;
+use r1cs::ar1cs::Ar1cs;
+use r1cs::circom;
+use r1cs::encoding;
+use r1cs::parser::R1csParser;
 use r1cs::witness;
+use r1cs::witness::WitnessTrace;
 use ring_math::PolynomialRingElement;
 use scalarff::Curve25519FieldElement;
 use scalarff::FieldElement;
 
 use crate::provers::AshlangProver;
+use crate::provers::EnvelopeProof;
+use crate::provers::ProofEnvelope;
 use crate::rings::Bn128PolynomialRing;
+use crate::rings::CustomPolynomialRing;
 use crate::rings::DilithiumPolynomialRingElement;
 use crate::rings::OxfoiPolynomialRing;
 
+mod builtin;
+mod cache;
 mod cli;
 mod compiler;
+mod constants;
+mod consteval;
+mod cse;
+mod emit;
+mod error;
+mod fieldcheck;
+mod fmt;
+mod fuzz;
+mod harness;
+mod interpreter;
+mod layout;
+mod library;
 mod log;
+mod lsp;
+mod manifest;
 mod parser;
+mod profile;
 mod provers;
 mod r1cs;
 mod rings;
 mod tasm;
-mod time;
-
-fn main() -> Result<()> {
-    let mut config = cli::parse()?;
-    return match config.target.as_str() {
-        "tasm" => match provers::TritonVMProver::prove(&config) {
-            Ok((_stark, _claim, _proof)) => {
-                println!("{:?}", _stark);
-                println!("{:?}", _claim);
-                Ok(())
+mod typecheck;
+
+// errors are returned to here and printed once, the commands
+// and the library never exit the process
+fn main() {
+    if let Err(e) = cli_main() {
+        eprintln!("{e:#}");
+        std::process::exit(1);
+    }
+}
+
+fn cli_main() -> Result<()> {
+    let matches = cli::matches();
+    if let Some(("lsp", matches)) = matches.subcommand() {
+        return lsp::run(cli::include_paths(matches));
+    }
+    if let Some(("fmt", matches)) = matches.subcommand() {
+        let (paths, check) = cli::fmt_args(matches);
+        return format_paths(&paths, check);
+    }
+    if let Some(("init", matches)) = matches.subcommand() {
+        return init(&cli::project_dir(matches));
+    }
+    if let Some(("build", matches)) = matches.subcommand() {
+        let manifest = Manifest::load(&cli::project_dir(matches))?;
+        let mut config = manifest.config()?;
+        cli::parse_build(matches, &mut config);
+        return run(config);
+    }
+    if let Some(("test", matches)) = matches.subcommand() {
+        let project = match Utf8Path::new(manifest::MANIFEST_FILE).exists() {
+            true => Some(Manifest::load(Utf8Path::new("."))?.config()?),
+            false => None,
+        };
+        let (config, filter) = cli::parse_test(matches, project)?;
+        return match (config.target.as_str(), config.field.as_str()) {
+            ("tasm", "foi" | "oxfoi") | ("r1cs", "foi" | "oxfoi") => {
+                run_tests::<OxfoiPolynomialRing>(&config, filter)
             }
-            Err(e) => {
-                println!("Triton VM errored");
-                println!("{e}");
-                std::process::exit(1);
+            ("r1cs", "curve25519") => run_tests::<Curve25519PolynomialRing>(&config, filter),
+            ("r1cs", "alt_bn128") => run_tests::<Bn128PolynomialRing>(&config, filter),
+            ("r1cs", "dilithium") => run_tests::<DilithiumPolynomialRingElement>(&config, filter),
+            ("r1cs", field) if field.starts_with(rings::CUSTOM_FIELD_PREFIX) => {
+                rings::init_custom_field(field)?;
+                run_tests::<CustomPolynomialRing>(&config, filter)
             }
-        },
+            _ => log::error!(ConfigError: &format!(
+                "Unsupported field for target {}: {}",
+                config.target, config.field
+            )),
+        };
+    }
+    if let Some(("lib", matches)) = matches.subcommand() {
+        let project = match Utf8Path::new(manifest::MANIFEST_FILE).exists() {
+            true => {
+                let manifest = Manifest::load(Utf8Path::new("."))?;
+                Some((manifest.config()?, manifest.entries()))
+            }
+            false => None,
+        };
+        let (config, entry_fns, out) = cli::parse_lib(matches, project)?;
+        return match (config.target.as_str(), config.field.as_str()) {
+            ("tasm", "foi" | "oxfoi") | ("r1cs", "foi" | "oxfoi") => {
+                build_library::<OxfoiPolynomialRing>(&config, &entry_fns, &out)
+            }
+            ("r1cs", "curve25519") => {
+                build_library::<Curve25519PolynomialRing>(&config, &entry_fns, &out)
+            }
+            ("r1cs", "alt_bn128") => {
+                build_library::<Bn128PolynomialRing>(&config, &entry_fns, &out)
+            }
+            ("r1cs", "dilithium") => {
+                build_library::<DilithiumPolynomialRingElement>(&config, &entry_fns, &out)
+            }
+            ("r1cs", field) if field.starts_with(rings::CUSTOM_FIELD_PREFIX) => {
+                rings::init_custom_field(field)?;
+                build_library::<CustomPolynomialRing>(&config, &entry_fns, &out)
+            }
+            _ => log::error!(ConfigError: &format!(
+                "Unsupported field for target {}: {}",
+                config.target, config.field
+            )),
+        };
+    }
+    if let Some(("verify", matches)) = matches.subcommand() {
+        let (config, path, fingerprint, key_dir) = cli::parse_verify(matches);
+        return verify(config, &path, fingerprint, key_dir.map(FnCache::new));
+    }
+    if let Some(("fuzz", matches)) = matches.subcommand() {
+        let (config, runs, seed) = cli::parse_fuzz(matches)?;
+        return fuzz(&config, runs, seed);
+    }
+    if let Some(("run", matches)) = matches.subcommand() {
+        let config = cli::parse_run(matches);
+        return match config.field.as_str() {
+            "foi" | "oxfoi" => interpret::<OxfoiPolynomialRing>(&config),
+            "curve25519" => interpret::<Curve25519PolynomialRing>(&config),
+            "alt_bn128" => interpret::<Bn128PolynomialRing>(&config),
+            "dilithium" => interpret::<DilithiumPolynomialRingElement>(&config),
+            field if field.starts_with(rings::CUSTOM_FIELD_PREFIX) => {
+                rings::init_custom_field(field)?;
+                interpret::<CustomPolynomialRing>(&config)
+            }
+            _ => log::error!(ConfigError: &format!("Unsupported field: {}", config.field)),
+        };
+    }
+    if let Some(("fields", matches)) = matches.subcommand() {
+        let (config, fields) = cli::parse_fields(matches);
+        return check_fields(&config, &fields);
+    }
+    run(cli::parse(&matches)?)
+}
+
+fn check_fields(config: &Config, fields: &[String]) -> Result<()> {
+    println!("comparing {} in {}", config.entry_fn, fields.join(", "));
+    let report = fieldcheck::check(config, fields)?;
+    for (field, e) in &report.errors {
+        println!("🟡 {} does not compile in {field}: {e}", config.entry_fn);
+    }
+    for difference in &report.differences {
+        println!("🔴 {difference}");
+    }
+    if !report.differences.is_empty() {
+        return log::error!(CompileError: &format!(
+            "{} static values differ between fields",
+            report.differences.len()
+        ));
+    }
+    println!(
+        "✅ {} static values are the same in every field",
+        report.compared
+    );
+    Ok(())
+}
+
+fn fuzz(config: &Config, runs: usize, seed: u64) -> Result<()> {
+    println!("fuzzing {} with seed {seed}", config.entry_fn);
+    let report = fuzz::fuzz(config, runs, seed)?;
+    if let Some(divergence) = &report.divergence {
+        println!("🔴 {divergence}");
+        return log::error!(
+            CompileError: "tasm and r1cs diverge",
+            &format!("reproduce using --seed {} --runs 1", divergence.seed)
+        );
+    }
+    let agree = match report.interpreted {
+        true => "tasm, r1cs, and the interpreter agree",
+        false => "tasm and r1cs agree",
+    };
+    println!(
+        "✅ {agree} in {} runs: {} accepted, {} rejected by both targets",
+        report.runs, report.accepted, report.rejected
+    );
+    Ok(())
+}
+
+// run the entry function natively and print the outputs, a
+// failed assertion is returned as an error
+fn interpret<T: PolynomialRingElement>(config: &Config) -> Result<()> {
+    let parse = |inputs: &[String]| {
+        inputs
+            .iter()
+            .map(|v| T::from_str(v))
+            .collect::<Result<Vec<_>>>()
+    };
+    let interpreter = Compiler::<T>::new(config)?.interpreter(&config.entry_fn)?;
+    let outputs = interpreter.run(parse(&config.inputs)?, parse(&config.secret_inputs)?)?;
+    print_outputs(
+        &outputs
+            .iter()
+            .map(|v| Ok(v.to_scalar()?.to_string()))
+            .collect::<Result<Vec<_>>>()?,
+    );
+    Ok(())
+}
+
+// run each test and print the result as it finishes
+fn run_tests<T: PolynomialRingElement>(config: &Config, filter: Option<String>) -> Result<()> {
+    let tests = harness::discover::<T>(config, filter.as_deref())?;
+    println!("running {} tests", tests.len());
+    let start = Instant::now();
+    let mut failed = vec![];
+    for name in &tests {
+        let result = harness::run::<T>(config, name);
+        if result.passed() {
+            println!("test {name} ... ok ({:?})", result.duration);
+        } else {
+            println!("test {name} ... FAILED ({:?})", result.duration);
+            failed.push(result);
+        }
+    }
+    for result in &failed {
+        println!();
+        println!("---- {} ----", result.name);
+        if let Some(e) = &result.error {
+            println!("{e}");
+        }
+    }
+    println!();
+    println!(
+        "test result: {}. {} passed; {} failed; finished in {:?}",
+        if failed.is_empty() { "ok" } else { "FAILED" },
+        tests.len() - failed.len(),
+        failed.len(),
+        start.elapsed()
+    );
+    if !failed.is_empty() {
+        return log::error!(CompileError: &format!("{} tests failed", failed.len()));
+    }
+    Ok(())
+}
+
+// compile the entrypoints of a library and write each circuit
+// and the library manifest
+fn build_library<T: PolynomialRingElement>(
+    config: &Config,
+    entry_fns: &[String],
+    out: &Utf8Path,
+) -> Result<()> {
+    let mut compiler: Compiler<T> = Compiler::new(config)?;
+    let library = compiler.compile_library(entry_fns)?;
+    for circuit in &library.circuits {
+        println!("{}: {}", circuit.entry_fn, circuit.fingerprint);
+    }
+    library.write(out)?;
+    println!(
+        "Wrote {} circuits and {} to {out}",
+        library.circuits.len(),
+        library::LIBRARY_FILE
+    );
+    Ok(())
+}
+
+// compile and prove in the configured target and field
+fn run(mut config: Config) -> Result<()> {
+    match config.target.as_str() {
+        "tasm" => {
+            let compiled = provers::TritonVMProver::compile(&config)?;
+            if let Some(fingerprint) = &compiled.fingerprint {
+                println!("program fingerprint: {fingerprint}");
+            }
+            if let Some(report) = &compiled.memory_report {
+                println!("{report}");
+            }
+            if let Some(profile) = &compiled.profile {
+                println!("{profile}");
+            }
+            if let Some(path) = &config.trace_only {
+                let report = provers::TritonVMProver::trace(
+                    &compiled.asm,
+                    config.inputs.clone(),
+                    config.secret_inputs.clone(),
+                    path,
+                )?;
+                println!("{report}");
+                println!("Wrote instruction trace to {path}");
+                print_outputs(
+                    &report
+                        .outputs
+                        .iter()
+                        .map(|v| v.value().to_string())
+                        .collect::<Vec<_>>(),
+                );
+                return Ok(());
+            }
+            let proof = provers::TritonVMProver::prove_ir(
+                &compiled.asm,
+                config.inputs.clone(),
+                config.secret_inputs.clone(),
+            )?;
+            let outputs = provers::TritonVMProver::outputs(&proof);
+            if let Some(path) = &config.proof_out {
+                ProofEnvelope::new(
+                    &proof,
+                    compiled.fingerprint.clone(),
+                    config.inputs.clone(),
+                    outputs.clone(),
+                )?
+                .save(path)?;
+                println!("Wrote proof to {path}");
+            }
+            print_outputs(&outputs);
+            Ok(())
+        }
         "r1cs" => match config.field.as_str() {
             "foi" => {
                 println!("⚠️ Field specifier \"foi\" is deprecated and will be removed. Instead use \"oxfoi\"");
@@ -48,10 +350,17 @@ fn main() -> Result<()> {
                 Ok(())
             }
             "curve25519" => {
-                let r1cs = compile_r1cs::<Curve25519PolynomialRing>(&mut config)?;
-                let proof =
-                    provers::SpartanProver::prove_ir(&r1cs, config.inputs, config.secret_inputs)?;
-                if provers::SpartanProver::verify(&r1cs, proof)? {
+                let compiled = compile_r1cs::<Curve25519PolynomialRing>(&mut config)?;
+                let r1cs = &compiled.constraints;
+                let (mut proof, report) = provers::SpartanProver::prove_report(
+                    r1cs,
+                    config.inputs.clone(),
+                    config.secret_inputs.clone(),
+                )?;
+                proof.fingerprint = Some(compiled.fingerprint.clone());
+                write_proof(&config, &compiled, &proof)?;
+                println!("{report}");
+                if report.valid {
                     println!("✅ spartan proof is valid");
                 } else {
                     println!("🔴 spartan proof is NOT valid");
@@ -59,62 +368,455 @@ fn main() -> Result<()> {
                 Ok(())
             }
             "alt_bn128" => {
-                compile_r1cs::<Bn128PolynomialRing>(&mut config)?;
+                #[cfg(not(feature = "groth16-prover"))]
+                if config.solidity_out.is_some() {
+                    return log::error!(
+                        ConfigError: "--solidity-out requires the groth16 prover",
+                        "enable the groth16-prover feature"
+                    );
+                }
+                #[cfg(not(any(feature = "groth16-prover", feature = "halo2-prover")))]
+                if config.proof_out.is_some() {
+                    return log::error!(
+                        ConfigError: "--proof-out in the alt_bn128 field requires a prover",
+                        "enable the groth16-prover or halo2-prover feature"
+                    );
+                }
+                #[cfg(not(feature = "nova-prover"))]
+                if config.fold.is_some() {
+                    return log::error!(
+                        ConfigError: "--fold requires the nova prover",
+                        "enable the nova-prover feature"
+                    );
+                }
+                #[allow(unused_variables)]
+                let compiled = compile_r1cs::<Bn128PolynomialRing>(&mut config)?;
+                #[cfg(feature = "nova-prover")]
+                if let Some(steps) = config.fold {
+                    return fold(&config, &compiled, steps);
+                }
+                #[allow(unused_variables)]
+                let key_cache = config.key_dir.clone().map(FnCache::new);
+                #[cfg(feature = "groth16-prover")]
+                {
+                    let r1cs = &compiled.constraints;
+                    // the proof is verified using the key from setup
+                    let (pk, vk) = match &key_cache {
+                        Some(cache) => provers::Groth16Prover::setup_cached(
+                            r1cs,
+                            &compiled.fingerprint,
+                            cache,
+                        )?,
+                        None => provers::Groth16Prover::setup(r1cs)?,
+                    };
+                    let mut proof = provers::Groth16Prover::prove(
+                        r1cs,
+                        &pk,
+                        config.inputs.clone(),
+                        config.secret_inputs.clone(),
+                    )?;
+                    proof.fingerprint = Some(compiled.fingerprint.clone());
+                    write_proof(&config, &compiled, &proof)?;
+                    if let Some(path) = &config.solidity_out {
+                        let contract =
+                            provers::solidity::verifier(r1cs, &vk, Some(&compiled.fingerprint))?;
+                        std::fs::write(path, contract)?;
+                        println!("Wrote solidity verifier to {path}");
+                        println!("calldata: {}", provers::solidity::calldata(&proof));
+                    }
+                    if provers::Groth16Prover::verify(&vk, &proof)? {
+                        println!("✅ groth16 proof is valid");
+                    } else {
+                        println!("🔴 groth16 proof is NOT valid");
+                    }
+                }
+                #[cfg(feature = "halo2-prover")]
+                {
+                    let r1cs = &compiled.constraints;
+                    // the proof is verified using the parameters from setup
+                    let (params, vk) = match &key_cache {
+                        Some(cache) => provers::Halo2Prover::setup_cached(r1cs, cache)?,
+                        None => provers::Halo2Prover::setup(r1cs)?,
+                    };
+                    let mut proof = provers::Halo2Prover::prove(
+                        r1cs,
+                        &params,
+                        config.inputs.clone(),
+                        config.secret_inputs.clone(),
+                    )?;
+                    proof.fingerprint = Some(compiled.fingerprint.clone());
+                    // the groth16 proof is written if both provers are enabled
+                    #[cfg(not(feature = "groth16-prover"))]
+                    write_proof(&config, &compiled, &proof)?;
+                    if provers::Halo2Prover::verify(&vk, &proof)? {
+                        println!("✅ halo2 proof is valid");
+                    } else {
+                        println!("🔴 halo2 proof is NOT valid");
+                    }
+                }
                 Ok(())
             }
             "dilithium" => {
                 compile_r1cs::<DilithiumPolynomialRingElement>(&mut config)?;
                 Ok(())
             }
+            _ if config.field.starts_with(rings::CUSTOM_FIELD_PREFIX) => {
+                rings::init_custom_field(&config.field)?;
+                compile_r1cs::<CustomPolynomialRing>(&mut config)?;
+                Ok(())
+            }
             _ => {
-                return log::error!(&format!(
+                return log::error!(ConfigError: &format!(
                     "Unsupported field for target r1cs: {}",
                     config.field
                 ));
             }
         },
         _ => {
-            return log::error!(&format!("Unsupported target: {}", config.target));
+            return log::error!(ConfigError: &format!("Unsupported target: {}", config.target));
+        }
+    }
+}
+
+// the constraints, fingerprint, and public values of a program
+// compiled and solved by compile_r1cs
+struct CompiledR1cs {
+    constraints: String,
+    fingerprint: String,
+    public_inputs: Vec<String>,
+    public_outputs: Vec<String>,
+}
+
+// write a proof envelope if one was requested
+// prove steps of a program by folding, the inputs of the program
+// are the initial state
+#[cfg(feature = "nova-prover")]
+fn fold(config: &Config, compiled: &CompiledR1cs, steps: usize) -> Result<()> {
+    let r1cs = &compiled.constraints;
+    let params = provers::NovaProver::setup(r1cs)?;
+    let start = Instant::now();
+    let mut proof =
+        provers::NovaProver::prove_steps(r1cs, &params, config.secret_inputs.clone(), steps)?;
+    proof.fingerprint = Some(compiled.fingerprint.clone());
+    println!("Folded {steps} steps in {:?}", start.elapsed());
+    match provers::NovaProver::verify_steps(&params, &proof)? {
+        Some(state) => {
+            println!("✅ nova proof is valid");
+            println!("final state: [{}]", state.join(", "));
         }
+        None => println!("🔴 nova proof is NOT valid"),
+    }
+    Ok(())
+}
+
+fn write_proof<P: EnvelopeProof>(
+    config: &Config,
+    compiled: &CompiledR1cs,
+    proof: &P,
+) -> Result<()> {
+    if let Some(path) = &config.proof_out {
+        ProofEnvelope::new(
+            proof,
+            Some(compiled.fingerprint.clone()),
+            compiled.public_inputs.clone(),
+            compiled.public_outputs.clone(),
+        )?
+        .save(path)?;
+        println!("Wrote proof to {path}");
+    }
+    Ok(())
+}
+
+// verify a proof envelope, optionally against the recompiled program
+fn verify(
+    config: Option<Config>,
+    path: &Utf8Path,
+    fingerprint: Option<String>,
+    keys: Option<FnCache>,
+) -> Result<()> {
+    let envelope = ProofEnvelope::load(path)?;
+    let mut expected = fingerprint;
+    let program = match config {
+        Some(mut config) => {
+            let (target, field) = match envelope.backend.as_str() {
+                "spartan" => ("r1cs", "curve25519"),
+                "groth16" | "halo2" => ("r1cs", "alt_bn128"),
+                "tritonvm" => ("tasm", "foi"),
+                backend => return log::error!(ProverError: &format!("unknown prover: {backend}")),
+            };
+            config.target = target.to_string();
+            config.field = field.to_string();
+            match target {
+                "tasm" => config.extension_priorities.push("tasm".to_string()),
+                _ => {
+                    config.extension_priorities.push("ar1cs".to_string());
+                    config.extension_priorities.push("r1cs".to_string());
+                }
+            }
+            let (program, fingerprint) = match field {
+                "curve25519" => compile_program::<Curve25519PolynomialRing>(&config)?,
+                "alt_bn128" => compile_program::<Bn128PolynomialRing>(&config)?,
+                _ => compile_program::<OxfoiPolynomialRing>(&config)?,
+            };
+            if expected.as_ref().is_some_and(|v| v != &fingerprint) {
+                return log::error!(
+                    ProverError: "program does not have the expected fingerprint",
+                    &format!("program fingerprint: {fingerprint}")
+                );
+            }
+            expected = Some(fingerprint);
+            Some(program)
+        }
+        None => None,
     };
+    if let Some(expected) = expected {
+        if envelope.fingerprint.as_deref() != Some(expected.as_str()) {
+            return log::error!(
+                ProverError: "proof was generated for a different program",
+                &format!(
+                    "proof fingerprint: {}\nprogram fingerprint: {expected}",
+                    envelope.fingerprint.as_deref().unwrap_or("none")
+                )
+            );
+        }
+    }
+    if !envelope.verify(program.as_deref(), keys.as_ref())? {
+        return log::error!(ProverError: &format!("{} proof is NOT valid", envelope.backend));
+    }
+    println!("✅ {} proof is valid", envelope.backend);
+    if program.is_none() {
+        println!(
+            "program fingerprint: {}",
+            envelope.fingerprint.as_deref().unwrap_or("none")
+        );
+    }
+    println!("public inputs: [{}]", envelope.public_inputs.join(", "));
+    println!("public outputs: [{}]", envelope.public_outputs.join(", "));
+    Ok(())
+}
+
+fn compile_program<T: PolynomialRingElement>(config: &Config) -> Result<(String, String)> {
+    let mut compiler: Compiler<T> = Compiler::new(config)?;
+    let program = compiler.compile(&config.entry_fn)?;
+    let fingerprint = compiler.fingerprint().unwrap_or_default().to_string();
+    Ok((program, fingerprint))
+}
+
+fn init(dir: &Utf8Path) -> Result<()> {
+    let name = match dir
+        .canonicalize_utf8()
+        .ok()
+        .and_then(|v| v.file_name().map(String::from))
+    {
+        Some(name) => name,
+        None => dir.file_name().unwrap_or("ash_project").to_string(),
+    };
+    std::fs::create_dir_all(dir)?;
+    let manifest = Manifest::new(&name, dir);
+    manifest.init()?;
+    println!("Created project {name} in {dir}");
+    println!("build it using `acc build {dir}`");
+    Ok(())
+}
+
+fn format_paths(paths: &[Utf8PathBuf], check: bool) -> Result<()> {
+    let changed = fmt::format_paths(paths, check)?;
+    for file in &changed {
+        if check {
+            println!("{file} is not formatted");
+        } else {
+            println!("formatted {file}");
+        }
+    }
+    if check && !changed.is_empty() {
+        return log::error!(
+            ConfigError: &format!("{} files are not formatted", changed.len()),
+            "run `acc fmt` to format them"
+        );
+    }
+    Ok(())
 }
 
 /// Used to compile and verify r1cs that does not yet have a default prover
-fn compile_r1cs<T: PolynomialRingElement>(config: &mut Config) -> Result<String> {
+fn compile_r1cs<T: PolynomialRingElement>(config: &mut Config) -> Result<CompiledR1cs>
+where
+    T::F: Send + Sync,
+{
     config.extension_priorities.push("ar1cs".to_string());
+    config.extension_priorities.push("r1cs".to_string());
     let mut compiler: Compiler<T> = Compiler::new(config)?;
 
     let constraints = compiler.compile(&config.entry_fn)?;
+    let fingerprint = compiler.fingerprint().unwrap_or_default().to_string();
+    println!("program fingerprint: {fingerprint}");
+    // compiled artifacts must read back identically
+    let ar1cs: Ar1cs<T::F> = constraints.parse()?;
+    if ar1cs.to_string() != constraints {
+        return log::error!(
+            CompileError: "compiled ar1cs does not round trip through Ar1cs",
+            "this is a compiler bug"
+        );
+    }
+    if let Some(profile) = compiler.profile() {
+        println!("{profile}");
+    }
 
-    let witness = witness::build::<T>(
-        &constraints,
-        config
-            .secret_inputs
-            .iter()
-            .map(|v| T::from_str(v))
-            .collect::<Result<Vec<_>>>()?,
-    );
-    if let Err(e) = witness {
-        println!("Failed to build witness: {:?}", e);
-        std::process::exit(1);
+    let inputs = config
+        .secret_inputs
+        .iter()
+        .map(|v| T::from_str(v))
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(path) = &config.stream_witness {
+        stream_witness::<T>(path, &constraints, inputs.clone())?;
+    }
+    let mut trace = WitnessTrace::new();
+    let witness = match (&config.witness_trace, config.jobs) {
+        (Some(_), _) => witness::build_traced::<T>(&constraints, inputs, &mut trace),
+        (None, Some(jobs)) => witness::build_parallel::<T>(&constraints, inputs, jobs),
+        (None, None) => witness::build::<T>(&constraints, inputs),
+    };
+    let witness = match witness {
+        Ok(witness) => witness,
+        Err(e) => {
+            write_trace(config, &trace)?;
+            return Err(e.context("Failed to build witness"));
+        }
+    };
+    if let Some(emitter) = Emitter::new(config) {
+        emitter.write_witness(&config.entry_fn, || emit::witness_json(&ar1cs, &witness))?;
     }
-    let witness = witness.unwrap();
 
-    let solved = witness::verify::<T>(&constraints, witness);
-    if let Err(e) = solved {
-        println!("Failed to solve r1cs: {:?}", e);
-        std::process::exit(1);
+    if let Some(path) = &config.circom_out {
+        let r1cs: R1csParser<T> = R1csParser::new(&constraints)?;
+        std::fs::write(
+            path.with_extension("r1cs"),
+            circom::write_r1cs(&r1cs.constraints)?,
+        )?;
+        std::fs::write(
+            path.with_extension("wtns"),
+            circom::write_wtns(&r1cs.constraints, &witness)?,
+        )?;
+        println!("Wrote circom r1cs and witness to {path}.{{r1cs,wtns}}");
+    }
+
+    if let Some(path) = &config.binary_out {
+        write_binary(path, &constraints, &ar1cs, &witness)?;
     }
+
+    let solved = match &config.witness_trace {
+        Some(_) => witness::verify_traced::<T>(&constraints, witness, &mut trace),
+        None => witness::verify::<T>(&constraints, witness),
+    };
+    write_trace(config, &trace)?;
+    let solved = solved.map_err(|e| e.context("Failed to solve r1cs"))?;
     println!();
     println!("R1CS: built and validated witness ✅");
-    let outputs = solved?;
-    if !outputs.is_empty() {
-        println!("Received the following outputs:");
-        for v in outputs {
-            println!("{v}");
-        }
-    } else {
+    let (public_inputs, public_outputs) = ProofEnvelope::split_public::<T>(&constraints, &solved)?;
+    print_outputs(&public_outputs);
+    Ok(CompiledR1cs {
+        constraints,
+        fingerprint,
+        public_inputs,
+        public_outputs,
+    })
+}
+
+// print the public outputs of a program in any target
+fn print_outputs(outputs: &[String]) {
+    if outputs.is_empty() {
         println!("No outputs were generated");
+        return;
+    }
+    println!("Received the following outputs:");
+    for v in outputs {
+        println!("{v}");
+    }
+}
+
+// write the witness trace if one was requested, the step
+// where the witness diverged is also printed
+fn write_trace<T: FieldElement>(config: &Config, trace: &WitnessTrace<T>) -> Result<()> {
+    if let Some(path) = &config.witness_trace {
+        trace.write(path)?;
+        if let Some(divergence) = trace.divergence() {
+            println!("{divergence}");
+        }
+        println!("Wrote witness trace to {path}");
+    }
+    Ok(())
+}
+
+// calculate the witness one constraint at a time and compare
+// the streamed file with a witness built in memory
+fn stream_witness<T: PolynomialRingElement>(
+    path: &Utf8Path,
+    constraints: &str,
+    inputs: Vec<T>,
+) -> Result<()> {
+    let out = BufWriter::new(File::create(path)?);
+    let streamed = witness::build_streaming::<T, _, _>(
+        Cursor::new(constraints.as_bytes()),
+        inputs.clone(),
+        out,
+    )?;
+    let decoded = encoding::decode_witness_stream::<T::F>(&std::fs::read(path)?)?;
+    if decoded != witness::build::<T>(constraints, inputs)? {
+        return log::error!(
+            CompileError: "streamed witness does not match the witness built in memory",
+            "this is a compiler bug"
+        );
     }
-    Ok(constraints)
+    println!("Streamed {} witness values to {path}", streamed.signals);
+    println!(
+        "at most {} values were held in memory",
+        streamed.peak_signals
+    );
+    Ok(())
+}
+
+// write the binary forms of the constraints and witness, and
+// compare the time to load them with the ar1cs text
+fn write_binary<T: FieldElement>(
+    path: &Utf8Path,
+    source: &str,
+    ar1cs: &Ar1cs<T>,
+    witness: &witness::Witness<T>,
+) -> Result<()> {
+    let ar1cs_bytes = encoding::encode_ar1cs(ar1cs);
+    let witness_bytes = encoding::encode_witness(witness);
+    std::fs::write(path.with_extension("ar1csb"), &ar1cs_bytes)?;
+    std::fs::write(path.with_extension("witb"), &witness_bytes)?;
+    println!("Wrote binary r1cs and witness to {path}.{{ar1csb,witb}}");
+
+    let start = Instant::now();
+    let parsed: Ar1cs<T> = source.parse()?;
+    let parse_time = start.elapsed();
+    let start = Instant::now();
+    let decoded = encoding::decode_ar1cs::<T>(&ar1cs_bytes)?;
+    let decode_time = start.elapsed();
+    let start = Instant::now();
+    let decoded_witness = encoding::decode_witness::<T>(&witness_bytes)?;
+    let witness_time = start.elapsed();
+    if decoded != parsed || decoded_witness != *witness {
+        return log::error!(
+            CompileError: "binary r1cs does not round trip",
+            "this is a compiler bug"
+        );
+    }
+    println!(
+        "ar1cs text: {} bytes, parsed in {:?}",
+        source.len(),
+        parse_time
+    );
+    println!(
+        "ar1cs binary: {} bytes, decoded in {:?}",
+        ar1cs_bytes.len(),
+        decode_time
+    );
+    println!(
+        "witness binary: {} bytes, decoded in {:?}",
+        witness_bytes.len(),
+        witness_time
+    );
+    Ok(())
 }
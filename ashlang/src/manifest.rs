@@ -0,0 +1,290 @@
+//! Project manifests. An `ash.toml` file names the entry function,
+//! target, field, and include directories of a project, and the
+//! packages it depends on, e.g.
+//!
+//! ```toml
+//! [package]
+//! name = "example"
+//! entry = "main"
+//! entries = ["deposit", "withdraw"]
+//! target = "r1cs"
+//! field = "curve25519"
+//! include = ["src"]
+//!
+//! [dependencies]
+//! utils = { path = "../utils" }
+//! hashes = { git = "https://github.com/org/hashes", rev = "4b1c2a7" }
+//! ```
+//!
+//! `entries` lists the entry functions compiled by `acc lib`, it
+//! defaults to `entry`.
+//!
+//! Each dependency contributes its own include directories, or
+//! its root directory if it has no manifest. Dependencies of
+//! dependencies are resolved the same way. Git dependencies are
+//! cloned into `.ash/deps/<name>` and checked out at `rev`.
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::fs;
+use std::process::Command;
+
+use anyhow::Result;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::cli::Config;
+use crate::log;
+
+/// The name of the manifest file in a project directory.
+pub const MANIFEST_FILE: &str = "ash.toml";
+
+/// A parsed `ash.toml` file.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Manifest {
+    pub package: Package,
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
+    // the directory containing the manifest
+    #[serde(skip)]
+    pub root: Utf8PathBuf,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Package {
+    pub name: String,
+    #[serde(default = "default_entry")]
+    pub entry: String,
+    // the entry functions of a library of circuits
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<String>,
+    pub target: String,
+    pub field: String,
+    // directories relative to the manifest
+    #[serde(default = "default_include")]
+    pub include: Vec<String>,
+}
+
+/// A package on disk or in a git repository. Git dependencies
+/// must specify a `rev` so builds are reproducible.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Dependency {
+    pub path: Option<String>,
+    pub git: Option<String>,
+    pub rev: Option<String>,
+}
+
+fn default_entry() -> String {
+    "main".to_string()
+}
+
+fn default_include() -> Vec<String> {
+    vec!["src".to_string()]
+}
+
+impl Manifest {
+    /// Read the manifest in a project directory.
+    pub fn load(dir: &Utf8Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(_) => {
+                return log::error!(
+                    ConfigError: &format!("Failed to read manifest: {path}"),
+                    "create a project using `acc init`"
+                )
+            }
+        };
+        let mut manifest: Manifest = match toml::from_str(&source) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                return log::error!(ConfigError: &format!("Failed to parse manifest {path}: {e}"))
+            }
+        };
+        manifest.root = dir.to_path_buf();
+        Ok(manifest)
+    }
+
+    /// A manifest for a new project with default settings.
+    pub fn new(name: &str, root: &Utf8Path) -> Self {
+        Manifest {
+            package: Package {
+                name: name.to_string(),
+                entry: default_entry(),
+                entries: vec![],
+                target: "r1cs".to_string(),
+                field: "curve25519".to_string(),
+                include: default_include(),
+            },
+            dependencies: BTreeMap::new(),
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// Write the manifest and an entry function to a new project
+    /// directory.
+    pub fn init(&self) -> Result<()> {
+        let path = self.root.join(MANIFEST_FILE);
+        if path.exists() {
+            return log::error!(ConfigError: &format!("{path} already exists"));
+        }
+        let src = self.root.join(&self.package.include[0]);
+        fs::create_dir_all(&src)?;
+        fs::write(&path, toml::to_string(self)?)?;
+        let entry = src.join(format!("{}.ash", self.package.entry));
+        if !entry.exists() {
+            fs::write(
+                &entry,
+                "let x = 2\nlet y = x * x\nassert(y == 4, \"y must be x squared\")\n",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The include paths of the package and every dependency.
+    pub fn include_paths(&self) -> Result<Vec<Utf8PathBuf>> {
+        let mut include_paths = vec![];
+        let mut visited = HashSet::new();
+        self.resolve(&self.root, &mut include_paths, &mut visited)?;
+        Ok(include_paths)
+    }
+
+    /// The entry functions compiled by `acc lib`.
+    pub fn entries(&self) -> Vec<String> {
+        match self.package.entries.is_empty() {
+            true => vec![self.package.entry.clone()],
+            false => self.package.entries.clone(),
+        }
+    }
+
+    /// The compiler configuration for building the package.
+    pub fn config(&self) -> Result<Config> {
+        Ok(Config {
+            include_paths: self.include_paths()?,
+            verbosity: 0,
+            inputs: vec![],
+            secret_inputs: vec![],
+            target: self.package.target.clone(),
+            extension_priorities: vec!["ash".to_string()],
+            entry_fn: self.package.entry.clone(),
+            field: self.package.field.clone(),
+            eliminate_dead_witness: true,
+            circom_out: None,
+            binary_out: None,
+            cache_dir: None,
+            report_memory: false,
+            profile: false,
+            witness_trace: None,
+            stream_witness: None,
+            jobs: None,
+            proof_out: None,
+            key_dir: None,
+            solidity_out: None,
+            fold: None,
+            unroll_limit: None,
+            checked_indexing: false,
+            trace_only: None,
+            symbols: false,
+            emit: vec![],
+            emit_dir: None,
+        })
+    }
+
+    // add the include directories of this manifest and its
+    // dependencies, each package is visited once
+    fn resolve(
+        &self,
+        project_root: &Utf8Path,
+        include_paths: &mut Vec<Utf8PathBuf>,
+        visited: &mut HashSet<Utf8PathBuf>,
+    ) -> Result<()> {
+        if !visited.insert(canonical(&self.root)?) {
+            return Ok(());
+        }
+        for dir in &self.package.include {
+            let path = self.root.join(dir);
+            if !path.is_dir() {
+                return log::error!(
+                    ConfigError: &format!("include directory does not exist: {path}"),
+                    &format!("check the include field of {}", self.root.join(MANIFEST_FILE))
+                );
+            }
+            include_paths.push(path);
+        }
+        for (name, dependency) in &self.dependencies {
+            let dir = dependency.fetch(name, &self.root, project_root)?;
+            if dir.join(MANIFEST_FILE).exists() {
+                Manifest::load(&dir)?.resolve(project_root, include_paths, visited)?;
+            } else if visited.insert(canonical(&dir)?) {
+                include_paths.push(dir);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Dependency {
+    // the directory containing the dependency, git dependencies
+    // are cloned into the project that is being built
+    fn fetch(&self, name: &str, root: &Utf8Path, project_root: &Utf8Path) -> Result<Utf8PathBuf> {
+        match (&self.path, &self.git, &self.rev) {
+            (Some(path), None, None) => {
+                let dir = root.join(path);
+                if !dir.is_dir() {
+                    return log::error!(
+                        ConfigError: &format!("dependency {name} does not exist: {dir}")
+                    );
+                }
+                Ok(dir)
+            }
+            (None, Some(url), Some(rev)) => {
+                let dir = project_root.join(".ash").join("deps").join(name);
+                if !dir.exists() {
+                    fs::create_dir_all(project_root.join(".ash").join("deps"))?;
+                    git(&["clone", "--quiet", url, dir.as_str()], name)?;
+                }
+                // fetch if the revision is not in the existing clone
+                if git(
+                    &["-C", dir.as_str(), "checkout", "--quiet", "--detach", rev],
+                    name,
+                )
+                .is_err()
+                {
+                    git(&["-C", dir.as_str(), "fetch", "--quiet", "origin"], name)?;
+                    git(
+                        &["-C", dir.as_str(), "checkout", "--quiet", "--detach", rev],
+                        name,
+                    )?;
+                }
+                Ok(dir)
+            }
+            (None, Some(_), None) => log::error!(
+                ConfigError: &format!("git dependency {name} does not specify a rev"),
+                "specify a commit so builds are reproducible, e.g. rev = \"4b1c2a7\""
+            ),
+            _ => log::error!(
+                ConfigError: &format!("invalid dependency {name}"),
+                "specify either a path or a git url and rev"
+            ),
+        }
+    }
+}
+
+fn git(args: &[&str], name: &str) -> Result<()> {
+    let status = Command::new("git").args(args).status();
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        _ => log::error!(ConfigError: &format!(
+            "Failed to fetch dependency {name}: git {}",
+            args.join(" ")
+        )),
+    }
+}
+
+fn canonical(path: &Utf8Path) -> Result<Utf8PathBuf> {
+    match path.canonicalize_utf8() {
+        Ok(path) => Ok(path),
+        Err(_) => log::error!(ConfigError: &format!("Failed to resolve path: {path}")),
+    }
+}
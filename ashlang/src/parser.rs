@@ -1,4 +1,6 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
 
 use anyhow::Result;
 use pest::iterators::Pair;
@@ -9,8 +11,11 @@ use pest::pratt_parser::PrattParser;
 use pest::Parser;
 use pest::RuleType;
 use pest_derive::Parser;
+use scalarff::BigUint;
 
 use self::AstNode::*;
+use crate::builtin;
+use crate::compiler::ReturnType;
 use crate::log;
 use log::error;
 
@@ -20,19 +25,87 @@ use log::error;
 pub enum AstNode {
     // a variable argument to a function call
     FnVar(Vec<String>),
+    // the declared dimensions of each argument, see `layout::dim_params`
+    FnDims(Vec<(String, Vec<String>)>),
     // a let defintion, const definition, or if statement
     Stmt(String, bool, Expr),
     ExprUnassigned(Expr),
     Rtrn(Expr),
     StaticDef(String, Expr),
-    If(Expr, Vec<AstNode>),
+    // a let definition with a type, e.g. `let x: u32 = 1`
+    TypedDef(String, VarType, Expr),
+    // condition, if block, else block
+    // an `else if` is represented as an else block
+    // containing a single If node
+    If(Expr, Vec<AstNode>, Vec<AstNode>),
     Loop(Expr, Vec<AstNode>),
+    // a loop that runs as long as the boolean
+    // expression evaluates to true at runtime
+    While(Expr, Vec<AstNode>),
     EmptyVecDef(String, Vec<usize>),
 
     // name, indices being assigned
     // and an expression representing the value
     // being assigned
     AssignVec(String, Vec<Expr>, Expr),
+
+    // declare a new variable read from the public inputs
+    PubInput(String),
+    // expose a variable as a public output
+    PubOutput(String),
+    // declare a new variable read from the secret inputs
+    SecretInput(String),
+
+    // a condition and the message reported if it fails
+    Assert(Expr, String),
+
+    // a table and the values that must be a row of it
+    Lookup(Arc<TableDef>, Vec<Expr>),
+
+    // arguments, return values, and the source of an
+    // inline ar1cs block
+    Ar1cs(Vec<String>, Vec<String>, String),
+    // arguments, return values, and the instructions of an
+    // inline tasm block
+    Asm(Vec<String>, Vec<String>, String),
+
+    // values declared by a block that is evaluated outside
+    // of the circuit, and the block
+    Unconstrained(Vec<String>, Vec<AstNode>),
+
+    // the source location of the following statement
+    Location(SourceLocation),
+    // whether calls in the following statement are inlined
+    // in the tasm target, from `#[inline]` or `#[noinline]`
+    Inline(bool),
+}
+
+/// A static table declared using `table`. Every row has the
+/// same number of columns and rows are distinct.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableDef {
+    pub name: String,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl TableDef {
+    /// The largest number of rows in a table.
+    pub const MAX_ROWS: usize = 1 << 20;
+
+    /// The number of columns in each row.
+    pub fn width(&self) -> usize {
+        self.rows[0].len()
+    }
+}
+
+/// The position of a statement in a source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: usize,
+    pub col: usize,
+    // the source line containing the statement
+    pub snippet: String,
 }
 
 /// An expression in the AST. Many expressions may appear on a single
@@ -43,6 +116,9 @@ pub enum Expr {
     VecLit(Vec<String>),
     Lit(String),
     Val(String, Vec<Expr>),
+    // a reference to a variable, only valid
+    // as a function argument
+    Ref(String, Vec<Expr>),
     FnCall(String, Vec<Expr>),
     NumOp {
         lhs: Box<Expr>,
@@ -54,15 +130,29 @@ pub enum Expr {
         bool_op: BoolOp,
         rhs: Box<Expr>,
     },
+    BitOp {
+        lhs: Box<Expr>,
+        bit_op: BitOp,
+        rhs: Box<Expr>,
+    },
 }
 
 /// Operations that output a boolean result.
+///
+/// Ordering comparisons operate on values
+/// in the range [0, 2^32).
 #[derive(Debug, Clone)]
 pub enum BoolOp {
     Equal,
     NotEqual,
     GreaterThan,
     LessThan,
+    GreaterThanOrEqual,
+    LessThanOrEqual,
+    // short circuiting logical operations
+    // on the results of other boolean operations
+    And,
+    Or,
 }
 
 /// Operations that output a numerical result.
@@ -74,6 +164,119 @@ pub enum NumOp {
     Mul,
 }
 
+/// Bitwise operations on integer types. Bits shifted
+/// past the width of the type are discarded.
+#[derive(Debug, Clone)]
+pub enum BitOp {
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+}
+
+/// An unsigned integer type. The result of each numerical
+/// operation on an integer variable is asserted to be in range,
+/// so arithmetic that overflows or underflows fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntType {
+    U32,
+    U64,
+}
+
+impl IntType {
+    pub fn bits(&self) -> usize {
+        match self {
+            IntType::U32 => 32,
+            IntType::U64 => 64,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            IntType::U32 => "u32",
+            IntType::U64 => "u64",
+        }
+    }
+}
+
+/// The type of a variable declared with an annotation,
+/// e.g. `let v: field[8] = v0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VarType {
+    /// a scalar, vector, or matrix of field elements with
+    /// the given dimensions, empty for a scalar
+    Field(Vec<usize>),
+    Int(IntType),
+}
+
+impl VarType {
+    pub fn name(&self) -> String {
+        match self {
+            VarType::Field(dimensions) => format!(
+                "field{}",
+                dimensions
+                    .iter()
+                    .map(|v| format!("[{v}]"))
+                    .collect::<String>()
+            ),
+            VarType::Int(int_type) => int_type.name().to_string(),
+        }
+    }
+
+    /// Whether a value with the given dimensions has this type.
+    /// Scalars and vectors of length 1 are not distinguished
+    /// because the targets represent them differently.
+    pub fn matches(&self, dimensions: &[usize]) -> bool {
+        let scalar = |v: &[usize]| v.is_empty() || v == [1];
+        match self {
+            VarType::Int(_) => scalar(dimensions),
+            VarType::Field(expected) => {
+                expected == dimensions || (scalar(expected) && scalar(dimensions))
+            }
+        }
+    }
+}
+
+impl Expr {
+    /// The integer type of a numerical expression, determined by
+    /// the integer variables it contains. Literals and other
+    /// values take the type of the expression they appear in.
+    pub fn int_type(&self, var_type: &impl Fn(&str) -> Option<IntType>) -> Result<Option<IntType>> {
+        match self {
+            Expr::Val(name, _) => Ok(var_type(name)),
+            Expr::FnCall(name, args) if name == builtin::NEG => {
+                match args.first().map(|v| v.int_type(var_type)).transpose()? {
+                    Some(Some(int_type)) => log::error!(
+                        TypeError: &format!("cannot negate a {} value", int_type.name()),
+                        "integer types are unsigned, negate a field element instead"
+                    ),
+                    _ => Ok(None),
+                }
+            }
+            // the shift amount does not determine the type
+            Expr::BitOp {
+                lhs,
+                bit_op: BitOp::Shl | BitOp::Shr,
+                ..
+            } => lhs.int_type(var_type),
+            Expr::NumOp { lhs, rhs, .. } | Expr::BitOp { lhs, rhs, .. } => {
+                match (lhs.int_type(var_type)?, rhs.int_type(var_type)?) {
+                    (Some(l), Some(r)) if l != r => log::error!(
+                        TypeError: &format!(
+                            "integer type mismatch in numeric operation: {} and {}",
+                            l.name(),
+                            r.name()
+                        )
+                    ),
+                    (l, r) => Ok(l.or(r)),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
 #[derive(Parser)]
 #[grammar = "grammar.pest"] // relative to project `src`
 pub struct AshPestParser;
@@ -84,12 +287,36 @@ pub struct AshParser {
     pub ast: Vec<AstNode>,
     pub fn_names: HashMap<String, u64>,
     pub entry_fn_name: String,
+    // file name used in source locations
+    pub file: String,
+    // module aliases introduced by `use`, keyed to the module path
+    pub uses: HashMap<String, String>,
+    // field names of each struct defined in the file, in layout order
+    pub structs: HashMap<String, Vec<String>>,
+    // tables defined in the file
+    pub tables: HashMap<String, Arc<TableDef>>,
+    // the struct type of each variable declared with one
+    pub var_structs: HashMap<String, String>,
+    // the maximum recursion depth declared in the function header
+    pub max_depth: Option<usize>,
+    // the dimensions of the returned value declared in the
+    // function header, empty for a scalar
+    pub return_shape: Option<Vec<String>>,
+    // whether calls of the function are inlined, declared using
+    // an attribute before the function header
+    pub inline: Option<bool>,
 }
 
 impl AshParser {
     /// Take a source file and a function name and output
     /// an instance of the parser.
     pub fn parse(source: &str, name: &str) -> Result<Self> {
+        Self::parse_file(source, name, &format!("{name}.ash"))
+    }
+
+    /// Like `parse`, but specify the path of the source file
+    /// used in compile errors.
+    pub fn parse_file(source: &str, name: &str, file: &str) -> Result<Self> {
         // append a new line to all source strings because
         // they aren't necessarily unix compatible files
         let source = format!("{source}\n");
@@ -97,22 +324,53 @@ impl AshParser {
             ast: Vec::new(),
             fn_names: HashMap::new(),
             entry_fn_name: name.to_string(),
+            file: file.to_string(),
+            uses: HashMap::new(),
+            structs: HashMap::new(),
+            tables: HashMap::new(),
+            var_structs: HashMap::new(),
+            max_depth: None,
+            return_shape: None,
+            inline: None,
         };
 
         match AshPestParser::parse(Rule::program, &source) {
             Ok(pairs) => {
                 let ast = out.build_ast_from_lines(pairs);
                 if let Err(e) = ast {
-                    return error!(&format!("error building program ast: {e}"));
+                    return error!(ParseError: &format!("error building program ast: {e}"));
                 }
             }
             Err(e) => {
-                return Err(anyhow::anyhow!(log::parse_error(e, name)));
+                return Err(log::parse_error(e, name));
             }
         }
         Ok(out)
     }
 
+    fn location(&self, pair: &Pair<Rule>) -> AstNode {
+        let start = pair.as_span().start_pos();
+        let (line, col) = start.line_col();
+        Location(SourceLocation {
+            file: self.file.clone(),
+            line,
+            col,
+            snippet: start.line_of().trim_end().to_string(),
+        })
+    }
+
+    // expand a module alias introduced by `use`, e.g.
+    // `use stdlib::crypto` turns `crypto::hash` into
+    // `stdlib::crypto::hash`
+    pub fn resolve_fn_name(&self, name: &str) -> String {
+        if let Some((module, rest)) = name.split_once("::") {
+            if let Some(path) = self.uses.get(module) {
+                return format!("{path}::{rest}");
+            }
+        }
+        name.to_string()
+    }
+
     fn mark_fn_call(&mut self, name: String) {
         let count = self.fn_names.entry(name).or_insert(0);
         *count += 1;
@@ -127,6 +385,15 @@ impl AshParser {
     }
 
     fn build_ast_from_lines(&mut self, pairs: Pairs<Rule>) -> Result<()> {
+        // structs are defined first so they may be used
+        // in the function header
+        for pair in pairs.clone() {
+            match pair.as_rule() {
+                Rule::struct_def => self.define_struct(pair)?,
+                Rule::table_def => self.define_table(pair)?,
+                _ => {}
+            }
+        }
         for pair in pairs {
             match pair.as_rule() {
                 Rule::fn_header => {
@@ -136,23 +403,93 @@ impl AshParser {
                     // be used
                     let pair = pair.into_inner();
                     let mut vars: Vec<String> = Vec::new();
+                    let mut dims = vec![];
                     for v in pair {
-                        vars.push(v.as_str().to_string());
+                        if v.as_rule() == Rule::recursive {
+                            let depth = v.into_inner().as_str().parse::<usize>()?;
+                            if depth == 0 {
+                                return error!(TypeError: "max recursion depth must be at least 1");
+                            }
+                            self.max_depth = Some(depth);
+                            continue;
+                        }
+                        if v.as_rule() == Rule::return_shape {
+                            self.return_shape = Some(
+                                v.into_inner()
+                                    .map(|v| v.into_inner().as_str().to_string())
+                                    .collect(),
+                            );
+                            continue;
+                        }
+                        let mut arg = v.into_inner();
+                        let name = AshParser::next_or_error(&mut arg)?.as_str().to_string();
+                        let mut arg_dims = vec![];
+                        for v in arg {
+                            if v.as_rule() == Rule::arg_dim {
+                                arg_dims.push(v.into_inner().as_str().to_string());
+                                continue;
+                            }
+                            self.struct_fields(v.as_str())?;
+                            self.var_structs
+                                .insert(name.clone(), v.as_str().to_string());
+                        }
+                        dims.push((name.clone(), arg_dims));
+                        vars.push(name);
+                    }
+                    if let Some(name) = dims.iter().flat_map(|(_, v)| v).find(|v| vars.contains(v))
+                    {
+                        return error!(ScopeError: &format!(
+                            "dimension \"{name}\" has the same name as a function argument"
+                        ));
+                    }
+                    if let Some(name) = self.return_shape.iter().flatten().find(|v| {
+                        v.parse::<usize>().is_err() && !dims.iter().any(|(_, d)| d.contains(v))
+                    }) {
+                        return error!(
+                            ScopeError: &format!("unknown return dimension \"{name}\""),
+                            "a return dimension must be a literal or a dimension of an argument, e.g. `(v[N]) -> field[N]`"
+                        );
                     }
-                    // let pair.next().unwrap()
                     self.ast.push(FnVar(vars));
+                    if dims.iter().any(|(_, v)| !v.is_empty()) {
+                        self.ast.push(FnDims(dims));
+                    }
+                }
+                Rule::attribute => self.inline = Some(Self::inline_attribute(pair)),
+                Rule::struct_def | Rule::table_def => {}
+                Rule::use_stmt => {
+                    let mut pair = pair.into_inner();
+                    let path = AshParser::next_or_error(&mut pair)?.as_str().to_string();
+                    let alias = path.rsplit("::").next().unwrap_or_default().to_string();
+                    if let Some(existing) = self.uses.get(&alias) {
+                        return error!(
+                            ScopeError: &format!("module name is already in use: {alias}"),
+                            &format!("{path} and {existing} are both imported as {alias}")
+                        );
+                    }
+                    self.uses.insert(alias, path);
                 }
                 Rule::stmt => {
+                    self.ast.push(self.location(&pair));
                     let mut pair = pair.into_inner();
                     let next = AshParser::next_or_error(&mut pair)?;
-                    let ast = self.build_ast_from_pair(next)?;
-                    self.ast.push(ast);
+                    let mut ast = self.build_stmt_from_pair(next)?;
+                    self.ast.append(&mut ast);
                 }
                 Rule::return_stmt => {
+                    self.ast.push(self.location(&pair));
                     let mut pair = pair.into_inner();
                     let next = AshParser::next_or_error(&mut pair)?;
-                    let expr = self.build_expr_from_pair(next)?;
-                    self.ast.push(Rtrn(expr));
+                    if next.as_rule() == Rule::tuple {
+                        // the tuple is returned as a vector
+                        let name = next.as_str().to_string();
+                        let mut ast = self.build_tuple(&name, next)?;
+                        self.ast.append(&mut ast);
+                        self.ast.push(Rtrn(Expr::Val(name, vec![])));
+                    } else {
+                        let expr = self.build_expr_from_pair(next)?;
+                        self.ast.push(Rtrn(expr));
+                    }
                 }
                 Rule::EOI => {}
                 _ => anyhow::bail!("unexpected line pair rule: {:?}", pair.as_rule()),
@@ -161,8 +498,309 @@ impl AshParser {
         Ok(())
     }
 
+    fn define_struct(&mut self, pair: Pair<Rule>) -> Result<()> {
+        let mut pair = pair.into_inner();
+        let name = AshParser::next_or_error(&mut pair)?.as_str().to_string();
+        let mut fields: Vec<String> = Vec::new();
+        for field in pair {
+            let field = field.as_str().to_string();
+            if fields.contains(&field) {
+                return error!(
+                    TypeError: &format!("field \"{field}\" is defined more than once in struct {name}")
+                );
+            }
+            fields.push(field);
+        }
+        if self.structs.insert(name.clone(), fields).is_some() {
+            return error!(ScopeError: &format!("struct {name} is defined more than once"));
+        }
+        Ok(())
+    }
+
+    fn define_table(&mut self, pair: Pair<Rule>) -> Result<()> {
+        let mut pair = pair.into_inner();
+        let name = AshParser::next_or_error(&mut pair)?.as_str().to_string();
+        let values = AshParser::next_or_error(&mut pair)?;
+        let mut rows = vec![];
+        if values.as_rule() == Rule::table_range {
+            let mut range = values.into_inner();
+            let start = AshParser::literal(AshParser::next_or_error(&mut range)?.as_str())?
+                .parse::<u64>()?;
+            let end = AshParser::literal(AshParser::next_or_error(&mut range)?.as_str())?
+                .parse::<u64>()?;
+            if start >= end || end - start > TableDef::MAX_ROWS as u64 {
+                return error!(
+                    TypeError: &format!("invalid range for table {name}: {start}..{end}"),
+                    &format!("a table range must be non-empty and contain at most {} values", TableDef::MAX_ROWS)
+                );
+            }
+            rows = (start..end).map(|v| vec![v.to_string()]).collect();
+        } else {
+            for row in values.into_inner() {
+                match row.as_rule() {
+                    Rule::literal => rows.push(vec![AshParser::literal(row.as_str())?]),
+                    _ => {
+                        let mut columns = vec![];
+                        for v in row.into_inner() {
+                            if v.as_rule() != Rule::literal {
+                                return error!(TypeError: &format!("rows of table {name} must be vectors of scalars"));
+                            }
+                            columns.push(AshParser::literal(v.as_str())?);
+                        }
+                        rows.push(columns);
+                    }
+                }
+            }
+        }
+        let width = rows[0].len();
+        if let Some(row) = rows.iter().find(|v| v.len() != width) {
+            return error!(
+                TypeError: &format!("rows of table {name} have different lengths"),
+                &format!("the first row has {width} columns, found a row with {}", row.len())
+            );
+        }
+        let mut seen = HashSet::new();
+        if let Some(row) = rows.iter().find(|v| !seen.insert(*v)) {
+            return error!(TypeError: &format!(
+                "table {name} contains the row [{}] more than once",
+                row.join(", ")
+            ));
+        }
+        let table = Arc::new(TableDef {
+            name: name.clone(),
+            rows,
+        });
+        if self.tables.insert(name.clone(), table).is_some() {
+            return error!(ScopeError: &format!("table {name} is defined more than once"));
+        }
+        Ok(())
+    }
+
+    fn struct_fields(&self, name: &str) -> Result<&Vec<String>> {
+        match self.structs.get(name) {
+            Some(fields) => Ok(fields),
+            None => error!(
+                TypeError: &format!("unknown struct type: {name}"),
+                "structs must be defined in the file they are used in, e.g. `struct Point { x, y }`"
+            ),
+        }
+    }
+
+    // the index of a struct field in the vector backing the struct
+    fn field_offset(&self, var: &str, field: &str) -> Result<Expr> {
+        let struct_name = match self.var_structs.get(var) {
+            Some(v) => v,
+            None => {
+                return error!(
+                    TypeError: &format!("variable \"{var}\" is not a struct"),
+                    "declare the variable with a struct literal or type e.g. `let p: Point = f()`"
+                )
+            }
+        };
+        let fields = self.struct_fields(struct_name)?;
+        match fields.iter().position(|v| v == field) {
+            Some(i) => Ok(Expr::Lit(i.to_string())),
+            None => error!(
+                TypeError: &format!("struct {struct_name} has no field \"{field}\""),
+                &format!("the fields of {struct_name} are: {}", fields.join(", "))
+            ),
+        }
+    }
+
+    // build the statements for a single `stmt` rule. Struct
+    // literals are lowered to a vector definition followed by
+    // an assignment to each field
+    // whether an attribute inlines calls, `#[inline]` or `#[noinline]`
+    fn inline_attribute(pair: Pair<Rule>) -> bool {
+        pair.into_inner().as_str() == "inline"
+    }
+
+    fn build_stmt_from_pair(&mut self, pair: Pair<Rule>) -> Result<Vec<AstNode>> {
+        if pair.as_rule() == Rule::attributed_stmt {
+            let mut inner = pair.into_inner();
+            let attribute = AshParser::next_or_error(&mut inner)?;
+            let stmt = AshParser::next_or_error(&mut inner)?;
+            let mut out = vec![
+                Inline(Self::inline_attribute(attribute)),
+                self.location(&stmt),
+            ];
+            let next = AshParser::next_or_error(&mut stmt.into_inner())?;
+            out.append(&mut self.build_stmt_from_pair(next)?);
+            return Ok(out);
+        }
+        if pair.as_rule() == Rule::tuple_def {
+            return self.build_tuple_def(pair);
+        }
+        if pair.as_rule() == Rule::var_def {
+            let mut inner = pair.clone().into_inner();
+            let var = AshParser::next_or_error(&mut inner)?;
+            let value = AshParser::next_or_error(&mut inner)?;
+            let mut varpair = var.into_inner();
+            let is_let = varpair.len() == 2;
+            let name = varpair.next_back().unwrap().as_str().to_string();
+            if value.as_rule() == Rule::struct_lit {
+                return self.build_struct_lit(name, is_let, value);
+            }
+            if is_let {
+                self.var_structs.remove(&name);
+            }
+        }
+        Ok(vec![self.build_ast_from_pair(pair)?])
+    }
+
+    // declare a vector with an element for each expression
+    // in a tuple
+    fn build_tuple(&mut self, name: &str, pair: Pair<Rule>) -> Result<Vec<AstNode>> {
+        let exprs = pair
+            .into_inner()
+            .map(|v| self.build_expr_from_pair(v))
+            .collect::<Result<Vec<_>>>()?;
+        let mut out = vec![EmptyVecDef(name.to_string(), vec![exprs.len()])];
+        for (i, expr) in exprs.into_iter().enumerate() {
+            out.push(AssignVec(
+                name.to_string(),
+                vec![Expr::Lit(i.to_string())],
+                expr,
+            ));
+        }
+        Ok(out)
+    }
+
+    // a tuple assignment is lowered to a vector holding the
+    // value, named by the tuple, and an assignment from each
+    // element. e.g. `let (x, y) = f()` is
+    //
+    // let (x, y): field[2] = f()
+    // let x = (x, y)[0]
+    // let y = (x, y)[1]
+    fn build_tuple_def(&mut self, pair: Pair<Rule>) -> Result<Vec<AstNode>> {
+        let mut pair = pair.into_inner();
+        let mut is_let = false;
+        let mut names: Vec<String> = Vec::new();
+        let mut value = None;
+        for v in pair.by_ref() {
+            match v.as_rule() {
+                Rule::let_r => is_let = true,
+                Rule::varname => names.push(v.as_str().to_string()),
+                _ => {
+                    value = Some(v);
+                    break;
+                }
+            }
+        }
+        let value = match value {
+            Some(v) => v,
+            None => anyhow::bail!("expected a value in tuple assignment"),
+        };
+        for (i, name) in names.iter().enumerate() {
+            if names[..i].contains(name) {
+                return error!(TypeError: &format!(
+                    "variable \"{name}\" is assigned more than once in tuple assignment"
+                ));
+            }
+        }
+        let tuple_name = format!("({})", names.join(", "));
+        let mut out = if value.as_rule() == Rule::tuple {
+            let len = value.clone().into_inner().len();
+            if len != names.len() {
+                return error!(TypeError: &format!(
+                    "cannot assign a tuple of {len} values to {tuple_name}"
+                ));
+            }
+            self.build_tuple(&tuple_name, value)?
+        } else {
+            vec![TypedDef(
+                tuple_name.clone(),
+                VarType::Field(vec![names.len()]),
+                self.build_expr_from_pair(value)?,
+            )]
+        };
+        for (i, name) in names.into_iter().enumerate() {
+            if is_let {
+                self.var_structs.remove(&name);
+            }
+            out.push(Stmt(
+                name,
+                is_let,
+                Expr::Val(tuple_name.clone(), vec![Expr::Lit(i.to_string())]),
+            ));
+        }
+        if is_let {
+            Ok(out)
+        } else {
+            // assigning existing variables, the tuple is
+            // scoped to a block that runs once
+            Ok(vec![Loop(Expr::Lit("1".to_string()), out)])
+        }
+    }
+
+    fn build_struct_lit(
+        &mut self,
+        name: String,
+        is_let: bool,
+        pair: Pair<Rule>,
+    ) -> Result<Vec<AstNode>> {
+        let mut pair = pair.into_inner();
+        let struct_name = AshParser::next_or_error(&mut pair)?.as_str().to_string();
+        let fields = self.struct_fields(&struct_name)?.clone();
+        let mut values: Vec<Option<Expr>> = vec![None; fields.len()];
+        for init in pair {
+            let mut init = init.into_inner();
+            let field = AshParser::next_or_error(&mut init)?.as_str().to_string();
+            let expr = self.build_expr_from_pair(AshParser::next_or_error(&mut init)?)?;
+            match fields.iter().position(|v| *v == field) {
+                Some(i) if values[i].is_none() => values[i] = Some(expr),
+                Some(_) => {
+                    return error!(TypeError: &format!(
+                        "field \"{field}\" is assigned more than once in {struct_name} literal"
+                    ))
+                }
+                None => {
+                    return error!(
+                        TypeError: &format!("struct {struct_name} has no field \"{field}\""),
+                        &format!("the fields of {struct_name} are: {}", fields.join(", "))
+                    )
+                }
+            }
+        }
+        // fields are assigned in order, so a literal may only
+        // declare a new variable
+        if !is_let {
+            return error!(
+                TypeError: &format!("cannot assign a {struct_name} literal to existing variable \"{name}\""),
+                &format!("assign each field instead e.g. `{name}.{} = v`", fields[0])
+            );
+        }
+        let mut out = vec![EmptyVecDef(name.clone(), vec![fields.len()])];
+        for (i, (field, value)) in fields.iter().zip(values).enumerate() {
+            match value {
+                Some(expr) => out.push(AssignVec(
+                    name.clone(),
+                    vec![Expr::Lit(i.to_string())],
+                    expr,
+                )),
+                None => {
+                    return error!(TypeError: &format!(
+                        "missing field \"{field}\" in {struct_name} literal"
+                    ))
+                }
+            }
+        }
+        self.var_structs.insert(name, struct_name);
+        Ok(out)
+    }
+
     fn build_ast_from_pair(&mut self, pair: pest::iterators::Pair<Rule>) -> Result<AstNode> {
         match pair.as_rule() {
+            Rule::field_assign => {
+                let mut pair = pair.into_inner();
+                let mut access = AshParser::next_or_error(&mut pair)?.into_inner();
+                let name = AshParser::next_or_error(&mut access)?.as_str().to_string();
+                let field = AshParser::next_or_error(&mut access)?.as_str().to_string();
+                let offset = self.field_offset(&name, &field)?;
+                let expr = self.build_expr_from_pair(AshParser::next_or_error(&mut pair)?)?;
+                Ok(AssignVec(name, vec![offset], expr))
+            }
             Rule::var_index_assign => {
                 let mut pair = pair.into_inner();
                 let next = AshParser::next_or_error(&mut pair)?;
@@ -174,6 +812,9 @@ impl AshParser {
                         name = n;
                         indices = i;
                     }
+                    Expr::FnCall(n, _) if n == builtin::SLICE => {
+                        return error!(ParseError: "a range of a vector cannot be assigned");
+                    }
                     _ => {
                         anyhow::bail!("unexpected expr in var_index_assign: {:?}, expected Val", v)
                     }
@@ -193,7 +834,7 @@ impl AshParser {
                         for i in indices {
                             match i {
                                 Expr::Lit(v) => {
-                                    indices_static.push(v.parse::<usize>().unwrap());
+                                    indices_static.push(v.parse::<usize>()?);
                                 }
                                 _ => {
                                     anyhow::bail!(
@@ -215,20 +856,111 @@ impl AshParser {
                 let iter_count = AshParser::next_or_error(&mut pair)?;
                 let iter_count_expr = self.build_expr_from_pair(iter_count)?;
                 let block = AshParser::next_or_error(&mut pair)?;
-                let block_inner = block.into_inner();
-                let block_ast = block_inner
-                    .map(|v| match v.as_rule() {
-                        Rule::stmt => {
-                            let mut pair = v.into_inner();
-                            let next = AshParser::next_or_error(&mut pair)?;
-                            self.build_ast_from_pair(next)
-                        }
-                        _ => Err(anyhow::anyhow!("invalid expression in block")),
-                    })
-                    .collect::<Result<Vec<AstNode>>>()?;
+                let block_ast = self.build_block_from_pair(block)?;
                 Ok(Loop(iter_count_expr, block_ast))
             }
+            Rule::while_stmt => {
+                let mut pair = pair.into_inner();
+                let bool_expr = AshParser::next_or_error(&mut pair)?;
+                let bool_expr = self.build_bool_expr_from_pair(bool_expr)?;
+                let block = AshParser::next_or_error(&mut pair)?;
+                let block_ast = self.build_block_from_pair(block)?;
+                Ok(While(bool_expr, block_ast))
+            }
             Rule::function_call => Ok(ExprUnassigned(self.build_expr_from_pair(pair)?)),
+            Rule::assert_stmt => {
+                // the statement is the default message
+                let source = pair.as_str().to_string();
+                let mut pair = pair.into_inner();
+                let condition = AshParser::next_or_error(&mut pair)?;
+                let condition = match condition.as_rule() {
+                    Rule::bool_expr => self.build_bool_expr_from_pair(condition)?,
+                    _ => self.build_expr_from_pair(condition)?,
+                };
+                let message = match pair.next() {
+                    Some(message) => AshParser::next_or_error(&mut message.into_inner())?
+                        .as_str()
+                        .to_string(),
+                    None => source,
+                };
+                Ok(Assert(condition, message))
+            }
+            Rule::lookup_stmt => {
+                let mut pair = pair.into_inner();
+                let name = AshParser::next_or_error(&mut pair)?.as_str();
+                let table = match self.tables.get(name) {
+                    Some(table) => table.clone(),
+                    None => {
+                        return error!(
+                            ScopeError: &format!("unknown table: {name}"),
+                            "tables must be defined in the file they are used in, e.g. `table byte = 0..256`"
+                        )
+                    }
+                };
+                let values = AshParser::next_or_error(&mut pair)?;
+                let entries = match values.as_rule() {
+                    Rule::tuple => values
+                        .into_inner()
+                        .map(|v| self.build_expr_from_pair(v))
+                        .collect::<Result<Vec<_>>>()?,
+                    _ => vec![self.build_expr_from_pair(values)?],
+                };
+                Ok(Lookup(table, entries))
+            }
+            Rule::pub_input => {
+                let mut pair = pair.into_inner();
+                let name = AshParser::next_or_error(&mut pair)?.as_str().to_string();
+                Ok(PubInput(name))
+            }
+            Rule::pub_output => {
+                let mut pair = pair.into_inner();
+                let name = AshParser::next_or_error(&mut pair)?.as_str().to_string();
+                Ok(PubOutput(name))
+            }
+            Rule::secret_input => {
+                let mut pair = pair.into_inner();
+                let name = AshParser::next_or_error(&mut pair)?.as_str().to_string();
+                Ok(SecretInput(name))
+            }
+            Rule::ar1cs_stmt | Rule::asm_stmt => {
+                let rule = pair.as_rule();
+                let mut pair = pair.into_inner();
+                let mut ports = || -> Result<Vec<String>> {
+                    Ok(AshParser::next_or_error(&mut pair)?
+                        .into_inner()
+                        .map(|v| v.as_str().to_string())
+                        .collect())
+                };
+                let args = ports()?;
+                let returns = ports()?;
+                let source = AshParser::next_or_error(&mut pair)?.as_str().to_string();
+                match rule {
+                    Rule::ar1cs_stmt => Ok(Ar1cs(args, returns, source)),
+                    _ => Ok(Asm(args, returns, source)),
+                }
+            }
+            Rule::unconstrained_stmt => {
+                let mut pair = pair.into_inner();
+                let returns = AshParser::next_or_error(&mut pair)?
+                    .into_inner()
+                    .map(|v| v.as_str().to_string())
+                    .collect::<Vec<_>>();
+                if returns.is_empty() {
+                    return log::error!(
+                        ParseError: "an unconstrained block must declare at least one value",
+                        "list the variables the block defines, e.g. `unconstrained (b) { ... }`"
+                    );
+                }
+                let block = AshParser::next_or_error(&mut pair)?;
+                let block_ast = self.build_block_from_pair(block)?;
+                if ReturnType::from_ast(&block_ast) != ReturnType::Void {
+                    return log::error!(
+                        ParseError: "cannot return from an unconstrained block",
+                        "declare the value in the block and return it after the block"
+                    );
+                }
+                Ok(Unconstrained(returns, block_ast))
+            }
             Rule::var_def => {
                 // get vardef
                 let mut pair = pair.into_inner();
@@ -252,6 +984,40 @@ impl AshParser {
                 let n = AshParser::next_or_error(&mut pair)?;
                 Ok(Stmt(name, is_let, self.build_expr_from_pair(n)?))
             }
+            Rule::typed_def => {
+                let mut pair = pair.into_inner();
+                // skip the let
+                AshParser::next_or_error(&mut pair)?;
+                let name = AshParser::next_or_error(&mut pair)?.as_str().to_string();
+                let type_pair = AshParser::next_or_error(&mut pair)?;
+                self.var_structs.remove(&name);
+                let var_type = match type_pair.as_rule() {
+                    // structs are stored as a vector with an element
+                    // for each field
+                    Rule::struct_type => {
+                        let len = self.struct_fields(type_pair.as_str())?.len();
+                        self.var_structs
+                            .insert(name.clone(), type_pair.as_str().to_string());
+                        VarType::Field(vec![len])
+                    }
+                    // extension field elements are stored as a vector
+                    // of base field coefficients
+                    Rule::xfe_type => VarType::Field(vec![builtin::EXTENSION_DEGREE]),
+                    Rule::field_type => VarType::Field(
+                        type_pair
+                            .into_inner()
+                            .map(|v| v.as_str().parse::<usize>())
+                            .collect::<Result<Vec<_>, _>>()?,
+                    ),
+                    _ => VarType::Int(match type_pair.as_str() {
+                        "u32" => IntType::U32,
+                        "u64" => IntType::U64,
+                        v => anyhow::bail!("unknown integer type: {v}"),
+                    }),
+                };
+                let expr = AshParser::next_or_error(&mut pair)?;
+                Ok(TypedDef(name, var_type, self.build_expr_from_pair(expr)?))
+            }
             Rule::static_def => {
                 let mut pair = pair.into_inner();
                 let name = AshParser::next_or_error(&mut pair)?.as_str().to_string();
@@ -264,38 +1030,18 @@ impl AshParser {
             Rule::if_stmt => {
                 let mut pair = pair.into_inner();
                 let bool_expr = AshParser::next_or_error(&mut pair)?;
-                let mut bool_expr_pair = bool_expr.into_inner();
-                let expr1 =
-                    self.build_expr_from_pair(AshParser::next_or_error(&mut bool_expr_pair)?)?;
-                let bool_op = match AshParser::next_or_error(&mut bool_expr_pair)?.as_rule() {
-                    Rule::equal => BoolOp::Equal,
-                    Rule::not_equal => BoolOp::NotEqual,
-                    Rule::gt => BoolOp::GreaterThan,
-                    Rule::lt => BoolOp::LessThan,
-                    _ => anyhow::bail!("invalid bool op"),
-                };
-                let expr2 =
-                    self.build_expr_from_pair(AshParser::next_or_error(&mut bool_expr_pair)?)?;
+                let bool_expr = self.build_bool_expr_from_pair(bool_expr)?;
                 let block = AshParser::next_or_error(&mut pair)?;
-                let block_inner = block.into_inner();
-                let block_ast = block_inner
-                    .map(|v| match v.as_rule() {
-                        Rule::stmt => {
-                            let mut pair = v.into_inner();
-                            let next = AshParser::next_or_error(&mut pair)?;
-                            self.build_ast_from_pair(next)
-                        }
-                        _ => anyhow::bail!("invalid expression in block"),
-                    })
-                    .collect::<Result<Vec<AstNode>>>()?;
-                Ok(If(
-                    Expr::BoolOp {
-                        lhs: Box::new(expr1),
-                        bool_op,
-                        rhs: Box::new(expr2),
+                let block_ast = self.build_block_from_pair(block)?;
+                let else_ast = match pair.next() {
+                    Some(next) => match next.as_rule() {
+                        Rule::if_stmt => vec![self.build_ast_from_pair(next)?],
+                        Rule::block => self.build_block_from_pair(next)?,
+                        _ => anyhow::bail!("invalid else clause"),
                     },
-                    block_ast,
-                ))
+                    None => vec![],
+                };
+                Ok(If(bool_expr, block_ast, else_ast))
             }
             unknown_expr => anyhow::bail!(
                 "Unable to build ast node, unexpected expression: {:?}",
@@ -304,18 +1050,184 @@ impl AshParser {
         }
     }
 
+    // build the list of statements contained in a `block` rule
+    fn build_block_from_pair(&mut self, pair: Pair<Rule>) -> Result<Vec<AstNode>> {
+        let mut out = vec![];
+        for v in pair.into_inner() {
+            match v.as_rule() {
+                Rule::stmt => {
+                    out.push(self.location(&v));
+                    let mut pair = v.into_inner();
+                    let next = AshParser::next_or_error(&mut pair)?;
+                    out.append(&mut self.build_stmt_from_pair(next)?);
+                }
+                _ => anyhow::bail!("invalid expression in block"),
+            }
+        }
+        Ok(out)
+    }
+
+    // build an Expr::BoolOp from a `bool_expr` rule
+    fn build_bool_expr_from_pair(&mut self, pair: Pair<Rule>) -> Result<Expr> {
+        let mut pair = pair.into_inner();
+        if pair.len() == 1 {
+            return self.build_bool_cmp_from_pair(AshParser::next_or_error(&mut pair)?);
+        }
+        let pratt = PrattParser::new()
+            .op(Op::infix(Rule::or, Assoc::Left))
+            .op(Op::infix(Rule::and, Assoc::Left));
+        pratt
+            .map_primary(|primary| match primary.as_rule() {
+                Rule::bool_cmp => self.build_bool_cmp_from_pair(primary),
+                _ => Err(anyhow::anyhow!("unexpected rule in pratt parser")),
+            })
+            .map_infix(|lhs, op, rhs| match op.as_rule() {
+                Rule::and => Ok(Expr::BoolOp {
+                    lhs: Box::new(lhs?),
+                    bool_op: BoolOp::And,
+                    rhs: Box::new(rhs?),
+                }),
+                Rule::or => Ok(Expr::BoolOp {
+                    lhs: Box::new(lhs?),
+                    bool_op: BoolOp::Or,
+                    rhs: Box::new(rhs?),
+                }),
+                _ => unreachable!(),
+            })
+            .parse(pair)
+    }
+
+    fn build_bool_cmp_from_pair(&mut self, pair: Pair<Rule>) -> Result<Expr> {
+        let mut bool_expr_pair = pair.into_inner();
+        let expr1 = self.build_expr_from_pair(AshParser::next_or_error(&mut bool_expr_pair)?)?;
+        let bool_op = match AshParser::next_or_error(&mut bool_expr_pair)?.as_rule() {
+            Rule::equal => BoolOp::Equal,
+            Rule::not_equal => BoolOp::NotEqual,
+            Rule::gt => BoolOp::GreaterThan,
+            Rule::lt => BoolOp::LessThan,
+            Rule::gte => BoolOp::GreaterThanOrEqual,
+            Rule::lte => BoolOp::LessThanOrEqual,
+            _ => anyhow::bail!("invalid bool op"),
+        };
+        let expr2 = self.build_expr_from_pair(AshParser::next_or_error(&mut bool_expr_pair)?)?;
+        Ok(Expr::BoolOp {
+            lhs: Box::new(expr1),
+            bool_op,
+            rhs: Box::new(expr2),
+        })
+    }
+
+    // the decimal value of a numeric literal, e.g. `0xff` and
+    // `0b1111_1111` are `255`. Leading zeros are removed so equal
+    // values have the same representation
+    fn literal(text: &str) -> Result<String> {
+        let digits = text.replace('_', "");
+        let (digits, radix) = match digits.get(..2) {
+            Some("0x") => (&digits[2..], 16),
+            Some("0b") => (&digits[2..], 2),
+            _ => (digits.as_str(), 10),
+        };
+        match BigUint::parse_bytes(digits.as_bytes(), radix) {
+            Some(v) => Ok(v.to_string()),
+            None => error!(ParseError: &format!("invalid numeric literal \"{text}\"")),
+        }
+    }
+
+    // a byte string is a vector literal with one element for
+    // each byte, e.g. `b"ab"` is `[97, 98]`
+    fn build_byte_str(pair: pest::iterators::Pair<Rule>) -> Result<Expr> {
+        let text = AshParser::next_or_error(&mut pair.into_inner())?.as_str();
+        let mut out = vec![];
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            let byte = match c {
+                '\\' => match chars.next() {
+                    Some('\\') => b'\\',
+                    Some('"') => b'"',
+                    Some('n') => b'\n',
+                    Some('t') => b'\t',
+                    Some('0') => 0,
+                    Some('x') => {
+                        let hex = chars.by_ref().take(2).collect::<String>();
+                        match u8::from_str_radix(&hex, 16) {
+                            Ok(v)
+                                if hex.len() == 2 && hex.chars().all(|c| c.is_ascii_hexdigit()) =>
+                            {
+                                v
+                            }
+                            _ => {
+                                return error!(
+                                    ParseError: &format!("invalid byte escape \"\\x{hex}\" in b\"{text}\""),
+                                    "a byte escape is two hex digits, e.g. `\\x7f`"
+                                );
+                            }
+                        }
+                    }
+                    v => {
+                        return error!(
+                            ParseError: &format!("invalid escape \"\\{}\" in b\"{text}\"", v.unwrap_or_default()),
+                            "the escapes are `\\\\`, `\\\"`, `\\n`, `\\t`, `\\0`, and `\\xNN`"
+                        );
+                    }
+                },
+                c if c.is_ascii() => c as u8,
+                c => {
+                    return error!(
+                        ParseError: &format!("non-ascii character \"{c}\" in b\"{text}\""),
+                        "write other bytes using escapes, e.g. `\\xff`"
+                    );
+                }
+            };
+            out.push(byte.to_string());
+        }
+        if out.is_empty() {
+            return error!(ParseError: "a byte string must contain at least one byte");
+        }
+        Ok(Expr::VecLit(out))
+    }
+
+    // a range in the last index is a slice of the indexed value,
+    // e.g. `v[1][2..5]` is `std::slice(v[1], 2, 5)`
+    fn build_var_indexed(&mut self, pair: pest::iterators::Pair<Rule>) -> Result<Expr> {
+        let mut pair = pair.into_inner();
+        let name = AshParser::next_or_error(&mut pair)?.as_str().to_string();
+        let mut indices: Vec<Expr> = Vec::new();
+        let mut range = None;
+        for v in pair {
+            if range.is_some() {
+                return error!(ParseError: "a range may only be the last index of a variable");
+            }
+            if v.as_rule() == Rule::index_range {
+                let mut bounds = v.into_inner();
+                let start = self.build_expr_from_pair(AshParser::next_or_error(&mut bounds)?)?;
+                let end = self.build_expr_from_pair(AshParser::next_or_error(&mut bounds)?)?;
+                range = Some((start, end));
+                continue;
+            }
+            indices.push(self.build_expr_from_pair(v)?);
+        }
+        Ok(match range {
+            Some((start, end)) => Expr::FnCall(
+                builtin::SLICE.to_string(),
+                vec![Expr::Val(name, indices), start, end],
+            ),
+            None => Expr::Val(name, indices),
+        })
+    }
+
     fn build_expr_from_pair(&mut self, pair: pest::iterators::Pair<Rule>) -> Result<Expr> {
         match pair.as_rule() {
-            Rule::var_indexed => {
+            Rule::var_indexed => self.build_var_indexed(pair),
+            Rule::literal => Ok(Expr::Lit(AshParser::literal(pair.as_str())?)),
+            Rule::ref_arg => {
                 let mut pair = pair.into_inner();
-                let name = AshParser::next_or_error(&mut pair)?.as_str().to_string();
-                let mut indices: Vec<Expr> = Vec::new();
-                for v in pair {
-                    indices.push(self.build_expr_from_pair(v)?);
+                let next = AshParser::next_or_error(&mut pair)?;
+                match self.build_expr_from_pair(next.clone())? {
+                    Expr::Val(name, indices) => Ok(Expr::Ref(name, indices)),
+                    _ => anyhow::bail!("invalid reference: {}", next.as_str()),
                 }
-                Ok(Expr::Val(name, indices))
             }
-            Rule::literal_dec => Ok(Expr::Lit(pair.as_str().to_string())),
+            Rule::varname => Ok(Expr::Val(pair.as_str().to_string(), vec![])),
             Rule::vec => {
                 let mut pair = pair.into_inner();
                 let next = AshParser::next_or_error(&mut pair)?;
@@ -329,9 +1241,9 @@ impl AshParser {
                     Ok(Expr::VecVec(out))
                 } else {
                     let mut out: Vec<String> = Vec::new();
-                    out.push(next.as_str().to_string());
+                    out.push(AshParser::literal(next.as_str())?);
                     for next in pair {
-                        out.push(next.as_str().to_string());
+                        out.push(AshParser::literal(next.as_str())?);
                     }
                     Ok(Expr::VecLit(out))
                 }
@@ -339,7 +1251,7 @@ impl AshParser {
             Rule::function_call => {
                 let mut pair = pair.into_inner();
                 let next = AshParser::next_or_error(&mut pair)?;
-                let fn_name = next.as_str().to_string();
+                let fn_name = self.resolve_fn_name(next.as_str());
                 let arg_pair = AshParser::next_or_error(&mut pair)?.into_inner();
                 let mut vars: Vec<Expr> = Vec::new();
                 for v in arg_pair {
@@ -354,16 +1266,16 @@ impl AshParser {
                 match n.as_rule() {
                     Rule::function_call => Ok(self.build_expr_from_pair(n)?),
                     Rule::varname => Ok(Expr::Val(n.as_str().to_string(), vec![])),
-                    Rule::var_indexed => {
+                    Rule::var_indexed => self.build_var_indexed(n),
+                    Rule::literal => Ok(Expr::Lit(AshParser::literal(n.as_str())?)),
+                    Rule::byte_str => AshParser::build_byte_str(n),
+                    Rule::field_access => {
                         let mut pair = n.into_inner();
                         let name = AshParser::next_or_error(&mut pair)?.as_str().to_string();
-                        let mut indices: Vec<Expr> = Vec::new();
-                        for v in pair {
-                            indices.push(self.build_expr_from_pair(v)?);
-                        }
-                        Ok(Expr::Val(name, indices))
+                        let field = AshParser::next_or_error(&mut pair)?.as_str();
+                        let offset = self.field_offset(&name, field)?;
+                        Ok(Expr::Val(name, vec![offset]))
                     }
-                    Rule::literal_dec => Ok(Expr::Lit(n.as_str().to_string())),
                     _ => anyhow::bail!("invalid atom"),
                 }
             }
@@ -372,15 +1284,32 @@ impl AshParser {
                 if pair.len() == 1 {
                     return self.build_expr_from_pair(AshParser::next_or_error(&mut pair)?);
                 }
+                // `**` calls std::pow
+                if pair.clone().any(|v| v.as_rule() == Rule::pow) {
+                    self.mark_fn_call(builtin::POW.to_string());
+                }
+                // lowest precedence first, matching rust
                 let pratt = PrattParser::new()
+                    .op(Op::infix(Rule::bit_or, Assoc::Left))
+                    .op(Op::infix(Rule::bit_xor, Assoc::Left))
+                    .op(Op::infix(Rule::bit_and, Assoc::Left))
+                    .op(Op::infix(Rule::shl, Assoc::Left) | Op::infix(Rule::shr, Assoc::Left))
                     .op(Op::infix(Rule::add, Assoc::Left) | Op::infix(Rule::sub, Assoc::Left))
-                    .op(Op::infix(Rule::mul, Assoc::Left) | Op::infix(Rule::inv, Assoc::Left));
+                    .op(Op::infix(Rule::mul, Assoc::Left)
+                        | Op::infix(Rule::inv, Assoc::Left)
+                        | Op::infix(Rule::matmul, Assoc::Left))
+                    .op(Op::prefix(Rule::neg))
+                    .op(Op::infix(Rule::pow, Assoc::Right));
                 pratt
                     .map_primary(|primary| match primary.as_rule() {
                         Rule::atom => self.build_expr_from_pair(primary),
                         Rule::expr => self.build_expr_from_pair(primary),
                         _ => Err(anyhow::anyhow!("unexpected rule in pratt parser")),
                     })
+                    .map_prefix(|op, rhs| match op.as_rule() {
+                        Rule::neg => Ok(Expr::FnCall(builtin::NEG.to_string(), vec![rhs?])),
+                        _ => Err(anyhow::anyhow!("unexpected prefix in pratt parser")),
+                    })
                     .map_infix(|lhs, op, rhs| match op.as_rule() {
                         Rule::add => Ok(Expr::NumOp {
                             lhs: Box::new(lhs?),
@@ -402,7 +1331,25 @@ impl AshParser {
                             op: NumOp::Inv,
                             rhs: Box::new(rhs?),
                         }),
-                        _ => unreachable!(),
+                        Rule::matmul => {
+                            Ok(Expr::FnCall(builtin::MATMUL.to_string(), vec![lhs?, rhs?]))
+                        }
+                        Rule::pow => Ok(Expr::FnCall(builtin::POW.to_string(), vec![lhs?, rhs?])),
+                        rule => {
+                            let bit_op = match rule {
+                                Rule::bit_and => BitOp::And,
+                                Rule::bit_or => BitOp::Or,
+                                Rule::bit_xor => BitOp::Xor,
+                                Rule::shl => BitOp::Shl,
+                                Rule::shr => BitOp::Shr,
+                                _ => unreachable!(),
+                            };
+                            Ok(Expr::BitOp {
+                                lhs: Box::new(lhs?),
+                                bit_op,
+                                rhs: Box::new(rhs?),
+                            })
+                        }
                     })
                     .parse(pair)
             }
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::parser::SourceLocation;
+
+// an asm comment marking the source line of the instructions
+// that follow it, e.g. `// ash 4 ./test-vectors/f.ash`
+static MARKER: &str = "// ash ";
+
+/// The cost of a single source line.
+///
+/// `count` is the number of constraints in the r1cs target or the
+/// number of instructions in the tasm target. `cycles` is the number
+/// of instructions executed in the tasm target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProfileLine {
+    pub file: String,
+    pub line: usize,
+    pub snippet: String,
+    pub count: usize,
+    pub cycles: u64,
+}
+
+/// Compiled output attributed to the source lines that produced it.
+///
+/// Constraints and instructions produced by a call are attributed to
+/// the lines of the called function, so the cost of a line excludes
+/// the functions it calls.
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    /// What `count` measures, e.g. `constraints`.
+    pub unit: String,
+    lines: Vec<ProfileLine>,
+    // index of each (file, line) in `lines`
+    index: HashMap<(String, usize), usize>,
+    total: usize,
+}
+
+impl Profile {
+    pub fn new(unit: &str) -> Self {
+        Self {
+            unit: unit.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Add to the count of a source line.
+    pub fn record(&mut self, location: &SourceLocation, count: usize) {
+        let key = (location.file.clone(), location.line);
+        let i = match self.index.get(&key) {
+            Some(i) => *i,
+            None => {
+                self.lines.push(ProfileLine {
+                    file: location.file.clone(),
+                    line: location.line,
+                    snippet: location.snippet.trim().to_string(),
+                    count: 0,
+                    cycles: 0,
+                });
+                self.index.insert(key, self.lines.len() - 1);
+                self.lines.len() - 1
+            }
+        };
+        self.lines[i].count += count;
+        self.total += count;
+    }
+
+    /// The sum of the counts of every line.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The asm comment marking the instructions of a source line.
+    pub fn marker(location: &SourceLocation) -> String {
+        format!("{MARKER}{} {}", location.line, location.file)
+    }
+
+    // the source line and size of each instruction in tasm
+    // assembly, instructions before the first marker of a
+    // label are not attributed
+    fn instructions(&self, asm: &str) -> Vec<(Option<usize>, usize)> {
+        let mut out = vec![];
+        let mut current = None;
+        for line in asm.lines() {
+            let line = line.trim();
+            if let Some(marker) = line.strip_prefix(MARKER) {
+                current = marker.split_once(' ').and_then(|(line, file)| {
+                    let line = line.parse::<usize>().ok()?;
+                    self.index.get(&(file.to_string(), line)).copied()
+                });
+                continue;
+            }
+            let instruction = line.split("//").next().unwrap_or_default().trim();
+            if instruction.is_empty() {
+                continue;
+            }
+            if instruction.ends_with(':') {
+                current = None;
+                continue;
+            }
+            // instructions with an argument use 2 words
+            out.push((current, instruction.split_whitespace().count().min(2)));
+        }
+        out
+    }
+
+    /// The index of the source line of each instruction address
+    /// in tasm assembly.
+    pub fn addresses(&self, asm: &str) -> Vec<Option<usize>> {
+        self.instructions(asm)
+            .into_iter()
+            .flat_map(|(line, size)| vec![line; size])
+            .collect()
+    }
+
+    /// Count the instructions attributed to each source line.
+    pub fn count_instructions(&mut self, asm: &str) {
+        for (line, _) in self.instructions(asm) {
+            if let Some(i) = line {
+                self.lines[i].count += 1;
+                self.total += 1;
+            }
+        }
+    }
+
+    /// Add an executed instruction to its source line.
+    pub fn record_cycle(&mut self, addresses: &[Option<usize>], instruction_pointer: usize) {
+        if let Some(Some(i)) = addresses.get(instruction_pointer) {
+            self.lines[*i].cycles += 1;
+        }
+    }
+
+    /// Each source line, most expensive first.
+    pub fn lines(&self) -> Vec<&ProfileLine> {
+        let mut lines = self.lines.iter().collect::<Vec<_>>();
+        lines.sort_by(|a, b| {
+            b.cycles
+                .cmp(&a.cycles)
+                .then(b.count.cmp(&a.count))
+                .then(a.file.cmp(&b.file))
+                .then(a.line.cmp(&b.line))
+        });
+        lines
+    }
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let lines = self.lines();
+        let show_cycles = lines.iter().any(|v| v.cycles > 0);
+        let location = |v: &ProfileLine| format!("{}:{}", v.file, v.line);
+        let width = lines
+            .iter()
+            .map(|v| location(v).len())
+            .chain(["location".len()])
+            .max()
+            .unwrap_or_default();
+        let unit_width = self.unit.len().max(8);
+        let header = if show_cycles {
+            format!("{:>unit_width$}  {:>10}", self.unit, "cycles")
+        } else {
+            format!("{:>unit_width$}", self.unit)
+        };
+        writeln!(f, "{header}  {:width$}  source", "location")?;
+        for v in &lines {
+            let cost = if show_cycles {
+                format!("{:>unit_width$}  {:>10}", v.count, v.cycles)
+            } else {
+                format!("{:>unit_width$}", v.count)
+            };
+            writeln!(f, "{cost}  {:width$}  {}", location(v), v.snippet)?;
+        }
+        // each file is a function
+        let mut functions: Vec<(String, usize, u64)> = vec![];
+        for v in &self.lines {
+            match functions.iter_mut().find(|(file, _, _)| *file == v.file) {
+                Some(function) => {
+                    function.1 += v.count;
+                    function.2 += v.cycles;
+                }
+                None => functions.push((v.file.clone(), v.count, v.cycles)),
+            }
+        }
+        functions.sort_by(|a, b| b.2.cmp(&a.2).then(b.1.cmp(&a.1)).then(a.0.cmp(&b.0)));
+        writeln!(f)?;
+        writeln!(f, "{header}  function")?;
+        for (file, count, cycles) in functions {
+            let cost = if show_cycles {
+                format!("{:>unit_width$}  {:>10}", count, cycles)
+            } else {
+                format!("{:>unit_width$}", count)
+            };
+            writeln!(f, "{cost}  {file}")?;
+        }
+        write!(f, "total {}: {}", self.unit, self.total)
+    }
+}
@@ -0,0 +1,194 @@
+//! A compiled program and each step of proving it. A program is
+//! compiled once, then its constraints can be inspected, its
+//! witness built, and proofs generated and verified in any prover
+//! supporting its target and field. Each step can be called
+//! separately, e.g. to build a witness without proving.
+//!
+//! ```no_run
+//! use std::collections::HashMap;
+//!
+//! use ashlang::rings::Curve25519PolynomialRing;
+//! use ashlang::Config;
+//! use ashlang::Program;
+//! use ashlang::SpartanProver;
+//!
+//! let config = Config::new("main", "r1cs", "curve25519");
+//! let sources = HashMap::from([(
+//!     "main.ash".to_string(),
+//!     "secret input x\nlet y = x * x\npub output y\n".to_string(),
+//! )]);
+//! let program = Program::<Curve25519PolynomialRing>::from_sources(&config, sources)?;
+//! println!("{} constraints", program.stats()?.constraints);
+//! let witness = program.witness(&["5".to_string()])?;
+//! println!("outputs: {:?}", program.outputs(&witness)?);
+//! let (pk, vk) = program.setup::<SpartanProver>()?;
+//! let proof = program.prove::<SpartanProver>(&pk, vec![], vec!["5".to_string()])?;
+//! assert!(program.verify::<SpartanProver>(&vk, &proof)?);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use anyhow::Result;
+use ring_math::PolynomialRingElement;
+
+use crate::cli::Config;
+use crate::compiler::Compiler;
+use crate::compiler::PublicKind;
+use crate::compiler::PublicValue;
+use crate::log;
+use crate::provers::AshlangProver;
+use crate::r1cs::parser::R1csParser;
+use crate::r1cs::witness;
+use crate::r1cs::witness::Witness;
+
+/// A program compiled for a target and field. `T` is the ring of
+/// the field the program is compiled in.
+#[derive(Clone, Debug)]
+pub struct Program<T: PolynomialRingElement> {
+    config: Config,
+    ir: String,
+    fingerprint: String,
+    public_values: Vec<PublicValue>,
+    _ring: PhantomData<T>,
+}
+
+/// The size of a compiled r1cs program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProgramStats {
+    /// constraints checked against the witness
+    pub constraints: usize,
+    /// symbolic constraints used to calculate the witness
+    pub witness_steps: usize,
+    /// the length of the witness, including the one signal
+    pub signals: usize,
+    /// non-zero coefficients in the constraints
+    pub coefficients: usize,
+    pub public_inputs: usize,
+    pub public_outputs: usize,
+}
+
+impl<T: PolynomialRingElement> Program<T> {
+    /// Compile the entry function of a config, reading functions
+    /// from the include paths.
+    pub fn compile(config: &Config) -> Result<Self> {
+        let compiler = Compiler::new(config)?;
+        Self::build(config, compiler)
+    }
+
+    /// Compile the entry function of a config, reading functions
+    /// from in-memory sources, see `Compiler::from_sources`.
+    pub fn from_sources(config: &Config, sources: HashMap<String, String>) -> Result<Self> {
+        let compiler = Compiler::from_sources(config, sources)?;
+        Self::build(config, compiler)
+    }
+
+    fn build(config: &Config, mut compiler: Compiler<T>) -> Result<Self> {
+        let ir = compiler.compile(&config.entry_fn)?;
+        Ok(Self {
+            config: config.clone(),
+            ir,
+            fingerprint: compiler.fingerprint().unwrap_or_default().to_string(),
+            public_values: compiler.public_values().to_vec(),
+            _ring: PhantomData,
+        })
+    }
+
+    /// The compiled ar1cs or tasm.
+    pub fn ir(&self) -> &str {
+        &self.ir
+    }
+
+    /// The target the program is compiled for.
+    pub fn target(&self) -> &str {
+        &self.config.target
+    }
+
+    /// A hash identifying the program, see `Compiler::fingerprint`.
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// Values declared using `pub input` and `pub output` in the
+    /// entry function.
+    pub fn public_values(&self) -> &[PublicValue] {
+        &self.public_values
+    }
+
+    /// Count the constraints and signals of an r1cs program.
+    pub fn stats(&self) -> Result<ProgramStats> {
+        self.require_r1cs("constraint stats")?;
+        let r1cs: R1csParser<T> = R1csParser::new(&self.ir)?;
+        let (symbolic, constraints): (Vec<_>, Vec<_>) =
+            r1cs.constraints.iter().partition(|c| c.symbolic);
+        let public =
+            |kind: PublicKind| self.public_values.iter().filter(|v| v.kind == kind).count();
+        Ok(ProgramStats {
+            constraints: constraints.len(),
+            witness_steps: symbolic.len(),
+            signals: r1cs.var_count(),
+            coefficients: constraints
+                .iter()
+                .map(|c| c.a.len() + c.b.len() + c.c.len())
+                .sum(),
+            public_inputs: public(PublicKind::Input),
+            public_outputs: public(PublicKind::Output),
+        })
+    }
+
+    /// Build the witness of an r1cs program and check it against
+    /// the constraints. Inputs are decimal strings, read in the
+    /// order they are declared in the program.
+    pub fn witness(&self, secret_inputs: &[String]) -> Result<Witness<T::F>> {
+        self.require_r1cs("witnesses")?;
+        let inputs = secret_inputs
+            .iter()
+            .map(|v| T::from_str(v))
+            .collect::<Result<Vec<_>>>()?;
+        let witness = witness::build::<T>(&self.ir, inputs)?;
+        witness::verify::<T>(&self.ir, witness.clone())?;
+        Ok(witness)
+    }
+
+    /// The values written to the public outputs by a witness of
+    /// this program, in the order they are written.
+    pub fn outputs(&self, witness: &Witness<T::F>) -> Result<Vec<String>> {
+        self.require_r1cs("witnesses")?;
+        witness::public_outputs::<T>(&self.ir, &witness.variables)
+    }
+
+    /// Derive the proving and verifying keys of the program for a
+    /// prover. The prover must support the target and field of the
+    /// program.
+    pub fn setup<P: AshlangProver>(&self) -> Result<(P::ProvingKey, P::VerifyingKey)> {
+        P::setup(&self.ir).map_err(Into::into)
+    }
+
+    /// Generate a proof of the program using a proving key from
+    /// `setup`.
+    pub fn prove<P: AshlangProver>(
+        &self,
+        pk: &P::ProvingKey,
+        public_inputs: Vec<String>,
+        secret_inputs: Vec<String>,
+    ) -> Result<P::Proof> {
+        P::prove(&self.ir, pk, public_inputs, secret_inputs).map_err(Into::into)
+    }
+
+    /// Verify a proof of the program generated by `prove` using the
+    /// verifying key from `setup`.
+    pub fn verify<P: AshlangProver>(&self, vk: &P::VerifyingKey, proof: &P::Proof) -> Result<bool> {
+        P::verify(vk, proof).map_err(Into::into)
+    }
+
+    // error if a step is only available in the r1cs target
+    fn require_r1cs(&self, step: &str) -> Result<()> {
+        if self.config.target != "r1cs" {
+            return log::error!(
+                ConfigError: &format!("{step} are only available in the r1cs target"),
+                &format!("the program is compiled for {}", self.config.target)
+            );
+        }
+        Ok(())
+    }
+}
@@ -2,17 +2,49 @@ use anyhow::Result;
 
 use crate::cli::Config;
 
-// TODO: make the resulting proof serializable here
-// e.g. `AshlangProver<T: Debug>`
-
 /// A trait representing an abstract prover implementation.
-/// The generic argument indicates the type of the proof that
-/// the prover builds.
-pub trait AshlangProver<T> {
+///
+/// Each prover derives keys from a compiled program using
+/// `setup`, proves the program using the proving key, and
+/// verifies proofs using only the verifying key. Code generic
+/// over `AshlangProver` can switch backends without changing how
+/// proofs are generated and checked.
+pub trait AshlangProver {
+    /// A proof and the public values it binds.
+    type Proof;
+    /// The data needed to prove a program, e.g. the output of a
+    /// trusted setup. Provers without setup use `()`.
+    type ProvingKey;
+    /// The data needed to verify a proof of a program.
+    type VerifyingKey;
+    type Error: Into<anyhow::Error> + std::fmt::Debug;
+
+    /// Derive the proving and verifying keys of a compiled
+    /// program.
+    fn setup(program: &str) -> Result<(Self::ProvingKey, Self::VerifyingKey), Self::Error>;
+    /// Generate a proof of a compiled program using a proving key
+    /// from `setup`.
+    fn prove(
+        program: &str,
+        pk: &Self::ProvingKey,
+        public_inputs: Vec<String>,
+        secret_inputs: Vec<String>,
+    ) -> Result<Self::Proof, Self::Error>;
+    /// Verify a proof using a verifying key from `setup`.
+    fn verify(vk: &Self::VerifyingKey, proof: &Self::Proof) -> Result<bool, Self::Error>;
+    /// The public outputs of the proven program as decimal
+    /// strings, in the order they are written
+    fn outputs(proof: &Self::Proof) -> Vec<String>;
     /// Generate a proof by compiling source files into an IR
-    fn prove(config: &Config) -> Result<T>;
-    /// Generate a proof from an existing IR
-    fn prove_ir(ir: &str, public_inputs: Vec<String>, secret_inputs: Vec<String>) -> Result<T>;
-    /// Verify a proof
-    fn verify(program: &str, proof: T) -> Result<bool>;
+    fn prove_config(config: &Config) -> Result<Self::Proof, Self::Error>;
+
+    /// Run `setup` and generate a proof of a compiled program.
+    fn prove_ir(
+        program: &str,
+        public_inputs: Vec<String>,
+        secret_inputs: Vec<String>,
+    ) -> Result<Self::Proof, Self::Error> {
+        let (pk, _) = Self::setup(program)?;
+        Self::prove(program, &pk, public_inputs, secret_inputs)
+    }
 }
@@ -0,0 +1,269 @@
+use std::fs;
+
+use anyhow::Result;
+use camino::Utf8Path;
+use ring_math::PolynomialRingElement;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::cache::FnCache;
+use crate::log;
+#[cfg(feature = "groth16-prover")]
+use crate::provers::Groth16Prover;
+#[cfg(feature = "halo2-prover")]
+use crate::provers::Halo2Prover;
+#[cfg(feature = "spartan-prover")]
+use crate::provers::SpartanProver;
+#[cfg(feature = "tritonvm-prover")]
+use crate::provers::TritonVMProver;
+use crate::r1cs::constraint::SymbolicOp;
+use crate::r1cs::parser::R1csParser;
+
+/// A proof that can be stored in a `ProofEnvelope`.
+pub trait EnvelopeProof: Sized {
+    /// The name of the prover, e.g. `spartan`.
+    const BACKEND: &'static str;
+    fn to_bytes(&self) -> Result<Vec<u8>>;
+    fn from_bytes(bytes: &[u8]) -> Result<Self>;
+}
+
+/// A portable, versioned container for a proof. Envelopes are
+/// written as json with the proof bytes hex encoded.
+///
+/// `fingerprint` identifies the proven program, see
+/// `Compiler::fingerprint`. Public inputs and outputs are decimal
+/// strings in the order they are read and written by the program.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofEnvelope {
+    pub version: u32,
+    pub backend: String,
+    pub fingerprint: Option<String>,
+    pub public_inputs: Vec<String>,
+    pub public_outputs: Vec<String>,
+    #[serde(with = "hex")]
+    pub proof: Vec<u8>,
+}
+
+impl ProofEnvelope {
+    /// The envelope format written by this version of ashlang.
+    pub const VERSION: u32 = 1;
+
+    pub fn new<P: EnvelopeProof>(
+        proof: &P,
+        fingerprint: Option<String>,
+        public_inputs: Vec<String>,
+        public_outputs: Vec<String>,
+    ) -> Result<Self> {
+        Ok(ProofEnvelope {
+            version: Self::VERSION,
+            backend: P::BACKEND.to_string(),
+            fingerprint,
+            public_inputs,
+            public_outputs,
+            proof: proof.to_bytes()?,
+        })
+    }
+
+    /// Decode the proof, failing if the envelope was written by
+    /// a different prover.
+    pub fn proof<P: EnvelopeProof>(&self) -> Result<P> {
+        if self.backend != P::BACKEND {
+            return log::error!(
+                ProverError: &format!(
+                    "proof was generated by {}, expected {}",
+                    self.backend,
+                    P::BACKEND
+                ),
+                "verify the proof using the field it was generated in"
+            );
+        }
+        P::from_bytes(&self.proof)
+    }
+
+    /// Split the public values of an r1cs witness, as returned by
+    /// `witness::verify`, into public inputs and outputs.
+    pub fn split_public<T: PolynomialRingElement>(
+        r1cs: &str,
+        values: &[T::F],
+    ) -> Result<(Vec<String>, Vec<String>)> {
+        let mut public_inputs = vec![];
+        let mut public_outputs = vec![];
+        let mut values = values.iter();
+        for op in public_ops::<T>(r1cs)? {
+            let v = match values.next() {
+                Some(v) => v.to_string(),
+                None => return log::error!(ProverError: "witness is missing a public value"),
+            };
+            match op {
+                (SymbolicOp::PublicInput, _) => public_inputs.push(v),
+                _ => public_outputs.push(v),
+            }
+        }
+        Ok((public_inputs, public_outputs))
+    }
+
+    /// The public values of the envelope for each public signal
+    /// of an r1cs program, in the order the signals are marked.
+    /// A signal marked more than once is included once.
+    pub fn public_signals<T: PolynomialRingElement>(&self, r1cs: &str) -> Result<Vec<String>> {
+        self.public_values(&public_ops::<T>(r1cs)?)
+    }
+
+    /// Like `public_signals`, using the public input and output
+    /// constraints of a program and the signal each one marks. The
+    /// values of a signal marked more than once must be equal.
+    pub fn public_values(&self, public_ops: &[(SymbolicOp, usize)]) -> Result<Vec<String>> {
+        let mut public_inputs = self.public_inputs.iter();
+        let mut public_outputs = self.public_outputs.iter();
+        let mut signals = vec![];
+        let mut values: Vec<String> = vec![];
+        for (op, signal) in public_ops {
+            let v = match op {
+                SymbolicOp::PublicInput => public_inputs.next(),
+                _ => public_outputs.next(),
+            };
+            let v = match v {
+                Some(v) => v,
+                None => {
+                    return log::error!(
+                        ProverError: "proof envelope has fewer public values than the program",
+                        "the proof was generated for a different program"
+                    )
+                }
+            };
+            match signals.iter().position(|s| s == signal) {
+                Some(i) if &values[i] != v => {
+                    return log::error!(
+                        ProverError: &format!("proof envelope has different values for public signal {signal}"),
+                        "a signal marked public more than once has one value"
+                    )
+                }
+                Some(_) => {}
+                None => {
+                    signals.push(*signal);
+                    values.push(v.clone());
+                }
+            }
+        }
+        if public_inputs.next().is_some() || public_outputs.next().is_some() {
+            return log::error!(
+                ProverError: "proof envelope has more public values than the program",
+                "the proof was generated for a different program"
+            );
+        }
+        Ok(values)
+    }
+
+    /// Verify the proof using the prover that generated it. `program`
+    /// is the compiled program the proof is expected to belong to.
+    /// `keys` is the key cache written during setup, see `--keys`.
+    ///
+    /// Groth16 proofs are verified using the verifying key cached for
+    /// the envelope fingerprint, so they can be verified without the
+    /// program. Halo2 proofs are verified using the universal
+    /// parameters cached for the size of the program. Spartan, halo2,
+    /// and Triton VM proofs must be verified against a program.
+    #[cfg_attr(
+        not(any(feature = "groth16-prover", feature = "halo2-prover")),
+        allow(unused_variables)
+    )]
+    pub fn verify(&self, program: Option<&str>, keys: Option<&FnCache>) -> Result<bool> {
+        match (self.backend.as_str(), program) {
+            #[cfg(feature = "spartan-prover")]
+            ("spartan", Some(r1cs)) => SpartanProver::verify_envelope(r1cs, self),
+            #[cfg(feature = "groth16-prover")]
+            ("groth16", program) => match keys {
+                Some(keys) => Groth16Prover::verify_envelope(program, self, keys),
+                None => log::error!(
+                    ConfigError: "groth16 proofs are verified using the verifying key from setup",
+                    "use --keys with the directory the proving key was cached in"
+                ),
+            },
+            #[cfg(feature = "halo2-prover")]
+            ("halo2", Some(r1cs)) => match keys {
+                Some(keys) => Halo2Prover::verify_envelope_cached(r1cs, self, keys),
+                None => log::error!(
+                    ConfigError: "halo2 proofs are verified using the universal parameters from setup",
+                    "use --keys with the directory the parameters were cached in"
+                ),
+            },
+            #[cfg(feature = "tritonvm-prover")]
+            ("tritonvm", Some(asm)) => TritonVMProver::verify_envelope(asm, self),
+            ("spartan" | "halo2" | "tritonvm", None) => log::error!(
+                ConfigError: &format!("{} proofs can only be verified against a program", self.backend),
+                "specify the entry function of the program"
+            ),
+            (backend @ ("spartan" | "groth16" | "halo2" | "tritonvm"), _) => log::error!(
+                ConfigError: &format!("{backend} proofs are not supported in this build"),
+                &format!("enable the {backend}-prover feature")
+            ),
+            (backend, _) => log::error!(ProverError: &format!("unknown prover: {backend}")),
+        }
+    }
+
+    pub fn save(&self, path: &Utf8Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Utf8Path) -> Result<Self> {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(_) => return log::error!(ConfigError: &format!("Failed to read proof: {path}")),
+        };
+        let envelope: ProofEnvelope = match serde_json::from_str(&source) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                return log::error!(ProverError: &format!("Failed to parse proof {path}: {e}"))
+            }
+        };
+        if envelope.version != Self::VERSION {
+            return log::error!(
+                ProverError: &format!("unsupported proof envelope version: {}", envelope.version),
+                &format!("this version of ashlang reads version {}", Self::VERSION)
+            );
+        }
+        Ok(envelope)
+    }
+}
+
+// the public input and output constraints of an r1cs program
+// and the signal each one marks, in the order they are solved
+fn public_ops<T: PolynomialRingElement>(r1cs: &str) -> Result<Vec<(SymbolicOp, usize)>> {
+    let parser: R1csParser<T> = R1csParser::new(r1cs)?;
+    Ok(parser
+        .constraints
+        .into_iter()
+        .filter(|c| c.symbolic)
+        .filter_map(|c| match (c.symbolic_op, c.out_i) {
+            (Some(op @ (SymbolicOp::PublicInput | SymbolicOp::Output)), Some(i)) => Some((op, i)),
+            _ => None,
+        })
+        .collect())
+}
+
+mod hex {
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let hex = bytes.iter().map(|v| format!("{v:02x}")).collect::<String>();
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() % 2 != 0 {
+            return Err(serde::de::Error::custom("hex string has an odd length"));
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                hex.get(i..i + 2)
+                    .and_then(|v| u8::from_str_radix(v, 16).ok())
+                    .ok_or_else(|| serde::de::Error::custom("invalid hex string"))
+            })
+            .collect()
+    }
+}
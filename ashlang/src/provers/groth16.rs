@@ -0,0 +1,449 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::Result;
+use ark_bn254::Bn254;
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use ark_groth16::Groth16;
+use ark_groth16::Proof;
+use ark_groth16::ProvingKey;
+use ark_groth16::VerifyingKey;
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_relations::r1cs::ConstraintSystemRef;
+use ark_relations::r1cs::LinearCombination;
+use ark_relations::r1cs::SynthesisError;
+use ark_relations::r1cs::Variable;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use ark_serialize::SerializationError;
+use ark_snark::SNARK;
+use rand::rngs::OsRng;
+use scalarff::alt_bn128::Bn128FieldElement;
+use scalarff::FieldElement;
+
+use crate::cache::FnCache;
+use crate::cli::Config;
+use crate::compiler::Compiler;
+use crate::log;
+use crate::provers::AshlangProver;
+#[cfg(feature = "serde")]
+use crate::provers::EnvelopeProof;
+#[cfg(feature = "serde")]
+use crate::provers::ProofEnvelope;
+use crate::r1cs::constraint::R1csConstraint;
+use crate::r1cs::constraint::SymbolicOp;
+use crate::r1cs::parser::R1csParser;
+use crate::rings::Bn128PolynomialRing;
+
+/// A groth16 proof and the public values it binds.
+///
+/// `inputs` are the values of the public signals, in the order
+/// they are marked in the program. `outputs` are the values
+/// written to the public outputs. `fingerprint` identifies the
+/// program if it was compiled from source, see
+/// `Compiler::fingerprint`.
+///
+/// The proof does not include a verifying key. A proof is only
+/// meaningful against a key from the setup of the program, see
+/// `setup_cached`.
+pub struct Groth16Proof {
+    pub proof: Proof<Bn254>,
+    pub inputs: Vec<Fr>,
+    pub outputs: Vec<String>,
+    pub fingerprint: Option<String>,
+}
+
+/// Bindings for proving ashlang programs using the groth16 implementation
+/// in [arkworks-rs/groth16](https://github.com/arkworks-rs/groth16) over alt_bn128.
+///
+/// `prove_ir` runs a new trusted setup for each proof and discards
+/// the keys. Use `setup`, `prove`, and `verify` to reuse keys from
+/// a single setup, or `setup_cached` to reuse keys across runs.
+pub struct Groth16Prover {}
+
+impl Groth16Prover {
+    /// Read the keys of a program from a cache, or run `setup` and
+    /// cache the keys if the program has not been set up before.
+    /// Keys are stored by program fingerprint, see
+    /// `Compiler::fingerprint`. The verifying key is also stored
+    /// on its own with the public signals of the program, see
+    /// `verifying_key_cached`.
+    pub fn setup_cached(
+        r1cs: &str,
+        fingerprint: &str,
+        cache: &FnCache,
+    ) -> Result<(ProvingKey<Bn254>, VerifyingKey<Bn254>)> {
+        let key = FnCache::key(&["groth16", fingerprint]);
+        let vk_key = FnCache::key(&["groth16-vk", fingerprint]);
+        // the cache is written by this machine so the points
+        // are not checked, checking them is slower than the setup
+        if let Some(bytes) = cache.get_bytes(&key) {
+            if let Ok(pk) = ProvingKey::<Bn254>::deserialize_uncompressed_unchecked(&bytes[..]) {
+                let vk = pk.vk.clone();
+                if cache.get_bytes(&vk_key).is_none() {
+                    cache.put_bytes(&vk_key, &verifying_key_bytes(r1cs, &vk)?)?;
+                }
+                return Ok((pk, vk));
+            }
+        }
+        let (pk, vk) = Self::setup(r1cs)?;
+        let mut bytes = vec![];
+        pk.serialize_uncompressed(&mut bytes)
+            .map_err(|e: SerializationError| {
+                anyhow::anyhow!("Failed to serialize groth16 key: {e}")
+            })?;
+        cache.put_bytes(&key, &bytes)?;
+        cache.put_bytes(&vk_key, &verifying_key_bytes(r1cs, &vk)?)?;
+        Ok((pk, vk))
+    }
+
+    /// The verifying key cached by `setup_cached` for a program
+    /// fingerprint, and the public signals of the program in the
+    /// order they are marked. Returns None if the program has not
+    /// been set up using the cache.
+    #[allow(clippy::type_complexity)]
+    pub fn verifying_key_cached(
+        fingerprint: &str,
+        cache: &FnCache,
+    ) -> Result<Option<(VerifyingKey<Bn254>, Vec<(SymbolicOp, usize)>)>> {
+        let bytes = match cache.get_bytes(&FnCache::key(&["groth16-vk", fingerprint])) {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+        let map_err = |e: SerializationError| {
+            anyhow::anyhow!("Failed to read cached groth16 verifying key: {e}")
+        };
+        let mut bytes = &bytes[..];
+        let vk = VerifyingKey::<Bn254>::deserialize_compressed(&mut bytes).map_err(map_err)?;
+        let outputs = Vec::<u8>::deserialize_compressed(&mut bytes).map_err(map_err)?;
+        let signals = Vec::<u64>::deserialize_compressed(&mut bytes).map_err(map_err)?;
+        if outputs.len() != signals.len() {
+            return log::error!(ProverError: "cached groth16 verifying key is malformed");
+        }
+        let public = outputs
+            .into_iter()
+            .zip(signals)
+            .map(|(output, i)| match output {
+                0 => (SymbolicOp::PublicInput, i as usize),
+                _ => (SymbolicOp::Output, i as usize),
+            })
+            .collect();
+        Ok(Some((vk, public)))
+    }
+
+    /// Generate a proof using keys from `setup_cached`.
+    pub fn prove_cached(
+        r1cs: &str,
+        inputs: Vec<String>,
+        secret_inputs: Vec<String>,
+        fingerprint: &str,
+        cache: &FnCache,
+    ) -> Result<Groth16Proof> {
+        if !inputs.is_empty() {
+            return log::error!(ProverError: "ashlang groth16 connector does not support public inputs, mark public signals in the program instead");
+        }
+        let (pk, _) = Self::setup_cached(r1cs, fingerprint, cache)?;
+        let (proof, inputs, outputs) = Self::prove_with_key(r1cs, &pk, secret_inputs)?;
+        Ok(Groth16Proof {
+            proof,
+            inputs,
+            outputs,
+            fingerprint: Some(fingerprint.to_string()),
+        })
+    }
+
+    /// Calculate a witness and generate a proof using an existing proving key.
+    /// Returns the proof, the public signals, and the public outputs.
+    pub fn prove_with_key(
+        r1cs: &str,
+        pk: &ProvingKey<Bn254>,
+        secret_inputs: Vec<String>,
+    ) -> Result<(Proof<Bn254>, Vec<Fr>, Vec<String>)> {
+        let inputs = secret_inputs
+            .iter()
+            .map(|v| Bn128PolynomialRing::from_str(v))
+            .collect::<Result<Vec<_>>>()?;
+        let witness = crate::r1cs::witness::build::<Bn128PolynomialRing>(r1cs, inputs)?;
+        let outputs =
+            crate::r1cs::witness::public_outputs::<Bn128PolynomialRing>(r1cs, &witness.variables)?;
+        let circuit = Groth16Circuit::new(r1cs, Some(witness.variables))?;
+        let public_inputs = circuit.public_values()?;
+        let proof = Groth16::<Bn254>::prove(pk, circuit, &mut OsRng)?;
+        Ok((proof, public_inputs, outputs))
+    }
+
+    /// Verify a proof using a verifying key from `setup`.
+    pub fn verify_with_key(
+        vk: &VerifyingKey<Bn254>,
+        proof: &Proof<Bn254>,
+        public_inputs: &[Fr],
+    ) -> Result<bool> {
+        Ok(Groth16::<Bn254>::verify(vk, public_inputs, proof)?)
+    }
+}
+
+impl AshlangProver for Groth16Prover {
+    type Proof = Groth16Proof;
+    type ProvingKey = ProvingKey<Bn254>;
+    type VerifyingKey = VerifyingKey<Bn254>;
+    type Error = anyhow::Error;
+
+    /// Generate proving and verifying keys for an ar1cs source file.
+    /// The randomness used is toxic waste and is discarded.
+    fn setup(r1cs: &str) -> Result<(ProvingKey<Bn254>, VerifyingKey<Bn254>)> {
+        let circuit = Groth16Circuit::new(r1cs, None)?;
+        Ok(Groth16::<Bn254>::circuit_specific_setup(
+            circuit, &mut OsRng,
+        )?)
+    }
+
+    fn prove(
+        r1cs: &str,
+        pk: &ProvingKey<Bn254>,
+        inputs: Vec<String>,
+        secret_inputs: Vec<String>,
+    ) -> Result<Groth16Proof> {
+        if !inputs.is_empty() {
+            return log::error!(ProverError: "ashlang groth16 connector does not support public inputs, mark public signals in the program instead");
+        }
+        let (proof, inputs, outputs) = Self::prove_with_key(r1cs, pk, secret_inputs)?;
+        Ok(Groth16Proof {
+            proof,
+            inputs,
+            outputs,
+            fingerprint: None,
+        })
+    }
+
+    fn prove_config(config: &Config) -> Result<Groth16Proof> {
+        let mut config = config.clone();
+        config.extension_priorities.push("ar1cs".to_string());
+        config.extension_priorities.push("r1cs".to_string());
+
+        if config.field != "alt_bn128" {
+            return log::error!(
+                ProverError: "unsupported curve for groth16 proof",
+                "field must be \"alt_bn128\""
+            );
+        }
+
+        let mut compiler: Compiler<Bn128PolynomialRing> = Compiler::new(&config)?;
+        let r1cs = compiler.compile(&config.entry_fn)?;
+        if let (Some(dir), Some(fingerprint)) = (&config.key_dir, compiler.fingerprint()) {
+            let cache = FnCache::new(dir.clone());
+            return Self::prove_cached(&r1cs, vec![], config.secret_inputs, fingerprint, &cache);
+        }
+        let mut proof = Self::prove_ir(&r1cs, vec![], config.secret_inputs)?;
+        proof.fingerprint = compiler.fingerprint().map(String::from);
+        Ok(proof)
+    }
+
+    fn verify(vk: &VerifyingKey<Bn254>, proof: &Groth16Proof) -> Result<bool> {
+        // make sure the public signals match the shape of the key
+        if proof.inputs.len() + 1 != vk.gamma_abc_g1.len() {
+            return Ok(false);
+        }
+        Self::verify_with_key(vk, &proof.proof, &proof.inputs)
+    }
+
+    fn outputs(proof: &Groth16Proof) -> Vec<String> {
+        proof.outputs.clone()
+    }
+}
+
+/// The non-symbolic constraints of an ar1cs program and,
+/// optionally, a witness.
+struct Groth16Circuit {
+    constraints: Vec<R1csConstraint<Bn128FieldElement>>,
+    // signals exposed as public inputs to the proof
+    public: Vec<usize>,
+    witness: Option<Vec<Bn128FieldElement>>,
+}
+
+impl Groth16Circuit {
+    fn new(r1cs: &str, witness: Option<Vec<Bn128FieldElement>>) -> Result<Self> {
+        let r1cs_parser: R1csParser<Bn128PolynomialRing> = R1csParser::new(r1cs)?;
+        let mut public = vec![];
+        for (_, i) in public_ops(&r1cs_parser.constraints) {
+            if !public.contains(&i) {
+                public.push(i);
+            }
+        }
+        Ok(Self {
+            constraints: r1cs_parser
+                .constraints
+                .into_iter()
+                .filter(|c| !c.symbolic)
+                .collect(),
+            public,
+            witness,
+        })
+    }
+
+    fn value(&self, i: usize) -> Result<Fr, SynthesisError> {
+        if let Some(witness) = &self.witness {
+            if let Some(v) = witness.get(i) {
+                return Ok(to_fr(v));
+            }
+        }
+        Err(SynthesisError::AssignmentMissing)
+    }
+
+    fn public_values(&self) -> Result<Vec<Fr>> {
+        self.public
+            .iter()
+            .map(|i| {
+                self.value(*i)
+                    .map_err(|_| anyhow::anyhow!("witness is missing public signal {i}"))
+            })
+            .collect()
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for Groth16Circuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let mut vars: HashMap<usize, Variable> = HashMap::new();
+        vars.insert(0, Variable::One);
+        // public signals must be allocated in order
+        for i in &self.public {
+            vars.insert(*i, cs.new_input_variable(|| self.value(*i))?);
+        }
+        for c in &self.constraints {
+            for (_, i) in c.a.iter().chain(c.b.iter()).chain(c.c.iter()) {
+                if !vars.contains_key(i) {
+                    vars.insert(*i, cs.new_witness_variable(|| self.value(*i))?);
+                }
+            }
+        }
+        let lc = |terms: &Vec<(Bn128FieldElement, usize)>| {
+            let mut out = LinearCombination::<Fr>::zero();
+            for (coef, i) in terms {
+                out = out + (to_fr(coef), vars[i]);
+            }
+            out
+        };
+        for c in &self.constraints {
+            cs.enforce_constraint(lc(&c.a), lc(&c.b), lc(&c.c))?;
+        }
+        Ok(())
+    }
+}
+
+fn to_fr(v: &Bn128FieldElement) -> Fr {
+    Fr::from_le_bytes_mod_order(&v.to_biguint().to_bytes_le())
+}
+
+// the public input and output constraints of a program and the
+// signal each one marks, in the order they are marked
+fn public_ops(constraints: &[R1csConstraint<Bn128FieldElement>]) -> Vec<(SymbolicOp, usize)> {
+    constraints
+        .iter()
+        .filter_map(|c| match (&c.symbolic_op, c.out_i) {
+            (Some(op @ (SymbolicOp::Output | SymbolicOp::PublicInput)), Some(i)) => {
+                Some((op.clone(), i))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// a verifying key followed by whether each public value is an
+// output and the signal it marks
+fn verifying_key_bytes(r1cs: &str, vk: &VerifyingKey<Bn254>) -> Result<Vec<u8>> {
+    let r1cs_parser: R1csParser<Bn128PolynomialRing> = R1csParser::new(r1cs)?;
+    let public = public_ops(&r1cs_parser.constraints);
+    let outputs = public
+        .iter()
+        .map(|(op, _)| u8::from(*op == SymbolicOp::Output))
+        .collect::<Vec<_>>();
+    let signals = public.iter().map(|(_, i)| *i as u64).collect::<Vec<_>>();
+    let mut bytes = vec![];
+    let map_err =
+        |e: SerializationError| anyhow::anyhow!("Failed to serialize groth16 verifying key: {e}");
+    vk.serialize_compressed(&mut bytes).map_err(map_err)?;
+    outputs.serialize_compressed(&mut bytes).map_err(map_err)?;
+    signals.serialize_compressed(&mut bytes).map_err(map_err)?;
+    Ok(bytes)
+}
+
+#[cfg(feature = "serde")]
+impl Groth16Prover {
+    /// Verify a proof envelope using the verifying key cached by
+    /// `setup_cached` for the envelope fingerprint. The key is
+    /// never read from the envelope.
+    ///
+    /// The public values of the envelope are matched to the public
+    /// signals cached with the key. If `r1cs` is known its public
+    /// signals must be the same.
+    pub fn verify_envelope(
+        r1cs: Option<&str>,
+        envelope: &ProofEnvelope,
+        cache: &FnCache,
+    ) -> Result<bool> {
+        let fingerprint = match &envelope.fingerprint {
+            Some(fingerprint) => fingerprint,
+            None => {
+                return log::error!(
+                    ProverError: "groth16 proof envelope has no program fingerprint",
+                    "groth16 proofs are verified using the key cached for the program fingerprint"
+                )
+            }
+        };
+        let (vk, public_signals) = match Self::verifying_key_cached(fingerprint, cache)? {
+            Some(v) => v,
+            None => {
+                return log::error!(
+                    ConfigError: &format!("no groth16 verifying key for program {fingerprint}"),
+                    "use --keys with the directory the proving key was cached in"
+                )
+            }
+        };
+        if let Some(r1cs) = r1cs {
+            let r1cs_parser: R1csParser<Bn128PolynomialRing> = R1csParser::new(r1cs)?;
+            if public_ops(&r1cs_parser.constraints) != public_signals {
+                return Ok(false);
+            }
+        }
+        let public = envelope
+            .public_values(&public_signals)?
+            .iter()
+            .map(|v| Ok(to_fr(&Bn128FieldElement::from_str(v)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let proof: Groth16Proof = envelope.proof()?;
+        if public != proof.inputs {
+            return Ok(false);
+        }
+        Self::verify(&vk, &proof)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl EnvelopeProof for Groth16Proof {
+    const BACKEND: &'static str = "groth16";
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![];
+        let map_err =
+            |e: SerializationError| anyhow::anyhow!("Failed to serialize groth16 proof: {e}");
+        self.proof
+            .serialize_compressed(&mut bytes)
+            .map_err(map_err)?;
+        self.inputs
+            .serialize_compressed(&mut bytes)
+            .map_err(map_err)?;
+        Ok(bytes)
+    }
+
+    fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        let map_err =
+            |e: SerializationError| anyhow::anyhow!("Failed to deserialize groth16 proof: {e}");
+        Ok(Groth16Proof {
+            proof: Proof::deserialize_compressed(&mut bytes).map_err(map_err)?,
+            inputs: Vec::<Fr>::deserialize_compressed(&mut bytes).map_err(map_err)?,
+            // the outputs and fingerprint are stored in the envelope
+            outputs: vec![],
+            fingerprint: None,
+        })
+    }
+}
@@ -0,0 +1,697 @@
+use std::collections::HashMap;
+use std::fs;
+#[cfg(feature = "serde")]
+use std::io::Read;
+use std::str::FromStr;
+
+use anyhow::Result;
+use camino::Utf8Path;
+use halo2_proofs::circuit::AssignedCell;
+use halo2_proofs::circuit::Layouter;
+use halo2_proofs::circuit::SimpleFloorPlanner;
+use halo2_proofs::circuit::Value;
+use halo2_proofs::halo2curves::bn256::Bn256;
+use halo2_proofs::halo2curves::bn256::Fr;
+use halo2_proofs::halo2curves::bn256::G1Affine;
+use halo2_proofs::halo2curves::ff::Field;
+use halo2_proofs::halo2curves::ff::PrimeField;
+use halo2_proofs::plonk::create_proof;
+use halo2_proofs::plonk::keygen_pk;
+use halo2_proofs::plonk::keygen_vk;
+use halo2_proofs::plonk::verify_proof;
+use halo2_proofs::plonk::Advice;
+use halo2_proofs::plonk::Circuit;
+use halo2_proofs::plonk::Column;
+use halo2_proofs::plonk::ConstraintSystem;
+use halo2_proofs::plonk::Error;
+use halo2_proofs::plonk::Fixed;
+use halo2_proofs::plonk::Instance;
+use halo2_proofs::plonk::Selector;
+use halo2_proofs::plonk::VerifyingKey;
+use halo2_proofs::poly::commitment::Params;
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_proofs::poly::kzg::multiopen::ProverSHPLONK;
+use halo2_proofs::poly::kzg::multiopen::VerifierSHPLONK;
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::Blake2bRead;
+use halo2_proofs::transcript::Blake2bWrite;
+use halo2_proofs::transcript::Challenge255;
+use halo2_proofs::transcript::TranscriptReadBuffer;
+use halo2_proofs::transcript::TranscriptWriterBuffer;
+use rand::rngs::OsRng;
+use scalarff::alt_bn128::Bn128FieldElement;
+use scalarff::FieldElement;
+
+use crate::cache::FnCache;
+use crate::cli::Config;
+use crate::compiler::Compiler;
+use crate::log;
+use crate::provers::AshlangProver;
+#[cfg(feature = "serde")]
+use crate::provers::EnvelopeProof;
+#[cfg(feature = "serde")]
+use crate::provers::ProofEnvelope;
+use crate::r1cs::constraint::R1csConstraint;
+use crate::r1cs::constraint::SymbolicOp;
+use crate::r1cs::parser::R1csParser;
+use crate::rings::Bn128PolynomialRing;
+
+/// A halo2 proof and the public values it binds.
+///
+/// `inputs` are the values of the public signals, in the order
+/// they are marked in the program. `outputs` are the values
+/// written to the public outputs. `fingerprint` identifies the
+/// program if it was compiled from source, see
+/// `Compiler::fingerprint`.
+///
+/// The proof does not include the universal parameters. Whoever
+/// generated the parameters can forge proofs, so a proof is only
+/// meaningful against parameters the verifier trusts, see
+/// `params_from_cache` and `read_params`.
+pub struct Halo2Proof {
+    pub proof: Vec<u8>,
+    pub inputs: Vec<Fr>,
+    pub outputs: Vec<String>,
+    pub fingerprint: Option<String>,
+}
+
+/// Universal parameters and the verifying key derived from them
+/// for a program, see `Halo2Prover::setup`.
+pub struct Halo2VerifyingKey {
+    pub params: ParamsKZG<Bn256>,
+    pub vk: VerifyingKey<G1Affine>,
+    // the number of public signals of the program
+    pub public: usize,
+}
+
+/// Bindings for proving ashlang programs using the plonkish
+/// [privacy-scaling-explorations/halo2](https://github.com/privacy-scaling-explorations/halo2)
+/// prover with KZG commitments over alt_bn128.
+///
+/// Each r1cs constraint is converted to rows of a plonkish circuit.
+/// Proving and verifying keys are derived from the program and a set
+/// of universal parameters, so there is no setup per program. The
+/// parameters are a trusted setup, `params` generates them locally
+/// and knows the toxic waste. Use an SRS from a ceremony when the
+/// prover and verifier do not trust each other, see `read_params`.
+pub struct Halo2Prover {}
+
+impl Halo2Prover {
+    /// Generate universal parameters large enough for circuits
+    /// with up to `2^k` rows.
+    pub fn params(k: u32) -> ParamsKZG<Bn256> {
+        ParamsKZG::<Bn256>::setup(k, OsRng)
+    }
+
+    /// Read universal parameters from a file written with
+    /// `ParamsKZG::write`, e.g. the SRS of a powers of tau ceremony,
+    /// and reduce them to `2^k` rows.
+    pub fn read_params(path: &Utf8Path, k: u32) -> Result<ParamsKZG<Bn256>> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                return log::error!(
+                    ConfigError: &format!("failed to read halo2 parameters from {path}"),
+                    &e.to_string()
+                )
+            }
+        };
+        let mut params = ParamsKZG::<Bn256>::read(&mut &bytes[..])?;
+        if params.k() < k {
+            return log::error!(ProverError: &format!(
+                "halo2 parameters in {path} are too small for program, need k = {k}"
+            ));
+        }
+        params.downsize(k);
+        Ok(params)
+    }
+
+    /// Read universal parameters from a cache, or generate and
+    /// cache them. Parameters do not depend on the program, so they
+    /// are stored by size and shared by every program with `2^k` or
+    /// fewer rows. Use `cache_params` to store parameters from a
+    /// ceremony instead of generating them.
+    pub fn params_cached(k: u32, cache: &FnCache) -> Result<ParamsKZG<Bn256>> {
+        if let Some(params) = Self::params_from_cache(k, cache)? {
+            return Ok(params);
+        }
+        let params = Self::params(k);
+        Self::cache_params(&params, cache)?;
+        Ok(params)
+    }
+
+    /// Read the universal parameters for `2^k` rows from a cache.
+    /// Returns None if they are not cached, parameters are never
+    /// generated here so a verifier only uses parameters it stored.
+    pub fn params_from_cache(k: u32, cache: &FnCache) -> Result<Option<ParamsKZG<Bn256>>> {
+        match cache.get_bytes(&params_key(k)) {
+            Some(bytes) => match ParamsKZG::<Bn256>::read(&mut &bytes[..]) {
+                Ok(params) => Ok(Some(params)),
+                Err(_) => log::error!(ProverError: "cached halo2 parameters are malformed"),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Store universal parameters in a cache, they are used by
+    /// `params_cached` and `params_from_cache` for programs with
+    /// `2^k` rows.
+    pub fn cache_params(params: &ParamsKZG<Bn256>, cache: &FnCache) -> Result<()> {
+        let mut bytes = vec![];
+        params.write(&mut bytes)?;
+        cache.put_bytes(&params_key(params.k()), &bytes)
+    }
+
+    /// Read the parameters for a program from a cache, or generate
+    /// and cache them, and derive the verifying key of the program.
+    pub fn setup_cached(
+        r1cs: &str,
+        cache: &FnCache,
+    ) -> Result<(ParamsKZG<Bn256>, Halo2VerifyingKey)> {
+        let circuit = Halo2Circuit::new(r1cs, None)?;
+        let params = Self::params_cached(circuit.k(), cache)?;
+        let vk = Self::verifying_key(&circuit, &params)?;
+        Ok((params, vk))
+    }
+
+    fn verifying_key(
+        circuit: &Halo2Circuit,
+        params: &ParamsKZG<Bn256>,
+    ) -> Result<Halo2VerifyingKey> {
+        Ok(Halo2VerifyingKey {
+            params: params.clone(),
+            vk: keygen_vk(params, circuit)?,
+            public: circuit.public.len(),
+        })
+    }
+
+    /// Generate a proof using parameters from `params_cached`.
+    pub fn prove_cached(
+        r1cs: &str,
+        inputs: Vec<String>,
+        secret_inputs: Vec<String>,
+        cache: &FnCache,
+    ) -> Result<Halo2Proof> {
+        if !inputs.is_empty() {
+            return log::error!(ProverError: "ashlang halo2 connector does not support public inputs, mark public signals in the program instead");
+        }
+        let circuit = Halo2Circuit::new(r1cs, None)?;
+        let params = Self::params_cached(circuit.k(), cache)?;
+        let (proof, inputs, outputs) = Self::prove_with_params(r1cs, &params, secret_inputs)?;
+        Ok(Halo2Proof {
+            proof,
+            inputs,
+            outputs,
+            fingerprint: None,
+        })
+    }
+
+    /// Calculate a witness and generate a proof using
+    /// existing universal parameters. Returns the proof, the
+    /// public signals, and the public outputs.
+    pub fn prove_with_params(
+        r1cs: &str,
+        params: &ParamsKZG<Bn256>,
+        secret_inputs: Vec<String>,
+    ) -> Result<(Vec<u8>, Vec<Fr>, Vec<String>)> {
+        let inputs = secret_inputs
+            .iter()
+            .map(|v| Bn128PolynomialRing::from_str(v))
+            .collect::<Result<Vec<_>>>()?;
+        let witness = crate::r1cs::witness::build::<Bn128PolynomialRing>(r1cs, inputs)?;
+        let outputs =
+            crate::r1cs::witness::public_outputs::<Bn128PolynomialRing>(r1cs, &witness.variables)?;
+        let circuit = Halo2Circuit::new(r1cs, Some(witness.variables))?;
+        if circuit.k() > params.k() {
+            return log::error!(ProverError: &format!(
+                "halo2 parameters are too small for program, need k = {}",
+                circuit.k()
+            ));
+        }
+        let public_inputs = circuit
+            .public
+            .iter()
+            .map(|i| circuit.value(*i))
+            .collect::<Result<Vec<_>>>()?;
+        let vk = keygen_vk(params, &circuit.without_witnesses())?;
+        let pk = keygen_pk(params, vk, &circuit.without_witnesses())?;
+        let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+        create_proof::<
+            KZGCommitmentScheme<Bn256>,
+            ProverSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            _,
+            Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+            _,
+        >(
+            params,
+            &pk,
+            &[circuit],
+            &[&[&public_inputs[..]]],
+            OsRng,
+            &mut transcript,
+        )?;
+        Ok((transcript.finalize(), public_inputs, outputs))
+    }
+
+    /// Verify a proof. The verifying key is derived from the program.
+    pub fn verify_with_params(
+        r1cs: &str,
+        params: &ParamsKZG<Bn256>,
+        proof: &[u8],
+        public_inputs: &[Fr],
+    ) -> Result<bool> {
+        let circuit = Halo2Circuit::new(r1cs, None)?;
+        if circuit.k() > params.k() || circuit.public.len() != public_inputs.len() {
+            return Ok(false);
+        }
+        let vk = keygen_vk(params, &circuit)?;
+        Self::verify_with_key(params, &vk, proof, public_inputs)
+    }
+
+    /// Verify a proof using a verifying key derived from `params`.
+    pub fn verify_with_key(
+        params: &ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        proof: &[u8],
+        public_inputs: &[Fr],
+    ) -> Result<bool> {
+        let strategy = SingleStrategy::new(params);
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+        Ok(verify_proof::<
+            KZGCommitmentScheme<Bn256>,
+            VerifierSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
+            SingleStrategy<'_, Bn256>,
+        >(
+            params.verifier_params(),
+            vk,
+            strategy,
+            &[&[public_inputs]],
+            &mut transcript,
+        )
+        .is_ok())
+    }
+}
+
+// the proving key is the universal parameters, the halo2 proving
+// key is derived from them for each proof
+impl AshlangProver for Halo2Prover {
+    type Proof = Halo2Proof;
+    type ProvingKey = ParamsKZG<Bn256>;
+    type VerifyingKey = Halo2VerifyingKey;
+    type Error = anyhow::Error;
+
+    /// Generate universal parameters large enough for the program
+    /// and derive its verifying key.
+    fn setup(r1cs: &str) -> Result<(ParamsKZG<Bn256>, Halo2VerifyingKey)> {
+        let circuit = Halo2Circuit::new(r1cs, None)?;
+        let params = Self::params(circuit.k());
+        let vk = Self::verifying_key(&circuit, &params)?;
+        Ok((params, vk))
+    }
+
+    fn prove(
+        r1cs: &str,
+        params: &ParamsKZG<Bn256>,
+        inputs: Vec<String>,
+        secret_inputs: Vec<String>,
+    ) -> Result<Halo2Proof> {
+        if !inputs.is_empty() {
+            return log::error!(ProverError: "ashlang halo2 connector does not support public inputs, mark public signals in the program instead");
+        }
+        let (proof, inputs, outputs) = Self::prove_with_params(r1cs, params, secret_inputs)?;
+        Ok(Halo2Proof {
+            proof,
+            inputs,
+            outputs,
+            fingerprint: None,
+        })
+    }
+
+    fn prove_config(config: &Config) -> Result<Halo2Proof> {
+        let mut config = config.clone();
+        config.extension_priorities.push("ar1cs".to_string());
+        config.extension_priorities.push("r1cs".to_string());
+
+        if config.field != "alt_bn128" {
+            return log::error!(
+                ProverError: "unsupported curve for halo2 proof",
+                "field must be \"alt_bn128\""
+            );
+        }
+
+        let mut compiler: Compiler<Bn128PolynomialRing> = Compiler::new(&config)?;
+        let r1cs = compiler.compile(&config.entry_fn)?;
+        let mut proof = match &config.key_dir {
+            Some(dir) => Self::prove_cached(
+                &r1cs,
+                vec![],
+                config.secret_inputs,
+                &FnCache::new(dir.clone()),
+            )?,
+            None => Self::prove_ir(&r1cs, vec![], config.secret_inputs)?,
+        };
+        proof.fingerprint = compiler.fingerprint().map(String::from);
+        Ok(proof)
+    }
+
+    fn verify(vk: &Halo2VerifyingKey, proof: &Halo2Proof) -> Result<bool> {
+        if proof.inputs.len() != vk.public {
+            return Ok(false);
+        }
+        Self::verify_with_key(&vk.params, &vk.vk, &proof.proof, &proof.inputs)
+    }
+
+    fn outputs(proof: &Halo2Proof) -> Vec<String> {
+        proof.outputs.clone()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Halo2Config {
+    // signal values and linear combination terms
+    x: Column<Advice>,
+    // running sum of a linear combination
+    acc: Column<Advice>,
+    coef: Column<Fixed>,
+    // product operands
+    l: Column<Advice>,
+    r: Column<Advice>,
+    o: Column<Advice>,
+    instance: Column<Instance>,
+    q_lin: Selector,
+    q_mul: Selector,
+}
+
+/// The non-symbolic constraints of an ar1cs program and,
+/// optionally, a witness.
+///
+/// Each signal is assigned once and copied into every linear
+/// combination that uses it. A linear combination with `n` terms
+/// is summed over `n + 1` rows. Each constraint adds a row
+/// enforcing `a * b = c` on the sums.
+#[derive(Clone)]
+struct Halo2Circuit {
+    constraints: Vec<R1csConstraint<Bn128FieldElement>>,
+    // signals exposed as public inputs to the proof
+    public: Vec<usize>,
+    // every signal other than `one`
+    signals: Vec<usize>,
+    witness: Option<Vec<Bn128FieldElement>>,
+}
+
+impl Halo2Circuit {
+    fn new(r1cs: &str, witness: Option<Vec<Bn128FieldElement>>) -> Result<Self> {
+        let r1cs_parser: R1csParser<Bn128PolynomialRing> = R1csParser::new(r1cs)?;
+        let mut public = vec![];
+        for c in &r1cs_parser.constraints {
+            if let Some(SymbolicOp::Output | SymbolicOp::PublicInput) = c.symbolic_op {
+                let i = c.out_i.unwrap();
+                if !public.contains(&i) {
+                    public.push(i);
+                }
+            }
+        }
+        let constraints = r1cs_parser
+            .constraints
+            .into_iter()
+            .filter(|c| !c.symbolic)
+            .collect::<Vec<_>>();
+        let mut signals = public.clone();
+        for c in &constraints {
+            signals.extend(
+                c.a.iter()
+                    .chain(c.b.iter())
+                    .chain(c.c.iter())
+                    .map(|(_, i)| *i),
+            );
+        }
+        signals.sort();
+        signals.dedup();
+        signals.retain(|i| *i != 0);
+        Ok(Self {
+            constraints,
+            public,
+            signals,
+            witness,
+        })
+    }
+
+    /// The smallest `k` such that the circuit fits in `2^k` rows.
+    fn k(&self) -> u32 {
+        let mut rows = 1 + self.signals.len();
+        for c in &self.constraints {
+            rows += c.a.len() + c.b.len() + c.c.len() + 4;
+        }
+        // leave room for blinding rows
+        let mut k = 4;
+        while (1 << k) < rows + 16 {
+            k += 1;
+        }
+        k
+    }
+
+    fn value(&self, i: usize) -> Result<Fr> {
+        if let Some(v) = self.witness.as_ref().and_then(|w| w.get(i)) {
+            to_fr(v)
+        } else {
+            log::error!(InputError: &format!("witness is missing signal {i}"))
+        }
+    }
+
+    fn cell_value(&self, i: usize) -> Value<Fr> {
+        match self.value(i) {
+            Ok(v) if self.witness.is_some() => Value::known(v),
+            _ => Value::unknown(),
+        }
+    }
+}
+
+impl Circuit<Fr> for Halo2Circuit {
+    type Config = Halo2Config;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            witness: None,
+            ..self.clone()
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Halo2Config {
+        let config = Halo2Config {
+            x: meta.advice_column(),
+            acc: meta.advice_column(),
+            coef: meta.fixed_column(),
+            l: meta.advice_column(),
+            r: meta.advice_column(),
+            o: meta.advice_column(),
+            instance: meta.instance_column(),
+            q_lin: meta.selector(),
+            q_mul: meta.selector(),
+        };
+        let constant = meta.fixed_column();
+        meta.enable_constant(constant);
+        for column in [config.x, config.acc, config.l, config.r, config.o] {
+            meta.enable_equality(column);
+        }
+        meta.enable_equality(config.instance);
+        meta.create_gate("linear combination term", |meta| {
+            let q = meta.query_selector(config.q_lin);
+            let coef = meta.query_fixed(config.coef, Rotation::cur());
+            let x = meta.query_advice(config.x, Rotation::cur());
+            let acc = meta.query_advice(config.acc, Rotation::cur());
+            let next = meta.query_advice(config.acc, Rotation::next());
+            vec![q * (acc + coef * x - next)]
+        });
+        meta.create_gate("r1cs product", |meta| {
+            let q = meta.query_selector(config.q_mul);
+            let l = meta.query_advice(config.l, Rotation::cur());
+            let r = meta.query_advice(config.r, Rotation::cur());
+            let o = meta.query_advice(config.o, Rotation::cur());
+            vec![q * (l * r - o)]
+        });
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Halo2Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let public_cells = layouter.assign_region(
+            || "r1cs",
+            |mut region| {
+                let mut row = 0;
+                let mut cells: HashMap<usize, AssignedCell<Fr, Fr>> = HashMap::new();
+                cells.insert(
+                    0,
+                    region.assign_advice_from_constant(|| "one", config.x, row, Fr::ONE)?,
+                );
+                row += 1;
+                for i in &self.signals {
+                    let cell = region.assign_advice(
+                        || format!("x{i}"),
+                        config.x,
+                        row,
+                        || self.cell_value(*i),
+                    )?;
+                    cells.insert(*i, cell);
+                    row += 1;
+                }
+                for c in &self.constraints {
+                    let mut sums = vec![];
+                    for lc in [&c.a, &c.b, &c.c] {
+                        let mut acc = region.assign_advice_from_constant(
+                            || "zero",
+                            config.acc,
+                            row,
+                            Fr::ZERO,
+                        )?;
+                        for (coef, i) in lc {
+                            let coef = to_fr(coef).map_err(|_| Error::Synthesis)?;
+                            config.q_lin.enable(&mut region, row)?;
+                            region.assign_fixed(
+                                || "coef",
+                                config.coef,
+                                row,
+                                || Value::known(coef),
+                            )?;
+                            let x = region.assign_advice(
+                                || "term",
+                                config.x,
+                                row,
+                                || cells[i].value().copied(),
+                            )?;
+                            region.constrain_equal(x.cell(), cells[i].cell())?;
+                            let sum =
+                                acc.value().copied() + x.value().copied() * Value::known(coef);
+                            row += 1;
+                            acc = region.assign_advice(|| "sum", config.acc, row, || sum)?;
+                        }
+                        sums.push(acc);
+                        row += 1;
+                    }
+                    config.q_mul.enable(&mut region, row)?;
+                    for (column, sum) in [config.l, config.r, config.o].into_iter().zip(sums) {
+                        let cell = region.assign_advice(
+                            || "operand",
+                            column,
+                            row,
+                            || sum.value().copied(),
+                        )?;
+                        region.constrain_equal(cell.cell(), sum.cell())?;
+                    }
+                    row += 1;
+                }
+                Ok(self
+                    .public
+                    .iter()
+                    .map(|i| cells[i].clone())
+                    .collect::<Vec<_>>())
+            },
+        )?;
+        for (x, cell) in public_cells.iter().enumerate() {
+            layouter.constrain_instance(cell.cell(), config.instance, x)?;
+        }
+        Ok(())
+    }
+}
+
+fn to_fr(v: &Bn128FieldElement) -> Result<Fr> {
+    let mut repr = <Fr as PrimeField>::Repr::default();
+    let bytes = v.to_biguint().to_bytes_le();
+    repr.as_mut()[..bytes.len()].copy_from_slice(&bytes);
+    Option::from(Fr::from_repr(repr))
+        .ok_or_else(|| anyhow::anyhow!("value is not in the bn256 scalar field: {v}"))
+}
+
+// parameters are stored by the number of rows they support
+fn params_key(k: u32) -> String {
+    FnCache::key(&["halo2-params", &k.to_string()])
+}
+
+#[cfg(feature = "serde")]
+impl Halo2Prover {
+    /// Verify a proof envelope using universal parameters supplied
+    /// by the verifier and check that its public values are the
+    /// public inputs of the proof. The parameters are never read
+    /// from the envelope.
+    pub fn verify_envelope(
+        r1cs: &str,
+        envelope: &ProofEnvelope,
+        params: &ParamsKZG<Bn256>,
+    ) -> Result<bool> {
+        let proof: Halo2Proof = envelope.proof()?;
+        let public = envelope
+            .public_signals::<Bn128PolynomialRing>(r1cs)?
+            .iter()
+            .map(|v| to_fr(&Bn128FieldElement::from_str(v)?))
+            .collect::<Result<Vec<_>>>()?;
+        if public != proof.inputs {
+            return Ok(false);
+        }
+        Self::verify_with_params(r1cs, params, &proof.proof, &proof.inputs)
+    }
+
+    /// Verify a proof envelope using the parameters cached for the
+    /// size of the program, see `params_from_cache`.
+    pub fn verify_envelope_cached(
+        r1cs: &str,
+        envelope: &ProofEnvelope,
+        cache: &FnCache,
+    ) -> Result<bool> {
+        let k = Halo2Circuit::new(r1cs, None)?.k();
+        match Self::params_from_cache(k, cache)? {
+            Some(params) => Self::verify_envelope(r1cs, envelope, &params),
+            None => log::error!(
+                ConfigError: &format!("no halo2 parameters for circuits with 2^{k} rows"),
+                "use --keys with the directory the parameters were cached in"
+            ),
+        }
+    }
+}
+
+// the number of public inputs, each input, and the proof
+#[cfg(feature = "serde")]
+impl EnvelopeProof for Halo2Proof {
+    const BACKEND: &'static str = "halo2";
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![];
+        bytes.extend((self.inputs.len() as u64).to_le_bytes());
+        for v in &self.inputs {
+            bytes.extend(v.to_repr());
+        }
+        bytes.extend(&self.proof);
+        Ok(bytes)
+    }
+
+    fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        let mut len = [0_u8; 8];
+        bytes.read_exact(&mut len)?;
+        let mut inputs = vec![];
+        for _ in 0..u64::from_le_bytes(len) {
+            let mut repr = [0_u8; 32];
+            bytes.read_exact(&mut repr)?;
+            match Option::<Fr>::from(Fr::from_repr(repr)) {
+                Some(v) => inputs.push(v),
+                None => {
+                    return log::error!(ProverError: "halo2 proof contains an invalid public input")
+                }
+            }
+        }
+        Ok(Halo2Proof {
+            proof: bytes.to_vec(),
+            inputs,
+            // the outputs and fingerprint are stored in the envelope
+            outputs: vec![],
+            fingerprint: None,
+        })
+    }
+}
@@ -1,13 +1,53 @@
 //! This module contains bindings to various prover implementations.
 //!
 mod ashlang_prover;
+#[cfg(feature = "serde")]
+mod envelope;
+#[cfg(feature = "groth16-prover")]
+mod groth16;
+#[cfg(feature = "halo2-prover")]
+mod halo2;
+#[cfg(feature = "nova-prover")]
+mod nova;
+#[cfg(feature = "groth16-prover")]
+pub mod solidity;
 #[cfg(feature = "spartan-prover")]
 mod spartan;
 #[cfg(feature = "tritonvm-prover")]
 mod tritonvm;
 
 pub use ashlang_prover::AshlangProver;
+#[cfg(feature = "serde")]
+pub use envelope::EnvelopeProof;
+#[cfg(feature = "serde")]
+pub use envelope::ProofEnvelope;
+#[cfg(feature = "groth16-prover")]
+pub use groth16::Groth16Proof;
+#[cfg(feature = "groth16-prover")]
+pub use groth16::Groth16Prover;
+#[cfg(feature = "halo2-prover")]
+pub use halo2::Halo2Proof;
+#[cfg(feature = "halo2-prover")]
+pub use halo2::Halo2Prover;
+#[cfg(feature = "halo2-prover")]
+pub use halo2::Halo2VerifyingKey;
+#[cfg(feature = "nova-prover")]
+pub use nova::NovaCircuit;
+#[cfg(feature = "nova-prover")]
+pub use nova::NovaParams;
+#[cfg(feature = "nova-prover")]
+pub use nova::NovaProof;
+#[cfg(feature = "nova-prover")]
+pub use nova::NovaProver;
+#[cfg(feature = "spartan-prover")]
+pub use spartan::SpartanProof;
 #[cfg(feature = "spartan-prover")]
 pub use spartan::SpartanProver;
+#[cfg(feature = "spartan-prover")]
+pub use spartan::SpartanReport;
+#[cfg(feature = "tritonvm-prover")]
+pub use tritonvm::CompiledTasm;
+#[cfg(feature = "tritonvm-prover")]
+pub use tritonvm::ExecutionReport;
 #[cfg(feature = "tritonvm-prover")]
 pub use tritonvm::TritonVMProver;
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::Result;
+use bellpepper_core::num::AllocatedNum;
+use bellpepper_core::ConstraintSystem;
+use bellpepper_core::LinearCombination;
+use bellpepper_core::SynthesisError;
+use bellpepper_core::Variable;
+use ff::Field;
+use ff::PrimeField;
+use nova_snark::provider::Bn256EngineKZG;
+use nova_snark::provider::GrumpkinEngine;
+use nova_snark::traits::circuit::StepCircuit;
+use nova_snark::traits::circuit::TrivialCircuit;
+use nova_snark::traits::snark::default_ck_hint;
+use nova_snark::traits::Engine;
+use nova_snark::PublicParams;
+use nova_snark::RecursiveSNARK;
+use scalarff::alt_bn128::Bn128FieldElement;
+use scalarff::BigUint;
+use scalarff::FieldElement;
+
+use crate::log;
+use crate::r1cs::constraint::R1csConstraint;
+use crate::r1cs::constraint::SymbolicOp;
+use crate::r1cs::parser::R1csParser;
+use crate::rings::Bn128PolynomialRing;
+
+type E1 = Bn256EngineKZG;
+type E2 = GrumpkinEngine;
+type F1 = <E1 as Engine>::Scalar;
+type F2 = <E2 as Engine>::Scalar;
+
+/// Public parameters for folding a step program.
+pub type NovaParams = PublicParams<E1, E2, NovaCircuit, TrivialCircuit<F2>>;
+
+/// A proof that a step program was executed `steps` times,
+/// starting with the state `z0`.
+///
+/// `fingerprint` identifies the step program if it was compiled
+/// from source, see `Compiler::fingerprint`.
+pub struct NovaProof {
+    pub snark: RecursiveSNARK<E1, E2, NovaCircuit, TrivialCircuit<F2>>,
+    pub steps: usize,
+    pub z0: Vec<String>,
+    pub fingerprint: Option<String>,
+}
+
+/// Bindings for folding repeated executions of an ashlang program
+/// using the [microsoft/Nova](https://github.com/microsoft/Nova)
+/// folding scheme over bn256 and grumpkin.
+///
+/// The program is a single step of an iterative computation, e.g.
+/// one link of a hash chain. The state is read using `pub input`
+/// and the next state is written using `pub output`, so each step
+/// has the same number of public inputs and outputs. Each step is
+/// folded into a running instance instead of being unrolled, so
+/// the cost of setup does not depend on the number of steps.
+pub struct NovaProver {}
+
+impl NovaProver {
+    /// Generate public parameters for a step program.
+    pub fn setup(r1cs: &str) -> Result<NovaParams> {
+        let circuit = NovaCircuit::new(r1cs)?;
+        Ok(PublicParams::setup(
+            &circuit,
+            &TrivialCircuit::default(),
+            &*default_ck_hint(),
+            &*default_ck_hint(),
+        )?)
+    }
+
+    /// Execute and fold `steps` steps of a program starting with
+    /// the state `z0`.
+    pub fn prove_steps(
+        r1cs: &str,
+        params: &NovaParams,
+        z0: Vec<String>,
+        steps: usize,
+    ) -> Result<NovaProof> {
+        if steps == 0 {
+            return log::error!(ProverError: "cannot fold zero steps");
+        }
+        let circuit = NovaCircuit::new(r1cs)?;
+        if z0.len() != circuit.arity() {
+            return log::error!(
+                ProverError: &format!(
+                    "step program reads {} public inputs, received {}",
+                    circuit.arity(),
+                    z0.len()
+                ),
+                "specify the initial state using -s"
+            );
+        }
+        let z0_primary = z0
+            .iter()
+            .map(|v| to_scalar(&Bn128FieldElement::from_str(v)?))
+            .collect::<Result<Vec<_>>>()?;
+        let secondary = TrivialCircuit::default();
+        let mut snark =
+            RecursiveSNARK::new(params, &circuit, &secondary, &z0_primary, &[F2::ZERO])?;
+        for _ in 0..steps {
+            snark.prove_step(params, &circuit, &secondary)?;
+        }
+        Ok(NovaProof {
+            snark,
+            steps,
+            z0,
+            fingerprint: None,
+        })
+    }
+
+    /// Verify a folded proof, returning the final state if the
+    /// proof is valid.
+    pub fn verify_steps(params: &NovaParams, proof: &NovaProof) -> Result<Option<Vec<String>>> {
+        let z0_primary = proof
+            .z0
+            .iter()
+            .map(|v| to_scalar(&Bn128FieldElement::from_str(v)?))
+            .collect::<Result<Vec<_>>>()?;
+        match proof
+            .snark
+            .verify(params, proof.steps, &z0_primary, &[F2::ZERO])
+        {
+            Ok((zn, _)) => Ok(Some(zn.iter().map(from_scalar).collect())),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+/// A single step of a folded computation, built from the
+/// non-symbolic constraints of an ar1cs program.
+#[derive(Clone)]
+pub struct NovaCircuit {
+    r1cs: String,
+    constraints: Vec<R1csConstraint<Bn128FieldElement>>,
+    // signals assigned by `pub input`, in the order they are read
+    inputs: Vec<usize>,
+    // signals marked by `pub output`, in the order they are written
+    outputs: Vec<usize>,
+}
+
+impl NovaCircuit {
+    fn new(r1cs: &str) -> Result<Self> {
+        let r1cs_parser: R1csParser<Bn128PolynomialRing> = R1csParser::new(r1cs)?;
+        let mut inputs = vec![];
+        let mut outputs = vec![];
+        for c in r1cs_parser.constraints.iter().filter(|c| c.symbolic) {
+            match (&c.symbolic_op, c.out_i) {
+                (Some(SymbolicOp::PublicInput), Some(i)) => inputs.push(i),
+                (Some(SymbolicOp::Output), Some(i)) => outputs.push(i),
+                (Some(SymbolicOp::Input), _) => {
+                    return log::error!(
+                        ProverError: "folded programs cannot read secret inputs",
+                        "read the state of each step using `pub input`"
+                    )
+                }
+                _ => {}
+            }
+        }
+        if inputs.is_empty() || inputs.len() != outputs.len() {
+            return log::error!(
+                ProverError: &format!(
+                    "step program has {} public inputs and {} outputs",
+                    inputs.len(),
+                    outputs.len()
+                ),
+                "each step must write a public output for each public input"
+            );
+        }
+        let constraints = r1cs_parser
+            .constraints
+            .into_iter()
+            .filter(|c| !c.symbolic)
+            .collect::<Vec<_>>();
+        // an output that no constraint uses could be any value, so
+        // the next state would not be bound to the step
+        for i in &outputs {
+            if !inputs.contains(i)
+                && !constraints
+                    .iter()
+                    .any(|c| c.a.iter().chain(&c.b).chain(&c.c).any(|(_, j)| j == i))
+            {
+                return log::error!(
+                    ProverError: &format!("step program output signal {i} is not constrained"),
+                    "each public output must be computed from the state using constraints"
+                );
+            }
+        }
+        Ok(Self {
+            r1cs: r1cs.to_string(),
+            constraints,
+            inputs,
+            outputs,
+        })
+    }
+
+    // calculate the witness of a step from its state
+    fn witness(&self, z: &[F1]) -> Result<Vec<Bn128FieldElement>> {
+        let inputs = z
+            .iter()
+            .map(|v| Bn128PolynomialRing::from_str(&from_scalar(v)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(crate::r1cs::witness::build::<Bn128PolynomialRing>(&self.r1cs, inputs)?.variables)
+    }
+}
+
+impl StepCircuit<F1> for NovaCircuit {
+    fn arity(&self) -> usize {
+        self.inputs.len()
+    }
+
+    fn synthesize<CS: ConstraintSystem<F1>>(
+        &self,
+        cs: &mut CS,
+        z: &[AllocatedNum<F1>],
+    ) -> Result<Vec<AllocatedNum<F1>>, SynthesisError> {
+        // the witness is only known when the state is known, e.g. not
+        // during setup
+        let witness = match z.iter().map(|v| v.get_value()).collect::<Option<Vec<_>>>() {
+            Some(z) => Some(
+                self.witness(&z)
+                    .map_err(|_| SynthesisError::Unsatisfiable)?,
+            ),
+            None => None,
+        };
+        let value = |i: usize| match witness.as_ref().and_then(|w| w.get(i)) {
+            Some(v) => to_scalar(v).map_err(|_| SynthesisError::Unsatisfiable),
+            None => Err(SynthesisError::AssignmentMissing),
+        };
+        let mut vars: HashMap<usize, AllocatedNum<F1>> = HashMap::new();
+        for (signal, v) in self.inputs.iter().zip(z) {
+            vars.insert(*signal, v.clone());
+        }
+        for c in &self.constraints {
+            for (_, i) in c.a.iter().chain(c.b.iter()).chain(c.c.iter()) {
+                if *i != 0 && !vars.contains_key(i) {
+                    let v =
+                        AllocatedNum::alloc(cs.namespace(|| format!("signal {i}")), || value(*i))?;
+                    vars.insert(*i, v);
+                }
+            }
+        }
+        let variable = |i: &usize| -> Variable {
+            match i {
+                0 => CS::one(),
+                i => vars[i].get_variable(),
+            }
+        };
+        let lc = |terms: &Vec<(Bn128FieldElement, usize)>| -> Result<LinearCombination<F1>, SynthesisError> {
+            let mut out = LinearCombination::zero();
+            for (coef, i) in terms {
+                let coef = to_scalar(coef).map_err(|_| SynthesisError::Unsatisfiable)?;
+                out = out + (coef, variable(i));
+            }
+            Ok(out)
+        };
+        for (j, constraint) in self.constraints.iter().enumerate() {
+            let (a, b, c) = (lc(&constraint.a)?, lc(&constraint.b)?, lc(&constraint.c)?);
+            cs.enforce(|| format!("constraint {j}"), |_| a, |_| b, |_| c);
+        }
+        // every output is an input or used by a constraint, see `new`
+        self.outputs
+            .iter()
+            .map(|i| vars.get(i).cloned().ok_or(SynthesisError::Unsatisfiable))
+            .collect()
+    }
+}
+
+fn to_scalar(v: &Bn128FieldElement) -> Result<F1> {
+    let mut repr = <F1 as PrimeField>::Repr::default();
+    let bytes = v.to_biguint().to_bytes_le();
+    repr.as_mut()[..bytes.len()].copy_from_slice(&bytes);
+    Option::from(F1::from_repr(repr))
+        .ok_or_else(|| anyhow::anyhow!("value is not in the bn256 scalar field: {v}"))
+}
+
+fn from_scalar(v: &F1) -> String {
+    BigUint::from_bytes_le(v.to_repr().as_ref()).to_string()
+}
@@ -0,0 +1,261 @@
+//! Export groth16 verifying keys as Solidity contracts. The
+//! contract verifies proofs using the alt_bn128 precompiles, so
+//! proofs generated by `Groth16Prover` can be verified on chain.
+//!
+//! The public inputs of the contract are the public signals of the
+//! program, in the order they are marked. The contract documents
+//! whether each one is a public input or output of the program.
+use std::fmt::Write;
+
+use anyhow::Result;
+use ark_bn254::Bn254;
+use ark_bn254::G1Affine;
+use ark_bn254::G2Affine;
+use ark_ff::PrimeField;
+use ark_groth16::VerifyingKey;
+
+use crate::log;
+use crate::provers::Groth16Proof;
+use crate::r1cs::constraint::SymbolicOp;
+use crate::r1cs::parser::R1csParser;
+use crate::rings::Bn128PolynomialRing;
+
+// the order of the alt_bn128 base field
+const Q: &str = "21888242871839275222246405745257275088696311157297823662689037894645226208583";
+// the order of the alt_bn128 scalar field
+const R: &str = "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+
+/// A Solidity contract verifying proofs of an ar1cs program using
+/// `vk`. `fingerprint` identifies the program in the contract
+/// documentation, see `Compiler::fingerprint`.
+pub fn verifier(r1cs: &str, vk: &VerifyingKey<Bn254>, fingerprint: Option<&str>) -> Result<String> {
+    let public = public_signals(r1cs)?;
+    if public.len() + 1 != vk.gamma_abc_g1.len() {
+        return log::error!(
+            ProverError: "verifying key does not match the program",
+            "generate the key from the same program"
+        );
+    }
+    let mut out = String::new();
+    writeln!(out, "// SPDX-License-Identifier: MIT")?;
+    writeln!(out, "pragma solidity ^0.8.0;")?;
+    writeln!(out)?;
+    writeln!(out, "/// Groth16 verifier generated by ashlang.")?;
+    if let Some(fingerprint) = fingerprint {
+        writeln!(out, "/// program fingerprint: {fingerprint}")?;
+    }
+    writeln!(out, "///")?;
+    if public.is_empty() {
+        writeln!(out, "/// The program has no public signals.")?;
+    } else {
+        writeln!(out, "/// Public signals:")?;
+        let mut inputs = 0;
+        let mut outputs = 0;
+        for (i, (op, signal)) in public.iter().enumerate() {
+            let name = match op {
+                SymbolicOp::PublicInput => {
+                    inputs += 1;
+                    format!("public input {}", inputs - 1)
+                }
+                _ => {
+                    outputs += 1;
+                    format!("output {}", outputs - 1)
+                }
+            };
+            writeln!(out, "///   input[{i}]: {name} (signal {signal})")?;
+        }
+    }
+    writeln!(out, "contract Groth16Verifier {{")?;
+    writeln!(out, "    uint256 constant Q = {Q};")?;
+    writeln!(out, "    uint256 constant R = {R};")?;
+    writeln!(out)?;
+    write_g1(&mut out, "ALPHA", &vk.alpha_g1)?;
+    write_g2(&mut out, "BETA", &vk.beta_g2)?;
+    write_g2(&mut out, "GAMMA", &vk.gamma_g2)?;
+    write_g2(&mut out, "DELTA", &vk.delta_g2)?;
+    for (i, p) in vk.gamma_abc_g1.iter().enumerate() {
+        write_g1(&mut out, &format!("IC{i}"), p)?;
+    }
+    writeln!(out)?;
+    writeln!(
+        out,
+        "    /// Verify a proof. `b` is encoded as `[[x1, x0], [y1, y0]]`."
+    )?;
+    writeln!(out, "    function verifyProof(")?;
+    writeln!(out, "        uint256[2] calldata a,")?;
+    writeln!(out, "        uint256[2][2] calldata b,")?;
+    writeln!(out, "        uint256[2] calldata c,")?;
+    writeln!(out, "        uint256[] calldata input")?;
+    writeln!(out, "    ) public view returns (bool) {{")?;
+    writeln!(out, "        if (input.length != {}) {{", public.len())?;
+    writeln!(out, "            return false;")?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "        uint256[2] memory x = [IC0_X, IC0_Y];")?;
+    for i in 0..public.len() {
+        writeln!(out, "        if (input[{i}] >= R) {{")?;
+        writeln!(out, "            return false;")?;
+        writeln!(out, "        }}")?;
+        writeln!(
+            out,
+            "        x = add(x, mul([IC{}_X, IC{}_Y], input[{i}]));",
+            i + 1,
+            i + 1
+        )?;
+    }
+    writeln!(out)?;
+    writeln!(
+        out,
+        "        // e(-a, b) * e(alpha, beta) * e(x, gamma) * e(c, delta) == 1"
+    )?;
+    writeln!(out, "        uint256[24] memory p;")?;
+    let terms = [
+        "a[0]",
+        "(Q - (a[1] % Q)) % Q",
+        "b[0][0]",
+        "b[0][1]",
+        "b[1][0]",
+        "b[1][1]",
+        "ALPHA_X",
+        "ALPHA_Y",
+        "BETA_X1",
+        "BETA_X0",
+        "BETA_Y1",
+        "BETA_Y0",
+        "x[0]",
+        "x[1]",
+        "GAMMA_X1",
+        "GAMMA_X0",
+        "GAMMA_Y1",
+        "GAMMA_Y0",
+        "c[0]",
+        "c[1]",
+        "DELTA_X1",
+        "DELTA_X0",
+        "DELTA_Y1",
+        "DELTA_Y0",
+    ];
+    for (i, term) in terms.iter().enumerate() {
+        writeln!(out, "        p[{i}] = {term};")?;
+    }
+    writeln!(out, "        uint256[1] memory result;")?;
+    writeln!(out, "        bool ok;")?;
+    writeln!(out, "        assembly {{")?;
+    writeln!(
+        out,
+        "            ok := staticcall(gas(), 8, p, 768, result, 32)"
+    )?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "        return ok && result[0] == 1;")?;
+    writeln!(out, "    }}")?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "    function add(uint256[2] memory p1, uint256[2] memory p2) internal view returns (uint256[2] memory r) {{"
+    )?;
+    writeln!(
+        out,
+        "        uint256[4] memory input = [p1[0], p1[1], p2[0], p2[1]];"
+    )?;
+    writeln!(out, "        bool ok;")?;
+    writeln!(out, "        assembly {{")?;
+    writeln!(
+        out,
+        "            ok := staticcall(gas(), 6, input, 128, r, 64)"
+    )?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "        require(ok, \"ec add failed\");")?;
+    writeln!(out, "    }}")?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "    function mul(uint256[2] memory p1, uint256 s) internal view returns (uint256[2] memory r) {{"
+    )?;
+    writeln!(out, "        uint256[3] memory input = [p1[0], p1[1], s];")?;
+    writeln!(out, "        bool ok;")?;
+    writeln!(out, "        assembly {{")?;
+    writeln!(
+        out,
+        "            ok := staticcall(gas(), 7, input, 96, r, 64)"
+    )?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "        require(ok, \"ec mul failed\");")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}")?;
+    Ok(out)
+}
+
+/// The arguments of `verifyProof` for a proof, as a json array
+/// of decimal strings, e.g. for use with `cast call`.
+pub fn calldata(proof: &Groth16Proof) -> String {
+    let a = g1(&proof.proof.a);
+    let b = g2(&proof.proof.b);
+    let c = g1(&proof.proof.c);
+    let inputs = proof
+        .inputs
+        .iter()
+        .map(|v| quote(&v.into_bigint().to_string()))
+        .collect::<Vec<_>>();
+    format!(
+        "[[{}, {}], [[{}, {}], [{}, {}]], [{}, {}], [{}]]",
+        quote(&a[0]),
+        quote(&a[1]),
+        quote(&b[0]),
+        quote(&b[1]),
+        quote(&b[2]),
+        quote(&b[3]),
+        quote(&c[0]),
+        quote(&c[1]),
+        inputs.join(", ")
+    )
+}
+
+// the public signals of a program and whether each is an input
+// or output, in the order they are exposed to the proof
+fn public_signals(r1cs: &str) -> Result<Vec<(SymbolicOp, usize)>> {
+    let parser: R1csParser<Bn128PolynomialRing> = R1csParser::new(r1cs)?;
+    let mut public: Vec<(SymbolicOp, usize)> = vec![];
+    for c in &parser.constraints {
+        if let (Some(op @ (SymbolicOp::PublicInput | SymbolicOp::Output)), Some(i)) =
+            (c.symbolic_op.clone(), c.out_i)
+        {
+            if !public.iter().any(|(_, signal)| *signal == i) {
+                public.push((op, i));
+            }
+        }
+    }
+    Ok(public)
+}
+
+fn write_g1(out: &mut String, name: &str, p: &G1Affine) -> Result<()> {
+    let [x, y] = g1(p);
+    writeln!(out, "    uint256 constant {name}_X = {x};")?;
+    writeln!(out, "    uint256 constant {name}_Y = {y};")?;
+    Ok(())
+}
+
+fn write_g2(out: &mut String, name: &str, p: &G2Affine) -> Result<()> {
+    let [x1, x0, y1, y0] = g2(p);
+    writeln!(out, "    uint256 constant {name}_X1 = {x1};")?;
+    writeln!(out, "    uint256 constant {name}_X0 = {x0};")?;
+    writeln!(out, "    uint256 constant {name}_Y1 = {y1};")?;
+    writeln!(out, "    uint256 constant {name}_Y0 = {y0};")?;
+    Ok(())
+}
+
+fn g1(p: &G1Affine) -> [String; 2] {
+    [p.x.into_bigint().to_string(), p.y.into_bigint().to_string()]
+}
+
+// the precompiles expect the imaginary part of each coordinate first
+fn g2(p: &G2Affine) -> [String; 4] {
+    [
+        p.x.c1.into_bigint().to_string(),
+        p.x.c0.into_bigint().to_string(),
+        p.y.c1.into_bigint().to_string(),
+        p.y.c0.into_bigint().to_string(),
+    ]
+}
+
+fn quote(v: &str) -> String {
+    format!("\"{v}\"")
+}
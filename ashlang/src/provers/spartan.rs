@@ -1,4 +1,7 @@
+use std::fmt::Display;
 use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
 
 use ring_math::Polynomial;
 use scalarff::Curve25519FieldElement;
@@ -7,7 +10,6 @@ extern crate libspartan;
 extern crate merlin;
 use anyhow::Result;
 use curve25519_dalek::scalar::Scalar;
-use libspartan::Assignment;
 use libspartan::InputsAssignment;
 use libspartan::Instance;
 use libspartan::SNARKGens;
@@ -19,8 +21,14 @@ use crate::cli::Config;
 use crate::compiler::Compiler;
 use crate::log;
 use crate::provers::AshlangProver;
-use crate::r1cs::constraint::R1csConstraint;
+#[cfg(feature = "serde")]
+use crate::provers::EnvelopeProof;
+#[cfg(feature = "serde")]
+use crate::provers::ProofEnvelope;
+use crate::r1cs::constraint::SymbolicOp;
 use crate::r1cs::parser::R1csParser;
+use crate::r1cs::sparse::R1csMatrices;
+use crate::r1cs::sparse::SparseMatrix;
 use crate::rings::Curve25519PolynomialRing;
 
 pub type SpartanConfig = (
@@ -31,49 +39,295 @@ pub type SpartanConfig = (
     Instance,
     Option<VarsAssignment>,
     Option<InputsAssignment>,
+    // the public outputs of the witness
+    Option<Vec<String>>,
+    // the values of the public signals
+    Option<Vec<String>>,
 );
 
-// contains the data necessary to
-// verify a proof
+/// A spartan proof and the data necessary to verify it.
+///
+/// `inputs` are the values of the public signals bound by the
+/// proof, in the order they are marked in the program, see
+/// `SpartanProver::verify_public`. Values are decimal strings so
+/// the proof does not depend on the types of the prover.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SpartanProof {
     pub snark: SNARK,
-    pub inputs: Assignment,
+    pub inputs: Vec<String>,
+    // the public outputs as decimal strings
+    pub outputs: Vec<String>,
+    // the fingerprint of the proven program, if it was compiled
+    // from source, see `Compiler::fingerprint`
+    pub fingerprint: Option<String>,
+}
+
+/// The size of a spartan instance and the cost of proving and
+/// verifying it, see `SpartanProver::prove_report`.
+#[derive(Debug, Clone)]
+pub struct SpartanReport {
+    pub constraints: usize,
+    pub variables: usize,
+    // the number of public signals bound by the proof
+    pub public_signals: usize,
+    // the size of the serialized proof in bytes, None without
+    // the serde feature
+    pub proof_size: Option<usize>,
+    pub prove_time: Duration,
+    pub verify_time: Duration,
+    pub valid: bool,
+}
+
+impl Display for SpartanReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "spartan instance: {} constraints, {} variables, {} public signals",
+            self.constraints, self.variables, self.public_signals
+        )?;
+        if let Some(size) = self.proof_size {
+            write!(f, "proof size: {size} bytes, ")?;
+        }
+        write!(
+            f,
+            "proved in {:?}, verified in {:?}",
+            self.prove_time, self.verify_time
+        )
+    }
 }
 
 /// Convert a vector into a fixed-size slice
-/// panic if the input vector.len() > 32
+/// error if the input vector.len() > 32
 /// if the input vector.len() < 32, fill the remainder with zeros
-fn to_32(v: Vec<u8>) -> [u8; 32] {
+fn to_32(v: Vec<u8>) -> Result<[u8; 32]> {
     let mut out: [u8; 32] = [0; 32];
     if v.len() > 32 {
-        panic!("too many bytes");
+        return log::error!(ProverError: &format!("{} bytes do not fit in a spartan scalar", v.len()));
     }
     for i in 0..32 {
         if i < v.len() {
             out[i] = v[i];
         }
     }
+    Ok(out)
+}
+
+// an error from building or checking a spartan assignment
+fn assignment_error<T>(e: impl std::fmt::Debug) -> Result<T> {
+    log::error!(ProverError: "invalid spartan assignment", &format!("{e:?}"))
+}
+
+// the signals exposed as public inputs of the proof, in the
+// order they are marked in the program
+fn public_signals(r1cs_parser: &R1csParser<Curve25519PolynomialRing>) -> Vec<usize> {
+    let mut out = vec![];
+    for c in r1cs_parser.constraints.iter().filter(|c| c.symbolic) {
+        if let (Some(SymbolicOp::Output | SymbolicOp::PublicInput), Some(i)) =
+            (&c.symbolic_op, c.out_i)
+        {
+            if !out.contains(&i) {
+                out.push(i);
+            }
+        }
+    }
     out
 }
 
+// the inputs in the order the program reads them, each `pub
+// input` is read from the public inputs and each `secret input`
+// from the secret inputs. Without public inputs every input is
+// read from the secret inputs
+fn program_inputs(
+    r1cs_parser: &R1csParser<Curve25519PolynomialRing>,
+    public_inputs: Vec<String>,
+    secret_inputs: Vec<String>,
+) -> Result<Vec<String>> {
+    if public_inputs.is_empty() {
+        return Ok(secret_inputs);
+    }
+    let mut public_inputs = public_inputs.into_iter();
+    let mut secret_inputs = secret_inputs.into_iter();
+    let mut out = vec![];
+    for c in r1cs_parser.constraints.iter().filter(|c| c.symbolic) {
+        let (kind, v) = match c.symbolic_op {
+            Some(SymbolicOp::PublicInput) => ("public", public_inputs.next()),
+            Some(SymbolicOp::Input) => ("secret", secret_inputs.next()),
+            _ => continue,
+        };
+        match v {
+            Some(v) => out.push(v),
+            None => {
+                return log::error!(
+                    InputError: &format!("the program reads more {kind} inputs than were given")
+                )
+            }
+        }
+    }
+    if public_inputs.next().is_some() || secret_inputs.next().is_some() {
+        return log::error!(InputError: "more inputs were given than the program reads");
+    }
+    Ok(out)
+}
+
+// the value of each public signal given the values of the
+// public inputs and outputs, in the order of `public_signals`
+fn public_values(
+    r1cs_parser: &R1csParser<Curve25519PolynomialRing>,
+    public_inputs: &[String],
+    public_outputs: &[String],
+) -> Result<Vec<String>> {
+    let signals = public_signals(r1cs_parser);
+    let mut public_inputs = public_inputs.iter();
+    let mut public_outputs = public_outputs.iter();
+    let mut values = vec![None; signals.len()];
+    for c in r1cs_parser.constraints.iter().filter(|c| c.symbolic) {
+        let v = match c.symbolic_op {
+            Some(SymbolicOp::PublicInput) => public_inputs.next(),
+            Some(SymbolicOp::Output) => public_outputs.next(),
+            _ => continue,
+        };
+        let Some(v) = v else {
+            return log::error!(
+                InputError: "fewer public values were given than the program declares"
+            );
+        };
+        if let Some(j) = signals.iter().position(|i| Some(*i) == c.out_i) {
+            values[j].get_or_insert(v.clone());
+        }
+    }
+    if public_inputs.next().is_some() || public_outputs.next().is_some() {
+        return log::error!(InputError: "more public values were given than the program declares");
+    }
+    Ok(values.into_iter().flatten().collect())
+}
+
+fn inputs_assignment(values: &[String]) -> Result<InputsAssignment> {
+    let inputs = values
+        .iter()
+        .map(|v| to_32(Curve25519FieldElement::from_str(v)?.to_bytes_le()))
+        .collect::<Result<Vec<_>>>()?;
+    InputsAssignment::new(&inputs).or_else(assignment_error)
+}
+
 /// Bindings for executing ashlang programs in the [microsoft/spartan](https://github.com/microsoft/Spartan) prover.
+///
+/// Spartan has no trusted setup. The public parameters and the
+/// commitment to the program are derived deterministically and
+/// are recomputed for each proof, they are not cached.
+///
+/// Signals marked public in the program, using `pub input` and
+/// the public outputs, are the public inputs of the spartan
+/// instance and are bound by the proof.
 pub struct SpartanProver {}
 
-impl AshlangProver<SpartanProof> for SpartanProver {
-    fn prove_ir(
+impl SpartanProver {
+    /// Generate a proof and verify it, reporting the size of the
+    /// instance and proof and the time taken by each step.
+    pub fn prove_report(
         r1cs: &str,
-        inputs: Vec<String>,
+        public_inputs: Vec<String>,
         secret_inputs: Vec<String>,
-    ) -> Result<SpartanProof> {
-        if !inputs.is_empty() {
-            anyhow::bail!("ashlang spartan connector does not support public inputs")
+    ) -> Result<(SpartanProof, SpartanReport)> {
+        let r1cs_parser: R1csParser<Curve25519PolynomialRing> = R1csParser::new(r1cs)?;
+        let start = Instant::now();
+        let proof = Self::prove_ir(r1cs, public_inputs, secret_inputs)?;
+        let prove_time = start.elapsed();
+        let start = Instant::now();
+        let valid = Self::check(r1cs, &proof, &proof.inputs)?;
+        let verify_time = start.elapsed();
+        #[cfg(feature = "serde")]
+        let proof_size = Some(EnvelopeProof::to_bytes(&proof)?.len());
+        #[cfg(not(feature = "serde"))]
+        let proof_size = None;
+        let report = SpartanReport {
+            constraints: r1cs_parser
+                .constraints
+                .iter()
+                .filter(|c| !c.symbolic)
+                .count(),
+            variables: r1cs_parser.var_count(),
+            public_signals: proof.inputs.len(),
+            proof_size,
+            prove_time,
+            verify_time,
+            valid,
+        };
+        Ok((proof, report))
+    }
+
+    /// Verify a proof against the values of the public inputs
+    /// read by `pub input` and the public outputs of the proof,
+    /// instead of the public signal values in the proof.
+    pub fn verify_public(
+        r1cs: &str,
+        proof: &SpartanProof,
+        public_inputs: &[String],
+    ) -> Result<bool> {
+        let r1cs_parser: R1csParser<Curve25519PolynomialRing> = R1csParser::new(r1cs)?;
+        let public = public_values(&r1cs_parser, public_inputs, &proof.outputs)?;
+        Self::check(r1cs, proof, &public)
+    }
+
+    // verify a proof given the values of the public signals
+    fn check(r1cs: &str, proof: &SpartanProof, public: &[String]) -> Result<bool> {
+        let spartan_config = transform_r1cs(r1cs, None)?;
+        let (
+            num_cons,
+            num_vars,
+            num_inputs,
+            num_non_zero_entries,
+            inst,
+            _assignment_vars,
+            _assignment_inputs,
+            _outputs,
+            _public,
+        ) = spartan_config;
+        if public.len() != num_inputs {
+            return Ok(false);
         }
+        let gens = SNARKGens::new(num_cons, num_vars, num_inputs, num_non_zero_entries);
+
+        // create a commitment to the R1CS instance
+        let (comm, _decomm) = SNARK::encode(&inst, &gens);
+        // verify the proof of satisfiability
+        let mut verifier_transcript = Transcript::new(b"ashlang-spartan");
+        Ok(proof
+            .snark
+            .verify(
+                &comm,
+                &inputs_assignment(public)?,
+                &mut verifier_transcript,
+                &gens,
+            )
+            .is_ok())
+    }
+}
+
+// spartan has no setup, the instance is derived from the program
+// when proving and verifying, so the verifying key is the ar1cs
+impl AshlangProver for SpartanProver {
+    type Proof = SpartanProof;
+    type ProvingKey = ();
+    type VerifyingKey = String;
+    type Error = anyhow::Error;
+
+    fn setup(r1cs: &str) -> Result<((), String)> {
+        Ok(((), r1cs.to_string()))
+    }
+
+    fn prove(
+        r1cs: &str,
+        _pk: &(),
+        public_inputs: Vec<String>,
+        secret_inputs: Vec<String>,
+    ) -> Result<SpartanProof> {
+        let r1cs_parser: R1csParser<Curve25519PolynomialRing> = R1csParser::new(r1cs)?;
+        let inputs = program_inputs(&r1cs_parser, public_inputs, secret_inputs)?;
         // produce public parameters
         let spartan_config = transform_r1cs(
-            &r1cs,
+            r1cs,
             Some(
-                secret_inputs
+                inputs
                     .iter()
                     .map(|v| Curve25519FieldElement::from_str(v))
                     .collect::<Result<Vec<_>>>()?,
@@ -87,6 +341,8 @@ impl AshlangProver<SpartanProof> for SpartanProver {
             inst,
             assignment_vars,
             assignment_inputs,
+            outputs,
+            public,
         ) = spartan_config;
         let gens = SNARKGens::new(num_cons, num_vars, num_inputs, num_non_zero_entries);
 
@@ -105,165 +361,106 @@ impl AshlangProver<SpartanProof> for SpartanProver {
                 &gens,
                 &mut prover_transcript,
             ),
-            inputs: assignment_inputs.unwrap(),
+            inputs: public.unwrap_or_default(),
+            outputs: outputs.unwrap_or_default(),
+            fingerprint: None,
         })
     }
 
-    fn prove(config: &Config) -> Result<SpartanProof> {
+    fn prove_config(config: &Config) -> Result<SpartanProof> {
         let mut config = config.clone();
         config.extension_priorities.push("ar1cs".to_string());
+        config.extension_priorities.push("r1cs".to_string());
 
         if config.field != "curve25519" {
             return log::error!(
-                "unsupported curve for microsoft/spartan proof",
+                ProverError: "unsupported curve for microsoft/spartan proof",
                 "field must be \"curve25519\""
             );
         }
 
         let mut compiler: Compiler<Curve25519PolynomialRing> = Compiler::new(&config)?;
         let r1cs = compiler.compile(&config.entry_fn)?;
-        Self::prove_ir(&r1cs, vec![], config.secret_inputs)
+        let mut proof = Self::prove_ir(&r1cs, config.inputs, config.secret_inputs)?;
+        proof.fingerprint = compiler.fingerprint().map(String::from);
+        Ok(proof)
     }
 
-    fn verify(r1cs: &str, serialized_proof: SpartanProof) -> Result<bool> {
-        let spartan_config = transform_r1cs(&r1cs, None)?;
-        let (
-            num_cons,
-            num_vars,
-            num_inputs,
-            num_non_zero_entries,
-            inst,
-            _assignment_vars,
-            _assignment_inputs,
-        ) = spartan_config;
-        let gens = SNARKGens::new(num_cons, num_vars, num_inputs, num_non_zero_entries);
-
-        // create a commitment to the R1CS instance
-        let (comm, _decomm) = SNARK::encode(&inst, &gens);
-        // verify the proof of satisfiability
-        let mut verifier_transcript = Transcript::new(b"ashlang-spartan");
+    fn verify(r1cs: &String, proof: &SpartanProof) -> Result<bool> {
+        Self::check(r1cs, proof, &proof.inputs)
+    }
 
-        // TODO: deal with the return value of this function
-        // instead of discarding it with is_ok
-        Ok(serialized_proof
-            .snark
-            .verify(
-                &comm,
-                &serialized_proof.inputs,
-                &mut verifier_transcript,
-                &gens,
-            )
-            .is_ok())
+    fn outputs(proof: &SpartanProof) -> Vec<String> {
+        proof.outputs.clone()
     }
 }
 
 /// Take an ar1cs source file and do the following:
 /// - calculate a witness given some inputs
-/// - rearrange the R1CS variables such that the `one` variable and all inputs are at the end
+/// - rearrange the R1CS variables such that the `one` variable is
+///   followed by the public signals, after all other variables
 /// - prepare a SpartanConfig structure to be used with `ashlang_spartan::prove`
 pub fn transform_r1cs(
     r1cs: &str,
     inputs: Option<Vec<Curve25519FieldElement>>,
 ) -> Result<SpartanConfig> {
-    // filter out the symbolic constraints
     let r1cs_parser: R1csParser<Curve25519PolynomialRing> = R1csParser::new(r1cs)?;
-    let constraints = r1cs_parser
-        .constraints
-        .iter()
-        .filter(|c| !c.symbolic)
-        .collect::<Vec<_>>();
+    let var_count = r1cs_parser.var_count();
+    let public = public_signals(&r1cs_parser);
+    // number of variables
+    let num_vars = var_count - 1 - public.len();
+    let num_inputs = public.len();
+
+    // spartan expects the private variables, then the one
+    // variable, then the public inputs
+    let mut columns = vec![0; var_count];
+    let mut next = 0;
+    for (i, column) in columns.iter_mut().enumerate() {
+        *column = if i == 0 {
+            num_vars
+        } else if let Some(j) = public.iter().position(|v| *v == i) {
+            num_vars + 1 + j
+        } else {
+            next += 1;
+            next - 1
+        };
+    }
+    // Symbolic constraints are dropped.
+    let matrices =
+        R1csMatrices::new(&r1cs_parser.constraints, var_count).map_columns(|i| columns[i]);
 
     // number of constraints
-    let num_cons = constraints.len();
-    // number of variables
-    let num_vars = r1cs_parser.var_count() - 1;
-    let num_inputs = 0;
+    let num_cons = matrices.rows();
 
     // this variable is absurdly complex, it works for now
     // but if anything weird happens ask the spartan authors
-    let mut num_non_zero_entries = 0;
-
-    // in each constraint remap the one variable to the end of the
-    // var vector
-    // TODO: when inputs are supported by ashlang they will
-    // need to be moved to the correct place as well.
-    let remapped_constraints = constraints
-        .iter()
-        .map(|constraint| {
-            let mut new_a = vec![];
-            let mut new_b = vec![];
-            let mut new_c = vec![];
-            for (v, var_i) in constraint.a.clone() {
-                if var_i == 0 {
-                    new_a.push((v, r1cs_parser.var_count() - 1));
-                } else if var_i == r1cs_parser.var_count() - 1 {
-                    new_a.push((v, 0));
-                } else {
-                    new_a.push((v, var_i));
-                }
-            }
-            for (v, var_i) in constraint.b.clone() {
-                if var_i == 0 {
-                    new_b.push((v, r1cs_parser.var_count() - 1));
-                } else if var_i == r1cs_parser.var_count() - 1 {
-                    new_b.push((v, 0));
-                } else {
-                    new_b.push((v, var_i));
-                }
-            }
-            for (v, var_i) in constraint.c.clone() {
-                if var_i == 0 {
-                    new_c.push((v, r1cs_parser.var_count() - 1));
-                } else if var_i == r1cs_parser.var_count() - 1 {
-                    new_c.push((v, 0));
-                } else {
-                    new_c.push((v, var_i));
-                }
-            }
-            R1csConstraint {
-                a: new_a,
-                b: new_b,
-                c: new_c,
-                out_i: None,
-                comment: None,
-                symbolic: false,
-                symbolic_op: None,
-            }
-        })
-        .collect::<Vec<_>>();
+    let num_non_zero_entries = matrices.a.nnz();
 
     // every row = constraint
     // every column = variable
 
     // We will encode the above constraints into three matrices, where
     // the coefficients in the matrix are in the little-endian byte order
-    let mut a_mat: Vec<(usize, usize, [u8; 32])> = Vec::new();
-    let mut b_mat: Vec<(usize, usize, [u8; 32])> = Vec::new();
-    let mut c_mat: Vec<(usize, usize, [u8; 32])> = Vec::new();
-
-    for (i, constraint) in remapped_constraints.iter().enumerate() {
-        for (v, col_i) in &constraint.a {
-            num_non_zero_entries += 1;
-            a_mat.push((i, *col_i, to_32(v.to_bytes_le())));
-        }
-        for (v, col_i) in &constraint.b {
-            b_mat.push((i, *col_i, to_32(v.to_bytes_le())));
-        }
-        for (v, col_i) in &constraint.c {
-            c_mat.push((i, *col_i, to_32(v.to_bytes_le())));
-        }
-    }
-
-    // println!("cons: {num_cons} vars: {num_vars}: non-0 entries: {num_non_zero_entries}");
+    let triplets = |m: &SparseMatrix<Curve25519FieldElement>| {
+        m.triplets()
+            .map(|(row, col, v)| Ok((row, col, to_32(v.to_bytes_le())?)))
+            .collect::<Result<Vec<(usize, usize, [u8; 32])>>>()
+    };
+    let a_mat = triplets(&matrices.a)?;
+    let b_mat = triplets(&matrices.b)?;
+    let c_mat = triplets(&matrices.c)?;
 
-    let inst = Instance::new(num_cons, num_vars, num_inputs, &a_mat, &b_mat, &c_mat);
-    if let Err(e) = inst {
-        panic!("error building instance: {:?}", e);
-    }
-    let inst = inst.unwrap();
-    // return here?
-    if inputs.is_none() {
-        // just return the instance?
+    let inst = match Instance::new(num_cons, num_vars, num_inputs, &a_mat, &b_mat, &c_mat) {
+        Ok(inst) => inst,
+        Err(e) => {
+            return log::error!(
+                ProverError: "error building spartan instance",
+                &format!("{e:?}")
+            )
+        }
+    };
+    // without inputs only the instance is built, e.g. to verify
+    let Some(inputs) = inputs else {
         return Ok((
             num_cons,
             num_vars,
@@ -272,42 +469,51 @@ pub fn transform_r1cs(
             inst,
             None,
             None,
+            None,
+            None,
         ));
-    }
+    };
 
     // build the witness
     let witness = crate::r1cs::witness::build::<Curve25519PolynomialRing>(
         r1cs,
         inputs
-            .unwrap()
             .iter()
             .map(|v| Curve25519PolynomialRing(Polynomial::new(vec![*v])))
             .collect(),
     )?;
-    let mut witness = witness.variables;
-
-    // put the one variable at the end of the witness vector
-    // all the R1csConstraint variables need to be modified similary
-    // see the massive iterator body below
-    let l = witness.len();
-    witness[0] = witness[l - 1];
-    witness[l - 1] = Curve25519FieldElement::from(1);
+    let outputs =
+        crate::r1cs::witness::public_outputs::<Curve25519PolynomialRing>(r1cs, &witness.variables)?;
+    let witness = witness.variables;
 
-    // create a VarsAssignment
+    // split the witness using the same columns as the matrices
     let mut vars = vec![Scalar::ZERO.to_bytes(); num_vars];
-    for i in 0..num_vars {
-        vars[i] = to_32(witness[i].to_bytes_le());
+    let mut inputs = vec![Scalar::ZERO.to_bytes(); num_inputs];
+    for (v, column) in witness.iter().zip(&columns).skip(1) {
+        let bytes = to_32(v.to_bytes_le())?;
+        if *column < num_vars {
+            vars[*column] = bytes;
+        } else {
+            inputs[column - num_vars - 1] = bytes;
+        }
     }
-    let assignment_vars = VarsAssignment::new(&vars).unwrap();
-
-    // create an InputsAssignment
-    let inputs = vec![Scalar::ZERO.to_bytes(); num_inputs];
-    let assignment_inputs = InputsAssignment::new(&inputs).unwrap();
+    let public_values = public
+        .iter()
+        .map(|i| witness[*i].to_string())
+        .collect::<Vec<_>>();
+    let assignment_vars = VarsAssignment::new(&vars).or_else(assignment_error)?;
+    let assignment_inputs = InputsAssignment::new(&inputs).or_else(assignment_error)?;
 
     // check if the instance we created is satisfiable
-    let res = inst.is_sat(&assignment_vars, &assignment_inputs);
-    // panic if the provided R1CS is not satisfied
-    assert!(res.unwrap());
+    if !inst
+        .is_sat(&assignment_vars, &assignment_inputs)
+        .or_else(assignment_error)?
+    {
+        return log::error!(
+            ProverError: "spartan instance is not satisfied by the witness",
+            "this is a compiler bug"
+        );
+    }
 
     Ok((
         num_cons,
@@ -317,5 +523,31 @@ pub fn transform_r1cs(
         inst,
         Some(assignment_vars),
         Some(assignment_inputs),
+        Some(outputs),
+        Some(public_values),
     ))
 }
+
+#[cfg(feature = "serde")]
+impl SpartanProver {
+    /// Verify a proof envelope. The public values of the envelope
+    /// must be the public signals bound by the proof.
+    pub fn verify_envelope(r1cs: &str, envelope: &ProofEnvelope) -> Result<bool> {
+        let proof: SpartanProof = envelope.proof()?;
+        let public = envelope.public_signals::<Curve25519PolynomialRing>(r1cs)?;
+        Self::check(r1cs, &proof, &public)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl EnvelopeProof for SpartanProof {
+    const BACKEND: &'static str = "spartan";
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+}
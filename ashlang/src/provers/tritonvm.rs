@@ -1,63 +1,172 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
 use std::str::FromStr;
 
 use anyhow::Result;
+use camino::Utf8PathBuf;
 use triton_vm::prelude::BFieldElement;
 use triton_vm::program::NonDeterminism;
+use triton_vm::program::Program;
 use triton_vm::program::PublicInput;
 use triton_vm::proof::Claim;
 use triton_vm::proof::Proof;
 use triton_vm::stark::Stark;
+use triton_vm::vm::VMState;
 
 use super::ashlang_prover::AshlangProver;
 use crate::cli::Config;
 use crate::compiler::Compiler;
 use crate::log;
+use crate::profile::Profile;
+#[cfg(feature = "serde")]
+use crate::provers::EnvelopeProof;
+#[cfg(feature = "serde")]
+use crate::provers::ProofEnvelope;
 use crate::rings::OxfoiPolynomialRing;
+use crate::tasm::memory::MemoryReport;
 
 /// Bindings for executing ashlang programs in the [tritonVM/triton-vm](https://github.com/TritonVM/triton-vm/) prover.
 pub struct TritonVMProver {}
 
-impl AshlangProver<(Stark, Claim, Proof)> for TritonVMProver {
-    fn prove_ir(
-        asm: &str,
-        public_inputs: Vec<String>,
-        secret_inputs: Vec<String>,
-    ) -> Result<(Stark, Claim, Proof)> {
-        // then attempt to prove the assembly in TritonVM
+/// The state of Triton VM after executing a program without
+/// generating a proof, see `TritonVMProver::trace`.
+#[derive(Debug, Clone)]
+pub struct ExecutionReport {
+    // the number of instructions executed
+    pub cycles: u32,
+    // the number of memory cells written
+    pub ram: usize,
+    // the op stack when the program halts, top first
+    pub stack: Vec<BFieldElement>,
+    pub outputs: Vec<BFieldElement>,
+}
+
+impl Display for ExecutionReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "cycles: {}", self.cycles)?;
+        writeln!(f, "ram: {} cells", self.ram)?;
+        write!(
+            f,
+            "stack: [{}]",
+            self.stack
+                .iter()
+                .map(|v| v.value().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+/// A program compiled for Triton VM and the reports requested
+/// by the config, see `TritonVMProver::compile`.
+#[derive(Debug, Clone)]
+pub struct CompiledTasm {
+    pub asm: String,
+    pub fingerprint: Option<String>,
+    pub memory_report: Option<MemoryReport>,
+    // the cycles of each source line, if profiling is enabled
+    pub profile: Option<Profile>,
+}
+
+impl TritonVMProver {
+    fn program(asm: &str) -> Result<Program> {
         let instructions = triton_vm::parser::parse(asm);
         if let Err(e) = instructions {
-            return log::error!(&format!("Failed to parse compiled tasm: {:?}", e));
+            return log::error!(ProverError: &format!("Failed to parse compiled tasm: {:?}", e));
         }
         let instructions = instructions.unwrap();
         let l_instructions = triton_vm::parser::to_labelled_instructions(instructions.as_slice());
-        let program = triton_vm::program::Program::new(l_instructions.as_slice());
+        Ok(Program::new(l_instructions.as_slice()))
+    }
+
+    fn inputs(
+        public_inputs: Vec<String>,
+        secret_inputs: Vec<String>,
+    ) -> (PublicInput, NonDeterminism) {
         let public_inputs = PublicInput::from(
             public_inputs
-                .clone()
                 .into_iter()
                 .map(|v| BFieldElement::from_str(&v).unwrap())
                 .collect::<Vec<_>>(),
         );
         let secret_inputs = NonDeterminism::from(
             secret_inputs
-                .clone()
                 .into_iter()
                 .map(|v| BFieldElement::from_str(&v).unwrap())
                 .collect::<Vec<_>>(),
         );
+        (public_inputs, secret_inputs)
+    }
 
-        Ok(triton_vm::prove_program(
-            &program,
-            public_inputs,
-            secret_inputs,
-        )?)
+    /// Execute compiled assembly without generating a proof,
+    /// returning the public output.
+    pub fn run(
+        asm: &str,
+        public_inputs: Vec<String>,
+        secret_inputs: Vec<String>,
+    ) -> Result<Vec<BFieldElement>> {
+        let program = Self::program(asm)?;
+        let (public_inputs, secret_inputs) = Self::inputs(public_inputs, secret_inputs);
+        let mut state = VMState::new(&program, public_inputs, secret_inputs);
+        while !state.halting {
+            if let Err(e) = state.step() {
+                return log::error!(ProverError: &format!("Triton VM errored: {e}"));
+            }
+        }
+        Ok(state.public_output)
+    }
+
+    /// Execute compiled assembly without generating a proof,
+    /// writing each executed instruction to a file. Each line of
+    /// the trace is the cycle, the instruction pointer, and the
+    /// instruction.
+    pub fn trace(
+        asm: &str,
+        public_inputs: Vec<String>,
+        secret_inputs: Vec<String>,
+        path: &Utf8PathBuf,
+    ) -> Result<ExecutionReport> {
+        let program = Self::program(asm)?;
+        let (public_inputs, secret_inputs) = Self::inputs(public_inputs, secret_inputs);
+        let mut out = BufWriter::new(File::create(path)?);
+        let mut state = VMState::new(&program, public_inputs, secret_inputs);
+        while !state.halting {
+            let instruction = match state.current_instruction() {
+                Ok(v) => v.to_string(),
+                Err(e) => return log::error!(ProverError: &format!("Triton VM errored: {e}")),
+            };
+            writeln!(
+                out,
+                "{}\t{}\t{instruction}",
+                state.cycle_count, state.instruction_pointer
+            )?;
+            if let Err(e) = state.step() {
+                // the trace ends at the failing instruction
+                out.flush()?;
+                return log::error!(
+                    ProverError: &format!("Triton VM errored: {e}"),
+                    &format!("the instruction trace was written to {path}")
+                );
+            }
+        }
+        out.flush()?;
+        Ok(ExecutionReport {
+            cycles: state.cycle_count,
+            ram: state.ram.len(),
+            stack: state.op_stack.stack.iter().rev().cloned().collect(),
+            outputs: state.public_output,
+        })
     }
 
-    fn prove(config: &Config) -> Result<(Stark, Claim, Proof)> {
+    /// Compile the entry function of a config in the oxfoi field.
+    /// The memory report is included if the config requests it.
+    pub fn compile(config: &Config) -> Result<CompiledTasm> {
         let mut config = config.clone();
         if config.field != "foi" && config.field != "goldilocks" {
             return log::error!(
-                &format!("Unsupported field for target tasm: {}", config.field),
+                ConfigError: &format!("Unsupported field for target tasm: {}", config.field),
                 "tasm only support execution in the foi (goldilocks) field"
             );
         }
@@ -69,11 +178,158 @@ impl AshlangProver<(Stark, Claim, Proof)> for TritonVMProver {
         // compile as needed
         //
         let asm = compiler.compile(&config.entry_fn)?;
-        // generate the proof
-        Self::prove_ir(&asm, config.inputs, config.secret_inputs)
+        let memory_report = compiler
+            .memory_report()
+            .filter(|_| config.report_memory)
+            .cloned();
+        let profile = match compiler.profile() {
+            Some(profile) => {
+                let mut profile = profile.clone();
+                Self::profile_cycles(
+                    &asm,
+                    &mut profile,
+                    config.inputs.clone(),
+                    config.secret_inputs.clone(),
+                )?;
+                Some(profile)
+            }
+            None => None,
+        };
+        Ok(CompiledTasm {
+            asm,
+            fingerprint: compiler.fingerprint().map(String::from),
+            memory_report,
+            profile,
+        })
+    }
+
+    /// Execute compiled assembly and add the number of instructions
+    /// executed for each source line to a profile. The assembly must
+    /// be compiled with profiling enabled.
+    pub fn profile_cycles(
+        asm: &str,
+        profile: &mut Profile,
+        public_inputs: Vec<String>,
+        secret_inputs: Vec<String>,
+    ) -> Result<()> {
+        let program = Self::program(asm)?;
+        let (public_inputs, secret_inputs) = Self::inputs(public_inputs, secret_inputs);
+        let addresses = profile.addresses(asm);
+        let mut state = VMState::new(&program, public_inputs, secret_inputs);
+        while !state.halting {
+            profile.record_cycle(&addresses, state.instruction_pointer);
+            if let Err(e) = state.step() {
+                return log::error!(ProverError: &format!("Triton VM errored while profiling: {e}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Triton VM has no setup, a proof is verified against the digest
+// of the program in the claim
+impl AshlangProver for TritonVMProver {
+    type Proof = (Stark, Claim, Proof);
+    type ProvingKey = ();
+    type VerifyingKey = Program;
+    type Error = anyhow::Error;
+
+    fn setup(asm: &str) -> Result<((), Program)> {
+        Ok(((), Self::program(asm)?))
+    }
+
+    fn prove(
+        asm: &str,
+        _pk: &(),
+        public_inputs: Vec<String>,
+        secret_inputs: Vec<String>,
+    ) -> Result<(Stark, Claim, Proof)> {
+        // then attempt to prove the assembly in TritonVM
+        let program = Self::program(asm)?;
+        let (public_inputs, secret_inputs) = Self::inputs(public_inputs, secret_inputs);
+
+        Ok(triton_vm::prove_program(
+            &program,
+            public_inputs,
+            secret_inputs,
+        )?)
+    }
+
+    fn prove_config(config: &Config) -> Result<(Stark, Claim, Proof)> {
+        let compiled = Self::compile(config)?;
+        Self::prove_ir(
+            &compiled.asm,
+            config.inputs.clone(),
+            config.secret_inputs.clone(),
+        )
+    }
+
+    fn verify(program: &Program, proof: &(Stark, Claim, Proof)) -> Result<bool> {
+        let (stark, claim, proof) = proof;
+        if claim.program_digest != Claim::about_program(program).program_digest {
+            return Ok(false);
+        }
+        Ok(triton_vm::verify(*stark, claim, proof))
+    }
+
+    fn outputs(proof: &(Stark, Claim, Proof)) -> Vec<String> {
+        proof
+            .1
+            .output
+            .iter()
+            .map(|v| v.value().to_string())
+            .collect()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TritonVMProver {
+    /// Verify a proof envelope of the program compiled to `asm` and
+    /// check that its public values are the input and output of the
+    /// claim. Proofs with a claim about a different program are not
+    /// valid.
+    pub fn verify_envelope(asm: &str, envelope: &ProofEnvelope) -> Result<bool> {
+        let program = Self::program(asm)?;
+        let (stark, claim, proof): (Stark, Claim, Proof) = envelope.proof()?;
+        if claim.program_digest != Claim::about_program(&program).program_digest {
+            return Ok(false);
+        }
+        let parse = |values: &[String]| {
+            values
+                .iter()
+                .map(|v| match BFieldElement::from_str(v) {
+                    Ok(v) => Ok(v),
+                    Err(_) => log::error!(ProverError: &format!("invalid public value: {v}")),
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+        if claim.input != parse(&envelope.public_inputs)?
+            || claim.output != parse(&envelope.public_outputs)?
+        {
+            return Ok(false);
+        }
+        Ok(triton_vm::verify(stark, &claim, &proof))
+    }
+
+    fn outputs(proof: &(Stark, Claim, Proof)) -> Vec<String> {
+        proof
+            .1
+            .output
+            .iter()
+            .map(|v| v.value().to_string())
+            .collect()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl EnvelopeProof for (Stark, Claim, Proof) {
+    const BACKEND: &'static str = "tritonvm";
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
     }
 
-    fn verify(_program: &str, _proof: (Stark, Claim, Proof)) -> Result<bool> {
-        panic!("tritonvm verification not implemented");
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(bincode::deserialize(bytes)?)
     }
 }
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::Result;
+use pest::iterators::Pair;
+use pest::iterators::Pairs;
+use pest::Parser;
+use scalarff::FieldElement;
+
+use super::constraint::index_to_string;
+use super::constraint::R1csConstraint;
+use super::constraint::SymbolicOp;
+use super::parser::R1csPestParser;
+use super::parser::Rule;
+use crate::log;
+
+/// The version of the ar1cs format written by the compiler. It
+/// is incremented when a change alters how an existing artifact
+/// is read.
+pub const AR1CS_VERSION: u32 = 1;
+
+static VERSION_PREFIX: &str = "# ar1cs v";
+static FIELD_PREFIX: &str = "# Compiled for ";
+static SYMBOL_PREFIX: &str = "# signal ";
+
+/// A public value of a compiled program, e.g. `pub input x`,
+/// and the signal holding it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ar1csPublic {
+    pub declaration: String,
+    pub signal: usize,
+}
+
+/// The source name of a variable and every signal it has held,
+/// e.g. `# signal hash::state: x4, x5`. Variables in functions
+/// are prefixed with the function name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ar1csSymbol {
+    pub name: String,
+    pub signals: Vec<usize>,
+}
+
+/// A compiled ar1cs program.
+///
+/// The text format begins with a header of comments
+///
+/// ```text
+/// # ar1cs v1
+/// # entry_fn
+/// # Compiled for foi
+/// #
+/// # pub input x: x3
+/// # signal x: x3
+/// ```
+///
+/// followed by one constraint or symbolic constraint per line.
+/// The symbol table is only written when compiling with
+/// `--symbols`.
+/// Displaying a parsed artifact reproduces the text written by
+/// the compiler exactly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ar1cs<T: FieldElement> {
+    pub version: u32,
+    pub entry_fn: String,
+    /// The name of the field the program was compiled for.
+    pub field: String,
+    pub public: Vec<Ar1csPublic>,
+    pub symbols: Vec<Ar1csSymbol>,
+    pub constraints: Vec<R1csConstraint<T>>,
+}
+
+impl<T: FieldElement> Ar1cs<T> {
+    pub fn new(entry_fn: &str, field: &str) -> Self {
+        Self {
+            version: AR1CS_VERSION,
+            entry_fn: entry_fn.to_string(),
+            field: field.to_string(),
+            public: vec![],
+            symbols: vec![],
+            constraints: vec![],
+        }
+    }
+}
+
+/// Read the symbol table from the header of ar1cs source without
+/// parsing the constraints. Source without a symbol table,
+/// including hand-written ar1cs, has no symbols.
+pub fn symbols(source: &str) -> Result<Vec<Ar1csSymbol>> {
+    source
+        .lines()
+        .take_while(|v| v.starts_with('#'))
+        .filter(|v| v.starts_with(SYMBOL_PREFIX))
+        .map(parse_symbol)
+        .collect()
+}
+
+/// The source names of each signal in a symbol table.
+pub fn signal_names(symbols: &[Ar1csSymbol]) -> HashMap<usize, Vec<&str>> {
+    let mut out: HashMap<usize, Vec<&str>> = HashMap::new();
+    for symbol in symbols {
+        for signal in &symbol.signals {
+            out.entry(*signal).or_default().push(&symbol.name);
+        }
+    }
+    out
+}
+
+impl<T: FieldElement> FromStr for Ar1cs<T> {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self> {
+        let mut lines = source.lines().peekable();
+        let version = lines
+            .next()
+            .and_then(|v| v.strip_prefix(VERSION_PREFIX))
+            .and_then(|v| v.parse::<u32>().ok());
+        let version = match version {
+            Some(v) => v,
+            None => {
+                return log::error!(
+                    ParseError: "ar1cs source does not begin with a version header",
+                    &format!("compiled ar1cs begins with `{VERSION_PREFIX}{AR1CS_VERSION}`")
+                )
+            }
+        };
+        if version > AR1CS_VERSION {
+            return log::error!(
+                ParseError: &format!("unsupported ar1cs version: {version}"),
+                &format!("this version of ashlang reads ar1cs up to version {AR1CS_VERSION}")
+            );
+        }
+        let entry_fn = lines.next().and_then(|v| v.strip_prefix("# "));
+        let field = lines.next().and_then(|v| v.strip_prefix(FIELD_PREFIX));
+        let (entry_fn, field) = match (entry_fn, field, lines.next()) {
+            (Some(entry_fn), Some(field), Some("#")) => (entry_fn, field),
+            _ => {
+                return log::error!(
+                    ParseError: "malformed ar1cs header",
+                    "the version is followed by the entry function, the field, and an empty comment"
+                )
+            }
+        };
+        let mut out = Self::new(entry_fn, field);
+        out.version = version;
+        while let Some(line) = lines.next_if(|v| v.starts_with("# pub ")) {
+            match line.strip_prefix("# ").and_then(|v| v.rsplit_once(": ")) {
+                Some((declaration, signal)) => out.public.push(Ar1csPublic {
+                    declaration: declaration.to_string(),
+                    signal: parse_signal(signal)?,
+                }),
+                None => {
+                    return log::error!(ParseError: &format!("malformed ar1cs public value: {line}"))
+                }
+            }
+        }
+        while let Some(line) = lines.next_if(|v| v.starts_with(SYMBOL_PREFIX)) {
+            out.symbols.push(parse_symbol(line)?);
+        }
+        let pairs = R1csPestParser::parse(Rule::program, source)
+            .map_err(|e| log::parse_error(e, &out.entry_fn))?;
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::type_header => {
+                    return log::error!(
+                        ParseError: "ar1cs artifacts cannot declare arguments",
+                        "functions written in ar1cs are read by the compiler"
+                    )
+                }
+                Rule::gate_def | Rule::gate_call => {
+                    return log::error!(
+                        ParseError: "ar1cs artifacts cannot contain gates",
+                        "gates are inlined when a function is compiled"
+                    )
+                }
+                Rule::constraint_line => {
+                    let mut pair = pair.into_inner();
+                    let a = parse_factor(next_pair(&mut pair)?)?;
+                    let b = parse_factor(next_pair(&mut pair)?)?;
+                    let c = parse_factor(next_pair(&mut pair)?)?;
+                    let mut constraint = R1csConstraint::new(a, b, c, "");
+                    constraint.comment = None;
+                    out.constraints.push(constraint);
+                }
+                Rule::symbolic_line => {
+                    let mut pair = pair.into_inner();
+                    let signal = parse_signal(next_pair(&mut pair)?.as_str())?;
+                    let a = parse_factor(next_pair(&mut pair)?)?;
+                    let op = next_pair(&mut pair)?.as_str().parse::<SymbolicOp>()?;
+                    let b = parse_factor(next_pair(&mut pair)?)?;
+                    let mut constraint = R1csConstraint::symbolic(signal, a, b, op, String::new());
+                    constraint.comment = None;
+                    out.constraints.push(constraint);
+                }
+                Rule::comment => {
+                    let text = pair
+                        .into_inner()
+                        .next()
+                        .map(|v| v.as_str().to_string())
+                        .unwrap_or_default();
+                    if let Some(c) = out.constraints.last_mut() {
+                        c.comment = Some(text);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl<T: FieldElement> Display for Ar1cs<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "{VERSION_PREFIX}{}", self.version)?;
+        writeln!(f, "# {}", self.entry_fn)?;
+        writeln!(f, "{FIELD_PREFIX}{}", self.field)?;
+        write!(f, "#")?;
+        for v in &self.public {
+            write!(f, "\n# {}: {}", v.declaration, index_to_string(&v.signal))?;
+        }
+        for v in &self.symbols {
+            let signals = v.signals.iter().map(index_to_string).collect::<Vec<_>>();
+            write!(f, "\n{SYMBOL_PREFIX}{}: {}", v.name, signals.join(", "))?;
+        }
+        for c in &self.constraints {
+            write!(f, "\n{c}")?;
+        }
+        Ok(())
+    }
+}
+
+// the next pair of a rule, the grammar guarantees it exists
+fn next_pair<'a>(pairs: &mut Pairs<'a, Rule>) -> Result<Pair<'a, Rule>> {
+    pairs
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("unexpected end of ar1cs rule"))
+}
+
+// a symbol table line, e.g. `# signal x: x3, x4`
+fn parse_symbol(line: &str) -> Result<Ar1csSymbol> {
+    match line
+        .strip_prefix(SYMBOL_PREFIX)
+        .and_then(|v| v.rsplit_once(": "))
+    {
+        Some((name, signals)) => Ok(Ar1csSymbol {
+            name: name.to_string(),
+            signals: signals
+                .split(", ")
+                .map(parse_signal)
+                .collect::<Result<Vec<_>>>()?,
+        }),
+        None => log::error!(ParseError: &format!("malformed ar1cs symbol: {line}")),
+    }
+}
+
+// a signal name, `one` or `x` followed by the signal index
+fn parse_signal(name: &str) -> Result<usize> {
+    if name == "one" {
+        return Ok(0);
+    }
+    match name.strip_prefix('x').map(|v| v.parse::<usize>()) {
+        Some(Ok(i)) => Ok(i),
+        _ => log::error!(ParseError: &format!("invalid ar1cs signal name: {name}")),
+    }
+}
+
+// a linear combination of (coefficient, signal) terms
+fn parse_factor<T: FieldElement>(pair: Pair<Rule>) -> Result<Vec<(T, usize)>> {
+    let mut pair = pair.into_inner();
+    let mut out = vec![];
+    while let Some(coef) = pair.next() {
+        let signal = next_pair(&mut pair)?;
+        out.push((T::from_str(coef.as_str())?, parse_signal(signal.as_str())?));
+    }
+    Ok(out)
+}
@@ -0,0 +1,320 @@
+//! Constraints over bits, used by the binary hash and signature
+//! gadgets.
+use anyhow::Result;
+use scalarff::BigUint;
+use scalarff::FieldElement;
+
+use super::constraint::R1csConstraint;
+use super::constraint::SymbolicOp;
+use crate::log;
+
+// the field must be large enough to hold the sum of several
+// 32 bit words without overflowing
+const MIN_FIELD_BITS: u64 = 40;
+
+/// A bit as a linear combination of signals. Signal 0 is `one`
+/// so constant bits do not use any signals.
+pub type Bit<F> = Vec<(F, usize)>;
+
+/// Allocates signals and constraints for operations on bits.
+/// Operations on constant bits are evaluated statically.
+pub struct BinaryCircuit<F: FieldElement> {
+    pub var_index: usize,
+    pub constraints: Vec<R1csConstraint<F>>,
+    // attached to symbolic constraints
+    message: String,
+}
+
+impl<F: FieldElement> BinaryCircuit<F> {
+    pub fn new(var_index: usize, message: &str) -> Result<Self> {
+        if F::prime().bits() < MIN_FIELD_BITS {
+            return log::error!(
+                ConfigError: &format!("binary operations are not supported in the {} field", F::name_str()),
+                &format!("binary gadgets require a field of at least {MIN_FIELD_BITS} bits")
+            );
+        }
+        Ok(Self {
+            var_index,
+            constraints: vec![],
+            message: message.to_string(),
+        })
+    }
+
+    pub fn constant(v: bool) -> Bit<F> {
+        if v {
+            vec![(F::one(), 0)]
+        } else {
+            vec![(F::zero(), 0)]
+        }
+    }
+
+    /// The bits of a constant, least significant first.
+    pub fn constant_bits(v: u64, len: usize) -> Vec<Bit<F>> {
+        (0..len)
+            .map(|i| Self::constant((v >> i) & 1 == 1))
+            .collect()
+    }
+
+    // the value of a bit that does not depend on any signals
+    fn constant_value(b: &Bit<F>) -> Option<F> {
+        if b.iter().all(|(_, i)| *i == 0) {
+            Some(b.iter().fold(F::zero(), |acc, (v, _)| acc + v.clone()))
+        } else {
+            None
+        }
+    }
+
+    pub fn not(a: &Bit<F>) -> Bit<F> {
+        let mut out = vec![(F::one(), 0)];
+        for (v, i) in a {
+            add_term(&mut out, F::zero() - v.clone(), *i);
+        }
+        out
+    }
+
+    pub fn and(&mut self, a: &Bit<F>, b: &Bit<F>) -> Bit<F> {
+        for (x, y) in [(a, b), (b, a)] {
+            if let Some(v) = Self::constant_value(x) {
+                return if v == F::zero() {
+                    Self::constant(false)
+                } else {
+                    y.clone()
+                };
+            }
+        }
+        self.product(a.clone(), b.clone(), "bitwise and")
+    }
+
+    pub fn xor(&mut self, a: &Bit<F>, b: &Bit<F>) -> Bit<F> {
+        for (x, y) in [(a, b), (b, a)] {
+            if let Some(v) = Self::constant_value(x) {
+                return if v == F::zero() {
+                    y.clone()
+                } else {
+                    Self::not(y)
+                };
+            }
+        }
+        // for bits a ^ b = (a - b)^2
+        let mut diff = a.clone();
+        for (v, i) in b {
+            add_term(&mut diff, F::zero() - v.clone(), *i);
+        }
+        self.product(diff.clone(), diff, "bitwise xor")
+    }
+
+    pub fn or(&mut self, a: &Bit<F>, b: &Bit<F>) -> Bit<F> {
+        // a | b = a + b - a * b
+        let ab = self.and(a, b);
+        let mut out = a.clone();
+        for (v, i) in b {
+            add_term(&mut out, v.clone(), *i);
+        }
+        for (v, i) in ab {
+            add_term(&mut out, F::zero() - v, i);
+        }
+        out
+    }
+
+    pub fn xor_words(&mut self, words: &[&[Bit<F>]]) -> Vec<Bit<F>> {
+        let mut out = words[0].to_vec();
+        for word in &words[1..] {
+            out = out
+                .iter()
+                .zip(word.iter())
+                .map(|(a, b)| self.xor(a, b))
+                .collect();
+        }
+        out
+    }
+
+    /// The sum of words modulo 2^n, where n is the length of
+    /// each word.
+    pub fn add_words(&mut self, words: &[&[Bit<F>]]) -> Vec<Bit<F>> {
+        let len = words[0].len();
+        let mut sum = vec![];
+        for word in words {
+            let mut coef = F::one();
+            for bit in word.iter() {
+                for (v, i) in bit {
+                    add_term(&mut sum, coef.clone() * v.clone(), *i);
+                }
+                coef = coef.clone() + coef;
+            }
+        }
+        // the sum of k words uses n + log2(k) bits
+        let carry_bits = usize::BITS - (words.len() - 1).leading_zeros();
+        let mut out = self.decompose(sum, len + carry_bits as usize);
+        out.truncate(len);
+        out
+    }
+
+    /// Constrain the little endian bit decomposition of a
+    /// linear combination. This asserts that the value is less
+    /// than 2^len.
+    pub fn decompose(&mut self, lc: Vec<(F, usize)>, len: usize) -> Vec<Bit<F>> {
+        if let Some(v) = Self::constant_value(&lc) {
+            let v = v.to_biguint();
+            return (0..len).map(|i| Self::constant(v.bit(i as u64))).collect();
+        }
+        let start = self.var_index;
+        self.var_index += len;
+        let mut sum = vec![];
+        let mut coef = F::one();
+        for x in 0..len {
+            let bit = start + x;
+            self.constraints.append(&mut vec![
+                R1csConstraint::symbolic(
+                    bit,
+                    vec![(F::from(x as u64), 0)],
+                    lc.clone(),
+                    SymbolicOp::Bit,
+                    self.message.clone(),
+                ),
+                R1csConstraint::new(
+                    vec![(F::one(), bit)],
+                    vec![(F::one(), bit)],
+                    vec![(F::one(), bit)],
+                    &format!("{bit} is a bit"),
+                )
+                .as_assignment(),
+            ]);
+            sum.push((coef.clone(), bit));
+            coef = coef.clone() + coef;
+        }
+        self.constraints.push(R1csConstraint::new(
+            sum,
+            vec![(F::one(), 0)],
+            lc,
+            &format!("bit decomposition into {start}..{}", start + len),
+        ));
+        (start..start + len).map(|i| vec![(F::one(), i)]).collect()
+    }
+
+    /// Constrain a new signal for each byte of little endian bits.
+    /// Returns the index of the first signal.
+    pub fn bytes_to_signals(&mut self, bytes: &[Vec<Bit<F>>]) -> usize {
+        let start = self.var_index;
+        self.var_index += bytes.len();
+        for (x, bits) in bytes.iter().enumerate() {
+            let out = start + x;
+            let mut lc = vec![];
+            let mut coef = F::one();
+            for bit in bits {
+                for (v, i) in bit {
+                    add_term(&mut lc, coef.clone() * v.clone(), *i);
+                }
+                coef = coef.clone() + coef;
+            }
+            self.constraints.append(&mut vec![
+                R1csConstraint::new(
+                    lc.clone(),
+                    vec![(F::one(), 0)],
+                    vec![(F::one(), out)],
+                    &format!("byte into {out}"),
+                )
+                .as_assignment(),
+                R1csConstraint::symbolic(
+                    out,
+                    lc,
+                    vec![(F::one(), 0)],
+                    SymbolicOp::Mul,
+                    self.message.clone(),
+                ),
+            ]);
+        }
+        start
+    }
+
+    /// Constrain that the little endian bits are less than a
+    /// constant, comparing from the most significant bit.
+    pub fn assert_less_than(&mut self, bits: &[Bit<F>], v: &BigUint) {
+        if v.bits() > bits.len() as u64 {
+            return;
+        }
+        // eq is 1 if all bits so far match the constant, lt is 1
+        // if the bits so far are less than the constant
+        let mut eq = Self::constant(true);
+        let mut lt = Self::constant(false);
+        for (i, bit) in bits.iter().enumerate().rev() {
+            if v.bit(i as u64) {
+                let less = self.and(&eq, &Self::not(bit));
+                for (coef, index) in less {
+                    add_term(&mut lt, coef, index);
+                }
+                eq = self.and(&eq, bit);
+            } else {
+                eq = self.and(&eq, &Self::not(bit));
+            }
+        }
+        self.constraints.push(R1csConstraint::new(
+            lt,
+            vec![(F::one(), 0)],
+            vec![(F::one(), 0)],
+            &format!("bits are less than {v}"),
+        ));
+    }
+
+    /// Constrain a new signal equal to the inverse of a linear
+    /// combination. This asserts that the value is not 0.
+    pub fn inverse(&mut self, lc: Vec<(F, usize)>) -> Vec<(F, usize)> {
+        if let Some(v) = Self::constant_value(&lc) {
+            return vec![(F::one() / v, 0)];
+        }
+        let out = self.var_index;
+        self.var_index += 1;
+        self.constraints.append(&mut vec![
+            R1csConstraint::new(
+                lc.clone(),
+                vec![(F::one(), out)],
+                vec![(F::one(), 0)],
+                &format!("inverse into {out}"),
+            ),
+            R1csConstraint::symbolic(
+                out,
+                vec![(F::one(), 0)],
+                lc,
+                SymbolicOp::Inv,
+                self.message.clone(),
+            ),
+        ]);
+        vec![(F::one(), out)]
+    }
+
+    /// Constrain a new signal equal to a * b. If either side is
+    /// constant the product is a linear combination.
+    pub fn product(&mut self, a: Vec<(F, usize)>, b: Vec<(F, usize)>, op: &str) -> Vec<(F, usize)> {
+        for (x, y) in [(&a, &b), (&b, &a)] {
+            if let Some(v) = Self::constant_value(x) {
+                let mut out = vec![(F::zero(), 0)];
+                for (coef, index) in y {
+                    add_term(&mut out, v.clone() * coef.clone(), *index);
+                }
+                return out;
+            }
+        }
+        let out = self.var_index;
+        self.var_index += 1;
+        self.constraints.append(&mut vec![
+            R1csConstraint::new(
+                a.clone(),
+                b.clone(),
+                vec![(F::one(), out)],
+                &format!("{op} into {out}"),
+            )
+            .as_assignment(),
+            R1csConstraint::symbolic(out, a, b, SymbolicOp::Mul, self.message.clone()),
+        ]);
+        vec![(F::one(), out)]
+    }
+}
+
+/// Add a term to a linear combination, combining terms
+/// that refer to the same signal.
+pub fn add_term<F: FieldElement>(lc: &mut Vec<(F, usize)>, coef: F, index: usize) {
+    if let Some(term) = lc.iter_mut().find(|(_, i)| *i == index) {
+        term.0 += coef;
+    } else {
+        lc.push((coef, index));
+    }
+}
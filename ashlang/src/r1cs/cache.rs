@@ -0,0 +1,271 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use ring_math::Matrix;
+use ring_math::PolynomialRingElement;
+use scalarff::FieldElement;
+
+use super::constraint::R1csConstraint;
+use super::parser::R1csParser;
+use super::vm::Var;
+use super::vm::VarLocation;
+use crate::cache::FnCache;
+use crate::log;
+
+/// Maps signals between a call site and the local signal space
+/// of a cached call. Local signal 0 is `one`, followed by the
+/// distinct argument signals in order of appearance, followed
+/// by the signals allocated during the call.
+pub struct CallSignals {
+    args: Vec<usize>,
+    start: usize,
+}
+
+impl CallSignals {
+    pub fn new<T: PolynomialRingElement>(args: &[Var<T>], start: usize) -> Self {
+        let mut signals: Vec<usize> = vec![];
+        for v in args {
+            if let Some(index) = v.index {
+                for i in index..index + v.value.len() {
+                    if !signals.contains(&i) {
+                        signals.push(i);
+                    }
+                }
+            }
+        }
+        Self {
+            args: signals,
+            start,
+        }
+    }
+
+    /// Build a cache key for a call to an ash function. Static
+    /// arguments are keyed by value, signal arguments are keyed by
+    /// shape and by which arguments share signals.
+    pub fn key<T: PolynomialRingElement>(
+        &self,
+        name: &str,
+        fn_hash: &str,
+        args: &[Var<T>],
+    ) -> Result<String> {
+        let mut layout = vec![];
+        for v in args {
+            let dimensions = join(v.value.dimensions.iter());
+            if let Some(index) = v.index {
+                let signals = (index..index + v.value.len())
+                    .map(|i| self.to_local(i).unwrap_or_default())
+                    .collect::<Vec<_>>();
+                layout.push(format!("signal {dimensions} {}", join(signals.iter())));
+            } else {
+                layout.push(format!("static {dimensions} {}", scalars(&v.value)?));
+            }
+        }
+        Ok(FnCache::key(&[
+            "r1cs",
+            env!("CARGO_PKG_VERSION"),
+            T::name_str(),
+            name,
+            fn_hash,
+            &layout.join("\n"),
+        ]))
+    }
+
+    fn to_local(&self, i: usize) -> Option<usize> {
+        if i == 0 {
+            Some(0)
+        } else if let Some(pos) = self.args.iter().position(|v| *v == i) {
+            Some(pos + 1)
+        } else if i >= self.start {
+            Some(i - self.start + self.args.len() + 1)
+        } else {
+            None
+        }
+    }
+
+    fn from_local(&self, i: usize) -> usize {
+        if i == 0 {
+            0
+        } else if i <= self.args.len() {
+            self.args[i - 1]
+        } else {
+            self.start + i - self.args.len() - 1
+        }
+    }
+}
+
+/// The result of evaluating an ash function in the r1cs target.
+pub struct CachedCall<T: PolynomialRingElement> {
+    // number of signals allocated by the call
+    pub signal_count: usize,
+    pub return_val: Option<Var<T>>,
+    // the final binding of each argument name, used to
+    // write back references
+    pub bindings: Vec<Option<Var<T>>>,
+    pub constraints: Vec<R1csConstraint<T::F>>,
+}
+
+impl<T: PolynomialRingElement> CachedCall<T> {
+    /// Encode the call as ar1cs with the return value and
+    /// bindings in header comments. Returns an error if the
+    /// call refers to signals outside of the call.
+    pub fn encode(&self, signals: &CallSignals) -> Result<String> {
+        let to_local = |i: usize| signals.to_local(i);
+        let mut lines = vec![
+            "# ashlang r1cs cache".to_string(),
+            format!("# signals {}", self.signal_count),
+            format!("# return {}", encode_var(&self.return_val, &to_local)?),
+        ];
+        for binding in &self.bindings {
+            lines.push(format!("# binding {}", encode_var(binding, &to_local)?));
+        }
+        for c in &self.constraints {
+            lines.push(map_constraint(c, &to_local)?.to_string());
+        }
+        Ok(lines.join("\n"))
+    }
+
+    pub fn decode(src: &str, signals: &CallSignals) -> Result<Self> {
+        let from_local = |i: usize| Some(signals.from_local(i));
+        let mut signal_count = None;
+        let mut return_val = None;
+        let mut bindings = vec![];
+        for line in src.lines() {
+            if let Some(v) = line.strip_prefix("# signals ") {
+                signal_count = Some(v.parse::<usize>()?);
+            } else if let Some(v) = line.strip_prefix("# return ") {
+                return_val = decode_var(v, &from_local)?;
+            } else if let Some(v) = line.strip_prefix("# binding ") {
+                bindings.push(decode_var(v, &from_local)?);
+            }
+        }
+        let signal_count = match signal_count {
+            Some(v) => v,
+            None => return log::error!("invalid cache entry: missing signal count"),
+        };
+        let parser: R1csParser<T> = R1csParser::new(src)?;
+        Ok(Self {
+            signal_count,
+            return_val,
+            bindings,
+            constraints: parser
+                .constraints
+                .iter()
+                .map(|c| map_constraint(c, &from_local))
+                .collect::<Result<Vec<_>>>()?,
+        })
+    }
+}
+
+fn map_constraint<F: FieldElement>(
+    c: &R1csConstraint<F>,
+    f: &impl Fn(usize) -> Option<usize>,
+) -> Result<R1csConstraint<F>> {
+    let map = |i: usize| match f(i) {
+        Some(v) => Ok(v),
+        None => log::error!(&format!("signal {i} is not part of the call")),
+    };
+    let map_lc = |lc: &Vec<(F, usize)>| {
+        lc.iter()
+            .map(|(coef, i)| map(*i).map(|i| (coef.clone(), i)))
+            .collect::<Result<Vec<_>>>()
+    };
+    Ok(R1csConstraint {
+        a: map_lc(&c.a)?,
+        b: map_lc(&c.b)?,
+        c: map_lc(&c.c)?,
+        out_i: c.out_i.map(map).transpose()?,
+        ..c.clone()
+    })
+}
+
+// encode a variable as `<location> <index> <dimensions> <values>`
+fn encode_var<T: PolynomialRingElement>(
+    v: &Option<Var<T>>,
+    f: &impl Fn(usize) -> Option<usize>,
+) -> Result<String> {
+    let v = match v {
+        Some(v) => v,
+        None => return Ok("none".to_string()),
+    };
+    let index = match v.index {
+        Some(i) => match f(i) {
+            Some(i) => i.to_string(),
+            None => return log::error!(&format!("signal {i} is not part of the call")),
+        },
+        None => "-".to_string(),
+    };
+    let location = match v.location {
+        VarLocation::Static => "static",
+        VarLocation::Constraint => "signal",
+    };
+    Ok(format!(
+        "{location} {index} {} {}",
+        or_dash(join(v.value.dimensions.iter())),
+        or_dash(scalars(&v.value)?)
+    ))
+}
+
+fn decode_var<T: PolynomialRingElement>(
+    src: &str,
+    f: &impl Fn(usize) -> Option<usize>,
+) -> Result<Option<Var<T>>> {
+    if src == "none" {
+        return Ok(None);
+    }
+    let parts = src.split(' ').collect::<Vec<_>>();
+    if parts.len() != 4 {
+        return log::error!(&format!("invalid cache entry variable: {src}"));
+    }
+    let location = match parts[0] {
+        "static" => VarLocation::Static,
+        "signal" => VarLocation::Constraint,
+        _ => return log::error!(&format!("invalid cache entry variable: {src}")),
+    };
+    let index = match parts[1] {
+        "-" => None,
+        i => f(i.parse::<usize>()?),
+    };
+    let split = |v: &str| {
+        if v == "-" {
+            vec![]
+        } else {
+            v.split(',').map(|v| v.to_string()).collect::<Vec<_>>()
+        }
+    };
+    Ok(Some(Var {
+        index,
+        location,
+        value: Matrix {
+            dimensions: split(parts[2])
+                .iter()
+                .map(|v| v.parse::<usize>().map_err(anyhow::Error::from))
+                .collect::<Result<Vec<_>>>()?,
+            values: split(parts[3])
+                .iter()
+                .map(|v| T::from_str(v))
+                .collect::<Result<Vec<_>>>()?,
+        },
+    }))
+}
+
+fn scalars<T: PolynomialRingElement>(m: &Matrix<T>) -> Result<String> {
+    Ok(join(
+        m.values
+            .iter()
+            .map(|v| v.to_scalar())
+            .collect::<Result<Vec<_>>>()?
+            .iter(),
+    ))
+}
+
+fn join<V: ToString>(values: impl Iterator<Item = V>) -> String {
+    values.map(|v| v.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn or_dash(v: String) -> String {
+    if v.is_empty() {
+        "-".to_string()
+    } else {
+        v
+    }
+}
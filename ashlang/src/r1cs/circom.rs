@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use scalarff::BigUint;
+use scalarff::FieldElement;
+
+use crate::log;
+use crate::r1cs::constraint::R1csConstraint;
+use crate::r1cs::constraint::SymbolicOp;
+use crate::r1cs::witness::Witness;
+
+/// Assignment of ashlang signals to iden3 wires.
+///
+/// Wire 0 is the constant `one`, followed by public outputs,
+/// public inputs, private inputs, and then every other signal.
+/// Wire labels are the original signal indices.
+pub struct WireMap {
+    pub wires: Vec<usize>,
+    pub signal_to_wire: HashMap<usize, usize>,
+    pub n_pub_out: usize,
+    pub n_pub_in: usize,
+    pub n_prv_in: usize,
+}
+
+impl WireMap {
+    pub fn new<T: FieldElement>(constraints: &[R1csConstraint<T>]) -> Self {
+        let mut out = Self {
+            wires: vec![],
+            signal_to_wire: HashMap::new(),
+            n_pub_out: 0,
+            n_pub_in: 0,
+            n_prv_in: 0,
+        };
+        out.push(0);
+        for op in [
+            SymbolicOp::Output,
+            SymbolicOp::PublicInput,
+            SymbolicOp::Input,
+        ] {
+            let start = out.wires.len();
+            for c in constraints {
+                if c.symbolic_op.as_ref() == Some(&op) {
+                    out.push(c.out_i.unwrap());
+                }
+            }
+            let count = out.wires.len() - start;
+            match op {
+                SymbolicOp::Output => out.n_pub_out = count,
+                SymbolicOp::PublicInput => out.n_pub_in = count,
+                _ => out.n_prv_in = count,
+            }
+        }
+        let mut rest = vec![];
+        for c in constraints {
+            rest.extend(
+                c.a.iter()
+                    .chain(c.b.iter())
+                    .chain(c.c.iter())
+                    .map(|(_, i)| *i),
+            );
+            rest.extend(c.out_i);
+        }
+        rest.sort();
+        for i in rest {
+            out.push(i);
+        }
+        out
+    }
+
+    // each signal is assigned a single wire, the first
+    // time it is seen
+    fn push(&mut self, signal: usize) {
+        if !self.signal_to_wire.contains_key(&signal) {
+            self.signal_to_wire.insert(signal, self.wires.len());
+            self.wires.push(signal);
+        }
+    }
+}
+
+/// Serialize constraints in the iden3 `.r1cs` binary format
+/// used by circom and snarkjs.
+///
+/// Symbolic constraints are not included. The witness
+/// for the file can be written using `write_wtns`.
+pub fn write_r1cs<T: FieldElement>(constraints: &[R1csConstraint<T>]) -> Result<Vec<u8>> {
+    let wires = WireMap::new(constraints);
+    let n8 = field_size::<T>();
+    let constraints = constraints
+        .iter()
+        .filter(|c| !c.symbolic)
+        .collect::<Vec<_>>();
+
+    let mut header = vec![];
+    header.extend((n8 as u32).to_le_bytes());
+    header.extend(biguint_bytes(&T::prime(), n8)?);
+    header.extend(u32_bytes(wires.wires.len())?);
+    header.extend(u32_bytes(wires.n_pub_out)?);
+    header.extend(u32_bytes(wires.n_pub_in)?);
+    header.extend(u32_bytes(wires.n_prv_in)?);
+    header.extend((wires.wires.len() as u64).to_le_bytes());
+    header.extend(u32_bytes(constraints.len())?);
+
+    let mut body = vec![];
+    for c in constraints {
+        for lc in [&c.a, &c.b, &c.c] {
+            // iden3 requires each wire at most once per factor
+            let mut terms: Vec<(usize, T)> = vec![];
+            for (coef, i) in lc {
+                let wire = wires.signal_to_wire[i];
+                if let Some(t) = terms.iter_mut().find(|(w, _)| *w == wire) {
+                    t.1 += coef.clone();
+                } else {
+                    terms.push((wire, coef.clone()));
+                }
+            }
+            terms.retain(|(_, coef)| *coef != T::zero());
+            terms.sort_by_key(|(w, _)| *w);
+            body.extend(u32_bytes(terms.len())?);
+            for (wire, coef) in terms {
+                body.extend(u32_bytes(wire)?);
+                body.extend(biguint_bytes(&coef.to_biguint(), n8)?);
+            }
+        }
+    }
+
+    let mut labels = vec![];
+    for signal in &wires.wires {
+        labels.extend((*signal as u64).to_le_bytes());
+    }
+
+    Ok(binary_file(b"r1cs", 1, vec![header, body, labels]))
+}
+
+/// Serialize a witness in the iden3 `.wtns` binary format.
+/// Values are written in the wire order used by `write_r1cs`.
+pub fn write_wtns<T: FieldElement>(
+    constraints: &[R1csConstraint<T>],
+    witness: &Witness<T>,
+) -> Result<Vec<u8>> {
+    let wires = WireMap::new(constraints);
+    let n8 = field_size::<T>();
+
+    let mut header = vec![];
+    header.extend((n8 as u32).to_le_bytes());
+    header.extend(biguint_bytes(&T::prime(), n8)?);
+    header.extend(u32_bytes(wires.wires.len())?);
+
+    let mut values = vec![];
+    for signal in &wires.wires {
+        if let Some(v) = witness.variables.get(*signal) {
+            values.extend(biguint_bytes(&v.to_biguint(), n8)?);
+        } else {
+            return log::error!(InputError: &format!(
+                "witness does not contain a value for signal {signal}"
+            ));
+        }
+    }
+
+    Ok(binary_file(b"wtns", 2, vec![header, values]))
+}
+
+/// Number of bytes used to store a field element. iden3
+/// formats use a multiple of 8 bytes.
+fn field_size<T: FieldElement>() -> usize {
+    let bits = T::prime().bits() as usize;
+    bits.div_ceil(64) * 8
+}
+
+fn biguint_bytes(v: &BigUint, n8: usize) -> Result<Vec<u8>> {
+    let mut out = v.to_bytes_le();
+    if out.len() > n8 {
+        return log::error!(&format!("value does not fit in {n8} bytes: {v}"));
+    }
+    out.resize(n8, 0);
+    Ok(out)
+}
+
+fn u32_bytes(v: usize) -> Result<[u8; 4]> {
+    if let Ok(v) = u32::try_from(v) {
+        Ok(v.to_le_bytes())
+    } else {
+        log::error!(&format!("value is too large for the iden3 format: {v}"))
+    }
+}
+
+/// iden3 binary files are a magic string, a version, and a
+/// list of sections. Sections are numbered from 1 and prefixed
+/// with their type and length.
+fn binary_file(magic: &[u8; 4], version: u32, sections: Vec<Vec<u8>>) -> Vec<u8> {
+    let mut out = vec![];
+    out.extend(magic);
+    out.extend(version.to_le_bytes());
+    out.extend((sections.len() as u32).to_le_bytes());
+    for (i, section) in sections.into_iter().enumerate() {
+        out.extend((i as u32 + 1).to_le_bytes());
+        out.extend((section.len() as u64).to_le_bytes());
+        out.extend(section);
+    }
+    out
+}
+
+/// A constraint system read from an iden3 `.r1cs` file.
+///
+/// Constraints reference iden3 wire indices. Symbolic
+/// constraints to compute the witness are derived by
+/// `witness_constraints`.
+pub struct CircomCircuit<T: FieldElement> {
+    pub n_wires: usize,
+    pub n_pub_out: usize,
+    pub n_pub_in: usize,
+    pub n_prv_in: usize,
+    pub constraints: Vec<R1csConstraint<T>>,
+}
+
+/// Parse an iden3 `.r1cs` file. The file must be compiled
+/// for the same field as `T`.
+pub fn read_r1cs<T: FieldElement>(bytes: &[u8]) -> Result<CircomCircuit<T>> {
+    let mut reader = Reader { bytes, offset: 0 };
+    if reader.take(4)? != b"r1cs" {
+        return log::error!(ParseError: "invalid circom r1cs file: bad magic string");
+    }
+    let version = reader.u32()?;
+    if version != 1 {
+        return log::error!(ParseError: &format!("unsupported circom r1cs version: {version}"));
+    }
+    let mut sections: HashMap<u32, &[u8]> = HashMap::new();
+    for _ in 0..reader.u32()? {
+        let section_type = reader.u32()?;
+        let size = reader.u64()?;
+        sections.insert(section_type, reader.take(size)?);
+    }
+    let (header, body) = match (sections.get(&1), sections.get(&2)) {
+        (Some(header), Some(body)) => (*header, *body),
+        _ => {
+            return log::error!(ParseError: "invalid circom r1cs file: missing header or constraint section")
+        }
+    };
+
+    let mut reader = Reader {
+        bytes: header,
+        offset: 0,
+    };
+    let n8 = reader.u32()? as usize;
+    let prime = BigUint::from_bytes_le(reader.take(n8 as u64)?);
+    if prime != T::prime() {
+        return log::error!(
+            ParseError: &format!("circom r1cs file is compiled for a different field: {prime}"),
+            &format!("expected the {} field: {}", T::name_str(), T::prime())
+        );
+    }
+    let n_wires = reader.u32()? as usize;
+    let n_pub_out = reader.u32()? as usize;
+    let n_pub_in = reader.u32()? as usize;
+    let n_prv_in = reader.u32()? as usize;
+    let _n_labels = reader.u64()?;
+    let n_constraints = reader.u32()?;
+
+    let mut reader = Reader {
+        bytes: body,
+        offset: 0,
+    };
+    let mut constraints = vec![];
+    for _ in 0..n_constraints {
+        let mut factors = vec![];
+        for _ in 0..3 {
+            let mut lc = vec![];
+            for _ in 0..reader.u32()? {
+                let wire = reader.u32()? as usize;
+                if wire >= n_wires {
+                    return log::error!(ParseError: &format!(
+                        "invalid circom r1cs file: wire {wire} is out of range"
+                    ));
+                }
+                let coef = T::from_biguint(&BigUint::from_bytes_le(reader.take(n8 as u64)?));
+                lc.push((coef, wire));
+            }
+            if lc.is_empty() {
+                // ar1cs requires at least one term in each factor
+                lc.push((T::zero(), 0));
+            }
+            factors.push(lc);
+        }
+        let c = factors.pop().unwrap();
+        let b = factors.pop().unwrap();
+        let a = factors.pop().unwrap();
+        constraints.push(R1csConstraint::new(a, b, c, ""));
+    }
+    Ok(CircomCircuit {
+        n_wires,
+        n_pub_out,
+        n_pub_in,
+        n_prv_in,
+        constraints,
+    })
+}
+
+impl<T: FieldElement> CircomCircuit<T> {
+    /// Derive symbolic constraints that calculate every wire
+    /// from the inputs, followed by the constraints of the circuit.
+    ///
+    /// A wire can be calculated if it is the only unknown wire in a
+    /// constraint and appears only in the `c` factor, e.g. circom
+    /// signals assigned using `<==`. Each calculation uses an extra
+    /// signal numbered after the last wire to hold the product.
+    /// Signals assigned using `<--` generally cannot be calculated.
+    pub fn witness_constraints(&self) -> Result<Vec<R1csConstraint<T>>> {
+        // wire 0 and the inputs are known before calculation
+        let n_in = self.n_pub_in + self.n_prv_in;
+        let mut known = (0..self.n_wires)
+            .map(|i| i == 0 || (i > self.n_pub_out && i <= self.n_pub_out + n_in))
+            .collect::<Vec<_>>();
+        let mut next_signal = self.n_wires;
+        let mut pending = self.constraints.iter().collect::<Vec<_>>();
+        let mut out = vec![];
+        loop {
+            let mut changed = false;
+            pending.retain(|c| {
+                if c.a.iter().chain(c.b.iter()).any(|(_, i)| !known[*i]) {
+                    return true;
+                }
+                let mut unknown =
+                    c.c.iter()
+                        .filter(|(_, i)| !known[*i])
+                        .map(|(_, i)| *i)
+                        .collect::<Vec<_>>();
+                unknown.sort();
+                unknown.dedup();
+                if unknown.len() > 1 {
+                    return true;
+                }
+                if let Some(x) = unknown.first() {
+                    let mut k = T::zero();
+                    for (coef, i) in &c.c {
+                        if i == x {
+                            k += coef.clone();
+                        }
+                    }
+                    if k == T::zero() {
+                        return true;
+                    }
+                    // x = (a * b - rest) / k
+                    let product = next_signal;
+                    next_signal += 1;
+                    let inv = T::one() / k;
+                    let mut def = vec![(inv.clone(), product)];
+                    for (coef, i) in &c.c {
+                        if i != x {
+                            def.push(((T::zero() - coef.clone()) * inv.clone(), *i));
+                        }
+                    }
+                    out.push(R1csConstraint::symbolic(
+                        product,
+                        c.a.clone(),
+                        c.b.clone(),
+                        SymbolicOp::Mul,
+                        "circom product".to_string(),
+                    ));
+                    out.push(R1csConstraint::symbolic(
+                        *x,
+                        def,
+                        vec![(T::zero(), 0)],
+                        SymbolicOp::Add,
+                        format!("circom wire {x}"),
+                    ));
+                    known[*x] = true;
+                    changed = true;
+                }
+                false
+            });
+            if !changed {
+                break;
+            }
+        }
+        if let Some(c) = pending.first() {
+            let (_, wire) =
+                c.a.iter()
+                    .chain(c.b.iter())
+                    .chain(c.c.iter())
+                    .find(|(_, i)| !known[*i])
+                    .unwrap();
+            return log::error!(
+                &format!("unable to calculate circom wire {wire}"),
+                "circom components may only use signals that are assigned using the <== operator"
+            );
+        }
+        out.extend(self.constraints.iter().cloned());
+        Ok(out)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: u64) -> Result<&'a [u8]> {
+        let end = self.offset.saturating_add(len as usize);
+        if end > self.bytes.len() {
+            return log::error!(ParseError: "invalid circom r1cs file: unexpected end of file");
+        }
+        let out = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(out)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+}
@@ -1,9 +1,16 @@
 use std::collections::HashMap;
 use std::fmt::Display;
+use std::str::FromStr;
 
 use anyhow::Result;
 use scalarff::FieldElement;
 
+use crate::log;
+
+/// The comment of a constraint created by an `assert` statement
+/// begins with this prefix, followed by the failure message.
+pub const ASSERT_COMMENT: &str = "assert: ";
+
 /// A single r1cs constraints.
 ///
 /// a b and c represent values in
@@ -13,6 +20,7 @@ use scalarff::FieldElement;
 /// indices may be specified multiple times
 /// and will be summed
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct R1csConstraint<T: FieldElement> {
     // (coefficient, var_index)
     pub a: Vec<(T, usize)>,
@@ -22,6 +30,11 @@ pub struct R1csConstraint<T: FieldElement> {
     pub comment: Option<String>,
     pub symbolic: bool,
     pub symbolic_op: Option<SymbolicOp>,
+    /// The constraint defines a signal and is satisfied by the
+    /// witness whatever the inputs are, e.g. `a * b = out` for
+    /// a product. Other constraints are checks.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub assignment: bool,
 }
 
 /// A mathematical operation that will be used during witness
@@ -31,8 +44,12 @@ pub struct R1csConstraint<T: FieldElement> {
 /// are not bound by ability to be expressed as r1cs
 /// constraints.
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SymbolicOp {
     Inv,
+    InvOrZero, // like Inv, but 0 is mapped to 0 instead of failing
+    Bit,       // extract bit `a` of `b`
+    Eq,        // 1 if a == b, otherwise 0
     Mul,
     Add,
     Sqrt,
@@ -41,18 +58,23 @@ pub enum SymbolicOp {
     Output,
 }
 
-impl From<&str> for SymbolicOp {
-    fn from(input: &str) -> Self {
-        match input {
+impl FromStr for SymbolicOp {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        Ok(match input {
             "/" => SymbolicOp::Inv,
+            "inv_or_zero" => SymbolicOp::InvOrZero,
+            "bit" => SymbolicOp::Bit,
+            "eq" => SymbolicOp::Eq,
             "*" => SymbolicOp::Mul,
             "+" => SymbolicOp::Add,
             "radix" => SymbolicOp::Sqrt,
             "input" => SymbolicOp::Input,
             "public_input" => SymbolicOp::PublicInput,
             "output" => SymbolicOp::Output,
-            _ => panic!("bad symbolic_op input \"{input}\""),
-        }
+            _ => return log::error!(ParseError: &format!("unknown symbolic operation \"{input}\"")),
+        })
     }
 }
 
@@ -60,6 +82,9 @@ impl Display for SymbolicOp {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let out = match self {
             SymbolicOp::Inv => "/".to_owned(),
+            SymbolicOp::InvOrZero => "inv_or_zero".to_owned(),
+            SymbolicOp::Bit => "bit".to_owned(),
+            SymbolicOp::Eq => "eq".to_owned(),
             SymbolicOp::Mul => "*".to_owned(),
             SymbolicOp::Add => "+".to_owned(),
             SymbolicOp::Sqrt => "radix".to_owned(),
@@ -175,9 +200,19 @@ impl<T: FieldElement> R1csConstraint<T> {
             comment: Some(comment.to_string()),
             symbolic: false,
             symbolic_op: None,
+            assignment: false,
         }
     }
 
+    /// mark the constraint as defining a signal
+    /// the witness calculated from the symbolic constraints
+    /// always satisfies it, so it is kept as is in a branch
+    /// with a signal condition
+    pub fn as_assignment(mut self) -> Self {
+        self.assignment = true;
+        self
+    }
+
     /// build a symbolic constraint used to solve the witness
     /// symbolic constraints are of the form `out_i = a (op) b`
     /// where operation may be any possible operation
@@ -203,6 +238,7 @@ impl<T: FieldElement> R1csConstraint<T> {
             comment: Some(comment),
             symbolic: true,
             symbolic_op: Some(op),
+            assignment: false,
         }
     }
 
@@ -222,6 +258,28 @@ impl<T: FieldElement> R1csConstraint<T> {
             SymbolicOp::Add => Ok(a + b),
             SymbolicOp::Mul => Ok(a * b),
             SymbolicOp::Inv => Ok(T::one() / b),
+            SymbolicOp::InvOrZero => {
+                if b == T::zero() {
+                    Ok(T::zero())
+                } else {
+                    Ok(T::one() / b)
+                }
+            }
+            SymbolicOp::Bit => {
+                let bit_index = a.to_biguint().to_string().parse::<u64>()?;
+                if b.to_biguint().bit(bit_index) {
+                    Ok(T::one())
+                } else {
+                    Ok(T::zero())
+                }
+            }
+            SymbolicOp::Eq => {
+                if a == b {
+                    Ok(T::one())
+                } else {
+                    Ok(T::zero())
+                }
+            }
             SymbolicOp::Sqrt => {
                 if a != (T::one() + T::one()) {
                     anyhow::bail!("Cannot calculate non-square root");
@@ -233,22 +291,22 @@ impl<T: FieldElement> R1csConstraint<T> {
                     // always return the positive value
                     Ok(b.sqrt())
                 } else {
-                    return crate::log::error!(&format!(
+                    return crate::log::error!(InputError: &format!(
                         "cannot take square root of non-residue element: {}",
                         b.to_string()
                     ));
                 }
             }
             SymbolicOp::PublicInput => crate::log::error!(
-                "cannot solve symbolic variable of type \"PublicInput\"",
+                InputError: "cannot solve symbolic variable of type \"PublicInput\"",
                 "witness build should prove public input values"
             ),
             SymbolicOp::Input => crate::log::error!(
-                "cannot solve symbolic variable of type \"Input\"",
+                InputError: "cannot solve symbolic variable of type \"Input\"",
                 "witness builder should provide input values"
             ),
             SymbolicOp::Output => crate::log::error!(
-                "cannot solve symbolic variable of type \"Output\"",
+                InputError: "cannot solve symbolic variable of type \"Output\"",
                 "witness builder should mark output values"
             ),
         }
@@ -0,0 +1,256 @@
+//! EdDSA signature verification over a twisted Edwards curve
+//! defined in the proving field.
+//!
+//! Signatures are verified as in circomlib's EdDSAPoseidonVerifier,
+//! `S * B = R + 8 * h * A` where `h = poseidon(Rx, Ry, Ax, Ay, msg)`.
+//! Baby Jubjub is used in the alt_bn128 field and the ed25519 curve
+//! is used in the curve25519 field.
+use std::str::FromStr;
+
+use anyhow::Result;
+use scalarff::BigUint;
+use scalarff::FieldElement;
+
+use super::binary::add_term;
+use super::binary::BinaryCircuit;
+use super::constraint::R1csConstraint;
+use crate::log;
+
+/// A point as a linear combination for each coordinate.
+pub type Point<F> = (Vec<(F, usize)>, Vec<(F, usize)>);
+
+/// A curve `a * x^2 + y^2 = 1 + d * x^2 * y^2` with a generator
+/// of the prime order subgroup.
+pub struct EdwardsCurve<F: FieldElement> {
+    pub a: F,
+    pub d: F,
+    pub base: (F, F),
+    // order of the subgroup generated by `base`
+    pub order: BigUint,
+}
+
+impl<F: FieldElement> EdwardsCurve<F> {
+    /// The curve used for signatures in the field.
+    pub fn new() -> Result<Self> {
+        // field prime, a, d, base x, base y, subgroup order
+        let (a, d, x, y, order) = match F::prime().to_string().as_str() {
+            "21888242871839275222246405745257275088548364400416034343698204186575808495617" => (
+                "168700",
+                "168696",
+                "5299619240641551281634865583518297030282874472190772894086521144482721001553",
+                "16950150798460657717958625567821834550301663161624707787222815936182638968203",
+                "2736030358979909402780800718157159386076813972158567259200215660948447373041",
+            ),
+            "57896044618658097711785492504343953926634992332820282019728792003956564819949" => (
+                "57896044618658097711785492504343953926634992332820282019728792003956564819948",
+                "37095705934669439343138083508754565189542113879843219016388785533085940283555",
+                "15112221349535400772501151409588531511454012693041857206046113283949847762202",
+                "46316835694926478169428394003475163141307993866256225615783033603165251855960",
+                "7237005577332262213973186563042994240857116359379907606001950938285454250989",
+            ),
+            _ => {
+                return log::error!(
+                    ConfigError: &format!("signatures are not supported in the {} field", F::name_str()),
+                    "eddsa_verify is supported in the alt_bn128 and curve25519 fields"
+                )
+            }
+        };
+        Ok(Self {
+            a: F::from_str(a)?,
+            d: F::from_str(d)?,
+            base: (F::from_str(x)?, F::from_str(y)?),
+            order: order.parse::<BigUint>()?,
+        })
+    }
+
+    /// Add two constant points.
+    pub fn add_static(&self, p: &(F, F), q: &(F, F)) -> (F, F) {
+        let (x1, y1) = p.clone();
+        let (x2, y2) = q.clone();
+        let tau = self.d.clone() * x1.clone() * x2.clone() * y1.clone() * y2.clone();
+        (
+            (x1.clone() * y2.clone() + y1.clone() * x2.clone()) / (F::one() + tau.clone()),
+            (y1 * y2 - self.a.clone() * x1 * x2) / (F::one() - tau),
+        )
+    }
+
+    /// Multiply a constant point by a scalar.
+    pub fn mul_static(&self, p: &(F, F), s: &BigUint) -> (F, F) {
+        let mut acc = (F::zero(), F::one());
+        for i in (0..s.bits()).rev() {
+            acc = self.add_static(&acc, &acc);
+            if s.bit(i) {
+                acc = self.add_static(&acc, p);
+            }
+        }
+        acc
+    }
+
+    /// Constrain the sum of two points. The addition law is
+    /// complete so `p` and `q` may be equal or the identity.
+    pub fn add(&self, circuit: &mut BinaryCircuit<F>, p: &Point<F>, q: &Point<F>) -> Point<F> {
+        let (x1, y1) = p;
+        let (x2, y2) = q;
+        let beta = circuit.product(x1.clone(), y2.clone(), "curve add beta");
+        let gamma = circuit.product(y1.clone(), x2.clone(), "curve add gamma");
+        let delta = circuit.product(
+            sum(&[(F::zero() - self.a.clone(), x1), (F::one(), y1)]),
+            sum(&[(F::one(), x2), (F::one(), y2)]),
+            "curve add delta",
+        );
+        let tau = circuit.product(beta.clone(), gamma.clone(), "curve add tau");
+        let one = vec![(F::one(), 0)];
+        let x_inv = circuit.inverse(sum(&[(F::one(), &one), (self.d.clone(), &tau)]));
+        let y_inv = circuit.inverse(sum(&[(F::one(), &one), (F::zero() - self.d.clone(), &tau)]));
+        let x = circuit.product(
+            sum(&[(F::one(), &beta), (F::one(), &gamma)]),
+            x_inv,
+            "curve add x",
+        );
+        let y = circuit.product(
+            sum(&[
+                (F::one(), &delta),
+                (self.a.clone(), &beta),
+                (F::zero() - F::one(), &gamma),
+            ]),
+            y_inv,
+            "curve add y",
+        );
+        (x, y)
+    }
+
+    /// Constrain a point multiplied by a scalar of little endian
+    /// bits using double and add.
+    pub fn mul(
+        &self,
+        circuit: &mut BinaryCircuit<F>,
+        p: &Point<F>,
+        bits: &[Vec<(F, usize)>],
+    ) -> Point<F> {
+        let mut acc = identity();
+        for bit in bits.iter().rev() {
+            acc = self.add(circuit, &acc, &acc);
+            let sum = self.add(circuit, &acc, p);
+            acc = (
+                select(circuit, bit, &sum.0, &acc.0),
+                select(circuit, bit, &sum.1, &acc.1),
+            );
+        }
+        acc
+    }
+
+    /// Constrain the generator multiplied by a scalar of little
+    /// endian bits. Multiples of the generator are computed
+    /// statically so each bit costs a single addition.
+    pub fn mul_base(&self, circuit: &mut BinaryCircuit<F>, bits: &[Vec<(F, usize)>]) -> Point<F> {
+        self.mul_const(circuit, &self.base, bits)
+    }
+
+    /// Constrain a constant point multiplied by a scalar of
+    /// little endian bits, see `mul_base`.
+    pub fn mul_const(
+        &self,
+        circuit: &mut BinaryCircuit<F>,
+        p: &(F, F),
+        bits: &[Vec<(F, usize)>],
+    ) -> Point<F> {
+        let mut acc = identity();
+        let mut multiple = p.clone();
+        for bit in bits {
+            // the multiple if the bit is 1, otherwise the identity
+            let x = sum(&[(multiple.0.clone(), bit)]);
+            let y = sum(&[
+                (F::one(), &[(F::one(), 0)]),
+                (multiple.1.clone() - F::one(), bit),
+            ]);
+            acc = self.add(circuit, &acc, &(x, y));
+            multiple = self.add_static(&multiple, &multiple);
+        }
+        acc
+    }
+
+    /// Constrain a point to be on the curve.
+    pub fn assert_on_curve(&self, circuit: &mut BinaryCircuit<F>, p: &Point<F>) {
+        let x2 = circuit.product(p.0.clone(), p.0.clone(), "curve point x^2");
+        let y2 = circuit.product(p.1.clone(), p.1.clone(), "curve point y^2");
+        let x2y2 = circuit.product(x2.clone(), y2.clone(), "curve point x^2 * y^2");
+        // a * x^2 + y^2 - d * x^2 * y^2 = 1
+        circuit.constraints.push(R1csConstraint::new(
+            sum(&[
+                (self.a.clone(), &x2),
+                (F::one(), &y2),
+                (F::zero() - self.d.clone(), &x2y2),
+            ]),
+            vec![(F::one(), 0)],
+            vec![(F::one(), 0)],
+            "point is on the curve",
+        ));
+    }
+
+    /// Constrain a signature `(r, s)` on a message by the public
+    /// key `a`, where `h` is the poseidon hash of the signature
+    /// point, the public key, and the message.
+    pub fn verify(
+        &self,
+        circuit: &mut BinaryCircuit<F>,
+        a: &Point<F>,
+        r: &Point<F>,
+        s: usize,
+        h: usize,
+    ) {
+        self.assert_on_curve(circuit, a);
+        self.assert_on_curve(circuit, r);
+        // s must be reduced so signatures are not malleable
+        let s_bits = circuit.decompose(vec![(F::one(), s)], self.order.bits() as usize);
+        circuit.assert_less_than(&s_bits, &self.order);
+        // h is any field element, the bits must not overflow the field
+        let h_bits = circuit.decompose(vec![(F::one(), h)], F::prime().bits() as usize);
+        circuit.assert_less_than(&h_bits, &F::prime());
+
+        // clear the cofactor, and reject public keys of small order
+        let mut a8 = a.clone();
+        for _ in 0..3 {
+            a8 = self.add(circuit, &a8, &a8);
+        }
+        circuit.inverse(a8.0.clone());
+
+        let left = self.mul_base(circuit, &s_bits);
+        let ha = self.mul(circuit, &a8, &h_bits);
+        let right = self.add(circuit, r, &ha);
+        for (l, r) in [(left.0, right.0), (left.1, right.1)] {
+            circuit.constraints.push(R1csConstraint::new(
+                l,
+                vec![(F::one(), 0)],
+                r,
+                "signature is valid",
+            ));
+        }
+    }
+}
+
+fn identity<F: FieldElement>() -> Point<F> {
+    (vec![(F::zero(), 0)], vec![(F::one(), 0)])
+}
+
+// constrain b + bit * (a - b)
+fn select<F: FieldElement>(
+    circuit: &mut BinaryCircuit<F>,
+    bit: &[(F, usize)],
+    a: &[(F, usize)],
+    b: &[(F, usize)],
+) -> Vec<(F, usize)> {
+    let diff = sum(&[(F::one(), a), (F::zero() - F::one(), b)]);
+    let t = circuit.product(bit.to_vec(), diff, "curve select");
+    sum(&[(F::one(), b), (F::one(), &t)])
+}
+
+// a sum of scaled linear combinations
+fn sum<F: FieldElement>(terms: &[(F, &[(F, usize)])]) -> Vec<(F, usize)> {
+    let mut out = vec![(F::zero(), 0)];
+    for (coef, lc) in terms {
+        for (v, i) in lc.iter() {
+            add_term(&mut out, coef.clone() * v.clone(), *i);
+        }
+    }
+    out
+}
@@ -0,0 +1,430 @@
+use std::io::Write;
+
+use anyhow::Result;
+use scalarff::BigUint;
+use scalarff::FieldElement;
+
+use crate::log;
+use crate::r1cs::ar1cs::Ar1cs;
+use crate::r1cs::ar1cs::Ar1csPublic;
+use crate::r1cs::ar1cs::Ar1csSymbol;
+use crate::r1cs::ar1cs::AR1CS_VERSION;
+use crate::r1cs::constraint::R1csConstraint;
+use crate::r1cs::constraint::SymbolicOp;
+use crate::r1cs::witness::Witness;
+
+// binary ar1cs and witness files begin with a magic string
+// followed by the ar1cs version
+static AR1CS_MAGIC: &[u8; 4] = b"ar1b";
+static WITNESS_MAGIC: &[u8; 4] = b"ashw";
+static WITNESS_STREAM_MAGIC: &[u8; 4] = b"ashs";
+
+// number of values written in each segment of a streamed witness
+const SEGMENT_LEN: usize = 4096;
+
+/// Encode a compiled program in a compact binary format.
+///
+/// Integers are written as LEB128 varints and field elements as
+/// a length followed by their little endian bytes, so the small
+/// coefficients and signal indices that make up most constraints
+/// use one or two bytes each. The field prime is included and is
+/// checked when decoding.
+pub fn encode_ar1cs<T: FieldElement>(ar1cs: &Ar1cs<T>) -> Vec<u8> {
+    let mut w = Writer::new::<T>(AR1CS_MAGIC, ar1cs.version);
+    w.string(&ar1cs.entry_fn);
+    w.string(&ar1cs.field);
+    w.varint(ar1cs.public.len() as u64);
+    for v in &ar1cs.public {
+        w.string(&v.declaration);
+        w.varint(v.signal as u64);
+    }
+    w.varint(ar1cs.constraints.len() as u64);
+    for c in &ar1cs.constraints {
+        match (&c.symbolic_op, c.out_i) {
+            (Some(op), Some(out)) if c.symbolic => {
+                w.bytes.push(op_code(op));
+                w.varint(out as u64);
+                w.lc(&c.a);
+                w.lc(&c.b);
+            }
+            _ => {
+                w.bytes.push(0);
+                w.lc(&c.a);
+                w.lc(&c.b);
+                w.lc(&c.c);
+            }
+        }
+        match &c.comment {
+            Some(comment) => {
+                w.bytes.push(1);
+                w.string(comment);
+            }
+            None => w.bytes.push(0),
+        }
+    }
+    // the symbol table is optional and written last, so programs
+    // without one are unchanged
+    if !ar1cs.symbols.is_empty() {
+        w.varint(ar1cs.symbols.len() as u64);
+        for v in &ar1cs.symbols {
+            w.string(&v.name);
+            w.varint(v.signals.len() as u64);
+            for i in &v.signals {
+                w.varint(*i as u64);
+            }
+        }
+    }
+    w.bytes
+}
+
+/// Decode a program written by `encode_ar1cs`. The program
+/// must be encoded for the same field as `T`.
+pub fn decode_ar1cs<T: FieldElement>(bytes: &[u8]) -> Result<Ar1cs<T>> {
+    let (mut r, version) = Reader::new::<T>(bytes, AR1CS_MAGIC)?;
+    let entry_fn = r.string()?;
+    let field = r.string()?;
+    let mut out = Ar1cs::new(&entry_fn, &field);
+    out.version = version;
+    for _ in 0..r.varint()? {
+        let declaration = r.string()?;
+        let signal = r.varint()? as usize;
+        out.public.push(Ar1csPublic {
+            declaration,
+            signal,
+        });
+    }
+    for _ in 0..r.varint()? {
+        let mut c = match r.take(1)?[0] {
+            0 => {
+                let a = r.lc::<T>()?;
+                let b = r.lc::<T>()?;
+                let c = r.lc::<T>()?;
+                R1csConstraint::new(a, b, c, "")
+            }
+            code => {
+                let op = op_from_code(code)?;
+                let out = r.varint()? as usize;
+                let a = r.lc::<T>()?;
+                let b = r.lc::<T>()?;
+                R1csConstraint::symbolic(out, a, b, op, String::new())
+            }
+        };
+        c.comment = match r.take(1)?[0] {
+            0 => None,
+            _ => Some(r.string()?),
+        };
+        out.constraints.push(c);
+    }
+    if !r.is_empty() {
+        for _ in 0..r.varint()? {
+            let name = r.string()?;
+            let mut signals = vec![];
+            for _ in 0..r.varint()? {
+                signals.push(r.varint()? as usize);
+            }
+            out.symbols.push(Ar1csSymbol { name, signals });
+        }
+    }
+    r.finish()?;
+    Ok(out)
+}
+
+/// Encode a witness in the binary format used by `encode_ar1cs`.
+pub fn encode_witness<T: FieldElement>(witness: &Witness<T>) -> Vec<u8> {
+    let mut w = Writer::new::<T>(WITNESS_MAGIC, AR1CS_VERSION);
+    w.varint(witness.outputs.len() as u64);
+    for i in &witness.outputs {
+        w.varint(*i as u64);
+    }
+    w.varint(witness.variables.len() as u64);
+    for v in &witness.variables {
+        w.element(v);
+    }
+    w.bytes
+}
+
+/// Decode a witness written by `encode_witness`.
+pub fn decode_witness<T: FieldElement>(bytes: &[u8]) -> Result<Witness<T>> {
+    let (mut r, _) = Reader::new::<T>(bytes, WITNESS_MAGIC)?;
+    let mut outputs = vec![];
+    for _ in 0..r.varint()? {
+        outputs.push(r.varint()? as usize);
+    }
+    let mut variables = vec![];
+    for _ in 0..r.varint()? {
+        variables.push(r.element::<T>()?);
+    }
+    r.finish()?;
+    Ok(Witness { outputs, variables })
+}
+
+/// Write witness values in segments as they are calculated, so
+/// the witness does not need to be held in memory.
+///
+/// Each segment is a count followed by (signal, value) pairs in
+/// the order the values were calculated. A segment with a count
+/// of 0 ends the values and is followed by the output signals.
+pub struct WitnessWriter<W: Write> {
+    out: W,
+    segment: Writer,
+    count: usize,
+    /// The number of values written.
+    pub written: usize,
+}
+
+impl<W: Write> WitnessWriter<W> {
+    pub fn new<T: FieldElement>(mut out: W) -> Result<Self> {
+        out.write_all(&Writer::new::<T>(WITNESS_STREAM_MAGIC, AR1CS_VERSION).bytes)?;
+        Ok(Self {
+            out,
+            segment: Writer::empty(),
+            count: 0,
+            written: 0,
+        })
+    }
+
+    pub fn push<T: FieldElement>(&mut self, signal: usize, value: &T) -> Result<()> {
+        self.segment.varint(signal as u64);
+        self.segment.element(value);
+        self.count += 1;
+        self.written += 1;
+        if self.count == SEGMENT_LEN {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if self.count == 0 {
+            return Ok(());
+        }
+        let mut prefix = Writer::empty();
+        prefix.varint(self.count as u64);
+        self.out.write_all(&prefix.bytes)?;
+        self.out.write_all(&self.segment.bytes)?;
+        self.segment.bytes.clear();
+        self.count = 0;
+        Ok(())
+    }
+
+    /// Write the remaining values and the output signals.
+    pub fn finish(mut self, outputs: &[usize]) -> Result<W> {
+        self.flush()?;
+        let mut end = Writer::empty();
+        end.varint(0);
+        end.varint(outputs.len() as u64);
+        for i in outputs {
+            end.varint(*i as u64);
+        }
+        self.out.write_all(&end.bytes)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Read a witness written by `WitnessWriter` into memory.
+pub fn decode_witness_stream<T: FieldElement>(bytes: &[u8]) -> Result<Witness<T>> {
+    let (mut r, _) = Reader::new::<T>(bytes, WITNESS_STREAM_MAGIC)?;
+    let mut values = vec![];
+    loop {
+        let count = r.varint()?;
+        if count == 0 {
+            break;
+        }
+        for _ in 0..count {
+            let signal = r.varint()? as usize;
+            values.push((signal, r.element::<T>()?));
+        }
+    }
+    let mut outputs = vec![];
+    for _ in 0..r.varint()? {
+        outputs.push(r.varint()? as usize);
+    }
+    r.finish()?;
+    values.sort_by_key(|(signal, _)| *signal);
+    if values.windows(2).any(|v| v[0].0 == v[1].0) {
+        return log::error!(ParseError: "invalid witness stream: a signal is written more than once");
+    }
+    Ok(Witness {
+        outputs,
+        variables: values.into_iter().map(|(_, v)| v).collect(),
+    })
+}
+
+fn op_code(op: &SymbolicOp) -> u8 {
+    // 0 marks a constraint that is not symbolic
+    match op {
+        SymbolicOp::Inv => 1,
+        SymbolicOp::InvOrZero => 2,
+        SymbolicOp::Bit => 3,
+        SymbolicOp::Eq => 4,
+        SymbolicOp::Mul => 5,
+        SymbolicOp::Add => 6,
+        SymbolicOp::Sqrt => 7,
+        SymbolicOp::Input => 8,
+        SymbolicOp::PublicInput => 9,
+        SymbolicOp::Output => 10,
+    }
+}
+
+fn op_from_code(code: u8) -> Result<SymbolicOp> {
+    Ok(match code {
+        1 => SymbolicOp::Inv,
+        2 => SymbolicOp::InvOrZero,
+        3 => SymbolicOp::Bit,
+        4 => SymbolicOp::Eq,
+        5 => SymbolicOp::Mul,
+        6 => SymbolicOp::Add,
+        7 => SymbolicOp::Sqrt,
+        8 => SymbolicOp::Input,
+        9 => SymbolicOp::PublicInput,
+        10 => SymbolicOp::Output,
+        _ => {
+            return log::error!(ParseError: &format!("invalid binary ar1cs: unknown operation {code}"))
+        }
+    })
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn empty() -> Self {
+        Self { bytes: vec![] }
+    }
+
+    fn new<T: FieldElement>(magic: &[u8; 4], version: u32) -> Self {
+        let mut out = Self::empty();
+        out.bytes.extend(magic);
+        out.varint(version as u64);
+        out.biguint(&T::prime());
+        out
+    }
+
+    fn varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.bytes.push((v as u8 & 0x7f) | 0x80);
+            v >>= 7;
+        }
+        self.bytes.push(v as u8);
+    }
+
+    fn biguint(&mut self, v: &BigUint) {
+        let bytes = if *v == BigUint::from(0_u32) {
+            vec![]
+        } else {
+            v.to_bytes_le()
+        };
+        self.varint(bytes.len() as u64);
+        self.bytes.extend(bytes);
+    }
+
+    fn element<T: FieldElement>(&mut self, v: &T) {
+        self.biguint(&v.to_biguint());
+    }
+
+    fn string(&mut self, v: &str) {
+        self.varint(v.len() as u64);
+        self.bytes.extend(v.as_bytes());
+    }
+
+    fn lc<T: FieldElement>(&mut self, lc: &[(T, usize)]) {
+        self.varint(lc.len() as u64);
+        for (coef, i) in lc {
+            self.varint(*i as u64);
+            self.element(coef);
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    // check the magic string and the field, returning the version
+    fn new<T: FieldElement>(bytes: &'a [u8], magic: &[u8; 4]) -> Result<(Self, u32)> {
+        let mut out = Self { bytes, offset: 0 };
+        if out.take(4)? != magic {
+            return log::error!(ParseError: "invalid binary ar1cs: bad magic string");
+        }
+        let version = out.varint()?;
+        if version > AR1CS_VERSION as u64 {
+            return log::error!(
+                ParseError: &format!("unsupported ar1cs version: {version}"),
+                &format!("this version of ashlang reads ar1cs up to version {AR1CS_VERSION}")
+            );
+        }
+        let prime = out.biguint()?;
+        if prime != T::prime() {
+            return log::error!(
+                ParseError: &format!("binary ar1cs is encoded for a different field: {prime}"),
+                &format!("expected the {} field: {}", T::name_str(), T::prime())
+            );
+        }
+        Ok((out, version as u32))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.offset.saturating_add(len);
+        if end > self.bytes.len() {
+            return log::error!(ParseError: "invalid binary ar1cs: unexpected end of file");
+        }
+        let out = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(out)
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut out = 0_u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            out |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(out);
+            }
+        }
+        log::error!(ParseError: "invalid binary ar1cs: varint is too long")
+    }
+
+    fn biguint(&mut self) -> Result<BigUint> {
+        let len = self.varint()? as usize;
+        Ok(BigUint::from_bytes_le(self.take(len)?))
+    }
+
+    fn element<T: FieldElement>(&mut self) -> Result<T> {
+        let v = self.biguint()?;
+        if v >= T::prime() {
+            return log::error!(ParseError: &format!("invalid binary ar1cs: {v} is not a field element"));
+        }
+        Ok(T::from_biguint(&v))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.varint()? as usize;
+        Ok(String::from_utf8(self.take(len)?.to_vec())?)
+    }
+
+    fn lc<T: FieldElement>(&mut self) -> Result<Vec<(T, usize)>> {
+        let mut out = vec![];
+        for _ in 0..self.varint()? {
+            let i = self.varint()? as usize;
+            out.push((self.element::<T>()?, i));
+        }
+        Ok(out)
+    }
+
+    // whether every byte has been read
+    fn is_empty(&self) -> bool {
+        self.offset == self.bytes.len()
+    }
+
+    fn finish(&self) -> Result<()> {
+        if self.offset != self.bytes.len() {
+            return log::error!(ParseError: "invalid binary ar1cs: unexpected bytes after the end of the file");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+//! Arithmetic in the cubic extension of the base field modulo
+//! `x^3 - x + 1`. An element is the coefficients of `x^0`, `x^1`,
+//! and `x^2`, each a linear combination of signals.
+//!
+//! The modulus is irreducible over the oxfoi field, where this is
+//! the extension field used by Triton. In other fields it may be
+//! reducible, so some non-zero elements have no inverse.
+use anyhow::Result;
+use scalarff::FieldElement;
+
+use super::binary::add_term;
+use super::binary::BinaryCircuit;
+use crate::log;
+
+/// An extension field element as a linear combination for each
+/// coefficient, starting with `x^0`.
+pub type Xfe<F> = Vec<Vec<(F, usize)>>;
+
+/// Constrain the product of two elements. Products of constant
+/// coefficients are evaluated statically.
+pub fn mul<F: FieldElement>(circuit: &mut BinaryCircuit<F>, a: &Xfe<F>, b: &Xfe<F>) -> Xfe<F> {
+    // the coefficients of the product before reduction
+    let mut c = vec![vec![(F::zero(), 0)]; 5];
+    for i in 0..3 {
+        for j in 0..3 {
+            let p = circuit.product(a[i].clone(), b[j].clone(), "xfe_mul");
+            c[i + j] = sum(&[(F::one(), &c[i + j]), (F::one(), &p)]);
+        }
+    }
+    // x^3 = x - 1 and x^4 = x^2 - x
+    let neg = F::zero() - F::one();
+    vec![
+        sum(&[(F::one(), &c[0]), (neg.clone(), &c[3])]),
+        sum(&[(F::one(), &c[1]), (F::one(), &c[3]), (neg, &c[4])]),
+        sum(&[(F::one(), &c[2]), (F::one(), &c[4])]),
+    ]
+}
+
+/// Constrain the inverse of an element. This asserts that the
+/// element is invertible.
+///
+/// The inverse is the first column of the inverse of the matrix
+/// multiplying by the element, calculated using cofactors.
+pub fn inv<F: FieldElement>(circuit: &mut BinaryCircuit<F>, a: &Xfe<F>) -> Result<Xfe<F>> {
+    let one = F::one();
+    let neg = F::zero() - F::one();
+    // a0 + a2 and a1 - a2 appear in several cofactors
+    let s = sum(&[(one.clone(), &a[0]), (one.clone(), &a[2])]);
+    let d = sum(&[(one.clone(), &a[1]), (neg.clone(), &a[2])]);
+    let mut product =
+        |x: &[(F, usize)], y: &[(F, usize)]| circuit.product(x.to_vec(), y.to_vec(), "xfe_inv");
+    let c0 = sum(&[
+        (one.clone(), &product(&s, &s)),
+        (neg.clone(), &product(&a[1], &d)),
+    ]);
+    let c1 = sum(&[
+        (neg.clone(), &product(&a[1], &s)),
+        (one.clone(), &product(&a[2], &d)),
+    ]);
+    let c2 = sum(&[
+        (one.clone(), &product(&a[1], &a[1])),
+        (neg.clone(), &product(&a[2], &s)),
+    ]);
+    let det = sum(&[
+        (one.clone(), &product(&a[0], &c0)),
+        (neg.clone(), &product(&a[2], &c1)),
+        (neg, &product(&a[1], &c2)),
+    ]);
+    // a constant element is checked statically
+    if det.iter().all(|(v, i)| *i == 0 && *v == F::zero()) {
+        return log::error!(
+            TypeError: "extension field element is not invertible",
+            "the element is zero, or the modulus is reducible in this field"
+        );
+    }
+    let det_inv = circuit.inverse(det);
+    Ok([c0, c1, c2]
+        .into_iter()
+        .map(|c| circuit.product(c, det_inv.clone(), "xfe_inv"))
+        .collect())
+}
+
+// a sum of scaled linear combinations
+fn sum<F: FieldElement>(terms: &[(F, &[(F, usize)])]) -> Vec<(F, usize)> {
+    let mut out = vec![(F::zero(), 0)];
+    for (coef, lc) in terms {
+        for (v, i) in lc.iter() {
+            add_term(&mut out, coef.clone() * v.clone(), *i);
+        }
+    }
+    out
+}
@@ -0,0 +1,407 @@
+//! Inner product argument verification over the Edwards curve of
+//! the proving field, see `r1cs::eddsa`.
+//!
+//! A proof shows that the commitment `P = <a, G> + <b, H>` opens
+//! to vectors with inner product `c`, using the folding argument
+//! from [Bulletproofs](https://eprint.iacr.org/2017/1066.pdf).
+//! Each round commits to the cross terms `L` and `R`, and the
+//! challenge `x` is the poseidon hash of the transcript. The
+//! verifier checks
+//!
+//! `8 * (P + c * U + sum(x^2 * L + x^-2 * R)) = 8 * (a * G' + b * H' + a * b * U)`
+//!
+//! where `G'` and `H'` are the generators folded by the challenges.
+//!
+//! Scalars are elements of the subgroup order, which is smaller
+//! than the proving field. Challenges are used as field elements
+//! and are implicitly reduced by the curve, and the prover
+//! supplies the inverse of each challenge so the verifier does
+//! not compute in the scalar field.
+use anyhow::Result;
+use scalarff::BigUint;
+use scalarff::FieldElement;
+
+use super::binary::BinaryCircuit;
+use super::constraint::R1csConstraint;
+use super::eddsa::EdwardsCurve;
+use super::eddsa::Point;
+use super::poseidon::PoseidonParams;
+use crate::log;
+
+/// The largest number of rounds that may be verified, e.g.
+/// vectors of at most 2^8 elements.
+pub const MAX_ROUNDS: usize = 8;
+
+// domain separators for generator derivation
+const LABEL_G: u64 = 1;
+const LABEL_H: u64 = 2;
+const LABEL_U: u64 = 3;
+
+/// Generators for vectors of a fixed length.
+pub struct IpaParams<F: FieldElement> {
+    pub curve: EdwardsCurve<F>,
+    pub g: Vec<(F, F)>,
+    pub h: Vec<(F, F)>,
+    pub u: (F, F),
+}
+
+/// An inner product argument. `l`, `r`, and `x_inv` have an
+/// entry for each round.
+#[allow(dead_code)]
+pub struct IpaProof<F: FieldElement> {
+    pub commitment: (F, F),
+    pub c: F,
+    pub l: Vec<(F, F)>,
+    pub r: Vec<(F, F)>,
+    pub x_inv: Vec<F>,
+    pub a: F,
+    pub b: F,
+}
+
+/// The signals of an inner product argument in a circuit.
+/// `challenges` is the transcript hash after each round.
+pub struct IpaSignals<F: FieldElement> {
+    pub commitment: Point<F>,
+    pub c: usize,
+    pub l: Vec<Point<F>>,
+    pub r: Vec<Point<F>>,
+    pub x_inv: Vec<usize>,
+    pub challenges: Vec<usize>,
+    pub a: usize,
+    pub b: usize,
+}
+
+impl<F: FieldElement> IpaParams<F> {
+    /// Derive generators for vectors of `len` elements. `len`
+    /// must be a power of two.
+    pub fn new(len: usize) -> Result<Self> {
+        if !len.is_power_of_two() || len < 2 || len > 1 << MAX_ROUNDS {
+            return log::error!(
+                TypeError: &format!("inner product arguments have 2 to {} elements, received {len}", 1 << MAX_ROUNDS),
+                "the vector length must be a power of two"
+            );
+        }
+        let curve = EdwardsCurve::<F>::new()?;
+        let hasher = PoseidonParams::<F>::new(3)?;
+        let mut g = vec![];
+        let mut h = vec![];
+        for i in 0..len {
+            g.push(generator(&curve, &hasher, LABEL_G, i)?);
+            h.push(generator(&curve, &hasher, LABEL_H, i)?);
+        }
+        let u = generator(&curve, &hasher, LABEL_U, 0)?;
+        Ok(Self { curve, g, h, u })
+    }
+
+    /// Prove the inner product of two vectors of scalars.
+    #[allow(dead_code)]
+    pub fn prove(&self, a: &[BigUint], b: &[BigUint]) -> Result<IpaProof<F>> {
+        if a.len() != self.g.len() || b.len() != self.g.len() {
+            return log::error!(ProverError: &format!(
+                "inner product vectors must have {} elements",
+                self.g.len()
+            ));
+        }
+        let n = &self.curve.order;
+        let mut a = a.iter().map(|v| v % n).collect::<Vec<_>>();
+        let mut b = b.iter().map(|v| v % n).collect::<Vec<_>>();
+        let mut g = self.g.clone();
+        let mut h = self.h.clone();
+        let commitment = self.curve.add_static(&self.msm(&a, &g), &self.msm(&b, &h));
+        let c = inner_product(&a, &b, n);
+        let mut t = PoseidonParams::<F>::new(3)?.hash(&[
+            commitment.0.clone(),
+            commitment.1.clone(),
+            F::from_biguint(&c),
+        ])?;
+        let round_hasher = PoseidonParams::<F>::new(5)?;
+        let mut l = vec![];
+        let mut r = vec![];
+        let mut x_inv = vec![];
+        while a.len() > 1 {
+            let m = a.len() / 2;
+            let (a_lo, a_hi) = a.split_at(m);
+            let (b_lo, b_hi) = b.split_at(m);
+            let (g_lo, g_hi) = g.split_at(m);
+            let (h_lo, h_hi) = h.split_at(m);
+            let c_l = inner_product(a_lo, b_hi, n);
+            let c_r = inner_product(a_hi, b_lo, n);
+            let l_point = self.curve.add_static(
+                &self
+                    .curve
+                    .add_static(&self.msm(a_lo, g_hi), &self.msm(b_hi, h_lo)),
+                &self.curve.mul_static(&self.u, &c_l),
+            );
+            let r_point = self.curve.add_static(
+                &self
+                    .curve
+                    .add_static(&self.msm(a_hi, g_lo), &self.msm(b_lo, h_hi)),
+                &self.curve.mul_static(&self.u, &c_r),
+            );
+            t = round_hasher.hash(&[
+                t,
+                l_point.0.clone(),
+                l_point.1.clone(),
+                r_point.0.clone(),
+                r_point.1.clone(),
+            ])?;
+            let x = t.to_biguint() % n;
+            if x == BigUint::from(0_u32) {
+                return log::error!(ProverError: "inner product challenge is zero");
+            }
+            // the order is prime
+            let xi = x.modpow(&(n - 2_u32), n);
+            let fold = |lo: &[BigUint], hi: &[BigUint], s_lo: &BigUint, s_hi: &BigUint| {
+                lo.iter()
+                    .zip(hi)
+                    .map(|(lo, hi)| (lo * s_lo + hi * s_hi) % n)
+                    .collect::<Vec<_>>()
+            };
+            let fold_points = |lo: &[(F, F)], hi: &[(F, F)], s_lo: &BigUint, s_hi: &BigUint| {
+                lo.iter()
+                    .zip(hi)
+                    .map(|(lo, hi)| {
+                        self.curve.add_static(
+                            &self.curve.mul_static(lo, s_lo),
+                            &self.curve.mul_static(hi, s_hi),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let next_a = fold(a_lo, a_hi, &x, &xi);
+            let next_b = fold(b_lo, b_hi, &xi, &x);
+            let next_g = fold_points(g_lo, g_hi, &xi, &x);
+            let next_h = fold_points(h_lo, h_hi, &x, &xi);
+            (a, b, g, h) = (next_a, next_b, next_g, next_h);
+            l.push(l_point);
+            r.push(r_point);
+            x_inv.push(F::from_biguint(&xi));
+        }
+        Ok(IpaProof {
+            commitment,
+            c: F::from_biguint(&c),
+            l,
+            r,
+            x_inv,
+            a: F::from_biguint(&a[0]),
+            b: F::from_biguint(&b[0]),
+        })
+    }
+
+    /// Constrain an inner product argument to be valid. The
+    /// number of rounds must match the generators.
+    pub fn verify(&self, circuit: &mut BinaryCircuit<F>, proof: &IpaSignals<F>) -> Result<()> {
+        let rounds = self.g.len().trailing_zeros() as usize;
+        if [
+            proof.l.len(),
+            proof.r.len(),
+            proof.x_inv.len(),
+            proof.challenges.len(),
+        ]
+        .iter()
+        .any(|v| *v != rounds)
+        {
+            return log::error!(TypeError: &format!(
+                "inner product argument must have {rounds} rounds"
+            ));
+        }
+        let curve = &self.curve;
+        for p in [&proof.commitment]
+            .into_iter()
+            .chain(&proof.l)
+            .chain(&proof.r)
+        {
+            curve.assert_on_curve(circuit, p);
+        }
+        // scalars must be reduced so proofs are not malleable
+        let scalar = |circuit: &mut BinaryCircuit<F>, i: usize| {
+            let bits = circuit.decompose(vec![(F::one(), i)], curve.order.bits() as usize);
+            circuit.assert_less_than(&bits, &curve.order);
+            bits
+        };
+        let c_bits = scalar(circuit, proof.c);
+        let a_bits = scalar(circuit, proof.a);
+        let b_bits = scalar(circuit, proof.b);
+
+        let cu = curve.mul_const(circuit, &self.u, &c_bits);
+        let mut p = curve.add(circuit, &proof.commitment, &cu);
+        let mut g = self.g.iter().map(constant_point).collect::<Vec<_>>();
+        let mut h = self.h.iter().map(constant_point).collect::<Vec<_>>();
+        for round in 0..rounds {
+            // the challenge is any field element, the bits must not
+            // overflow the field
+            let x_bits = circuit.decompose(
+                vec![(F::one(), proof.challenges[round])],
+                F::prime().bits() as usize,
+            );
+            circuit.assert_less_than(&x_bits, &F::prime());
+            let xi_bits = scalar(circuit, proof.x_inv[round]);
+            // x^-1 * (x * B) = B if x_inv is the inverse of x
+            let xb = curve.mul_base(circuit, &x_bits);
+            let one = curve.mul(circuit, &xb, &xi_bits);
+            assert_equal(
+                circuit,
+                &one,
+                &constant_point(&curve.base),
+                "challenge inverse",
+            );
+
+            let xl = curve.mul(circuit, &proof.l[round], &x_bits);
+            let xxl = curve.mul(circuit, &xl, &x_bits);
+            let xr = curve.mul(circuit, &proof.r[round], &xi_bits);
+            let xxr = curve.mul(circuit, &xr, &xi_bits);
+            p = curve.add(circuit, &xxl, &p);
+            p = curve.add(circuit, &p, &xxr);
+
+            let m = g.len() / 2;
+            let mut next_g = vec![];
+            let mut next_h = vec![];
+            for i in 0..m {
+                let mut scale = |p: &Point<F>, constant: &(F, F), bits: &[Vec<(F, usize)>]| {
+                    // generators are constant in the first round
+                    if round == 0 {
+                        curve.mul_const(circuit, constant, bits)
+                    } else {
+                        curve.mul(circuit, p, bits)
+                    }
+                };
+                let g_lo = scale(&g[i], &self.g[i], &xi_bits);
+                let g_hi = scale(&g[m + i], &self.g[m + i], &x_bits);
+                let h_lo = scale(&h[i], &self.h[i], &x_bits);
+                let h_hi = scale(&h[m + i], &self.h[m + i], &xi_bits);
+                next_g.push(curve.add(circuit, &g_lo, &g_hi));
+                next_h.push(curve.add(circuit, &h_lo, &h_hi));
+            }
+            g = next_g;
+            h = next_h;
+        }
+
+        let ag = curve.mul(circuit, &g[0], &a_bits);
+        let bh = curve.mul(circuit, &h[0], &b_bits);
+        let bu = curve.mul_const(circuit, &self.u, &b_bits);
+        let abu = curve.mul(circuit, &bu, &a_bits);
+        let sum = curve.add(circuit, &ag, &bh);
+        let expected = curve.add(circuit, &sum, &abu);
+        // clear the cofactor, the commitments are not checked to
+        // be in the subgroup
+        let mut p8 = p;
+        let mut expected8 = expected;
+        for _ in 0..3 {
+            p8 = curve.add(circuit, &p8, &p8);
+            expected8 = curve.add(circuit, &expected8, &expected8);
+        }
+        assert_equal(circuit, &p8, &expected8, "inner product argument is valid");
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    fn msm(&self, scalars: &[BigUint], points: &[(F, F)]) -> (F, F) {
+        scalars
+            .iter()
+            .zip(points)
+            .fold((F::zero(), F::one()), |acc, (s, p)| {
+                self.curve.add_static(&acc, &self.curve.mul_static(p, s))
+            })
+    }
+}
+
+// hash to a point in the prime order subgroup by incrementing a
+// counter until the x coordinate is on the curve
+fn generator<F: FieldElement>(
+    curve: &EdwardsCurve<F>,
+    hasher: &PoseidonParams<F>,
+    label: u64,
+    index: usize,
+) -> Result<(F, F)> {
+    let p = F::prime();
+    let legendre_exp = (p.clone() - 1_u32) / 2_u32;
+    let mut counter = 0_u64;
+    loop {
+        let x = hasher.hash(&[F::from(label), F::from(index as u64), F::from(counter)])?;
+        counter += 1;
+        // y^2 = (1 - a * x^2) / (1 - d * x^2)
+        let x2 = x.clone() * x.clone();
+        let den = F::one() - curve.d.clone() * x2.clone();
+        if den == F::zero() {
+            continue;
+        }
+        let y2 = (F::one() - curve.a.clone() * x2) / den;
+        let y2 = y2.to_biguint();
+        if y2 != BigUint::from(0_u32) && y2.modpow(&legendre_exp, &p) != BigUint::from(1_u32) {
+            continue;
+        }
+        let y = sqrt(&y2, &p);
+        // use the smaller root so the point is deterministic
+        let y = std::cmp::min(y.clone(), (p.clone() - y) % &p);
+        let point = curve.mul_static(&(x, F::from_biguint(&y)), &BigUint::from(8_u32));
+        if point != (F::zero(), F::one()) {
+            return Ok(point);
+        }
+    }
+}
+
+// tonelli-shanks, `v` must be a quadratic residue
+fn sqrt(v: &BigUint, p: &BigUint) -> BigUint {
+    let zero = BigUint::from(0_u32);
+    let one = BigUint::from(1_u32);
+    if *v == zero {
+        return zero;
+    }
+    let p_minus_one = p - 1_u32;
+    let mut q = p_minus_one.clone();
+    let mut s = 0_u32;
+    while q.clone() % 2_u32 == zero {
+        q /= 2_u32;
+        s += 1;
+    }
+    let legendre_exp = p_minus_one.clone() / 2_u32;
+    let mut z = BigUint::from(2_u32);
+    while z.modpow(&legendre_exp, p) != p_minus_one {
+        z += 1_u32;
+    }
+    let mut m = s;
+    let mut c = z.modpow(&q, p);
+    let mut t = v.modpow(&q, p);
+    let mut r = v.modpow(&((q + 1_u32) / 2_u32), p);
+    while t != one {
+        let mut i = 0;
+        let mut tt = t.clone();
+        while tt != one {
+            tt = tt.clone() * tt % p;
+            i += 1;
+        }
+        let b = c.modpow(&(BigUint::from(1_u32) << (m - i - 1)), p);
+        m = i;
+        c = b.clone() * b.clone() % p;
+        t = t * c.clone() % p;
+        r = r * b % p;
+    }
+    r
+}
+
+#[allow(dead_code)]
+fn inner_product(a: &[BigUint], b: &[BigUint], n: &BigUint) -> BigUint {
+    a.iter()
+        .zip(b)
+        .fold(BigUint::from(0_u32), |acc, (a, b)| (acc + a * b) % n)
+}
+
+fn constant_point<F: FieldElement>(p: &(F, F)) -> Point<F> {
+    (vec![(p.0.clone(), 0)], vec![(p.1.clone(), 0)])
+}
+
+fn assert_equal<F: FieldElement>(
+    circuit: &mut BinaryCircuit<F>,
+    p: &Point<F>,
+    q: &Point<F>,
+    message: &str,
+) {
+    for (l, r) in [(&p.0, &q.0), (&p.1, &q.1)] {
+        circuit.constraints.push(R1csConstraint::new(
+            l.clone(),
+            vec![(F::one(), 0)],
+            r.clone(),
+            message,
+        ));
+    }
+}
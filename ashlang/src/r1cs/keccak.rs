@@ -0,0 +1,131 @@
+//! Keccak-256 over bytes of constrained bits, as used by
+//! Ethereum. This is the original Keccak padding, not SHA3.
+use scalarff::FieldElement;
+
+use super::binary::BinaryCircuit;
+use super::binary::Bit;
+
+// bytes absorbed per permutation
+const RATE: usize = 136;
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+// rotation offsets indexed by [x][y]
+const ROTATIONS: [[usize; 5]; 5] = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+];
+
+/// Hash a message of bytes, each byte is 8 little endian bits.
+/// Returns the 32 byte digest.
+pub fn hash<F: FieldElement>(
+    circuit: &mut BinaryCircuit<F>,
+    message: &[Vec<Bit<F>>],
+) -> Vec<Vec<Bit<F>>> {
+    // at least one byte of padding is added
+    let padding = RATE - message.len() % RATE;
+    let mut padded = message.to_vec();
+    padded.push(BinaryCircuit::constant_bits(0x01, 8));
+    padded.resize(message.len() + padding, BinaryCircuit::constant_bits(0, 8));
+    // the final byte may also be the first padding byte
+    let last = padded.len() - 1;
+    padded[last][7] = BinaryCircuit::constant(true);
+
+    // 25 lanes of 64 bits, lane x + 5y
+    let mut state = vec![BinaryCircuit::constant_bits(0, 64); 25];
+    for block in padded.chunks(RATE) {
+        for (k, byte) in block.iter().enumerate() {
+            for (j, bit) in byte.iter().enumerate() {
+                let lane = &mut state[k / 8];
+                lane[(k % 8) * 8 + j] = circuit.xor(&lane[(k % 8) * 8 + j], bit);
+            }
+        }
+        for rc in ROUND_CONSTANTS {
+            state = round(circuit, &state, rc);
+        }
+    }
+
+    state[..4]
+        .iter()
+        .flat_map(|lane| lane.chunks(8).map(|v| v.to_vec()).collect::<Vec<_>>())
+        .collect()
+}
+
+fn round<F: FieldElement>(
+    circuit: &mut BinaryCircuit<F>,
+    a: &[Vec<Bit<F>>],
+    rc: u64,
+) -> Vec<Vec<Bit<F>>> {
+    // theta
+    let c = (0..5)
+        .map(|x| circuit.xor_words(&[&a[x], &a[x + 5], &a[x + 10], &a[x + 15], &a[x + 20]]))
+        .collect::<Vec<_>>();
+    let d = (0..5)
+        .map(|x| circuit.xor_words(&[&c[(x + 4) % 5], &rotl(&c[(x + 1) % 5], 1)]))
+        .collect::<Vec<_>>();
+    let a = (0..25)
+        .map(|i| circuit.xor_words(&[&a[i], &d[i % 5]]))
+        .collect::<Vec<_>>();
+
+    // rho and pi
+    let mut b = vec![vec![]; 25];
+    for x in 0..5 {
+        for y in 0..5 {
+            b[y + 5 * ((2 * x + 3 * y) % 5)] = rotl(&a[x + 5 * y], ROTATIONS[x][y]);
+        }
+    }
+
+    // chi
+    let mut out = (0..25)
+        .map(|i| {
+            let (x, y) = (i % 5, i / 5);
+            let b1 = &b[(x + 1) % 5 + 5 * y];
+            let b2 = &b[(x + 2) % 5 + 5 * y];
+            (0..64)
+                .map(|j| {
+                    let v = circuit.and(&BinaryCircuit::not(&b1[j]), &b2[j]);
+                    circuit.xor(&b[i][j], &v)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // iota
+    let rc = BinaryCircuit::constant_bits(rc, 64);
+    out[0] = circuit.xor_words(&[&out[0], &rc]);
+    out
+}
+
+fn rotl<F: FieldElement>(x: &[Bit<F>], n: usize) -> Vec<Bit<F>> {
+    (0..x.len())
+        .map(|i| x[(i + x.len() - n) % x.len()].clone())
+        .collect()
+}
@@ -0,0 +1,284 @@
+//! Lookups of signals in static tables, expanded into r1cs
+//! constraints.
+use anyhow::Result;
+use scalarff::BigUint;
+use scalarff::FieldElement;
+
+use super::constraint::R1csConstraint;
+use super::constraint::SymbolicOp;
+use crate::log;
+use crate::parser::TableDef;
+
+/// A table of distinct rows of field elements.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LookupTable<F: FieldElement> {
+    pub name: String,
+    pub rows: Vec<Vec<F>>,
+}
+
+impl<F: FieldElement> LookupTable<F> {
+    pub fn new(table: &TableDef) -> Result<Self> {
+        let mut rows = vec![];
+        for row in &table.rows {
+            let mut values = vec![];
+            for v in row {
+                let value = F::from_str(v)?;
+                if &value.to_biguint().to_string() != v {
+                    return log::error!(
+                        TypeError: &format!("value {v} in table {} is too large for the {} field", table.name, F::name_str())
+                    );
+                }
+                values.push(value);
+            }
+            rows.push(values);
+        }
+        Ok(Self {
+            name: table.name.clone(),
+            rows,
+        })
+    }
+
+    /// The number of columns in each row.
+    pub fn width(&self) -> usize {
+        self.rows[0].len()
+    }
+
+    /// Check that a lookup passes one value per column.
+    pub fn check_width(&self, len: usize) -> Result<()> {
+        if len != self.width() {
+            return log::error!(
+                TypeError: &format!(
+                    "lookup in table {} expects {} values, received {len}",
+                    self.name,
+                    self.width()
+                ),
+                "pass one value for each column of the table"
+            );
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, row: &[F]) -> bool {
+        self.rows.iter().any(|v| v == row)
+    }
+
+    // the number of bits if the table is a single column
+    // containing 0..2^bits in order
+    fn range_bits(&self) -> Option<usize> {
+        let len = self.rows.len();
+        if self.width() != 1 || len < 2 || !len.is_power_of_two() {
+            return None;
+        }
+        let bits = len.trailing_zeros() as usize;
+        if bits as u64 >= F::prime().bits() {
+            return None;
+        }
+        let mut i = F::zero();
+        for row in &self.rows {
+            if row[0] != i {
+                return None;
+            }
+            i += F::one();
+        }
+        Some(bits)
+    }
+}
+
+/// A lookup of linear combinations in a table. The lookup is
+/// satisfied if the values of the entries are a row of the table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lookup<F: FieldElement> {
+    pub table: String,
+    pub entries: Vec<Vec<(F, usize)>>,
+}
+
+/// Allocates signals and constraints for lookups in plain r1cs.
+pub struct LookupCircuit<F: FieldElement> {
+    pub var_index: usize,
+    pub constraints: Vec<R1csConstraint<F>>,
+    // attached to symbolic constraints
+    message: String,
+}
+
+impl<F: FieldElement> LookupCircuit<F> {
+    pub fn new(var_index: usize, message: &str) -> Self {
+        Self {
+            var_index,
+            constraints: vec![],
+            message: message.to_string(),
+        }
+    }
+
+    /// Expand a lookup into constraints. The cheapest of the
+    /// following is used:
+    ///
+    /// - a table of 0..2^n is a bit decomposition of the entry,
+    ///   n + 1 constraints
+    /// - a single column is a product of differences that must
+    ///   be zero, one constraint per row
+    /// - each row is a selector bit, one selector is set and
+    ///   the entries are the sum of the selected row, one
+    ///   constraint per row and column
+    pub fn expand(&mut self, lookup: &Lookup<F>, table: &LookupTable<F>) -> Result<()> {
+        table.check_width(lookup.entries.len())?;
+        if let Some(bits) = table.range_bits() {
+            self.bits(&lookup.entries[0], bits);
+        } else if table.width() == 1 {
+            self.product(&lookup.entries[0], table);
+        } else {
+            self.selectors(&lookup.entries, table)?;
+        }
+        Ok(())
+    }
+
+    fn bits(&mut self, lc: &[(F, usize)], bits: usize) {
+        let start = self.var_index;
+        self.var_index += bits;
+        let mut sum = vec![];
+        let mut coef = F::one();
+        for x in 0..bits {
+            let bit = start + x;
+            self.constraints.append(&mut vec![
+                R1csConstraint::symbolic(
+                    bit,
+                    vec![(F::from(x as u64), 0)],
+                    lc.to_vec(),
+                    SymbolicOp::Bit,
+                    self.message.clone(),
+                ),
+                R1csConstraint::new(
+                    vec![(F::one(), bit)],
+                    vec![(F::one(), bit)],
+                    vec![(F::one(), bit)],
+                    &format!("{bit} is a bit"),
+                )
+                .as_assignment(),
+            ]);
+            sum.push((coef.clone(), bit));
+            coef = coef.clone() + coef;
+        }
+        self.constraints.push(R1csConstraint::new(
+            sum,
+            vec![(F::one(), 0)],
+            lc.to_vec(),
+            &format!("lookup range 0..2^{bits}"),
+        ));
+    }
+
+    fn product(&mut self, lc: &[(F, usize)], table: &LookupTable<F>) {
+        // lc - t
+        let diff = |t: &F| {
+            let mut out = lc.to_vec();
+            out.push((F::zero() - t.clone(), 0));
+            out
+        };
+        let mut acc = diff(&table.rows[0][0]);
+        for row in &table.rows[1..] {
+            let p = self.var_index;
+            self.var_index += 1;
+            self.constraints.append(&mut vec![
+                R1csConstraint::symbolic(
+                    p,
+                    acc.clone(),
+                    diff(&row[0]),
+                    SymbolicOp::Mul,
+                    self.message.clone(),
+                ),
+                R1csConstraint::new(
+                    acc,
+                    diff(&row[0]),
+                    vec![(F::one(), p)],
+                    &format!("lookup in {} product into {p}", table.name),
+                )
+                .as_assignment(),
+            ]);
+            acc = vec![(F::one(), p)];
+        }
+        // the product is zero if the entry is in the table
+        self.constraints.push(R1csConstraint::new(
+            acc,
+            vec![(F::one(), 0)],
+            vec![(F::zero(), 0)],
+            &format!("lookup in {}", table.name),
+        ));
+    }
+
+    fn selectors(&mut self, entries: &[Vec<(F, usize)>], table: &LookupTable<F>) -> Result<()> {
+        // each row is packed into a single element to calculate
+        // the selectors, values in the table are distinct digits
+        let base = table
+            .rows
+            .iter()
+            .flatten()
+            .map(|v| v.to_biguint())
+            .max()
+            .unwrap_or_default()
+            + 1_u32;
+        if base.pow(table.width() as u32) >= F::prime() {
+            return log::error!(
+                ConfigError: &format!("table {} is too large for the {} field", table.name, F::name_str()),
+                "rows of a table with more than one column must fit in a single field element"
+            );
+        }
+        let mut coefs = vec![];
+        let mut coef = BigUint::from(1_u32);
+        for _ in 0..table.width() {
+            coefs.push(F::from_biguint(&coef));
+            coef *= base.clone();
+        }
+        let pack = |row: &dyn Fn(usize) -> Vec<(F, usize)>| {
+            let mut out = vec![];
+            for (c, coef) in coefs.iter().enumerate() {
+                for (v, i) in row(c) {
+                    out.push((v * coef.clone(), i));
+                }
+            }
+            out
+        };
+        let packed = pack(&|c| entries[c].clone());
+        let start = self.var_index;
+        self.var_index += table.rows.len();
+        let mut sum = vec![];
+        let mut columns = vec![vec![]; table.width()];
+        for (j, row) in table.rows.iter().enumerate() {
+            let s = start + j;
+            self.constraints.append(&mut vec![
+                R1csConstraint::symbolic(
+                    s,
+                    packed.clone(),
+                    pack(&|c| vec![(row[c].clone(), 0)]),
+                    SymbolicOp::Eq,
+                    self.message.clone(),
+                ),
+                R1csConstraint::new(
+                    vec![(F::one(), s)],
+                    vec![(F::one(), s)],
+                    vec![(F::one(), s)],
+                    &format!("{s} is a bit"),
+                )
+                .as_assignment(),
+            ]);
+            sum.push((F::one(), s));
+            for (c, v) in row.iter().enumerate() {
+                if *v != F::zero() {
+                    columns[c].push((v.clone(), s));
+                }
+            }
+        }
+        self.constraints.push(R1csConstraint::new(
+            sum,
+            vec![(F::one(), 0)],
+            vec![(F::one(), 0)],
+            &format!("one-hot selector for lookup in {}", table.name),
+        ));
+        for (c, column) in columns.into_iter().enumerate() {
+            self.constraints.push(R1csConstraint::new(
+                column,
+                vec![(F::one(), 0)],
+                entries[c].clone(),
+                &format!("lookup in {} column {c}", table.name),
+            ));
+        }
+        Ok(())
+    }
+}
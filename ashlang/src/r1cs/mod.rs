@@ -1,5 +1,20 @@
 //! Core logic for the r1cs target.
+pub mod ar1cs;
+pub mod binary;
+pub mod cache;
+pub mod circom;
 pub mod constraint;
+pub mod eddsa;
+pub mod encoding;
+pub mod extension;
+pub mod ipa;
+pub mod keccak;
+pub mod lookup;
+pub mod optimizer;
 pub mod parser;
+pub mod poseidon;
+pub mod sha256;
+pub mod solver;
+pub mod sparse;
 pub mod vm;
 pub mod witness;
@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use anyhow::Result;
+use scalarff::FieldElement;
+
+use crate::log;
+use crate::r1cs::constraint::R1csConstraint;
+use crate::r1cs::constraint::SymbolicOp;
+use crate::r1cs::constraint::ASSERT_COMMENT;
+
+/// A linear combination of signals as (coefficient, var_index) pairs.
+type Lc<T> = Vec<(T, usize)>;
+
+/// Simplify a set of constraints without changing the set of
+/// valid witnesses.
+///
+/// - duplicate signals in a linear combination are merged and
+///   zero coefficients are removed
+/// - constraints that are linear (one factor is a constant) are
+///   used to substitute a signal out of all other constraints.
+///   This removes assignments, copies, and identity operations
+///   like `x * 1 + 0`
+/// - constraints that are always satisfied are removed
+///
+/// Symbolic constraints are not modified so the witness is
+/// calculated exactly as before. Signals that are marked as
+/// inputs or outputs are never substituted. The first
+/// non-symbolic constraint is the field safety constraint and
+/// is never modified.
+/// Constraints created by `assert` statements are never used
+/// for substitution so their messages are reported when a
+/// witness fails to satisfy them.
+pub fn optimize<T: FieldElement>(
+    constraints: Vec<R1csConstraint<T>>,
+) -> Result<Vec<R1csConstraint<T>>> {
+    let mut protected: HashSet<usize> = HashSet::new();
+    protected.insert(0);
+    for c in &constraints {
+        if let Some(SymbolicOp::Input | SymbolicOp::PublicInput | SymbolicOp::Output) =
+            c.symbolic_op
+        {
+            protected.insert(c.out_i.unwrap());
+        }
+    }
+    // signal index keyed to a linear combination that
+    // does not contain any substituted signals
+    let mut subs: HashMap<usize, Lc<T>> = HashMap::new();
+    // signal index keyed to substitutions that contain it
+    let mut users: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut out = vec![];
+    let mut is_first = true;
+    for c in constraints {
+        if c.symbolic {
+            out.push(c);
+            continue;
+        }
+        if is_first {
+            is_first = false;
+            out.push(c);
+            continue;
+        }
+        let c = R1csConstraint {
+            a: substitute(&c.a, &subs),
+            b: substitute(&c.b, &subs),
+            c: substitute(&c.c, &subs),
+            ..c
+        };
+        let is_assert = c
+            .comment
+            .as_ref()
+            .is_some_and(|v| v.starts_with(ASSERT_COMMENT));
+        if let Some(lc) = linear_form(&c) {
+            if lc.is_empty() {
+                // always satisfied
+                continue;
+            }
+            if constant(&lc).is_some() {
+                return log::error!(&format!(
+                    "constraint can never be satisfied: {}",
+                    c.comment.clone().unwrap_or_default()
+                ));
+            }
+            if let Some((coef, x)) = lc
+                .iter()
+                .find(|(_, i)| !is_assert && !protected.contains(i))
+                .cloned()
+            {
+                // coef*x + rest = 0
+                // x = -rest / coef
+                let inv = T::one() / coef;
+                let def = lc
+                    .iter()
+                    .filter(|(_, i)| *i != x)
+                    .map(|(k, i)| ((T::zero() - k.clone()) * inv.clone(), *i))
+                    .collect::<Lc<T>>();
+                let x_sub = HashMap::from([(x, def.clone())]);
+                for key in users.remove(&x).unwrap_or_default() {
+                    let updated = substitute(subs.get(&key).unwrap(), &x_sub);
+                    for (_, i) in &def {
+                        users.entry(*i).or_default().push(key);
+                    }
+                    subs.insert(key, updated);
+                }
+                for (_, i) in &def {
+                    users.entry(*i).or_default().push(x);
+                }
+                subs.insert(x, def);
+                continue;
+            }
+        }
+        out.push(c);
+    }
+    // apply substitutions made after a constraint was kept
+    Ok(out
+        .into_iter()
+        .map(|c| {
+            if c.symbolic {
+                return c;
+            }
+            R1csConstraint {
+                a: non_empty(substitute(&c.a, &subs)),
+                b: non_empty(substitute(&c.b, &subs)),
+                c: non_empty(substitute(&c.c, &subs)),
+                ..c
+            }
+        })
+        .collect())
+}
+
+/// Combine terms with the same signal and remove terms
+/// with a zero coefficient.
+fn merge<T: FieldElement>(lc: Lc<T>) -> Lc<T> {
+    let mut positions: HashMap<usize, usize> = HashMap::new();
+    let mut out: Lc<T> = vec![];
+    for (coef, index) in lc {
+        if let Some(p) = positions.get(&index) {
+            out[*p].0 += coef;
+        } else {
+            positions.insert(index, out.len());
+            out.push((coef, index));
+        }
+    }
+    out.into_iter().filter(|(c, _)| *c != T::zero()).collect()
+}
+
+fn substitute<T: FieldElement>(lc: &Lc<T>, subs: &HashMap<usize, Lc<T>>) -> Lc<T> {
+    let mut out = vec![];
+    for (coef, index) in lc {
+        if let Some(def) = subs.get(index) {
+            for (k, i) in def {
+                out.push((coef.clone() * k.clone(), *i));
+            }
+        } else {
+            out.push((coef.clone(), *index));
+        }
+    }
+    merge(out)
+}
+
+/// The value of a linear combination if it contains only
+/// the `one` signal.
+fn constant<T: FieldElement>(lc: &Lc<T>) -> Option<T> {
+    let mut out = T::zero();
+    for (coef, index) in lc {
+        if *index != 0 {
+            return None;
+        }
+        out += coef.clone();
+    }
+    Some(out)
+}
+
+/// If either factor is a constant express the constraint
+/// as a single linear combination equal to 0.
+fn linear_form<T: FieldElement>(c: &R1csConstraint<T>) -> Option<Lc<T>> {
+    let scaled = |lc: &Lc<T>, k: T| {
+        lc.iter()
+            .map(|(coef, i)| (coef.clone() * k.clone(), *i))
+            .collect::<Lc<T>>()
+    };
+    let product = if let Some(k) = constant(&c.a) {
+        scaled(&c.b, k)
+    } else if let Some(k) = constant(&c.b) {
+        scaled(&c.a, k)
+    } else {
+        return None;
+    };
+    Some(merge(
+        [product, scaled(&c.c, T::zero() - T::one())].concat(),
+    ))
+}
+
+/// ar1cs requires at least one term in each factor.
+fn non_empty<T: FieldElement>(lc: Lc<T>) -> Lc<T> {
+    if lc.is_empty() {
+        vec![(T::zero(), 0)]
+    } else {
+        lc
+    }
+}
+
+/// Remove constraints and signals that do not affect the
+/// inputs, outputs, or assertions of a program, then renumber
+/// the remaining signals so they are contiguous.
+///
+/// A non-symbolic constraint is removed if it contains a signal
+/// that appears only in the `c` factor of that constraint, and
+/// nowhere else. Such a constraint can always be satisfied by
+/// assigning the signal. Symbolic constraints are removed if the
+/// signal they assign is not needed by any remaining constraint.
+/// The first non-symbolic constraint is the field safety
+/// constraint and is never removed.
+pub fn eliminate_dead<T: FieldElement>(
+    constraints: Vec<R1csConstraint<T>>,
+) -> Vec<R1csConstraint<T>> {
+    let mut protected: HashSet<usize> = HashSet::new();
+    protected.insert(0);
+    for c in &constraints {
+        if let Some(SymbolicOp::Input | SymbolicOp::PublicInput | SymbolicOp::Output) =
+            c.symbolic_op
+        {
+            protected.insert(c.out_i.unwrap());
+        }
+    }
+    let first_constraint = constraints.iter().position(|c| !c.symbolic);
+    let signals = |c: &R1csConstraint<T>| {
+        c.a.iter()
+            .chain(c.b.iter())
+            .chain(c.c.iter())
+            .map(|(_, i)| *i)
+            .collect::<HashSet<usize>>()
+    };
+    // number of non-symbolic constraints each signal appears in
+    let mut counts: HashMap<usize, usize> = HashMap::new();
+    for c in constraints.iter().filter(|c| !c.symbolic) {
+        for i in signals(c) {
+            *counts.entry(i).or_default() += 1;
+        }
+    }
+    let mut removed = vec![false; constraints.len()];
+    loop {
+        let mut changed = false;
+        for (x, c) in constraints.iter().enumerate() {
+            if c.symbolic || removed[x] || Some(x) == first_constraint {
+                continue;
+            }
+            let is_dead = c.c.iter().any(|(coef, i)| {
+                *coef != T::zero()
+                    && !protected.contains(i)
+                    && counts.get(i) == Some(&1)
+                    && !c.a.iter().chain(c.b.iter()).any(|(_, j)| j == i)
+            });
+            if is_dead {
+                removed[x] = true;
+                changed = true;
+                for i in signals(c) {
+                    *counts.get_mut(&i).unwrap() -= 1;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    // signals needed to satisfy the remaining constraints
+    let mut needed = protected.clone();
+    for (x, c) in constraints.iter().enumerate() {
+        if !c.symbolic && !removed[x] {
+            needed.extend(signals(c));
+        }
+    }
+    // walk backward so each symbolic constraint is visited
+    // after every constraint that may depend on it
+    for (x, c) in constraints.iter().enumerate().rev() {
+        if !c.symbolic {
+            continue;
+        }
+        if needed.contains(&c.out_i.unwrap()) {
+            needed.extend(signals(c));
+        } else {
+            removed[x] = true;
+        }
+    }
+    let mut remaining = needed.into_iter().collect::<Vec<_>>();
+    remaining.sort();
+    let new_index = remaining
+        .iter()
+        .enumerate()
+        .map(|(new, old)| (*old, new))
+        .collect::<HashMap<usize, usize>>();
+    let renumber = |lc: &Lc<T>| {
+        lc.iter()
+            .map(|(coef, i)| (coef.clone(), *new_index.get(i).unwrap()))
+            .collect::<Lc<T>>()
+    };
+    constraints
+        .iter()
+        .enumerate()
+        .filter(|(x, _)| !removed[*x])
+        .map(|(_, c)| R1csConstraint {
+            a: renumber(&c.a),
+            b: renumber(&c.b),
+            c: renumber(&c.c),
+            out_i: c.out_i.map(|i| *new_index.get(&i).unwrap()),
+            comment: c.comment.clone(),
+            symbolic: c.symbolic,
+            symbolic_op: c.symbolic_op.clone(),
+            assignment: c.assignment,
+        })
+        .collect()
+}
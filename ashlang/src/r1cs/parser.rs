@@ -1,12 +1,15 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::str::FromStr;
 
+use super::circom;
 use super::constraint::string_to_index;
 use super::constraint::R1csConstraint;
 use super::constraint::SymbolicOp;
 use crate::log;
 use anyhow::anyhow;
 use anyhow::Result;
+use pest::iterators::Pair;
 use pest::Parser;
 use pest_derive::Parser;
 use ring_math::PolynomialRingElement;
@@ -23,6 +26,9 @@ pub struct R1csParser<T: PolynomialRingElement> {
     pub arg_names: Vec<String>,
     pub return_name_index: HashMap<String, usize>,
     pub return_names: Vec<String>,
+    // internal signals of a function, allocated after the
+    // return values
+    pub local_name_index: HashMap<String, usize>,
     pub is_function: bool,
 }
 
@@ -47,6 +53,82 @@ impl<T: PolynomialRingElement> R1csParser<T> {
         signal_index_max + 1
     }
 
+    /// Return the number of new signals a call to this function
+    /// allocates. Return values come first, followed by any
+    /// internal signals.
+    pub fn new_signal_count(&self) -> usize {
+        // symbolic constraints may assign signals that are
+        // not otherwise constrained
+        let symbolic_count = self
+            .constraints
+            .iter()
+            .filter_map(|c| c.out_i)
+            .map(|i| i + 1)
+            .max()
+            .unwrap_or_default();
+        self.var_count()
+            .max(symbolic_count)
+            .max(self.arg_names.len() + self.return_names.len())
+            - self.arg_names.len()
+    }
+
+    /// Build a function from an iden3 `.r1cs` file compiled by circom.
+    ///
+    /// The public and private inputs of the circuit become arguments,
+    /// in wire order. The outputs of the circuit become return values.
+    pub fn from_circom(bytes: &[u8]) -> Result<Self> {
+        let circuit = circom::read_r1cs::<T::F>(bytes)?;
+        let constraints = circuit.witness_constraints()?;
+        let n_out = circuit.n_pub_out;
+        let n_in = circuit.n_pub_in + circuit.n_prv_in;
+        // order local signals as one, inputs, outputs, internal
+        let local = |wire: usize| {
+            if wire == 0 || wire > n_out + n_in {
+                wire
+            } else if wire > n_out {
+                wire - n_out
+            } else {
+                wire + n_in
+            }
+        };
+        let remap = |lc: &Vec<(T::F, usize)>| {
+            lc.iter()
+                .map(|(coef, i)| (coef.clone(), local(*i)))
+                .collect::<Vec<_>>()
+        };
+        let mut out = R1csParser {
+            constraints: constraints
+                .iter()
+                .map(|c| R1csConstraint {
+                    a: remap(&c.a),
+                    b: remap(&c.b),
+                    c: remap(&c.c),
+                    out_i: c.out_i.map(local),
+                    ..c.clone()
+                })
+                .collect(),
+            arg_name_index: HashMap::new(),
+            arg_names: vec![],
+            is_function: true,
+            return_names: vec![],
+            return_name_index: HashMap::new(),
+            local_name_index: HashMap::new(),
+        };
+        out.arg_name_index.insert("one".to_string(), 0);
+        out.arg_names.push("one".to_string());
+        for x in 0..n_in {
+            let name = format!("in{x}");
+            out.arg_name_index.insert(name.clone(), x + 1);
+            out.arg_names.push(name);
+        }
+        for x in 0..n_out {
+            let name = format!("out{x}");
+            out.return_name_index.insert(name.clone(), x + n_in + 1);
+            out.return_names.push(name);
+        }
+        Ok(out)
+    }
+
     pub fn new(source: &str) -> Result<Self> {
         let mut out = R1csParser {
             constraints: Vec::new(),
@@ -55,9 +137,13 @@ impl<T: PolynomialRingElement> R1csParser<T> {
             is_function: false,
             return_names: vec![],
             return_name_index: HashMap::new(),
+            local_name_index: HashMap::new(),
         };
         out.arg_name_index.insert("one".to_string(), 0);
         out.arg_names.push("one".to_string());
+        let mut gates = HashMap::new();
+        // the number of gates inlined, used to name internal signals
+        let mut calls = 0;
         let parsed = R1csPestParser::parse(Rule::program, source)?;
         for pair in parsed {
             match pair.as_rule() {
@@ -69,10 +155,9 @@ impl<T: PolynomialRingElement> R1csParser<T> {
                     for v in args_tuple {
                         let varname = v.as_str().to_string();
                         if out.arg_name_index.contains_key(&varname) {
-                            return Err(anyhow::anyhow!(
-                                "ar1cs parse error: duplicate arg name: {}",
-                                varname
-                            ));
+                            return log::error!(
+                                ParseError: &format!("ar1cs parse error: duplicate arg name: {varname}")
+                            );
                         }
                         out.arg_name_index
                             .insert(varname.clone(), out.arg_name_index.len());
@@ -84,16 +169,14 @@ impl<T: PolynomialRingElement> R1csParser<T> {
                         // varname
                         let varname = v.as_str();
                         if out.arg_name_index.contains_key(varname) {
-                            return Err(anyhow::anyhow!(
-                                "ar1cs parse error: return arg name is not unique: {}",
-                                varname
-                            ));
+                            return log::error!(
+                                ParseError: &format!("ar1cs parse error: return arg name is not unique: {varname}")
+                            );
                         }
                         if out.return_name_index.contains_key(varname) {
-                            return Err(anyhow::anyhow!(
-                                "ar1cs parse error: return arg name is not unique: {}",
-                                varname
-                            ));
+                            return log::error!(
+                                ParseError: &format!("ar1cs parse error: return arg name is not unique: {varname}")
+                            );
                         }
                         out.return_names.push(varname.to_string());
                         out.return_name_index.insert(
@@ -102,95 +185,165 @@ impl<T: PolynomialRingElement> R1csParser<T> {
                         );
                     }
                 }
-                Rule::constraint_line => {
-                    let mut pair = pair.into_inner();
-                    let a = pair.next().unwrap();
-                    let a = out.parse_inner(a)?;
-                    let b = pair.next().unwrap();
-                    let b = out.parse_inner(b)?;
-                    let c = pair.next().unwrap();
-                    let c = out.parse_inner(c)?;
-                    out.constraints.push(R1csConstraint::new(a, b, c, ""));
-                }
-                Rule::symbolic_line => {
-                    let mut pair = pair.into_inner();
-                    // println!("{:?}", pair);
-                    let o = pair.next().unwrap();
-                    let a = pair.next().unwrap();
-                    let a = out.parse_inner(a)?;
-                    let op = pair.next().unwrap();
-                    let op = SymbolicOp::from(op.as_str());
-                    let b = pair.next().unwrap();
-                    let b = out.parse_inner(b)?;
-                    let out_index;
-                    if out.is_function {
-                        if let Some(i) = out.return_name_index.get(o.as_str()) {
-                            out_index = *i;
-                        } else {
-                            return Err(anyhow!(
-                                "constraints can only be assigned to return values"
-                            ));
-                        }
-                    } else {
-                        out_index = string_to_index(o.as_str());
-                    }
-                    out.constraints.push(R1csConstraint::symbolic(
-                        out_index,
-                        a,
-                        b,
-                        op,
-                        "".to_string(),
-                    ));
-                }
-                Rule::comment => {
-                    let mut pair = pair.into_inner();
-                    let text = pair.next();
-                    if text.is_none() {
-                        continue;
-                    }
-                    let text = text.unwrap().as_str().to_string();
-                    if !out.constraints.is_empty() {
-                        out.constraints.last_mut().unwrap().comment = Some(text);
+                Rule::gate_def => {
+                    let (name, gate) = Gate::new(pair, &gates)?;
+                    if gates.contains_key(&name) {
+                        return log::error!(
+                            ParseError: &format!("ar1cs parse error: duplicate gate name: {name}")
+                        );
                     }
+                    gates.insert(name, gate);
                 }
-                Rule::comment_line => {}
                 Rule::EOI => {}
                 _ => {
-                    return Err(anyhow::anyhow!("{:?}", pair.as_rule()));
+                    if let Some(line) = Line::from_pair(pair) {
+                        out.push_line(line, &gates, &mut calls)?;
+                    }
                 }
             }
         }
         Ok(out)
     }
 
-    pub fn parse_inner(&mut self, p: pest::iterators::Pair<Rule>) -> Result<Vec<(T::F, usize)>> {
-        let mut pair = p.into_inner();
-        let mut out_terms = Vec::new();
-        while let Some(v) = pair.next() {
-            let coef = v.as_str();
-            let var_index = pair.next().unwrap().as_str();
-            if self.is_function {
-                // restrict the signals that may be accessed by literal
-                if !self.arg_name_index.contains_key(var_index)
-                    && !self.return_name_index.contains_key(var_index)
-                    && var_index != "0"
-                {
-                    return Err(anyhow!("cannot access signals by literal in ar1cs source"));
-                }
-                if let Some(v) = self.arg_name_index.get(var_index) {
-                    // if signal is a variable
-                    out_terms.push((T::F::from_str(coef)?, *v));
-                } else if let Some(v) = self.return_name_index.get(var_index) {
-                    out_terms.push((T::F::from_str(coef)?, *v));
-                } else {
-                    // if coef is a literal
-                    out_terms.push((T::F::from_str(coef)?, string_to_index(var_index)));
+    fn push_line(
+        &mut self,
+        line: Line,
+        gates: &HashMap<String, Gate>,
+        calls: &mut usize,
+    ) -> Result<()> {
+        match line {
+            Line::Constraint(a, b, c) => {
+                let a = self.resolve(&a)?;
+                let b = self.resolve(&b)?;
+                let c = self.resolve(&c)?;
+                self.constraints.push(R1csConstraint::new(a, b, c, ""));
+            }
+            Line::Symbolic(o, a, op, b) => {
+                let a = self.resolve(&a)?;
+                let b = self.resolve(&b)?;
+                let out_index = self.write_signal(&o)?;
+                self.constraints.push(R1csConstraint::symbolic(
+                    out_index,
+                    a,
+                    b,
+                    op,
+                    "".to_string(),
+                ));
+            }
+            Line::Call(outputs, name, inputs) => {
+                self.call_gate(&outputs, &name, &inputs, gates, calls)?;
+            }
+            Line::Comment(text) => {
+                if let Some(c) = self.constraints.last_mut() {
+                    c.comment = Some(text);
                 }
-            } else {
-                out_terms.push((T::F::from_str(coef)?, string_to_index(var_index)));
             }
         }
-        Ok(out_terms)
+        Ok(())
+    }
+
+    // inline the constraints of a gate, ports are replaced with
+    // the signals of the call and internal signals are allocated
+    fn call_gate(
+        &mut self,
+        outputs: &[String],
+        name: &str,
+        inputs: &[String],
+        gates: &HashMap<String, Gate>,
+        calls: &mut usize,
+    ) -> Result<()> {
+        if !self.is_function {
+            return log::error!(
+                ParseError: &format!("ar1cs parse error: gate {name} is called outside of a function"),
+                "gates can only be called in ar1cs functions, which begin with a header like `(a) -> (b)`"
+            );
+        }
+        let gate = Gate::get(gates, name, inputs.len(), outputs.len())?;
+        *calls += 1;
+        let call = *calls;
+        let ports = gate
+            .inputs
+            .iter()
+            .zip(inputs)
+            .chain(gate.outputs.iter().zip(outputs))
+            .map(|(port, signal)| (port.as_str(), signal.as_str()))
+            .collect::<HashMap<_, _>>();
+        let rename = |v: &str| match ports.get(v) {
+            Some(signal) => signal.to_string(),
+            None if v == "one" => v.to_string(),
+            // `.` is not allowed in source so internal signals
+            // are distinct from names in the caller
+            None => format!("{name}.{call}.{v}"),
+        };
+        for o in outputs {
+            if outputs.iter().filter(|v| *v == o).count() > 1 {
+                return log::error!(
+                    ParseError: &format!("ar1cs parse error: signal {o} is assigned more than once in a call to gate {name}")
+                );
+            }
+        }
+        // allocate every signal assigned by the gate so constraints
+        // may reference signals that are assigned later
+        for line in &gate.lines {
+            for v in line.writes() {
+                self.write_signal(&rename(v))?;
+            }
+        }
+        for line in &gate.lines {
+            self.push_line(line.rename(&rename), gates, calls)?;
+        }
+        Ok(())
+    }
+
+    // the (coefficient, signal index) terms of a linear combination
+    fn resolve(&self, terms: &NamedTerms) -> Result<Vec<(T::F, usize)>> {
+        terms
+            .iter()
+            .map(|(coef, name)| Ok((T::F::from_str(coef)?, self.read_signal(name)?)))
+            .collect()
+    }
+
+    // the index of a signal that is read
+    fn read_signal(&self, name: &str) -> Result<usize> {
+        if !self.is_function {
+            return Ok(string_to_index(name));
+        }
+        // restrict the signals that may be accessed by name
+        let index = self
+            .arg_name_index
+            .get(name)
+            .or_else(|| self.return_name_index.get(name))
+            .or_else(|| self.local_name_index.get(name));
+        match index {
+            Some(i) => Ok(*i),
+            None => log::error!(
+                ParseError: &format!("unknown signal in ar1cs function: {name}"),
+                "signals are arguments, return values, or assigned before they are read"
+            ),
+        }
+    }
+
+    // the index of a signal that is assigned, internal signals of
+    // a function are allocated after the return values
+    fn write_signal(&mut self, name: &str) -> Result<usize> {
+        if !self.is_function {
+            return Ok(string_to_index(name));
+        }
+        if self.arg_name_index.contains_key(name) {
+            return log::error!(
+                ParseError: "constraints can only be assigned to return values and internal signals"
+            );
+        }
+        if let Some(i) = self
+            .return_name_index
+            .get(name)
+            .or_else(|| self.local_name_index.get(name))
+        {
+            return Ok(*i);
+        }
+        let index = self.arg_names.len() + self.return_names.len() + self.local_name_index.len();
+        self.local_name_index.insert(name.to_string(), index);
+        Ok(index)
     }
 
     pub fn signals_as_args(
@@ -203,7 +356,7 @@ impl<T: PolynomialRingElement> R1csParser<T> {
         // push the 1 signal to the front of the arg list
         args.insert(0, 0);
         if args.len() != self.arg_names.len() {
-            return log::error!(&format!(
+            return log::error!(TypeError: &format!(
                 "error calling function, incorrect number of arguments, got {} expected {}",
                 args.len(),
                 self.arg_names.len()
@@ -217,14 +370,12 @@ impl<T: PolynomialRingElement> R1csParser<T> {
                 return Err(anyhow!("error mapping signal as arg"));
             }
         }
-        // map return values to calculated offsets
-        for x in 0..self.return_names.len() {
-            let local_index = self.return_name_index.get(&self.return_names[x]);
-            if let Some(local_index) = local_index {
-                signal_map.insert(*local_index, index_start + x);
-            } else {
-                return Err(anyhow!("error mapping signal as arg"));
-            }
+        // map return values and internal signals to calculated offsets
+        for local_index in self.arg_names.len()..self.arg_names.len() + self.new_signal_count() {
+            signal_map.insert(
+                local_index,
+                index_start + local_index - self.arg_names.len(),
+            );
         }
         self.constraints
             .iter()
@@ -256,3 +407,201 @@ impl<T: PolynomialRingElement> R1csParser<T> {
             .collect::<Result<Vec<_>>>()
     }
 }
+
+// (coefficient, signal name) terms of a linear combination
+type NamedTerms = Vec<(String, String)>;
+
+// a line of ar1cs source with signals referenced by name
+#[derive(Clone)]
+enum Line {
+    Constraint(NamedTerms, NamedTerms, NamedTerms),
+    Symbolic(String, NamedTerms, SymbolicOp, NamedTerms),
+    // outputs, gate name, inputs
+    Call(Vec<String>, String, Vec<String>),
+    Comment(String),
+}
+
+impl Line {
+    // lines that do not affect the constraints are None
+    fn from_pair(pair: Pair<Rule>) -> Option<Self> {
+        let rule = pair.as_rule();
+        let mut pair = pair.into_inner();
+        match rule {
+            Rule::constraint_line => {
+                let a = terms(pair.next()?);
+                let b = terms(pair.next()?);
+                let c = terms(pair.next()?);
+                Some(Line::Constraint(a, b, c))
+            }
+            Rule::symbolic_line => {
+                let o = pair.next()?.as_str().to_string();
+                let a = terms(pair.next()?);
+                let op = pair.next()?.as_str().parse().ok()?;
+                let b = terms(pair.next()?);
+                Some(Line::Symbolic(o, a, op, b))
+            }
+            Rule::gate_call => {
+                let outputs = names(pair.next()?);
+                let name = pair.next()?.as_str().to_string();
+                let inputs = names(pair.next()?);
+                Some(Line::Call(outputs, name, inputs))
+            }
+            Rule::comment => Some(Line::Comment(pair.next()?.as_str().to_string())),
+            _ => None,
+        }
+    }
+
+    // the signals read by the line
+    fn reads(&self) -> Vec<&String> {
+        match self {
+            Line::Constraint(a, b, c) => a.iter().chain(b).chain(c).map(|(_, v)| v).collect(),
+            Line::Symbolic(_, a, _, b) => a.iter().chain(b).map(|(_, v)| v).collect(),
+            Line::Call(_, _, inputs) => inputs.iter().collect(),
+            Line::Comment(_) => vec![],
+        }
+    }
+
+    // the signals assigned by the line
+    fn writes(&self) -> Vec<&String> {
+        match self {
+            Line::Symbolic(o, _, _, _) => vec![o],
+            Line::Call(outputs, _, _) => outputs.iter().collect(),
+            _ => vec![],
+        }
+    }
+
+    // a copy of the line with each signal renamed
+    fn rename(&self, f: &impl Fn(&str) -> String) -> Self {
+        let terms = |v: &NamedTerms| -> NamedTerms {
+            v.iter()
+                .map(|(coef, name)| (coef.clone(), f(name)))
+                .collect()
+        };
+        let names = |v: &[String]| -> Vec<String> { v.iter().map(|name| f(name)).collect() };
+        match self {
+            Line::Constraint(a, b, c) => Line::Constraint(terms(a), terms(b), terms(c)),
+            Line::Symbolic(o, a, op, b) => Line::Symbolic(f(o), terms(a), op.clone(), terms(b)),
+            Line::Call(outputs, name, inputs) => {
+                Line::Call(names(outputs), name.clone(), names(inputs))
+            }
+            Line::Comment(_) => self.clone(),
+        }
+    }
+}
+
+// a reusable group of constraints with named ports, the
+// constraints are inlined at each call
+#[derive(Clone)]
+struct Gate {
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    lines: Vec<Line>,
+}
+
+impl Gate {
+    fn new(pair: Pair<Rule>, gates: &HashMap<String, Gate>) -> Result<(String, Self)> {
+        let mut pair = pair.into_inner();
+        let name = pair.next().unwrap().as_str().to_string();
+        let inputs = names(pair.next().unwrap());
+        let outputs = names(pair.next().unwrap());
+        let gate = Self {
+            inputs,
+            outputs,
+            lines: pair.filter_map(Line::from_pair).collect(),
+        };
+        gate.check(&name, gates)?;
+        Ok((name, gate))
+    }
+
+    // a gate by name, checking the number of ports in a call
+    fn get<'a>(
+        gates: &'a HashMap<String, Gate>,
+        name: &str,
+        inputs: usize,
+        outputs: usize,
+    ) -> Result<&'a Gate> {
+        let gate = match gates.get(name) {
+            Some(gate) => gate,
+            None => {
+                return log::error!(
+                    ScopeError: &format!("unknown ar1cs gate: {name}"),
+                    "gates must be defined before they are called"
+                )
+            }
+        };
+        if gate.inputs.len() != inputs || gate.outputs.len() != outputs {
+            return log::error!(TypeError: &format!(
+                "gate {name} has {} inputs and {} outputs, called with {inputs} inputs and {outputs} outputs",
+                gate.inputs.len(),
+                gate.outputs.len()
+            ));
+        }
+        Ok(gate)
+    }
+
+    // signals other than ports are internal to the gate. Each
+    // signal must be assigned once, before it is used to assign
+    // other signals.
+    fn check(&self, name: &str, gates: &HashMap<String, Gate>) -> Result<()> {
+        let mut ports = HashSet::new();
+        for port in self.inputs.iter().chain(&self.outputs) {
+            if port == "one" || !ports.insert(port) {
+                return log::error!(
+                    ParseError: &format!("ar1cs parse error: port name of gate {name} is not unique: {port}")
+                );
+            }
+        }
+        // signals that are assigned anywhere in the gate
+        let mut written = self.inputs.iter().collect::<HashSet<_>>();
+        for line in &self.lines {
+            for v in line.writes() {
+                if v == "one" || !written.insert(v) {
+                    return log::error!(
+                        ParseError: &format!("ar1cs parse error: signal {v} in gate {name} is assigned more than once"),
+                        "inputs are assigned by the caller"
+                    );
+                }
+            }
+        }
+        for v in &self.outputs {
+            if !written.contains(v) {
+                return log::error!(
+                    ParseError: &format!("ar1cs parse error: gate {name} does not assign output {v}")
+                );
+            }
+        }
+        let mut assigned = self.inputs.iter().collect::<HashSet<_>>();
+        for line in &self.lines {
+            if let Line::Call(outputs, gate, inputs) = line {
+                Gate::get(gates, gate, inputs.len(), outputs.len())?;
+            }
+            // constraints are not used to calculate the witness
+            let known = match line {
+                Line::Constraint(_, _, _) => &written,
+                _ => &assigned,
+            };
+            for v in line.reads() {
+                if v != "one" && !known.contains(v) {
+                    return log::error!(
+                        ParseError: &format!("ar1cs parse error: signal {v} in gate {name} is read before it is assigned")
+                    );
+                }
+            }
+            assigned.extend(line.writes());
+        }
+        Ok(())
+    }
+}
+
+fn terms(pair: Pair<Rule>) -> NamedTerms {
+    let mut pair = pair.into_inner();
+    let mut out = vec![];
+    while let (Some(coef), Some(name)) = (pair.next(), pair.next()) {
+        out.push((coef.as_str().to_string(), name.as_str().to_string()));
+    }
+    out
+}
+
+fn names(pair: Pair<Rule>) -> Vec<String> {
+    pair.into_inner().map(|v| v.as_str().to_string()).collect()
+}
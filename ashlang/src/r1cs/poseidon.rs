@@ -0,0 +1,213 @@
+//! Poseidon parameters for the r1cs target.
+//!
+//! Round constants and the MDS matrix are generated from the
+//! Grain LFSR as described in the
+//! [Poseidon paper](https://eprint.iacr.org/2019/458.pdf), matching
+//! the reference implementation and circomlib in the alt_bn128 field.
+//! The MDS matrix is not checked for invariant subspace trails.
+use anyhow::Result;
+use scalarff::BigUint;
+use scalarff::FieldElement;
+
+use crate::log;
+
+/// The s-box exponent, x^5 is used in every supported field.
+pub const ALPHA: u64 = 5;
+
+/// The number of full rounds, split evenly before and
+/// after the partial rounds.
+pub const FULL_ROUNDS: usize = 8;
+
+// the number of partial rounds for 128 bit security with x^5
+// in a ~255 bit field, indexed by width - 2
+const PARTIAL_ROUNDS: [usize; 16] = [
+    56, 57, 56, 60, 60, 63, 64, 63, 60, 66, 60, 65, 70, 60, 64, 68,
+];
+
+/// The largest number of inputs that may be hashed at once.
+pub const MAX_INPUTS: usize = PARTIAL_ROUNDS.len();
+
+/// Poseidon parameters for a width `t` permutation.
+pub struct PoseidonParams<F: FieldElement> {
+    pub t: usize,
+    pub partial_rounds: usize,
+    // `t` constants for each round
+    pub round_constants: Vec<Vec<F>>,
+    pub mds: Vec<Vec<F>>,
+}
+
+impl<F: FieldElement> PoseidonParams<F> {
+    /// Generate the parameters for hashing `input_count` elements.
+    pub fn new(input_count: usize) -> Result<Self> {
+        if input_count == 0 || input_count > MAX_INPUTS {
+            return log::error!(
+                TypeError: &format!("poseidon accepts 1 to {MAX_INPUTS} inputs, received {input_count}")
+            );
+        }
+        let p = F::prime();
+        let p_minus_one = p.clone() - 1_u32;
+        // x^5 must be a permutation and x^3 must not be, round
+        // numbers are only known for large fields
+        if p_minus_one.clone() % 3_u32 != BigUint::from(0_u32)
+            || p_minus_one % ALPHA == BigUint::from(0_u32)
+            || p.bits() < 254
+        {
+            return log::error!(
+                ConfigError: &format!("poseidon is not supported in the {} field", F::name_str()),
+                "poseidon requires a field of at least 254 bits where x^5 is the smallest permutation"
+            );
+        }
+        let t = input_count + 1;
+        let partial_rounds = PARTIAL_ROUNDS[t - 2];
+        let n = p.bits() as usize;
+        let mut grain = Grain::new(n, t, FULL_ROUNDS, partial_rounds);
+
+        let mut round_constants = vec![];
+        for _ in 0..FULL_ROUNDS + partial_rounds {
+            let mut constants = vec![];
+            for _ in 0..t {
+                // sample until the value is in the field
+                let mut v = grain.next_uint(n);
+                while v >= p {
+                    v = grain.next_uint(n);
+                }
+                constants.push(F::from_biguint(&v));
+            }
+            round_constants.push(constants);
+        }
+
+        // a cauchy matrix from 2t distinct elements
+        let mds = loop {
+            let values = (0..2 * t)
+                .map(|_| F::from_biguint(&(grain.next_uint(n) % p.clone())))
+                .collect::<Vec<_>>();
+            let distinct = values
+                .iter()
+                .enumerate()
+                .all(|(i, v)| !values[..i].contains(v));
+            let (xs, ys) = values.split_at(t);
+            let nonzero = xs
+                .iter()
+                .all(|x| ys.iter().all(|y| x.clone() + y.clone() != F::zero()));
+            if distinct && nonzero {
+                break xs
+                    .iter()
+                    .map(|x| {
+                        ys.iter()
+                            .map(|y| F::one() / (x.clone() + y.clone()))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+            }
+        };
+
+        Ok(Self {
+            t,
+            partial_rounds,
+            round_constants,
+            mds,
+        })
+    }
+
+    /// Hash `t - 1` elements outside of a circuit. The result is
+    /// the same as the `std::poseidon` precompile.
+    pub fn hash(&self, inputs: &[F]) -> Result<F> {
+        if inputs.len() + 1 != self.t {
+            return log::error!(&format!(
+                "poseidon parameters hash {} inputs, received {}",
+                self.t - 1,
+                inputs.len()
+            ));
+        }
+        // the capacity element is 0
+        let mut state = vec![F::zero()];
+        state.extend(inputs.iter().cloned());
+        for round in 0..FULL_ROUNDS + self.partial_rounds {
+            for (v, c) in state.iter_mut().zip(&self.round_constants[round]) {
+                *v = v.clone() + c.clone();
+            }
+            let sbox_count = if self.is_full_round(round) { self.t } else { 1 };
+            for v in state.iter_mut().take(sbox_count) {
+                let v2 = v.clone() * v.clone();
+                *v = v2.clone() * v2 * v.clone();
+            }
+            state = self
+                .mds
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .zip(&state)
+                        .fold(F::zero(), |acc, (m, v)| acc + m.clone() * v.clone())
+                })
+                .collect();
+        }
+        Ok(state[0].clone())
+    }
+
+    /// Whether every element of the state passes through the
+    /// s-box in a round.
+    pub fn is_full_round(&self, round: usize) -> bool {
+        round < FULL_ROUNDS / 2 || round >= FULL_ROUNDS / 2 + self.partial_rounds
+    }
+}
+
+// the 80 bit self-shrinking Grain LFSR used to generate parameters
+struct Grain {
+    state: Vec<bool>,
+}
+
+impl Grain {
+    fn new(n: usize, t: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        let mut state = vec![];
+        // prime field, x^alpha s-box
+        push_bits(&mut state, 1, 2);
+        push_bits(&mut state, 0, 4);
+        push_bits(&mut state, n, 12);
+        push_bits(&mut state, t, 12);
+        push_bits(&mut state, full_rounds, 10);
+        push_bits(&mut state, partial_rounds, 10);
+        state.extend([true; 30]);
+        let mut out = Self { state };
+        for _ in 0..160 {
+            out.step();
+        }
+        out
+    }
+
+    fn step(&mut self) -> bool {
+        let s = &self.state;
+        let bit = s[62] ^ s[51] ^ s[38] ^ s[23] ^ s[13] ^ s[0];
+        self.state.remove(0);
+        self.state.push(bit);
+        bit
+    }
+
+    // output the second bit of each pair where the first bit is set
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let keep = self.step();
+            let bit = self.step();
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    // read `bits` bits as a big endian integer
+    fn next_uint(&mut self, bits: usize) -> BigUint {
+        let mut out = BigUint::from(0_u32);
+        for _ in 0..bits {
+            out <<= 1;
+            if self.next_bit() {
+                out += 1_u32;
+            }
+        }
+        out
+    }
+}
+
+fn push_bits(out: &mut Vec<bool>, v: usize, len: usize) {
+    for i in (0..len).rev() {
+        out.push((v >> i) & 1 == 1);
+    }
+}
@@ -0,0 +1,124 @@
+//! SHA-256 over bytes of constrained bits.
+use scalarff::FieldElement;
+
+use super::binary::BinaryCircuit;
+use super::binary::Bit;
+
+const K: [u64; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const H: [u64; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Hash a message of bytes, each byte is 8 little endian bits.
+/// Returns the 32 byte digest.
+pub fn hash<F: FieldElement>(
+    circuit: &mut BinaryCircuit<F>,
+    message: &[Vec<Bit<F>>],
+) -> Vec<Vec<Bit<F>>> {
+    // append a 1 bit, zeros, and the message length in bits
+    let len = message.len();
+    let mut padded = message.to_vec();
+    padded.push(BinaryCircuit::constant_bits(0x80, 8));
+    while padded.len() % 64 != 56 {
+        padded.push(BinaryCircuit::constant_bits(0, 8));
+    }
+    for byte in ((len as u64) * 8).to_be_bytes() {
+        padded.push(BinaryCircuit::constant_bits(byte.into(), 8));
+    }
+
+    let mut h = H
+        .iter()
+        .map(|v| BinaryCircuit::constant_bits(*v, 32))
+        .collect::<Vec<_>>();
+    for block in padded.chunks(64) {
+        // words are big endian
+        let mut w = block
+            .chunks(4)
+            .map(|bytes| bytes.iter().rev().flatten().cloned().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        for t in 16..64 {
+            let s0 = circuit.xor_words(&[
+                &rotr(&w[t - 15], 7),
+                &rotr(&w[t - 15], 18),
+                &shr(&w[t - 15], 3),
+            ]);
+            let s1 = circuit.xor_words(&[
+                &rotr(&w[t - 2], 17),
+                &rotr(&w[t - 2], 19),
+                &shr(&w[t - 2], 10),
+            ]);
+            let next = circuit.add_words(&[&s1, &w[t - 7], &s0, &w[t - 16]]);
+            w.push(next);
+        }
+
+        let mut v = h.clone();
+        for t in 0..64 {
+            let (a, b, c, d) = (&v[0], &v[1], &v[2], &v[3]);
+            let (e, f, g, hh) = (&v[4], &v[5], &v[6], &v[7]);
+            let s1 = circuit.xor_words(&[&rotr(e, 6), &rotr(e, 11), &rotr(e, 25)]);
+            let ch = (0..32)
+                .map(|i| {
+                    let ef = circuit.and(&e[i], &f[i]);
+                    let eg = circuit.and(&BinaryCircuit::not(&e[i]), &g[i]);
+                    circuit.xor(&ef, &eg)
+                })
+                .collect::<Vec<_>>();
+            let s0 = circuit.xor_words(&[&rotr(a, 2), &rotr(a, 13), &rotr(a, 22)]);
+            let maj = (0..32)
+                .map(|i| {
+                    let ab = circuit.and(&a[i], &b[i]);
+                    let ac = circuit.and(&a[i], &c[i]);
+                    let bc = circuit.and(&b[i], &c[i]);
+                    let out = circuit.xor(&ab, &ac);
+                    circuit.xor(&out, &bc)
+                })
+                .collect::<Vec<_>>();
+            let k = BinaryCircuit::constant_bits(K[t], 32);
+            let new_e = circuit.add_words(&[d, hh, &s1, &ch, &k, &w[t]]);
+            let new_a = circuit.add_words(&[hh, &s1, &ch, &k, &w[t], &s0, &maj]);
+            v = vec![
+                new_a,
+                v[0].clone(),
+                v[1].clone(),
+                v[2].clone(),
+                new_e,
+                v[4].clone(),
+                v[5].clone(),
+                v[6].clone(),
+            ];
+        }
+        h = h
+            .iter()
+            .zip(v.iter())
+            .map(|(x, y)| circuit.add_words(&[x, y]))
+            .collect();
+    }
+
+    h.iter()
+        .flat_map(|word| word.chunks(8).rev().map(|v| v.to_vec()).collect::<Vec<_>>())
+        .collect()
+}
+
+fn rotr<F: FieldElement>(x: &[Bit<F>], n: usize) -> Vec<Bit<F>> {
+    (0..x.len()).map(|i| x[(i + n) % x.len()].clone()).collect()
+}
+
+fn shr<F: FieldElement>(x: &[Bit<F>], n: usize) -> Vec<Bit<F>> {
+    (0..x.len())
+        .map(|i| {
+            x.get(i + n)
+                .cloned()
+                .unwrap_or_else(|| BinaryCircuit::constant(false))
+        })
+        .collect()
+}
@@ -0,0 +1,177 @@
+//! Order symbolic constraints for witness calculation.
+//!
+//! The compiler writes each symbolic constraint after the
+//! constraints assigning the signals it reads, but hand-written
+//! ar1cs may define signals in any order. `order` sorts the
+//! symbolic constraints by the signals they read so the witness
+//! can be solved one constraint at a time. Constraints that are
+//! already in evaluation order keep their order, and inputs are
+//! always read in the order they are declared.
+//!
+//! Signals that are read but never assigned, and constraints
+//! that read each other's signals, cannot be solved and are
+//! reported with the constraints that read them.
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+
+use anyhow::Result;
+use scalarff::FieldElement;
+
+use super::constraint::index_to_string;
+use super::constraint::R1csConstraint;
+use super::constraint::SymbolicOp;
+use crate::log;
+
+/// The indices of the symbolic constraints in an order where
+/// each constraint follows the constraints assigning the signals
+/// it reads. Constraints that are not symbolic are skipped.
+pub fn order<T: FieldElement>(constraints: &[R1csConstraint<T>]) -> Result<Vec<usize>> {
+    // signal index keyed to the constraint assigning it
+    let mut assigned_by: HashMap<usize, usize> = HashMap::new();
+    for (i, c) in constraints.iter().enumerate() {
+        if !c.symbolic || c.symbolic_op == Some(SymbolicOp::Output) {
+            continue;
+        }
+        let out_i = c.out_i.unwrap();
+        if out_i == 0 || assigned_by.insert(out_i, i).is_some() {
+            return log::error!(
+                InputError: &format!("variable {} already set", index_to_string(&out_i)),
+                &format!("constraint: {c}")
+            );
+        }
+    }
+
+    let mut unassigned = vec![];
+    let mut remaining: HashMap<usize, usize> = HashMap::new();
+    let mut dependents: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut ready = BinaryHeap::new();
+    for (i, c) in constraints.iter().enumerate().filter(|(_, c)| c.symbolic) {
+        let mut deps = 0;
+        for signal in reads(c) {
+            if signal == 0 {
+                continue;
+            }
+            match assigned_by.get(&signal) {
+                Some(j) => {
+                    dependents.entry(*j).or_default().push(i);
+                    deps += 1;
+                }
+                None => unassigned.push((signal, c)),
+            }
+        }
+        if deps == 0 {
+            ready.push(Reverse(i));
+        } else {
+            remaining.insert(i, deps);
+        }
+    }
+    if !unassigned.is_empty() {
+        let mut signals = unassigned.iter().map(|(v, _)| *v).collect::<Vec<_>>();
+        signals.sort();
+        signals.dedup();
+        let signals = signals.iter().map(index_to_string).collect::<Vec<_>>();
+        return log::error!(
+            InputError: &format!("signals are read but never assigned: {}", signals.join(", ")),
+            &unassigned
+                .iter()
+                .map(|(_, c)| format!("constraint: {c}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+
+    // the lowest ready constraint is solved first so constraints
+    // in evaluation order keep their order
+    let mut out = vec![];
+    while let Some(Reverse(i)) = ready.pop() {
+        out.push(i);
+        for j in dependents.remove(&i).unwrap_or_default() {
+            let deps = remaining.get_mut(&j).unwrap();
+            *deps -= 1;
+            if *deps == 0 {
+                remaining.remove(&j);
+                ready.push(Reverse(j));
+            }
+        }
+    }
+    if remaining.is_empty() {
+        return Ok(out);
+    }
+    let cycle = find_cycle(constraints, &assigned_by, &remaining);
+    let mut signals = cycle
+        .iter()
+        .map(|i| index_to_string(&constraints[*i].out_i.unwrap()))
+        .collect::<Vec<_>>();
+    signals.push(signals[0].clone());
+    log::error!(
+        InputError: &format!(
+            "symbolic constraints form a cycle, {} constraints cannot be solved: {}",
+            remaining.len(),
+            signals.join(" -> ")
+        ),
+        &cycle
+            .iter()
+            .map(|i| format!("constraint: {}", constraints[*i]))
+            .collect::<Vec<_>>()
+            .join("\n")
+    )
+}
+
+/// The signals read by a symbolic constraint, an output reads
+/// the signal it marks.
+pub fn reads<T: FieldElement>(c: &R1csConstraint<T>) -> Vec<usize> {
+    let mut out =
+        c.a.iter()
+            .chain(c.b.iter())
+            .map(|(_, i)| *i)
+            .collect::<Vec<_>>();
+    if c.symbolic_op == Some(SymbolicOp::Output) {
+        out.extend(c.out_i);
+    }
+    out
+}
+
+/// The signals marked by public input and output constraints, in
+/// the order they are declared.
+pub fn public_signals<T: FieldElement>(constraints: &[R1csConstraint<T>]) -> Vec<usize> {
+    constraints
+        .iter()
+        .filter(|c| {
+            c.symbolic
+                && matches!(
+                    c.symbolic_op,
+                    Some(SymbolicOp::PublicInput | SymbolicOp::Output)
+                )
+        })
+        .map(|c| c.out_i.unwrap())
+        .collect()
+}
+
+// each unsolved constraint reads a signal assigned by another
+// unsolved constraint, so following the reads from any of them
+// reaches a cycle
+fn find_cycle<T: FieldElement>(
+    constraints: &[R1csConstraint<T>],
+    assigned_by: &HashMap<usize, usize>,
+    remaining: &HashMap<usize, usize>,
+) -> Vec<usize> {
+    let mut path: Vec<usize> = vec![*remaining.keys().min().unwrap()];
+    loop {
+        let i = *path.last().unwrap();
+        let next = reads(&constraints[i])
+            .iter()
+            .filter_map(|v| assigned_by.get(v))
+            .copied()
+            .filter(|j| remaining.contains_key(j))
+            .min()
+            .unwrap();
+        if let Some(start) = path.iter().position(|v| *v == next) {
+            // the cycle in the order it is read
+            let mut cycle = path.split_off(start);
+            cycle.reverse();
+            return cycle;
+        }
+        path.push(next);
+    }
+}
@@ -0,0 +1,149 @@
+use ring_math::SparseVector;
+use scalarff::FieldElement;
+
+use super::constraint::R1csConstraint;
+
+/// A matrix of linear combinations, stored as a sparse vector
+/// for each row.
+///
+/// Row `i` holds the terms of a linear combination as (signal,
+/// coefficient) entries sorted by signal. Zero coefficients are
+/// not stored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SparseMatrix<T: FieldElement> {
+    pub columns: usize,
+    pub rows: Vec<SparseVector<T>>,
+}
+
+impl<T: FieldElement> SparseMatrix<T> {
+    /// Create a matrix with no rows and the specified number of
+    /// columns.
+    pub fn new(columns: usize) -> Self {
+        Self {
+            columns,
+            rows: vec![],
+        }
+    }
+
+    /// Append a linear combination as a row. Terms on the same
+    /// signal are summed.
+    pub fn push_row(&mut self, lc: &[(T, usize)]) {
+        if let Some((_, i)) = lc.iter().find(|(_, i)| *i >= self.columns) {
+            panic!(
+                "signal {i} is outside of a sparse matrix with {} columns",
+                self.columns
+            );
+        }
+        self.rows.push(lc_to_sparse(lc, self.columns));
+    }
+
+    /// The number of non-zero entries.
+    pub fn nnz(&self) -> usize {
+        self.rows.iter().map(|row| row.nnz()).sum()
+    }
+
+    /// Each non-zero entry as (row, column, coefficient).
+    pub fn triplets(&self) -> impl Iterator<Item = (usize, usize, &T)> {
+        self.rows
+            .iter()
+            .enumerate()
+            .flat_map(|(row, v)| v.iter().map(move |(i, v)| (row, i, v)))
+    }
+
+    /// A copy of the matrix with each column moved using a
+    /// function.
+    pub fn map_columns(&self, f: impl Fn(usize) -> usize) -> Self {
+        let mut out = Self::new(self.columns);
+        for row in &self.rows {
+            out.push_row(
+                &row.iter()
+                    .map(|(i, v)| (v.clone(), f(i)))
+                    .collect::<Vec<_>>(),
+            );
+        }
+        out
+    }
+
+    /// Multiply the matrix by a witness.
+    pub fn mul_witness(&self, witness: &[T]) -> Vec<T> {
+        self.rows
+            .iter()
+            .map(|row| {
+                row.iter().fold(T::zero(), |acc, (i, v)| {
+                    acc + v.clone() * witness[i].clone()
+                })
+            })
+            .collect()
+    }
+}
+
+/// Convert a linear combination of (coefficient, signal) terms
+/// to a sparse vector with `len` signals.
+pub fn lc_to_sparse<T: FieldElement>(lc: &[(T, usize)], len: usize) -> SparseVector<T> {
+    SparseVector::from_entries(len, lc.iter().map(|(v, i)| (*i, v.clone())).collect())
+}
+
+/// Convert a sparse vector to a linear combination of
+/// (coefficient, signal) terms.
+#[allow(dead_code)]
+pub fn sparse_to_lc<T: FieldElement>(v: &SparseVector<T>) -> Vec<(T, usize)> {
+    v.iter().map(|(i, v)| (v.clone(), i)).collect()
+}
+
+/// The `A`, `B`, and `C` matrices of a rank 1 constraint system.
+/// A witness `w` satisfies the system if `Aw * Bw = Cw` element
+/// wise.
+///
+/// Each constraint is a row and each signal is a column. Wide
+/// circuits reference few signals per constraint, so only the
+/// terms of each constraint are stored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct R1csMatrices<T: FieldElement> {
+    pub a: SparseMatrix<T>,
+    pub b: SparseMatrix<T>,
+    pub c: SparseMatrix<T>,
+}
+
+impl<T: FieldElement> R1csMatrices<T> {
+    /// Build matrices from the constraints of an ar1cs program.
+    /// Symbolic constraints are skipped.
+    pub fn new(constraints: &[R1csConstraint<T>], signal_count: usize) -> Self {
+        let mut out = Self {
+            a: SparseMatrix::new(signal_count),
+            b: SparseMatrix::new(signal_count),
+            c: SparseMatrix::new(signal_count),
+        };
+        for c in constraints.iter().filter(|c| !c.symbolic) {
+            out.a.push_row(&c.a);
+            out.b.push_row(&c.b);
+            out.c.push_row(&c.c);
+        }
+        out
+    }
+
+    /// The number of constraints.
+    pub fn rows(&self) -> usize {
+        self.a.rows.len()
+    }
+
+    /// A copy of the matrices with each signal moved using a
+    /// function.
+    pub fn map_columns(&self, f: impl Fn(usize) -> usize) -> Self {
+        Self {
+            a: self.a.map_columns(&f),
+            b: self.b.map_columns(&f),
+            c: self.c.map_columns(&f),
+        }
+    }
+
+    /// The index of the first constraint not satisfied by a
+    /// witness, if any.
+    pub fn unsatisfied(&self, witness: &[T]) -> Option<usize> {
+        let a = self.a.mul_witness(witness);
+        let b = self.b.mul_witness(witness);
+        let c = self.c.mul_witness(witness);
+        (0..self.rows()).find(|i| a[*i].clone() * b[*i].clone() != c[*i])
+    }
+}
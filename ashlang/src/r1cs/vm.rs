@@ -3,15 +3,49 @@ use std::collections::HashMap;
 use anyhow::Result;
 use ring_math::Matrix;
 use ring_math::PolynomialRingElement;
+use scalarff::BigUint;
 use scalarff::FieldElement;
 
+use crate::builtin;
 use crate::compiler::CompilerState;
+use crate::consteval::ConstEval;
+use crate::fieldcheck::StaticValue;
+use crate::layout;
 use crate::log;
 use crate::parser::AstNode;
+use crate::parser::BitOp;
+use crate::parser::BoolOp;
 use crate::parser::Expr;
+use crate::parser::IntType;
 use crate::parser::NumOp;
+use crate::parser::SourceLocation;
+use crate::parser::TableDef;
+use crate::parser::VarType;
+use crate::r1cs::binary::add_term;
+use crate::r1cs::binary::BinaryCircuit;
+use crate::r1cs::cache::CachedCall;
+use crate::r1cs::cache::CallSignals;
 use crate::r1cs::constraint::R1csConstraint;
 use crate::r1cs::constraint::SymbolicOp;
+use crate::r1cs::constraint::ASSERT_COMMENT;
+use crate::r1cs::eddsa::EdwardsCurve;
+use crate::r1cs::extension;
+use crate::r1cs::ipa::IpaParams;
+use crate::r1cs::ipa::IpaSignals;
+use crate::r1cs::ipa::MAX_ROUNDS;
+use crate::r1cs::keccak;
+use crate::r1cs::lookup::Lookup;
+use crate::r1cs::lookup::LookupCircuit;
+use crate::r1cs::lookup::LookupTable;
+use crate::r1cs::parser::R1csParser;
+use crate::r1cs::poseidon::PoseidonParams;
+use crate::r1cs::poseidon::FULL_ROUNDS;
+use crate::r1cs::sha256;
+
+// number of bits an operand of an ordering
+// comparison may use, matches the u32 comparisons
+// in the tasm target
+const COMPARISON_BITS: usize = 32;
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum VarLocation {
@@ -41,12 +75,19 @@ where
     pub var_index: usize,
     // local scope name keyed to global variable index
     pub vars: HashMap<String, Var<T>>,
+    // variables declared with an integer type
+    pub int_types: HashMap<String, IntType>,
     pub compiler_state: &'a mut CompilerState<T>,
     // a, b, c
     pub constraints: Vec<R1csConstraint<T::F>>,
     pub args: Vec<Var<T>>,
     pub return_val: Option<Var<T>>,
     pub name: String,
+    // location of the statement being evaluated
+    pub location: Option<SourceLocation>,
+    // whether the statement being evaluated is in a branch with
+    // a signal condition, see `eval_branches`
+    pub conditional: bool,
 }
 
 impl<'a, T: PolynomialRingElement> VM<'a, T>
@@ -69,10 +110,13 @@ where
             name: "entrypoint".to_string(),
             var_index: 1,
             vars: HashMap::new(),
+            int_types: HashMap::new(),
             compiler_state,
             constraints,
             args: Vec::new(),
             return_val: None,
+            location: None,
+            conditional: false,
         }
     }
 
@@ -80,22 +124,42 @@ where
         VM {
             var_index: vm.var_index,
             vars: HashMap::new(),
+            int_types: HashMap::new(),
             compiler_state: vm.compiler_state,
             constraints: Vec::new(),
             args,
             return_val: None,
             name: name.to_string(),
+            location: None,
+            conditional: vm.conditional,
         }
     }
 
     pub fn eval_ast(&mut self, ast: Vec<AstNode>) -> Result<()> {
+        let outer_location = self.location.clone();
+        let out = self
+            .eval_statements(ast)
+            .map_err(|e| log::locate(e, &self.location));
+        self.location = outer_location;
+        out
+    }
+
+    fn eval_statements(&mut self, ast: Vec<AstNode>) -> Result<()> {
+        let mut statement_start = None;
         for v in ast {
             match v {
+                AstNode::Location(location) => {
+                    self.profile_statement(statement_start);
+                    statement_start = self.profile_start();
+                    self.location = Some(location);
+                }
+                // every call is inlined in r1cs
+                AstNode::Inline(_) => {}
                 AstNode::Stmt(name, is_let, expr) => {
                     if is_let && self.vars.contains_key(&name) {
-                        return log::error!(&format!("variable already defined: {name}"));
+                        return log::error!(ScopeError: &format!("variable already defined: {name}"));
                     } else if !is_let && !self.vars.contains_key(&name) {
-                        return log::error!(&format!("variable does not exist in scope: {name}"));
+                        return log::error!(ScopeError: &format!("variable does not exist in scope: {name}"));
                     }
                     if is_let {
                         self.compiler_state
@@ -106,16 +170,46 @@ where
                             .messages
                             .insert(0, format!("re-assign {name}"));
                     }
+                    // integer operations are range checked when evaluated
+                    let expr_type = expr.int_type(&|name| self.int_types.get(name).copied())?;
                     let v = self.eval(&expr)?;
                     if v.location == VarLocation::Constraint {
                         // if we get a constrained variable from the
                         // evaluation we simply store that as a named variable
-                        self.vars.insert(name, v);
+                        self.vars.insert(name.clone(), v);
                     } else {
                         // if we get a static variable from the evaluation
                         // we constraint the assigment into a new signal
+                        self.trace_static(&name, &v.value)?;
                         let new_var = self.static_to_constraint(&v.value)?;
-                        self.vars.insert(name, new_var);
+                        self.vars.insert(name.clone(), new_var);
+                    }
+                    self.name_signals(&name);
+                    if is_let {
+                        self.int_types.remove(&name);
+                    } else if let Some(int_type) = self.int_types.get(&name).copied() {
+                        if expr_type != Some(int_type) {
+                            let v = self.vars.get(&name).unwrap().clone();
+                            self.assert_int(&v, int_type)?;
+                        }
+                    }
+                }
+                AstNode::TypedDef(name, var_type, expr) => {
+                    let expr_type = expr.int_type(&|name| self.int_types.get(name).copied())?;
+                    self.eval_statements(vec![AstNode::Stmt(name.clone(), true, expr)])?;
+                    let v = self.vars.get(&name).unwrap().clone();
+                    if !var_type.matches(&v.value.dimensions) {
+                        return log::error!(TypeError: &format!(
+                            "variable \"{name}\" is declared as {} but assigned a value of dimensions {:?}",
+                            var_type.name(),
+                            v.value.dimensions
+                        ));
+                    }
+                    if let VarType::Int(int_type) = var_type {
+                        if expr_type != Some(int_type) {
+                            self.assert_int(&v, int_type)?;
+                        }
+                        self.int_types.insert(name, int_type);
                     }
                 }
                 AstNode::FnVar(names) => {
@@ -123,11 +217,43 @@ where
                         let name = v;
                         if self.vars.contains_key(name) {
                             return log::error!(
-                                &format!("variable already defined: {name}"),
+                                ScopeError: &format!("variable already defined: {name}"),
                                 "attempting to define variable in function header"
                             );
                         }
                         self.vars.insert(name.clone(), self.args[i].clone());
+                        self.name_signals(name);
+                    }
+                }
+                AstNode::FnDims(params) => {
+                    let dimensions = params
+                        .iter()
+                        .map(|(name, _)| {
+                            self.vars
+                                .get(name)
+                                .map(|v| v.value.dimensions.clone())
+                                .unwrap_or_default()
+                        })
+                        .collect::<Vec<_>>();
+                    for (name, len) in layout::dim_params(&params, &dimensions)? {
+                        if self.vars.contains_key(&name) {
+                            return log::error!(ScopeError: &format!("variable already defined: {name}"));
+                        }
+                        let value = ConstEval::new(
+                            &*self.compiler_state,
+                            self.static_values(),
+                            self.int_types.clone(),
+                        )
+                        .eval(&Expr::Lit(len.to_string()))?;
+                        self.trace_static(&name, &value)?;
+                        self.vars.insert(
+                            name,
+                            Var {
+                                index: None,
+                                location: VarLocation::Static,
+                                value,
+                            },
+                        );
                     }
                 }
                 AstNode::Rtrn(expr) => {
@@ -141,17 +267,45 @@ where
                             "you likely have called return more than once"
                         );
                     }
-                    self.return_val = Some(self.eval(&expr)?);
+                    let v = self.eval(&expr)?;
+                    if let Some(return_type) = self.compiler_state.fn_return_type.get(&self.name) {
+                        return_type.check(&self.name, &v.value.dimensions, |name| {
+                            let var = self.vars.get(name)?;
+                            match var.location {
+                                VarLocation::Static if var.value.values.len() == 1 => {
+                                    var.value.values[0]
+                                        .to_scalar()
+                                        .ok()?
+                                        .to_biguint()
+                                        .to_string()
+                                        .parse::<usize>()
+                                        .ok()
+                                }
+                                _ => None,
+                            }
+                        })?;
+                    }
+                    self.return_val = Some(v);
                 }
                 AstNode::StaticDef(name, expr) => {
                     if self.vars.contains_key(&name) {
-                        return log::error!("variable already defined: {name}");
-                    }
-                    let v = self.eval(&expr)?;
-                    if v.location != VarLocation::Static {
-                        return log::error!("static variable cannot be assigned from signal");
+                        return log::error!(ScopeError: "variable already defined: {name}");
                     }
-                    self.vars.insert(name, v);
+                    let value = ConstEval::new(
+                        &*self.compiler_state,
+                        self.static_values(),
+                        self.int_types.clone(),
+                    )
+                    .eval(&expr)?;
+                    self.trace_static(&name, &value)?;
+                    self.vars.insert(
+                        name,
+                        Var {
+                            index: None,
+                            location: VarLocation::Static,
+                            value,
+                        },
+                    );
                 }
                 AstNode::ExprUnassigned(expr) => {
                     self.compiler_state
@@ -159,20 +313,69 @@ where
                         .insert(0, "unassigned expression".to_string());
                     self.eval(&expr)?;
                 }
+                AstNode::Assert(expr, message) => {
+                    self.compiler_state
+                        .messages
+                        .insert(0, format!("assert: {message}"));
+                    let v = match expr {
+                        Expr::BoolOp { .. } => self.eval_condition(&expr)?,
+                        _ => self.eval(&expr)?,
+                    };
+                    if v.value.len() != 1 {
+                        return log::error!(TypeError: "assertion must be a scalar, received a vector/matrix");
+                    }
+                    if v.location == VarLocation::Static {
+                        if v.value.values[0] != T::one() {
+                            self.static_assert_failed(&format!("assertion failed: {message}"))?;
+                        }
+                    } else {
+                        // v * 1 = 1
+                        self.constraints.push(R1csConstraint::new(
+                            vec![(T::F::one(), v.index.unwrap())],
+                            vec![(T::F::one(), 0)],
+                            vec![(T::F::one(), 0)],
+                            &format!("{ASSERT_COMMENT}{message}"),
+                        ));
+                    }
+                }
+                AstNode::Lookup(table, entries) => {
+                    self.compiler_state
+                        .messages
+                        .insert(0, format!("lookup in {}", table.name));
+                    self.lookup(&table, &entries)?;
+                }
+                AstNode::Ar1cs(args, returns, source) => {
+                    self.compiler_state
+                        .messages
+                        .insert(0, "ar1cs block".to_string());
+                    self.ar1cs_block(&args, &returns, &source)?;
+                }
+                AstNode::Unconstrained(returns, body) => {
+                    self.compiler_state
+                        .messages
+                        .insert(0, "unconstrained block".to_string());
+                    self.unconstrained_block(&returns, body)?;
+                }
+                AstNode::Asm(_, _, _) => {
+                    return log::error!(
+                        ConfigError: "asm blocks are only supported in the tasm target",
+                        "write the block in ash or ar1cs to compile it for r1cs"
+                    );
+                }
                 AstNode::Loop(expr, body) => {
                     self.compiler_state
                         .messages
                         .insert(0, "loop condition".to_string());
                     let v = self.eval(&expr)?;
                     if v.location != VarLocation::Static {
-                        return log::error!("loop condition must be static variable");
+                        return log::error!(TypeError: "loop condition must be static variable");
                     }
                     if v.value.is_empty() {
-                        return log::error!("loop condition is an empty matrix");
+                        return log::error!(TypeError: "loop condition is an empty matrix");
                     }
                     if v.value.len() > 1 {
                         return log::error!(
-                            "loop condition must be a scalar, received a vector/matrix"
+                            TypeError: "loop condition must be a scalar, received a vector/matrix"
                         );
                     }
                     // track the old variables, delete any variables
@@ -186,102 +389,2032 @@ where
                             .insert(0, format!("loop iteration {i}"));
                         self.eval_ast(body.clone())?;
                         i += 1_u32;
-                        let current_vars = self.vars.clone();
-                        for k in current_vars.keys() {
-                            if !old_vars.contains_key(k) {
-                                self.vars.remove(k);
-                            }
+                        self.drop_block_vars(&old_vars);
+                    }
+                }
+                AstNode::If(expr, if_block, else_block) => {
+                    self.compiler_state
+                        .messages
+                        .insert(0, "if condition".to_string());
+                    let condition = self.eval_condition(&expr)?;
+                    if condition.location == VarLocation::Static {
+                        // the branch can be selected at compile time
+                        let block = if condition.value.values[0] == T::one() {
+                            if_block
+                        } else {
+                            else_block
+                        };
+                        let old_vars = self.vars.clone();
+                        self.eval_ast(block)?;
+                        self.drop_block_vars(&old_vars);
+                    } else {
+                        self.eval_branches(condition.index.unwrap(), if_block, else_block)?;
+                    }
+                }
+                AstNode::PubInput(name) => {
+                    self.declare_input(name, SymbolicOp::PublicInput, "pub input")?;
+                }
+                AstNode::SecretInput(name) => {
+                    self.declare_input(name, SymbolicOp::Input, "secret input")?;
+                }
+                AstNode::PubOutput(name) => {
+                    self.compiler_state
+                        .messages
+                        .insert(0, format!("pub output {name}"));
+                    let v = match self.vars.get(&name) {
+                        Some(v) => v.clone(),
+                        None => {
+                            return log::error!(ScopeError: &format!(
+                                "variable does not exist in scope: {name}"
+                            ))
+                        }
+                    };
+                    if v.value.len() != 1 {
+                        return log::error!(TypeError: &format!("public output \"{name}\" must be a scalar"));
+                    }
+                    let v = if v.location == VarLocation::Static {
+                        self.static_to_constraint(&v.value)?
+                    } else {
+                        v
+                    };
+                    self.constraints.push(R1csConstraint::symbolic(
+                        v.index.unwrap(),
+                        vec![(T::F::one(), 0)],
+                        vec![(T::F::one(), 0)],
+                        SymbolicOp::Output,
+                        format!("pub output {name}"),
+                    ));
+                }
+                AstNode::EmptyVecDef(name, dimensions) => {
+                    if self.vars.contains_key(&name) {
+                        return log::error!(ScopeError: &format!("variable already defined: {name}"));
+                    }
+                    // elements are static zeros until they are assigned
+                    let len = dimensions.iter().product();
+                    self.vars.insert(
+                        name,
+                        Var {
+                            index: None,
+                            location: VarLocation::Static,
+                            value: Matrix {
+                                dimensions,
+                                values: vec![T::zero(); len],
+                            },
+                        },
+                    );
+                }
+                AstNode::AssignVec(name, indices, expr) => {
+                    self.compiler_state
+                        .messages
+                        .insert(0, format!("assign {name}[]"));
+                    let target = match self.vars.get(&name) {
+                        Some(v) => v.clone(),
+                        None => {
+                            return log::error!(ScopeError: &format!("variable does not exist in scope: {name}"))
+                        }
+                    };
+                    let indices = self.eval_static_indices(&name, &indices)?;
+                    layout::offset(&name, &target.value.dimensions, &indices)?;
+                    let (slot, offset) = target.value.retrieve_indices(&indices);
+                    let v = self.eval(&expr)?;
+                    if v.value.dimensions != slot.dimensions {
+                        return log::error!(TypeError: &format!(
+                            "cannot assign a value of dimensions {:?} to {name}{indices:?}, expected dimensions {:?}",
+                            v.value.dimensions,
+                            slot.dimensions
+                        ));
+                    }
+                    let new_var = self.assign_indices(&target, offset, &v)?;
+                    self.vars.insert(name.clone(), new_var);
+                    self.name_signals(&name);
+                }
+                _ => {
+                    return log::error!(&format!("ast node not supported for r1cs: {:?}", v));
+                }
+            }
+        }
+        self.profile_statement(statement_start);
+        Ok(())
+    }
+
+    // the number of constraints created and attributed to a
+    // source line when a statement begins
+    fn profile_start(&self) -> Option<(usize, usize)> {
+        self.compiler_state
+            .profile
+            .as_ref()
+            .map(|profile| (self.constraints.len(), profile.total()))
+    }
+
+    // attribute the constraints created by a statement to its
+    // source line, excluding those attributed to the lines of
+    // the functions it calls
+    fn profile_statement(&mut self, start: Option<(usize, usize)>) {
+        if let (Some((len, total)), Some(location), Some(profile)) =
+            (start, &self.location, &mut self.compiler_state.profile)
+        {
+            let created = self.constraints.len().saturating_sub(len);
+            let nested = profile.total() - total;
+            profile.record(location, created.saturating_sub(nested));
+        }
+    }
+
+    /// Serialization to/from AST representation
+    pub fn build_var_from_ast_vec(&mut self, expr: &Expr) -> Result<(Vec<usize>, Vec<T>)> {
+        let (dimensions, values) = layout::vec_literal(expr);
+        let values = values
+            .iter()
+            .map(|v| T::from_str(v))
+            .collect::<Result<_>>()?;
+        Ok((dimensions, values))
+    }
+
+    /// Record a static value assigned to a variable if the
+    /// compiler is tracing statics.
+    fn trace_static(&mut self, name: &str, value: &Matrix<T>) -> Result<()> {
+        if self.compiler_state.static_trace.is_none() {
+            return Ok(());
+        }
+        let value = StaticValue {
+            name: name.to_string(),
+            location: self.location.clone(),
+            values: value
+                .values
+                .iter()
+                .map(|v| Ok(v.to_scalar()?.to_biguint()))
+                .collect::<Result<Vec<_>>>()?,
+        };
+        if let Some(trace) = &mut self.compiler_state.static_trace {
+            trace.push(value);
+        }
+        Ok(())
+    }
+
+    /// Take a static variable and constrain it's current value
+    /// into a signal or set of signals
+    fn static_to_constraint(&mut self, matrix: &Matrix<T>) -> Result<Var<T>> {
+        let index = self.var_index;
+        for i in 0..matrix.len() {
+            // constrain each entry in the vector literal
+            // (v_i*one * 1*one) - v*one = 0
+            self.constraints.push(
+                R1csConstraint::new(
+                    vec![(T::F::one(), self.var_index + i)],
+                    vec![(T::F::one(), 0)],
+                    vec![(matrix.values[i].to_scalar()?, 0)],
+                    &format!(
+                        "scalar literal ({}) to signal index ({}) (member of vector)",
+                        matrix.values[i], i,
+                    ),
+                )
+                .as_assignment(),
+            );
+            self.constraints.push(R1csConstraint::symbolic(
+                self.var_index + i,
+                vec![(T::F::one(), 0)],
+                vec![(matrix.values[i].to_scalar()?, 0)],
+                SymbolicOp::Mul,
+                format!(
+                    "scalar literal ({}) to signal index {} (member of vector)",
+                    matrix.values[i], i,
+                ),
+            ));
+        }
+        self.var_index += matrix.len();
+        Ok(Var {
+            index: Some(index),
+            location: VarLocation::Constraint,
+            value: matrix.clone(),
+        })
+    }
+
+    /// Replace the elements of a variable starting at `offset`.
+    /// Signals are immutable, so if either side is a signal every
+    /// element is copied into a new contiguous set of signals.
+    fn assign_indices(&mut self, target: &Var<T>, offset: usize, v: &Var<T>) -> Result<Var<T>> {
+        let mut value = target.value.clone();
+        value
+            .values
+            .splice(offset..offset + v.value.len(), v.value.values.clone());
+        if target.location == VarLocation::Static && v.location == VarLocation::Static {
+            return Ok(Var {
+                index: None,
+                location: VarLocation::Static,
+                value,
+            });
+        }
+        let index = self.var_index;
+        self.var_index += value.len();
+        for x in 0..value.len() {
+            let ovi = index + x;
+            // each element comes from either the assigned value
+            // or the previous value of the variable
+            let (source, i) = if x >= offset && x < offset + v.value.len() {
+                (v, x - offset)
+            } else {
+                (target, x)
+            };
+            let lc = match source.index {
+                Some(source_index) => vec![(T::F::one(), source_index + i)],
+                None => vec![(source.value.values[i].to_scalar()?, 0)],
+            };
+            self.constraints.append(&mut vec![
+                R1csConstraint::new(
+                    lc.clone(),
+                    vec![(T::F::one(), 0)],
+                    vec![(T::F::one(), ovi)],
+                    &format!("vector assignment into {ovi}"),
+                )
+                .as_assignment(),
+                R1csConstraint::symbolic(
+                    ovi,
+                    lc,
+                    vec![(T::F::one(), 0)],
+                    SymbolicOp::Mul,
+                    self.compiler_state.messages[0].clone(),
+                ),
+            ]);
+        }
+        Ok(Var {
+            index: Some(index),
+            location: VarLocation::Constraint,
+            value,
+        })
+    }
+
+    /// Remove variables that were created after `old_vars` was
+    /// captured. Used to end the scope of a block.
+    fn drop_block_vars(&mut self, old_vars: &HashMap<String, Var<T>>) {
+        self.vars.retain(|k, _| old_vars.contains_key(k));
+        self.int_types.retain(|k, _| old_vars.contains_key(k));
+    }
+
+    /// Evaluate both branches of a conditional whose condition is
+    /// a signal. Constraints of each branch are only enforced if
+    /// the branch is taken, see `gate_constraints`. Variables of
+    /// the enclosing scope that are re-assigned are selected based
+    /// on the condition signal.
+    fn eval_branches(
+        &mut self,
+        condition_index: usize,
+        if_block: Vec<AstNode>,
+        else_block: Vec<AstNode>,
+    ) -> Result<()> {
+        let has_returned = self.return_val.is_some();
+        let conditional = self.conditional;
+        self.conditional = true;
+        let old_vars = self.vars.clone();
+        let start = self.constraints.len();
+        self.eval_ast(if_block)?;
+        self.gate_constraints(start, vec![(T::F::one(), condition_index)])?;
+        self.drop_block_vars(&old_vars);
+        let if_vars = std::mem::replace(&mut self.vars, old_vars.clone());
+        let start = self.constraints.len();
+        self.eval_ast(else_block)?;
+        self.gate_constraints(
+            start,
+            vec![(T::F::one(), 0), (-T::F::one(), condition_index)],
+        )?;
+        self.drop_block_vars(&old_vars);
+        let else_vars = std::mem::replace(&mut self.vars, old_vars.clone());
+        self.conditional = conditional;
+        if self.return_val.is_some() != has_returned {
+            return log::error!(
+                "cannot return from a conditional block with a signal condition",
+                "both branches are evaluated in the r1cs target, move the return statement after the conditional"
+            );
+        }
+        // sort the names so signals are allocated in a
+        // deterministic order
+        let mut names = old_vars.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            let if_var = if_vars.get(&name).unwrap();
+            let else_var = else_vars.get(&name).unwrap();
+            if if_var == else_var {
+                self.vars.insert(name, if_var.clone());
+                continue;
+            }
+            self.compiler_state
+                .messages
+                .insert(0, format!("conditional assignment of {name}"));
+            let selected = self.select(condition_index, if_var, else_var)?;
+            self.vars.insert(name.clone(), selected);
+            self.name_signals(&name);
+        }
+        Ok(())
+    }
+
+    /// Only enforce the constraints added since `start` if the
+    /// selector, a linear combination that is 0 or 1, is 1.
+    ///
+    /// Constraints marked as assignments when they were emitted
+    /// are satisfied by the witness in both branches and are kept.
+    /// Other constraints are checks, e.g. assertions and range
+    /// checks, and are multiplied by the selector:
+    ///
+    /// selector * (a * b - c) = 0
+    ///
+    /// If neither `a` nor `b` is constant the product is assigned
+    /// to a new signal first. The witness of a branch that is not
+    /// taken must still be calculated, so inversions map 0 to 0.
+    fn gate_constraints(&mut self, start: usize, selector: Vec<(T::F, usize)>) -> Result<()> {
+        let branch = self.constraints.split_off(start);
+        let constant = |lc: &[(T::F, usize)]| {
+            lc.iter()
+                .all(|(_, i)| *i == 0)
+                .then(|| lc.iter().fold(T::F::zero(), |acc, (v, _)| acc + v.clone()))
+        };
+        let scale = |lc: &[(T::F, usize)], k: &T::F| {
+            lc.iter()
+                .map(|(v, i)| (v.clone() * k.clone(), *i))
+                .collect::<Vec<_>>()
+        };
+        for mut c in branch {
+            if c.symbolic {
+                match c.symbolic_op {
+                    Some(SymbolicOp::Inv) => c.symbolic_op = Some(SymbolicOp::InvOrZero),
+                    Some(SymbolicOp::Sqrt) => {
+                        return log::error!(
+                            "cannot take a square root in a branch with a signal condition",
+                            "both branches are evaluated in the r1cs target, take the square root before the conditional"
+                        )
+                    }
+                    Some(SymbolicOp::Input | SymbolicOp::PublicInput | SymbolicOp::Output) => {
+                        return log::error!(
+                            "cannot declare inputs or outputs in a branch with a signal condition",
+                            "both branches are evaluated in the r1cs target, declare the value before the conditional"
+                        )
+                    }
+                    _ => {}
+                }
+                self.constraints.push(c);
+                continue;
+            }
+            if c.assignment {
+                self.constraints.push(c);
+                continue;
+            }
+            let product = match (constant(&c.a), constant(&c.b)) {
+                (_, Some(k)) => scale(&c.a, &k),
+                (Some(k), _) => scale(&c.b, &k),
+                _ => {
+                    let index = self.var_index;
+                    self.var_index += 1;
+                    self.constraints.append(&mut vec![
+                        R1csConstraint::new(
+                            c.a.clone(),
+                            c.b.clone(),
+                            vec![(T::F::one(), index)],
+                            &format!("conditional check product into {index}"),
+                        )
+                        .as_assignment(),
+                        R1csConstraint::symbolic(
+                            index,
+                            c.a.clone(),
+                            c.b.clone(),
+                            SymbolicOp::Mul,
+                            self.compiler_state.messages[0].clone(),
+                        ),
+                    ]);
+                    vec![(T::F::one(), index)]
+                }
+            };
+            // (selector) * (a*b - c) - (0*one) = 0
+            c.a = selector.clone();
+            c.b = product
+                .into_iter()
+                .chain(scale(&c.c, &-T::F::one()))
+                .collect();
+            c.c = vec![(T::F::zero(), 0)];
+            self.constraints.push(c);
+        }
+        Ok(())
+    }
+
+    /// Report an assertion on static values that does not hold.
+    /// In a branch with a signal condition the branch may not be
+    /// taken, so a constraint that never holds is added instead. It
+    /// requires the branch not to be taken once it is gated.
+    fn static_assert_failed(&mut self, message: &str) -> Result<()> {
+        if !self.conditional {
+            return log::error!(message);
+        }
+        // (1*one) * (1*one) - (0*one) = 0
+        self.constraints.push(R1csConstraint::new(
+            vec![(T::F::one(), 0)],
+            vec![(T::F::one(), 0)],
+            vec![(T::F::zero(), 0)],
+            &format!("{ASSERT_COMMENT}{message}"),
+        ));
+        Ok(())
+    }
+
+    /// Convert a static into a signal, or return the signal
+    /// unmodified.
+    fn var_to_signal(&mut self, v: &Var<T>) -> Result<Var<T>> {
+        if v.location == VarLocation::Static {
+            self.static_to_constraint(&v.value)
+        } else {
+            Ok(v.clone())
+        }
+    }
+
+    /// Constrain a new variable equal to `a` if the condition signal
+    /// is 1, or `b` if the condition signal is 0.
+    ///
+    /// out = b + condition * (a - b)
+    fn select(&mut self, condition_index: usize, a: &Var<T>, b: &Var<T>) -> Result<Var<T>> {
+        if a.value.dimensions != b.value.dimensions {
+            return log::error!(
+                TypeError: "variable dimensions differ between conditional branches",
+                "a variable must have the same dimensions in each branch of a conditional"
+            );
+        }
+        let a = self.var_to_signal(a)?;
+        let b = self.var_to_signal(b)?;
+        let len = a.value.len();
+        let diff_index = self.var_index;
+        self.var_index += len;
+        let out_index = self.var_index;
+        self.var_index += len;
+        for x in 0..len {
+            let ai = a.index.unwrap() + x;
+            let bi = b.index.unwrap() + x;
+            let ti = diff_index + x;
+            let ovi = out_index + x;
+            // (1*condition) * (1*ai + -1*bi) - (1*ti) = 0
+            // (1*bi + 1*ti) * (1*1) - (1*ovi) = 0
+            self.constraints.append(&mut vec![
+                R1csConstraint::new(
+                    vec![(T::F::one(), condition_index)],
+                    vec![(T::F::one(), ai), (-T::F::one(), bi)],
+                    vec![(T::F::one(), ti)],
+                    &format!("conditional difference between {ai} and {bi} into {ti}"),
+                )
+                .as_assignment(),
+                R1csConstraint::symbolic(
+                    ti,
+                    vec![(T::F::one(), condition_index)],
+                    vec![(T::F::one(), ai), (-T::F::one(), bi)],
+                    SymbolicOp::Mul,
+                    self.compiler_state.messages[0].clone(),
+                ),
+                R1csConstraint::new(
+                    vec![(T::F::one(), bi), (T::F::one(), ti)],
+                    vec![(T::F::one(), 0)],
+                    vec![(T::F::one(), ovi)],
+                    &format!("conditional selection between {ai} and {bi} into {ovi}"),
+                )
+                .as_assignment(),
+                R1csConstraint::symbolic(
+                    ovi,
+                    vec![(T::F::one(), bi), (T::F::one(), ti)],
+                    vec![(T::F::one(), 0)],
+                    SymbolicOp::Mul,
+                    self.compiler_state.messages[0].clone(),
+                ),
+            ]);
+        }
+        Ok(Var {
+            index: Some(out_index),
+            location: VarLocation::Constraint,
+            value: a.value.clone(),
+        })
+    }
+
+    /// Constrain two new signals indicating whether the signal at
+    /// index `d` is zero. Returns (is_zero, is_nonzero).
+    fn is_zero(&mut self, d: usize) -> Result<(usize, usize)> {
+        let inv = self.var_index;
+        let is_nonzero = inv + 1;
+        let is_zero = inv + 2;
+        self.var_index += 3;
+        // inv = 1/d, or 0 if d == 0
+        // is_nonzero = d * inv
+        // is_zero = 1 - is_nonzero
+        // d * is_zero = 0
+        self.constraints.append(&mut vec![
+            R1csConstraint::symbolic(
+                inv,
+                vec![(T::F::one(), 0)],
+                vec![(T::F::one(), d)],
+                SymbolicOp::InvOrZero,
+                self.compiler_state.messages[0].clone(),
+            ),
+            R1csConstraint::symbolic(
+                is_nonzero,
+                vec![(T::F::one(), d)],
+                vec![(T::F::one(), inv)],
+                SymbolicOp::Mul,
+                self.compiler_state.messages[0].clone(),
+            ),
+            R1csConstraint::new(
+                vec![(T::F::one(), d)],
+                vec![(T::F::one(), inv)],
+                vec![(T::F::one(), is_nonzero)],
+                &format!("{d} is non-zero into {is_nonzero}"),
+            )
+            .as_assignment(),
+            R1csConstraint::symbolic(
+                is_zero,
+                vec![(T::F::one(), 0), (-T::F::one(), is_nonzero)],
+                vec![(T::F::one(), 0)],
+                SymbolicOp::Mul,
+                self.compiler_state.messages[0].clone(),
+            ),
+            R1csConstraint::new(
+                vec![(T::F::one(), 0), (-T::F::one(), is_nonzero)],
+                vec![(T::F::one(), 0)],
+                vec![(T::F::one(), is_zero)],
+                &format!("{d} is zero into {is_zero}"),
+            )
+            .as_assignment(),
+            R1csConstraint::new(
+                vec![(T::F::one(), d)],
+                vec![(T::F::one(), is_zero)],
+                vec![(T::F::zero(), 0)],
+                &format!("{d} is zero or {is_zero} is zero"),
+            )
+            .as_assignment(),
+        ]);
+        Ok((is_zero, is_nonzero))
+    }
+
+    /// Evaluate a boolean expression into a static or a signal
+    /// that is constrained to be 0 or 1.
+    fn eval_condition(&mut self, expr: &Expr) -> Result<Var<T>> {
+        let (lhs, bool_op, rhs) = match expr {
+            Expr::BoolOp { lhs, bool_op, rhs } => (lhs, bool_op, rhs),
+            _ => return log::error!(TypeError: "conditions must be boolean expressions"),
+        };
+        if matches!(bool_op, BoolOp::And | BoolOp::Or) {
+            return self.eval_logic_op(bool_op, lhs, rhs);
+        }
+        let lv = self.eval(lhs)?;
+        let rv = self.eval(rhs)?;
+        if lv.value.len() != 1 || rv.value.len() != 1 {
+            return log::error!(TypeError: "boolean operations may only be applied to scalars");
+        }
+        // ordering comparisons are expressed as either
+        // a >= b or a < b
+        let is_equal = match bool_op {
+            BoolOp::Equal => true,
+            BoolOp::NotEqual => false,
+            BoolOp::GreaterThanOrEqual => return self.eval_comparison(&lv, &rv, true),
+            BoolOp::LessThan => return self.eval_comparison(&lv, &rv, false),
+            BoolOp::LessThanOrEqual => return self.eval_comparison(&rv, &lv, true),
+            BoolOp::GreaterThan => return self.eval_comparison(&rv, &lv, false),
+            BoolOp::And | BoolOp::Or => unreachable!(),
+        };
+        let diff = self.eval_numop_vars(&lv, &NumOp::Sub, &rv)?;
+        if diff.location == VarLocation::Static {
+            let diff_is_zero = diff.value.values[0] == T::zero();
+            return Ok(Var {
+                index: None,
+                location: VarLocation::Static,
+                value: Matrix::from(if diff_is_zero == is_equal {
+                    T::one()
+                } else {
+                    T::zero()
+                }),
+            });
+        }
+        let (is_zero, is_nonzero) = self.is_zero(diff.index.unwrap())?;
+        Ok(Var {
+            index: Some(if is_equal { is_zero } else { is_nonzero }),
+            location: VarLocation::Constraint,
+            value: Matrix::from(T::zero()),
+        })
+    }
+
+    /// Combine two conditions. A static operand that determines
+    /// the result short circuits the other operand.
+    ///
+    /// a && b = a * b
+    /// a || b = a + b - a * b
+    fn eval_logic_op(&mut self, bool_op: &BoolOp, lhs: &Expr, rhs: &Expr) -> Result<Var<T>> {
+        let is_and = matches!(bool_op, BoolOp::And);
+        let lv = self.eval_condition(lhs)?;
+        if lv.location == VarLocation::Static {
+            let l_true = lv.value.values[0] == T::one();
+            if l_true != is_and {
+                // false && b = false
+                // true || b = true
+                return Ok(lv);
+            }
+            // true && b = b
+            // false || b = b
+            return self.eval_condition(rhs);
+        }
+        let rv = self.eval_condition(rhs)?;
+        if rv.location == VarLocation::Static {
+            let r_true = rv.value.values[0] == T::one();
+            return Ok(if r_true != is_and { rv } else { lv });
+        }
+        let l_index = lv.index.unwrap();
+        let r_index = rv.index.unwrap();
+        let product_index = self.var_index;
+        self.var_index += 1;
+        // (1*l) * (1*r) - (1*product) = 0
+        self.constraints.append(&mut vec![
+            R1csConstraint::new(
+                vec![(T::F::one(), l_index)],
+                vec![(T::F::one(), r_index)],
+                vec![(T::F::one(), product_index)],
+                &format!("{l_index} and {r_index} into {product_index}"),
+            )
+            .as_assignment(),
+            R1csConstraint::symbolic(
+                product_index,
+                vec![(T::F::one(), l_index)],
+                vec![(T::F::one(), r_index)],
+                SymbolicOp::Mul,
+                self.compiler_state.messages[0].clone(),
+            ),
+        ]);
+        if is_and {
+            return Ok(Var {
+                index: Some(product_index),
+                location: VarLocation::Constraint,
+                value: Matrix::from(T::zero()),
+            });
+        }
+        let or_index = self.var_index;
+        self.var_index += 1;
+        let or_lc = vec![
+            (T::F::one(), l_index),
+            (T::F::one(), r_index),
+            (-T::F::one(), product_index),
+        ];
+        // (1*l + 1*r + -1*product) * (1*one) - (1*or) = 0
+        self.constraints.append(&mut vec![
+            R1csConstraint::new(
+                or_lc.clone(),
+                vec![(T::F::one(), 0)],
+                vec![(T::F::one(), or_index)],
+                &format!("{l_index} or {r_index} into {or_index}"),
+            )
+            .as_assignment(),
+            R1csConstraint::symbolic(
+                or_index,
+                or_lc,
+                vec![(T::F::one(), 0)],
+                SymbolicOp::Mul,
+                self.compiler_state.messages[0].clone(),
+            ),
+        ]);
+        Ok(Var {
+            index: Some(or_index),
+            location: VarLocation::Constraint,
+            value: Matrix::from(T::zero()),
+        })
+    }
+
+    /// Compare two scalars in the range [0, 2^32). Outputs a >= b
+    /// if `is_gte` is true, otherwise outputs a < b.
+    fn eval_comparison(&mut self, a: &Var<T>, b: &Var<T>, is_gte: bool) -> Result<Var<T>> {
+        if a.location == VarLocation::Static && b.location == VarLocation::Static {
+            let av = a.value.values[0].to_scalar()?.to_biguint();
+            let bv = b.value.values[0].to_scalar()?.to_biguint();
+            return Ok(Var {
+                index: None,
+                location: VarLocation::Static,
+                value: Matrix::from(if (av >= bv) == is_gte {
+                    T::one()
+                } else {
+                    T::zero()
+                }),
+            });
+        }
+        let a = self.var_to_signal(a)?;
+        let b = self.var_to_signal(b)?;
+        let a_index = a.index.unwrap();
+        let b_index = b.index.unwrap();
+        // range check both operands
+        self.decompose_bits(vec![(T::F::one(), a_index)], COMPARISON_BITS)?;
+        self.decompose_bits(vec![(T::F::one(), b_index)], COMPARISON_BITS)?;
+        // a - b + 2^n has bit n set iff a >= b
+        let mut offset = T::F::one();
+        for _ in 0..COMPARISON_BITS {
+            offset = offset.clone() + offset;
+        }
+        let bits_index = self.decompose_bits(
+            vec![(T::F::one(), a_index), (-T::F::one(), b_index), (offset, 0)],
+            COMPARISON_BITS + 1,
+        )?;
+        let gte_index = bits_index + COMPARISON_BITS;
+        if is_gte {
+            return Ok(Var {
+                index: Some(gte_index),
+                location: VarLocation::Constraint,
+                value: Matrix::from(T::zero()),
+            });
+        }
+        let lt_index = self.var_index;
+        self.var_index += 1;
+        // (1*one + -1*gte) * (1*one) - (1*lt) = 0
+        self.constraints.append(&mut vec![
+            R1csConstraint::new(
+                vec![(T::F::one(), 0), (-T::F::one(), gte_index)],
+                vec![(T::F::one(), 0)],
+                vec![(T::F::one(), lt_index)],
+                &format!("{a_index} is less than {b_index} into {lt_index}"),
+            )
+            .as_assignment(),
+            R1csConstraint::symbolic(
+                lt_index,
+                vec![(T::F::one(), 0), (-T::F::one(), gte_index)],
+                vec![(T::F::one(), 0)],
+                SymbolicOp::Mul,
+                self.compiler_state.messages[0].clone(),
+            ),
+        ]);
+        Ok(Var {
+            index: Some(lt_index),
+            location: VarLocation::Constraint,
+            value: Matrix::from(T::zero()),
+        })
+    }
+
+    /// Constrain `bit_count` new signals to be the little endian
+    /// bit decomposition of a linear combination. Returns the
+    /// index of the least significant bit.
+    fn decompose_bits(&mut self, lc: Vec<(T::F, usize)>, bit_count: usize) -> Result<usize> {
+        let start_index = self.var_index;
+        self.var_index += bit_count;
+        let mut sum = vec![];
+        let mut coef = T::F::one();
+        let mut bit_offset = T::F::zero();
+        for x in 0..bit_count {
+            let bit_index = start_index + x;
+            // (1*bit) * (1*bit) - (1*bit) = 0
+            self.constraints.append(&mut vec![
+                R1csConstraint::symbolic(
+                    bit_index,
+                    vec![(bit_offset.clone(), 0)],
+                    lc.clone(),
+                    SymbolicOp::Bit,
+                    self.compiler_state.messages[0].clone(),
+                ),
+                R1csConstraint::new(
+                    vec![(T::F::one(), bit_index)],
+                    vec![(T::F::one(), bit_index)],
+                    vec![(T::F::one(), bit_index)],
+                    &format!("{bit_index} is a bit"),
+                )
+                .as_assignment(),
+            ]);
+            sum.push((coef.clone(), bit_index));
+            coef = coef.clone() + coef;
+            bit_offset += T::F::one();
+        }
+        // (sum of bits) * (1*one) - (lc) = 0
+        self.constraints.push(R1csConstraint::new(
+            sum,
+            vec![(T::F::one(), 0)],
+            lc,
+            &format!(
+                "bit decomposition into {start_index}..{}",
+                start_index + bit_count
+            ),
+        ));
+        Ok(start_index)
+    }
+
+    /// Constrain values to be a row of a table. Lookups of static
+    /// values are checked at compile time.
+    fn lookup(&mut self, table: &TableDef, entries: &[Expr]) -> Result<()> {
+        let table = LookupTable::<T::F>::new(table)?;
+        let mut lcs = vec![];
+        for expr in entries {
+            let v = self.eval(expr)?;
+            for (i, value) in v.value.values.iter().enumerate() {
+                lcs.push(match v.index {
+                    Some(index) => vec![(T::F::one(), index + i)],
+                    None => vec![(value.to_scalar()?, 0)],
+                });
+            }
+        }
+        table.check_width(lcs.len())?;
+        if lcs.iter().flatten().all(|(_, i)| *i == 0) {
+            let row = lcs.iter().map(|v| v[0].0.clone()).collect::<Vec<_>>();
+            if !table.contains(&row) {
+                self.static_assert_failed(&format!(
+                    "lookup failed: [{}] is not a row of table {}",
+                    row.iter()
+                        .map(|v| v.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    table.name
+                ))?;
+            }
+            return Ok(());
+        }
+        let mut circuit = LookupCircuit::new(self.var_index, &self.compiler_state.messages[0]);
+        circuit.expand(
+            &Lookup {
+                table: table.name.clone(),
+                entries: lcs,
+            },
+            &table,
+        )?;
+        self.var_index = circuit.var_index;
+        self.constraints.append(&mut circuit.constraints);
+        Ok(())
+    }
+
+    /// The signals of arguments to an ar1cs function. Vectors are
+    /// passed as one argument per element, statics are constrained
+    /// into new signals.
+    fn ar1cs_args(&mut self, args: &[Var<T>]) -> Result<Vec<usize>> {
+        let mut out = vec![];
+        for v in args {
+            if let Some(i) = v.index {
+                out.extend(i..i + v.value.len());
+                continue;
+            }
+            if v.value.len() != 1 {
+                return log::error!(TypeError: "cannot pass a vector static to an r1cs function");
+            }
+            // if we get a static variable we need to
+            // assert equality of it's current value
+            // to turn it into a signal
+            let index = self.var_index;
+            self.var_index += 1;
+            self.constraints.push(
+                R1csConstraint::new(
+                    vec![(T::F::one(), index)],
+                    vec![(T::F::one(), 0)],
+                    vec![(v.value.values[0].to_scalar()?, 0)],
+                    &format!(
+                        "assigning literal ({}) to signal {index}",
+                        v.value.values[0]
+                    ),
+                )
+                .as_assignment(),
+            );
+            self.constraints.push(R1csConstraint::symbolic(
+                index,
+                vec![(v.value.values[0].to_scalar()?, 0)],
+                vec![(T::F::zero(), 0)],
+                SymbolicOp::Add,
+                self.compiler_state.messages[0].clone(),
+            ));
+            out.push(index);
+        }
+        Ok(out)
+    }
+
+    /// Inline the constraints of an `ar1cs` block. The return
+    /// values are declared as variables in the current scope.
+    fn ar1cs_block(&mut self, args: &[String], returns: &[String], source: &str) -> Result<()> {
+        let header = format!("({}) -> ({})\n", args.join(", "), returns.join(", "));
+        let parser = R1csParser::<T>::new(&format!("{header}{source}"))?;
+        let mut vars = vec![];
+        for name in args {
+            match self.vars.get(name) {
+                Some(v) if v.value.len() == 1 => vars.push(v.clone()),
+                Some(_) => {
+                    return log::error!(
+                        TypeError: &format!("argument \"{name}\" to an ar1cs block must be a scalar")
+                    )
+                }
+                None => {
+                    return log::error!(
+                        ScopeError: &format!("variable does not exist in scope: {name}")
+                    )
+                }
+            }
+        }
+        let signals = self.ar1cs_args(&vars)?;
+        let mut constraints = parser.signals_as_args(self.var_index, signals)?;
+        self.constraints.append(&mut constraints);
+        let return_index = self.var_index;
+        self.var_index += parser.new_signal_count();
+        for (i, name) in returns.iter().enumerate() {
+            if self.vars.contains_key(name) {
+                return log::error!(ScopeError: &format!("variable already defined: {name}"));
+            }
+            self.vars.insert(
+                name.clone(),
+                Var {
+                    index: Some(return_index + i),
+                    location: VarLocation::Constraint,
+                    value: Matrix::from(T::zero()),
+                },
+            );
+            self.name_signals(name);
+        }
+        Ok(())
+    }
+
+    /// Evaluate a block only to calculate the witness. Constraints
+    /// created in the block are removed, the symbolic constraints
+    /// calculating the declared values are kept, so the values
+    /// must be constrained after the block.
+    fn unconstrained_block(&mut self, returns: &[String], body: Vec<AstNode>) -> Result<()> {
+        for name in returns {
+            if self.vars.contains_key(name) {
+                return log::error!(ScopeError: &format!("variable already defined: {name}"));
+            }
+        }
+        let old_vars = self.vars.clone();
+        let start = self.constraints.len();
+        self.eval_ast(body)?;
+        for (name, v) in &old_vars {
+            if self.vars.get(name) != Some(v) {
+                return log::error!(
+                    ScopeError: &format!("unconstrained block assigns \"{name}\""),
+                    "an unconstrained block may only declare new values, assign the variable after the block"
+                );
+            }
+        }
+        let mut values = vec![];
+        for name in returns {
+            match self.vars.get(name) {
+                Some(v) if v.value.len() == 1 => values.push((name.clone(), v.clone())),
+                Some(_) => {
+                    return log::error!(TypeError: &format!(
+                        "value \"{name}\" declared by an unconstrained block must be a scalar"
+                    ))
+                }
+                None => {
+                    return log::error!(ScopeError: &format!(
+                        "unconstrained block does not declare \"{name}\""
+                    ))
+                }
+            }
+        }
+        self.drop_block_vars(&old_vars);
+        let block_constraints = self.constraints.split_off(start);
+        self.constraints
+            .extend(block_constraints.into_iter().filter(|c| c.symbolic));
+        self.vars.extend(values);
+        Ok(())
+    }
+
+    // declare a signal that is assigned the next input value
+    // during witness calculation
+    fn declare_input(&mut self, name: String, op: SymbolicOp, comment: &str) -> Result<()> {
+        if self.vars.contains_key(&name) {
+            return log::error!(ScopeError: &format!("variable already defined: {name}"));
+        }
+        let index = self.var_index;
+        self.var_index += 1;
+        self.constraints.push(R1csConstraint::symbolic(
+            index,
+            vec![(T::F::one(), 0)],
+            vec![(T::F::one(), 0)],
+            op,
+            format!("{comment} {name}"),
+        ));
+        self.vars.insert(
+            name.clone(),
+            Var {
+                index: Some(index),
+                location: VarLocation::Constraint,
+                value: Matrix::from(T::zero()),
+            },
+        );
+        self.name_signals(&name);
+        Ok(())
+    }
+
+    // record the source name of the signals held by a variable
+    // in the symbol table, if one is being written. Variables
+    // in functions are prefixed with the function name
+    fn name_signals(&mut self, name: &str) {
+        let (Some(symbols), Some(v)) = (&mut self.compiler_state.symbols, self.vars.get(name))
+        else {
+            return;
+        };
+        let Some(index) = v.index.filter(|_| v.location == VarLocation::Constraint) else {
+            return;
+        };
+        let name = match self.name.as_str() {
+            "entrypoint" => name.to_string(),
+            fn_name => format!("{fn_name}::{name}"),
+        };
+        symbols
+            .entry(name)
+            .or_default()
+            .extend(index..index + v.value.len());
+    }
+
+    // the value of each variable in scope, None for signals
+    fn static_values(&self) -> HashMap<String, Option<Matrix<T>>> {
+        self.vars
+            .iter()
+            .map(|(name, v)| {
+                let value = match v.location {
+                    VarLocation::Static => Some(v.value.clone()),
+                    VarLocation::Constraint => None,
+                };
+                (name.clone(), value)
+            })
+            .collect()
+    }
+
+    /// Assert that a variable is a scalar in the range of an
+    /// integer type.
+    fn assert_int(&mut self, v: &Var<T>, int_type: IntType) -> Result<()> {
+        let name = int_type.name();
+        let bits = int_type.bits();
+        // the product of two integers must not overflow the field
+        let max = (BigUint::from(1_u32) << bits) - 1_u32;
+        if max.clone() * max >= T::F::prime() {
+            return log::error!(
+                ConfigError: &format!("{name} is not supported in the {} field", T::name_str()),
+                &format!("{name} requires a field of more than {} bits", 2 * bits)
+            );
+        }
+        if v.value.len() != 1 {
+            return log::error!(TypeError: &format!("{name} variables must be scalars"));
+        }
+        match v.index {
+            Some(index) => {
+                self.decompose_bits(vec![(T::F::one(), index)], bits)?;
+            }
+            None => {
+                let value = v.value.values[0].to_scalar()?.to_biguint();
+                if value.bits() > bits as u64 {
+                    return log::error!(
+                        &format!("{name} overflow: {value} is out of range"),
+                        &format!("the result of a {name} operation must be less than 2^{bits}")
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluate a bitwise operation on two integers. Signals are
+    /// decomposed into bits and the result is packed into a new
+    /// signal. Shift amounts must be static.
+    fn eval_bitop(
+        &mut self,
+        lv: &Var<T>,
+        bit_op: &BitOp,
+        rv: &Var<T>,
+        int_type: IntType,
+    ) -> Result<Var<T>> {
+        let name = int_type.name();
+        let bits = int_type.bits();
+        if lv.value.len() != 1 || rv.value.len() != 1 {
+            return log::error!(TypeError: &format!("{name} bitwise operands must be scalars"));
+        }
+        let shift = match bit_op {
+            BitOp::Shl | BitOp::Shr => {
+                if rv.index.is_some() {
+                    return log::error!(
+                        "shift amounts must be static",
+                        "shifting by a signal is not supported in r1cs"
+                    );
+                }
+                let shift = rv.value.values[0].to_scalar()?.to_biguint();
+                if shift >= BigUint::from(bits as u32) {
+                    return log::error!(
+                        &format!("{name} shift amount {shift} is out of range"),
+                        &format!("the shift amount must be less than {bits}")
+                    );
+                }
+                Some(shift.to_string().parse::<usize>()?)
+            }
+            _ => None,
+        };
+        // static operands are not range checked by the decomposition
+        if lv.index.is_none() {
+            self.assert_int(lv, int_type)?;
+        }
+        if rv.index.is_none() && shift.is_none() {
+            self.assert_int(rv, int_type)?;
+        }
+        if lv.index.is_none() && rv.index.is_none() {
+            let l = lv.value.values[0].to_scalar()?.to_biguint();
+            let r = rv.value.values[0].to_scalar()?.to_biguint();
+            let out = match (bit_op, shift) {
+                (BitOp::And, _) => l & r,
+                (BitOp::Or, _) => l | r,
+                (BitOp::Xor, _) => l ^ r,
+                (BitOp::Shl, Some(n)) => (l << n) & ((BigUint::from(1_u32) << bits) - 1_u32),
+                (BitOp::Shr, Some(n)) => l >> n,
+                _ => unreachable!(),
+            };
+            return Ok(Var {
+                index: None,
+                location: VarLocation::Static,
+                value: Matrix::from(T::from_str(&out.to_string())?),
+            });
+        }
+        let mut circuit = BinaryCircuit::new(self.var_index, &self.compiler_state.messages[0])?;
+        let mut decompose = |v: &Var<T>| -> Result<Vec<Vec<(T::F, usize)>>> {
+            let lc = match v.index {
+                Some(index) => vec![(T::F::one(), index)],
+                None => vec![(v.value.values[0].to_scalar()?, 0)],
+            };
+            Ok(circuit.decompose(lc, bits))
+        };
+        let l = decompose(lv)?;
+        let out = match (bit_op, shift) {
+            (BitOp::Shl, Some(n)) => (0..bits)
+                .map(|i| match i.checked_sub(n) {
+                    Some(j) => l[j].clone(),
+                    None => BinaryCircuit::constant(false),
+                })
+                .collect::<Vec<_>>(),
+            (BitOp::Shr, Some(n)) => (0..bits)
+                .map(|i| {
+                    l.get(i + n)
+                        .cloned()
+                        .unwrap_or_else(|| BinaryCircuit::constant(false))
+                })
+                .collect::<Vec<_>>(),
+            _ => {
+                let r = decompose(rv)?;
+                l.iter()
+                    .zip(r.iter())
+                    .map(|(a, b)| match bit_op {
+                        BitOp::And => circuit.and(a, b),
+                        BitOp::Or => circuit.or(a, b),
+                        _ => circuit.xor(a, b),
+                    })
+                    .collect::<Vec<_>>()
+            }
+        };
+        let index = circuit.bytes_to_signals(&[out]);
+        self.var_index = circuit.var_index;
+        self.constraints.append(&mut circuit.constraints);
+        Ok(Var {
+            index: Some(index),
+            location: VarLocation::Constraint,
+            value: Matrix::from(T::zero()),
+        })
+    }
+
+    /// Constrain a new signal to be the poseidon hash of some
+    /// scalars. The state is tracked as linear combinations so
+    /// signals are only allocated by the s-box and the output.
+    fn poseidon(&mut self, args: &[Var<T>]) -> Result<Var<T>> {
+        let params = PoseidonParams::<T::F>::new(args.len())?;
+        // the capacity element is 0
+        let mut state: Vec<Vec<(T::F, usize)>> = vec![vec![]];
+        for v in args {
+            if v.value.len() != 1 {
+                return log::error!(TypeError: "poseidon arguments must be scalars");
+            }
+            state.push(match v.index {
+                Some(i) => vec![(T::F::one(), i)],
+                None => vec![(v.value.values[0].to_scalar()?, 0)],
+            });
+        }
+        for round in 0..FULL_ROUNDS + params.partial_rounds {
+            for (lc, c) in state.iter_mut().zip(&params.round_constants[round]) {
+                add_term(lc, c.clone(), 0);
+            }
+            let sbox_count = if params.is_full_round(round) {
+                params.t
+            } else {
+                1
+            };
+            for lc in state.iter_mut().take(sbox_count) {
+                *lc = vec![(T::F::one(), self.pow5(lc))];
+            }
+            state = params
+                .mds
+                .iter()
+                .map(|row| {
+                    let mut out = vec![];
+                    for (coef, lc) in row.iter().zip(&state) {
+                        for (v, i) in lc {
+                            add_term(&mut out, coef.clone() * v.clone(), *i);
+                        }
+                    }
+                    out
+                })
+                .collect();
+        }
+        let out = self.var_index;
+        self.var_index += 1;
+        self.constraints.append(&mut vec![
+            R1csConstraint::new(
+                state[0].clone(),
+                vec![(T::F::one(), 0)],
+                vec![(T::F::one(), out)],
+                &format!("poseidon output into {out}"),
+            )
+            .as_assignment(),
+            R1csConstraint::symbolic(
+                out,
+                state[0].clone(),
+                vec![(T::F::one(), 0)],
+                SymbolicOp::Mul,
+                self.compiler_state.messages[0].clone(),
+            ),
+        ]);
+        Ok(Var {
+            index: Some(out),
+            location: VarLocation::Constraint,
+            value: Matrix::from(T::zero()),
+        })
+    }
+
+    /// Derive a challenge from some scalars. The previous
+    /// challenge, or 0 for the first, is hashed with the arguments
+    /// so every challenge depends on the whole transcript.
+    fn challenge(&mut self, args: &[Var<T>]) -> Result<Var<T>> {
+        if args.is_empty() || args.len() > builtin::CHALLENGE_INPUTS {
+            return log::error!(TypeError: &format!(
+                "challenge accepts 1 to {} inputs, received {}",
+                builtin::CHALLENGE_INPUTS,
+                args.len()
+            ));
+        }
+        // the transcript is built while compiling, so it would
+        // include challenges in branches that are not taken
+        if self.conditional {
+            return log::error!(
+                TypeError: "cannot derive a challenge in a branch with a signal condition",
+                "derive the challenge before the if statement"
+            );
+        }
+        let state = match self.compiler_state.transcript {
+            Some(i) => Var {
+                index: Some(i),
+                location: VarLocation::Constraint,
+                value: Matrix::from(T::zero()),
+            },
+            None => Var {
+                index: None,
+                location: VarLocation::Static,
+                value: Matrix::from(T::zero()),
+            },
+        };
+        let out = self.poseidon(&[vec![state], args.to_vec()].concat())?;
+        self.compiler_state.transcript = out.index;
+        Ok(out)
+    }
+
+    /// Constrain a leaf to be included in a merkle tree. `path`
+    /// is the sibling at each level starting at the leaf, and
+    /// `indices` is 1 at each level where the current node is
+    /// the right child.
+    fn merkle_verify(
+        &mut self,
+        root: &Var<T>,
+        leaf: &Var<T>,
+        path: &Var<T>,
+        indices: &Var<T>,
+    ) -> Result<()> {
+        if root.value.len() != 1 || leaf.value.len() != 1 {
+            return log::error!(TypeError: "merkle_verify root and leaf must be scalars");
+        }
+        if path.value.dimensions.len() != 1 || path.value.dimensions != indices.value.dimensions {
+            return log::error!(
+                TypeError: "merkle_verify path and indices must be vectors of the same length"
+            );
+        }
+        let mut node = self.var_to_signal(leaf)?;
+        for i in 0..path.value.len() {
+            let element = |v: &Var<T>| {
+                let (value, offset) = v.value.retrieve_indices(&[i]);
+                Var {
+                    index: v.index.map(|index| index + offset),
+                    location: v.location.clone(),
+                    value,
+                }
+            };
+            let sibling = element(path);
+            let bit = self.var_to_signal(&element(indices))?.index.unwrap();
+            self.constraints.push(R1csConstraint::new(
+                vec![(T::F::one(), bit)],
+                vec![(T::F::one(), bit)],
+                vec![(T::F::one(), bit)],
+                &format!("merkle index {bit} is a bit"),
+            ));
+            let left = self.select(bit, &sibling, &node)?;
+            let right = self.select(bit, &node, &sibling)?;
+            node = self.poseidon(&[left, right])?;
+        }
+        let root = self.var_to_signal(root)?;
+        self.constraints.push(R1csConstraint::new(
+            vec![(T::F::one(), node.index.unwrap())],
+            vec![(T::F::one(), 0)],
+            vec![(T::F::one(), root.index.unwrap())],
+            "merkle root",
+        ));
+        Ok(())
+    }
+
+    /// Constrain a signature `(r, s)` on a message by the public
+    /// key `a`. The arguments are `[ax, ay, rx, ry, s, msg]`.
+    fn eddsa_verify(&mut self, args: &[Var<T>]) -> Result<()> {
+        if args.iter().any(|v| v.value.len() != 1) {
+            return log::error!(TypeError: "eddsa_verify arguments must be scalars");
+        }
+        let curve = EdwardsCurve::<T::F>::new()?;
+        let h = self.poseidon(&[
+            args[2].clone(),
+            args[3].clone(),
+            args[0].clone(),
+            args[1].clone(),
+            args[5].clone(),
+        ])?;
+        let signals = args[..5]
+            .iter()
+            .map(|v| Ok(self.var_to_signal(v)?.index.unwrap()))
+            .collect::<Result<Vec<_>>>()?;
+        let point = |x: usize, y: usize| (vec![(T::F::one(), x)], vec![(T::F::one(), y)]);
+        let mut circuit = BinaryCircuit::new(self.var_index, &self.compiler_state.messages[0])?;
+        curve.verify(
+            &mut circuit,
+            &point(signals[0], signals[1]),
+            &point(signals[2], signals[3]),
+            signals[4],
+            h.index.unwrap(),
+        );
+        self.var_index = circuit.var_index;
+        self.constraints.append(&mut circuit.constraints);
+        Ok(())
+    }
+
+    /// Constrain an inner product argument to be valid, see
+    /// `r1cs::ipa`. The arguments are `[p, c, l, r, x_inv, a, b]`
+    /// where `p` is a point, `l` and `r` have a point for each
+    /// round, and `x_inv` has a scalar for each round.
+    fn ipa_verify(&mut self, args: &[Var<T>]) -> Result<()> {
+        let rounds = args[4].value.len();
+        if args[0].value.dimensions != vec![2]
+            || [1, 5, 6].iter().any(|i| args[*i].value.len() != 1)
+            || args[4].value.dimensions.len() != 1
+            || args[2].value.dimensions != vec![rounds, 2]
+            || args[3].value.dimensions != vec![rounds, 2]
+        {
+            return log::error!(
+                TypeError: "invalid ipa_verify arguments",
+                "p is a point [x, y], l and r are a matrix with a point for each round, x_inv is a vector, and c, a, and b are scalars"
+            );
+        }
+        if rounds == 0 || rounds > MAX_ROUNDS {
+            return log::error!(TypeError: &format!(
+                "ipa_verify accepts 1 to {MAX_ROUNDS} rounds, received {rounds}"
+            ));
+        }
+        let signals = args
+            .iter()
+            .map(|v| Ok(self.var_to_signal(v)?.index.unwrap()))
+            .collect::<Result<Vec<_>>>()?;
+        let scalar = |i: usize| Var {
+            index: Some(i),
+            location: VarLocation::Constraint,
+            value: Matrix::from(T::zero()),
+        };
+        let point = |i: usize| (vec![(T::F::one(), i)], vec![(T::F::one(), i + 1)]);
+        let (p, c, l, r, x_inv, a, b) = (
+            signals[0], signals[1], signals[2], signals[3], signals[4], signals[5], signals[6],
+        );
+        // the transcript is hashed outside of the binary circuit
+        let mut t = self.poseidon(&[scalar(p), scalar(p + 1), scalar(c)])?;
+        let mut challenges = vec![];
+        for i in 0..rounds {
+            let (li, ri) = (l + 2 * i, r + 2 * i);
+            t = self.poseidon(&[t, scalar(li), scalar(li + 1), scalar(ri), scalar(ri + 1)])?;
+            challenges.push(t.index.unwrap());
+        }
+        let params = IpaParams::<T::F>::new(1 << rounds)?;
+        let mut circuit = BinaryCircuit::new(self.var_index, &self.compiler_state.messages[0])?;
+        params.verify(
+            &mut circuit,
+            &IpaSignals {
+                commitment: point(p),
+                c,
+                l: (0..rounds).map(|i| point(l + 2 * i)).collect(),
+                r: (0..rounds).map(|i| point(r + 2 * i)).collect(),
+                x_inv: (0..rounds).map(|i| x_inv + i).collect(),
+                challenges,
+                a,
+                b,
+            },
+        )?;
+        self.var_index = circuit.var_index;
+        self.constraints.append(&mut circuit.constraints);
+        Ok(())
+    }
+
+    /// Reduce the elements of a value to a scalar. A sum is a
+    /// single linear constraint. Products and maximums are
+    /// combined in pairs, so the depth of the circuit is
+    /// logarithmic in the number of elements.
+    fn reduce(&mut self, name: &str, v: &Var<T>) -> Result<Var<T>> {
+        let len = v.value.len();
+        if len == 0 {
+            return log::error!(TypeError: &format!("{name} of an empty value"));
+        }
+        if name == builtin::SUM {
+            if v.location == VarLocation::Static {
+                let sum = v
+                    .value
+                    .values
+                    .iter()
+                    .cloned()
+                    .fold(T::zero(), |acc, v| acc + v);
+                return Ok(Var {
+                    index: None,
+                    location: VarLocation::Static,
+                    value: Matrix::from(sum),
+                });
+            }
+            let lc = (0..len)
+                .map(|x| (T::F::one(), v.index.unwrap() + x))
+                .collect::<Vec<_>>();
+            let oi = self.var_index;
+            self.var_index += 1;
+            self.constraints.append(&mut vec![
+                R1csConstraint::new(
+                    lc.clone(),
+                    vec![(T::F::one(), 0)],
+                    vec![(T::F::one(), oi)],
+                    &format!("sum of {len} elements into {oi}"),
+                )
+                .as_assignment(),
+                R1csConstraint::symbolic(
+                    oi,
+                    lc,
+                    vec![(T::F::one(), 0)],
+                    SymbolicOp::Mul,
+                    self.compiler_state.messages[0].clone(),
+                ),
+            ]);
+            return Ok(Var {
+                index: Some(oi),
+                location: VarLocation::Constraint,
+                value: Matrix::from(T::zero()),
+            });
+        }
+        let mut level = (0..len)
+            .map(|x| Var {
+                index: v.index.map(|i| i + x),
+                location: v.location.clone(),
+                value: Matrix::from(v.value.values[x].clone()),
+            })
+            .collect::<Vec<_>>();
+        while level.len() > 1 {
+            let mut next = vec![];
+            for pair in level.chunks(2) {
+                next.push(match pair {
+                    [a, b] if name == builtin::PROD => self.eval_numop_vars(a, &NumOp::Mul, b)?,
+                    [a, b] => {
+                        let gte = self.eval_comparison(a, b, true)?;
+                        match gte.location {
+                            VarLocation::Static if gte.value.values[0] == T::one() => a.clone(),
+                            VarLocation::Static => b.clone(),
+                            VarLocation::Constraint => self.select(gte.index.unwrap(), a, b)?,
                         }
                     }
-                }
-                _ => {
-                    return log::error!(&format!("ast node not supported for r1cs: {:?}", v));
-                }
+                    _ => pair[0].clone(),
+                });
             }
+            level = next;
         }
-        Ok(())
+        Ok(level.remove(0))
     }
 
-    /// Serialization to/from AST representation
-    pub fn build_var_from_ast_vec(&mut self, expr: &Expr) -> Result<(Vec<usize>, Vec<T>)> {
-        // first iterate all the way through to the first literal
-        let mut dimensions: Vec<usize> = Vec::new();
-        let mut root_expr = expr.clone();
-        // first calculate the dimensions of the matrix
-        loop {
-            match root_expr {
-                Expr::VecVec(v) => {
-                    dimensions.push(v.len());
-                    root_expr = v[0].clone();
-                }
-                Expr::VecLit(v) => {
-                    dimensions.push(v.len());
-                    break;
-                }
-                _ => {}
+    /// Raise each element of a value to a static power by square
+    /// and multiply. A signal costs one constraint per squaring
+    /// and one per set bit of the exponent after the first, e.g.
+    /// `x ** 13` costs 5 constraints. A static value is static.
+    fn pow(&mut self, v: &Var<T>, e: &BigUint) -> Result<Var<T>> {
+        let mut out: Option<Var<T>> = None;
+        let mut square = v.clone();
+        for i in 0..e.bits() {
+            if e.bit(i) {
+                out = Some(match out {
+                    Some(out) => self.eval_numop_vars(&out, &NumOp::Mul, &square)?,
+                    None => square.clone(),
+                });
+            }
+            if i + 1 < e.bits() {
+                square = self.eval_numop_vars(&square, &NumOp::Mul, &square)?;
             }
         }
-        // then pull all the literals into a 1 dimensional vec
-        let mut vec_rep: Vec<T> = Vec::new();
-        Self::extract_literals(expr, &mut vec_rep)?;
-        Ok((dimensions, vec_rep))
+        // x ** 0 is 1
+        Ok(out.unwrap_or_else(|| Var {
+            index: None,
+            location: VarLocation::Static,
+            value: Matrix {
+                dimensions: v.value.dimensions.clone(),
+                values: vec![T::one(); v.value.len()],
+            },
+        }))
     }
 
-    fn extract_literals(expr: &Expr, out: &mut Vec<T>) -> Result<()> {
-        match expr {
-            Expr::VecVec(v) => {
-                for a in v {
-                    Self::extract_literals(a, out)?
+    /// Constrain each element of a dot product or matrix product
+    /// to be a sum of products of the operands, see `layout`. A product
+    /// of two signals is constrained into a new signal, a product
+    /// with a static element is a coefficient of the sum. If both
+    /// operands are static the result is static.
+    fn sum_of_products(&mut self, name: &str, lhs: &Var<T>, rhs: &Var<T>) -> Result<Var<T>> {
+        let (l, r) = (&lhs.value.dimensions, &rhs.value.dimensions);
+        let (mut dimensions, terms) = if name == builtin::DOT {
+            (vec![], vec![layout::dot_terms(l, r)?])
+        } else {
+            (
+                layout::matmul_dimensions(l, r)?,
+                layout::matmul_terms(l, r)?,
+            )
+        };
+        if dimensions.is_empty() {
+            dimensions.push(1);
+        }
+        if lhs.location == VarLocation::Static && rhs.location == VarLocation::Static {
+            let values = terms
+                .iter()
+                .map(|pairs| {
+                    pairs.iter().fold(T::zero(), |acc, (i, j)| {
+                        acc + lhs.value.values[*i].clone() * rhs.value.values[*j].clone()
+                    })
+                })
+                .collect();
+            return Ok(Var {
+                index: None,
+                location: VarLocation::Static,
+                value: Matrix { dimensions, values },
+            });
+        }
+        let out_index = self.var_index;
+        self.var_index += terms.len();
+        for (x, pairs) in terms.iter().enumerate() {
+            let oi = out_index + x;
+            let mut out_lc = vec![];
+            for (i, j) in pairs {
+                match (lhs.index, rhs.index) {
+                    (Some(l), Some(r)) => {
+                        let (li, ri) = (l + i, r + j);
+                        let pi = self.var_index;
+                        self.var_index += 1;
+                        self.constraints.append(&mut vec![
+                            R1csConstraint::new(
+                                vec![(T::F::one(), li)],
+                                vec![(T::F::one(), ri)],
+                                vec![(T::F::one(), pi)],
+                                &format!("product of {li} and {ri} into {pi}"),
+                            )
+                            .as_assignment(),
+                            R1csConstraint::symbolic(
+                                pi,
+                                vec![(T::F::one(), li)],
+                                vec![(T::F::one(), ri)],
+                                SymbolicOp::Mul,
+                                self.compiler_state.messages[0].clone(),
+                            ),
+                        ]);
+                        out_lc.push((T::F::one(), pi));
+                    }
+                    (Some(l), None) => out_lc.push((rhs.value.values[*j].to_scalar()?, l + i)),
+                    (None, Some(r)) => out_lc.push((lhs.value.values[*i].to_scalar()?, r + j)),
+                    (None, None) => unreachable!(),
                 }
             }
-            Expr::VecLit(v) => {
-                let mut vv = v
-                    .clone()
-                    .iter()
-                    .map(|v| T::from_str(v))
-                    .collect::<Result<_>>()?;
-                out.append(&mut vv);
+            self.constraints.append(&mut vec![
+                R1csConstraint::new(
+                    out_lc.clone(),
+                    vec![(T::F::one(), 0)],
+                    vec![(T::F::one(), oi)],
+                    &format!("sum of products into {oi}"),
+                )
+                .as_assignment(),
+                R1csConstraint::symbolic(
+                    oi,
+                    out_lc,
+                    vec![(T::F::one(), 0)],
+                    SymbolicOp::Mul,
+                    self.compiler_state.messages[0].clone(),
+                ),
+            ]);
+        }
+        Ok(Var {
+            index: Some(out_index),
+            location: VarLocation::Constraint,
+            value: Matrix {
+                dimensions,
+                values: vec![T::zero(); terms.len()],
+            },
+        })
+    }
+
+    /// Constrain a new vector to be the product or inverse of
+    /// extension field elements, see `r1cs::extension`. If every
+    /// argument is static the result is static.
+    fn extension_op(&mut self, name: &str, args: &[Var<T>]) -> Result<Var<T>> {
+        let arity = if name == builtin::XFE_MUL { 2 } else { 1 };
+        if args.len() != arity {
+            return log::error!(TypeError: &format!(
+                "{name} expects {arity} arguments, received {}",
+                args.len()
+            ));
+        }
+        if args
+            .iter()
+            .any(|v| v.value.dimensions != vec![builtin::EXTENSION_DEGREE])
+        {
+            return log::error!(
+                TypeError: &format!("{name} arguments must be extension field elements"),
+                "declare each argument using `let v: xfe = [c0, c1, c2]`"
+            );
+        }
+        let elements = args
+            .iter()
+            .map(|v| {
+                (0..builtin::EXTENSION_DEGREE)
+                    .map(|i| match v.location {
+                        VarLocation::Static => Ok(vec![(v.value.values[i].to_scalar()?, 0)]),
+                        VarLocation::Constraint => Ok(vec![(T::F::one(), v.index.unwrap() + i)]),
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut circuit = BinaryCircuit::new(self.var_index, &self.compiler_state.messages[0])?;
+        let out = if name == builtin::XFE_MUL {
+            extension::mul(&mut circuit, &elements[0], &elements[1])
+        } else {
+            extension::inv(&mut circuit, &elements[0])?
+        };
+        self.var_index = circuit.var_index;
+        self.constraints.append(&mut circuit.constraints);
+        let dimensions = vec![builtin::EXTENSION_DEGREE];
+        if out.iter().flatten().all(|(_, i)| *i == 0) {
+            let values = out
+                .iter()
+                .map(|lc| T::from_str(&lc[0].0.to_biguint().to_string()))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(Var {
+                index: None,
+                location: VarLocation::Static,
+                value: Matrix { dimensions, values },
+            });
+        }
+        // constrain a signal for each coefficient so the result is
+        // a contiguous vector
+        let start = self.var_index;
+        self.var_index += out.len();
+        for (x, lc) in out.into_iter().enumerate() {
+            self.constraints.append(&mut vec![
+                R1csConstraint::new(
+                    lc.clone(),
+                    vec![(T::F::one(), 0)],
+                    vec![(T::F::one(), start + x)],
+                    &format!("{name} into {}", start + x),
+                )
+                .as_assignment(),
+                R1csConstraint::symbolic(
+                    start + x,
+                    lc,
+                    vec![(T::F::one(), 0)],
+                    SymbolicOp::Mul,
+                    self.compiler_state.messages[0].clone(),
+                ),
+            ]);
+        }
+        Ok(Var {
+            index: Some(start),
+            location: VarLocation::Constraint,
+            value: Matrix {
+                dimensions,
+                values: vec![T::zero(); builtin::EXTENSION_DEGREE],
+            },
+        })
+    }
+
+    /// Constrain a new vector of 32 byte signals to be the sha256
+    /// or keccak256 digest of a vector of bytes. Each input byte
+    /// is constrained to be less than 256.
+    fn binary_hash(&mut self, name: &str, bytes: &Var<T>) -> Result<Var<T>> {
+        if bytes.value.dimensions.len() != 1 {
+            return log::error!(TypeError: &format!("{name} argument must be a vector of bytes"));
+        }
+        let bytes = self.var_to_signal(bytes)?;
+        let mut circuit = BinaryCircuit::new(self.var_index, &self.compiler_state.messages[0])?;
+        let input = (0..bytes.value.len())
+            .map(|i| circuit.decompose(vec![(T::F::one(), bytes.index.unwrap() + i)], 8))
+            .collect::<Vec<_>>();
+        let digest = if name == builtin::SHA256 {
+            sha256::hash(&mut circuit, &input)
+        } else {
+            keccak::hash(&mut circuit, &input)
+        };
+        let index = circuit.bytes_to_signals(&digest);
+        self.var_index = circuit.var_index;
+        self.constraints.append(&mut circuit.constraints);
+        Ok(Var {
+            index: Some(index),
+            location: VarLocation::Constraint,
+            value: Matrix {
+                dimensions: vec![digest.len()],
+                values: vec![T::zero(); digest.len()],
+            },
+        })
+    }
+
+    /// Constrain a new signal to be a linear combination raised
+    /// to the fifth power. Returns the index of the signal.
+    fn pow5(&mut self, lc: &[(T::F, usize)]) -> usize {
+        let x2 = self.var_index;
+        let x4 = x2 + 1;
+        let x5 = x2 + 2;
+        self.var_index += 3;
+        for (a, b, out) in [
+            (lc.to_vec(), lc.to_vec(), x2),
+            (vec![(T::F::one(), x2)], vec![(T::F::one(), x2)], x4),
+            (vec![(T::F::one(), x4)], lc.to_vec(), x5),
+        ] {
+            self.constraints.append(&mut vec![
+                R1csConstraint::new(
+                    a.clone(),
+                    b.clone(),
+                    vec![(T::F::one(), out)],
+                    &format!("poseidon s-box into {out}"),
+                )
+                .as_assignment(),
+                R1csConstraint::symbolic(
+                    out,
+                    a,
+                    b,
+                    SymbolicOp::Mul,
+                    self.compiler_state.messages[0].clone(),
+                ),
+            ]);
+        }
+        x5
+    }
+
+    fn eval_static_indices(&mut self, name: &String, indices: &[Expr]) -> Result<Vec<usize>> {
+        let index_vars = indices
+            .iter()
+            .map(|v| self.eval(v))
+            .collect::<Result<Vec<_>>>()?;
+        Self::static_indices(name, &index_vars)
+    }
+
+    fn static_indices(name: &String, index_vars: &[Var<T>]) -> Result<Vec<usize>> {
+        let mut new_indices = vec![];
+        for v in index_vars {
+            if v.value.len() != 1 || v.location != VarLocation::Static {
+                return log::error!(
+                    TypeError: "index notation must contain a scalar static expression in: {name}"
+                );
+            }
+            if let Ok(index) = v.value.values[0]
+                .to_scalar()?
+                .to_biguint()
+                .to_string()
+                .parse::<usize>()
+            {
+                new_indices.push(index);
             }
-            _ => unreachable!(),
         }
-        Ok(())
+        Ok(new_indices)
     }
 
-    /// Take a static variable and constrain it's current value
-    /// into a signal or set of signals
-    fn static_to_constraint(&mut self, matrix: &Matrix<T>) -> Result<Var<T>> {
-        let index = self.var_index;
-        for i in 0..matrix.len() {
-            // constrain each entry in the vector literal
-            // (v_i*one * 1*one) - v*one = 0
-            self.constraints.push(R1csConstraint::new(
-                vec![(T::F::one(), self.var_index + i)],
-                vec![(T::F::one(), 0)],
-                vec![(matrix.values[i].to_scalar()?, 0)],
-                &format!(
-                    "scalar literal ({}) to signal index ({}) (member of vector)",
-                    matrix.values[i], i,
+    /// Index a variable using signal and static indices. Each index
+    /// is applied to the outermost remaining dimension in turn.
+    fn eval_dynamic_indices(
+        &mut self,
+        name: &String,
+        v: Var<T>,
+        index_vars: Vec<Var<T>>,
+    ) -> Result<Var<T>> {
+        if index_vars.len() > v.value.dimensions.len() {
+            return log::error!(TypeError: &format!("too many indices for variable: {name}"));
+        }
+        let mut out = v;
+        for index in index_vars {
+            if index.value.len() != 1 {
+                return log::error!(TypeError: &format!(
+                    "index notation must contain a scalar expression in: {name}"
+                ));
+            }
+            if index.location == VarLocation::Static {
+                let i = Self::static_indices(name, &[index])?;
+                layout::offset(name, &out.value.dimensions, &i)?;
+                let (matrix, offset) = out.value.retrieve_indices(&i);
+                out = Var {
+                    index: out.index.map(|v| v + offset),
+                    location: out.location,
+                    value: matrix,
+                };
+            } else {
+                self.compiler_state
+                    .messages
+                    .insert(0, format!("dynamic index into {name}"));
+                out = self.select_dynamic_index(&out, index.index.unwrap())?;
+            }
+        }
+        Ok(out)
+    }
+
+    /// Read the element(s) at a signal index in the outermost
+    /// dimension of a variable. A one-hot selector is constrained
+    /// such that
+    ///
+    /// s_j * s_j = s_j
+    /// sum(s_j) = 1
+    /// sum(j * s_j) = index
+    ///
+    /// and the output is sum(s_j * v[j])
+    fn select_dynamic_index(&mut self, v: &Var<T>, index: usize) -> Result<Var<T>> {
+        let count = v.value.dimensions[0];
+        let slice_dimensions = v.value.dimensions[1..].to_vec();
+        let slice_len = slice_dimensions.iter().product::<usize>();
+        let selector_index = self.var_index;
+        self.var_index += count;
+        let mut sum = vec![];
+        let mut weighted_sum = vec![];
+        let mut j_coef = T::F::zero();
+        for j in 0..count {
+            let s = selector_index + j;
+            // (1*s) * (1*s) - (1*s) = 0
+            self.constraints.append(&mut vec![
+                R1csConstraint::symbolic(
+                    s,
+                    vec![(j_coef.clone(), 0)],
+                    vec![(T::F::one(), index)],
+                    SymbolicOp::Eq,
+                    self.compiler_state.messages[0].clone(),
                 ),
-            ));
-            self.constraints.push(R1csConstraint::symbolic(
-                self.var_index + i,
+                R1csConstraint::new(
+                    vec![(T::F::one(), s)],
+                    vec![(T::F::one(), s)],
+                    vec![(T::F::one(), s)],
+                    &format!("{s} is a bit"),
+                )
+                .as_assignment(),
+            ]);
+            sum.push((T::F::one(), s));
+            weighted_sum.push((j_coef.clone(), s));
+            j_coef += T::F::one();
+        }
+        // exactly one selector is set, and it's set
+        // at the position of the index. This is only satisfiable
+        // if the index is less than the count, so the index is
+        // always bounds checked
+        let comment = if self.compiler_state.checked_indexing {
+            format!("index {index} is less than {count}")
+        } else {
+            format!("one-hot selector for {index}")
+        };
+        self.constraints.append(&mut vec![
+            R1csConstraint::new(
+                sum,
                 vec![(T::F::one(), 0)],
-                vec![(matrix.values[i].to_scalar()?, 0)],
-                SymbolicOp::Mul,
-                format!(
-                    "scalar literal ({}) to signal index {} (member of vector)",
-                    matrix.values[i], i,
+                vec![(T::F::one(), 0)],
+                &comment,
+            ),
+            R1csConstraint::new(
+                weighted_sum,
+                vec![(T::F::one(), 0)],
+                vec![(T::F::one(), index)],
+                &format!("one-hot selector position is {index}"),
+            ),
+        ]);
+        let out_index = self.var_index;
+        self.var_index += slice_len;
+        for x in 0..slice_len {
+            let oi = out_index + x;
+            let mut out_lc = vec![];
+            if let Some(v_index) = v.index {
+                // multiply each selector by the corresponding element
+                let product_index = self.var_index;
+                self.var_index += count;
+                for j in 0..count {
+                    let s = selector_index + j;
+                    let ei = v_index + j * slice_len + x;
+                    let pi = product_index + j;
+                    self.constraints.append(&mut vec![
+                        R1csConstraint::new(
+                            vec![(T::F::one(), s)],
+                            vec![(T::F::one(), ei)],
+                            vec![(T::F::one(), pi)],
+                            &format!("select {ei} by {s} into {pi}"),
+                        )
+                        .as_assignment(),
+                        R1csConstraint::symbolic(
+                            pi,
+                            vec![(T::F::one(), s)],
+                            vec![(T::F::one(), ei)],
+                            SymbolicOp::Mul,
+                            self.compiler_state.messages[0].clone(),
+                        ),
+                    ]);
+                    out_lc.push((T::F::one(), pi));
+                }
+            } else {
+                // static elements are coefficients of the selectors
+                for j in 0..count {
+                    out_lc.push((
+                        v.value.values[j * slice_len + x].to_scalar()?,
+                        selector_index + j,
+                    ));
+                }
+            }
+            self.constraints.append(&mut vec![
+                R1csConstraint::new(
+                    out_lc.clone(),
+                    vec![(T::F::one(), 0)],
+                    vec![(T::F::one(), oi)],
+                    &format!("dynamic index {index} into {oi}"),
+                )
+                .as_assignment(),
+                R1csConstraint::symbolic(
+                    oi,
+                    out_lc,
+                    vec![(T::F::one(), 0)],
+                    SymbolicOp::Mul,
+                    self.compiler_state.messages[0].clone(),
                 ),
-            ));
+            ]);
         }
-        self.var_index += matrix.len();
         Ok(Var {
-            index: Some(index),
+            index: Some(out_index),
             location: VarLocation::Constraint,
-            value: matrix.clone(),
+            value: Matrix {
+                dimensions: if slice_dimensions.is_empty() {
+                    vec![1]
+                } else {
+                    slice_dimensions
+                },
+                values: vec![T::zero(); slice_len],
+            },
         })
     }
 
+    /// Assign a value to a variable that was passed to a function
+    /// by reference. If the reference is to part of a vector the
+    /// vector is copied into new signals with the referenced
+    /// elements replaced.
+    fn assign_reference(
+        &mut self,
+        name: &String,
+        indices: &[Expr],
+        new_var: &Var<T>,
+    ) -> Result<()> {
+        let v = match self.vars.get(name) {
+            Some(v) => v.clone(),
+            None => return log::error!(ScopeError: &format!("variable not found: {name}")),
+        };
+        let new_indices = self.eval_static_indices(name, indices)?;
+        let (matrix, offset) = v.value.retrieve_indices(&new_indices);
+        if matrix.dimensions != new_var.value.dimensions {
+            return log::error!(
+                TypeError: &format!("reference \"{name}\" was assigned a value of different dimensions"),
+                "a function may not change the dimensions of a referenced variable"
+            );
+        }
+        if new_indices.is_empty() {
+            self.vars.insert(name.clone(), new_var.clone());
+            self.name_signals(name);
+            return Ok(());
+        }
+        let v = self.var_to_signal(&v)?;
+        let new_var = self.var_to_signal(new_var)?;
+        let len = v.value.len();
+        let out_index = self.var_index;
+        self.var_index += len;
+        for x in 0..len {
+            let src = if x >= offset && x < offset + matrix.len() {
+                new_var.index.unwrap() + (x - offset)
+            } else {
+                v.index.unwrap() + x
+            };
+            let oi = out_index + x;
+            self.constraints.append(&mut vec![
+                R1csConstraint::new(
+                    vec![(T::F::one(), src)],
+                    vec![(T::F::one(), 0)],
+                    vec![(T::F::one(), oi)],
+                    &format!("copy {src} into {oi}"),
+                )
+                .as_assignment(),
+                R1csConstraint::symbolic(
+                    oi,
+                    vec![(T::F::one(), src)],
+                    vec![(T::F::one(), 0)],
+                    SymbolicOp::Mul,
+                    self.compiler_state.messages[0].clone(),
+                ),
+            ]);
+        }
+        self.vars.insert(
+            name.clone(),
+            Var {
+                index: Some(out_index),
+                location: VarLocation::Constraint,
+                value: v.value.clone(),
+            },
+        );
+        self.name_signals(name);
+        Ok(())
+    }
+
     pub fn eval(&mut self, expr: &Expr) -> Result<Var<T>> {
         match &expr {
             Expr::VecVec(_) | Expr::VecLit(_) => {
@@ -292,57 +2425,223 @@ where
                     value: Matrix { dimensions, values },
                 })
             }
+            Expr::FnCall(name, vars) if name == builtin::POSEIDON => {
+                self.compiler_state.messages.insert(0, format!("{name}()"));
+                let args = vars
+                    .iter()
+                    .map(|v| self.eval(v))
+                    .collect::<Result<Vec<_>>>()?;
+                self.poseidon(&args)
+            }
+            Expr::FnCall(name, vars) if name == builtin::CHALLENGE => {
+                self.compiler_state.messages.insert(0, format!("{name}()"));
+                let args = vars
+                    .iter()
+                    .map(|v| self.eval(v))
+                    .collect::<Result<Vec<_>>>()?;
+                self.challenge(&args)
+            }
+            Expr::FnCall(name, vars) if name == builtin::EDDSA_VERIFY => {
+                self.compiler_state.messages.insert(0, format!("{name}()"));
+                let args = vars
+                    .iter()
+                    .map(|v| self.eval(v))
+                    .collect::<Result<Vec<_>>>()?;
+                if args.len() != 6 {
+                    return log::error!(TypeError: &format!(
+                        "eddsa_verify expects 6 arguments (ax, ay, rx, ry, s, msg), received {}",
+                        args.len()
+                    ));
+                }
+                self.eddsa_verify(&args)?;
+                // eddsa_verify has no return value
+                Ok(Var {
+                    index: None,
+                    location: VarLocation::Static,
+                    value: Matrix::from(T::one()),
+                })
+            }
+            Expr::FnCall(name, vars) if name == builtin::IPA_VERIFY => {
+                self.compiler_state.messages.insert(0, format!("{name}()"));
+                let args = vars
+                    .iter()
+                    .map(|v| self.eval(v))
+                    .collect::<Result<Vec<_>>>()?;
+                if args.len() != 7 {
+                    return log::error!(TypeError: &format!(
+                        "ipa_verify expects 7 arguments (p, c, l, r, x_inv, a, b), received {}",
+                        args.len()
+                    ));
+                }
+                self.ipa_verify(&args)?;
+                // ipa_verify has no return value
+                Ok(Var {
+                    index: None,
+                    location: VarLocation::Static,
+                    value: Matrix::from(T::one()),
+                })
+            }
+            Expr::FnCall(name, vars) if name == builtin::SHA256 || name == builtin::KECCAK256 => {
+                self.compiler_state.messages.insert(0, format!("{name}()"));
+                if vars.len() != 1 {
+                    return log::error!(TypeError: &format!(
+                        "{name} expects 1 argument (bytes), received {}",
+                        vars.len()
+                    ));
+                }
+                let bytes = self.eval(&vars[0])?;
+                self.binary_hash(name, &bytes)
+            }
+            Expr::FnCall(name, vars) if name == builtin::XFE_MUL || name == builtin::XFE_INV => {
+                self.compiler_state.messages.insert(0, format!("{name}()"));
+                let args = vars
+                    .iter()
+                    .map(|v| self.eval(v))
+                    .collect::<Result<Vec<_>>>()?;
+                self.extension_op(name, &args)
+            }
+            Expr::FnCall(name, vars) if name == builtin::SLICE => {
+                if vars.len() != 3 {
+                    return log::error!(TypeError: &format!(
+                        "{name} expects 3 arguments (value, start, end), received {}",
+                        vars.len()
+                    ));
+                }
+                let v = self.eval(&vars[0])?;
+                let var_name = match &vars[0] {
+                    Expr::Val(name, _) => name.clone(),
+                    _ => "value".to_string(),
+                };
+                let bounds = vars[1..]
+                    .iter()
+                    .map(|v| self.eval(v))
+                    .collect::<Result<Vec<_>>>()?;
+                let bounds = Self::static_indices(&var_name, &bounds)?;
+                if bounds.len() != 2 {
+                    return log::error!(TypeError: &format!("invalid range bounds for: {var_name}"));
+                }
+                // a slice reads the same signals as the value
+                let (offset, dimensions) =
+                    layout::slice(&var_name, &v.value.dimensions, bounds[0], bounds[1])?;
+                let len = layout::len(&dimensions);
+                Ok(Var {
+                    index: v.index.map(|v| v + offset),
+                    location: v.location,
+                    value: Matrix {
+                        dimensions,
+                        values: v.value.values[offset..offset + len].to_vec(),
+                    },
+                })
+            }
+            Expr::FnCall(name, vars) if name == builtin::NEG => {
+                if vars.len() != 1 {
+                    return log::error!(TypeError: &format!(
+                        "{name} expects 1 argument, received {}",
+                        vars.len()
+                    ));
+                }
+                // subtracted from a static zero, so negating a
+                // signal is free
+                let v = self.eval(&vars[0])?;
+                let zero = Var {
+                    index: None,
+                    location: VarLocation::Static,
+                    value: Matrix {
+                        dimensions: v.value.dimensions.clone(),
+                        values: vec![T::zero(); v.value.values.len()],
+                    },
+                };
+                self.eval_numop_vars(&zero, &NumOp::Sub, &v)
+            }
+            Expr::FnCall(name, vars) if builtin::REDUCTIONS.contains(&name.as_str()) => {
+                self.compiler_state.messages.insert(0, format!("{name}()"));
+                if vars.len() != 1 {
+                    return log::error!(TypeError: &format!(
+                        "{name} expects 1 argument, received {}",
+                        vars.len()
+                    ));
+                }
+                let v = self.eval(&vars[0])?;
+                self.reduce(name, &v)
+            }
+            Expr::FnCall(name, vars) if name == builtin::POW => {
+                self.compiler_state.messages.insert(0, format!("{name}()"));
+                if vars.len() != 2 {
+                    return log::error!(TypeError: &format!(
+                        "{name} expects 2 arguments, received {}",
+                        vars.len()
+                    ));
+                }
+                let v = self.eval(&vars[0])?;
+                let e = self.eval(&vars[1])?;
+                if e.location != VarLocation::Static || e.value.len() != 1 {
+                    return log::error!(
+                        TypeError: &format!("the exponent of {name} must be a static scalar in r1cs"),
+                        "declare the exponent with `static`"
+                    );
+                }
+                self.pow(&v, &e.value.values[0].to_scalar()?.to_biguint())
+            }
+            Expr::FnCall(name, vars) if name == builtin::DOT || name == builtin::MATMUL => {
+                self.compiler_state.messages.insert(0, format!("{name}()"));
+                if vars.len() != 2 {
+                    return log::error!(TypeError: &format!(
+                        "{name} expects 2 arguments, received {}",
+                        vars.len()
+                    ));
+                }
+                let lhs = self.eval(&vars[0])?;
+                let rhs = self.eval(&vars[1])?;
+                self.sum_of_products(name, &lhs, &rhs)
+            }
+            Expr::FnCall(name, vars) if name == builtin::MERKLE_VERIFY => {
+                self.compiler_state.messages.insert(0, format!("{name}()"));
+                let args = vars
+                    .iter()
+                    .map(|v| self.eval(v))
+                    .collect::<Result<Vec<_>>>()?;
+                if args.len() != 4 {
+                    return log::error!(TypeError: &format!(
+                        "merkle_verify expects 4 arguments (root, leaf, path, indices), received {}",
+                        args.len()
+                    ));
+                }
+                self.merkle_verify(&args[0], &args[1], &args[2], &args[3])?;
+                // merkle_verify has no return value
+                Ok(Var {
+                    index: None,
+                    location: VarLocation::Static,
+                    value: Matrix::from(T::one()),
+                })
+            }
             Expr::FnCall(name, vars) => {
                 // TODO: break this into separate functions
                 let path = self.compiler_state.fn_to_path.get(name).unwrap();
                 self.compiler_state
                     .messages
                     .insert(0, format!("{}() ({})", name, path));
-                let args: Vec<Var<T>> = vars.iter().map(|v| self.eval(v)).collect::<Result<_>>()?;
+                // references alias the signals of the referenced
+                // variable, the callee bindings are written back
+                // after the call
+                let args: Vec<Var<T>> = vars
+                    .iter()
+                    .map(|v| match v {
+                        Expr::Ref(name, indices) => {
+                            self.eval(&Expr::Val(name.clone(), indices.clone()))
+                        }
+                        _ => self.eval(v),
+                    })
+                    .collect::<Result<_>>()?;
                 // look for an ar1cs implementation first
-                if let Some(v) = self.compiler_state.fn_to_r1cs_parser.get(name) {
-                    let constrain_args_if_needed = args
-                        .iter()
-                        .map(|v| {
-                            if let Some(i) = v.index {
-                                return Ok(i);
-                            }
-                            if v.value.len() != 1 {
-                                return log::error!(
-                                    "cannot pass a vector static to an r1cs function"
-                                );
-                            }
-                            // if we get a static variable we need to
-                            // assert equality of it's current value
-                            // to turn it into a signal
-                            // log::error!("cannot pass a static variable to a r1cs function");
-                            let index = self.var_index;
-                            self.var_index += 1;
-                            self.constraints.push(R1csConstraint::new(
-                                vec![(T::F::one(), index)],
-                                vec![(T::F::one(), 0)],
-                                vec![(v.value.values[0].to_scalar()?, 0)],
-                                &format!(
-                                    "assigning literal ({}) to signal {index}",
-                                    v.value.values[0]
-                                ),
-                            ));
-                            self.constraints.push(R1csConstraint::symbolic(
-                                index,
-                                vec![(v.value.values[0].to_scalar()?, 0)],
-                                vec![(T::F::zero(), 0)],
-                                SymbolicOp::Add,
-                                self.compiler_state.messages[0].clone(),
-                            ));
-                            Ok(index)
-                        })
-                        .collect::<Result<_>>()?;
-                    let out_constraints =
-                        v.signals_as_args(self.var_index, constrain_args_if_needed)?;
+                if self.compiler_state.fn_to_r1cs_parser.contains_key(name) {
+                    let signals = self.ar1cs_args(&args)?;
+                    let v = self.compiler_state.fn_to_r1cs_parser.get(name).unwrap();
+                    let out_constraints = v.signals_as_args(self.var_index, signals)?;
                     self.constraints.append(&mut out_constraints.clone());
                     let return_index = self.var_index;
-                    self.var_index += v.return_names.len();
-                    return if !v.return_names.is_empty() {
+                    self.var_index += v.new_signal_count();
+                    let return_count = v.return_names.len();
+                    return if return_count == 1 {
                         Ok(Var {
                             index: Some(return_index),
                             location: VarLocation::Constraint,
@@ -350,6 +2649,16 @@ where
                             // use the symbolic constraint to determine the value
                             value: Matrix::from(T::zero()),
                         })
+                    } else if return_count > 1 {
+                        // multiple return values are returned as a vector
+                        Ok(Var {
+                            index: Some(return_index),
+                            location: VarLocation::Constraint,
+                            value: Matrix {
+                                dimensions: vec![return_count],
+                                values: vec![T::zero(); return_count],
+                            },
+                        })
                     } else {
                         Ok(Var {
                             index: None,
@@ -360,19 +2669,35 @@ where
                 }
                 let fn_ast = self.compiler_state.fn_to_ast.get(name);
                 if fn_ast.is_none() {
-                    return log::error!("function not found: {name}");
+                    return log::error!(ScopeError: "function not found: {name}");
                 }
                 let fn_ast = fn_ast.unwrap().clone();
-                let mut vm = VM::from(self, args, name);
-                vm.eval_ast(fn_ast)?;
-                let return_val = vm.return_val;
-                let new_var_index = vm.var_index;
-                let mut out_constraints = vm.constraints;
-                self.constraints.append(&mut out_constraints);
-                self.var_index = new_var_index;
+                let arg_names = fn_ast
+                    .iter()
+                    .find_map(|v| match v {
+                        AstNode::FnVar(names) => Some(names.clone()),
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                let (return_val, bindings) = self.eval_fn_ast(name, fn_ast, &args, &arg_names)?;
+                for (i, v) in vars.iter().enumerate() {
+                    if let Expr::Ref(ref_name, indices) = v {
+                        if let Some(Some(new_var)) = bindings.get(i) {
+                            if *new_var != args[i] {
+                                self.compiler_state
+                                    .messages
+                                    .insert(0, format!("write reference {ref_name}"));
+                                self.assign_reference(ref_name, indices, new_var)?;
+                            }
+                        }
+                    }
+                }
                 if let Some(v) = return_val {
                     Ok(v)
                 } else {
+                    // the compiler ensures functions without a return
+                    // statement are only called as statements
+                    // so this value is never used
                     Ok(Var {
                         index: None,
                         location: VarLocation::Static,
@@ -380,34 +2705,26 @@ where
                     })
                 }
             }
+            Expr::Ref(_, _) => {
+                log::error!(TypeError: "references may only be passed as function arguments")
+            }
             Expr::Val(name, indices) => {
-                let mut new_indices = vec![];
-                for index_expr in indices {
-                    let v = self.eval(index_expr)?;
-                    if v.value.len() != 1 || v.location != VarLocation::Static {
-                        return log::error!(
-                            "index notation must contain a scalar static expression in: {name}"
-                        );
-                    }
-                    if v.value.len() != 1 {
-                        return log::error!(
-                            "index notation must contain a scalar static expression in: {name}"
-                        );
-                    }
-                    if let Ok(index) = v.value.values[0]
-                        .to_scalar()?
-                        .to_biguint()
-                        .to_string()
-                        .parse::<usize>()
-                    {
-                        new_indices.push(index);
-                    }
-                }
+                let index_vars = indices
+                    .iter()
+                    .map(|v| self.eval(v))
+                    .collect::<Result<Vec<_>>>()?;
                 let v = self.vars.get(name);
                 if v.is_none() {
-                    return log::error!(&format!("variable not found: {name}"));
+                    return log::error!(ScopeError: &format!("variable not found: {name}"));
+                }
+                let v = v.unwrap().clone();
+                if index_vars
+                    .iter()
+                    .any(|v| v.location == VarLocation::Constraint)
+                {
+                    return self.eval_dynamic_indices(name, v, index_vars);
                 }
-                let v = v.unwrap();
+                let new_indices = Self::static_indices(name, &index_vars)?;
                 let (matrix, offset) = v.value.retrieve_indices(&new_indices);
                 if let Some(index) = v.index {
                     Ok(Var {
@@ -423,7 +2740,28 @@ where
                     })
                 }
             }
-            Expr::NumOp { lhs, op, rhs } => self.eval_numop(lhs, op, rhs),
+            Expr::NumOp { lhs, op, rhs } => {
+                let int_type = expr.int_type(&|name| self.int_types.get(name).copied())?;
+                let out = self.eval_numop(lhs, op, rhs)?;
+                if let Some(int_type) = int_type {
+                    self.assert_int(&out, int_type)?;
+                }
+                Ok(out)
+            }
+            Expr::BitOp { lhs, bit_op, rhs } => {
+                let int_type = match expr.int_type(&|name| self.int_types.get(name).copied())? {
+                    Some(int_type) => int_type,
+                    None => {
+                        return log::error!(
+                            TypeError: "bitwise operators require integer operands",
+                            "declare an operand with an integer type e.g. `let x: u32 = 1`"
+                        )
+                    }
+                };
+                let lv = self.eval(lhs)?;
+                let rv = self.eval(rhs)?;
+                self.eval_bitop(&lv, bit_op, &rv, int_type)
+            }
             Expr::Lit(val) => Ok(Var {
                 index: None,
                 location: VarLocation::Static,
@@ -435,6 +2773,91 @@ where
         }
     }
 
+    // evaluate the body of an ash function, returning the return value
+    // and the final binding of each argument. If a cache is configured
+    // a previous evaluation with the same source and argument types is
+    // reused instead
+    fn eval_fn_ast(
+        &mut self,
+        name: &str,
+        fn_ast: Vec<AstNode>,
+        args: &[Var<T>],
+        arg_names: &[String],
+    ) -> Result<(Option<Var<T>>, Vec<Option<Var<T>>>)> {
+        let signals = CallSignals::new(args, self.var_index);
+        // cached calls are not evaluated, so their signals are
+        // not named. Calls in a branch with a signal condition may
+        // add constraints that never hold and are not cached
+        let key = match &self.compiler_state.cache {
+            Some(_) if self.compiler_state.symbols.is_none() && !self.conditional => signals
+                .key(name, &self.compiler_state.fn_hash(name), args)
+                .ok(),
+            _ => None,
+        };
+        if let (Some(cache), Some(key)) = (&self.compiler_state.cache, &key) {
+            // entries that cannot be read are rebuilt
+            let cached = cache
+                .get(key)
+                .and_then(|src| CachedCall::<T>::decode(&src, &signals).ok());
+            if let Some(mut call) = cached {
+                self.constraints.append(&mut call.constraints);
+                self.var_index += call.signal_count;
+                return Ok((call.return_val, call.bindings));
+            }
+        }
+        // recursive calls are inlined up to the declared max depth
+        let depth = self
+            .compiler_state
+            .fn_depth
+            .get(name)
+            .copied()
+            .unwrap_or_default();
+        if depth > 0 {
+            match self.compiler_state.fn_max_depth.get(name) {
+                None => {
+                    return log::error!(
+                        TypeError: &format!("function \"{name}\" calls itself without a max recursion depth"),
+                        "declare the maximum number of nested calls in the function header e.g. `(n) recursive 16`"
+                    )
+                }
+                Some(max_depth) if depth >= *max_depth => {
+                    return log::error!(
+                        TypeError: &format!("function \"{name}\" exceeded its max recursion depth of {max_depth}"),
+                        "recursion is inlined in r1cs, the condition that ends it must be static"
+                    )
+                }
+                _ => {}
+            }
+        }
+        self.compiler_state
+            .fn_depth
+            .insert(name.to_string(), depth + 1);
+        let start = self.var_index;
+        let transcript = self.compiler_state.transcript;
+        let mut vm = VM::from(self, args.to_vec(), name);
+        vm.eval_ast(fn_ast)?;
+        vm.compiler_state.fn_depth.insert(name.to_string(), depth);
+        let mut call = CachedCall {
+            signal_count: vm.var_index - start,
+            return_val: vm.return_val,
+            bindings: arg_names.iter().map(|v| vm.vars.get(v).cloned()).collect(),
+            constraints: vm.constraints,
+        };
+        // calls that update the transcript depend on earlier
+        // challenges and are not cached
+        let key = key.filter(|_| self.compiler_state.transcript == transcript);
+        if let (Some(cache), Some(key)) = (&self.compiler_state.cache, &key) {
+            // calls that refer to signals outside of their arguments
+            // are not cached
+            if let Ok(src) = call.encode(&signals) {
+                cache.put(key, &src)?;
+            }
+        }
+        self.constraints.append(&mut call.constraints);
+        self.var_index += call.signal_count;
+        Ok((call.return_val, call.bindings))
+    }
+
     // handle the following cases
     // 1. lhs and rhs are both constraint variables
     // 2. lhs is a constraint variable and rhs is a static (and vis-versa)
@@ -442,18 +2865,22 @@ where
     fn eval_numop(&mut self, lhs: &Expr, op: &NumOp, rhs: &Expr) -> Result<Var<T>> {
         let lv = self.eval(lhs)?;
         let rv = self.eval(rhs)?;
+        self.eval_numop_vars(&lv, op, &rv)
+    }
+
+    fn eval_numop_vars(&mut self, lv: &Var<T>, op: &NumOp, rv: &Var<T>) -> Result<Var<T>> {
         // check that the variables are the same type
         lv.value.assert_eq_shape(&rv.value);
 
         if lv.location == VarLocation::Constraint && rv.location == VarLocation::Constraint {
             // both are signals
-            self.eval_numop_signals(&lv, op, &rv)
+            self.eval_numop_signals(lv, op, rv)
         } else if lv.location != rv.location {
             // one is signal one is static
-            self.eval_numop_signal_static(&lv, op, &rv)
+            self.eval_numop_signal_static(lv, op, rv)
         } else {
             // both static
-            self.eval_numop_static(&lv, op, &rv)
+            self.eval_numop_static(lv, op, rv)
         }
     }
 
@@ -513,7 +2940,8 @@ where
                             vec![(T::F::one(), 0)],
                             vec![(T::F::one(), ovi)],
                             &format!("addition between ({cv}) and {svi} into {ovi}"),
-                        ),
+                        )
+                        .as_assignment(),
                         R1csConstraint::symbolic(
                             ovi,
                             vec![(cv.to_scalar()?, 0), (T::F::one(), svi)],
@@ -545,7 +2973,8 @@ where
                             vec![(T::F::one(), svi)],
                             vec![(T::F::one(), ovi)],
                             &format!("multiplication between ({cv}) and {svi} into {ovi}"),
-                        ),
+                        )
+                        .as_assignment(),
                         R1csConstraint::symbolic(
                             ovi,
                             vec![(cv.to_scalar()?, 0)],
@@ -579,7 +3008,8 @@ where
                                 vec![(T::F::one(), 0)],
                                 vec![(T::F::one(), lvi)],
                                 &format!("subtraction between {lvi} and ({cv}) into {ovi}"),
-                            ),
+                            )
+                            .as_assignment(),
                             R1csConstraint::symbolic(
                                 ovi,
                                 vec![(T::F::one(), lvi), (-cv.to_scalar()?, 0)],
@@ -604,7 +3034,8 @@ where
                                 vec![(T::F::one(), 0)],
                                 vec![(lv.to_scalar()?, 0)],
                                 &format!("subtraction between ({lv}) and {rvi} into {ovi}"),
-                            ),
+                            )
+                            .as_assignment(),
                             R1csConstraint::symbolic(
                                 ovi,
                                 vec![(lv.to_scalar()?, 0), (-T::F::one(), rvi)],
@@ -641,7 +3072,8 @@ where
                                 vec![(T::F::one(), 0)],
                                 vec![(T::F::one(), ovi)],
                                 &format!("modinv between {lvi} and ({cv}) into {ovi}"),
-                            ),
+                            )
+                            .as_assignment(),
                             R1csConstraint::symbolic(
                                 ovi,
                                 vec![(icv.to_scalar()?, lvi)],
@@ -693,7 +3125,8 @@ where
                                 vec![(T::F::one(), 0)],
                                 vec![(T::F::one(), ovi)],
                                 &format!("multiply {rvi} and ({lv}) into {ovi}"),
-                            ),
+                            )
+                            .as_assignment(),
                             R1csConstraint::symbolic(
                                 ovi,
                                 vec![(lv.to_scalar()?, rvi)],
@@ -732,7 +3165,8 @@ where
                             vec![(T::F::one(), 0)],
                             vec![(T::F::one(), ovi)],
                             &format!("addition between {lvi} and {rvi} into {ovi}"),
-                        ),
+                        )
+                        .as_assignment(),
                         R1csConstraint::symbolic(
                             ovi,
                             vec![(T::F::one(), lvi), (T::F::one(), rvi)],
@@ -763,7 +3197,8 @@ where
                             vec![(T::F::one(), rvi)],
                             vec![(T::F::one(), ovi)],
                             &format!("multiplication between {lvi} and {rvi} into {ovi}"),
-                        ),
+                        )
+                        .as_assignment(),
                         R1csConstraint::symbolic(
                             ovi,
                             vec![(T::F::one(), lvi)],
@@ -794,7 +3229,8 @@ where
                             vec![(T::F::one(), 0)],
                             vec![(T::F::one(), ovi)],
                             &format!("subtraction between {lvi} and {rvi} into {ovi}"),
-                        ),
+                        )
+                        .as_assignment(),
                         R1csConstraint::symbolic(
                             ovi,
                             vec![(T::F::one(), lvi), (-T::F::one(), rvi)],
@@ -854,7 +3290,8 @@ where
                             vec![(T::F::one(), rvi)],
                             vec![(T::F::one(), ovi)],
                             &format!("multiplication of {lvi} and {rvi} into {ovi} (2/2)"),
-                        ),
+                        )
+                        .as_assignment(),
                         R1csConstraint::symbolic(
                             ovi,
                             vec![(T::F::one(), lvi)],
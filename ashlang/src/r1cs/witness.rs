@@ -1,33 +1,249 @@
 use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::BufRead;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
 
 use anyhow::Result;
+use camino::Utf8Path;
+#[cfg(not(target_arch = "wasm32"))]
+use rayon::prelude::*;
 use ring_math::PolynomialRingElement;
+#[cfg(not(target_arch = "wasm32"))]
+use ring_math::Vector;
 use scalarff::FieldElement;
 
+use crate::r1cs::ar1cs;
+use crate::r1cs::encoding::WitnessWriter;
 use crate::r1cs::parser::R1csParser;
 
+use super::constraint::index_to_string;
+use super::constraint::R1csConstraint;
 use super::constraint::SymbolicOp;
+use super::constraint::ASSERT_COMMENT;
+use super::solver;
 
 /// A structure representing a witness computation
 ///
 /// `outputs`: a list of indices of variables that should be publicly revealed
 /// `variables`: values of the variables in the computation
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Witness<T: FieldElement> {
     pub outputs: Vec<usize>,
     pub variables: Vec<T>,
 }
 
+/// A single step of witness calculation, or the check of a
+/// constraint against a witness.
+#[derive(Clone, Debug)]
+pub struct TraceStep<T: FieldElement> {
+    /// index of the constraint in the ar1cs source
+    pub constraint: usize,
+    /// the operation solved, `None` when a constraint is checked
+    pub symbolic_op: Option<SymbolicOp>,
+    pub comment: Option<String>,
+    /// the signal assigned by a symbolic step
+    pub out: Option<usize>,
+    /// the value of each factor, `a` and `b` and for a check `c`,
+    /// `None` if a signal in the factor is not assigned
+    pub operands: Vec<Option<T>>,
+    /// the value assigned by a symbolic step
+    pub value: Option<T>,
+    /// the value of each signal read by the step
+    pub signals: Vec<(usize, T)>,
+    /// why the step failed, if it did
+    pub error: Option<String>,
+}
+
+/// A record of each step of witness calculation and
+/// verification, used to find where a witness diverges from
+/// the constraints.
+#[derive(Clone, Debug, Default)]
+pub struct WitnessTrace<T: FieldElement> {
+    pub steps: Vec<TraceStep<T>>,
+    // signal index keyed to the step that assigned it
+    assigned: HashMap<usize, usize>,
+}
+
+impl<T: FieldElement> WitnessTrace<T> {
+    pub fn new() -> Self {
+        Self {
+            steps: vec![],
+            assigned: HashMap::new(),
+        }
+    }
+
+    // record a step for a constraint, values that are not yet
+    // known are omitted
+    fn record(
+        &mut self,
+        index: usize,
+        c: &R1csConstraint<T>,
+        vars: &HashMap<usize, T>,
+        value: Option<T>,
+        error: Option<String>,
+    ) {
+        let lc = |lc: &Vec<(T, usize)>| {
+            lc.iter().try_fold(T::zero(), |acc, (coef, i)| {
+                vars.get(i).map(|v| acc + coef.clone() * v.clone())
+            })
+        };
+        let mut factors = vec![&c.a, &c.b];
+        if !c.symbolic {
+            factors.push(&c.c);
+        }
+        let mut signals = vec![];
+        for (_, i) in factors.iter().flat_map(|v| v.iter()) {
+            if let Some(v) = vars.get(i) {
+                if !signals.iter().any(|(j, _)| j == i) {
+                    signals.push((*i, v.clone()));
+                }
+            }
+        }
+        if let (Some(out), true) = (c.out_i, value.is_some()) {
+            if c.symbolic_op != Some(SymbolicOp::Output) {
+                self.assigned.insert(out, self.steps.len());
+            }
+        }
+        self.steps.push(TraceStep {
+            constraint: index,
+            symbolic_op: c.symbolic_op.clone(),
+            comment: c.comment.clone(),
+            out: c.out_i,
+            operands: factors.into_iter().map(lc).collect(),
+            value,
+            signals,
+            error,
+        });
+    }
+
+    /// The step that assigned a signal.
+    pub fn origin(&self, signal: usize) -> Option<&TraceStep<T>> {
+        self.assigned.get(&signal).map(|i| &self.steps[*i])
+    }
+
+    /// The first step that failed.
+    pub fn failure(&self) -> Option<&TraceStep<T>> {
+        self.steps.iter().find(|v| v.error.is_some())
+    }
+
+    /// Describe the first failed step and the steps that
+    /// assigned each signal it reads.
+    pub fn divergence(&self) -> Option<String> {
+        let step = self.failure()?;
+        let mut out = vec![format!("witness diverges at {step}")];
+        for (signal, value) in &step.signals {
+            match self.origin(*signal) {
+                Some(origin) => out.push(format!("  {signal} = {value} assigned by {origin}")),
+                None if *signal == 0 => {}
+                None => out.push(format!("  {signal} = {value}")),
+            }
+        }
+        Some(out.join("\n"))
+    }
+
+    /// Write the trace to a file.
+    pub fn write(&self, path: &Utf8Path) -> Result<()> {
+        std::fs::write(path, format!("{self}\n"))?;
+        Ok(())
+    }
+}
+
+impl<T: FieldElement> Display for TraceStep<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let value = |v: &Option<T>| match v {
+            Some(v) => v.to_string(),
+            None => "?".to_string(),
+        };
+        let operands = self.operands.iter().map(value).collect::<Vec<_>>();
+        write!(f, "constraint {}: ", self.constraint)?;
+        match (&self.symbolic_op, self.out) {
+            (
+                Some(op @ (SymbolicOp::Input | SymbolicOp::PublicInput | SymbolicOp::Output)),
+                Some(out),
+            ) => write!(f, "{out} = {} ({op})", value(&self.value))?,
+            (Some(op), Some(out)) => write!(
+                f,
+                "{out} = {} = {} {op} {}",
+                value(&self.value),
+                operands[0],
+                operands[1]
+            )?,
+            _ => write!(f, "{} * {} = {}", operands[0], operands[1], operands[2])?,
+        }
+        if let Some(comment) = &self.comment {
+            write!(f, " # {comment}")?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, " FAILED: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: FieldElement> Display for WitnessTrace<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            writeln!(f, "{i:>6}  {step}")?;
+        }
+        match self.divergence() {
+            Some(divergence) => write!(f, "{divergence}"),
+            None => write!(f, "all {} steps succeeded", self.steps.len()),
+        }
+    }
+}
+
+/// The values written to the public outputs of a program by a
+/// witness, as decimal strings in the order they are written.
+/// Public inputs are not included.
+pub fn public_outputs<T: PolynomialRingElement>(
+    r1cs: &str,
+    variables: &[T::F],
+) -> Result<Vec<String>> {
+    let r1cs: R1csParser<T> = R1csParser::new(r1cs)?;
+    r1cs.constraints
+        .iter()
+        .filter(|c| c.symbolic && c.symbolic_op == Some(SymbolicOp::Output))
+        .map(|c| match variables.get(c.out_i.unwrap()) {
+            Some(v) => Ok(v.to_string()),
+            None => crate::log::error!(InputError: "witness is missing a public output"),
+        })
+        .collect()
+}
+
 /// Verify that a witness satisfies the constraints of an ar1cs source string.
 /// This function handles parsing the ar1cs source string.
 pub fn verify<T: PolynomialRingElement>(r1cs: &str, witness: Witness<T::F>) -> Result<Vec<T::F>> {
+    verify_inner::<T>(r1cs, witness, None)
+}
+
+/// Verify a witness and record the check of each constraint
+/// in a trace.
+pub fn verify_traced<T: PolynomialRingElement>(
+    r1cs: &str,
+    witness: Witness<T::F>,
+    trace: &mut WitnessTrace<T::F>,
+) -> Result<Vec<T::F>> {
+    verify_inner::<T>(r1cs, witness, Some(trace))
+}
+
+fn verify_inner<T: PolynomialRingElement>(
+    r1cs: &str,
+    witness: Witness<T::F>,
+    mut trace: Option<&mut WitnessTrace<T::F>>,
+) -> Result<Vec<T::F>> {
     // confirm that the witness is correct
+    let symbols = ar1cs::symbols(r1cs)?;
+    let names = ar1cs::signal_names(&symbols);
     let r1cs: R1csParser<T> = R1csParser::new(r1cs)?;
     let mut vars: HashMap<usize, T::F> = HashMap::new();
     for (i, v) in witness.variables.iter().enumerate() {
         vars.insert(i, v.clone());
     }
 
-    for c in &r1cs.constraints {
+    for (i, c) in r1cs.constraints.iter().enumerate() {
         if c.symbolic {
             continue;
         }
@@ -43,8 +259,34 @@ pub fn verify<T: PolynomialRingElement>(r1cs: &str, witness: Witness<T::F>) -> R
         for (coef, index) in &c.c {
             c_lc += coef.clone() * vars.get(index).unwrap().clone();
         }
-        if a_lc.clone() * b_lc.clone() != c_lc {
-            anyhow::bail!("Constraint failed: {:?}", c)
+        let satisfied = a_lc.clone() * b_lc.clone() == c_lc;
+        if let Some(trace) = trace.as_deref_mut() {
+            let error = (!satisfied).then(|| "constraint is not satisfied".to_string());
+            trace.record(i, c, &vars, None, error);
+        }
+        if !satisfied {
+            let labels = label_signals(c, &names);
+            if let Some(message) = c
+                .comment
+                .as_ref()
+                .and_then(|v| v.strip_prefix(ASSERT_COMMENT))
+            {
+                let hint = match labels {
+                    Some(labels) => format!("constraint: {c}\nsignals: {labels}"),
+                    None => format!("constraint: {c}"),
+                };
+                return crate::log::error!(
+                    InputError: &format!("assertion failed: {message}"),
+                    &hint
+                );
+            }
+            if let Some(labels) = labels {
+                return crate::log::error!(
+                    InputError: &format!("Constraint failed: {:?}", c),
+                    &format!("signals: {labels}")
+                );
+            }
+            return crate::log::error!(InputError: &format!("Constraint failed: {:?}", c));
         }
     }
     Ok(witness
@@ -56,68 +298,378 @@ pub fn verify<T: PolynomialRingElement>(r1cs: &str, witness: Witness<T::F>) -> R
 
 /// Take an ar1cs source file and a set of inputs and build a witness.
 pub fn build<T: PolynomialRingElement>(r1cs: &str, inputs: Vec<T>) -> Result<Witness<T::F>> {
+    build_inner(r1cs, inputs, None)
+}
+
+/// Build a witness and record each symbolic step in a trace.
+/// The trace contains the steps before a failure.
+pub fn build_traced<T: PolynomialRingElement>(
+    r1cs: &str,
+    inputs: Vec<T>,
+    trace: &mut WitnessTrace<T::F>,
+) -> Result<Witness<T::F>> {
+    build_inner(r1cs, inputs, Some(trace))
+}
+
+fn build_inner<T: PolynomialRingElement>(
+    r1cs: &str,
+    inputs: Vec<T>,
+    mut trace: Option<&mut WitnessTrace<T::F>>,
+) -> Result<Witness<T::F>> {
     let r1cs: R1csParser<T> = R1csParser::new(r1cs)?;
     let mut vars: HashMap<usize, T::F> = HashMap::new();
-    let mut outputs = vec![];
     let mut input_counter = 0_usize;
     vars.insert(0, T::F::one());
     // build the witness
-    for c in &r1cs.constraints {
-        if !c.symbolic {
-            continue;
-        }
-        match c.symbolic_op.as_ref().unwrap() {
-            SymbolicOp::Input => {
-                // we'll take the next input value and set it
-                if input_counter >= inputs.len() {
-                    return crate::log::error!(
-                        "not enough inputs supplied to fulfill symbolic constraints",
-                        "the number of inputs must match the number of input constraints"
-                    );
+    for i in solver::order(&r1cs.constraints)? {
+        let c = &r1cs.constraints[i];
+        let step = solve_step(c, &inputs, &mut input_counter, &vars);
+        if let Some(trace) = trace.as_deref_mut() {
+            match &step {
+                // outputs are traced with their existing value
+                Ok(v) => {
+                    let value = v
+                        .clone()
+                        .or_else(|| c.out_i.and_then(|i| vars.get(&i).cloned()));
+                    trace.record(i, c, &vars, value, None);
                 }
-                vars.insert(c.out_i.unwrap(), inputs[input_counter].to_scalar()?);
-                input_counter += 1;
+                Err(e) => trace.record(i, c, &vars, None, Some(e.to_string())),
             }
-            SymbolicOp::PublicInput => {
-                // we'll take the relevant signal and mark it as public
-                if input_counter >= inputs.len() {
-                    return crate::log::error!(
-                        "not enough inputs supplied to fulfill symbolic constraints",
-                        "the number of inputs must match the number of input constraints"
-                    );
-                }
-                outputs.push(c.out_i.unwrap());
-                vars.insert(c.out_i.unwrap(), inputs[input_counter].to_scalar()?);
-                input_counter += 1;
-            }
-            SymbolicOp::Output => {
-                outputs.push(c.out_i.unwrap());
+        }
+        if let Some(v) = step? {
+            vars.insert(c.out_i.unwrap(), v);
+        }
+    }
+    if input_counter != inputs.len() {
+        return crate::log::error!(InputError: &format!(
+            "not all inputs were used in witness calculation, {} inputs unused",
+            inputs.len() - input_counter
+        ));
+    }
+    let mut out = vars.keys().copied().collect::<Vec<usize>>();
+    out.sort();
+    Ok(Witness {
+        outputs: solver::public_signals(&r1cs.constraints),
+        variables: out
+            .iter()
+            .map(|k| vars.get(k).unwrap().clone())
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Build a witness using up to `jobs` threads.
+///
+/// Symbolic constraints are ordered using `solver::order`, then
+/// grouped into levels where each constraint reads only signals
+/// assigned in lower levels, and the constraints in a level are
+/// solved in parallel. Inputs are taken in order before any
+/// constraint is solved.
+///
+/// Not available in wasm, use `build` instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn build_parallel<T>(r1cs: &str, inputs: Vec<T>, jobs: usize) -> Result<Witness<T::F>>
+where
+    T: PolynomialRingElement,
+    T::F: Send + Sync,
+{
+    let r1cs: R1csParser<T> = R1csParser::new(r1cs)?;
+    let mut vars: HashMap<usize, T::F> = HashMap::new();
+    let mut input_counter = 0_usize;
+    vars.insert(0, T::F::one());
+    // the level each signal is assigned in, inputs are level 0
+    let mut signal_level: HashMap<usize, usize> = HashMap::from([(0, 0)]);
+    let mut levels: Vec<Vec<&R1csConstraint<T::F>>> = vec![];
+    for c in solver::order(&r1cs.constraints)?
+        .into_iter()
+        .map(|i| &r1cs.constraints[i])
+    {
+        let out_i = c.out_i.unwrap();
+        let is_io = matches!(
+            c.symbolic_op,
+            Some(SymbolicOp::Input | SymbolicOp::PublicInput | SymbolicOp::Output)
+        );
+        if c.symbolic_op != Some(SymbolicOp::Output) && signal_level.contains_key(&out_i) {
+            return crate::log::error!(
+                InputError: &format!("variable {out_i} already set"),
+                "setting a variable multiple times is considered a programming error"
+            );
+        }
+        if is_io {
+            if let Some(v) = solve_step(c, &inputs, &mut input_counter, &vars)? {
+                vars.insert(out_i, v);
+                signal_level.insert(out_i, 0);
             }
-            _ => {
-                let v = c.solve_symbolic(&vars)?;
-                if vars.contains_key(&c.out_i.unwrap()) {
+            continue;
+        }
+        let mut level = 1;
+        for (_, i) in c.a.iter().chain(c.b.iter()) {
+            match signal_level.get(i) {
+                Some(l) => level = level.max(l + 1),
+                None => {
                     return crate::log::error!(
-                        &format!("variable {} already set", c.out_i.unwrap()),
-                        "setting a variable multiple times is considered a programming error"
-                    );
+                        InputError: &format!("variable {i} is read before it is set"),
+                        "symbolic constraints may only read signals assigned by earlier constraints"
+                    )
                 }
-                vars.insert(c.out_i.unwrap(), v);
             }
         }
+        signal_level.insert(out_i, level);
+        if levels.len() < level {
+            levels.resize(level, vec![]);
+        }
+        levels[level - 1].push(c);
     }
     if input_counter != inputs.len() {
-        return crate::log::error!(&format!(
+        return crate::log::error!(InputError: &format!(
             "not all inputs were used in witness calculation, {} inputs unused",
             inputs.len() - input_counter
         ));
     }
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+    for level in levels {
+        // inversions in a level are independent, so they share a
+        // single field inversion
+        let (inversions, level): (Vec<_>, Vec<_>) = level
+            .into_iter()
+            .partition(|c| matches!(c.symbolic_op, Some(SymbolicOp::Inv | SymbolicOp::InvOrZero)));
+        let denominators =
+            Vector::from_vec(inversions.iter().map(|c| eval_lc(&c.b, &vars)).collect());
+        let mut solved = vec![];
+        for ((c, d), inv) in inversions
+            .iter()
+            .zip(denominators.iter())
+            .zip(denominators.batch_inverse().iter())
+        {
+            // zero is left as zero, Inv reports the error when it
+            // is solved on its own
+            let v = if *d == T::F::zero() && c.symbolic_op == Some(SymbolicOp::Inv) {
+                c.solve_symbolic(&vars)?
+            } else {
+                inv.clone()
+            };
+            solved.push((c.out_i.unwrap(), v));
+        }
+        solved.extend(pool.install(|| {
+            level
+                .par_iter()
+                .map(|c| Ok((c.out_i.unwrap(), c.solve_symbolic(&vars)?)))
+                .collect::<Result<Vec<_>>>()
+        })?);
+        vars.extend(solved);
+    }
     let mut out = vars.keys().copied().collect::<Vec<usize>>();
     out.sort();
     Ok(Witness {
-        outputs,
+        outputs: solver::public_signals(&r1cs.constraints),
         variables: out
             .iter()
             .map(|k| vars.get(k).unwrap().clone())
             .collect::<Vec<_>>(),
     })
 }
+
+/// A summary of a witness written by `build_streaming`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamedWitness {
+    pub outputs: Vec<usize>,
+    /// The number of values written.
+    pub signals: usize,
+    /// The most values held in memory at once.
+    pub peak_signals: usize,
+}
+
+/// Build a witness without holding the constraints or the full
+/// witness in memory, writing each value as it is calculated.
+///
+/// The ar1cs source is read twice, one line at a time. The first
+/// pass finds the last symbolic constraint that reads each signal,
+/// the second calculates the witness and drops each value once it
+/// is no longer needed. Constraints that are not symbolic are
+/// skipped. Constraints are not reordered, each symbolic
+/// constraint must follow the constraints assigning the signals it
+/// reads. The output can be read using
+/// `encoding::decode_witness_stream`.
+pub fn build_streaming<T, R, W>(mut r1cs: R, inputs: Vec<T>, out: W) -> Result<StreamedWitness>
+where
+    T: PolynomialRingElement,
+    R: BufRead + Seek,
+    W: Write,
+{
+    // signal index keyed to the position of the last
+    // symbolic constraint reading it, 0 if it is never read
+    let mut last_use: Vec<usize> = vec![];
+    let mut position = 0_usize;
+    for_each_symbolic::<T, _>(&mut r1cs, |c| {
+        position += 1;
+        for i in solver::reads(&c) {
+            if i >= last_use.len() {
+                last_use.resize(i + 1, 0);
+            }
+            last_use[i] = position;
+        }
+        Ok(())
+    })?;
+
+    r1cs.seek(SeekFrom::Start(0))?;
+    let mut writer = WitnessWriter::new::<T::F>(out)?;
+    let mut vars: HashMap<usize, T::F> = HashMap::new();
+    let mut assigned: Vec<bool> = vec![true];
+    let mut outputs = vec![];
+    let mut input_counter = 0_usize;
+    let mut peak_signals = 1;
+    vars.insert(0, T::F::one());
+    writer.push(0, &T::F::one())?;
+    position = 0;
+    for_each_symbolic::<T, _>(&mut r1cs, |c| {
+        position += 1;
+        if let Some(i) = solver::reads(&c)
+            .into_iter()
+            .find(|i| assigned.get(*i) != Some(&true))
+        {
+            return crate::log::error!(
+                InputError: &format!("variable {i} is read before it is set"),
+                "streamed constraints must be in evaluation order, use `witness::build` to solve constraints in any order"
+            );
+        }
+        if matches!(
+            c.symbolic_op,
+            Some(SymbolicOp::PublicInput | SymbolicOp::Output)
+        ) {
+            outputs.push(c.out_i.unwrap());
+        }
+        if let Some(v) = solve_step(&c, &inputs, &mut input_counter, &vars)? {
+            let out_i = c.out_i.unwrap();
+            if assigned.get(out_i) == Some(&true) {
+                return crate::log::error!(
+                    InputError: &format!("variable {out_i} already set"),
+                    "setting a variable multiple times is considered a programming error"
+                );
+            }
+            if out_i >= assigned.len() {
+                assigned.resize(out_i + 1, false);
+            }
+            assigned[out_i] = true;
+            writer.push(out_i, &v)?;
+            if last_use.get(out_i).is_some_and(|p| *p > position) {
+                vars.insert(out_i, v);
+            }
+        }
+        peak_signals = peak_signals.max(vars.len());
+        // drop values that are not read again
+        for i in solver::reads(&c) {
+            if i != 0 && last_use[i] == position {
+                vars.remove(&i);
+            }
+        }
+        Ok(())
+    })?;
+    if input_counter != inputs.len() {
+        return crate::log::error!(InputError: &format!(
+            "not all inputs were used in witness calculation, {} inputs unused",
+            inputs.len() - input_counter
+        ));
+    }
+    let signals = writer.written;
+    writer.finish(&outputs)?;
+    Ok(StreamedWitness {
+        outputs,
+        signals,
+        peak_signals,
+    })
+}
+
+// call a function with each symbolic constraint in ar1cs
+// source, parsing one line at a time
+fn for_each_symbolic<T: PolynomialRingElement, R: BufRead>(
+    r1cs: R,
+    mut f: impl FnMut(R1csConstraint<T::F>) -> Result<()>,
+) -> Result<()> {
+    for line in r1cs.lines() {
+        let line = line?;
+        let trimmed = line.trim_start();
+        // constraints are not needed to calculate the witness
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("0 =") {
+            continue;
+        }
+        if trimmed.starts_with('(') {
+            return crate::log::error!(
+                InputError: "cannot build a witness for an ar1cs function",
+                "only compiled programs can be streamed"
+            );
+        }
+        for c in R1csParser::<T>::new(&line)?.constraints {
+            if c.symbolic {
+                f(c)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// the source names of the signals in a constraint, e.g.
+// `x3 = a, x4 = hash::state`, if any are named
+fn label_signals<T: FieldElement>(
+    c: &R1csConstraint<T>,
+    names: &HashMap<usize, Vec<&str>>,
+) -> Option<String> {
+    let mut signals =
+        c.a.iter()
+            .chain(c.b.iter())
+            .chain(c.c.iter())
+            .map(|(_, i)| *i)
+            .collect::<Vec<_>>();
+    signals.sort();
+    signals.dedup();
+    let labels = signals
+        .iter()
+        .filter_map(|i| {
+            names
+                .get(i)
+                .map(|v| format!("{} = {}", index_to_string(i), v.join(" / ")))
+        })
+        .collect::<Vec<_>>();
+    (!labels.is_empty()).then(|| labels.join(", "))
+}
+
+// the value of a linear combination of assigned signals
+#[cfg(not(target_arch = "wasm32"))]
+fn eval_lc<T: FieldElement>(lc: &[(T, usize)], vars: &HashMap<usize, T>) -> T {
+    lc.iter().fold(T::zero(), |acc, (coef, i)| {
+        acc + coef.clone() * vars.get(i).unwrap().clone()
+    })
+}
+
+// solve a symbolic constraint, returning the value of the
+// signal it assigns, if any
+fn solve_step<T: PolynomialRingElement>(
+    c: &R1csConstraint<T::F>,
+    inputs: &[T],
+    input_counter: &mut usize,
+    vars: &HashMap<usize, T::F>,
+) -> Result<Option<T::F>> {
+    match c.symbolic_op.as_ref().unwrap() {
+        SymbolicOp::Input | SymbolicOp::PublicInput => {
+            // we'll take the next input value and set it
+            if *input_counter >= inputs.len() {
+                return crate::log::error!(
+                    InputError: "not enough inputs supplied to fulfill symbolic constraints",
+                    "the number of inputs must match the number of input constraints"
+                );
+            }
+            let v = inputs[*input_counter].to_scalar()?;
+            *input_counter += 1;
+            Ok(Some(v))
+        }
+        SymbolicOp::Output => Ok(None),
+        _ => {
+            let v = c.solve_symbolic(vars)?;
+            if vars.contains_key(&c.out_i.unwrap()) {
+                return crate::log::error!(
+                    InputError: &format!("variable {} already set", c.out_i.unwrap()),
+                    "setting a variable multiple times is considered a programming error"
+                );
+            }
+            Ok(Some(v))
+        }
+    }
+}
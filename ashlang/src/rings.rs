@@ -1,3 +1,21 @@
+use std::collections::hash_map::RandomState;
+use std::fmt::Display;
+use std::hash::BuildHasher;
+use std::hash::Hasher;
+use std::iter::Product;
+use std::iter::Sum;
+use std::ops::Add;
+use std::ops::AddAssign;
+use std::ops::Div;
+use std::ops::Mul;
+use std::ops::MulAssign;
+use std::ops::Neg;
+use std::ops::Sub;
+use std::ops::SubAssign;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use anyhow::Result;
 use ring_math::polynomial_ring;
 use ring_math::Polynomial;
 use ring_math::PolynomialRingElement;
@@ -5,9 +23,12 @@ use ring_math::PolynomialRingElement;
 use scalarff::alt_bn128::Bn128FieldElement;
 use scalarff::oxfoi::OxfoiFieldElement;
 use scalarff::scalar_ring;
+use scalarff::BigUint;
 use scalarff::Curve25519FieldElement;
 use scalarff::FieldElement;
 
+use crate::log;
+
 polynomial_ring!(
     Bn128PolynomialRing,
     Bn128FieldElement,
@@ -57,3 +78,437 @@ polynomial_ring!(
     },
     "dilithium x^64+1 polynomial ring"
 );
+
+/// The prefix of a field specifier with a prime chosen at
+/// runtime, e.g. `custom:101`.
+pub const CUSTOM_FIELD_PREFIX: &str = "custom:";
+
+// the prime of the custom field and its name, e.g. `custom:101`
+static CUSTOM_FIELD: OnceLock<(BigUint, &'static str)> = OnceLock::new();
+
+/// Set the prime of `CustomFieldElement` from a field specifier
+/// like `custom:101`. The prime can only be set once per process,
+/// setting the same prime again does nothing.
+pub fn init_custom_field(field: &str) -> Result<()> {
+    let prime = match field
+        .strip_prefix(CUSTOM_FIELD_PREFIX)
+        .and_then(|v| v.parse::<BigUint>().ok())
+    {
+        Some(prime) => prime,
+        None => {
+            return log::error!(
+                ConfigError: &format!("invalid custom field: {field}"),
+                "specify a prime in decimal, e.g. custom:101"
+            )
+        }
+    };
+    if !is_prime(&prime) {
+        return log::error!(
+            ConfigError: &format!("custom field modulus is not prime: {prime}"),
+            "the modulus of a field must be prime"
+        );
+    }
+    // the name is leaked once, when the field is first set
+    let (existing, _) = CUSTOM_FIELD.get_or_init(|| {
+        let name: &'static str =
+            Box::leak(format!("{CUSTOM_FIELD_PREFIX}{prime}").into_boxed_str());
+        (prime.clone(), name)
+    });
+    if *existing != prime {
+        return log::error!(
+            ConfigError: &format!("custom field is already initialized with prime {existing}"),
+            "a process may only use one custom prime"
+        );
+    }
+    Ok(())
+}
+
+// primes used for trial division before the probable prime tests
+const SMALL_PRIMES: [u32; 25] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+
+// miller-rabin rounds with random bases, in addition to base 2
+const RANDOM_ROUNDS: usize = 16;
+
+// Baillie-PSW: trial division, a strong probable prime test to base
+// 2, and a strong lucas probable prime test. No composite is known
+// to pass it, and miller-rabin with random bases is used as well so
+// a composite can't be chosen to pass a fixed set of bases.
+fn is_prime(n: &BigUint) -> bool {
+    let zero = BigUint::from(0_u32);
+    if *n < BigUint::from(2_u32) {
+        return false;
+    }
+    for p in SMALL_PRIMES {
+        if *n == BigUint::from(p) {
+            return true;
+        }
+        if n % p == zero {
+            return false;
+        }
+    }
+    if *n < BigUint::from(97_u32 * 97) {
+        return true;
+    }
+    if !is_strong_probable_prime(n, &BigUint::from(2_u32)) {
+        return false;
+    }
+    let state = RandomState::new();
+    let range = n - 3_u32;
+    for round in 0..RANDOM_ROUNDS {
+        // random bytes at least as long as n, reduced to [2, n - 2]
+        let mut bytes = vec![];
+        for i in 0..n.to_bytes_le().len().div_ceil(8) + 1 {
+            let mut hasher = state.build_hasher();
+            hasher.write_usize(round);
+            hasher.write_usize(i);
+            bytes.extend(hasher.finish().to_le_bytes());
+        }
+        let base = BigUint::from_bytes_le(&bytes) % &range + 2_u32;
+        if !is_strong_probable_prime(n, &base) {
+            return false;
+        }
+    }
+    is_strong_lucas_probable_prime(n)
+}
+
+// miller-rabin with a single base, n is odd and greater than base
+fn is_strong_probable_prime(n: &BigUint, base: &BigUint) -> bool {
+    let one = BigUint::from(1_u32);
+    let n_minus_one = n - 1_u32;
+    let (d, s) = odd_part(&n_minus_one);
+    let mut x = base.modpow(&d, n);
+    if x == one || x == n_minus_one {
+        return true;
+    }
+    for _ in 1..s {
+        x = &x * &x % n;
+        if x == n_minus_one {
+            return true;
+        }
+    }
+    false
+}
+
+// the strong lucas test with the parameters of selfridge's method
+// A: D is the first of 5, -7, 9, -11, ... with jacobi(D, n) = -1,
+// P = 1 and Q = (1 - D) / 4. n is odd and has no small factors.
+fn is_strong_lucas_probable_prime(n: &BigUint) -> bool {
+    let zero = BigUint::from(0_u32);
+    let one = BigUint::from(1_u32);
+    // no D exists for a square
+    let root = isqrt(n);
+    if &root * &root == *n {
+        return false;
+    }
+    let mut d_abs = 5_u32;
+    let mut negative = false;
+    // D and Q mod n
+    let (d, q) = loop {
+        let d = if negative {
+            n - (BigUint::from(d_abs) % n)
+        } else {
+            BigUint::from(d_abs)
+        };
+        match jacobi(&d, n) {
+            -1 => break (d, (n + 1_u32 - &d) * mod_inverse_of_4(n) % n),
+            0 => return *n == BigUint::from(d_abs),
+            _ => {}
+        }
+        d_abs += 2;
+        negative = !negative;
+    };
+    if gcd(q.clone(), n.clone()) != one {
+        return false;
+    }
+    let (k, s) = odd_part(&(n + 1_u32));
+    // halve a value mod n
+    let half = |v: BigUint| match v.to_bytes_le()[0] % 2 {
+        0 => v / 2_u32,
+        _ => (v + n) / 2_u32,
+    };
+    let double_q = |q: &BigUint| (q * 2_u32) % n;
+    // U_1 = 1, V_1 = P = 1, and Q^1
+    let mut u = one.clone();
+    let mut v = one.clone();
+    let mut q_k = q.clone();
+    for bit in k.to_str_radix(2).chars().skip(1) {
+        // U_2k = U_k V_k, V_2k = V_k^2 - 2 Q^k
+        u = &u * &v % n;
+        v = (&v * &v + n - double_q(&q_k)) % n;
+        q_k = &q_k * &q_k % n;
+        if bit == '1' {
+            // U_k+1 = (P U_k + V_k) / 2, V_k+1 = (D U_k + P V_k) / 2
+            let next_u = half((&u + &v) % n);
+            v = half((&d * &u + &v) % n);
+            u = next_u;
+            q_k = &q_k * &q % n;
+        }
+    }
+    if u == zero || v == zero {
+        return true;
+    }
+    for _ in 1..s {
+        v = (&v * &v + n - double_q(&q_k)) % n;
+        if v == zero {
+            return true;
+        }
+        q_k = &q_k * &q_k % n;
+    }
+    false
+}
+
+// n = d * 2^s with d odd
+fn odd_part(n: &BigUint) -> (BigUint, usize) {
+    let zero = BigUint::from(0_u32);
+    let mut d = n.clone();
+    let mut s = 0;
+    while &d % 2_u32 == zero {
+        d /= 2_u32;
+        s += 1;
+    }
+    (d, s)
+}
+
+// the jacobi symbol (a / n) for odd n
+fn jacobi(a: &BigUint, n: &BigUint) -> i32 {
+    let zero = BigUint::from(0_u32);
+    let one = BigUint::from(1_u32);
+    let mut a = a % n;
+    let mut n = n.clone();
+    let mut out = 1;
+    while a != zero {
+        while &a % 2_u32 == zero {
+            a /= 2_u32;
+            let r = &n % 8_u32;
+            if r == BigUint::from(3_u32) || r == BigUint::from(5_u32) {
+                out = -out;
+            }
+        }
+        std::mem::swap(&mut a, &mut n);
+        if &a % 4_u32 == BigUint::from(3_u32) && &n % 4_u32 == BigUint::from(3_u32) {
+            out = -out;
+        }
+        a %= &n;
+    }
+    if n == one {
+        out
+    } else {
+        0
+    }
+}
+
+// 4^-1 mod n for odd n
+fn mod_inverse_of_4(n: &BigUint) -> BigUint {
+    let half = (n + 1_u32) / 2_u32;
+    &half * &half % n
+}
+
+fn gcd(mut a: BigUint, mut b: BigUint) -> BigUint {
+    let zero = BigUint::from(0_u32);
+    while b != zero {
+        let r = &a % &b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+// the largest x with x^2 <= n
+fn isqrt(n: &BigUint) -> BigUint {
+    let zero = BigUint::from(0_u32);
+    if *n == zero {
+        return zero;
+    }
+    // start above the root and decrease
+    let mut x = BigUint::from(1_u32) << n.bits().div_ceil(2);
+    loop {
+        let y = (&x + n / &x) / 2_u32;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+/// An element of a prime field chosen at runtime using
+/// `init_custom_field`. Arithmetic uses arbitrary precision
+/// integers, so it is slower than the fields with a fixed prime.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CustomFieldElement(BigUint);
+
+impl CustomFieldElement {
+    fn reduce(v: BigUint) -> Self {
+        Self(v % Self::prime())
+    }
+}
+
+impl FieldElement for CustomFieldElement {
+    fn zero() -> Self {
+        Self(BigUint::from(0_u32))
+    }
+
+    fn one() -> Self {
+        Self(BigUint::from(1_u32))
+    }
+
+    fn byte_len() -> usize {
+        Self::prime().bits().div_ceil(8) as usize
+    }
+
+    fn serialize(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn deserialize(str: &str) -> Self {
+        Self::from_str(str).unwrap()
+    }
+
+    fn prime() -> BigUint {
+        CUSTOM_FIELD
+            .get()
+            .map(|(prime, _)| prime.clone())
+            .expect("custom field is not initialized")
+    }
+
+    fn name_str() -> &'static str {
+        CUSTOM_FIELD
+            .get()
+            .map(|(_, name)| *name)
+            .unwrap_or("custom")
+    }
+
+    fn from_usize(value: usize) -> Self {
+        Self::reduce(BigUint::from(value))
+    }
+
+    fn to_bytes_le(&self) -> Vec<u8> {
+        let mut bytes = self.0.to_bytes_le();
+        bytes.resize(Self::byte_len(), 0);
+        bytes
+    }
+
+    fn from_bytes_le(bytes: &[u8]) -> Self {
+        Self::reduce(BigUint::from_bytes_le(bytes))
+    }
+}
+
+impl Display for CustomFieldElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for CustomFieldElement {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(Self::reduce(s.parse::<BigUint>()?))
+    }
+}
+
+impl From<u64> for CustomFieldElement {
+    fn from(value: u64) -> Self {
+        Self::reduce(BigUint::from(value))
+    }
+}
+
+impl Add for CustomFieldElement {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::reduce(self.0 + other.0)
+    }
+}
+
+impl Sub for CustomFieldElement {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::reduce(self.0 + Self::prime() - other.0)
+    }
+}
+
+impl Mul for CustomFieldElement {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::reduce(self.0 * other.0)
+    }
+}
+
+impl Div for CustomFieldElement {
+    type Output = Self;
+
+    // multiply by the inverse using fermat's little theorem
+    fn div(self, other: Self) -> Self {
+        let prime = Self::prime();
+        let inv = other.0.modpow(&(prime.clone() - 2_u32), &prime);
+        Self::reduce(self.0 * inv)
+    }
+}
+
+impl Neg for CustomFieldElement {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::zero() - self
+    }
+}
+
+impl AddAssign for CustomFieldElement {
+    fn add_assign(&mut self, other: Self) {
+        *self = self.clone() + other;
+    }
+}
+
+impl SubAssign for CustomFieldElement {
+    fn sub_assign(&mut self, other: Self) {
+        *self = self.clone() - other;
+    }
+}
+
+impl MulAssign for CustomFieldElement {
+    fn mul_assign(&mut self, other: Self) {
+        *self = self.clone() * other;
+    }
+}
+
+impl Sum for CustomFieldElement {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |acc, v| acc + v)
+    }
+}
+
+impl Product for CustomFieldElement {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::one(), |acc, v| acc * v)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for CustomFieldElement {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CustomFieldElement {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let v = <String as serde::Deserialize>::deserialize(deserializer)?;
+        Self::from_str(&v).map_err(serde::de::Error::custom)
+    }
+}
+
+polynomial_ring!(
+    CustomPolynomialRing,
+    CustomFieldElement,
+    {
+        let mut p = Polynomial::new(vec![CustomFieldElement::one()]);
+        p.term(&CustomFieldElement::one(), 64);
+        p
+    },
+    "custom prime x^64+1 polynomial ring"
+);
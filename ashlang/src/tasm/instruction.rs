@@ -0,0 +1,159 @@
+//! A model of the stack effects of Triton VM instructions, used
+//! to check assembly written inline in ash functions.
+use anyhow::Result;
+
+use crate::log;
+
+/// The number of elements an instruction reads from the top of
+/// the stack and the number it leaves in their place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StackEffect {
+    pub reads: usize,
+    pub writes: usize,
+}
+
+impl StackEffect {
+    fn new(reads: usize, writes: usize) -> Self {
+        Self { reads, writes }
+    }
+
+    /// The stack effect of a single instruction, e.g. `dup 3`.
+    /// Control flow is rejected because the effect of a jump
+    /// cannot be known from the instruction alone.
+    pub fn of(instruction: &str) -> Result<Self> {
+        let mut parts = instruction.split_whitespace();
+        let name = parts.next().unwrap_or_default();
+        let arg = parts.next();
+        if parts.next().is_some() {
+            return log::error!(ParseError: &format!("too many arguments in instruction: {instruction}"));
+        }
+        // the number of elements moved by the instruction, 1..=5
+        let count = || -> Result<usize> {
+            match arg.and_then(|v| v.parse::<usize>().ok()) {
+                Some(n) if (1..=5).contains(&n) => Ok(n),
+                _ => log::error!(
+                    ParseError: &format!("invalid instruction: {instruction}"),
+                    &format!("{name} expects a count from 1 to 5")
+                ),
+            }
+        };
+        // a stack position, 0..16
+        let position = || -> Result<usize> {
+            match arg.and_then(|v| v.parse::<usize>().ok()) {
+                Some(i) if i < 16 => Ok(i),
+                _ => log::error!(
+                    ParseError: &format!("invalid instruction: {instruction}"),
+                    &format!("{name} expects a stack position from 0 to 15")
+                ),
+            }
+        };
+        let literal = || -> Result<()> {
+            match arg.map(|v| v.parse::<i128>()) {
+                Some(Ok(_)) => Ok(()),
+                _ => log::error!(
+                    ParseError: &format!("invalid instruction: {instruction}"),
+                    &format!("{name} expects an integer")
+                ),
+            }
+        };
+        let no_arg = || -> Result<()> {
+            match arg {
+                None => Ok(()),
+                Some(_) => log::error!(
+                    ParseError: &format!("invalid instruction: {instruction}"),
+                    &format!("{name} does not take an argument")
+                ),
+            }
+        };
+        let effect = match name {
+            "push" => {
+                literal()?;
+                Self::new(0, 1)
+            }
+            "addi" => {
+                literal()?;
+                Self::new(1, 1)
+            }
+            "pop" | "write_io" => Self::new(count()?, 0),
+            "divine" | "read_io" => Self::new(0, count()?),
+            "read_mem" => Self::new(1, count()? + 1),
+            "write_mem" => Self::new(count()? + 1, 1),
+            "dup" => {
+                let i = position()?;
+                Self::new(i + 1, i + 2)
+            }
+            "swap" | "pick" | "place" => {
+                let i = position()?;
+                Self::new(i + 1, i + 1)
+            }
+            "call" | "return" | "recurse" | "recurse_or_return" | "skiz" => {
+                return log::error!(
+                    ParseError: &format!("control flow is not supported in asm blocks: {instruction}"),
+                    "call a function written in tasm instead"
+                )
+            }
+            _ => {
+                no_arg()?;
+                match name {
+                    "nop" | "halt" | "sponge_init" => Self::new(0, 0),
+                    "assert" => Self::new(1, 0),
+                    "assert_vector" => Self::new(10, 5),
+                    "hash" => Self::new(10, 5),
+                    "sponge_absorb" => Self::new(10, 0),
+                    "sponge_absorb_mem" => Self::new(1, 1),
+                    "sponge_squeeze" => Self::new(0, 10),
+                    "add" | "mul" | "eq" | "lt" | "and" | "xor" | "pow" => Self::new(2, 1),
+                    "invert" | "log_2_floor" | "pop_count" => Self::new(1, 1),
+                    "split" => Self::new(1, 2),
+                    "div_mod" => Self::new(2, 2),
+                    "xx_add" | "xx_mul" => Self::new(6, 3),
+                    "x_invert" => Self::new(3, 3),
+                    "xb_mul" => Self::new(4, 3),
+                    "merkle_step" => Self::new(6, 6),
+                    "merkle_step_mem" => Self::new(8, 8),
+                    "xx_dot_step" | "xb_dot_step" => Self::new(5, 5),
+                    _ => {
+                        return log::error!(
+                            ParseError: &format!("unknown instruction: {instruction}")
+                        )
+                    }
+                }
+            }
+        };
+        Ok(effect)
+    }
+}
+
+/// Check that assembly consumes `inputs` elements and leaves
+/// `outputs` elements on the stack, without reading elements
+/// below the inputs. Returns the instructions with comments
+/// and empty lines removed.
+pub fn check_stack_effect(source: &str, inputs: usize, outputs: usize) -> Result<Vec<String>> {
+    let mut depth = inputs;
+    let mut out = vec![];
+    for line in source.lines() {
+        let instruction = line.split('#').next().unwrap_or_default().trim();
+        if instruction.is_empty() {
+            continue;
+        }
+        let effect = StackEffect::of(instruction)?;
+        if effect.reads > depth {
+            return log::error!(
+                MemoryError: &format!(
+                    "instruction \"{instruction}\" reads {} stack elements, the asm block has {depth}",
+                    effect.reads
+                ),
+                "asm blocks may only access their arguments and values they push"
+            );
+        }
+        depth = depth - effect.reads + effect.writes;
+        out.push(instruction.to_string());
+    }
+    if depth != outputs {
+        return log::error!(
+            MemoryError: &format!("asm block leaves {depth} elements on the stack, expected {outputs}"),
+            "the arguments are consumed and one element is left for each return value"
+        );
+    }
+    Ok(out)
+}
@@ -0,0 +1,61 @@
+use std::fmt::Display;
+
+/// The memory used by a compiled tasm function, measured in
+/// field elements.
+///
+/// `frame` is the memory allocated by the function itself. `peak`
+/// also includes the frames of the functions it calls, e.g. the
+/// memory used above the frame pointer passed to the function.
+/// Recursive functions are measured at their max depth.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FnMemory {
+    pub name: String,
+    pub calls: u64,
+    pub frame: usize,
+    pub peak: usize,
+}
+
+/// A static analysis of the memory used by a tasm program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryReport {
+    pub entrypoint: FnMemory,
+    /// Each compiled function, sorted by decreasing peak usage.
+    pub functions: Vec<FnMemory>,
+    /// Memory reserved by the compiler, e.g. recursion counters.
+    pub reserved: usize,
+}
+
+impl MemoryReport {
+    /// The maximum number of memory cells used by the program.
+    pub fn total(&self) -> usize {
+        self.entrypoint.peak + self.reserved
+    }
+}
+
+impl Display for MemoryReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let width = self
+            .functions
+            .iter()
+            .map(|v| v.name.len())
+            .chain([self.entrypoint.name.len(), "function".len()])
+            .max()
+            .unwrap_or_default();
+        writeln!(
+            f,
+            "{:width$}  {:>8}  {:>8}  {:>8}",
+            "function", "calls", "frame", "peak"
+        )?;
+        for v in [&self.entrypoint].into_iter().chain(&self.functions) {
+            writeln!(
+                f,
+                "{:width$}  {:>8}  {:>8}  {:>8}",
+                v.name, v.calls, v.frame, v.peak
+            )?;
+        }
+        writeln!(f, "reserved by the compiler: {}", self.reserved)?;
+        write!(f, "total memory: {} field elements", self.total())
+    }
+}
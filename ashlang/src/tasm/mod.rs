@@ -1,2 +1,5 @@
 pub mod asm_parser;
+pub mod instruction;
+pub mod memory;
+pub mod peephole;
 pub mod vm;
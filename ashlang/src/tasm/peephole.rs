@@ -0,0 +1,72 @@
+//! Peephole optimization of Triton assembly. Each rule replaces
+//! two adjacent instructions with a shorter equivalent sequence.
+//! A replacement is checked against the instruction before it,
+//! so rules are applied until none match.
+//!
+//! Labels and comments separate instructions and are never
+//! rewritten. The instruction after `skiz` is never rewritten
+//! because it may be skipped at runtime.
+
+/// Apply the rewrite rules to a program, returning the
+/// optimized program.
+pub fn optimize(asm: &[String]) -> Vec<String> {
+    let mut out: Vec<String> = Vec::with_capacity(asm.len());
+    for line in asm {
+        let mut pending = vec![line.clone()];
+        while let Some(line) = pending.pop() {
+            let skipped =
+                out.len() > 1 && out[out.len() - 2].split_whitespace().next() == Some("skiz");
+            let replacement = out
+                .last()
+                .filter(|_| !skipped)
+                .and_then(|last| rewrite(last, &line));
+            match replacement {
+                Some(replacement) => {
+                    out.pop();
+                    pending.extend(replacement.into_iter().rev());
+                }
+                None => out.push(line),
+            }
+        }
+    }
+    out
+}
+
+// the replacement for two adjacent instructions, if a rule
+// applies. Every replacement is shorter than the instructions
+// it replaces
+fn rewrite(a: &str, b: &str) -> Option<Vec<String>> {
+    let (op_a, arg_a) = parse(a)?;
+    let (op_b, arg_b) = parse(b)?;
+    match (op_a, arg_a, op_b, arg_b) {
+        // adding zero or multiplying by one
+        ("push", Some("0"), "add", None) | ("push", Some("1"), "mul", None) => Some(vec![]),
+        // a value that is removed immediately
+        ("push" | "dup", Some(_), "pop", Some("1")) => Some(vec![]),
+        // swapping the same elements twice
+        ("swap", Some(x), "swap", Some(y)) if x == y => Some(vec![]),
+        // the order of the arguments of a commutative operation
+        ("swap", Some("1"), "add" | "mul" | "eq", None) => Some(vec![b.to_string()]),
+        // `pop` removes at most 5 elements
+        ("pop", Some(x), "pop", Some(y)) => {
+            let n = x.parse::<usize>().ok()? + y.parse::<usize>().ok()?;
+            (n <= 5).then(|| vec![format!("pop {n}")])
+        }
+        _ => None,
+    }
+}
+
+// the name and argument of an instruction, or None for a
+// label, comment, or empty line
+fn parse(line: &str) -> Option<(&str, Option<&str>)> {
+    if line.contains("//") || line.trim_end().ends_with(':') {
+        return None;
+    }
+    let mut parts = line.split_whitespace();
+    let op = parts.next()?;
+    let arg = parts.next();
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((op, arg))
+}
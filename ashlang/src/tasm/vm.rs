@@ -3,20 +3,42 @@ use std::collections::HashMap;
 
 use anyhow::anyhow;
 use anyhow::Result;
+use ring_math::Matrix;
+use ring_math::Polynomial;
 use ring_math::PolynomialRingElement;
+use scalarff::BigUint;
+use scalarff::FieldElement;
 
+use crate::builtin;
 use crate::compiler::CompilerState;
+use crate::compiler::ReturnType;
+use crate::consteval::ConstEval;
+use crate::layout;
 use crate::log;
 use crate::parser::AstNode;
+use crate::parser::BitOp;
 use crate::parser::BoolOp;
 use crate::parser::Expr;
+use crate::parser::IntType;
 use crate::parser::NumOp;
+use crate::parser::SourceLocation;
+use crate::parser::VarType;
+use crate::profile::Profile;
+use crate::r1cs::lookup::LookupTable;
+use crate::tasm::instruction::check_stack_effect;
+use crate::tasm::memory::FnMemory;
+
+// number of elements absorbed by the `hash` instruction
+const HASH_RATE: usize = 10;
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum VarLocation {
     Stack,
     Memory,
     Static,
+    // a scalar in memory whose absolute address
+    // is stored on the stack
+    Reference,
 }
 
 #[derive(Clone, Debug)]
@@ -24,6 +46,7 @@ pub struct Var {
     stack_index: Option<usize>,
     block_index: usize,
     location: VarLocation,
+    // offset from the frame pointer of the function
     memory_index: Option<usize>,
     // e.g. 2x3x4
     // [
@@ -39,7 +62,8 @@ pub struct Var {
     //   ]
     // ]
     dimensions: Vec<usize>,
-    value: Option<Vec<u64>>,
+    // the value of each element of a static
+    value: Option<Vec<BigUint>>,
 }
 
 // represents the type of an argument to a function
@@ -47,7 +71,7 @@ pub struct Var {
 pub struct ArgType {
     pub location: VarLocation,
     pub dimensions: Vec<usize>,
-    pub value: Option<Vec<u64>>,
+    pub value: Option<Vec<BigUint>>,
 }
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
@@ -59,13 +83,16 @@ pub struct FnCall {
 
 impl FnCall {
     pub fn typed_name(&self) -> String {
-        let mut out = self.name.to_owned();
+        // `:` ends a label in tasm so module paths are rewritten
+        let mut out = self.name.replace("::", "__");
         if !self.arg_types.is_empty() {
             out.push_str("____");
         }
         for arg in &self.arg_types {
             out.push('_');
-            if arg.dimensions.is_empty() && arg.value.is_none() {
+            if arg.location == VarLocation::Reference {
+                out.push('r');
+            } else if arg.dimensions.is_empty() && arg.value.is_none() {
                 out.push('s');
             } else if arg.dimensions.is_empty() && arg.value.is_some() {
                 out.push_str(
@@ -90,6 +117,40 @@ impl FnCall {
 }
 
 static RETURN_VAR: &str = "_____return_____";
+// the absolute address where the memory of the function
+// begins, passed by the caller as the last argument
+static FRAME_VAR: &str = "_____frame_____";
+// the deepest stack element that can be accessed with `dup`
+const MAX_DUP: usize = 15;
+// scalars declared while more elements than this are on the
+// stack are stored in memory, leaving room for temporary
+// values while evaluating expressions
+const SPILL_DEPTH: usize = 11;
+/// Static loops with at most this many iterations are
+/// unrolled, see `--unroll-limit`.
+pub const UNROLL_LIMIT: usize = 64;
+// functions with at most this many instructions are inlined
+// unless an attribute says otherwise
+const INLINE_MAX_INSTRUCTIONS: usize = 10;
+
+// the body of a function without the final `return`, if the
+// function can be copied to the call site. The body must run
+// from start to end, so it may not return early, recurse, or
+// declare labels
+fn inline_body(asm: &[String]) -> Option<Vec<String>> {
+    let (last, body) = asm.split_last()?;
+    let straight_line = instruction(last) == "return"
+        && body
+            .iter()
+            .map(|v| instruction(v))
+            .all(|v| !v.ends_with(':') && !matches!(v, "return" | "recurse" | "recurse_or_return"));
+    straight_line.then(|| body.to_vec())
+}
+
+// a line of assembly without comments
+fn instruction(line: &str) -> &str {
+    line.split("//").next().unwrap_or_default().trim()
+}
 
 /**
  * This structure is used to track a simple model
@@ -102,7 +163,8 @@ static RETURN_VAR: &str = "_____return_____";
  * Iterate over the AST to see what variables
  * are accessed the most
  *
- * Automatically move vars between memory and stack
+ * Scalars declared once the stack is deep are
+ * stored in memory, see `declare_scalar`
  */
 pub struct VM<'a, T: PolynomialRingElement> {
     // represents the contents of the stack
@@ -113,6 +175,9 @@ pub struct VM<'a, T: PolynomialRingElement> {
     // as items are pushed/popped on the stack
     pub vars: HashMap<String, Var>,
 
+    // variables declared with an integer type
+    pub int_types: HashMap<String, IntType>,
+
     // compiled assembly
     pub asm: Vec<String>,
 
@@ -125,34 +190,57 @@ pub struct VM<'a, T: PolynomialRingElement> {
     // but not lower blocks
     pub block_depth: usize,
 
-    // the absolute position where our memory region begins
-    pub memory_start: usize,
+    // the absolute address where the memory frame begins if
+    // it is known at compile time, otherwise the address is
+    // passed on the stack by the caller
+    pub frame_address: Option<usize>,
 
-    // the current free memory index, relative to memory_start
+    // the current free memory index, relative to the frame
     pub memory_index: usize,
 
+    // the most memory used by called functions, relative
+    // to the frame
+    pub memory_peak: usize,
+
     pub return_type: Option<ArgType>,
 
     pub compiler_state: &'a mut CompilerState<T>,
+
+    // location of the statement being evaluated
+    pub location: Option<SourceLocation>,
+
+    // whether calls in the statement being evaluated are
+    // inlined, from an `#[inline]` or `#[noinline]` attribute
+    pub call_inline: Option<bool>,
 }
 
 impl<'a, T: PolynomialRingElement> VM<'a, T> {
     pub fn new(compiler_state: &'a mut CompilerState<T>) -> Self {
-        let memory_start = compiler_state.memory_offset;
-        compiler_state.memory_offset += 2_usize.pow(32);
         VM {
             vars: HashMap::new(),
+            int_types: HashMap::new(),
             stack: Vec::new(),
             asm: Vec::new(),
             has_returned: false,
             block_depth: 0,
             memory_index: 0,
-            memory_start,
+            memory_peak: 0,
+            frame_address: None,
             compiler_state,
             return_type: None,
+            location: None,
+            call_inline: None,
         }
     }
 
+    // the entrypoint is never called so its memory frame
+    // begins at address 0
+    pub fn new_entrypoint(compiler_state: &'a mut CompilerState<T>) -> Self {
+        let mut vm = Self::new(compiler_state);
+        vm.frame_address = Some(0);
+        vm
+    }
+
     // begin execution of a block
     // we'll track any variables created during
     // execution and remove them from the stack
@@ -186,6 +274,7 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                 pop_count += 1;
             }
             self.vars.remove(k);
+            self.int_types.remove(k);
         }
         self.stack_pop(pop_count);
         self.block_depth -= 1;
@@ -212,18 +301,26 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
         self.stack.truncate(self.stack.len() - count);
     }
 
-    fn stack_push(&mut self, v: u64) {
+    fn stack_push(&mut self, v: impl std::fmt::Display) {
         self.asm.push(format!("push {v}"));
         self.stack.push("".to_string());
     }
 
     fn stack_dup(&mut self, i: usize) -> Result<()> {
         if i > self.stack.len() {
-            return Err(anyhow::anyhow!(
-                "cannot dup more elements than are on the stack {} {}",
-                i,
-                self.stack.len()
-            ));
+            return log::error!(
+                MemoryError: &format!(
+                    "cannot dup more elements than are on the stack {} {}",
+                    i,
+                    self.stack.len()
+                )
+            );
+        }
+        if self.stack.len() - i > MAX_DUP {
+            return log::error!(
+                MemoryError: &format!("cannot dup more than {MAX_DUP} elements deep"),
+                &format!("a function may keep at most {MAX_DUP} scalars on the stack, pass fewer arguments or store some scalars in a vector")
+            );
         }
         self.asm.push(format!("dup {}", self.stack.len() - i));
         self.stack.push("".to_string());
@@ -235,7 +332,7 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
             return Ok(());
         }
         if i > 16 {
-            return Err(anyhow::anyhow!("cannot swap more than 16 elements deep"));
+            return log::error!(MemoryError: "cannot swap more than 16 elements deep");
         }
         self.asm.push(format!("swap {}", i));
         Ok(())
@@ -253,97 +350,256 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
         self.stack.truncate(self.stack.len() - count);
     }
 
+    // push the absolute address of an offset in the
+    // memory frame onto the stack
+    fn push_address(&mut self, offset: usize) -> Result<()> {
+        if let Some(frame_address) = self.frame_address {
+            self.stack_push(frame_address + offset);
+            return Ok(());
+        }
+        let frame_index = match self.vars.get(FRAME_VAR) {
+            Some(v) => v.stack_index.unwrap(),
+            None => return log::error!(MemoryError: "unexpected: function has no frame pointer"),
+        };
+        if self.stack.len() - frame_index > MAX_DUP {
+            return log::error!(
+                MemoryError: "frame pointer is too deep in the stack to access memory",
+                &format!("a function may keep at most {MAX_DUP} scalars on the stack while accessing memory, store some scalars in a vector")
+            );
+        }
+        self.stack_dup(frame_index)?;
+        if offset > 0 {
+            self.stack_push(offset);
+            self.asm.push("add".to_string());
+            self.stack.pop();
+        }
+        Ok(())
+    }
+
+    // pass the address of the first free memory cell to an
+    // ash function as its frame pointer, returns the number
+    // of stack elements pushed
+    fn push_callee_frame(&mut self, call: &FnCall) -> Result<usize> {
+        if !self.compiler_state.is_fn_ash.contains_key(&call.name) {
+            // assembly functions manage their own memory
+            return Ok(0);
+        }
+        // recursive calls are measured once the function
+        // is compiled
+        if let Some(memory) = self.compiler_state.fn_memory.get(call) {
+            self.memory_peak = cmp::max(self.memory_peak, self.memory_index + memory.peak);
+        }
+        self.push_address(self.memory_index)?;
+        Ok(1)
+    }
+
+    // the most memory used by this function and the
+    // functions it calls
+    pub fn memory_used(&self) -> usize {
+        cmp::max(self.memory_peak, self.memory_index)
+    }
+
+    // the memory used by a call to this function
+    pub fn fn_memory(&self, call: &FnCall) -> FnMemory {
+        let mut peak = self.memory_used();
+        if self.compiler_state.recursive_fn.contains(&FnCall {
+            return_type: None,
+            ..call.clone()
+        }) {
+            // each nested call begins at most one frame
+            // after its caller
+            let max_depth = self.compiler_state.fn_max_depth[&call.name];
+            peak += (max_depth - 1) * self.memory_index;
+        }
+        FnMemory {
+            name: call.typed_name(),
+            calls: 0,
+            frame: self.memory_index,
+            peak,
+        }
+    }
+
+    // write literal values into the memory frame
+    fn write_values(&mut self, offset: usize, values: &[BigUint]) -> Result<()> {
+        self.push_address(offset)?;
+        let address_index = self.stack.len();
+        // at most 5 elements can be written at once
+        for chunk in values.chunks(5) {
+            // the element written to the lowest address
+            // must be on the top of the stack
+            for v in chunk.iter().rev() {
+                self.stack_push(v);
+            }
+            self.stack_dup(address_index)?;
+            self.stack_write_mem(chunk.len());
+            // replace the address with the next address
+            self.stack_swap(1)?;
+            self.stack_pop(1);
+        }
+        self.stack_pop(1);
+        Ok(())
+    }
+
     // define a static that will be available in
     // the current VM object
     pub fn static_var(&mut self, name: String, expr: Expr) -> Result<()> {
         // check for duplicate var names
         if self.vars.contains_key(&name) {
             return log::error!(
-                &format!("variable name \"{name}\" is already in use"),
+                ScopeError: &format!("variable name \"{name}\" is already in use"),
                 "you're attempting to define a static variable with the same name as another variable"
             );
         }
-        match &expr {
-            Expr::Lit(v) => {
-                self.vars.insert(
-                    name,
-                    Var {
-                        stack_index: None,
-                        block_index: self.block_depth,
-                        location: VarLocation::Static,
-                        memory_index: None,
-                        dimensions: vec![],
-                        value: Some(vec![(*v).parse::<u64>().unwrap()]),
-                    },
-                );
-            }
-            Expr::Val(ref_name, indices) => {
-                if !indices.is_empty() {
-                    return log::error!("static var index assignment not supported");
-                }
-                if let Some(v) = self.vars.get(&ref_name.clone()) {
-                    match v.location {
-                        VarLocation::Static => {
-                            self.vars.insert(name, v.clone());
-                        }
-                        _ => {
-                            return log::error!("dynamically evaluated statics not supported");
-                        }
-                    }
-                } else {
-                    return log::error!(&format!("unknown variable {ref_name}"));
-                }
-            }
-            Expr::NumOp {
-                lhs: _,
-                op: _,
-                rhs: _,
-            } => {
-                let out = self.eval(expr, false)?;
-                if out.is_none() {
-                    return log::error!("static expression evaluated to stack variable");
-                }
-                let out = out.unwrap();
-                if out.location != VarLocation::Static {
-                    return log::error!("static expression evaluated to memory variable");
-                }
-                self.vars.insert(name, out);
-            }
-            Expr::FnCall(_, _) => {
-                if let Some(v) = self.eval(expr.clone(), false)? {
-                    if v.location != VarLocation::Static {
-                        return log::error!(
-                            "static expression evaluated to memory variable in FnCall"
-                        );
-                    }
-                    self.vars.insert(name.clone(), v);
-                } else {
-                    return log::error!("static expression evaluated to stack variable in FnCall");
-                }
-            }
-            Expr::BoolOp {
-                lhs: _,
-                bool_op: _,
-                rhs: _,
-            } => {
-                return log::error!("boolean operations in statics is not supported");
+        let value = ConstEval::new(
+            &*self.compiler_state,
+            self.static_values(),
+            self.int_types.clone(),
+        )
+        .eval(&expr)?;
+        // scalars are represented without dimensions
+        let dimensions = if value.dimensions == [1] {
+            vec![]
+        } else {
+            value.dimensions.clone()
+        };
+        let value = value
+            .values
+            .iter()
+            .map(|v| Ok(v.to_scalar()?.to_biguint()))
+            .collect::<Result<Vec<_>>>()?;
+        self.vars.insert(
+            name,
+            Var {
+                stack_index: None,
+                block_index: self.block_depth,
+                location: VarLocation::Static,
+                memory_index: None,
+                dimensions,
+                value: Some(value),
+            },
+        );
+        Ok(())
+    }
+
+    // the value of each variable in scope, None if the
+    // variable is not static
+    fn static_values(&self) -> HashMap<String, Option<Matrix<T>>> {
+        self.vars
+            .iter()
+            .map(|(name, v)| {
+                let value = match (&v.location, &v.value) {
+                    (VarLocation::Static, Some(value)) => Some(Matrix {
+                        dimensions: if v.dimensions.is_empty() {
+                            vec![1]
+                        } else {
+                            v.dimensions.clone()
+                        },
+                        values: value.iter().map(element).collect(),
+                    }),
+                    _ => None,
+                };
+                (name.clone(), value)
+            })
+            .collect()
+    }
+
+    // The return type of a call to a function that is already
+    // being compiled. Calls with the same argument types are
+    // compiled to a recursive `call`, other calls are compiled
+    // as a new implementation up to the declared max depth
+    fn recursive_return_type(&mut self, call: &FnCall) -> Result<Option<ArgType>> {
+        let name = &call.name;
+        let depth = self
+            .compiler_state
+            .fn_call_stack
+            .iter()
+            .filter(|v| v.name == *name)
+            .count();
+        if depth == 0 {
+            return Ok(None);
+        }
+        let max_depth = match self.compiler_state.fn_max_depth.get(name) {
+            Some(v) => *v,
+            None => {
+                return log::error!(
+                    TypeError: &format!("function \"{name}\" calls itself without a max recursion depth"),
+                    "declare the maximum number of nested calls in the function header e.g. `(n) recursive 16`"
+                )
             }
-            Expr::VecVec(_) | Expr::VecLit(_) => {
-                let (dimensions, vec) = self.build_var_from_ast_vec(expr);
-                self.vars.insert(
-                    name,
-                    Var {
-                        stack_index: None,
-                        block_index: self.block_depth,
-                        location: VarLocation::Static,
-                        memory_index: None,
-                        dimensions,
-                        value: Some(vec),
-                    },
+        };
+        if !self.compiler_state.fn_call_stack.contains(call) {
+            // static arguments changed, this is a new implementation
+            if depth >= max_depth {
+                return log::error!(
+                    TypeError: &format!("function \"{name}\" exceeded its max recursion depth of {max_depth} while compiling"),
+                    "each distinct set of static arguments is compiled separately"
                 );
             }
+            return Ok(None);
         }
-        Ok(())
+        self.compiler_state.recursive_fn.insert(call.clone());
+        // recursive functions return a scalar on the stack or
+        // nothing, the implementation is checked once it is compiled
+        Ok(Some(match self.compiler_state.fn_return_type.get(name) {
+            Some(ReturnType::Value(_)) => ArgType {
+                location: VarLocation::Stack,
+                dimensions: vec![],
+                value: None,
+            },
+            _ => ArgType {
+                location: VarLocation::Memory,
+                dimensions: vec![],
+                value: None,
+            },
+        }))
+    }
+
+    // Wrap the assembly of a recursive function with a counter
+    // of the active calls and assert that the max depth is not
+    // exceeded. Each call has its own stack variables and
+    // memory frame.
+    fn recursion_frame(&mut self, name: &str, return_type: &ArgType) -> Result<Vec<String>> {
+        let expected_return = match self.compiler_state.fn_return_type.get(name) {
+            Some(ReturnType::Value(_)) => VarLocation::Stack,
+            _ => VarLocation::Memory,
+        };
+        if return_type.location != expected_return || !return_type.dimensions.is_empty() {
+            return log::error!(
+                TypeError: &format!("recursive function \"{name}\" must return a scalar or nothing"),
+                "return a scalar variable or expression"
+            );
+        }
+        let max_depth = self.compiler_state.fn_max_depth[name];
+        // the counter is shared by every call so it is stored
+        // in memory reserved at the top of the address space
+        self.compiler_state.reserved_memory += 1;
+        let counter = format!("-{}", self.compiler_state.reserved_memory);
+        let mut asm = vec![
+            format!("push {counter}"),
+            "read_mem 1".to_string(),
+            "pop 1".to_string(),
+            "push 1".to_string(),
+            "add".to_string(),
+            format!("push {}", max_depth + 1),
+            "dup 1".to_string(),
+            "lt".to_string(),
+            "assert".to_string(),
+            format!("push {counter}"),
+            "write_mem 1".to_string(),
+            "pop 1".to_string(),
+        ];
+        asm.append(&mut self.asm.clone());
+        asm.append(&mut vec![
+            format!("push {counter}"),
+            "read_mem 1".to_string(),
+            "pop 1".to_string(),
+            "push -1".to_string(),
+            "add".to_string(),
+            format!("push {counter}"),
+            "write_mem 1".to_string(),
+            "pop 1".to_string(),
+        ]);
+        Ok(asm)
     }
 
     // return a value to the calling function
@@ -374,6 +630,20 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
             });
             self.stack_pop(self.stack.len() - 1);
         }
+        if let Some(call) = self.compiler_state.fn_call_stack.last() {
+            let dimensions = &self.return_type.as_ref().unwrap().dimensions;
+            if let Some(return_type) = self.compiler_state.fn_return_type.get(&call.name) {
+                return_type.check(&call.name, dimensions, |name| {
+                    let v = self.vars.get(name)?;
+                    match (&v.location, &v.value) {
+                        (VarLocation::Static, Some(value)) if value.len() == 1 => {
+                            value[0].to_string().parse::<usize>().ok()
+                        }
+                        _ => None,
+                    }
+                })?;
+            }
+        }
         // when we're done executing a block we clear
         // everything on the stack so that when we return
         // to the previous position the stack is in a
@@ -403,52 +673,30 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
     // defines a new mutable variable in the current block scope
     pub fn let_var(&mut self, name: String, expr: Expr) -> Result<()> {
         if self.vars.contains_key(&name) {
-            return log::error!(&format!("var is not unique {name}"));
+            return log::error!(ScopeError: &format!("var is not unique {name}"));
         }
         match &expr {
             Expr::VecLit(_) | Expr::VecVec(_) => {
-                let (dimensions, vec) = self.build_var_from_ast_vec(expr);
+                let (dimensions, vec) = self.build_var_from_ast_vec(expr)?;
                 let v = Var {
                     stack_index: None,
                     block_index: self.block_depth,
                     location: VarLocation::Memory,
-                    memory_index: Some(self.memory_start + self.memory_index),
+                    memory_index: Some(self.memory_index),
                     dimensions,
                     value: None,
                 };
                 self.vars.insert(name, v);
                 // put the variable in memory
-                for vv in vec.clone().iter().rev() {
-                    // self.asm.push(format!("push {vv}"))
-                    self.stack_push(*vv);
-                }
-                self.stack_push(u64::try_from(self.memory_start + self.memory_index).unwrap());
+                self.write_values(self.memory_index, &vec)?;
                 self.memory_index += vec.len();
-                // TODO: batch insertion
-                for _ in vec.iter() {
-                    self.stack_write_mem(1);
-                }
-                // pop the updated ram pointer
-                // track memory index in this VM instead
-                self.stack_pop(1);
                 Ok(())
             }
             _ => {
                 let out = self.eval(expr, false)?;
                 if out.is_none() {
                     // stack based variable
-                    self.vars.insert(
-                        name,
-                        Var {
-                            stack_index: Some(self.stack.len()),
-                            block_index: self.block_depth,
-                            location: VarLocation::Stack,
-                            memory_index: None,
-                            dimensions: vec![],
-                            value: None,
-                        },
-                    );
-                    return Ok(());
+                    return self.declare_scalar(name);
                 }
                 let out = out.unwrap();
                 match out.location {
@@ -460,39 +708,21 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                     VarLocation::Static => {
                         // if static is a scalar write to stack
                         if out.value.clone().unwrap().len() == 1 {
-                            self.stack_push(out.value.clone().unwrap()[0]);
-                            self.vars.insert(
-                                name,
-                                Var {
-                                    stack_index: Some(self.stack.len()),
-                                    block_index: self.block_depth,
-                                    location: VarLocation::Stack,
-                                    memory_index: None,
-                                    dimensions: vec![],
-                                    value: None,
-                                },
-                            );
-                            return Ok(());
+                            self.stack_push(&out.value.clone().unwrap()[0]);
+                            return self.declare_scalar(name);
                         }
                         // write to memory
                         let v = Var {
                             stack_index: None,
                             block_index: self.block_depth,
                             location: VarLocation::Memory,
-                            memory_index: Some(self.memory_start + self.memory_index),
+                            memory_index: Some(self.memory_index),
                             dimensions: out.dimensions,
                             value: out.value.clone(),
                         };
-                        self.memory_index += out.value.unwrap().len();
                         // copy values into memory
-                        let mut offset = v.memory_index.unwrap();
-                        for v in v.value.as_ref().unwrap() {
-                            self.stack_push(*v);
-                            self.stack_push(u64::try_from(offset).unwrap());
-                            self.stack_write_mem(1);
-                            self.stack_pop(1);
-                            offset += 1;
-                        }
+                        self.write_values(self.memory_index, v.value.as_ref().unwrap())?;
+                        self.memory_index += out.value.unwrap().len();
                         self.vars.insert(name, v);
                         Ok(())
                     }
@@ -511,7 +741,7 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
     // must be index 1 in the stark stack.
     pub fn fn_var(&mut self, name: String, t: ArgType) -> Result<()> {
         if self.vars.contains_key(&name) {
-            return log::error!(&format!(
+            return log::error!(ScopeError: &format!(
                 "function argument variable \"{name}\" is not unique"
             ));
         }
@@ -560,57 +790,257 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                 );
                 Ok(())
             }
+            VarLocation::Reference => {
+                // the address of the referenced scalar
+                // is on the stack
+                self.stack.push(name.clone());
+                self.vars.insert(
+                    name.clone(),
+                    Var {
+                        stack_index: Some(self.stack.len()),
+                        block_index: self.block_depth,
+                        location: VarLocation::Reference,
+                        memory_index: None,
+                        dimensions: vec![],
+                        value: None,
+                    },
+                );
+                Ok(())
+            }
         }
     }
 
     // assign a variable that already exists
     //
     // do this by evaluating the expression on
+    // declare the scalar on the top of the stack as a
+    // variable. Once the stack is deep the value is moved
+    // to the memory frame so every variable stays within
+    // reach of `dup` and `swap`
+    fn declare_scalar(&mut self, name: String) -> Result<()> {
+        if self.stack.len() <= SPILL_DEPTH {
+            self.vars.insert(
+                name,
+                Var {
+                    stack_index: Some(self.stack.len()),
+                    block_index: self.block_depth,
+                    location: VarLocation::Stack,
+                    memory_index: None,
+                    dimensions: vec![],
+                    value: None,
+                },
+            );
+            return Ok(());
+        }
+        self.vars.insert(
+            name,
+            Var {
+                stack_index: None,
+                block_index: self.block_depth,
+                location: VarLocation::Memory,
+                memory_index: Some(self.memory_index),
+                dimensions: vec![],
+                value: None,
+            },
+        );
+        self.write_scalar(self.memory_index)?;
+        self.memory_index += 1;
+        Ok(())
+    }
+
+    // move the scalar on the top of the stack into the
+    // memory frame
+    fn write_scalar(&mut self, offset: usize) -> Result<()> {
+        self.push_address(offset)?;
+        self.stack_write_mem(1);
+        self.stack_pop(1);
+        Ok(())
+    }
+
     // the top of the stack, then swapping with
     // the real location on the stack, and popping
     // the swapped value
     pub fn set_var(&mut self, name: String, expr: Expr) -> Result<()> {
         if !self.vars.contains_key(&name) {
             return log::error!(
-                &format!("var does not exist \"{name}\""),
+                ScopeError: &format!("var does not exist \"{name}\""),
                 "you're attempting to assign a value to a variable that is not in scope"
             );
         }
         let v = self.vars.get(&name).unwrap();
         if v.location == VarLocation::Static {
             return log::error!(
-                &format!("cannot assign static var \"{name}\""),
+                TypeError: &format!("cannot assign static var \"{name}\""),
                 "you're attempting to assign a value to variable that is a static"
             );
         }
+        if v.location == VarLocation::Memory && v.dimensions.is_empty() {
+            // a scalar moved to memory by `declare_scalar`
+            let Some(memory_index) = v.memory_index else {
+                return log::error!(MemoryError: "unexpected: scalar in memory has no memory index");
+            };
+            if self.eval_to_stack(expr, false)?.is_some() {
+                return log::error!(
+                    TypeError: &format!("cannot assign memory value to scalar var \"{name}\""),
+                    "you're attempting to assign a vector to a scalar variable"
+                );
+            }
+            return self.write_scalar(memory_index);
+        }
         if v.location == VarLocation::Memory {
-            // TODO: allow assigning memory based variable
-            // partially or entirely
-            // e.g. v[0] = [1, 2, 3]
-            // or v = [[1, 2, 3], [4, 5, 6], [7, 8, 9]]
-            return log::error!(&format!("cannot assign memory var \"{name}\""), "you're attempting to re-assign a vector or matrix variable directly. This is not yet supported.");
+            return self.assign_memory_slice(&name, &[], expr);
         }
+        let is_reference = v.location == VarLocation::Reference;
         // new value is on the top of the stack
         let v = self.eval_to_stack(expr, false)?;
         if let Some(v) = v {
             if v.location == VarLocation::Memory {
                 return log::error!(
-                    &format!("cannot assign memory value to stack var \"{name}\""),
+                    TypeError: &format!("cannot assign memory value to stack var \"{name}\""),
                     "you're attempting to assign a vector to a scalar variable"
                 );
             }
             if v.location == VarLocation::Static {
                 return log::error!(
-                    &format!("cannot assign vector static value to stack var \"{name}\""),
+                    TypeError: &format!("cannot assign vector static value to stack var \"{name}\""),
                     "you're attempting to assign a vector to a scalar variable"
                 );
             }
         }
+        if is_reference {
+            // write the value to the referenced memory cell
+            self.stack_dup(self.vars.get(&name).unwrap().stack_index.unwrap())?;
+            self.stack_write_mem(1);
+            self.stack_pop(1);
+            return Ok(());
+        }
         self.stack_swap(self.stack_index(&name)?)?;
         self.stack_pop(1);
         Ok(())
     }
 
+    // write a vector into a memory variable, or into
+    // a slice of a memory variable
+    //
+    // e.g. v = [[1, 2], [3, 4]] or v[0] = [1, 2]
+    pub fn assign_memory_slice(
+        &mut self,
+        name: &String,
+        indices: &[Expr],
+        expr: Expr,
+    ) -> Result<()> {
+        let v = self.vars.get(name).unwrap().clone();
+        let src = match expr {
+            Expr::VecLit(_) | Expr::VecVec(_) => {
+                let (dimensions, values) = self.build_var_from_ast_vec(expr)?;
+                Var {
+                    stack_index: None,
+                    block_index: self.block_depth,
+                    location: VarLocation::Static,
+                    memory_index: None,
+                    dimensions,
+                    value: Some(values),
+                }
+            }
+            _ => match self.eval(expr, false)? {
+                Some(src) => src,
+                None => {
+                    return log::error!(
+                        TypeError: &format!("cannot assign scalar to vector \"{name}\""),
+                        "you're attempting to assign a scalar to a vector or matrix"
+                    )
+                }
+            },
+        };
+        let dimensions = v.dimensions[indices.len()..].to_vec();
+        if src.dimensions != dimensions {
+            return log::error!(
+                TypeError: &format!("dimension mismatch in assignment to \"{name}\""),
+                &format!(
+                    "expected a value of dimensions {:?}, received {:?}",
+                    dimensions, src.dimensions
+                )
+            );
+        }
+        // absolute address of the first element being assigned
+        self.calc_vec_offset(name, &v.dimensions, indices)?;
+        if let Some(mem_index) = v.memory_index {
+            self.push_address(mem_index)?;
+        } else if let Some(stack_index) = v.stack_index {
+            self.stack_dup(stack_index)?;
+        } else {
+            return log::error!(MemoryError: "unexpected: variable has no memory or stack index");
+        }
+        self.asm.push("add".to_string());
+        self.stack.pop();
+        let dest_index = self.stack.len();
+        let len = layout::len(&dimensions);
+        let mut offset = 0;
+        while offset < len {
+            // at most 5 elements can be written at once
+            let count = cmp::min(5, len - offset);
+            // the element written to the lowest address
+            // must be on the top of the stack
+            for x in (offset..offset + count).rev() {
+                self.load_scalar(&src, Some(x))?;
+            }
+            self.stack_dup(dest_index)?;
+            if offset > 0 {
+                self.stack_push(offset);
+                self.asm.push("add".to_string());
+                self.stack.pop();
+            }
+            self.stack_write_mem(count);
+            self.stack_pop(1);
+            offset += count;
+        }
+        // remove the destination address
+        self.stack_pop(1);
+        Ok(())
+    }
+
+    // push the absolute memory address of a scalar
+    // onto the stack so it can be passed by reference
+    pub fn push_reference(&mut self, name: &String, indices: &[Expr]) -> Result<()> {
+        let v = match self.vars.get(name) {
+            Some(v) => v.clone(),
+            None => return log::error!(ScopeError: &format!("unknown variable: {name}")),
+        };
+        match v.location {
+            VarLocation::Reference => {
+                if !indices.is_empty() {
+                    return log::error!(TypeError: &format!("cannot access reference \"{name}\" by index"));
+                }
+                // pass the existing reference along
+                self.stack_dup(v.stack_index.unwrap())
+            }
+            VarLocation::Memory => {
+                if indices.len() != v.dimensions.len() {
+                    return log::error!(
+                        TypeError: &format!("cannot reference vector \"{name}\""),
+                        "references must point to a single element e.g. `&v[0]`"
+                    );
+                }
+                // offset is pushed onto the stack
+                self.calc_vec_offset(name, &v.dimensions, indices)?;
+                if let Some(mem_index) = v.memory_index {
+                    self.push_address(mem_index)?;
+                } else if let Some(stack_index) = v.stack_index {
+                    self.stack_dup(stack_index)?;
+                } else {
+                    return log::error!(MemoryError: "unexpected: variable has no memory or stack index");
+                }
+                self.asm.push("add".to_string());
+                self.stack.pop();
+                Ok(())
+            }
+            VarLocation::Stack | VarLocation::Static => log::error!(
+                TypeError: &format!("cannot reference variable \"{name}\""),
+                "only vector elements have a memory address, declare the variable as a vector e.g. `let v[1]`"
+            ),
+        }
+    }
+
     // get the index of a variable in the execution stack
     //
     // the local stack tracks the relative positions of
@@ -618,22 +1048,65 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
     pub fn stack_index(&self, var_name: &String) -> Result<usize> {
         if let Some(var) = self.vars.get(var_name) {
             if var.location == VarLocation::Memory {
-                return log::error!(&format!(
+                return log::error!(MemoryError: &format!(
                     "cannot get stack index of memory variable \"{var_name}\""
                 ));
             }
             if var.location == VarLocation::Static {
-                return log::error!(&format!(
+                return log::error!(MemoryError: &format!(
                     "cannot get stack index of static variable \"{var_name}\""
                 ));
             }
             if let Some(stack_index) = var.stack_index {
                 Ok(self.stack.len() - stack_index)
             } else {
-                Err(anyhow::anyhow!("var does not have a stack index"))
+                log::error!(MemoryError: "var does not have a stack index")
             }
         } else {
-            log::error!(&format!("unknown variable \"{var_name}\""))
+            log::error!(ScopeError: &format!("unknown variable \"{var_name}\""))
+        }
+    }
+
+    // mark the instructions of the current source line so
+    // they can be attributed when profiling
+    fn mark_location(&mut self) {
+        if let (Some(profile), Some(location)) = (&mut self.compiler_state.profile, &self.location)
+        {
+            profile.record(location, 0);
+            self.asm.push(Profile::marker(location));
+        }
+    }
+
+    // call a compiled function, or copy its body in place of
+    // the call if it should be inlined
+    fn emit_call(&mut self, call: &FnCall) {
+        let label = call.typed_name();
+        let body = self
+            .compiler_state
+            .compiled_fn
+            .iter()
+            .find(|(fn_call, _)| fn_call.typed_name() == label)
+            .filter(|(fn_call, _)| !self.compiler_state.recursive_fn.contains(fn_call))
+            .and_then(|(_, asm)| inline_body(asm));
+        let Some(body) = body else {
+            self.asm.push(format!("call {label}"));
+            return;
+        };
+        // a call site attribute takes precedence over the
+        // attribute of the function, then the size of the body
+        let inline = self
+            .call_inline
+            .or_else(|| self.compiler_state.fn_inline.get(&call.name).copied())
+            .unwrap_or_else(|| {
+                let len = body.iter().filter(|v| !instruction(v).is_empty()).count();
+                len <= INLINE_MAX_INSTRUCTIONS
+            });
+        if inline {
+            self.asm.append(&mut body.clone());
+            // attribute the following instructions to the caller
+            self.mark_location();
+        } else {
+            self.asm.push(format!("call {label}"));
         }
     }
 
@@ -644,96 +1117,555 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
         self.asm.push(format!("call {block_name}"));
     }
 
-    pub fn extract_literals(expr: Expr, out: &mut Vec<u64>) {
-        match expr {
-            Expr::VecVec(v) => {
-                for a in v {
-                    Self::extract_literals(a, out);
-                }
+    // compile an AST into a new block and call it
+    // from the current position
+    //
+    // the block must leave the stack as it found it
+    pub fn compile_block(&mut self, block_ast: Vec<AstNode>) -> Result<()> {
+        let block_name = format!("block_____{}", self.compiler_state.block_counter);
+        self.compiler_state.block_counter += 1;
+        self.call_block(&block_name);
+        let start_asm_len = self.asm.len();
+        self.mark_location();
+        self.begin_block();
+        // blocks can't take args
+        self.eval_ast(block_ast, vec![], None)?;
+        self.end_block()?;
+        // pull the resulting asm as the block asm
+        let mut block_asm = self.asm.drain(start_asm_len..).collect::<Vec<String>>();
+        block_asm.insert(0, format!("{block_name}:"));
+        block_asm.push("return".to_string());
+        self.compiler_state.block_fn_asm.push(block_asm);
+        Ok(())
+    }
+
+    // compile a static loop into a block that runs the body
+    // and then jumps back to the beginning of the block. A
+    // counter on the stack below the body is decremented
+    // each iteration and the block returns when it is 0
+    //
+    // the body must leave the stack as it found it
+    fn compile_loop(&mut self, count: u64, block_ast: Vec<AstNode>) -> Result<()> {
+        self.stack_push(count);
+        let block_name = format!("block_____{}", self.compiler_state.block_counter);
+        self.compiler_state.block_counter += 1;
+        self.call_block(&block_name);
+        let start_asm_len = self.asm.len();
+        self.mark_location();
+        self.asm.append(&mut vec![
+            format!("dup 0"),
+            format!("push 0"),
+            format!("eq"),
+            format!("skiz"),
+            format!("return"),
+        ]);
+        self.begin_block();
+        self.eval_ast(block_ast, vec![], None)?;
+        self.end_block()?;
+        self.asm.append(&mut vec![
+            format!("push -1"),
+            format!("add"),
+            format!("recurse"),
+        ]);
+        let mut block_asm = self.asm.drain(start_asm_len..).collect::<Vec<String>>();
+        block_asm.insert(0, format!("{block_name}:"));
+        self.compiler_state.block_fn_asm.push(block_asm);
+        // remove the counter
+        self.stack_pop(1);
+        Ok(())
+    }
+
+    // evaluate a short circuiting logical operation
+    //
+    // the rhs is evaluated in a block that is only called
+    // if the lhs does not determine the result. The block
+    // replaces the lhs on the stack with the rhs
+    fn eval_logic_op(&mut self, bool_op: &BoolOp, lhs: &Expr, rhs: &Expr) -> Result<Option<Var>> {
+        if self.eval_to_stack(lhs.clone(), false)?.is_some() {
+            return log::error!(TypeError: "cannot apply boolean operation to memory vars");
+        }
+        self.stack_dup(self.stack.len())?;
+        if let BoolOp::Or = bool_op {
+            // the rhs is only needed if the lhs is 0
+            self.stack_push(0);
+            self.asm.push("eq".to_string());
+            self.stack.pop();
+        }
+        self.asm.push("skiz".to_string());
+        self.stack.pop();
+        let block_name = format!("block_____{}", self.compiler_state.block_counter);
+        self.compiler_state.block_counter += 1;
+        self.call_block(&block_name);
+        let start_asm_len = self.asm.len();
+        self.mark_location();
+        self.stack_pop(1);
+        if self.eval_to_stack(rhs.clone(), false)?.is_some() {
+            return log::error!(TypeError: "cannot apply boolean operation to memory vars");
+        }
+        let mut block_asm = self.asm.drain(start_asm_len..).collect::<Vec<String>>();
+        block_asm.insert(0, format!("{block_name}:"));
+        block_asm.push("return".to_string());
+        self.compiler_state.block_fn_asm.push(block_asm);
+        Ok(None)
+    }
+
+    pub fn build_var_from_ast_vec(&mut self, expr: Expr) -> Result<(Vec<usize>, Vec<BigUint>)> {
+        let (dimensions, values) = layout::vec_literal(&expr);
+        let values = values
+            .iter()
+            .map(|v| literal::<T>(v))
+            .collect::<Result<_>>()?;
+        Ok((dimensions, values))
+    }
+
+    // output a single stack element
+    pub fn calc_vec_offset(
+        &mut self,
+        name: &str,
+        dimensions: &[usize],
+        indices: &[Expr],
+    ) -> Result<()> {
+        // if all values are literals we can calculate statically and push that to the stack
+        if indices.iter().all(|v| matches!(v, Expr::Lit(_))) {
+            let offset = self.calc_vec_offset_static(name, dimensions, indices)?;
+            self.stack_push(offset);
+            return Ok(());
+        }
+        if indices.len() > dimensions.len() {
+            return log::error!(TypeError: &format!("too many indices for variable \"{name}\""));
+        }
+        self.stack_push(0); // offset
+        for (depth, index) in indices.iter().enumerate() {
+            let o = self.eval_to_stack(index.clone(), false)?;
+            if o.is_some() {
+                return log::error!(TypeError: "vector variables are not allowed as indices");
+            }
+            if self.compiler_state.checked_indexing {
+                // index < len, `lt` fails if the index is not a u32
+                self.asm.append(&mut vec![
+                    format!("// bounds check {name}[{depth}]"),
+                    format!("push {}", dimensions[depth]),
+                    "dup 1".to_string(),
+                    "lt".to_string(),
+                    "assert".to_string(),
+                ]);
             }
-            Expr::VecLit(v) => {
-                let mut vv = v
-                    .clone()
-                    .iter()
-                    .map(|v| v.parse::<u64>().unwrap())
-                    .collect();
-                out.append(&mut vv);
+            let stride = layout::stride(dimensions, depth);
+            if stride != 1 {
+                self.stack_push(stride);
+                self.asm.push("mul".to_string());
+                self.stack.pop();
             }
-            _ => unreachable!(),
+            self.asm.push("add".to_string());
+            self.stack.pop();
         }
+        Ok(())
     }
 
-    pub fn dimensions_to_len(dimensions: Vec<usize>) -> usize {
-        let mut len = 1;
-        for d in &dimensions {
-            len *= d;
-        }
-        len
+    pub fn calc_vec_offset_static(
+        &mut self,
+        name: &str,
+        dimensions: &[usize],
+        indices: &[Expr],
+    ) -> Result<usize> {
+        let indices = indices
+            .iter()
+            .map(|v| self.static_index(v))
+            .collect::<Result<Vec<usize>>>()?;
+        layout::offset(name, dimensions, &indices)
     }
 
-    pub fn build_var_from_ast_vec(&mut self, expr: Expr) -> (Vec<usize>, Vec<u64>) {
-        // first iterate all the way through to the first literal
-        let mut dimensions: Vec<usize> = Vec::new();
-        let mut root_expr = expr.clone();
-        // first calculate the dimensions of the matrix
-        loop {
-            match root_expr {
-                Expr::VecVec(v) => {
-                    dimensions.push(v.len());
-                    root_expr = v[0].clone();
+    // the value of a literal or static scalar used as an index
+    fn static_index(&mut self, v: &Expr) -> Result<usize> {
+        match v {
+            Expr::Lit(v) => Ok((*v).parse::<usize>()?),
+            Expr::Val(_, _) => {
+                let out = self.eval(v.clone(), false)?;
+                if out.is_none() {
+                    return log::error!(TypeError: "vector cannot be indexed by stack variable");
                 }
-                Expr::VecLit(v) => {
-                    dimensions.push(v.len());
-                    break;
+                let out = out.unwrap();
+                if out.location != VarLocation::Static {
+                    return log::error!(TypeError: "non-static variables are not allowed as indices");
                 }
-                _ => {}
+                if !out.dimensions.is_empty() {
+                    return log::error!(TypeError: "vector cannot be indexed by non-scalar");
+                }
+                Ok(out.value.unwrap()[0].to_string().parse::<usize>()?)
             }
+            _ => log::error!(
+                TypeError: "static indices must be literals or static variables",
+                "declare the index with `static`"
+            ),
         }
-        // then pull all the literals into a 1 dimensional vec
-        let mut vec_rep: Vec<u64> = Vec::new();
-        Self::extract_literals(expr, &mut vec_rep);
-        (dimensions, vec_rep)
     }
 
-    // output a single stack element
-    pub fn calc_vec_offset(&mut self, dimensions: &Vec<usize>, indices: &[Expr]) -> Result<()> {
-        let sum = |vec: &Vec<usize>, start: usize| -> usize {
-            let mut out = 1;
-            for v in &vec[start..] {
-                out *= v;
+    // the negation of a value. Statics are negated at compile
+    // time and vectors in memory are negated into a new vector
+    fn neg(&mut self, args: &[Expr]) -> Result<Option<Var>> {
+        if args.len() != 1 {
+            return log::error!(TypeError: &format!(
+                "{} expects 1 argument, received {}",
+                builtin::NEG,
+                args.len()
+            ));
+        }
+        let Some(v) = self.eval(args[0].clone(), false)? else {
+            // a scalar on the stack
+            self.asm
+                .append(&mut vec![format!("push -1"), format!("mul")]);
+            return Ok(None);
+        };
+        let zero = Var {
+            stack_index: None,
+            block_index: self.block_depth,
+            location: VarLocation::Static,
+            memory_index: None,
+            dimensions: v.dimensions.clone(),
+            value: Some(vec![BigUint::from(0_u32); layout::len(&v.dimensions)]),
+        };
+        self.op_elements(&zero, &v, None, |a, b| {
+            (
+                a - b,
+                vec![format!("push -1"), format!("mul"), format!("add")],
+            )
+        })
+    }
+
+    // a slice of a memory or static value. A memory slice
+    // addresses the elements of the value without copying them
+    fn slice(&mut self, args: &[Expr]) -> Result<Option<Var>> {
+        if args.len() != 3 {
+            return log::error!(TypeError: &format!(
+                "{} expects 3 arguments (value, start, end), received {}",
+                builtin::SLICE,
+                args.len()
+            ));
+        }
+        let name = match &args[0] {
+            Expr::Val(name, _) => name.clone(),
+            _ => "value".to_string(),
+        };
+        let start = self.static_index(&args[1])?;
+        let end = self.static_index(&args[2])?;
+        let Some(v) = self.eval(args[0].clone(), false)? else {
+            return log::error!(TypeError: &format!("scalar \"{name}\" cannot be sliced"));
+        };
+        let (offset, dimensions) = layout::slice(&name, &v.dimensions, start, end)?;
+        match v.location {
+            VarLocation::Static => {
+                let Some(value) = v.value.clone() else {
+                    return Err(anyhow!("static variable does not have values defined"));
+                };
+                let len = layout::len(&dimensions);
+                Ok(Some(Var {
+                    dimensions,
+                    value: Some(value[offset..offset + len].to_vec()),
+                    ..v
+                }))
+            }
+            VarLocation::Memory if v.memory_index.is_some() => Ok(Some(Var {
+                memory_index: v.memory_index.map(|v| v + offset),
+                dimensions,
+                ..v
+            })),
+            VarLocation::Memory if offset == 0 => Ok(Some(Var { dimensions, ..v })),
+            _ => log::error!(
+                MemoryError: &format!("cannot slice stack addressed memory variable \"{name}\""),
+                "assign the variable with `let` before slicing it"
+            ),
+        }
+    }
+
+    // hash up to 10 scalars using the native `hash` instruction
+    //
+    // Triton VM hashes using Tip5 so the digest differs from the
+    // poseidon hash in the r1cs target
+    fn poseidon(&mut self, args: &[Expr]) -> Result<Option<Var>> {
+        if args.is_empty() || args.len() > HASH_RATE {
+            return log::error!(TypeError: &format!(
+                "poseidon accepts 1 to {HASH_RATE} inputs in the tasm target, received {}",
+                args.len()
+            ));
+        }
+        // pad the input with zeros, the first argument is
+        // the top element of the stack
+        for _ in args.len()..HASH_RATE {
+            self.stack_push(0);
+        }
+        for arg in args.iter().rev() {
+            if self.eval_to_stack(arg.clone(), false)?.is_some() {
+                return log::error!(TypeError: "poseidon arguments must be scalars");
+            }
+        }
+        // the 10 inputs are replaced by a 5 element digest
+        self.asm.push("hash".to_string());
+        self.stack.truncate(self.stack.len() - 5);
+        // keep the top element of the digest
+        self.stack_swap(4)?;
+        self.stack_pop(4);
+        Ok(None)
+    }
+
+    // hash the previous challenge and up to 9 scalars, the same
+    // as `std::poseidon(previous, ..)`. The latest challenge is
+    // stored in reserved memory, which is 0 before the first
+    fn challenge(&mut self, args: &[Expr]) -> Result<Option<Var>> {
+        if args.is_empty() || args.len() > builtin::CHALLENGE_INPUTS {
+            return log::error!(TypeError: &format!(
+                "challenge accepts 1 to {} inputs, received {}",
+                builtin::CHALLENGE_INPUTS,
+                args.len()
+            ));
+        }
+        let address = match self.compiler_state.transcript_address {
+            Some(address) => address,
+            None => {
+                self.compiler_state.reserved_memory += 1;
+                let address = self.compiler_state.reserved_memory;
+                self.compiler_state.transcript_address = Some(address);
+                address
             }
-            out
         };
-        // if all values are literals we can calculate statically and push that to the stack
-        let mut is_static = true;
-        for i in indices {
-            match i {
-                Expr::Lit(_) => {}
+        for _ in args.len() + 1..HASH_RATE {
+            self.stack_push(0);
+        }
+        for arg in args.iter().rev() {
+            if self.eval_to_stack(arg.clone(), false)?.is_some() {
+                return log::error!(TypeError: "challenge arguments must be scalars");
+            }
+        }
+        // the previous challenge is the first input
+        self.asm.append(&mut vec![
+            format!("push -{address}"),
+            "read_mem 1".to_string(),
+            "pop 1".to_string(),
+        ]);
+        self.stack.push("".to_string());
+        self.asm.push("hash".to_string());
+        self.stack.truncate(self.stack.len() - 5);
+        // store the top element of the digest for the next challenge
+        self.asm.append(&mut vec![
+            "dup 0".to_string(),
+            format!("push -{address}"),
+            "write_mem 1".to_string(),
+            "pop 1".to_string(),
+        ]);
+        self.stack_swap(4)?;
+        self.stack_pop(4);
+        Ok(None)
+    }
+
+    // multiply or invert extension field elements using the
+    // native instructions. Elements are pushed with the x^0
+    // coefficient on top and the result is written to memory
+    fn extension_op(
+        &mut self,
+        name: &str,
+        args: &[Expr],
+        is_returning: bool,
+    ) -> Result<Option<Var>> {
+        let arity = if name == builtin::XFE_MUL { 2 } else { 1 };
+        if args.len() != arity {
+            return log::error!(TypeError: &format!(
+                "{name} expects {arity} arguments, received {}",
+                args.len()
+            ));
+        }
+        let mut elements = vec![];
+        for arg in args {
+            match self.eval(arg.clone(), false)? {
+                Some(v) if v.dimensions == vec![builtin::EXTENSION_DEGREE] => elements.push(v),
                 _ => {
-                    is_static = false;
-                    break;
+                    return log::error!(
+                        TypeError: &format!("{name} arguments must be extension field elements"),
+                        "declare each argument using `let v: xfe = [c0, c1, c2]`"
+                    )
                 }
             }
         }
-        if is_static {
-            let offset = self.calc_vec_offset_static(dimensions, indices)?;
-            self.stack_push(offset.try_into().unwrap());
-            return Ok(());
+        for v in &elements {
+            for x in (0..builtin::EXTENSION_DEGREE).rev() {
+                self.load_scalar(v, Some(x))?;
+            }
         }
-        self.stack_push(0); // offset
-        for x in 0..indices.len() {
-            let o = self.eval_to_stack(indices[x].clone(), false)?;
-            if o.is_some() {
-                return log::error!("vector variables are not allowed as indices");
+        if name == builtin::XFE_MUL {
+            self.asm.push("xx_mul".to_string());
+            self.stack
+                .truncate(self.stack.len() - builtin::EXTENSION_DEGREE);
+        } else {
+            self.asm.push("x_invert".to_string());
+        }
+        let out = if is_returning {
+            let return_var = self.vars.get(RETURN_VAR).unwrap();
+            Var {
+                stack_index: Some(return_var.stack_index.unwrap()),
+                block_index: self.block_depth,
+                location: VarLocation::Memory,
+                memory_index: None,
+                dimensions: vec![builtin::EXTENSION_DEGREE],
+                value: None,
             }
-            if x == indices.len() - 1 && indices.len() == dimensions.len() {
-                self.asm.push("add".to_string());
-                self.stack.pop();
-            } else {
-                let dim_sum = sum(dimensions, x + 1);
-                self.stack_push(dim_sum.try_into().unwrap());
-                self.asm.push("mul".to_string());
+        } else {
+            let v = Var {
+                stack_index: None,
+                block_index: self.block_depth,
+                location: VarLocation::Memory,
+                memory_index: Some(self.memory_index),
+                dimensions: vec![builtin::EXTENSION_DEGREE],
+                value: None,
+            };
+            self.memory_index += builtin::EXTENSION_DEGREE;
+            v
+        };
+        // the coefficients are written at once, x^0 is on top
+        // so it's written to the lowest address
+        self.push_element_address(&out, 0)?;
+        self.stack_write_mem(builtin::EXTENSION_DEGREE);
+        self.stack_pop(1);
+        Ok(Some(out))
+    }
+
+    // reduce the elements of a value to a scalar on the stack
+    // using a running accumulator. Elements are read in chunks
+    // of up to 5 and combined with the accumulator
+    fn reduce(&mut self, name: &str, args: &[Expr]) -> Result<Option<Var>> {
+        if args.len() != 1 {
+            return log::error!(TypeError: &format!(
+                "{name} expects 1 argument, received {}",
+                args.len()
+            ));
+        }
+        let Some(v) = self.eval(args[0].clone(), false)? else {
+            return log::error!(TypeError: &format!(
+                "{name} expects a vector or matrix, received a scalar"
+            ));
+        };
+        let len = layout::len(&v.dimensions);
+        if len == 0 {
+            return log::error!(TypeError: &format!("{name} of an empty value"));
+        }
+        for start in (0..len).step_by(5) {
+            let count = (len - start).min(5);
+            self.load_elements(&v, start, count)?;
+            // the first element starts the accumulator
+            let ops = if start == 0 { count - 1 } else { count };
+            for _ in 0..ops {
+                match name {
+                    builtin::SUM => self.asm.push("add".to_string()),
+                    builtin::PROD => self.asm.push("mul".to_string()),
+                    // max(a, b) = b + (b < a) * (a - b)
+                    _ => self.asm.append(
+                        &mut [
+                            "dup 1", "dup 1", "lt", "dup 2", "dup 2", "push -1", "mul", "add",
+                            "mul", "add", "swap 1", "pop 1",
+                        ]
+                        .map(String::from)
+                        .to_vec(),
+                    ),
+                }
                 self.stack.pop();
+            }
+        }
+        Ok(None)
+    }
+
+    // a dot product or matrix product, see `layout`. Each element is
+    // a sum of products of elements read from the operands. A
+    // scalar result is left on the stack, other results are
+    // written to memory
+    fn sum_of_products(
+        &mut self,
+        name: &str,
+        args: &[Expr],
+        is_returning: bool,
+    ) -> Result<Option<Var>> {
+        if args.len() != 2 {
+            return log::error!(TypeError: &format!(
+                "{name} expects 2 arguments, received {}",
+                args.len()
+            ));
+        }
+        let mut operands = vec![];
+        for arg in args {
+            match self.eval(arg.clone(), false)? {
+                Some(v) => operands.push(v),
+                None => {
+                    return log::error!(TypeError: &format!(
+                        "{name} arguments must be vectors or matrices"
+                    ))
+                }
+            }
+        }
+        let (lhs, rhs) = (&operands[0], &operands[1]);
+        let (dimensions, terms) = if name == builtin::DOT {
+            (
+                vec![],
+                vec![layout::dot_terms(&lhs.dimensions, &rhs.dimensions)?],
+            )
+        } else {
+            (
+                layout::matmul_dimensions(&lhs.dimensions, &rhs.dimensions)?,
+                layout::matmul_terms(&lhs.dimensions, &rhs.dimensions)?,
+            )
+        };
+        if dimensions.is_empty() {
+            self.push_sum_of_products(lhs, rhs, &terms[0])?;
+            return Ok(None);
+        }
+        let out = if is_returning {
+            let return_var = self.vars.get(RETURN_VAR).unwrap();
+            Var {
+                stack_index: Some(return_var.stack_index.unwrap()),
+                block_index: self.block_depth,
+                location: VarLocation::Memory,
+                memory_index: None,
+                dimensions,
+                value: None,
+            }
+        } else {
+            let v = Var {
+                stack_index: None,
+                block_index: self.block_depth,
+                location: VarLocation::Memory,
+                memory_index: Some(self.memory_index),
+                dimensions,
+                value: None,
+            };
+            self.memory_index += terms.len();
+            v
+        };
+        // elements are written in chunks of up to 5, the last
+        // element of a chunk is computed first so the first
+        // element is on top
+        for start in (0..terms.len()).step_by(5) {
+            let count = (terms.len() - start).min(5);
+            for x in (start..start + count).rev() {
+                self.push_sum_of_products(lhs, rhs, &terms[x])?;
+            }
+            self.push_element_address(&out, start)?;
+            self.stack_write_mem(count);
+            self.stack_pop(1);
+        }
+        Ok(Some(out))
+    }
+
+    // push the sum of the products of pairs of elements
+    fn push_sum_of_products(
+        &mut self,
+        lhs: &Var,
+        rhs: &Var,
+        pairs: &[(usize, usize)],
+    ) -> Result<()> {
+        if pairs.is_empty() {
+            self.stack_push(0);
+        }
+        for (x, (i, j)) in pairs.iter().enumerate() {
+            self.load_scalar(lhs, Some(*i))?;
+            self.load_scalar(rhs, Some(*j))?;
+            self.asm.push("mul".to_string());
+            self.stack.pop();
+            if x > 0 {
                 self.asm.push("add".to_string());
                 self.stack.pop();
             }
@@ -741,57 +1673,163 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
         Ok(())
     }
 
-    pub fn calc_vec_offset_static(
-        &mut self,
-        dimensions: &Vec<usize>,
-        indices: &[Expr],
-    ) -> Result<usize> {
-        let sum = |vec: &Vec<usize>, start: usize| -> usize {
-            let mut out = 1;
-            for v in &vec[start..] {
-                out *= v;
+    // assert that a leaf is included in a merkle tree of
+    // `std::poseidon` hashes. `path` is the sibling at each
+    // level starting at the leaf, and `indices` is 1 at each
+    // level where the current node is the right child
+    fn merkle_verify(&mut self, args: &[Expr]) -> Result<Option<Var>> {
+        if args.len() != 4 {
+            return log::error!(TypeError: &format!(
+                "merkle_verify expects 4 arguments (root, leaf, path, indices), received {}",
+                args.len()
+            ));
+        }
+        let path = self.eval(args[2].clone(), false)?;
+        let indices = self.eval(args[3].clone(), false)?;
+        let (path, indices) = match (path, indices) {
+            (Some(path), Some(indices))
+                if path.dimensions.len() == 1 && path.dimensions == indices.dimensions =>
+            {
+                (path, indices)
+            }
+            _ => {
+                return log::error!(
+                    TypeError: "merkle_verify path and indices must be vectors of the same length"
+                )
             }
-            out
         };
-        let indices = indices
-            .iter()
-            .map(|v| match v {
-                Expr::Lit(v) => Ok((*v).parse::<usize>().unwrap()),
-                Expr::Val(_, _) => {
-                    let out = self.eval(v.clone(), false)?;
-                    if out.is_none() {
-                        return log::error!("vector cannot be indexed by stack variable");
-                    }
-                    let out = out.unwrap();
-                    if out.location != VarLocation::Static {
-                        return log::error!("non-static variables are not allowed as indices");
-                    }
-                    if !out.dimensions.is_empty() {
-                        return log::error!("vector cannot be indexed by non-scalar");
-                    }
-                    Ok(out.value.unwrap()[0].try_into().unwrap())
-                }
-                // we should probably do some additional checks here
-                _ => unreachable!(),
-            })
-            .collect::<Result<Vec<usize>>>()?;
-        let mut offset = 0;
-        for x in 0..indices.len() {
-            // for each index we sum the deeper dimensions
-            // to determine how far to move in the array storage
-            if x == indices.len() - 1 && indices.len() == dimensions.len() {
-                offset += indices[x];
-            } else {
-                offset += indices[x] * sum(dimensions, x + 1);
+        if self.eval_to_stack(args[1].clone(), false)?.is_some() {
+            return log::error!(TypeError: "merkle_verify root and leaf must be scalars");
+        }
+        for i in 0..path.dimensions[0] {
+            self.load_scalar(&indices, Some(i))?;
+            // assert the index is 0 or 1
+            for v in ["dup 0", "dup 0", "dup 0", "mul", "eq", "assert"] {
+                self.asm.push(v.to_string());
+            }
+            self.load_scalar(&path, Some(i))?;
+            // stack: node, index, sibling
+            // left = node + index * (sibling - node)
+            // right = sibling + node - left
+            for v in [
+                "dup 0", "dup 3", "push -1", "mul", "add", "dup 2", "mul", "dup 3", "add", "dup 1",
+                "dup 4", "add", "dup 1", "push -1", "mul", "add",
+            ] {
+                self.asm.push(v.to_string());
+            }
+            // hash(left, right) as in `poseidon`
+            for _ in 2..HASH_RATE {
+                self.asm.push("push 0".to_string());
+            }
+            for v in ["dup 8", "dup 10", "hash", "swap 4", "pop 4"] {
+                self.asm.push(v.to_string());
+            }
+            // replace the node with the hash
+            for v in ["swap 5", "pop 5"] {
+                self.asm.push(v.to_string());
+            }
+            self.stack.truncate(self.stack.len() - 2);
+        }
+        if self.eval_to_stack(args[0].clone(), false)?.is_some() {
+            return log::error!(TypeError: "merkle_verify root and leaf must be scalars");
+        }
+        self.asm.push("eq".to_string());
+        self.asm.push("assert".to_string());
+        self.stack.truncate(self.stack.len() - 2);
+        // merkle_verify has no return value
+        Ok(Some(Var {
+            stack_index: None,
+            block_index: self.block_depth,
+            location: VarLocation::Static,
+            memory_index: None,
+            dimensions: vec![],
+            value: Some(vec![BigUint::from(1_u32)]),
+        }))
+    }
+
+    /// Insert an asm block. The arguments are pushed in order and
+    /// replaced by the return values, the last return value is
+    /// on top of the stack.
+    fn asm_block(&mut self, args: &[String], returns: &[String], source: &str) -> Result<()> {
+        let instructions = check_stack_effect(source, args.len(), returns.len())?;
+        for name in returns {
+            if self.vars.contains_key(name) {
+                return log::error!(ScopeError: &format!("var is not unique {name}"));
+            }
+        }
+        for name in args {
+            if !self.vars.contains_key(name) {
+                return log::error!(ScopeError: &format!("variable does not exist: {name}"));
+            }
+            if self
+                .eval_to_stack(Expr::Val(name.clone(), vec![]), false)?
+                .is_some()
+            {
+                return log::error!(
+                    TypeError: &format!("argument \"{name}\" to an asm block must be a scalar")
+                );
+            }
+        }
+        self.asm.push("// asm block".to_string());
+        self.asm.extend(instructions);
+        self.stack.truncate(self.stack.len() - args.len());
+        for name in returns {
+            self.stack.push(name.clone());
+            self.vars.insert(
+                name.clone(),
+                Var {
+                    stack_index: Some(self.stack.len()),
+                    block_index: self.block_depth,
+                    location: VarLocation::Stack,
+                    memory_index: None,
+                    dimensions: vec![],
+                    value: None,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    // the values of an unconstrained block are read from the
+    // secret input using divine, the block is not compiled
+    fn unconstrained_block(&mut self, returns: &[String]) -> Result<()> {
+        for name in returns {
+            if self.vars.contains_key(name) {
+                return log::error!(ScopeError: &format!("var is not unique {name}"));
             }
         }
-        Ok(offset)
+        self.asm.push("// unconstrained block".to_string());
+        for name in returns {
+            self.read_input(name.clone(), "divine 1")?;
+        }
+        Ok(())
+    }
+
+    // declare a variable read from an input stream
+    fn read_input(&mut self, name: String, instruction: &str) -> Result<()> {
+        if self.vars.contains_key(&name) {
+            return log::error!(ScopeError: &format!("var is not unique {name}"));
+        }
+        self.asm.push(instruction.to_string());
+        self.stack.push("".to_string());
+        self.vars.insert(
+            name,
+            Var {
+                stack_index: Some(self.stack.len()),
+                block_index: self.block_depth,
+                location: VarLocation::Stack,
+                memory_index: None,
+                dimensions: vec![],
+                value: None,
+            },
+        );
+        Ok(())
     }
 
     pub fn static_to_stack(&mut self, v: &Var) -> Result<()> {
         if v.location == VarLocation::Static && v.value.clone().unwrap().len() == 1 {
             // static we can put on stack
-            self.stack_push(v.value.clone().unwrap()[0]);
+            self.stack_push(&v.value.clone().unwrap()[0]);
             return Ok(());
         }
         anyhow::bail!("static variable is not a scalar");
@@ -822,12 +1860,52 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
             Expr::VecVec(_v) => Err(anyhow::anyhow!(
                 "matrix literals must be assigned before operation"
             )),
+            Expr::FnCall(name, vars) if name == builtin::POSEIDON => self.poseidon(vars),
+            Expr::FnCall(name, vars) if name == builtin::CHALLENGE => self.challenge(vars),
+            Expr::FnCall(name, vars) if name == builtin::MERKLE_VERIFY => self.merkle_verify(vars),
+            Expr::FnCall(name, vars) if name == builtin::XFE_MUL || name == builtin::XFE_INV => {
+                self.extension_op(name, vars, is_returning)
+            }
+            Expr::FnCall(name, vars) if name == builtin::SLICE => self.slice(vars),
+            Expr::FnCall(name, vars) if name == builtin::NEG => self.neg(vars),
+            Expr::FnCall(name, vars) if builtin::REDUCTIONS.contains(&name.as_str()) => {
+                self.reduce(name, vars)
+            }
+            Expr::FnCall(name, vars) if name == builtin::DOT || name == builtin::MATMUL => {
+                self.sum_of_products(name, vars, is_returning)
+            }
+            Expr::FnCall(name, _) if name == builtin::SHA256 || name == builtin::KECCAK256 => {
+                log::error!(
+                    ConfigError: &format!("{name} is not supported in the tasm target"),
+                    "binary hash functions are only available in the r1cs target"
+                )
+            }
+            Expr::FnCall(name, _) if name == builtin::EDDSA_VERIFY => log::error!(
+                ConfigError: &format!("{name} is not supported in the tasm target"),
+                "signature verification is only available in the r1cs target"
+            ),
+            Expr::FnCall(name, _) if name == builtin::IPA_VERIFY => log::error!(
+                ConfigError: &format!("{name} is not supported in the tasm target"),
+                "inner product arguments are only available in the r1cs target"
+            ),
             Expr::FnCall(name, vars) => {
                 let mut arg_types: Vec<ArgType> = Vec::new();
                 // we push these but don't pop them here
                 // the destination function will handle that
                 let mut stack_arg_count = 0;
                 for v in vars {
+                    if let Expr::Ref(ref_name, indices) = v {
+                        // the function receives the address of the
+                        // referenced scalar
+                        self.push_reference(ref_name, indices)?;
+                        arg_types.push(ArgType {
+                            location: VarLocation::Reference,
+                            dimensions: vec![],
+                            value: None,
+                        });
+                        stack_arg_count += 1;
+                        continue;
+                    }
                     // if it's a stack variable the asm will be modified as needed
                     let o = if self.compiler_state.is_fn_ash.contains_key(name) {
                         // if it's an ash function we can statically evaluate
@@ -851,7 +1929,7 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                         // 1 stack element we don't need to mutate
                         // the virtual stack
                         if let Some(mem_index) = v.memory_index {
-                            self.stack_push(mem_index.try_into().unwrap());
+                            self.push_address(mem_index)?;
                             stack_arg_count += 1;
                         } else if let Some(stack_index) = v.stack_index {
                             // give a copy of the stack memory index or value
@@ -862,7 +1940,7 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                         } else if v.location == VarLocation::Static {
                             //
                         } else {
-                            return Err(anyhow::anyhow!("unexpected: variable has no memory or stack index and is not static"));
+                            return log::error!(MemoryError: "unexpected: variable has no memory or stack index and is not static");
                         }
                     } else {
                         arg_types.push(ArgType {
@@ -881,15 +1959,20 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                 };
                 if let Some(call_type) = self.compiler_state.fn_return_types.get(&call) {
                     call.return_type = Some(call_type.return_type.as_ref().unwrap().clone());
+                } else if let Some(return_type) = self.recursive_return_type(&call)? {
+                    // the function is being compiled, call the
+                    // implementation that is being built
+                    call.return_type = Some(return_type);
                 } else {
                     let fn_ast = self.compiler_state.fn_to_ast.get(name).unwrap().clone();
+                    self.compiler_state.fn_call_stack.push(call.clone());
                     let mut vm = VM::new(self.compiler_state);
                     vm.eval_ast(fn_ast.clone(), arg_types.clone(), None)?;
                     vm.return_if_needed();
                     if let Some(return_type) = vm.return_type {
                         call.return_type = Some(return_type);
                     } else {
-                        return log::error!(&format!(
+                        return log::error!(TypeError: &format!(
                             "unable to determine return type for function \"{}\"",
                             name
                         ), "you may be calling a tasm function with the wrong number or type of arguments");
@@ -899,8 +1982,15 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                     let mut vm = VM::new(self.compiler_state);
                     vm.eval_ast(fn_ast, arg_types.clone(), call.return_type.clone())?;
                     vm.return_if_needed();
-                    let mut asm = vm.asm.clone();
+                    let no_return_call = vm.compiler_state.fn_call_stack.pop().unwrap();
+                    let mut asm = if vm.compiler_state.recursive_fn.contains(&no_return_call) {
+                        vm.recursion_frame(name, call.return_type.as_ref().unwrap())?
+                    } else {
+                        vm.asm.clone()
+                    };
                     asm.push("return".to_string());
+                    let memory = vm.fn_memory(&call);
+                    self.compiler_state.fn_memory.insert(call.clone(), memory);
                     // let no_return_call = call.clone();
                     self.compiler_state.compiled_fn.insert(call.clone(), asm);
                     // self.compiler_state
@@ -916,6 +2006,9 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                     .or_insert_with(|| 1);
                 // the function pops all arguments off the stack before it returns
                 match call.return_type.clone().unwrap().location {
+                    VarLocation::Reference => {
+                        log::error!(TypeError: "functions cannot return references")
+                    }
                     VarLocation::Static => {
                         for _ in 0..stack_arg_count {
                             self.stack.pop();
@@ -930,32 +2023,37 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                         }))
                     }
                     VarLocation::Stack => {
+                        stack_arg_count += self.push_callee_frame(&call)?;
                         for _ in 0..stack_arg_count {
                             self.stack.pop();
                         }
                         self.stack.push("".to_string());
                         // if the return value is a stack variable
                         // we need to increment the virtual stack
-                        self.asm.push(format!("call {}", call.typed_name()));
+                        self.emit_call(&call);
                         Ok(None)
                     }
                     VarLocation::Memory => {
+                        // the return value is written to memory
+                        // owned by the caller
+                        let memory_index = self.memory_index;
                         if is_returning {
                             if let Some(v) = self.vars.get(RETURN_VAR) {
                                 self.stack_dup(v.stack_index.unwrap())?;
                             } else {
-                                return Err(anyhow!("no return memory address"));
+                                return log::error!(MemoryError: "no return memory address");
                             }
                         } else {
-                            self.stack_push(
-                                (self.memory_start + self.memory_index).try_into().unwrap(),
-                            );
+                            self.push_address(memory_index)?;
+                            self.memory_index +=
+                                layout::len(&call.return_type.as_ref().unwrap().dimensions);
                         }
+                        stack_arg_count += self.push_callee_frame(&call)?;
                         for _ in 0..stack_arg_count {
                             self.stack.pop();
                         }
                         self.stack.pop();
-                        self.asm.push(format!("call {}", call.typed_name()));
+                        self.emit_call(&call);
                         if is_returning {
                             if let Some(v) = self.vars.get(RETURN_VAR) {
                                 Ok(Some(Var {
@@ -967,14 +2065,9 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                                     value: None,
                                 }))
                             } else {
-                                Err(anyhow!("no return memory address"))
+                                log::error!(MemoryError: "no return memory address")
                             }
                         } else {
-                            let len = VM::<T>::dimensions_to_len(
-                                call.clone().return_type.unwrap().dimensions,
-                            );
-                            let memory_index = self.memory_start + self.memory_index;
-                            self.memory_index += len;
                             Ok(Some(Var {
                                 stack_index: None,
                                 location: VarLocation::Memory,
@@ -990,10 +2083,13 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
             Expr::Val(name, indices) => {
                 // if the val is a static we push to stack
                 if !self.vars.contains_key(name) {
-                    return log::error!(&format!("unknown variable: {name}"));
+                    return log::error!(ScopeError: &format!("unknown variable: {name}"));
                 }
                 let v = self.vars.get(name).unwrap().clone();
-                self.load_variable(&v, indices)
+                self.load_variable(name, &v, indices)
+            }
+            Expr::Ref(_, _) => {
+                log::error!(TypeError: "references may only be passed as function arguments")
             }
             Expr::Lit(v) => Ok(Some(Var {
                 stack_index: None,
@@ -1001,180 +2097,354 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                 dimensions: vec![],
                 memory_index: None,
                 block_index: self.block_depth,
-                value: Some(vec![v.parse::<u64>().unwrap()]),
+                value: Some(vec![literal::<T>(v)?]),
             })),
             Expr::NumOp { lhs, op, rhs } => {
-                // only allow variables of same dimensions
-                // for now
-                let mut lv = self.eval(*lhs.clone(), false)?;
-                let mut rv = self.eval(*rhs.clone(), false)?;
-                if lv.is_some()
-                    && rv.is_none()
-                    && self.static_to_stack(&lv.clone().unwrap()).is_ok()
-                {
-                    lv = None;
-                    self.asm.push("swap 1".to_string());
+                let int_type = expr.int_type(&|name| self.int_types.get(name).copied())?;
+                let out = self.eval_numop(lhs, op, rhs, is_returning)?;
+                if let Some(int_type) = int_type {
+                    self.assert_int(&out, int_type)?;
                 }
-                if rv.is_some()
-                    && lv.is_none()
-                    && self.static_to_stack(&rv.clone().unwrap()).is_ok()
-                {
-                    rv = None;
+                Ok(out)
+            }
+            Expr::BoolOp { lhs, bool_op, rhs } => {
+                if matches!(bool_op, BoolOp::And | BoolOp::Or) {
+                    return self.eval_logic_op(bool_op, lhs, rhs);
                 }
+                let lv = self.eval_to_stack(*lhs.clone(), false)?;
+                let rv = self.eval_to_stack(*rhs.clone(), false)?;
                 if lv.is_none() != rv.is_none() {
-                    return log::error!("type mismatch in numeric operation");
+                    return log::error!(TypeError: "cannot apply boolean operation to stack and memory vars");
                 }
                 if lv.is_some() {
-                    let lvu = lv.unwrap();
-                    let rvu = rv.unwrap();
-                    if lvu.dimensions.len() != rvu.dimensions.len() {
-                        return log::error!(
-                            "type mismatch in numeric operation, vector width mismatch"
-                        );
+                    return log::error!(TypeError: "cannot apply boolean operation to memory vars");
+                }
+                // each operation consumes the two operands and leaves
+                // a single 0 or 1 on the top of the stack
+                match bool_op {
+                    BoolOp::Equal => {
+                        self.asm.push("eq".to_string());
+                        self.stack.pop();
                     }
-                    for x in 0..lvu.dimensions.len() {
-                        if lvu.dimensions[x] != rvu.dimensions[x] {
-                            return log::error!(
-                                "type mismatch in numeric operation, vector height mismatch"
-                            );
-                        }
+                    BoolOp::NotEqual => {
+                        self.asm
+                            .append(&mut vec![format!("eq"), format!("push 0"), format!("eq")]);
+                        self.stack.pop();
                     }
-                    let out_v = if is_returning
-                        && lvu.location != VarLocation::Static
-                        && rvu.location != VarLocation::Static
-                    {
-                        let return_var = self.vars.get(RETURN_VAR).unwrap();
-                        Some(Var {
-                            stack_index: Some(return_var.stack_index.unwrap()),
-                            block_index: self.block_depth,
-                            location: VarLocation::Memory,
-                            memory_index: None,
-                            dimensions: lvu.dimensions.clone(),
-                            value: None,
-                        })
-                    } else {
-                        None
-                    };
-                    // operate on elements in a vector stored in memory
-                    // store the result in memory
-                    // TODO: batch memory read/write operations
-                    return match op {
-                        NumOp::Add => self
-                            .op_elements(&lvu, &rvu, out_v, |a, b| (a + b, vec![format!("add")])),
-                        NumOp::Mul => self
-                            .op_elements(&lvu, &rvu, out_v, |a, b| (a * b, vec![format!("mul")])),
-                        NumOp::Sub => self.op_elements(&lvu, &rvu, out_v, |a, b| {
-                            (
-                                a - b,
-                                vec![format!("push -1"), format!("mul"), format!("add")],
-                            )
-                        }),
-                        NumOp::Inv => self.op_elements(&lvu, &rvu, out_v, |a, b| {
-                            (a / b, vec![format!("invert"), format!("mul")])
-                        }),
-                    };
-                }
-                match op {
-                    // each one of these removes two elements and
-                    // adds 1
-                    // so we have a net effect of a single pop
-                    NumOp::Add => {
-                        self.asm.push("add".to_string());
+                    // `lt` computes st0 < st1 and crashes the vm
+                    // if either operand is not a u32
+                    BoolOp::GreaterThan => {
+                        self.asm.push("lt".to_string());
                         self.stack.pop();
                     }
-                    NumOp::Sub => {
-                        self.asm.append(&mut vec![
-                            format!("push -1"),
-                            format!("mul"),
-                            format!("add"),
-                        ]);
+                    BoolOp::LessThan => {
+                        self.asm.append(&mut vec![format!("swap 1"), format!("lt")]);
                         self.stack.pop();
                     }
-                    NumOp::Mul => {
-                        self.asm.push("mul".to_string());
+                    BoolOp::GreaterThanOrEqual => {
+                        self.asm.append(&mut vec![
+                            format!("swap 1"),
+                            format!("lt"),
+                            format!("push 0"),
+                            format!("eq"),
+                        ]);
                         self.stack.pop();
                     }
-                    NumOp::Inv => {
+                    BoolOp::LessThanOrEqual => {
                         self.asm
-                            .append(&mut vec![format!("invert"), format!("mul")]);
+                            .append(&mut vec![format!("lt"), format!("push 0"), format!("eq")]);
                         self.stack.pop();
                     }
+                    BoolOp::And | BoolOp::Or => unreachable!(),
                 }
                 Ok(None)
             }
-            Expr::BoolOp { lhs, bool_op, rhs } => {
+            Expr::BitOp { lhs, bit_op, rhs } => {
+                if expr
+                    .int_type(&|name| self.int_types.get(name).copied())?
+                    .is_none()
+                {
+                    return log::error!(
+                        TypeError: "bitwise operators require integer operands",
+                        "declare an operand with an integer type e.g. `let x: u32 = 1`"
+                    );
+                }
                 let lv = self.eval_to_stack(*lhs.clone(), false)?;
                 let rv = self.eval_to_stack(*rhs.clone(), false)?;
-                if lv.is_none() != rv.is_none() {
-                    return log::error!("cannot apply boolean operation to stack and memory vars");
+                if lv.is_some() || rv.is_some() {
+                    return log::error!(TypeError: "bitwise operands must be scalars");
                 }
-                if lv.is_some() {
-                    return log::error!("cannot apply boolean operation to memory vars");
-                }
-                match bool_op {
-                    BoolOp::Equal => {
-                        // we're popping the two inputs off the stack
-                        // eq removes 1, and skiz removes 1
-                        self.asm.append(&mut vec![format!("eq"), format!("skiz")]);
-                        self.stack.pop();
-                        self.stack.pop();
+                // each operation consumes the two operands and leaves
+                // the result on the top of the stack. `and`, `xor`, and
+                // `pow` crash the vm if an operand is not a u32
+                match bit_op {
+                    BitOp::And => {
+                        self.asm.push("and".to_string());
                     }
-                    BoolOp::NotEqual => {
+                    BitOp::Xor => {
+                        self.asm.push("xor".to_string());
+                    }
+                    // a | b = a + b - (a & b)
+                    BitOp::Or => {
                         self.asm.append(&mut vec![
-                            format!("eq"),
+                            format!("dup 1"),
+                            format!("dup 1"),
+                            format!("and"),
                             format!("push -1"),
+                            format!("mul"),
+                            format!("add"),
                             format!("add"),
-                            format!("skiz"),
                         ]);
-                        self.stack.pop();
-                        self.stack.pop();
                     }
-                    _ => {
-                        return Err(anyhow!("boolean operation not supported"));
+                    // multiply by 2^n and discard the high 32 bits
+                    BitOp::Shl => {
+                        self.asm.append(&mut vec![
+                            format!("push 32"),
+                            format!("dup 1"),
+                            format!("lt"),
+                            format!("assert"),
+                            format!("push 2"),
+                            format!("pow"),
+                            format!("mul"),
+                            format!("split"),
+                            format!("swap 1"),
+                            format!("pop 1"),
+                        ]);
+                    }
+                    // divide by 2^n and discard the remainder
+                    BitOp::Shr => {
+                        self.asm.append(&mut vec![
+                            format!("push 32"),
+                            format!("dup 1"),
+                            format!("lt"),
+                            format!("assert"),
+                            format!("push 2"),
+                            format!("pow"),
+                            format!("div_mod"),
+                            format!("pop 1"),
+                        ]);
                     }
                 }
+                self.stack.pop();
                 Ok(None)
             }
         }
     }
 
+    fn eval_numop(
+        &mut self,
+        lhs: &Expr,
+        op: &NumOp,
+        rhs: &Expr,
+        is_returning: bool,
+    ) -> Result<Option<Var>> {
+        // only allow variables of same dimensions
+        // for now
+        let mut lv = self.eval(lhs.clone(), false)?;
+        let mut rv = self.eval(rhs.clone(), false)?;
+        if lv.is_some() && rv.is_none() && self.static_to_stack(&lv.clone().unwrap()).is_ok() {
+            lv = None;
+            self.asm.push("swap 1".to_string());
+        }
+        if rv.is_some() && lv.is_none() && self.static_to_stack(&rv.clone().unwrap()).is_ok() {
+            rv = None;
+        }
+        if lv.is_none() != rv.is_none() {
+            return log::error!(TypeError: "type mismatch in numeric operation");
+        }
+        if lv.is_some() {
+            let lvu = lv.unwrap();
+            let rvu = rv.unwrap();
+            if lvu.dimensions.len() != rvu.dimensions.len() {
+                return log::error!(
+                    TypeError: "type mismatch in numeric operation, vector width mismatch"
+                );
+            }
+            for x in 0..lvu.dimensions.len() {
+                if lvu.dimensions[x] != rvu.dimensions[x] {
+                    return log::error!(
+                        TypeError: "type mismatch in numeric operation, vector height mismatch"
+                    );
+                }
+            }
+            let out_v = if is_returning
+                && lvu.location != VarLocation::Static
+                && rvu.location != VarLocation::Static
+            {
+                let return_var = self.vars.get(RETURN_VAR).unwrap();
+                Some(Var {
+                    stack_index: Some(return_var.stack_index.unwrap()),
+                    block_index: self.block_depth,
+                    location: VarLocation::Memory,
+                    memory_index: None,
+                    dimensions: lvu.dimensions.clone(),
+                    value: None,
+                })
+            } else {
+                None
+            };
+            // operate on elements in a vector stored in memory
+            // store the result in memory
+            return match op {
+                NumOp::Add => {
+                    self.op_elements(&lvu, &rvu, out_v, |a, b| (a + b, vec![format!("add")]))
+                }
+                NumOp::Mul => {
+                    self.op_elements(&lvu, &rvu, out_v, |a, b| (a * b, vec![format!("mul")]))
+                }
+                NumOp::Sub => self.op_elements(&lvu, &rvu, out_v, |a, b| {
+                    (
+                        a - b,
+                        vec![format!("push -1"), format!("mul"), format!("add")],
+                    )
+                }),
+                NumOp::Inv => self.op_elements(&lvu, &rvu, out_v, |a, b| {
+                    (a / b, vec![format!("invert"), format!("mul")])
+                }),
+            };
+        }
+        match op {
+            // each one of these removes two elements and
+            // adds 1
+            // so we have a net effect of a single pop
+            NumOp::Add => {
+                self.asm.push("add".to_string());
+                self.stack.pop();
+            }
+            NumOp::Sub => {
+                self.asm.append(&mut vec![
+                    format!("push -1"),
+                    format!("mul"),
+                    format!("add"),
+                ]);
+                self.stack.pop();
+            }
+            NumOp::Mul => {
+                self.asm.push("mul".to_string());
+                self.stack.pop();
+            }
+            NumOp::Inv => {
+                self.asm
+                    .append(&mut vec![format!("invert"), format!("mul")]);
+                self.stack.pop();
+            }
+        }
+        Ok(None)
+    }
+
+    // assert that a variable is in the range of an integer type
+    fn assert_int_var(&mut self, name: &str, int_type: IntType) -> Result<()> {
+        let v = self.eval(Expr::Val(name.to_string(), vec![]), false)?;
+        self.assert_int(&v, int_type)?;
+        if v.is_none() {
+            self.stack_pop(1);
+        }
+        Ok(())
+    }
+
+    // assert that a scalar result is in the range of an integer
+    // type. Values on the stack are checked using `split`
+    fn assert_int(&mut self, v: &Option<Var>, int_type: IntType) -> Result<()> {
+        let name = int_type.name();
+        if int_type != IntType::U32 {
+            return log::error!(
+                ConfigError: &format!("{name} is not supported in the tasm target"),
+                "integers are lowered to u32 instructions, use u32"
+            );
+        }
+        match v {
+            None => {
+                // the high 32 bits must be 0
+                self.asm.append(&mut vec![
+                    format!("dup 0"),
+                    format!("split"),
+                    format!("pop 1"),
+                    format!("push 0"),
+                    format!("eq"),
+                    format!("assert"),
+                ]);
+            }
+            Some(v) if v.location == VarLocation::Static && v.dimensions.is_empty() => {
+                let value = v.value.clone().unwrap()[0].clone();
+                if value >= BigUint::from(1_u32) << int_type.bits() {
+                    return log::error!(
+                        &format!("{name} overflow: {value} is out of range"),
+                        &format!("the result of a {name} operation must be less than 2^32")
+                    );
+                }
+            }
+            Some(_) => {
+                return log::error!(TypeError: &format!("{name} variables must be scalars"));
+            }
+        }
+        Ok(())
+    }
+
     pub fn eval_ast(
         &mut self,
         ast: Vec<AstNode>,
         arg_types: Vec<ArgType>,
         return_type: Option<ArgType>,
     ) -> Result<()> {
+        let outer_location = self.location.clone();
+        let out = self
+            .eval_statements(ast, arg_types, return_type)
+            .map_err(|e| log::locate(e, &self.location));
+        self.location = outer_location;
+        out
+    }
+
+    fn eval_statements(
+        &mut self,
+        ast: Vec<AstNode>,
+        arg_types: Vec<ArgType>,
+        return_type: Option<ArgType>,
+    ) -> Result<()> {
+        // an attribute applies to the statement following it
+        let mut pending_inline = None;
         for v in ast {
             match v {
+                AstNode::Location(location) => {
+                    self.location = Some(location);
+                    self.call_inline = pending_inline.take();
+                    self.mark_location();
+                }
+                AstNode::Inline(inline) => pending_inline = Some(inline),
                 AstNode::AssignVec(name, indices, expr) => {
                     if !self.vars.contains_key(&name) {
-                        return log::error!(&format!(
+                        return log::error!(ScopeError: &format!(
                             "attempting to assign to undeclared variable \"{name}\""
                         ));
                     }
+                    let v = self.vars.get(&name).unwrap();
+                    if v.location == VarLocation::Memory && indices.len() < v.dimensions.len() {
+                        // assigning a vector into part of a vector
+                        self.assign_memory_slice(&name, &indices, expr)?;
+                        continue;
+                    }
                     // value being assigned
                     let o = self.eval_to_stack(expr, false)?;
                     let v = self.vars.get(&name).unwrap().clone();
                     // offset is pushed onto the stack
-                    self.calc_vec_offset(&v.dimensions, &indices)?;
+                    self.calc_vec_offset(&name, &v.dimensions, &indices)?;
                     match indices.len().cmp(&v.dimensions.len()) {
                         cmp::Ordering::Less => {
                             return Err(anyhow::anyhow!("cannot assign vec"));
                         }
                         cmp::Ordering::Greater => {
-                            return log::error!("var dimension is too low for assignment", "you're accessing an index on a scalar, or an n+1 dimension on a vector of n dimensions");
+                            return log::error!(TypeError: "var dimension is too low for assignment", "you're accessing an index on a scalar, or an n+1 dimension on a vector of n dimensions");
                         }
                         cmp::Ordering::Equal => {
                             // assigning a scalar into a specific index in a vec
                             if o.is_some() {
-                                return log::error!(&format!(
+                                return log::error!(TypeError: &format!(
                                     "attempting to assign memory value to scalar \"{name}\""
                                 ));
                             }
                             // push the expr to the stack then into memory
                             // we're accessing a scalar, move it to the stack
                             if let Some(mem_index) = v.memory_index {
-                                self.stack_push(mem_index.try_into().unwrap());
+                                self.push_address(mem_index)?;
                                 self.asm.push("add".to_string());
                                 self.stack.pop();
                                 self.stack_write_mem(1);
@@ -1187,7 +2457,7 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                                 self.stack_pop(1);
                             } else {
                                 return log::error!(
-                                    "unexpected: variable has no memory or stack index"
+                                    MemoryError: "unexpected: variable has no memory or stack index"
                                 );
                             }
                         }
@@ -1195,18 +2465,18 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                 }
                 AstNode::EmptyVecDef(name, dimensions) => {
                     if self.vars.contains_key(&name) {
-                        return log::error!(&format!(
+                        return log::error!(ScopeError: &format!(
                             "attempting to define a variable that already exists \"{name}\""
                         ));
                     }
-                    let len = VM::<T>::dimensions_to_len(dimensions.clone());
+                    let len = layout::len(&dimensions);
                     self.vars.insert(
                         name.clone(),
                         Var {
                             stack_index: None,
                             block_index: self.block_depth,
                             location: VarLocation::Memory,
-                            memory_index: Some(self.memory_start + self.memory_index),
+                            memory_index: Some(self.memory_index),
                             dimensions,
                             value: None,
                         },
@@ -1214,21 +2484,122 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                     self.memory_index += len;
                 }
                 AstNode::Stmt(name, is_let, expr) => {
+                    // integer operations are range checked when evaluated
+                    let expr_type = expr.int_type(&|name| self.int_types.get(name).copied())?;
                     if is_let {
+                        self.int_types.remove(&name);
                         self.let_var(name, expr)?;
                     } else {
-                        self.set_var(name, expr)?
+                        self.set_var(name.clone(), expr)?;
+                        if let Some(int_type) = self.int_types.get(&name).copied() {
+                            if expr_type != Some(int_type) {
+                                self.assert_int_var(&name, int_type)?;
+                            }
+                        }
+                    }
+                }
+                AstNode::TypedDef(name, var_type, expr) => {
+                    let expr_type = expr.int_type(&|name| self.int_types.get(name).copied())?;
+                    self.let_var(name.clone(), expr)?;
+                    let dimensions = &self.vars.get(&name).unwrap().dimensions;
+                    if !var_type.matches(dimensions) {
+                        return log::error!(TypeError: &format!(
+                            "variable \"{name}\" is declared as {} but assigned a value of dimensions {:?}",
+                            var_type.name(),
+                            dimensions
+                        ));
+                    }
+                    if let VarType::Int(int_type) = var_type {
+                        if expr_type != Some(int_type) {
+                            self.assert_int_var(&name, int_type)?;
+                        }
+                        self.int_types.insert(name, int_type);
                     }
                 }
+                AstNode::PubInput(name) => self.read_input(name, "read_io 1")?,
+                // secret inputs are read from the non-determinism
+                AstNode::SecretInput(name) => self.read_input(name, "divine 1")?,
+                AstNode::PubOutput(name) => {
+                    if !self.vars.contains_key(&name) {
+                        return log::error!(ScopeError: &format!("variable does not exist: {name}"));
+                    }
+                    if self
+                        .eval_to_stack(Expr::Val(name.clone(), vec![]), false)?
+                        .is_some()
+                    {
+                        return log::error!(TypeError: &format!("public output \"{name}\" must be a scalar"));
+                    }
+                    self.asm.push("write_io 1".to_string());
+                    self.stack.pop();
+                }
                 AstNode::ExprUnassigned(expr) => {
                     let o = self.eval(expr, false)?;
                     if o.is_none() {
                         self.stack_pop(1);
                     }
                 }
+                AstNode::Assert(expr, message) => {
+                    if self.eval_to_stack(expr, false)?.is_some() {
+                        return log::error!(TypeError: "assertion must be a scalar, received a vector/matrix");
+                    }
+                    // the message is kept as a comment for
+                    // debugging failed executions
+                    self.asm.push(format!("// assert: {message}"));
+                    self.asm.push("assert".to_string());
+                    self.stack.pop();
+                }
+                AstNode::Asm(args, returns, source) => {
+                    self.asm_block(&args, &returns, &source)?;
+                }
+                AstNode::Unconstrained(returns, _) => {
+                    self.unconstrained_block(&returns)?;
+                }
+                AstNode::Ar1cs(_, _, _) => {
+                    return log::error!(
+                        ConfigError: "ar1cs blocks are only supported in the r1cs target",
+                        "write the block in ash to compile it for tasm"
+                    );
+                }
+                AstNode::Lookup(table, entries) => {
+                    let table = LookupTable::<T::F>::new(&table)?;
+                    let width = table.width();
+                    table.check_width(entries.len())?;
+                    // each column is read from below the row
+                    // product and the count
+                    if width > 14 {
+                        return log::error!(
+                            MemoryError: &format!("table {} has too many columns", table.name),
+                            "lookups in the tasm target support at most 14 columns"
+                        );
+                    }
+                    for expr in entries {
+                        if self.eval_to_stack(expr, false)?.is_some() {
+                            return log::error!(TypeError: "lookup values must be scalars in the tasm target");
+                        }
+                    }
+                    // count the rows equal to the values, rows are
+                    // distinct so the count is 1 if the lookup passes
+                    self.asm.push(format!("// lookup in {}", table.name));
+                    self.stack_push(0);
+                    for row in &table.rows {
+                        self.asm.push("push 1".to_string());
+                        for (c, v) in row.iter().enumerate() {
+                            self.asm.append(&mut vec![
+                                format!("dup {}", width - c + 1),
+                                format!("push {v}"),
+                                "eq".to_string(),
+                                "mul".to_string(),
+                            ]);
+                        }
+                        self.asm.push("add".to_string());
+                    }
+                    self.asm.push("assert".to_string());
+                    self.stack.pop();
+                    self.stack_pop(width);
+                }
                 AstNode::FnVar(vars) => {
                     if arg_types.len() != vars.len() {
-                        return log::error!(&format!(
+                        return log::error!(TypeError: &format!(
                             "function argument count mismatch: expected {}, got {}",
                             arg_types.len(),
                             vars.len()
@@ -1258,6 +2629,32 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                             },
                         )?;
                     }
+                    if self.frame_address.is_none() {
+                        self.fn_var(
+                            FRAME_VAR.to_string(),
+                            ArgType {
+                                location: VarLocation::Stack,
+                                dimensions: vec![],
+                                value: None,
+                            },
+                        )?;
+                    }
+                }
+                // dimension parameters are static in each
+                // specialization of the function
+                AstNode::FnDims(params) => {
+                    let dimensions = params
+                        .iter()
+                        .map(|(name, _)| {
+                            self.vars
+                                .get(name)
+                                .map(|v| v.dimensions.clone())
+                                .unwrap_or_default()
+                        })
+                        .collect::<Vec<_>>();
+                    for (name, len) in layout::dim_params(&params, &dimensions)? {
+                        self.static_var(name, Expr::Lit(len.to_string()))?;
+                    }
                 }
                 AstNode::Rtrn(expr) => {
                     self.return_expr(expr)?;
@@ -1269,39 +2666,80 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                     // Expr::Lit and Expr::Val containing other statics
                     self.static_var(name, expr)?;
                 }
-                AstNode::If(expr, block_ast) => {
+                AstNode::If(expr, block_ast, else_ast) => {
                     let v = self.eval_to_stack(expr, false)?;
                     if v.is_some() {
                         return Err(anyhow::anyhow!("if node returned memory value"));
                     }
+                    if else_ast.is_empty() {
+                        // skiz consumes the boolean result
+                        self.asm.push("skiz".to_string());
+                        self.stack.pop();
+                        self.compile_block(block_ast)?;
+                    } else {
+                        // keep a copy of the condition on the stack
+                        // while the if block executes so we can
+                        // invert it for the else block
+                        self.stack_dup(self.stack.len())?;
+                        self.asm.push("skiz".to_string());
+                        self.stack.pop();
+                        self.compile_block(block_ast)?;
+                        self.stack_push(0);
+                        self.asm.push("eq".to_string());
+                        self.stack.pop();
+                        self.asm.push("skiz".to_string());
+                        self.stack.pop();
+                        self.compile_block(else_ast)?;
+                    }
+                }
+                AstNode::While(expr, block_ast) => {
+                    // the loop is compiled into a block that
+                    // evaluates the condition on each entry, returns
+                    // if it is false, and otherwise executes the body
+                    // and then jumps back to the beginning of the block
+                    //
+                    // the body must leave the stack as it found it
                     let block_name = format!("block_____{}", self.compiler_state.block_counter);
                     self.compiler_state.block_counter += 1;
                     self.call_block(&block_name);
-                    // vm.eval(expr1);
-                    // vm.eval(expr2);
-                    // push 0 to the stack based on the bool_op
                     let start_asm_len = self.asm.len();
+                    self.mark_location();
+                    let v = self.eval_to_stack(expr, false)?;
+                    if v.is_some() {
+                        return Err(anyhow::anyhow!("while node returned memory value"));
+                    }
+                    // invert the condition so we return when it is 0
+                    self.asm.append(&mut vec![
+                        format!("push 0"),
+                        format!("eq"),
+                        format!("skiz"),
+                        format!("return"),
+                    ]);
+                    self.stack.pop();
                     self.begin_block();
-                    // blocks can't take args
                     self.eval_ast(block_ast, vec![], None)?;
                     self.end_block()?;
-                    // pull the resulting asm as the block asm
                     let mut block_asm = self.asm.drain(start_asm_len..).collect::<Vec<String>>();
                     block_asm.insert(0, format!("{block_name}:"));
-                    block_asm.push("return".to_string());
+                    block_asm.push("recurse".to_string());
                     self.compiler_state.block_fn_asm.push(block_asm);
                 }
                 AstNode::Loop(expr, block_ast) => {
                     let o = self.eval(expr, false)?;
                     if o.is_none() {
-                        return log::error!("loop condition must be static");
+                        return log::error!(TypeError: "loop condition must be static");
                     }
                     let o = o.unwrap();
                     if o.location != VarLocation::Static {
-                        return log::error!("loop condition must be static");
+                        return log::error!(TypeError: "loop condition must be static");
                     }
 
-                    for _ in 0..o.value.clone().unwrap()[0] {
+                    let count = o.value.clone().unwrap()[0].to_string().parse::<u64>()?;
+                    if count > self.compiler_state.unroll_limit as u64 {
+                        self.compile_loop(count, block_ast)?;
+                        continue;
+                    }
+                    for _ in 0..count {
                         self.begin_block();
                         self.eval_ast(block_ast.clone(), vec![], None)?;
                         self.end_block()?;
@@ -1317,19 +2755,19 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
         match v.location {
             VarLocation::Stack => {
                 if offset.is_some() {
-                    return log::error!("attempting to access stack variable by index");
+                    return log::error!(MemoryError: "attempting to access stack variable by index");
                 }
                 self.stack_dup(v.stack_index.unwrap())?;
             }
             VarLocation::Memory => {
                 if let Some(offset) = offset {
-                    self.stack_push(offset.try_into().unwrap());
+                    self.stack_push(offset);
                 } else {
-                    self.calc_vec_offset(&v.dimensions, &[])?;
+                    self.stack_push(0);
                 }
                 // we're accessing a scalar, move it to the stack
                 if let Some(mem_index) = v.memory_index {
-                    self.stack_push(mem_index.try_into().unwrap());
+                    self.push_address(mem_index)?;
                     self.asm.push("add".to_string());
                     self.stack.pop();
                     self.stack_read_mem(1);
@@ -1341,7 +2779,7 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                     self.stack_read_mem(1);
                     self.stack_pop(1);
                 } else {
-                    return Err(anyhow!("unexpected: variable has no memory or stack index"));
+                    return log::error!(MemoryError: "unexpected: variable has no memory or stack index");
                 }
             }
             VarLocation::Static => {
@@ -1353,20 +2791,37 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                     return Err(anyhow!("static variable access must have an offset"));
                 }
                 let value = v.value.as_ref().unwrap();
-                self.stack_push(value[offset.unwrap()]);
+                self.stack_push(&value[offset.unwrap()]);
+            }
+            VarLocation::Reference => {
+                if offset.is_some() {
+                    return log::error!(MemoryError: "attempting to access reference by index");
+                }
+                self.stack_dup(v.stack_index.unwrap())?;
+                self.stack_read_mem(1);
+                self.stack_pop(1);
             }
         }
         Ok(())
     }
 
     // load a stack, memory or static variable and return it
-    fn load_variable(&mut self, v: &Var, indices: &[Expr]) -> Result<Option<Var>> {
+    fn load_variable(&mut self, name: &str, v: &Var, indices: &[Expr]) -> Result<Option<Var>> {
         match v.location {
             VarLocation::Stack => {
                 if !indices.is_empty() {
-                    return log::error!("attempting to access stack variable \"unknown\" by index");
+                    return log::error!(MemoryError: "attempting to access stack variable \"unknown\" by index");
+                }
+                self.stack_dup(v.stack_index.unwrap())?;
+            }
+            VarLocation::Reference => {
+                if !indices.is_empty() {
+                    return log::error!(MemoryError: "attempting to access reference by index");
                 }
+                // read the referenced scalar onto the stack
                 self.stack_dup(v.stack_index.unwrap())?;
+                self.stack_read_mem(1);
+                self.stack_pop(1);
             }
             VarLocation::Memory => {
                 // return a subset of the original variable based on the
@@ -1379,11 +2834,17 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                 //
                 // if we're operating on two scalars (length 1 vector)
                 // we should move the value to the stack
-                if indices.len() == v.dimensions.len() {
-                    self.calc_vec_offset(&v.dimensions, indices)?;
+                let is_scalar = v.dimensions.is_empty() && indices.is_empty();
+                if let (true, Some(mem_index)) = (is_scalar, v.memory_index) {
+                    // a scalar moved to memory by `declare_scalar`
+                    self.push_address(mem_index)?;
+                    self.stack_read_mem(1);
+                    self.stack_pop(1);
+                } else if indices.len() == v.dimensions.len() {
+                    self.calc_vec_offset(name, &v.dimensions, indices)?;
                     // we're accessing a scalar, move it to the stack
                     if let Some(mem_index) = v.memory_index {
-                        self.stack_push(mem_index.try_into().unwrap());
+                        self.push_address(mem_index)?;
                         self.asm.push("add".to_string());
                         self.stack.pop();
                         self.stack_read_mem(1);
@@ -1395,16 +2856,16 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                         self.stack_read_mem(1);
                         self.stack_pop(1);
                     } else {
-                        return Err(anyhow!("unexpected: variable has no memory or stack index"));
+                        return log::error!(MemoryError: "unexpected: variable has no memory or stack index");
                     }
                 } else {
-                    let offset = self.calc_vec_offset_static(&v.dimensions, indices)?;
+                    let offset = self.calc_vec_offset_static(name, &v.dimensions, indices)?;
                     // we're accessing a vec/mat, leave it in memory
                     if let Some(mem_index) = v.memory_index {
                         if v.stack_index.is_some() {
-                            return Err(anyhow!(
-                                "memory variable should not have a stack and memory index defined"
-                            ));
+                            return log::error!(
+                                MemoryError: "memory variable should not have a stack and memory index defined"
+                            );
                         }
                         return Ok(Some(Var {
                             stack_index: None,
@@ -1416,9 +2877,9 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                         }));
                     } else if offset == 0 {
                         if v.stack_index.is_none() {
-                            return Err(anyhow!(
-                                "memory variable has neither stack nor memory index defined"
-                            ));
+                            return log::error!(
+                                MemoryError: "memory variable has neither stack nor memory index defined"
+                            );
                         }
                         return Ok(Some(Var {
                             stack_index: v.stack_index,
@@ -1429,9 +2890,9 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                             value: None,
                         }));
                     } else {
-                        return Err(anyhow::anyhow!(
-                            "cannot access stack addressed memory variable by index"
-                        ));
+                        return log::error!(
+                            MemoryError: "cannot access stack addressed memory variable by index"
+                        );
                     }
                 }
             }
@@ -1440,7 +2901,7 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                     return Err(anyhow!("static variable does not have values defined"));
                 }
                 let value = v.value.as_ref().unwrap();
-                let offset = self.calc_vec_offset_static(&v.dimensions, indices)?;
+                let offset = self.calc_vec_offset_static(name, &v.dimensions, indices)?;
                 if indices.len() == v.dimensions.len() {
                     // returning a scalar
                     return Ok(Some(Var {
@@ -1449,19 +2910,20 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                         location: v.location.clone(),
                         memory_index: v.memory_index,
                         dimensions: vec![],
-                        value: Some(vec![value[offset]]),
+                        value: Some(vec![value[offset].clone()]),
                     }));
                 } else {
                     // returning a vector
-                    anyhow::bail!("static vector slices not supported");
-                    // return Ok(Some(Var {
-                    //     stack_index: v.stack_index,
-                    //     block_index: v.block_index,
-                    //     location: v.location.clone(),
-                    //     memory_index: v.memory_index,
-                    //     dimensions: v.dimensions[indices.len()..].to_vec(),
-                    //     value: Some(value[offset..].to_vec()),
-                    // }));
+                    let dimensions = v.dimensions[indices.len()..].to_vec();
+                    let len = layout::len(&dimensions);
+                    return Ok(Some(Var {
+                        stack_index: v.stack_index,
+                        block_index: v.block_index,
+                        location: v.location.clone(),
+                        memory_index: v.memory_index,
+                        dimensions,
+                        value: Some(value[offset..offset + len].to_vec()),
+                    }));
                 }
             }
         }
@@ -1477,7 +2939,7 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
         out_: Option<Var>,
         ops: fn(T, T) -> (T, Vec<String>),
     ) -> Result<Option<Var>> {
-        let total_len = VM::<T>::dimensions_to_len(v1.dimensions.clone());
+        let total_len = layout::len(&v1.dimensions);
         if v1.location == VarLocation::Static
             && v2.location == VarLocation::Static
             && out_.is_none()
@@ -1492,10 +2954,10 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
             };
             for x in 0..total_len {
                 let (out_v, _) = ops(
-                    T::from(v1.value.as_ref().unwrap()[x]),
-                    T::from(v2.value.as_ref().unwrap()[x]),
+                    element(&v1.value.as_ref().unwrap()[x]),
+                    element(&v2.value.as_ref().unwrap()[x]),
                 );
-                let out_v = out_v.to_string().parse::<u64>().unwrap();
+                let out_v = out_v.to_scalar()?.to_biguint();
                 out.value.as_mut().unwrap().push(out_v);
             }
             return Ok(Some(out));
@@ -1508,36 +2970,99 @@ impl<'a, T: PolynomialRingElement> VM<'a, T> {
                 stack_index: None,
                 block_index: self.block_depth,
                 location: VarLocation::Memory,
-                memory_index: Some(self.memory_start + self.memory_index),
+                memory_index: Some(self.memory_index),
                 dimensions: v1.dimensions.clone(),
                 value: None,
             };
             self.memory_index += total_len;
         }
         // TODO: assert equal shape
-        for x in 0..total_len {
-            self.load_scalar(v1, Some(x))?;
-            // make sure the RHS is read second so the inv operation
-            // is applied to the correct operand
-            self.load_scalar(v2, Some(x))?;
-            // v1 and v2 are operated on and a single output
-            // remains
-            self.asm.append(&mut ops(T::zero(), T::one()).1);
-            self.stack.pop();
-
-            if let Some(memory_index) = out.memory_index {
-                self.stack_push((memory_index + x).try_into().unwrap());
-            } else if let Some(stack_index) = out.stack_index {
-                self.stack_dup(stack_index)?;
-                self.stack_push(x.try_into().unwrap());
-                self.asm.push("add".to_string());
+        // elements are read, operated on, and written in
+        // batches of at most 5
+        let mut start = 0;
+        while start < total_len {
+            let count = cmp::min(5, total_len - start);
+            self.load_elements(v1, start, count)?;
+            self.load_elements(v2, start, count)?;
+            // the stack is a_0 .. a_n b_0 .. b_n with b_n on top.
+            // Results are computed starting with the last element
+            // and placed below the remaining operands so r_0
+            // ends on top, as it's written to the lowest address
+            for remaining in (1..=count).rev() {
+                self.asm.push(format!("pick {remaining}"));
+                // make sure the RHS is on top so the inv operation
+                // is applied to the correct operand
+                self.asm.push("swap 1".to_string());
+                self.asm.append(&mut ops(T::zero(), T::one()).1);
                 self.stack.pop();
+                if remaining > 1 {
+                    self.asm.push(format!("place {}", 2 * (remaining - 1)));
+                }
             }
-            // the final output is written to memory below and
-            // removed from the stack
-            self.stack_write_mem(1);
+            self.push_element_address(&out, start)?;
+            // the results are written to memory and removed
+            // from the stack
+            self.stack_write_mem(count);
             self.stack_pop(1);
+            start += count;
         }
         Ok(Some(out))
     }
+
+    // push consecutive elements of a vector onto the stack,
+    // the element at `start` is deepest
+    fn load_elements(&mut self, v: &Var, start: usize, count: usize) -> Result<()> {
+        match v.location {
+            VarLocation::Static => {
+                let Some(value) = &v.value else {
+                    return Err(anyhow!("static variable does not have values defined"));
+                };
+                for x in start..start + count {
+                    self.stack_push(&value[x]);
+                }
+            }
+            VarLocation::Memory => {
+                // `read_mem` reads downward from the address so
+                // the last element is read first
+                self.push_element_address(v, start + count - 1)?;
+                self.stack_read_mem(count);
+                self.stack_pop(1);
+            }
+            VarLocation::Stack | VarLocation::Reference => {
+                return log::error!(MemoryError: "attempting to access stack variable by index");
+            }
+        }
+        Ok(())
+    }
+
+    // push the absolute address of an element of a memory
+    // variable onto the stack
+    fn push_element_address(&mut self, v: &Var, offset: usize) -> Result<()> {
+        if let Some(memory_index) = v.memory_index {
+            self.push_address(memory_index + offset)
+        } else if let Some(stack_index) = v.stack_index {
+            self.stack_dup(stack_index)?;
+            if offset > 0 {
+                self.stack_push(offset);
+                self.asm.push("add".to_string());
+                self.stack.pop();
+            }
+            Ok(())
+        } else {
+            log::error!(MemoryError: "unexpected: variable has no memory or stack index")
+        }
+    }
+}
+
+// the field element with the value of an element of a static
+fn element<T: PolynomialRingElement>(v: &BigUint) -> T {
+    T::from(Polynomial {
+        coefficients: vec![T::F::from_biguint(v)],
+    })
+}
+
+// the value of a literal, literals larger than the field are
+// reduced
+fn literal<T: PolynomialRingElement>(v: &str) -> Result<BigUint> {
+    Ok(T::from_str(v)?.to_scalar()?.to_biguint())
 }
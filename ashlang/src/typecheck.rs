@@ -0,0 +1,598 @@
+//! A type check pass over the ast of each function, run before
+//! either target is compiled so every error is reported at once
+//! instead of only the first error reached during codegen.
+//!
+//! Types are inferred from literals, statics, and annotations.
+//! The types of function arguments and return values are only
+//! known when a function is compiled, values derived from them
+//! are checked by the target.
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::builtin;
+use crate::error::AshlangError;
+use crate::layout;
+use crate::log;
+use crate::parser::AstNode;
+use crate::parser::Expr;
+use crate::parser::IntType;
+use crate::parser::SourceLocation;
+use crate::parser::VarType;
+
+// what is known about a value
+#[derive(Debug, Clone, PartialEq)]
+struct Type {
+    // empty for a scalar
+    dimensions: Option<Vec<usize>>,
+    // whether the value is known at compile time
+    is_static: Option<bool>,
+}
+
+impl Type {
+    fn unknown() -> Self {
+        Self {
+            dimensions: None,
+            is_static: None,
+        }
+    }
+
+    fn scalar(is_static: bool) -> Self {
+        Self {
+            dimensions: Some(vec![]),
+            is_static: Some(is_static),
+        }
+    }
+
+    fn is_scalar(&self) -> Option<bool> {
+        self.dimensions
+            .as_ref()
+            .map(|v| VarType::Field(vec![]).matches(v))
+    }
+
+    // whether both values are known to have different shapes
+    fn mismatch(&self, other: &Type) -> bool {
+        match (&self.dimensions, &other.dimensions) {
+            (Some(l), Some(r)) => !VarType::Field(l.clone()).matches(r),
+            _ => false,
+        }
+    }
+
+    fn name(&self) -> String {
+        match &self.dimensions {
+            Some(dimensions) => VarType::Field(dimensions.clone()).name(),
+            None => "unknown".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Var {
+    t: Type,
+    // the annotated type, if any
+    declared: Option<VarType>,
+}
+
+/// A variable and the type inferred where it is defined.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct VarInfo {
+    pub name: String,
+    // the statement defining the variable
+    pub location: Option<SourceLocation>,
+    // e.g. `field[3]`, or `unknown` if the shape depends on
+    // the function arguments
+    pub type_name: String,
+    pub is_static: Option<bool>,
+}
+
+/// Check the ast of each function, returning an error that
+/// describes every type and scope error found.
+pub fn check(asts: &[&[AstNode]]) -> Result<()> {
+    let mut errors = vec![];
+    for ast in asts {
+        errors.append(&mut check_fn(ast).0);
+    }
+    if errors.len() <= 1 {
+        return match errors.pop() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        };
+    }
+    let details = errors
+        .iter()
+        .map(|e| match e.downcast_ref::<AshlangError>() {
+            Some(e) => match e.location() {
+                Some(l) => format!("{}:{}:{}: {}", l.file, l.line, l.col, e.message()),
+                None => e.message().to_string(),
+            },
+            None => e.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    log::error!(&format!("found {} errors", errors.len()), &details)
+}
+
+/// Check the ast of a single function, returning each error
+/// found and the variables defined in the function.
+pub fn check_fn(ast: &[AstNode]) -> (Vec<anyhow::Error>, Vec<VarInfo>) {
+    let mut checker = TypeChecker {
+        vars: HashMap::new(),
+        location: None,
+        errors: vec![],
+        defined: vec![],
+    };
+    checker.check_block(ast);
+    (checker.errors, checker.defined)
+}
+
+struct TypeChecker {
+    vars: HashMap<String, Var>,
+    location: Option<SourceLocation>,
+    errors: Vec<anyhow::Error>,
+    // every variable defined, in order
+    defined: Vec<VarInfo>,
+}
+
+impl TypeChecker {
+    // check each statement, collecting the errors
+    fn check_block(&mut self, ast: &[AstNode]) {
+        for node in ast {
+            if let AstNode::Location(location) = node {
+                self.location = Some(location.clone());
+                continue;
+            }
+            // nested blocks move the location
+            let location = self.location.clone();
+            let result = self.check_node(node);
+            self.location = location;
+            self.report(result);
+        }
+    }
+
+    // variables defined in a block are dropped at the end of the
+    // block, variables assigned in the block may change type
+    fn check_scoped(&mut self, ast: &[AstNode]) {
+        let outer = self.vars.clone();
+        self.check_block(ast);
+        let inner = std::mem::replace(&mut self.vars, outer);
+        for (name, v) in self.vars.iter_mut() {
+            if inner.get(name).map(|v| &v.t) != Some(&v.t) {
+                v.t = Type::unknown();
+            }
+        }
+    }
+
+    fn check_node(&mut self, node: &AstNode) -> Result<()> {
+        match node {
+            AstNode::FnVar(names) => {
+                for name in names {
+                    self.define(name, Type::unknown(), None)?;
+                }
+            }
+            // named dimensions are static scalars set by the caller
+            AstNode::FnDims(params) => {
+                for (_, dims) in params {
+                    for name in dims {
+                        if name.parse::<usize>().is_err() && !self.vars.contains_key(name) {
+                            self.define(name, Type::scalar(true), None)?;
+                        }
+                    }
+                }
+            }
+            AstNode::Stmt(name, true, expr) => {
+                let t = self.expr_type(expr);
+                let dimensions = t.as_ref().ok().and_then(|t| t.dimensions.clone());
+                self.define(
+                    name,
+                    Type {
+                        dimensions,
+                        is_static: Some(false),
+                    },
+                    None,
+                )?;
+                t?;
+            }
+            AstNode::Stmt(name, false, expr) => {
+                let v = self.var(name)?.clone();
+                let t = self.expr_type(expr)?;
+                if let Some(declared) = &v.declared {
+                    Self::check_declared(name, declared, &t)?;
+                }
+                self.vars.get_mut(name).unwrap().t = Type {
+                    dimensions: t.dimensions,
+                    is_static: Some(false),
+                };
+            }
+            AstNode::TypedDef(name, var_type, expr) => {
+                let t = self.expr_type(expr);
+                let dimensions = match var_type {
+                    VarType::Field(dimensions) => dimensions.clone(),
+                    VarType::Int(_) => vec![],
+                };
+                self.define(
+                    name,
+                    Type {
+                        dimensions: Some(dimensions),
+                        is_static: Some(false),
+                    },
+                    Some(var_type.clone()),
+                )?;
+                Self::check_declared(name, var_type, &t?)?;
+            }
+            AstNode::StaticDef(name, expr) => {
+                let t = self.expr_type(expr);
+                self.define(
+                    name,
+                    Type {
+                        dimensions: t.as_ref().ok().and_then(|t| t.dimensions.clone()),
+                        is_static: Some(true),
+                    },
+                    None,
+                )?;
+                if t?.is_static == Some(false) {
+                    return log::error!(
+                        TypeError: &format!("static variable \"{name}\" cannot be assigned from a signal"),
+                        "statics must be computed from literals, other statics, and ash functions"
+                    );
+                }
+            }
+            AstNode::ExprUnassigned(expr) | AstNode::Rtrn(expr) => {
+                self.expr_type(expr)?;
+            }
+            // errors in a condition are reported before errors
+            // in the block
+            AstNode::If(condition, if_block, else_block) => {
+                let condition = self.expr_type(condition).map(|_| ());
+                self.report(condition);
+                self.check_scoped(if_block);
+                self.check_scoped(else_block);
+            }
+            AstNode::Assert(condition, _) => {
+                let t = self.expr_type(condition)?;
+                if t.is_scalar() == Some(false) {
+                    return log::error!(TypeError: &format!(
+                        "assertion must be a scalar, received {}",
+                        t.name()
+                    ));
+                }
+            }
+            AstNode::Lookup(table, entries) => {
+                // the number of values, if every shape is known
+                let mut len = Some(0);
+                for expr in entries {
+                    let t = self.expr_type(expr)?;
+                    len = len
+                        .zip(t.dimensions)
+                        .map(|(len, v)| len + v.iter().product::<usize>());
+                }
+                if let Some(len) = len.filter(|v| *v != table.width()) {
+                    return log::error!(TypeError: &format!(
+                        "lookup in table {} expects {} values, received {len}",
+                        table.name,
+                        table.width()
+                    ));
+                }
+            }
+            AstNode::While(condition, body) => {
+                let condition = self.expr_type(condition).map(|_| ());
+                self.report(condition);
+                self.check_scoped(body);
+            }
+            AstNode::Loop(expr, body) => {
+                let condition = self.loop_condition(expr);
+                self.report(condition);
+                self.check_scoped(body);
+            }
+            AstNode::EmptyVecDef(name, dimensions) => {
+                self.define(
+                    name,
+                    Type {
+                        dimensions: Some(dimensions.clone()),
+                        is_static: Some(false),
+                    },
+                    None,
+                )?;
+            }
+            AstNode::AssignVec(name, indices, expr) => {
+                let v = self.var(name)?.clone();
+                let target = self.index(name, &v.t, indices)?;
+                let t = self.expr_type(expr)?;
+                if target.mismatch(&t) {
+                    return log::error!(TypeError: &format!(
+                        "type mismatch in assignment to \"{name}\": expected {} received {}",
+                        target.name(),
+                        t.name()
+                    ));
+                }
+            }
+            AstNode::PubInput(name) | AstNode::SecretInput(name) => {
+                self.define(name, Type::scalar(false), None)?;
+            }
+            AstNode::Ar1cs(args, returns, _) | AstNode::Asm(args, returns, _) => {
+                for name in args {
+                    if self.var(name)?.t.is_scalar() == Some(false) {
+                        return log::error!(
+                            TypeError: &format!("argument \"{name}\" to an inline block must be a scalar")
+                        );
+                    }
+                }
+                for name in returns {
+                    self.define(name, Type::scalar(false), None)?;
+                }
+            }
+            AstNode::Unconstrained(returns, body) => {
+                self.check_scoped(body);
+                for name in returns {
+                    self.define(name, Type::scalar(false), None)?;
+                }
+            }
+            AstNode::PubOutput(name) => {
+                if self.var(name)?.t.is_scalar() == Some(false) {
+                    return log::error!(TypeError: &format!("public output \"{name}\" must be a scalar"));
+                }
+            }
+            AstNode::Location(_) | AstNode::Inline(_) => {}
+        }
+        Ok(())
+    }
+
+    fn report(&mut self, result: Result<()>) {
+        if let Err(e) = result {
+            self.errors.push(log::locate(e, &self.location));
+        }
+    }
+
+    fn loop_condition(&self, expr: &Expr) -> Result<()> {
+        let t = self.expr_type(expr)?;
+        if t.is_scalar() == Some(false) {
+            return log::error!(TypeError: &format!(
+                "loop condition must be a scalar, received {}",
+                t.name()
+            ));
+        }
+        if t.is_static == Some(false) {
+            return log::error!(
+                TypeError: "loop condition must be static",
+                "the number of iterations must be known at compile time, use a while loop for a condition evaluated at runtime"
+            );
+        }
+        Ok(())
+    }
+
+    fn define(&mut self, name: &str, t: Type, declared: Option<VarType>) -> Result<()> {
+        if self.vars.contains_key(name) {
+            return log::error!(ScopeError: &format!("variable already defined: {name}"));
+        }
+        self.defined.push(VarInfo {
+            name: name.to_string(),
+            location: self.location.clone(),
+            type_name: match &declared {
+                Some(declared) => declared.name(),
+                None => t.name(),
+            },
+            is_static: t.is_static,
+        });
+        self.vars.insert(name.to_string(), Var { t, declared });
+        Ok(())
+    }
+
+    fn var(&self, name: &str) -> Result<&Var> {
+        match self.vars.get(name) {
+            Some(v) => Ok(v),
+            None => log::error!(ScopeError: &format!("variable does not exist in scope: {name}")),
+        }
+    }
+
+    fn int_type(&self, name: &str) -> Option<IntType> {
+        match self.vars.get(name).and_then(|v| v.declared.as_ref()) {
+            Some(VarType::Int(int_type)) => Some(*int_type),
+            _ => None,
+        }
+    }
+
+    fn check_declared(name: &str, declared: &VarType, t: &Type) -> Result<()> {
+        if let Some(dimensions) = &t.dimensions {
+            if !declared.matches(dimensions) {
+                return log::error!(TypeError: &format!(
+                    "variable \"{name}\" is declared as {} but assigned {}",
+                    declared.name(),
+                    t.name()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // the type of a variable after applying indices
+    fn index(&self, name: &str, t: &Type, indices: &[Expr]) -> Result<Type> {
+        let mut is_static = t.is_static;
+        for i in indices {
+            let index = self.expr_type(i)?;
+            if index.is_scalar() == Some(false) {
+                return log::error!(TypeError: &format!(
+                    "index notation must contain a scalar expression in: {name}"
+                ));
+            }
+            is_static = both_static(is_static, index.is_static);
+        }
+        let dimensions = match &t.dimensions {
+            Some(dimensions) => dimensions,
+            None => {
+                return Ok(Type {
+                    dimensions: None,
+                    is_static,
+                })
+            }
+        };
+        if indices.len() > dimensions.len() {
+            return log::error!(TypeError: &format!("too many indices for variable: {name}"));
+        }
+        for (i, len) in indices.iter().zip(dimensions) {
+            if let Expr::Lit(v) = i {
+                if v.parse::<usize>().unwrap_or(usize::MAX) >= *len {
+                    return log::error!(TypeError: &format!(
+                        "index {v} is out of bounds for a dimension of length {len} in: {name}"
+                    ));
+                }
+            }
+        }
+        Ok(Type {
+            dimensions: Some(dimensions[indices.len()..].to_vec()),
+            is_static,
+        })
+    }
+
+    fn expr_type(&self, expr: &Expr) -> Result<Type> {
+        match expr {
+            Expr::Lit(_) => Ok(Type::scalar(true)),
+            Expr::VecLit(values) => Ok(Type {
+                dimensions: Some(vec![values.len()]),
+                is_static: Some(true),
+            }),
+            Expr::VecVec(rows) => {
+                let mut inner: Option<Type> = None;
+                for row in rows {
+                    let t = self.expr_type(row)?;
+                    if inner.as_ref().is_some_and(|v| *v != t) {
+                        return log::error!(
+                            TypeError: "vector elements must have the same dimensions"
+                        );
+                    }
+                    inner = Some(t);
+                }
+                let dimensions = inner
+                    .and_then(|v| v.dimensions)
+                    .map(|v| [vec![rows.len()], v].concat());
+                Ok(Type {
+                    dimensions,
+                    is_static: Some(true),
+                })
+            }
+            Expr::Val(name, indices) | Expr::Ref(name, indices) => {
+                let t = self.var(name)?.t.clone();
+                self.index(name, &t, indices)
+            }
+            Expr::FnCall(name, args)
+                if (name == builtin::DOT || name == builtin::MATMUL) && args.len() == 2 =>
+            {
+                let l = self.expr_type(&args[0])?;
+                let r = self.expr_type(&args[1])?;
+                let dimensions = match (&l.dimensions, &r.dimensions) {
+                    (Some(l), Some(r)) if name == builtin::DOT => {
+                        layout::dot_terms(l, r)?;
+                        Some(vec![])
+                    }
+                    (Some(l), Some(r)) => Some(layout::matmul_dimensions(l, r)?),
+                    _ => None,
+                };
+                // only the r1cs target evaluates static products
+                // at compile time
+                Ok(Type {
+                    dimensions,
+                    is_static: both_static(l.is_static, r.is_static).filter(|v| !v),
+                })
+            }
+            Expr::FnCall(name, args) if name == builtin::NEG && args.len() == 1 => {
+                expr.int_type(&|name| self.int_type(name))?;
+                self.expr_type(&args[0])
+            }
+            Expr::FnCall(name, args) if name == builtin::SLICE && args.len() == 3 => {
+                let t = self.expr_type(&args[0])?;
+                self.expr_type(&args[1])?;
+                self.expr_type(&args[2])?;
+                let var_name = match &args[0] {
+                    Expr::Val(name, _) => name.as_str(),
+                    _ => "value",
+                };
+                // the length is known when the bounds are literals
+                let dimensions = match (&t.dimensions, &args[1], &args[2]) {
+                    (Some(dimensions), Expr::Lit(start), Expr::Lit(end)) => {
+                        Some(layout::slice(var_name, dimensions, start.parse()?, end.parse()?)?.1)
+                    }
+                    _ => None,
+                };
+                Ok(Type {
+                    dimensions,
+                    is_static: t.is_static,
+                })
+            }
+            Expr::FnCall(name, args)
+                if builtin::REDUCTIONS.contains(&name.as_str()) && args.len() == 1 =>
+            {
+                let t = self.expr_type(&args[0])?;
+                if t.dimensions.as_ref().is_some_and(|v| v.is_empty()) {
+                    return log::error!(TypeError: &format!(
+                        "{name} expects a vector or matrix, received a scalar"
+                    ));
+                }
+                Ok(Type {
+                    dimensions: Some(vec![]),
+                    is_static: t.is_static.filter(|v| !v),
+                })
+            }
+            Expr::FnCall(_, args) => {
+                for arg in args {
+                    self.expr_type(arg)?;
+                }
+                Ok(Type::unknown())
+            }
+            Expr::NumOp { lhs, rhs, .. } => {
+                expr.int_type(&|name| self.int_type(name))?;
+                let l = self.expr_type(lhs)?;
+                let r = self.expr_type(rhs)?;
+                if l.mismatch(&r) {
+                    return log::error!(TypeError: &format!(
+                        "type mismatch in numeric operation: {} and {}",
+                        l.name(),
+                        r.name()
+                    ));
+                }
+                Ok(Type {
+                    dimensions: l.dimensions.or(r.dimensions),
+                    is_static: both_static(l.is_static, r.is_static),
+                })
+            }
+            Expr::BoolOp { lhs, rhs, .. } => {
+                let l = self.expr_type(lhs)?;
+                let r = self.expr_type(rhs)?;
+                if l.is_scalar() == Some(false) || r.is_scalar() == Some(false) {
+                    return log::error!(TypeError: &format!(
+                        "boolean operations may only be applied to scalars, received {} and {}",
+                        l.name(),
+                        r.name()
+                    ));
+                }
+                Ok(Type {
+                    dimensions: Some(vec![]),
+                    is_static: both_static(l.is_static, r.is_static),
+                })
+            }
+            Expr::BitOp { lhs, rhs, .. } => {
+                if expr.int_type(&|name| self.int_type(name))?.is_none() {
+                    return log::error!(
+                        TypeError: "bitwise operators require integer operands",
+                        "declare an operand with an integer type e.g. `let x: u32 = 1`"
+                    );
+                }
+                let l = self.expr_type(lhs)?;
+                let r = self.expr_type(rhs)?;
+                if l.is_scalar() == Some(false) || r.is_scalar() == Some(false) {
+                    return log::error!(TypeError: "bitwise operands must be scalars");
+                }
+                Ok(Type {
+                    dimensions: Some(vec![]),
+                    is_static: both_static(l.is_static, r.is_static),
+                })
+            }
+        }
+    }
+}
+
+fn both_static(l: Option<bool>, r: Option<bool>) -> Option<bool> {
+    match (l, r) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    }
+}
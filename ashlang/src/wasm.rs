@@ -0,0 +1,89 @@
+//! Bindings for compiling programs and building witnesses in a
+//! browser. Functions are read from in-memory sources, file IO
+//! and threads are not available in `wasm32-unknown-unknown`.
+use std::collections::HashMap;
+
+use anyhow::Result;
+use ring_math::PolynomialRingElement;
+use wasm_bindgen::prelude::*;
+
+use crate::cli::Config;
+use crate::compiler::Compiler;
+use crate::log;
+use crate::r1cs::witness;
+use crate::rings::Bn128PolynomialRing;
+use crate::rings::Curve25519PolynomialRing;
+use crate::rings::DilithiumPolynomialRingElement;
+use crate::rings::OxfoiPolynomialRing;
+
+/// Compile an entry function for a target and field. `paths` and
+/// `sources` are the path and content of each file, e.g.
+/// `["main.ash", "stdlib/hash.ash"]`. Returns the compiled ar1cs
+/// or tasm.
+#[wasm_bindgen]
+pub fn compile(
+    entry_fn: &str,
+    target: &str,
+    field: &str,
+    paths: Vec<String>,
+    sources: Vec<String>,
+) -> Result<String, JsError> {
+    let out = in_memory_sources(paths, sources).and_then(|sources| {
+        let config = Config::new(entry_fn, target, field);
+        match (target, field) {
+            ("tasm", "foi" | "oxfoi") | ("r1cs", "foi" | "oxfoi") => {
+                compile_in::<OxfoiPolynomialRing>(&config, sources)
+            }
+            ("r1cs", "curve25519") => compile_in::<Curve25519PolynomialRing>(&config, sources),
+            ("r1cs", "alt_bn128") => compile_in::<Bn128PolynomialRing>(&config, sources),
+            ("r1cs", "dilithium") => compile_in::<DilithiumPolynomialRingElement>(&config, sources),
+            _ => log::error!(ConfigError: &format!(
+                "Unsupported field for target {target}: {field}"
+            )),
+        }
+    });
+    out.map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Build and verify the witness of compiled ar1cs. Inputs and the
+/// returned values are decimal strings, one value per signal.
+#[wasm_bindgen]
+pub fn build_witness(r1cs: &str, field: &str, inputs: Vec<String>) -> Result<Vec<String>, JsError> {
+    let out = match field {
+        "foi" | "oxfoi" => build_in::<OxfoiPolynomialRing>(r1cs, inputs),
+        "curve25519" => build_in::<Curve25519PolynomialRing>(r1cs, inputs),
+        "alt_bn128" => build_in::<Bn128PolynomialRing>(r1cs, inputs),
+        "dilithium" => build_in::<DilithiumPolynomialRingElement>(r1cs, inputs),
+        _ => log::error!(ConfigError: &format!("Unsupported field for target r1cs: {field}")),
+    };
+    out.map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn in_memory_sources(paths: Vec<String>, sources: Vec<String>) -> Result<HashMap<String, String>> {
+    if paths.len() != sources.len() {
+        return log::error!(
+            ConfigError: &format!("received {} paths and {} sources", paths.len(), sources.len()),
+            "pass one path for each source"
+        );
+    }
+    Ok(paths.into_iter().zip(sources).collect())
+}
+
+fn compile_in<T: PolynomialRingElement>(
+    config: &Config,
+    sources: HashMap<String, String>,
+) -> Result<String> {
+    let mut compiler: Compiler<T> = Compiler::from_sources(config, sources)?;
+    compiler.compile(&config.entry_fn)
+}
+
+fn build_in<T: PolynomialRingElement>(r1cs: &str, inputs: Vec<String>) -> Result<Vec<String>> {
+    let inputs = inputs
+        .iter()
+        .map(|v| T::from_str(v))
+        .collect::<Result<Vec<_>>>()?;
+    let witness = witness::build::<T>(r1cs, inputs)?;
+    let values = witness.variables.iter().map(|v| v.to_string()).collect();
+    witness::verify::<T>(r1cs, witness)?;
+    Ok(values)
+}
@@ -0,0 +1,46 @@
+//! Compare schoolbook and NTT polynomial multiplication.
+//!
+//! Run using `cargo bench --bench polynomial_mul`.
+use std::time::Duration;
+use std::time::Instant;
+
+use ring_math::Polynomial;
+use scalarff::alt_bn128::Bn128FieldElement;
+use scalarff::FieldElement;
+use scalarff::OxfoiFieldElement;
+
+const RUNS: u32 = 10;
+
+fn sample<T: FieldElement>(len: usize) -> Polynomial<T> {
+    let mut r = rand::thread_rng();
+    Polynomial::new((0..len).map(|_| T::sample_uniform(&mut r)).collect())
+}
+
+// the average duration of a multiplication
+fn time<T: FieldElement>(
+    a: &Polynomial<T>,
+    b: &Polynomial<T>,
+    f: fn(&Polynomial<T>, &Polynomial<T>) -> Polynomial<T>,
+) -> Duration {
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        std::hint::black_box(f(a, b));
+    }
+    start.elapsed() / RUNS
+}
+
+fn bench<T: FieldElement>() {
+    println!("{}", T::name_str());
+    for len in [16, 64, 256, 1024, 4096] {
+        let a = sample::<T>(len);
+        let b = sample::<T>(len);
+        let schoolbook = time(&a, &b, |a, b| a.clone() * b.clone());
+        let ntt = time(&a, &b, |a, b| a.mul_ntt(b));
+        println!("  {len:>5} coefficients: schoolbook {schoolbook:>12?}, ntt {ntt:>12?}");
+    }
+}
+
+fn main() {
+    bench::<OxfoiFieldElement>();
+    bench::<Bn128FieldElement>();
+}
@@ -1,7 +1,13 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use scalarff::FieldElement;
 
 use super::vector::Vector;
 
+// the number of rows and inner terms multiplied at a time, so
+// the rows being read stay in cache
+const BLOCK_SIZE: usize = 64;
+
 /// A two dimensional matrix implementation
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, PartialEq)]
@@ -128,6 +134,107 @@ impl<T: FieldElement> Matrix2D<T> {
         }
     }
 
+    /// Return the transpose of the matrix.
+    pub fn transpose(&self) -> Self {
+        let (rows, cols) = self.dimensions;
+        let mut values = Vec::with_capacity(rows * cols);
+        for j in 0..cols {
+            for i in 0..rows {
+                values.push(self.values[i * cols + j].clone());
+            }
+        }
+        Self {
+            dimensions: (cols, rows),
+            values,
+        }
+    }
+
+    /// Multiply by another matrix. The product is calculated in
+    /// blocks of rows and inner terms so each row of `other` is
+    /// reused while it is in cache.
+    pub fn mul_matrix(&self, other: &Self) -> Self {
+        let (rows, cols) = self.check_mul_dimensions(other);
+        let mut values = vec![T::zero(); rows * cols];
+        if cols > 0 {
+            for (block, out) in values.chunks_mut(BLOCK_SIZE * cols).enumerate() {
+                self.mul_block(other, block * BLOCK_SIZE, out);
+            }
+        }
+        Self {
+            dimensions: (rows, cols),
+            values,
+        }
+    }
+
+    /// Multiply by a column vector.
+    pub fn mul_vec(&self, v: &Vector<T>) -> Vector<T> {
+        let (rows, cols) = self.dimensions;
+        assert_eq!(
+            cols,
+            v.len(),
+            "matrix vector multiplication dimensions mismatch"
+        );
+        Vector::from_vec(
+            (0..rows)
+                .map(|i| dot(&self.values[i * cols..(i + 1) * cols], v.to_vec_ref()))
+                .collect(),
+        )
+    }
+
+    /// Multiply the transpose of the matrix by a column vector
+    /// without building the transpose. Rows are read in order,
+    /// so this is faster than multiplying by `transpose()`.
+    pub fn mul_vec_transposed(&self, v: &Vector<T>) -> Vector<T> {
+        let (rows, cols) = self.dimensions;
+        assert_eq!(
+            rows,
+            v.len(),
+            "matrix vector multiplication dimensions mismatch"
+        );
+        let mut out = vec![T::zero(); cols];
+        for (i, scale) in v.iter().enumerate() {
+            if *scale == T::zero() {
+                continue;
+            }
+            for (o, a) in out.iter_mut().zip(&self.values[i * cols..(i + 1) * cols]) {
+                *o += a.clone() * scale.clone();
+            }
+        }
+        Vector::from_vec(out)
+    }
+
+    // the dimensions of the product of self and other
+    fn check_mul_dimensions(&self, other: &Self) -> (usize, usize) {
+        assert_eq!(
+            self.dimensions.1, other.dimensions.0,
+            "matrix multiplication dimensions mismatch"
+        );
+        (self.dimensions.0, other.dimensions.1)
+    }
+
+    // write the rows of the product starting at `row` into `out`
+    fn mul_block(&self, other: &Self, row: usize, out: &mut [T]) {
+        let inner = self.dimensions.1;
+        let cols = other.dimensions.1;
+        let rows = out.len() / cols;
+        for k0 in (0..inner).step_by(BLOCK_SIZE) {
+            let k1 = (k0 + BLOCK_SIZE).min(inner);
+            for i in 0..rows {
+                let a = &self.values[(row + i) * inner..(row + i + 1) * inner];
+                let out_row = &mut out[i * cols..(i + 1) * cols];
+                for (k, a) in a.iter().enumerate().take(k1).skip(k0) {
+                    if *a == T::zero() {
+                        continue;
+                    }
+                    let b = &other.values[k * cols..(k + 1) * cols];
+                    for (o, b) in out_row.iter_mut().zip(b) {
+                        *o += a.clone() * b.clone();
+                    }
+                }
+            }
+        }
+    }
+
     /// Sample a uniform random matrix of the specified dimensions
     /// from the underlying field.
     #[cfg(feature = "rand")]
@@ -166,6 +273,49 @@ impl<T: FieldElement> Matrix2D<T> {
     }
 }
 
+#[cfg(feature = "parallel")]
+impl<T: FieldElement + Send + Sync> Matrix2D<T> {
+    /// Multiply by another matrix, calculating blocks of rows
+    /// in parallel. See `mul_matrix`.
+    pub fn par_mul_matrix(&self, other: &Self) -> Self {
+        let (rows, cols) = self.check_mul_dimensions(other);
+        let mut values = vec![T::zero(); rows * cols];
+        if cols > 0 {
+            values
+                .par_chunks_mut(BLOCK_SIZE * cols)
+                .enumerate()
+                .for_each(|(block, out)| self.mul_block(other, block * BLOCK_SIZE, out));
+        }
+        Self {
+            dimensions: (rows, cols),
+            values,
+        }
+    }
+
+    /// Multiply by a column vector, calculating rows in parallel.
+    pub fn par_mul_vec(&self, v: &Vector<T>) -> Vector<T> {
+        let (rows, cols) = self.dimensions;
+        assert_eq!(
+            cols,
+            v.len(),
+            "matrix vector multiplication dimensions mismatch"
+        );
+        Vector::from_vec(
+            (0..rows)
+                .into_par_iter()
+                .map(|i| dot(&self.values[i * cols..(i + 1) * cols], v.to_vec_ref()))
+                .collect(),
+        )
+    }
+}
+
+// the sum of the element-wise product of two slices
+fn dot<T: FieldElement>(a: &[T], b: &[T]) -> T {
+    a.iter()
+        .zip(b)
+        .fold(T::zero(), |acc, (a, b)| acc + a.clone() * b.clone())
+}
+
 impl<T: FieldElement> std::fmt::Display for Matrix2D<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let (rows, cols) = self.dimensions;
@@ -223,18 +373,15 @@ impl<T: FieldElement> std::ops::Mul<Vector<T>> for Matrix2D<T> {
     type Output = Vector<T>;
 
     fn mul(self, other: Vector<T>) -> Vector<T> {
-        let mut out = Vec::new();
-        let (m_rows, m_cols) = self.dimensions;
-        for i in 0..m_rows {
-            let row = self.values[i * m_cols..(i + 1) * m_cols].to_vec();
+        self.mul_vec(&other)
+    }
+}
 
-            out.push(
-                (other.clone() * Vector::from_vec(row))
-                    .iter()
-                    .fold(T::zero(), |acc, v| acc + v.clone()),
-            );
-        }
-        Vector::from_vec(out)
+impl<T: FieldElement> std::ops::Mul<Matrix2D<T>> for Matrix2D<T> {
+    type Output = Matrix2D<T>;
+
+    fn mul(self, other: Matrix2D<T>) -> Matrix2D<T> {
+        self.mul_matrix(&other)
     }
 }
 
@@ -266,4 +413,49 @@ mod test {
             assert!(out.norm_l2() < root_128_approx * input.norm_l2());
         }
     }
+
+    // the product calculated using the definition
+    fn naive_mul(
+        a: &Matrix2D<OxfoiFieldElement>,
+        b: &Matrix2D<OxfoiFieldElement>,
+    ) -> Matrix2D<OxfoiFieldElement> {
+        let mut out = Matrix2D::zero(a.dimensions.0, b.dimensions.1);
+        for i in 0..a.dimensions.0 {
+            for j in 0..b.dimensions.1 {
+                out.values[i * b.dimensions.1 + j] = a.row(i).dot_product(b.column(j));
+            }
+        }
+        out
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn test_blocked_mul() {
+        let mut rng = rand::thread_rng();
+        // dimensions that are not a multiple of the block size
+        let a = Matrix2D::<OxfoiFieldElement>::sample_uniform(70, 130, &mut rng);
+        let b = Matrix2D::<OxfoiFieldElement>::sample_uniform(130, 3, &mut rng);
+        assert!(a.mul_matrix(&b) == naive_mul(&a, &b));
+        #[cfg(feature = "parallel")]
+        assert!(a.par_mul_matrix(&b) == naive_mul(&a, &b));
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn test_mul_vec() {
+        let mut rng = rand::thread_rng();
+        let m = Matrix2D::<OxfoiFieldElement>::sample_uniform(20, 30, &mut rng);
+        let v = super::Vector::sample_uniform(30, &mut rng);
+        let u = super::Vector::sample_uniform(20, &mut rng);
+        let expected = (0..20)
+            .map(|i| m.row(i).dot_product(v.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(m.mul_vec(&v).to_vec(), expected);
+        assert_eq!(
+            m.mul_vec_transposed(&u).to_vec(),
+            m.transpose().mul_vec(&u).to_vec()
+        );
+        #[cfg(feature = "parallel")]
+        assert_eq!(m.par_mul_vec(&v).to_vec(), expected);
+    }
 }
@@ -0,0 +1,245 @@
+use scalarff::BigUint;
+use scalarff::FieldElement;
+
+use super::Polynomial;
+
+/// A multiplicative subgroup of a field with a power of 2
+/// number of elements. Polynomials with fewer coefficients than
+/// the domain size are converted between coefficient and
+/// evaluation form using the number theoretic transform.
+///
+/// Evaluations are ordered by powers of the root of unity,
+/// e.g. `[p(1), p(w), p(w^2), ...]`. The coset transforms
+/// evaluate over `[p(g), p(g*w), p(g*w^2), ...]` where `g` is
+/// `offset`, e.g. to evaluate a quotient where the vanishing
+/// polynomial of the domain is zero.
+#[derive(Clone, Debug)]
+pub struct EvaluationDomain<T: FieldElement> {
+    pub size: usize,
+    /// A primitive `size`-th root of unity
+    pub root: T,
+    /// An element not in the domain, used to shift the domain
+    /// to a coset
+    pub offset: T,
+    root_inv: T,
+    offset_inv: T,
+    size_inv: T,
+}
+
+impl<T: FieldElement> EvaluationDomain<T> {
+    /// Create a domain with at least `min_size` elements.
+    ///
+    /// Returns `None` if the field does not have a root of unity
+    /// of the required order, e.g. the curve25519 field only has
+    /// a 4th root of unity.
+    pub fn new(min_size: usize) -> Option<Self> {
+        let size = min_size.max(1).next_power_of_two();
+        let order = T::prime() - 1_u32;
+        if order.clone() % size != BigUint::from(0_u32) {
+            return None;
+        }
+        // a quadratic non-residue raised to order/size generates
+        // the subgroup of size elements
+        let candidates = (2..1024).map(T::from_usize);
+        let half = order.clone() / 2_u32;
+        let non_residue = candidates.clone().find(|v| pow(v, &half) != T::one())?;
+        let root = pow(&non_residue, &(order / size));
+        let offset = candidates
+            .clone()
+            .find(|v| pow(v, &BigUint::from(size)) != T::one())?;
+        Some(Self {
+            size,
+            root_inv: T::one() / root.clone(),
+            offset_inv: T::one() / offset.clone(),
+            size_inv: T::one() / T::from_usize(size),
+            root,
+            offset,
+        })
+    }
+
+    /// The elements of the domain, `[1, w, w^2, ...]`.
+    pub fn elements(&self) -> Vec<T> {
+        let mut out = Vec::with_capacity(self.size);
+        let mut v = T::one();
+        for _ in 0..self.size {
+            out.push(v.clone());
+            v *= self.root.clone();
+        }
+        out
+    }
+
+    /// Evaluate a polynomial at each element of the domain.
+    pub fn ntt(&self, coefficients: &[T]) -> Vec<T> {
+        let mut values = self.pad(coefficients);
+        transform(&mut values, &self.root);
+        values
+    }
+
+    /// Interpolate the coefficients of the polynomial with the
+    /// given evaluations over the domain.
+    pub fn intt(&self, evaluations: &[T]) -> Vec<T> {
+        let mut values = self.pad(evaluations);
+        transform(&mut values, &self.root_inv);
+        for v in values.iter_mut() {
+            *v *= self.size_inv.clone();
+        }
+        values
+    }
+
+    /// Evaluate a polynomial at each element of the coset of
+    /// the domain.
+    pub fn coset_ntt(&self, coefficients: &[T]) -> Vec<T> {
+        let mut values = self.pad(coefficients);
+        scale_powers(&mut values, &self.offset);
+        transform(&mut values, &self.root);
+        values
+    }
+
+    /// Interpolate the coefficients of the polynomial with the
+    /// given evaluations over the coset of the domain.
+    pub fn coset_intt(&self, evaluations: &[T]) -> Vec<T> {
+        let mut values = self.intt(evaluations);
+        scale_powers(&mut values, &self.offset_inv);
+        values
+    }
+
+    /// Evaluate a polynomial at each element of the domain.
+    pub fn evaluate(&self, p: &Polynomial<T>) -> Vec<T> {
+        self.ntt(&p.coefficients)
+    }
+
+    /// The polynomial with the given evaluations over the domain.
+    pub fn interpolate(&self, evaluations: &[T]) -> Polynomial<T> {
+        Polynomial::new(self.intt(evaluations))
+    }
+
+    // copy values into a vector of the domain size
+    fn pad(&self, values: &[T]) -> Vec<T> {
+        if values.len() > self.size {
+            panic!(
+                "{} values do not fit in a domain of size {}",
+                values.len(),
+                self.size
+            );
+        }
+        let mut out = values.to_vec();
+        out.resize(self.size, T::zero());
+        out
+    }
+}
+
+/// Raise a field element to a power using square and multiply.
+pub fn pow<T: FieldElement>(v: &T, e: &BigUint) -> T {
+    let mut out = T::one();
+    for i in (0..e.bits()).rev() {
+        out = out.clone() * out;
+        if e.bit(i) {
+            out *= v.clone();
+        }
+    }
+    out
+}
+
+// multiply the value at index i by v^i
+fn scale_powers<T: FieldElement>(values: &mut [T], v: &T) {
+    let mut scale = T::one();
+    for value in values.iter_mut() {
+        *value *= scale.clone();
+        scale *= v.clone();
+    }
+}
+
+// in place iterative radix 2 transform, the length of
+// values must be the order of root
+fn transform<T: FieldElement>(values: &mut [T], root: &T) {
+    let n = values.len();
+    if n <= 1 {
+        return;
+    }
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let w = pow(root, &BigUint::from(n / len));
+        for start in (0..n).step_by(len) {
+            let mut wk = T::one();
+            for k in 0..len / 2 {
+                let u = values[start + k].clone();
+                let v = values[start + k + len / 2].clone() * wk.clone();
+                values[start + k] = u.clone() + v.clone();
+                values[start + k + len / 2] = u - v;
+                wk *= w.clone();
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EvaluationDomain;
+    use crate::Polynomial;
+    use scalarff::alt_bn128::Bn128FieldElement;
+    use scalarff::Curve25519FieldElement;
+    use scalarff::FieldElement;
+    use scalarff::OxfoiFieldElement;
+
+    #[cfg(feature = "rand")]
+    fn sample<T: FieldElement>(len: usize) -> Polynomial<T> {
+        let mut r = rand::thread_rng();
+        Polynomial::new((0..len).map(|_| T::sample_uniform(&mut r)).collect())
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn ntt_roundtrip() {
+        let domain = EvaluationDomain::<OxfoiFieldElement>::new(64).unwrap();
+        let p = sample::<OxfoiFieldElement>(50);
+        let evaluations = domain.evaluate(&p);
+        for (x, v) in domain.elements().iter().zip(&evaluations) {
+            assert_eq!(p.evaluate(x), *v);
+        }
+        assert_eq!(domain.interpolate(&evaluations), p);
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn coset_roundtrip() {
+        let domain = EvaluationDomain::<Bn128FieldElement>::new(16).unwrap();
+        let p = sample::<Bn128FieldElement>(16);
+        let evaluations = domain.coset_ntt(&p.coefficients);
+        for (x, v) in domain.elements().iter().zip(&evaluations) {
+            assert_eq!(p.evaluate(&(x.clone() * domain.offset.clone())), *v);
+        }
+        assert_eq!(domain.coset_intt(&evaluations), p.coefficients);
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn mul_ntt() {
+        for len in [1, 2, 7, 33] {
+            let a = sample::<OxfoiFieldElement>(len);
+            let b = sample::<OxfoiFieldElement>(len + 3);
+            assert_eq!(a.mul_ntt(&b), a.clone() * b.clone());
+            let a = sample::<Bn128FieldElement>(len);
+            let b = sample::<Bn128FieldElement>(len + 3);
+            assert_eq!(a.mul_ntt(&b), a.clone() * b.clone());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn mul_ntt_fallback() {
+        // the curve25519 field only has a 4th root of unity
+        assert!(EvaluationDomain::<Curve25519FieldElement>::new(4).is_some());
+        assert!(EvaluationDomain::<Curve25519FieldElement>::new(5).is_none());
+        let a = sample::<Curve25519FieldElement>(8);
+        let b = sample::<Curve25519FieldElement>(8);
+        assert_eq!(a.mul_ntt(&b), a.clone() * b.clone());
+    }
+}
@@ -2,6 +2,7 @@ use std::fmt::Debug;
 
 use scalarff::FieldElement;
 
+use crate::EvaluationDomain;
 use crate::Vector;
 
 /// A univariate polynomial with coefficients in a field
@@ -111,6 +112,38 @@ impl<T: FieldElement> Polynomial<T> {
         0
     }
 
+    /// Evaluate the polynomial at a point
+    pub fn evaluate(&self, x: &T) -> T {
+        let mut out = T::zero();
+        for v in self.coefficients.iter().rev() {
+            out = out * x.clone() + v.clone();
+        }
+        out
+    }
+
+    /// Multiply using the number theoretic transform. Falls back
+    /// to schoolbook multiplication if the field does not have a
+    /// large enough root of unity.
+    pub fn mul_ntt(&self, other: &Self) -> Self {
+        if self.coefficients.is_empty() || other.coefficients.is_empty() {
+            return Self::zero();
+        }
+        let len = self.coefficients.len() + other.coefficients.len() - 1;
+        let domain = match EvaluationDomain::<T>::new(len) {
+            Some(domain) => domain,
+            None => return self.clone() * other.clone(),
+        };
+        let a = domain.ntt(&self.coefficients);
+        let b = domain.ntt(&other.coefficients);
+        let product = a.into_iter().zip(b).map(|(a, b)| a * b).collect::<Vec<_>>();
+        let mut coefficients = domain.intt(&product);
+        // trim trailing zeros to match schoolbook multiplication
+        while coefficients.last().is_some_and(|v| *v == T::zero()) {
+            coefficients.pop();
+        }
+        Self { coefficients }
+    }
+
     /// a fast method for multiplying by a single term polynomial
     /// with a coefficient of 1
     /// e.g. multiplying by x^5
@@ -0,0 +1,233 @@
+use scalarff::FieldElement;
+
+use super::matrix2d::Matrix2D;
+use super::sparse_vector::SparseVector;
+use super::vector::Vector;
+
+/// A two dimensional matrix in compressed sparse row (CSR) form.
+///
+/// Only non-zero entries are stored. The entries of row `i` are
+/// `columns[row_offsets[i]..row_offsets[i + 1]]` and the matching
+/// `values`, sorted by column.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, PartialEq)]
+pub struct SparseMatrix<T: FieldElement> {
+    pub dimensions: (usize, usize), // (rows, cols)
+    pub row_offsets: Vec<usize>,
+    pub columns: Vec<usize>,
+    pub values: Vec<T>,
+}
+
+impl<T: FieldElement> SparseMatrix<T> {
+    /// Create a matrix with no rows and the specified
+    /// number of columns.
+    pub fn new(columns: usize) -> Self {
+        Self {
+            dimensions: (0, columns),
+            row_offsets: vec![0],
+            columns: vec![],
+            values: vec![],
+        }
+    }
+
+    /// Build a matrix from (row, column, value) entries. Entries
+    /// at the same position are summed and zeros are dropped.
+    pub fn from_triplets(rows: usize, columns: usize, mut entries: Vec<(usize, usize, T)>) -> Self {
+        entries.sort_by_key(|(row, col, _)| (*row, *col));
+        let mut out = Self::new(columns);
+        let mut entries = entries.into_iter().peekable();
+        for row in 0..rows {
+            let mut terms = vec![];
+            while let Some((_, col, v)) = entries.next_if(|(r, _, _)| *r == row) {
+                terms.push((col, v));
+            }
+            out.push_row(terms);
+        }
+        if let Some((row, _, _)) = entries.next() {
+            panic!("attempt to build a sparse matrix with an entry outside of the matrix dimensions. Entry row {row}, number of rows {rows}");
+        }
+        out
+    }
+
+    /// Append a row of (column, value) terms. Terms may be in any
+    /// order, terms in the same column are summed.
+    pub fn push_row(&mut self, mut terms: Vec<(usize, T)>) {
+        terms.sort_by_key(|(col, _)| *col);
+        for (col, v) in terms {
+            if col >= self.dimensions.1 {
+                panic!("attempt to push a row with a column outside of matrix dimensions. Column {col}, number of columns {}", self.dimensions.1);
+            }
+            let row_start = self.row_offsets[self.dimensions.0];
+            if self.columns.len() > row_start && self.columns.last() == Some(&col) {
+                let last = self.values.last_mut().unwrap();
+                *last += v;
+                if *last == T::zero() {
+                    self.columns.pop();
+                    self.values.pop();
+                }
+            } else if v != T::zero() {
+                self.columns.push(col);
+                self.values.push(v);
+            }
+        }
+        self.dimensions.0 += 1;
+        self.row_offsets.push(self.columns.len());
+    }
+
+    /// The number of non-zero entries.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Retrieve the (column, value) entries of a row by index. Panics
+    /// if the index is greater than or equal to the number of rows.
+    pub fn row(&self, index: usize) -> impl Iterator<Item = (usize, &T)> {
+        let rows = self.dimensions.0;
+        if index >= rows {
+            panic!("attempt to retrieve a row outside of matrix dimensions. Requested row {index}, number of rows {rows}");
+        }
+        let range = self.row_offsets[index]..self.row_offsets[index + 1];
+        self.columns[range.clone()]
+            .iter()
+            .copied()
+            .zip(self.values[range].iter())
+    }
+
+    /// Each non-zero entry as (row, column, value), in row order.
+    pub fn triplets(&self) -> impl Iterator<Item = (usize, usize, &T)> {
+        (0..self.dimensions.0).flat_map(move |row| self.row(row).map(move |(col, v)| (row, col, v)))
+    }
+
+    /// Retrieve a row by index as a sparse vector. Panics if the
+    /// index is greater than or equal to the number of rows.
+    pub fn row_vector(&self, index: usize) -> SparseVector<T> {
+        SparseVector {
+            len: self.dimensions.1,
+            entries: self.row(index).map(|(i, v)| (i, v.clone())).collect(),
+        }
+    }
+
+    /// Append a sparse vector as a row.
+    pub fn push_sparse_row(&mut self, row: SparseVector<T>) {
+        assert_eq!(
+            row.len, self.dimensions.1,
+            "sparse matrix row length mismatch"
+        );
+        self.push_row(row.entries);
+    }
+
+    /// Return a dense copy of the matrix.
+    pub fn to_dense(&self) -> Matrix2D<T> {
+        let (rows, cols) = self.dimensions;
+        let mut out = Matrix2D::zero(rows, cols);
+        for (row, col, v) in self.triplets() {
+            out.values[row * cols + col] = v.clone();
+        }
+        out
+    }
+}
+
+impl<T: FieldElement> From<&Matrix2D<T>> for SparseMatrix<T> {
+    fn from(matrix: &Matrix2D<T>) -> Self {
+        let (rows, cols) = matrix.dimensions;
+        let mut out = Self::new(cols);
+        for i in 0..rows {
+            out.push_row(
+                matrix.values[i * cols..(i + 1) * cols]
+                    .iter()
+                    .cloned()
+                    .enumerate()
+                    .collect(),
+            );
+        }
+        out
+    }
+}
+
+impl<T: FieldElement> std::ops::Mul<&Vector<T>> for &SparseMatrix<T> {
+    type Output = Vector<T>;
+
+    fn mul(self, other: &Vector<T>) -> Vector<T> {
+        assert_eq!(
+            self.dimensions.1,
+            other.len(),
+            "sparse matrix vector multiplication dimensions mismatch"
+        );
+        Vector::from_vec(
+            (0..self.dimensions.0)
+                .map(|i| {
+                    self.row(i).fold(T::zero(), |acc, (col, v)| {
+                        acc + v.clone() * other[col].clone()
+                    })
+                })
+                .collect(),
+        )
+    }
+}
+
+impl<T: FieldElement> std::ops::Mul<Vector<T>> for SparseMatrix<T> {
+    type Output = Vector<T>;
+
+    fn mul(self, other: Vector<T>) -> Vector<T> {
+        &self * &other
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use scalarff::FieldElement;
+    use scalarff::OxfoiFieldElement;
+
+    use super::Matrix2D;
+    use super::SparseMatrix;
+    use super::Vector;
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn test_sparse_mul_matches_dense() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            let mut dense = Matrix2D::<OxfoiFieldElement>::sample_uniform(12, 30, &mut rng);
+            // zero most entries
+            for (i, v) in dense.values.iter_mut().enumerate() {
+                if i % 7 != 0 {
+                    *v = OxfoiFieldElement::zero();
+                }
+            }
+            let sparse = SparseMatrix::from(&dense);
+            assert_eq!(
+                sparse.nnz(),
+                dense
+                    .values
+                    .iter()
+                    .filter(|v| **v != OxfoiFieldElement::zero())
+                    .count()
+            );
+            assert!(sparse.to_dense() == dense);
+            let input = Vector::sample_uniform(30, &mut rng);
+            assert!(&sparse * &input == dense * input);
+        }
+    }
+
+    #[test]
+    fn test_sparse_triplets() {
+        let one = OxfoiFieldElement::one();
+        let m = SparseMatrix::from_triplets(
+            3,
+            4,
+            vec![
+                (2, 1, one),
+                (0, 3, one),
+                (0, 3, one),
+                (2, 0, one),
+                (1, 2, one),
+                (1, 2, -one),
+            ],
+        );
+        assert_eq!(m.dimensions, (3, 4));
+        assert_eq!(m.nnz(), 3);
+        assert!(m.row(0).collect::<Vec<_>>() == vec![(3, &(one + one))]);
+        assert_eq!(m.row(1).count(), 0);
+        assert_eq!(m.row(2).map(|(col, _)| col).collect::<Vec<_>>(), vec![0, 1]);
+    }
+}
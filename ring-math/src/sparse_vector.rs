@@ -0,0 +1,269 @@
+use scalarff::FieldElement;
+
+use super::matrix2d::Matrix2D;
+use super::vector::Vector;
+
+/// A vector storing only non-zero entries, e.g. a row of a
+/// constraint system that references a few signals.
+///
+/// `entries` are (index, value) pairs sorted by index. Indices
+/// are less than `len`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SparseVector<T: FieldElement> {
+    pub len: usize,
+    pub entries: Vec<(usize, T)>,
+}
+
+impl<T: FieldElement> SparseVector<T> {
+    /// Create a zero vector of the specified length.
+    pub fn new(len: usize) -> Self {
+        Self {
+            len,
+            entries: vec![],
+        }
+    }
+
+    /// Build a vector from (index, value) entries. Entries may be
+    /// in any order, entries at the same index are summed and
+    /// zeros are dropped.
+    pub fn from_entries(len: usize, mut entries: Vec<(usize, T)>) -> Self {
+        entries.sort_by_key(|(i, _)| *i);
+        let mut out = Self::new(len);
+        for (i, v) in entries {
+            if i >= len {
+                panic!("attempt to build a sparse vector with an entry outside of the vector length. Entry {i}, length {len}");
+            }
+            if out.entries.last().map(|(last, _)| *last) == Some(i) {
+                let last = &mut out.entries.last_mut().unwrap().1;
+                *last += v;
+                if *last == T::zero() {
+                    out.entries.pop();
+                }
+            } else if v != T::zero() {
+                out.entries.push((i, v));
+            }
+        }
+        out
+    }
+
+    /// The number of non-zero entries.
+    pub fn nnz(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Retrieve the value at an index.
+    pub fn get(&self, index: usize) -> T {
+        match self.entries.binary_search_by_key(&index, |(i, _)| *i) {
+            Ok(x) => self.entries[x].1.clone(),
+            Err(_) => T::zero(),
+        }
+    }
+
+    /// The non-zero (index, value) entries in index order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.entries.iter().map(|(i, v)| (*i, v))
+    }
+
+    /// Return a dense copy of the vector.
+    pub fn to_dense(&self) -> Vector<T> {
+        let mut out = vec![T::zero(); self.len];
+        for (i, v) in self.iter() {
+            out[i] = v.clone();
+        }
+        Vector::from_vec(out)
+    }
+
+    /// Compute the inner product with a dense vector.
+    pub fn dot_product(&self, other: &Vector<T>) -> T {
+        assert_eq!(
+            self.len,
+            other.len(),
+            "sparse vector dot product length mismatch"
+        );
+        self.iter()
+            .fold(T::zero(), |acc, (i, v)| acc + v.clone() * other[i].clone())
+    }
+
+    /// Compute the inner product with another sparse vector.
+    pub fn dot_sparse(&self, other: &Self) -> T {
+        assert_eq!(
+            self.len, other.len,
+            "sparse vector dot product length mismatch"
+        );
+        let mut out = T::zero();
+        let mut other_entries = other.entries.iter().peekable();
+        for (i, v) in &self.entries {
+            while other_entries.next_if(|(j, _)| j < i).is_some() {}
+            if let Some((_, w)) = other_entries.next_if(|(j, _)| j == i) {
+                out += v.clone() * w.clone();
+            }
+        }
+        out
+    }
+
+    /// Multiply as a row vector by a dense matrix, the sum of the
+    /// matrix rows scaled by each entry.
+    pub fn mul_matrix(&self, matrix: &Matrix2D<T>) -> Vector<T> {
+        let (rows, cols) = matrix.dimensions;
+        assert_eq!(
+            self.len, rows,
+            "sparse vector matrix multiplication dimensions mismatch"
+        );
+        let mut out = vec![T::zero(); cols];
+        for (i, v) in self.iter() {
+            for (o, m) in out.iter_mut().zip(&matrix.values[i * cols..(i + 1) * cols]) {
+                *o += v.clone() * m.clone();
+            }
+        }
+        Vector::from_vec(out)
+    }
+}
+
+impl<T: FieldElement> From<&Vector<T>> for SparseVector<T> {
+    fn from(v: &Vector<T>) -> Self {
+        Self::from_entries(v.len(), v.iter().cloned().enumerate().collect())
+    }
+}
+
+impl<T: FieldElement> std::ops::Add<&SparseVector<T>> for &SparseVector<T> {
+    type Output = SparseVector<T>;
+
+    fn add(self, other: &SparseVector<T>) -> SparseVector<T> {
+        assert_eq!(
+            self.len, other.len,
+            "sparse vector addition length mismatch"
+        );
+        SparseVector::from_entries(
+            self.len,
+            self.entries
+                .iter()
+                .chain(other.entries.iter())
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+impl<T: FieldElement> std::ops::Add<&Vector<T>> for &SparseVector<T> {
+    type Output = Vector<T>;
+
+    fn add(self, other: &Vector<T>) -> Vector<T> {
+        assert_eq!(
+            self.len,
+            other.len(),
+            "sparse vector addition length mismatch"
+        );
+        let mut out = other.to_vec();
+        for (i, v) in self.iter() {
+            out[i] += v.clone();
+        }
+        Vector::from_vec(out)
+    }
+}
+
+impl<T: FieldElement> std::ops::Mul<T> for &SparseVector<T> {
+    type Output = SparseVector<T>;
+
+    fn mul(self, other: T) -> SparseVector<T> {
+        if other == T::zero() {
+            return SparseVector::new(self.len);
+        }
+        SparseVector {
+            len: self.len,
+            entries: self
+                .iter()
+                .map(|(i, v)| (i, v.clone() * other.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl<T: FieldElement> std::ops::Mul<&SparseVector<T>> for &Matrix2D<T> {
+    type Output = Vector<T>;
+
+    /// Multiply by a sparse column vector, reading only the
+    /// columns of the non-zero entries.
+    fn mul(self, other: &SparseVector<T>) -> Vector<T> {
+        let (rows, cols) = self.dimensions;
+        assert_eq!(
+            cols, other.len,
+            "matrix sparse vector multiplication dimensions mismatch"
+        );
+        Vector::from_vec(
+            (0..rows)
+                .map(|r| {
+                    other.iter().fold(T::zero(), |acc, (i, v)| {
+                        acc + self.values[r * cols + i].clone() * v.clone()
+                    })
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use scalarff::FieldElement;
+    use scalarff::OxfoiFieldElement;
+
+    use super::Matrix2D;
+    use super::SparseVector;
+    use super::Vector;
+    use crate::SparseMatrix;
+
+    // a random vector with most entries zero
+    #[cfg(feature = "rand")]
+    fn sample_sparse(len: usize, rng: &mut impl rand::Rng) -> Vector<OxfoiFieldElement> {
+        let mut v = Vector::sample_uniform(len, rng);
+        for (i, x) in v.iter_mut().enumerate() {
+            if i % 5 != 0 {
+                *x = OxfoiFieldElement::zero();
+            }
+        }
+        v
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn test_sparse_vector_matches_dense() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            let dense = sample_sparse(40, &mut rng);
+            let other = sample_sparse(40, &mut rng);
+            let sparse = SparseVector::from(&dense);
+            let sparse_other = SparseVector::from(&other);
+            assert_eq!(sparse.nnz(), 8);
+            assert!(sparse.to_dense() == dense);
+            let input = Vector::sample_uniform(40, &mut rng);
+            assert_eq!(sparse.dot_product(&input), dense.dot_product(input.clone()));
+            assert_eq!(
+                sparse.dot_sparse(&sparse_other),
+                dense.dot_product(other.clone())
+            );
+            assert!((&sparse + &sparse_other).to_dense() == dense.clone() + other.clone());
+            assert!(&sparse + &input == dense.clone() + input.clone());
+            let m = Matrix2D::<OxfoiFieldElement>::sample_uniform(6, 40, &mut rng);
+            assert!(&m * &sparse == m.clone() * dense.clone());
+            assert!(sparse.mul_matrix(&m.transpose()) == m.mul_vec(&dense));
+        }
+    }
+
+    #[test]
+    fn test_sparse_vector_entries() {
+        let one = OxfoiFieldElement::one();
+        let v =
+            SparseVector::from_entries(5, vec![(3, one), (1, one), (3, one), (4, one), (4, -one)]);
+        assert_eq!(v.nnz(), 2);
+        assert_eq!(v.get(3), one + one);
+        assert_eq!(v.get(4), OxfoiFieldElement::zero());
+        assert_eq!(v.iter().map(|(i, _)| i).collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!((&v * OxfoiFieldElement::zero()).nnz(), 0);
+
+        let mut m = SparseMatrix::new(5);
+        m.push_sparse_row(v.clone());
+        m.push_row(vec![(0, one)]);
+        assert!(m.row_vector(0) == v);
+        assert_eq!(m.row_vector(1).entries, vec![(0, one)]);
+    }
+}
@@ -30,6 +30,30 @@ impl<T: FieldElement> Vector<T> {
         out
     }
 
+    /// Invert each element using a single field inversion
+    /// (Montgomery's trick). Zero elements are left as zero.
+    pub fn batch_inverse(&self) -> Vector<T> {
+        // prefix[i] is the product of the non-zero elements before i
+        let mut prefix = Vec::with_capacity(self.len());
+        let mut acc = T::one();
+        for v in self.iter() {
+            prefix.push(acc.clone());
+            if *v != T::zero() {
+                acc *= v.clone();
+            }
+        }
+        let mut inv = T::one() / acc;
+        let mut out = vec![T::zero(); self.len()];
+        for (i, v) in self.iter().enumerate().rev() {
+            if *v == T::zero() {
+                continue;
+            }
+            out[i] = inv.clone() * prefix[i].clone();
+            inv *= v.clone();
+        }
+        Vector(out)
+    }
+
     /// Calculate the l1 norm for this vector. That is
     /// the summation of all coefficients
     pub fn norm_l1(&self) -> BigUint {
@@ -167,3 +191,31 @@ impl<T: FieldElement> std::ops::Mul<T> for Vector<T> {
         Vector::from_vec(self.iter().map(|v| v.clone() * other.clone()).collect())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use scalarff::FieldElement;
+    use scalarff::OxfoiFieldElement;
+
+    use super::Vector;
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn test_batch_inverse() {
+        let mut rng = rand::thread_rng();
+        let mut v = Vector::<OxfoiFieldElement>::sample_uniform(50, &mut rng);
+        v.0[0] = OxfoiFieldElement::zero();
+        v.0[17] = OxfoiFieldElement::zero();
+        let inverses = v.batch_inverse();
+        for (a, b) in v.iter().zip(inverses.iter()) {
+            if *a == OxfoiFieldElement::zero() {
+                assert_eq!(*b, OxfoiFieldElement::zero());
+            } else {
+                assert_eq!(*b, OxfoiFieldElement::one() / a.clone());
+            }
+        }
+        assert!(Vector::<OxfoiFieldElement>::new()
+            .batch_inverse()
+            .is_empty());
+    }
+}